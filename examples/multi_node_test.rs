@@ -8,7 +8,6 @@
 use anyhow::Result;
 use clap::Parser;
 use std::process::{Command, Stdio};
-use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -108,7 +107,7 @@ async fn start_node(
     // 注意：这里需要实际编译后的二进制文件
     // 在实际使用中，可能需要先编译，然后运行编译后的二进制
     let mut child = Command::new("cargo")
-        .args(&[
+        .args([
             "run",
             "--release",
             "--",
@@ -0,0 +1,226 @@
+//! 本地管理 / 健康检查 HTTP 接口
+//!
+//! 仅实现最小的 HTTP/1.1 GET 处理，避免引入完整的 web 框架：
+//! - `GET /health`：就绪状态，未就绪时返回 503
+//! - `GET /stats`：训练统计 JSON
+//! - `GET /jobs`：已发现的训练任务
+//! - `GET /power-saver`、`POST /power-saver/on|off`：查询 / 切换低功耗模式
+//! - `GET /analytics/peers`：分析库中按节点汇总的贡献（最有帮助的在前）
+//! - `GET /analytics/recent`、`GET /analytics/recent/<PeerId>`：最近接受的更新记录
+//! - `POST /audit`：重新计算模型哈希并与最新检查点、奖励 Merkle 记录、账本不变量交叉核对，报告发现的问题
+//! - `GET /reputation/export`：由 ETH 密钥签名的声誉迁移证明，供迁移到新机器时导入
+//! - `GET /provenance/<模型哈希>`：模型的来源（宣告节点、贡献的更新、奖励 Merkle 记录），见 [`crate::provenance`]
+//! - `GET /status`：角色、节点心跳、模型哈希一致性、拓扑、质押账本、密钥冲突与 ENS / SNS 名称（轻量节点同样可用）
+//!
+//! 请求处理失败时返回 500 与 `{"error": 错误信息, "code": 错误码}`，错误码见 [`crate::errors`]，
+//! 不属于库错误时 `code` 为 null。
+
+use crate::analytics::AnalyticsDb;
+use crate::audit::Auditor;
+use crate::consensus::{ConsensusEngine, KeyConflict, LedgerEntry};
+use crate::errors;
+use crate::jobs::JobRegistry;
+use crate::monitor::{HashAgreement, PeerMonitor, PeerStatus, TopologyView};
+use crate::names::{NameResolver, PeerNames};
+use crate::power::PowerSaver;
+use crate::provenance;
+use crate::readiness::{ReadinessGate, ReadinessReport};
+use crate::role::NodeRole;
+use crate::stats::TrainingStatsManager;
+use anyhow::Result;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// `/analytics/recent` 返回的最大条数
+const RECENT_LIMIT: usize = 200;
+
+/// 管理接口可访问的节点共享状态
+#[derive(Clone)]
+pub struct AdminState {
+    pub readiness: Arc<ReadinessGate>,
+    pub stats: Arc<TrainingStatsManager>,
+    pub jobs: Arc<JobRegistry>,
+    pub monitor: Arc<PeerMonitor>,
+    pub consensus: Arc<ConsensusEngine>,
+    pub role: NodeRole,
+    pub light: bool,
+    pub peer_id: String,
+    pub names: Arc<NameResolver>,
+    pub power: Arc<PowerSaver>,
+    /// 未启用分析库时为 None
+    pub analytics: Option<Arc<AnalyticsDb>>,
+    pub auditor: Auditor,
+}
+
+/// `/status` 响应
+#[derive(Serialize)]
+struct StatusReport {
+    role: NodeRole,
+    light: bool,
+    readiness: ReadinessReport,
+    peers: Vec<PeerStatus>,
+    hash_agreement: Vec<HashAgreement>,
+    topology: Vec<TopologyView>,
+    ledger: Vec<LedgerEntry>,
+    key_conflicts: Vec<KeyConflict>,
+    /// 已解析的 ENS / SNS 名称（未配置解析端点时为空）
+    names: Vec<PeerNames>,
+}
+
+impl AdminState {
+    fn status(&self) -> StatusReport {
+        StatusReport {
+            role: self.role,
+            light: self.light,
+            readiness: self.readiness.report(),
+            peers: self.monitor.peers(),
+            hash_agreement: self.monitor.hash_agreement(),
+            topology: self.monitor.topology(),
+            ledger: self.consensus.export_ledger(),
+            key_conflicts: self.consensus.key_conflicts(),
+            names: self.peer_names(),
+        }
+    }
+
+    fn peer_names(&self) -> Vec<PeerNames> {
+        if !self.names.is_enabled() {
+            return Vec::new();
+        }
+        let mut names: Vec<_> = self
+            .consensus
+            .export_pins()
+            .iter()
+            .map(|pin| self.names.names(pin))
+            .filter(|n| n.ens.is_some() || n.sns.is_some())
+            .collect();
+        names.sort_by(|a, b| a.peer.cmp(&b.peer));
+        names
+    }
+}
+
+pub struct AdminServer;
+
+impl AdminServer {
+    /// 绑定地址并持续处理请求，accept 失败时返回错误交由 Supervisor 重启
+    pub async fn serve(bind: SocketAddr, state: AdminState) -> Result<()> {
+        let listener = TcpListener::bind(bind).await?;
+        println!("[Admin] 管理接口监听 http://{}", listener.local_addr()?);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let state = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, state).await {
+                    eprintln!("[Admin] 请求处理失败: {:?}", e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: AdminState) -> Result<()> {
+    let mut buf = vec![0u8; 4096];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, body) = match route(method, path, &state).await {
+        Ok(response) => response,
+        Err(e) => {
+            let code = errors::code_of(&e);
+            if let Some(code) = code {
+                state.stats.record_error(code);
+            }
+            let body = serde_json::json!({ "error": format!("{e:#}"), "code": code });
+            (500, body.to_string())
+        }
+    };
+    write_response(&mut stream, status, &body).await
+}
+
+async fn route(method: &str, path: &str, state: &AdminState) -> Result<(u16, String)> {
+    Ok(match (method, path) {
+        ("GET", "/health") => {
+            let report = state.readiness.report();
+            let status = if report.ready { 200 } else { 503 };
+            (status, serde_json::to_string(&report)?)
+        }
+        ("GET", "/stats") => (200, state.stats.export_json()?),
+        ("GET", "/jobs") => (200, serde_json::to_string(&state.jobs.list())?),
+        ("GET", "/status") => (200, serde_json::to_string(&state.status())?),
+        ("GET", "/power-saver") => (200, power_saver_json(&state.power)),
+        ("POST", "/power-saver/on") | ("POST", "/power-saver/off") => {
+            let enabled = path.ends_with("/on");
+            if state.power.set_enabled(enabled) != enabled {
+                println!("[低功耗] 通过管理接口{}低功耗模式", if enabled { "开启" } else { "关闭" });
+            }
+            (200, power_saver_json(&state.power))
+        }
+        ("GET", "/analytics/peers") => match &state.analytics {
+            Some(db) => (200, serde_json::to_string(&db.peer_contributions(0)?)?),
+            None => (404, r#"{"error":"analytics disabled"}"#.to_string()),
+        },
+        ("GET", p) if p == "/analytics/recent" || p.starts_with("/analytics/recent/") => {
+            let sender = p.strip_prefix("/analytics/recent/");
+            match &state.analytics {
+                Some(db) => (200, serde_json::to_string(&db.recent(sender, RECENT_LIMIT)?)?),
+                None => (404, r#"{"error":"analytics disabled"}"#.to_string()),
+            }
+        }
+        ("POST", "/audit") => {
+            let auditor = state.auditor.clone();
+            // 审计要读检查点文件并遍历整个模型，放到阻塞线程执行
+            let report = tokio::task::spawn_blocking(move || auditor.run()).await?;
+            (200, serde_json::to_string_pretty(&report)?)
+        }
+        ("GET", p) if p.starts_with("/provenance/") => {
+            let hash = p["/provenance/".len()..].to_lowercase();
+            let state = state.clone();
+            // 可能要逐个读取检查点并重新计算哈希，放到阻塞线程执行
+            let found = tokio::task::spawn_blocking(move || {
+                provenance::lookup(
+                    &hash,
+                    &state.auditor.sessions,
+                    state.auditor.state_dir.as_deref(),
+                    &state.monitor,
+                    state.analytics.as_deref(),
+                )
+            })
+            .await??;
+            match found {
+                Some(provenance) => (200, serde_json::to_string_pretty(&provenance)?),
+                None => (404, r#"{"error":"unknown model hash"}"#.to_string()),
+            }
+        }
+        ("GET", "/reputation/export") => {
+            let attestation = state.consensus.export_attestation(&state.peer_id)?;
+            (200, serde_json::to_string_pretty(&attestation)?)
+        }
+        _ => (404, r#"{"error":"not found"}"#.to_string()),
+    })
+}
+
+fn power_saver_json(power: &PowerSaver) -> String {
+    serde_json::json!({ "enabled": power.is_enabled() }).to_string()
+}
+
+async fn write_response(stream: &mut TcpStream, status: u16, body: &str) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
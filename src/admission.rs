@@ -0,0 +1,63 @@
+//! 无质押节点的工作量证明准入
+//!
+//! 为抵御大量零质押身份的 Sybil 洪泛，新节点需要在 `KeyAnnounce` 中附带
+//! hashcash 风格的工作量证明：keccak256(peer, eth 地址, sol 公钥, nonce)
+//! 的前导零比特数不低于配置的难度。证明绑定了完整身份，无法在身份间复用。
+
+use sha3::{Digest, Keccak256};
+
+/// 默认难度（前导零比特数），普通设备约需数十毫秒
+pub const DEFAULT_POW_DIFFICULTY: u8 = 16;
+/// 可配置的最大难度，更高的难度在普通设备上需要数小时
+pub const MAX_POW_DIFFICULTY: u8 = 28;
+/// 搜索次数上限为期望次数（2^难度）的这么多倍，找不到的概率约为 e^-64
+const SOLVE_ATTEMPT_FACTOR_BITS: u32 = 6;
+
+fn pow_hash(peer: &str, eth_address: &str, sol_pubkey: &str, nonce: u64) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(peer.as_bytes());
+    hasher.update(eth_address.to_lowercase().as_bytes());
+    hasher.update(sol_pubkey.as_bytes());
+    hasher.update(nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            return bits + byte.leading_zeros();
+        }
+    }
+    bits
+}
+
+/// 搜索满足难度的 nonce；难度超过 `MAX_POW_DIFFICULTY` 或超出搜索次数上限时返回 None
+pub fn solve(peer: &str, eth_address: &str, sol_pubkey: &str, difficulty: u8) -> Option<u64> {
+    if difficulty > MAX_POW_DIFFICULTY {
+        return None;
+    }
+    let attempts = 1u64 << (difficulty as u32 + SOLVE_ATTEMPT_FACTOR_BITS);
+    (0..attempts).find(|nonce| verify(peer, eth_address, sol_pubkey, *nonce, difficulty))
+}
+
+pub fn verify(peer: &str, eth_address: &str, sol_pubkey: &str, nonce: u64, difficulty: u8) -> bool {
+    leading_zero_bits(&pow_hash(peer, eth_address, sol_pubkey, nonce)) >= difficulty as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pow_difficulty() {
+        let nonce = solve("peer-a", "0xAbC", "sol", 12).unwrap();
+        assert!(verify("peer-a", "0xabc", "sol", nonce, 12));
+        assert!(!verify("peer-a", "0xabc", "sol", nonce, 40));
+        assert_eq!(leading_zero_bits(&[0, 0b0001_0000, 0xff]), 11);
+        assert!(verify("anyone", "", "", 0, 0));
+        assert_eq!(solve("peer-a", "0xabc", "sol", MAX_POW_DIFFICULTY + 1), None);
+    }
+}
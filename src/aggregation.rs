@@ -0,0 +1,167 @@
+//! 聚合者的更新汇聚
+//!
+//! 训练者通过 QUIC 直接把稀疏更新发给聚合者，聚合者在一个窗口内按发送者缓存最新更新，
+//! 窗口结束时按坐标使用鲁棒规则（中位数 / 截尾均值）合并为一条更新再统一广播，
+//! gossip 流量约减少为原来的 1 / 训练者数。
+
+use crate::types::{compress_indices, decompress_indices, SparseUpdate};
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// 坐标级聚合规则
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregationRule {
+    Mean,
+    Median,
+    /// 两端各去掉该比例的极值后取均值
+    TrimmedMean(f32),
+}
+
+impl Default for AggregationRule {
+    fn default() -> Self {
+        AggregationRule::TrimmedMean(0.2)
+    }
+}
+
+impl AggregationRule {
+    pub fn aggregate(&self, values: &mut [f32]) -> f32 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        match self {
+            AggregationRule::Mean => mean(values),
+            AggregationRule::Median => {
+                let mid = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[mid - 1] + values[mid]) / 2.0
+                } else {
+                    values[mid]
+                }
+            }
+            AggregationRule::TrimmedMean(ratio) => {
+                let trim = (values.len() as f32 * ratio.clamp(0.0, 0.49)) as usize;
+                mean(&values[trim..values.len() - trim])
+            }
+        }
+    }
+}
+
+/// 解析 `mean` / `median` / `trimmed[:ratio]`
+impl FromStr for AggregationRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None if s == "mean" => Ok(AggregationRule::Mean),
+            None if s == "median" => Ok(AggregationRule::Median),
+            None if s == "trimmed" => Ok(AggregationRule::default()),
+            Some(("trimmed", ratio)) => ratio
+                .parse()
+                .map(AggregationRule::TrimmedMean)
+                .map_err(|_| anyhow!("invalid trim ratio {:?}", ratio)),
+            _ => Err(anyhow!("unknown aggregation rule {:?}", s)),
+        }
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// 单个会话的更新缓存
+pub struct UpdateAggregator {
+    rule: AggregationRule,
+    /// 发送者 -> 本窗口内最新的更新（同一训练者重复发送只保留最后一条）
+    pending: HashMap<String, SparseUpdate>,
+    /// 发送者 -> 本窗口内收到的更新次数
+    counts: HashMap<String, u32>,
+}
+
+impl UpdateAggregator {
+    pub fn new(rule: AggregationRule) -> Self {
+        Self {
+            rule,
+            pending: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, sender: &str, update: SparseUpdate) {
+        *self.counts.entry(sender.to_string()).or_default() += 1;
+        self.pending.insert(sender.to_string(), update);
+    }
+
+    /// 本窗口内各发送者的更新次数
+    pub fn contributions(&self) -> &HashMap<String, u32> {
+        &self.counts
+    }
+
+    pub fn pending_senders(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 合并并清空本窗口的更新，没有缓存时返回 None
+    pub fn merge(&mut self) -> Option<SparseUpdate> {
+        self.counts.clear();
+        if self.pending.is_empty() {
+            return None;
+        }
+        let mut per_index: BTreeMap<usize, Vec<f32>> = BTreeMap::new();
+        let mut version = 0;
+        for (_, update) in self.pending.drain() {
+            version = version.max(update.version);
+            for (idx, value) in decompress_indices(&update.indices)
+                .into_iter()
+                .zip(update.values)
+            {
+                per_index.entry(idx).or_default().push(value);
+            }
+        }
+        let indices: Vec<usize> = per_index.keys().copied().collect();
+        let values = per_index
+            .into_values()
+            .map(|mut vals| self.rule.aggregate(&mut vals))
+            .collect();
+        Some(SparseUpdate {
+            indices: compress_indices(&indices),
+            values,
+            version,
+            seq: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(pairs: &[(usize, f32)]) -> SparseUpdate {
+        let indices: Vec<usize> = pairs.iter().map(|(i, _)| *i).collect();
+        SparseUpdate {
+            indices: compress_indices(&indices),
+            values: pairs.iter().map(|(_, v)| *v).collect(),
+            version: 1,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_median_rejects_outlier() {
+        let mut aggregator = UpdateAggregator::new(AggregationRule::Median);
+        aggregator.push("a", update(&[(2, 1.0), (5, 2.0)]));
+        aggregator.push("b", update(&[(2, 1.2)]));
+        aggregator.push("c", update(&[(2, 1000.0)]));
+        let merged = aggregator.merge().unwrap();
+        assert_eq!(decompress_indices(&merged.indices), vec![2, 5]);
+        assert_eq!(merged.values, vec![1.2, 2.0]);
+        assert!(aggregator.merge().is_none());
+    }
+
+    #[test]
+    fn test_trimmed_mean() {
+        let mut values = vec![10.0, 1.0, 2.0, 3.0, -10.0];
+        assert_eq!(AggregationRule::TrimmedMean(0.2).aggregate(&mut values), 2.0);
+    }
+}
@@ -0,0 +1,243 @@
+//! 已接受更新的本地分析库
+//!
+//! 每条通过全部校验并被接受的稀疏更新 / 密集快照都记录一行元数据
+//! （会话、发送者、版本、k、范数、接收时间、应用前后的损失变化）到本地 SQLite，
+//! 超过保留期的记录定期清理。研究者可以直接用 sqlite3 打开数据库分析，
+//! 也可以通过管理接口查询按节点汇总的贡献。
+//!
+//! 损失取 `1 - 收敛度`，与稀疏度自动调节使用的口径一致；
+//! 聚合者只缓存不立即应用，因此其记录没有损失变化。
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+pub const DB_FILE: &str = "analytics.sqlite";
+
+#[derive(Clone, Debug)]
+pub struct AnalyticsConfig {
+    /// 记录保留时长
+    pub retention: Duration,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(7 * 24 * 3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateKind {
+    Sparse,
+    Dense,
+}
+
+impl UpdateKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            UpdateKind::Sparse => "sparse",
+            UpdateKind::Dense => "dense",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateRecord {
+    pub session: String,
+    pub sender: String,
+    pub kind: UpdateKind,
+    pub version: u64,
+    /// 稀疏更新的非零个数，密集快照为参数维度
+    pub k: usize,
+    /// 更新值的 L2 范数
+    pub norm: f32,
+    pub received_at_ms: u64,
+    /// 应用后损失减去应用前损失，负值表示帮助收敛
+    pub loss_delta: Option<f32>,
+}
+
+/// 按节点汇总的贡献
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerContribution {
+    pub sender: String,
+    pub updates: u64,
+    pub mean_k: f64,
+    pub mean_norm: f64,
+    /// 有损失变化的更新中的平均值
+    pub mean_loss_delta: Option<f64>,
+    pub total_loss_delta: f64,
+    pub last_seen_ms: u64,
+}
+
+pub struct AnalyticsDb {
+    config: AnalyticsConfig,
+    conn: Mutex<Connection>,
+}
+
+impl AnalyticsDb {
+    pub fn open(path: &Path, config: AnalyticsConfig) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?, config)
+    }
+
+    fn with_connection(conn: Connection, config: AnalyticsConfig) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS updates (
+                id INTEGER PRIMARY KEY,
+                session TEXT NOT NULL,
+                sender TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                k INTEGER NOT NULL,
+                norm REAL NOT NULL,
+                received_at_ms INTEGER NOT NULL,
+                loss_delta REAL
+            );
+            CREATE INDEX IF NOT EXISTS updates_sender ON updates (sender, received_at_ms);
+            CREATE INDEX IF NOT EXISTS updates_time ON updates (received_at_ms);",
+        )?;
+        Ok(Self {
+            config,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn record(&self, record: &UpdateRecord) -> Result<()> {
+        self.conn.lock().execute(
+            "INSERT INTO updates (session, sender, kind, version, k, norm, received_at_ms, loss_delta)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.session,
+                record.sender,
+                record.kind.as_str(),
+                record.version as i64,
+                record.k as i64,
+                record.norm as f64,
+                record.received_at_ms as i64,
+                record.loss_delta.map(f64::from),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 删除超过保留期的记录，返回删除条数
+    pub fn prune(&self, now_ms: u64) -> Result<usize> {
+        let cutoff = now_ms.saturating_sub(self.config.retention.as_millis() as u64);
+        Ok(self
+            .conn
+            .lock()
+            .execute("DELETE FROM updates WHERE received_at_ms < ?1", params![cutoff as i64])?)
+    }
+
+    /// 各节点自 `since_ms` 起的贡献汇总，按总损失变化升序（最有帮助的在前）
+    pub fn peer_contributions(&self, since_ms: u64) -> Result<Vec<PeerContribution>> {
+        self.contributions("received_at_ms >= ?1", params![since_ms as i64])
+    }
+
+    /// 某会话中版本不超过 `version` 的更新按节点汇总，即参与形成该版本模型的贡献
+    pub fn model_contributions(&self, session: &str, version: u64) -> Result<Vec<PeerContribution>> {
+        self.contributions("session = ?1 AND version <= ?2", params![session, version as i64])
+    }
+
+    fn contributions(&self, filter: &str, args: impl rusqlite::Params) -> Result<Vec<PeerContribution>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT sender, COUNT(*), AVG(k), AVG(norm), AVG(loss_delta),
+                    COALESCE(SUM(loss_delta), 0), MAX(received_at_ms)
+             FROM updates WHERE {filter}
+             GROUP BY sender ORDER BY 6 ASC, 2 DESC"
+        ))?;
+        let rows = stmt.query_map(args, |row| {
+            Ok(PeerContribution {
+                sender: row.get(0)?,
+                updates: row.get::<_, i64>(1)? as u64,
+                mean_k: row.get(2)?,
+                mean_norm: row.get(3)?,
+                mean_loss_delta: row.get(4)?,
+                total_loss_delta: row.get(5)?,
+                last_seen_ms: row.get::<_, i64>(6)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// 最近的记录，`sender` 为 Some 时只查该节点
+    pub fn recent(&self, sender: Option<&str>, limit: usize) -> Result<Vec<UpdateRecord>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT session, sender, kind, version, k, norm, received_at_ms, loss_delta
+             FROM updates WHERE ?1 IS NULL OR sender = ?1
+             ORDER BY received_at_ms DESC, id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![sender, limit as i64], |row| {
+            let kind: String = row.get(2)?;
+            Ok(UpdateRecord {
+                session: row.get(0)?,
+                sender: row.get(1)?,
+                kind: if kind == "dense" {
+                    UpdateKind::Dense
+                } else {
+                    UpdateKind::Sparse
+                },
+                version: row.get::<_, i64>(3)? as u64,
+                k: row.get::<_, i64>(4)? as usize,
+                norm: row.get::<_, f64>(5)? as f32,
+                received_at_ms: row.get::<_, i64>(6)? as u64,
+                loss_delta: row.get::<_, Option<f64>>(7)?.map(|d| d as f32),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// 更新值的 L2 范数
+pub fn l2_norm(values: &[f32]) -> f32 {
+    values.iter().map(|v| v * v).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contributions_and_retention() {
+        let db = AnalyticsDb::with_connection(
+            Connection::open_in_memory().unwrap(),
+            AnalyticsConfig {
+                retention: Duration::from_secs(60),
+            },
+        )
+        .unwrap();
+        let record = |sender: &str, at: u64, delta: Option<f32>| UpdateRecord {
+            session: "default".into(),
+            sender: sender.into(),
+            kind: UpdateKind::Sparse,
+            version: 1,
+            k: 16,
+            norm: l2_norm(&[3.0, 4.0]),
+            received_at_ms: at,
+            loss_delta: delta,
+        };
+        db.record(&record("helpful", 100_000, Some(-0.02))).unwrap();
+        db.record(&record("helpful", 110_000, Some(-0.01))).unwrap();
+        db.record(&record("noisy", 120_000, Some(0.05))).unwrap();
+        db.record(&record("stale", 1_000, None)).unwrap();
+
+        let peers = db.peer_contributions(0).unwrap();
+        assert_eq!(peers[0].sender, "helpful");
+        assert_eq!(peers[0].updates, 2);
+        assert!((peers[0].mean_norm - 5.0).abs() < 1e-6);
+        assert_eq!(peers.last().unwrap().sender, "noisy");
+        assert_eq!(db.model_contributions("default", 0).unwrap().len(), 0);
+        assert_eq!(db.model_contributions("default", 1).unwrap().len(), 3);
+
+        assert_eq!(db.prune(130_000).unwrap(), 1);
+        assert_eq!(db.recent(Some("stale"), 10).unwrap().len(), 0);
+        assert_eq!(db.recent(None, 10).unwrap()[0].sender, "noisy");
+    }
+}
@@ -0,0 +1,213 @@
+//! 已验证更新的合并队列
+//!
+//! 签名、准入与信任检查通过的稀疏更新先进入有界队列，由事件循环在处理入站消息的间隙
+//! 分批合并进模型，入站洪峰时不会在事件循环里无限制地串行合并。
+//! 稀疏增量逐条保留，同一发送者的多条增量不互相取代。
+//! 队列满时按策略丢弃，丢弃数量计入统计，被丢弃的条目交还调用方处理。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+/// 队列满时的丢弃策略
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shedding {
+    /// 丢弃信任分最低的更新（同分时丢弃较早的）；新更新的信任分更低时直接丢弃新更新
+    #[default]
+    LowestTrust,
+    /// 丢弃最早入队的更新
+    Oldest,
+}
+
+impl FromStr for Shedding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lowest_trust" => Ok(Shedding::LowestTrust),
+            "oldest" => Ok(Shedding::Oldest),
+            _ => Err(anyhow!(
+                "未知的丢弃策略 {:?}（可选 lowest_trust、oldest）",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ApplyQueueConfig {
+    pub capacity: usize,
+    /// 每次从队列取出合并的最大条数，其余留给下一轮，期间事件循环继续处理入站消息
+    pub batch: usize,
+    pub shedding: Shedding,
+}
+
+impl Default for ApplyQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            batch: 16,
+            shedding: Shedding::default(),
+        }
+    }
+}
+
+/// 队列计数，随统计导出
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ApplyQueueStats {
+    pub pending: usize,
+    pub queued: u64,
+    /// 队列满时按策略丢弃的数量
+    pub shed: u64,
+}
+
+struct Entry<T> {
+    session: String,
+    sender: String,
+    trust: f32,
+    item: T,
+}
+
+pub struct ApplyQueue<T> {
+    config: ApplyQueueConfig,
+    entries: VecDeque<Entry<T>>,
+    stats: ApplyQueueStats,
+}
+
+impl<T> ApplyQueue<T> {
+    pub fn new(config: ApplyQueueConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            stats: ApplyQueueStats::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn batch(&self) -> usize {
+        self.config.batch.max(1)
+    }
+
+    /// 入队 `sender` 在 `session` 中的增量更新，不取代同一发送者已排队的增量；
+    /// 返回被丢弃的 (发送者, 条目)（可能就是本次入队的）
+    pub fn push(
+        &mut self,
+        session: &str,
+        sender: &str,
+        trust: f32,
+        item: T,
+    ) -> Option<(String, T)> {
+        self.stats.queued += 1;
+        self.insert(session, sender, trust, item)
+    }
+
+    /// `sender` 在 `session` 中是否有满足条件的条目仍在排队
+    pub fn pending(&self, session: &str, sender: &str, matches: impl Fn(&T) -> bool) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.session == session && e.sender == sender && matches(&e.item))
+    }
+
+    fn insert(&mut self, session: &str, sender: &str, trust: f32, item: T) -> Option<(String, T)> {
+        let mut shed = None;
+        if self.entries.len() >= self.config.capacity.max(1) {
+            self.stats.shed += 1;
+            let victim = match self.config.shedding {
+                Shedding::Oldest => 0,
+                Shedding::LowestTrust => {
+                    let (pos, lowest) = self
+                        .entries
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| a.trust.total_cmp(&b.trust))
+                        .map(|(pos, e)| (pos, e.trust))
+                        .unwrap_or((0, f32::MAX));
+                    if trust < lowest {
+                        return Some((sender.to_string(), item));
+                    }
+                    pos
+                }
+            };
+            shed = self.entries.remove(victim).map(|e| (e.sender, e.item));
+        }
+        self.entries.push_back(Entry {
+            session: session.to_string(),
+            sender: sender.to_string(),
+            trust,
+            item,
+        });
+        shed
+    }
+
+    /// 取出最早入队的更新：(会话, 发送者, 更新)
+    pub fn pop(&mut self) -> Option<(String, String, T)> {
+        self.entries
+            .pop_front()
+            .map(|e| (e.session, e.sender, e.item))
+    }
+
+    pub fn stats(&self) -> ApplyQueueStats {
+        ApplyQueueStats {
+            pending: self.entries.len(),
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_every_delta_and_sheds_by_policy() {
+        let config = |shedding| ApplyQueueConfig {
+            capacity: 3,
+            batch: 2,
+            shedding,
+        };
+        let mut queue = ApplyQueue::new(config(Shedding::LowestTrust));
+        assert_eq!(queue.push("s", "alice", 0.9, 1), None);
+        assert_eq!(queue.push("s", "bob", 0.2, 2), None);
+        assert_eq!(queue.push("other", "alice", 0.9, 3), None);
+        assert!(queue.pending("s", "bob", |v| *v == 2));
+        // 队列已满：丢弃信任分最低的 bob，信任分更低的新更新直接丢弃，条目交还调用方
+        assert_eq!(queue.push("s", "carol", 0.5, 4), Some(("bob".into(), 2)));
+        assert_eq!(
+            queue.push("s", "mallory", 0.1, 5),
+            Some(("mallory".into(), 5))
+        );
+        assert!(!queue.pending("s", "bob", |_| true));
+        let drained: Vec<i32> = std::iter::from_fn(|| queue.pop())
+            .map(|(_, _, v)| v)
+            .collect();
+        assert_eq!(drained, vec![1, 3, 4]);
+        assert_eq!(
+            queue.stats(),
+            ApplyQueueStats {
+                pending: 0,
+                queued: 5,
+                shed: 2,
+            }
+        );
+
+        // 同一发送者的增量逐条保留，不互相取代
+        let mut queue = ApplyQueue::new(config(Shedding::LowestTrust));
+        queue.push("s", "alice", 0.9, 1);
+        queue.push("s", "alice", 0.9, 2);
+        let drained: Vec<i32> = std::iter::from_fn(|| queue.pop())
+            .map(|(_, _, v)| v)
+            .collect();
+        assert_eq!(drained, vec![1, 2]);
+        assert!("unknown".parse::<Shedding>().is_err());
+
+        let mut queue = ApplyQueue::new(config("oldest".parse().unwrap()));
+        for (i, sender) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            queue.push("s", sender, 1.0, i);
+        }
+        assert_eq!(queue.pop().map(|(_, sender, _)| sender), Some("b".into()));
+    }
+}
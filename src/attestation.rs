@@ -0,0 +1,111 @@
+//! 声誉迁移证明
+//!
+//! 节点的 libp2p PeerId 随机器变化，而声誉应当绑定在链上身份（ETH 地址）上。
+//! 运营者在旧节点导出一份由 ETH 密钥签名的证明，包含旧 PeerId 以及本地账本与密钥绑定；
+//! 新节点用同一 ETH 密钥启动并导入后，在网络上广播 `IdentityMigration`，
+//! 其他节点确认旧 PeerId 固定的 ETH 地址与新签名者一致后，把账本记录迁移到新 PeerId。
+
+use crate::consensus::{ConsensusError, KeyPin, LedgerEntry};
+use crate::crypto::{verify_eth, CryptoSuite, EthSignature};
+use crate::persistence::unix_now_secs;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationAttestation {
+    pub eth_address: String,
+    /// 旧节点的 PeerId
+    pub previous_peer: String,
+    pub issued_at_secs: u64,
+    /// 旧节点视角下的质押 / 声誉账本
+    pub ledger: Vec<LedgerEntry>,
+    pub key_pins: Vec<KeyPin>,
+    pub signature: EthSignature,
+}
+
+impl ReputationAttestation {
+    pub fn create(
+        crypto: &CryptoSuite,
+        previous_peer: String,
+        ledger: Vec<LedgerEntry>,
+        key_pins: Vec<KeyPin>,
+    ) -> Result<Self, ConsensusError> {
+        let eth_address = crypto.eth_address();
+        let issued_at_secs = unix_now_secs();
+        let bytes = signing_bytes(&eth_address, &previous_peer, issued_at_secs, &ledger, &key_pins)?;
+        Ok(Self {
+            signature: crypto.sign_eth(&bytes)?,
+            eth_address,
+            previous_peer,
+            issued_at_secs,
+            ledger,
+            key_pins,
+        })
+    }
+
+    /// 校验签名，并要求证明属于当前节点的 ETH 身份
+    pub fn verify_for(&self, eth_address: &str) -> Result<()> {
+        if !self.eth_address.eq_ignore_ascii_case(eth_address) {
+            return Err(anyhow!(
+                "attestation belongs to {}, node runs as {}",
+                self.eth_address,
+                eth_address
+            ));
+        }
+        if !self.signature.address.eq_ignore_ascii_case(&self.eth_address) {
+            return Err(anyhow!("attestation signer does not match its eth address"));
+        }
+        let bytes = signing_bytes(
+            &self.eth_address,
+            &self.previous_peer,
+            self.issued_at_secs,
+            &self.ledger,
+            &self.key_pins,
+        )?;
+        if !verify_eth(&bytes, &self.signature) {
+            return Err(anyhow!("invalid attestation signature"));
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+}
+
+fn signing_bytes(
+    eth_address: &str,
+    previous_peer: &str,
+    issued_at_secs: u64,
+    ledger: &[LedgerEntry],
+    key_pins: &[KeyPin],
+) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(&(
+        eth_address.to_lowercase(),
+        previous_peer,
+        issued_at_secs,
+        ledger,
+        key_pins,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+
+    #[test]
+    fn test_attestation_bound_to_eth_identity() {
+        let crypto = CryptoSuite::new(CryptoConfig::default()).unwrap();
+        let other = CryptoSuite::new(CryptoConfig::default()).unwrap();
+        let mut attestation =
+            ReputationAttestation::create(&crypto, "old-peer".into(), Vec::new(), Vec::new())
+                .unwrap();
+        assert!(attestation.verify_for(&crypto.eth_address()).is_ok());
+        assert!(attestation.verify_for(&other.eth_address()).is_err());
+
+        attestation.previous_peer = "someone-else".into();
+        assert!(attestation.verify_for(&crypto.eth_address()).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! 管理接口触发的完整状态审计
+//!
+//! 怀疑状态损坏或遭受攻击后，通过 `POST /audit` 做一次全量检查并报告发现的问题：
+//! - 重新计算各会话模型的哈希，检查参数是否全部有限
+//! - 与最新的完好检查点对比：检查点版本不应高于内存模型，同版本时报告哈希是否一致
+//!   （合并旧版本的更新不会提升版本号，同版本哈希不同本身不算问题）；损坏的检查点文件逐个报告
+//! - 复核状态目录中每批奖励记录的 Merkle 根
+//! - 校验质押账本的不变量：数值有限、质押非负、声誉不低于下限、总权重有效
+
+use crate::checkpoint;
+use crate::consensus::{ConsensusEngine, LedgerEntry};
+use crate::rewards;
+use crate::session::Session;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// `update_stake` 允许的最低声誉
+const MIN_REPUTATION: f64 = -1.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelAudit {
+    pub session: String,
+    pub version: u64,
+    pub hash: String,
+    /// 最新完好检查点的版本与哈希，未配置检查点目录或没有可用检查点时为 None
+    pub checkpoint_version: Option<u64>,
+    pub checkpoint_hash: Option<String>,
+    /// 检查点与内存模型同版本时二者哈希是否一致
+    pub matches_checkpoint: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    /// 没有发现任何问题
+    pub ok: bool,
+    pub models: Vec<ModelAudit>,
+    pub reward_batches: usize,
+    pub ledger_entries: usize,
+    pub discrepancies: Vec<String>,
+}
+
+/// 审计所需的节点状态（启动时的会话）
+#[derive(Clone)]
+pub struct Auditor {
+    pub sessions: Vec<Arc<Session>>,
+    pub consensus: Arc<ConsensusEngine>,
+    /// 奖励批次所在的状态目录，未持久化时为 None
+    pub state_dir: Option<PathBuf>,
+}
+
+impl Auditor {
+    pub fn run(&self) -> AuditReport {
+        let mut discrepancies = Vec::new();
+        let models = self
+            .sessions
+            .iter()
+            .filter_map(|session| audit_model(session, &mut discrepancies))
+            .collect();
+        let reward_batches = self.audit_rewards(&mut discrepancies);
+        let ledger = self.consensus.export_ledger();
+        discrepancies.extend(ledger_discrepancies(&ledger));
+        let total = self.consensus.total_stake_weight();
+        if !total.is_finite() {
+            discrepancies.push(format!("账本总权重无效: {}", total));
+        }
+        if !discrepancies.is_empty() {
+            println!("[审计] 发现 {} 处问题", discrepancies.len());
+        }
+        AuditReport {
+            ok: discrepancies.is_empty(),
+            models,
+            reward_batches,
+            ledger_entries: ledger.len(),
+            discrepancies,
+        }
+    }
+
+    fn audit_rewards(&self, discrepancies: &mut Vec<String>) -> usize {
+        let Some(dir) = &self.state_dir else {
+            return 0;
+        };
+        let batches = match rewards::load_batches(dir) {
+            Ok(batches) => batches,
+            Err(e) => {
+                discrepancies.push(format!("读取奖励批次失败: {}", e));
+                return 0;
+            }
+        };
+        for (path, batch) in &batches {
+            let matches = match batch {
+                Ok(batch) => batch.root_matches(),
+                Err(e) => Err(anyhow::anyhow!("{}", e)),
+            };
+            match matches {
+                Ok(true) => {}
+                Ok(false) => {
+                    discrepancies.push(format!("奖励批次 {} 的 Merkle 根与叶子不符", path.display()))
+                }
+                Err(e) => discrepancies.push(format!("奖励批次 {} 无效: {}", path.display(), e)),
+            }
+        }
+        batches.len()
+    }
+}
+
+fn audit_model(session: &Session, discrepancies: &mut Vec<String>) -> Option<ModelAudit> {
+    let model = session.inference.as_ref()?;
+    let snapshot = model.tensor_snapshot();
+    let hash = snapshot.hash();
+    if !snapshot.values.iter().all(|v| v.is_finite()) {
+        discrepancies.push(format!("[{}] 模型参数中有非有限值", session.id));
+    }
+    let mut audit = ModelAudit {
+        session: session.id.clone(),
+        version: snapshot.version,
+        hash,
+        checkpoint_version: None,
+        checkpoint_hash: None,
+        matches_checkpoint: None,
+    };
+    let Some(dir) = model.checkpoint_dir() else {
+        return Some(audit);
+    };
+    let scanned = match checkpoint::scan(dir) {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            discrepancies.push(format!("[{}] 读取检查点目录失败: {}", session.id, e));
+            return Some(audit);
+        }
+    };
+    let mut latest = None;
+    for (path, result) in scanned {
+        match result {
+            Err(e) => discrepancies.push(format!("[{}] 检查点 {} 损坏: {}", session.id, path.display(), e)),
+            Ok(checkpoint) if checkpoint.params.len() != snapshot.values.len() => discrepancies.push(format!(
+                "[{}] 检查点 {} 维度 {} 与模型维度 {} 不符",
+                session.id,
+                path.display(),
+                checkpoint.params.len(),
+                snapshot.values.len()
+            )),
+            Ok(checkpoint) => {
+                latest.get_or_insert(checkpoint);
+            }
+        }
+    }
+    if let Some(checkpoint) = latest {
+        let checkpoint_hash =
+            crate::types::TensorSnapshot::new(checkpoint.params, checkpoint.version).hash();
+        if checkpoint.version > snapshot.version {
+            discrepancies.push(format!(
+                "[{}] 最新检查点 v{} 高于内存模型 v{}",
+                session.id, checkpoint.version, snapshot.version
+            ));
+        }
+        audit.matches_checkpoint =
+            (checkpoint.version == snapshot.version).then(|| checkpoint_hash == audit.hash);
+        audit.checkpoint_version = Some(checkpoint.version);
+        audit.checkpoint_hash = Some(checkpoint_hash);
+    }
+    Some(audit)
+}
+
+/// 质押账本的不变量
+pub fn ledger_discrepancies(entries: &[LedgerEntry]) -> Vec<String> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let values = [entry.stake_eth, entry.stake_sol, entry.reputation];
+        if !values.iter().all(|v| v.is_finite()) {
+            out.push(format!("账本条目 {} 含非有限值", entry.peer));
+            continue;
+        }
+        if entry.stake_eth < 0.0 || entry.stake_sol < 0.0 {
+            out.push(format!(
+                "账本条目 {} 质押为负: eth {}, sol {}",
+                entry.peer, entry.stake_eth, entry.stake_sol
+            ));
+        }
+        if entry.reputation < MIN_REPUTATION {
+            out.push(format!("账本条目 {} 声誉 {} 低于下限", entry.peer, entry.reputation));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_invariants() {
+        let entry = |peer: &str, stake_eth: f64, reputation: f64| LedgerEntry {
+            peer: peer.into(),
+            stake_eth,
+            stake_sol: 0.1,
+            reputation,
+        };
+        assert!(ledger_discrepancies(&[entry("ok", 1.0, -1.0), entry("rich", 1e9, 1e9)]).is_empty());
+        let found = ledger_discrepancies(&[
+            entry("negative", -0.5, 1.0),
+            entry("nan", f64::NAN, 1.0),
+            entry("disgraced", 1.0, -3.0),
+        ]);
+        assert_eq!(found.len(), 3);
+        assert!(found[0].contains("negative") && found[1].contains("nan") && found[2].contains("disgraced"));
+    }
+}
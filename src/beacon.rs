@@ -0,0 +1,529 @@
+//! 随机信标
+//!
+//! 委员会选举、数据分片分配与稀疏更新的 fanout 抽样需要一个全网一致、任何节点都无法单独
+//! 操纵的随机种子。信标采用提交-揭示：每个信标纪元，已确认质押的节点广播签名的
+//! `GgsMessage::BeaconShare`，其中揭示上一份额提交的秘密，并提交下一纪元要揭示的新秘密的哈希。
+//! 检查点即上一轮已确定的信标值（首轮为会话的创世值）。凑齐门限数量的份额后，收集者把份额
+//! 打包为 `GgsMessage::BeaconRound` 广播，信标值为 `keccak(检查点 || 纪元 || 各签名者揭示的秘密)`。
+//! 签名不参与信标值，秘密在上一轮信标值确定之前就已提交，签名者无法通过重新签名或挑选秘密
+//! 来操纵结果，最多只能不揭示。揭示与上一轮提交不符的份额和轮次被拒绝。
+//! 同一纪元出现多个有效轮次时取揭示数更多者，再取签名者列表字典序较小者，与信标值无关，
+//! 收集者挑选份额子集也无法据此挑选结果；全网据此收敛到同一个值。
+//! 落后的节点没有上一轮的提交、无法复核揭示，直接采纳更新纪元的有效轮次，从其信标值继续。
+//! 委员会按质押加权、以信标值为种子不放回地抽取，所有节点可复核。
+
+use crate::crypto::SignatureBundle;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct BeaconConfig {
+    pub enabled: bool,
+    /// 信标纪元长度，按 Unix 时间对齐
+    pub epoch: Duration,
+    /// 确定一轮所需的份额数
+    pub threshold: usize,
+}
+
+impl Default for BeaconConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            epoch: Duration::from_secs(600),
+            threshold: 3,
+        }
+    }
+}
+
+impl BeaconConfig {
+    pub fn epoch_at(&self, unix_secs: u64) -> u64 {
+        unix_secs / self.epoch.as_secs().max(1)
+    }
+
+    /// 只接受不晚于下一纪元的份额与轮次（容忍时钟偏差），远期纪元会冻结信标
+    pub fn admits(&self, epoch: u64, unix_secs: u64) -> bool {
+        epoch <= self.epoch_at(unix_secs).saturating_add(1)
+    }
+}
+
+/// 一个节点的信标份额：对 (检查点, 提交, 揭示) 的签名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeaconShare {
+    pub signer: String,
+    /// 下一纪元要揭示的秘密的哈希（十六进制）
+    pub commit: String,
+    /// 上一份额提交的秘密（十六进制）；没有可揭示的秘密时为空
+    pub reveal: String,
+    pub signature: SignatureBundle,
+}
+
+/// 凑齐门限的一轮信标，份额按签名者排序且不重复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeaconRound {
+    pub epoch: u64,
+    /// 上一轮的信标值（十六进制）
+    pub checkpoint: String,
+    pub shares: Vec<BeaconShare>,
+}
+
+impl BeaconRound {
+    /// 信标值只由检查点、纪元与揭示的秘密决定，不含签名
+    pub fn value(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.checkpoint.as_bytes());
+        hasher.update(self.epoch.to_be_bytes());
+        for share in self.shares.iter().filter(|share| !share.reveal.is_empty()) {
+            hasher.update(share.signer.as_bytes());
+            hasher.update(share.reveal.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// 揭示了秘密的份额数
+    pub fn revealed(&self) -> usize {
+        self.shares
+            .iter()
+            .filter(|share| !share.reveal.is_empty())
+            .count()
+    }
+
+    /// 同一纪元的两轮中本轮是否胜出：揭示数更多，其次签名者列表字典序更小
+    fn outranks(&self, other: &BeaconRound) -> bool {
+        match self.revealed().cmp(&other.revealed()) {
+            Ordering::Equal => self
+                .shares
+                .iter()
+                .map(|share| &share.signer)
+                .lt(other.shares.iter().map(|share| &share.signer)),
+            ordering => ordering == Ordering::Greater,
+        }
+    }
+
+    /// 份额有序不重复、数量达到门限且签名全部有效
+    pub fn verify(
+        &self,
+        session: &str,
+        threshold: usize,
+        mut valid: impl FnMut(&str, &[u8], &SignatureBundle) -> bool,
+    ) -> bool {
+        self.shares.len() >= threshold.max(1)
+            && self
+                .shares
+                .windows(2)
+                .all(|pair| pair[0].signer < pair[1].signer)
+            && self.shares.iter().all(|share| {
+                let bytes = share_bytes(
+                    session,
+                    self.epoch,
+                    &self.checkpoint,
+                    &share.commit,
+                    &share.reveal,
+                );
+                valid(&share.signer, &bytes, &share.signature)
+            })
+    }
+}
+
+/// 份额签名覆盖的字节
+pub fn share_bytes(
+    session: &str,
+    epoch: u64,
+    checkpoint: &str,
+    commit: &str,
+    reveal: &str,
+) -> Vec<u8> {
+    format!("ggs-beacon:{session}:{epoch}:{checkpoint}:{commit}:{reveal}").into_bytes()
+}
+
+/// 秘密的提交值
+pub fn commitment(secret: &str) -> String {
+    hex::encode(Keccak256::digest(secret.as_bytes()))
+}
+
+/// 会话的创世检查点
+pub fn genesis(session: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"ggs-beacon-genesis");
+    hasher.update(session.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 从信标值派生某一用途的随机数生成器，不同用途互不相关
+pub fn derive_rng(seed: &[u8; 32], purpose: &str) -> StdRng {
+    let mut hasher = Keccak256::new();
+    hasher.update(seed);
+    hasher.update(purpose.as_bytes());
+    StdRng::from_seed(hasher.finalize().into())
+}
+
+/// 按质押加权、不放回地抽取 `size` 名委员，无质押的候选不参选；候选顺序不影响结果
+pub fn elect(seed: &[u8; 32], candidates: &[(String, f32)], size: usize) -> Vec<String> {
+    let mut sorted: Vec<&(String, f32)> = candidates
+        .iter()
+        .filter(|(_, stake)| *stake > 0.0)
+        .collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    sorted.dedup_by(|a, b| a.0 == b.0);
+    let mut rng = derive_rng(seed, "committee");
+    let mut keyed: Vec<(f32, &String)> = sorted
+        .into_iter()
+        .map(|(peer, stake)| (rng.gen::<f32>().powf(1.0 / stake), peer))
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed
+        .into_iter()
+        .take(size)
+        .map(|(_, peer)| peer.clone())
+        .collect()
+}
+
+/// 单个会话的信标链：最近确定的一轮、它所接续的上一轮与收集中的份额
+pub struct BeaconChain {
+    session: String,
+    threshold: usize,
+    latest: Option<BeaconRound>,
+    /// `latest` 所接续的一轮，用于复核同一纪元其他轮次的揭示
+    parent: Option<BeaconRound>,
+    /// 收集中的 (纪元, 检查点) -> 签名者 -> 份额
+    pending: Option<(u64, String, BTreeMap<String, BeaconShare>)>,
+    /// 本节点已提交、待下一份额揭示的秘密
+    secret: Option<String>,
+}
+
+impl BeaconChain {
+    pub fn new(session: &str, threshold: usize) -> Self {
+        Self {
+            session: session.to_string(),
+            threshold: threshold.max(1),
+            latest: None,
+            parent: None,
+            pending: None,
+            secret: None,
+        }
+    }
+
+    /// 下一轮签名的检查点
+    pub fn checkpoint(&self) -> String {
+        match &self.latest {
+            Some(round) => hex::encode(round.value()),
+            None => genesis(&self.session),
+        }
+    }
+
+    /// 最近确定的纪元
+    pub fn epoch(&self) -> Option<u64> {
+        self.latest.as_ref().map(|round| round.epoch)
+    }
+
+    /// 最近确定的信标值
+    pub fn seed(&self) -> Option<[u8; 32]> {
+        self.latest.as_ref().map(BeaconRound::value)
+    }
+
+    /// 为本节点的下一份额生成 (提交, 揭示)：揭示上次提交且已计入最近一轮的秘密，并提交新秘密
+    pub fn next_secret(&mut self, signer: &str) -> (String, String) {
+        let reveal = self
+            .secret
+            .take()
+            .filter(|secret| {
+                let commit = commitment(secret);
+                self.latest.as_ref().is_some_and(|round| {
+                    round
+                        .shares
+                        .iter()
+                        .any(|s| s.signer == signer && s.commit == commit)
+                })
+            })
+            .unwrap_or_default();
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = hex::encode(secret);
+        let commit = commitment(&secret);
+        self.secret = Some(secret);
+        (commit, reveal)
+    }
+
+    /// 接续 `checkpoint` 的轮次应当对照其提交的上一轮：`Some(None)` 为创世（没有任何提交），
+    /// 本节点不知道该检查点对应哪一轮时为 None
+    fn base_of(&self, checkpoint: &str) -> Option<Option<&BeaconRound>> {
+        if checkpoint == self.checkpoint() {
+            return Some(self.latest.as_ref());
+        }
+        let latest = self
+            .latest
+            .as_ref()
+            .filter(|latest| latest.checkpoint == checkpoint)?;
+        match &self.parent {
+            Some(parent) => Some(Some(parent)),
+            None if latest.checkpoint == genesis(&self.session) => Some(None),
+            None => None,
+        }
+    }
+
+    /// 揭示全部与签名者在 `base` 中的提交相符；未揭示的份额总是相符
+    fn reveals_match(base: Option<&BeaconRound>, shares: &[BeaconShare]) -> bool {
+        shares
+            .iter()
+            .filter(|share| !share.reveal.is_empty())
+            .all(|share| {
+                let commit = commitment(&share.reveal);
+                base.is_some_and(|round| {
+                    round
+                        .shares
+                        .iter()
+                        .any(|s| s.signer == share.signer && s.commit == commit)
+                })
+            })
+    }
+
+    /// 记录签名已校验的份额：接续最近一轮的下一纪元，或补充最近一轮本身。凑齐门限后每收到
+    /// 一个新份额都返回可广播的一轮，揭示更多的一轮胜出（由调用方再经 `accept` 采纳）
+    pub fn add_share(
+        &mut self,
+        epoch: u64,
+        checkpoint: &str,
+        share: BeaconShare,
+    ) -> Option<BeaconRound> {
+        let extends =
+            checkpoint == self.checkpoint() && self.epoch().is_none_or(|latest| epoch > latest);
+        let current = self
+            .latest
+            .as_ref()
+            .is_some_and(|latest| latest.epoch == epoch && latest.checkpoint == checkpoint);
+        if !extends && !current {
+            return None;
+        }
+        match self.base_of(checkpoint) {
+            Some(base) if Self::reveals_match(base, std::slice::from_ref(&share)) => {}
+            _ => return None,
+        }
+        let collecting =
+            self.pending
+                .as_ref()
+                .is_some_and(|(pending_epoch, pending_checkpoint, _)| {
+                    *pending_epoch == epoch && pending_checkpoint == checkpoint
+                });
+        if !collecting {
+            if self
+                .pending
+                .as_ref()
+                .is_some_and(|(pending_epoch, _, _)| *pending_epoch > epoch)
+            {
+                return None;
+            }
+            self.pending = Some((epoch, checkpoint.to_string(), BTreeMap::new()));
+        }
+        let (_, _, shares) = self.pending.as_mut()?;
+        if shares.insert(share.signer.clone(), share).is_some() || shares.len() < self.threshold {
+            return None;
+        }
+        Some(BeaconRound {
+            epoch,
+            checkpoint: checkpoint.to_string(),
+            shares: shares.values().cloned().collect(),
+        })
+    }
+
+    /// 采纳份额已校验的一轮：更新的纪元，或同一纪元中胜出的轮次。接续已知轮次时揭示必须与
+    /// 提交相符；不接续任何已知轮次的只在本节点确实错过了整轮时采纳，否则揭示无从复核
+    pub fn accept(&mut self, round: BeaconRound) -> bool {
+        if round.shares.len() < self.threshold {
+            return false;
+        }
+        match self.base_of(&round.checkpoint) {
+            Some(base) if !Self::reveals_match(base, &round.shares) => return false,
+            Some(_) => {}
+            None if self
+                .epoch()
+                .is_some_and(|latest| round.epoch <= latest.saturating_add(1)) =>
+            {
+                return false
+            }
+            None => {}
+        }
+        let adopt = match &self.latest {
+            None => true,
+            Some(latest) => {
+                round.epoch > latest.epoch
+                    || (round.epoch == latest.epoch && round.outranks(latest))
+            }
+        };
+        if !adopt {
+            return false;
+        }
+        // 继续收集该轮的份额，之后到达的揭示可以凑出胜出的一轮
+        if self
+            .pending
+            .as_ref()
+            .is_none_or(|(epoch, _, _)| *epoch <= round.epoch)
+        {
+            let mut shares = match self.pending.take() {
+                Some((epoch, checkpoint, shares))
+                    if epoch == round.epoch && checkpoint == round.checkpoint =>
+                {
+                    shares
+                }
+                _ => BTreeMap::new(),
+            };
+            for share in &round.shares {
+                let kept = shares
+                    .entry(share.signer.clone())
+                    .or_insert_with(|| share.clone());
+                if kept.reveal.is_empty() {
+                    *kept = share.clone();
+                }
+            }
+            self.pending = Some((round.epoch, round.checkpoint.clone(), shares));
+        }
+        self.parent = match self.latest.take() {
+            Some(latest) if hex::encode(latest.value()) == round.checkpoint => Some(latest),
+            Some(latest) if latest.checkpoint == round.checkpoint => self.parent.take(),
+            _ => None,
+        };
+        self.latest = Some(round);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+
+    #[test]
+    fn test_threshold_round_is_verifiable_and_converges() {
+        let signers: Vec<(String, CryptoSuite)> = ["a", "b", "c"]
+            .iter()
+            .map(|peer| {
+                (
+                    peer.to_string(),
+                    CryptoSuite::new(CryptoConfig::default()).unwrap(),
+                )
+            })
+            .collect();
+        let share =
+            |i: usize, epoch: u64, checkpoint: &str, commit: &str, reveal: &str| BeaconShare {
+                signer: signers[i].0.clone(),
+                commit: commit.to_string(),
+                reveal: reveal.to_string(),
+                signature: signers[i]
+                    .1
+                    .sign_bytes(&share_bytes("vision", epoch, checkpoint, commit, reveal))
+                    .unwrap(),
+            };
+        let valid = |peer: &str, bytes: &[u8], sig: &SignatureBundle| {
+            signers
+                .iter()
+                .any(|(signer, crypto)| signer == peer && crypto.eth_address() == sig.eth.address)
+                && signers[0].1.verify(bytes, sig)
+        };
+
+        let mut chain = BeaconChain::new("vision", 2);
+        let mut other = BeaconChain::new("vision", 2);
+        assert_eq!(chain.checkpoint(), genesis("vision"));
+        let checkpoint = chain.checkpoint();
+        // 首轮没有可揭示的秘密
+        let (commit_a, reveal) = chain.next_secret("a");
+        assert!(reveal.is_empty());
+        let commit_b = commitment("secret-b");
+        assert!(chain
+            .add_share(
+                5,
+                &checkpoint,
+                share(1, 5, &checkpoint, &commit_b, "secret-b")
+            )
+            .is_none());
+        assert!(chain
+            .add_share(5, &checkpoint, share(1, 5, &checkpoint, &commit_b, ""))
+            .is_none());
+        // 重复的份额不计数
+        assert!(chain
+            .add_share(5, &checkpoint, share(1, 5, &checkpoint, &commit_b, ""))
+            .is_none());
+        let round = chain
+            .add_share(5, &checkpoint, share(0, 5, &checkpoint, &commit_a, ""))
+            .unwrap();
+        assert_eq!(round.shares[0].signer, "a");
+        assert!(round.verify("vision", 2, valid));
+        assert!(!round.verify("other", 2, valid));
+        assert!(!round.verify("vision", 3, valid));
+
+        // 另一节点凑出的不同份额组合：揭示数相同，两边都取签名者列表较小的一轮
+        let commit_c = commitment("secret-c");
+        assert!(other
+            .add_share(5, &checkpoint, share(2, 5, &checkpoint, &commit_c, ""))
+            .is_none());
+        let alternative = other
+            .add_share(5, &checkpoint, share(1, 5, &checkpoint, &commit_b, ""))
+            .unwrap();
+        assert!(chain.accept(round.clone()));
+        assert!(other.accept(alternative.clone()));
+        assert!(!chain.accept(alternative.clone()));
+        assert!(other.accept(round.clone()));
+        assert_eq!(chain.seed(), other.seed());
+        assert_eq!(chain.seed(), Some(round.value()));
+        // 旧纪元的轮次不采纳
+        assert!(!chain.accept(BeaconRound {
+            epoch: 4,
+            ..round.clone()
+        }));
+        assert_ne!(chain.checkpoint(), checkpoint);
+
+        // 下一纪元揭示上一轮提交的秘密；与提交不符的揭示被拒绝
+        let next = chain.checkpoint();
+        let (commit, reveal_a) = chain.next_secret("a");
+        assert_eq!(commitment(&reveal_a), commit_a);
+        assert!(chain
+            .add_share(6, &next, share(1, 6, &next, &commit_b, "forged"))
+            .is_none());
+        assert!(chain
+            .add_share(6, &next, share(1, 6, &next, &commit_b, "secret-b"))
+            .is_none());
+        let revealed = chain
+            .add_share(6, &next, share(0, 6, &next, &commit, &reveal_a))
+            .unwrap();
+        assert_eq!(revealed.revealed(), 2);
+        // 信标值不含签名，重新签名无法改变结果
+        let mut resigned = revealed.clone();
+        resigned.shares[0].signature = resigned.shares[1].signature.clone();
+        assert_eq!(resigned.value(), revealed.value());
+        let mut forged = revealed.clone();
+        forged.shares[1].reveal = "forged".into();
+        assert!(!chain.accept(forged));
+        assert!(chain.accept(revealed.clone()));
+        // 新节点直接采纳更新的纪元；已跟上的节点不采纳不接续本链的下一纪元
+        let mut fresh = BeaconChain::new("vision", 2);
+        assert!(fresh.accept(revealed.clone()));
+        let detached = BeaconRound {
+            epoch: 7,
+            checkpoint: "00".into(),
+            ..revealed
+        };
+        assert!(!chain.accept(detached));
+
+        let config = BeaconConfig::default();
+        let now = 6_000;
+        assert!(config.admits(config.epoch_at(now) + 1, now));
+        assert!(!config.admits(config.epoch_at(now) + 2, now));
+        assert!(!config.admits(u64::MAX, now));
+
+        let seed = chain.seed().unwrap();
+        let candidates = vec![
+            ("a".to_string(), 3.0),
+            ("b".to_string(), 1.0),
+            ("c".to_string(), 0.0),
+            ("d".to_string(), 2.0),
+        ];
+        let committee = elect(&seed, &candidates, 2);
+        assert_eq!(committee.len(), 2);
+        assert!(!committee.contains(&"c".to_string()));
+        let reversed: Vec<(String, f32)> = candidates.iter().rev().cloned().collect();
+        assert_eq!(elect(&seed, &reversed, 2), committee);
+        assert_eq!(elect(&seed, &candidates, 10).len(), 3);
+    }
+}
@@ -0,0 +1,216 @@
+//! 按发送者的更新序号（版本向量）
+//!
+//! 每个节点为自己发出的稀疏更新分配单调递增的序号，并保留最近若干条以备补发。
+//! 接收方按发送者记录已见的最高序号与缺失的序号：序号跳跃时发出补发请求，
+//! 而不是悄悄跳过中间的更新；补发到达时填补空洞，重复的更新直接丢弃。
+//! 序号 0 表示旧版本节点未编号的更新，不做因果追踪。
+
+use crate::types::SparseUpdate;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// 单个发送者最多追踪 / 补发的缺失序号数；落后更多视为对方重启或长时间断连
+pub const MAX_BACKFILL: u64 = 64;
+
+/// 同一请求方两次补发之间的最小间隔
+pub const BACKFILL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Observation {
+    /// 新的更新（含补发填补的空洞）
+    Fresh,
+    /// 已处理过的序号
+    Duplicate,
+    /// 新的更新，且其前面缺失了这些序号
+    Gap(Range<u64>),
+}
+
+#[derive(Default)]
+struct PeerCursor {
+    highest: u64,
+    missing: BTreeSet<u64>,
+}
+
+/// 接收方：发送者 -> 已见序号
+#[derive(Default)]
+pub struct VersionVector {
+    peers: HashMap<String, PeerCursor>,
+}
+
+impl VersionVector {
+    pub fn observe(&mut self, peer: &str, seq: u64) -> Observation {
+        if seq == 0 {
+            return Observation::Fresh;
+        }
+        let Some(cursor) = self.peers.get_mut(peer) else {
+            // 加入之前的更新不补发
+            self.peers.insert(
+                peer.to_string(),
+                PeerCursor {
+                    highest: seq,
+                    missing: BTreeSet::new(),
+                },
+            );
+            return Observation::Fresh;
+        };
+        if seq > cursor.highest {
+            let gap = (cursor.highest + 1).max(seq.saturating_sub(MAX_BACKFILL))..seq;
+            cursor.highest = seq;
+            cursor.missing.extend(gap.clone());
+            while cursor.missing.len() as u64 > MAX_BACKFILL {
+                cursor.missing.pop_first();
+            }
+            if gap.is_empty() {
+                Observation::Fresh
+            } else {
+                Observation::Gap(gap)
+            }
+        } else if cursor.missing.remove(&seq) {
+            Observation::Fresh
+        } else if cursor.highest - seq > MAX_BACKFILL {
+            // 序号大幅回退：发送者重启后重新编号
+            *cursor = PeerCursor {
+                highest: seq,
+                missing: BTreeSet::new(),
+            };
+            Observation::Fresh
+        } else {
+            Observation::Duplicate
+        }
+    }
+
+    /// 已见但未能处理（被丢弃）的序号重新记为缺失，发送方重发时按新更新接收
+    pub fn forget(&mut self, peer: &str, seq: u64) {
+        if let Some(cursor) = self.peers.get_mut(peer) {
+            if seq != 0 && seq <= cursor.highest {
+                cursor.missing.insert(seq);
+            }
+        }
+    }
+}
+
+/// 发送方：为本节点的更新编号并保留最近的更新以备补发
+pub struct SentLog {
+    next_seq: u64,
+    recent: VecDeque<SparseUpdate>,
+}
+
+impl Default for SentLog {
+    fn default() -> Self {
+        Self {
+            next_seq: 1,
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl SentLog {
+    /// 分配序号并记录
+    pub fn stamp(&mut self, mut update: SparseUpdate) -> SparseUpdate {
+        update.seq = self.next_seq;
+        self.next_seq += 1;
+        self.recent.push_back(update.clone());
+        while self.recent.len() as u64 > MAX_BACKFILL {
+            self.recent.pop_front();
+        }
+        update
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.recent.iter().map(update_bytes).sum()
+    }
+
+    /// 内存紧张时丢弃最旧的补发记录，返回释放的字节数
+    pub fn shed(&mut self, target: usize) -> usize {
+        let mut freed = 0;
+        while freed < target {
+            match self.recent.pop_front() {
+                Some(update) => freed += update_bytes(&update),
+                None => break,
+            }
+        }
+        freed
+    }
+
+    /// 仍保留着的、落在请求区间内的更新
+    pub fn range(&self, seqs: &Range<u64>) -> Vec<SparseUpdate> {
+        self.recent
+            .iter()
+            .filter(|u| seqs.contains(&u.seq))
+            .cloned()
+            .collect()
+    }
+}
+
+/// 发送方：按请求方限制补发频率，避免一个节点反复请求放大流量
+#[derive(Default)]
+pub struct BackfillLimiter {
+    served: HashMap<String, Instant>,
+}
+
+impl BackfillLimiter {
+    /// 距上次为该请求方补发已超过 `BACKFILL_INTERVAL` 时放行并记录
+    pub fn admit(&mut self, peer: &str, now: Instant) -> bool {
+        if let Some(last) = self.served.get(peer) {
+            if now.duration_since(*last) < BACKFILL_INTERVAL {
+                return false;
+            }
+        }
+        self.served
+            .retain(|_, last| now.duration_since(*last) < BACKFILL_INTERVAL);
+        self.served.insert(peer.to_string(), now);
+        true
+    }
+}
+
+fn update_bytes(update: &SparseUpdate) -> usize {
+    (update.indices.len() + update.values.len()) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_detection_and_backfill() {
+        let mut log = SentLog::default();
+        let sent: Vec<_> = (0..5)
+            .map(|version| {
+                log.stamp(SparseUpdate {
+                    indices: vec![0],
+                    values: vec![1.0],
+                    version,
+                    seq: 0,
+                })
+            })
+            .collect();
+        assert_eq!(sent[4].seq, 5);
+
+        let mut vv = VersionVector::default();
+        assert_eq!(vv.observe("a", 1), Observation::Fresh);
+        // 2、3 丢失
+        assert_eq!(vv.observe("a", 4), Observation::Gap(2..4));
+        let backfill = log.range(&(2..4));
+        assert_eq!(backfill.len(), 2);
+        for update in &backfill {
+            assert_eq!(vv.observe("a", update.seq), Observation::Fresh);
+        }
+        assert_eq!(vv.observe("a", 3), Observation::Duplicate);
+        // 被丢弃的更新重发时重新接收
+        vv.forget("a", 3);
+        assert_eq!(vv.observe("a", 3), Observation::Fresh);
+        assert_eq!(vv.observe("a", 5), Observation::Fresh);
+        // 重启后从 1 重新编号
+        assert_eq!(vv.observe("a", 500), Observation::Gap(436..500));
+        assert_eq!(vv.observe("a", 1), Observation::Fresh);
+        assert_eq!(vv.observe("a", 0), Observation::Fresh);
+
+        let mut limiter = BackfillLimiter::default();
+        let now = Instant::now();
+        assert!(limiter.admit("b", now));
+        assert!(!limiter.admit("b", now + Duration::from_secs(1)));
+        assert!(limiter.admit("c", now + Duration::from_secs(1)));
+        assert!(limiter.admit("b", now + BACKFILL_INTERVAL));
+    }
+}
@@ -0,0 +1,252 @@
+//! 与节点身份绑定的 QUIC 证书
+//!
+//! 证书直接用节点的 ed25519 身份密钥签发，证书公钥即 PeerId 中的公钥，TLS 握手签名证明对端持有该密钥：
+//! - 按心跳宣告的地址拨号时固定对端 PeerId，证书公钥不符即中止握手，中间人无法冒充
+//! - 引导列表等未知身份的地址只要求证书与某个身份绑定，连接建立后从证书取出对端 PeerId
+//! - 双向认证：入站连接同样出示证书，连接表按证书中的 PeerId 记录对端
+
+use libp2p::identity::{self, PeerId};
+use rustls::client::{ClientSessionMemoryCache, Resumption, ServerCertVerified, ServerCertVerifier};
+use rustls::server::{ClientCertVerified, ClientCertVerifier};
+use rustls::{Certificate, DistinguishedName, PrivateKey, ServerName};
+use std::sync::Arc;
+use std::time::SystemTime;
+use thiserror::Error;
+use x509_parser::oid_registry::OID_SIG_ED25519;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// 证书中的主机名，对端校验 PeerId 而不校验主机名
+pub const SERVER_NAME: &str = "ggs-quic";
+
+/// ed25519 私钥的 PKCS#8 v1 编码前缀，后接 32 字节种子
+const PKCS8_ED25519_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+#[derive(Debug, Error)]
+pub enum CertError {
+    #[error("QUIC 证书需要 ed25519 节点身份")]
+    NotEd25519,
+    #[error("生成 QUIC 证书失败: {0}")]
+    Generate(#[from] rcgen::Error),
+    #[error("无法解析对端证书: {0}")]
+    Parse(String),
+    #[error("对端证书属于 {actual}，期望 {expected}")]
+    PeerMismatch { expected: String, actual: String },
+}
+
+/// 本节点的证书与私钥
+#[derive(Clone)]
+pub struct NodeCert {
+    pub cert: Certificate,
+    pub key: PrivateKey,
+}
+
+impl NodeCert {
+    /// 用节点身份密钥自签发证书
+    pub fn from_identity(keypair: &identity::Keypair) -> Result<Self, CertError> {
+        let ed25519 = keypair.clone().try_into_ed25519().map_err(|_| CertError::NotEd25519)?;
+        let mut pkcs8 = PKCS8_ED25519_PREFIX.to_vec();
+        pkcs8.extend_from_slice(ed25519.secret().as_ref());
+        let key_pair = rcgen::KeyPair::from_der(&pkcs8)?;
+        let mut params = rcgen::CertificateParams::new(vec![SERVER_NAME.into()]);
+        params.alg = &rcgen::PKCS_ED25519;
+        params.key_pair = Some(key_pair);
+        let cert = rcgen::Certificate::from_params(params)?;
+        Ok(Self {
+            cert: Certificate(cert.serialize_der()?),
+            key: PrivateKey(pkcs8),
+        })
+    }
+
+    /// 服务端配置：要求对端出示与身份绑定的证书，接受恢复会话的 0-RTT 数据
+    pub fn server_config(&self) -> Result<rustls::ServerConfig, rustls::Error> {
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(Arc::new(IdentityVerifier { expected: None }))
+            .with_single_cert(vec![self.cert.clone()], self.key.clone())?;
+        // QUIC 要求 0 或 u32::MAX
+        config.max_early_data_size = u32::MAX;
+        Ok(config)
+    }
+
+    /// 拨号配置：`expected` 为已知的对端 PeerId 时只接受该节点的证书；
+    /// 会话票据存入 `tickets`，重连时据此恢复会话并可发送 0-RTT 数据
+    pub fn client_config(
+        &self,
+        expected: Option<PeerId>,
+        tickets: Arc<ClientSessionMemoryCache>,
+    ) -> Result<rustls::ClientConfig, rustls::Error> {
+        let mut config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(IdentityVerifier { expected }))
+            .with_client_auth_cert(vec![self.cert.clone()], self.key.clone())?;
+        config.resumption = Resumption::store(tickets);
+        config.enable_early_data = true;
+        Ok(config)
+    }
+}
+
+/// 拨号时的 TLS 主机名。会话票据按主机名缓存，已知对端时以其 PeerId 区分，
+/// 票据只会在同一节点上恢复；证书校验只看 PeerId，不校验主机名
+pub fn server_name(peer: Option<&str>) -> String {
+    match peer {
+        Some(peer) => format!("{}.{}", peer.to_ascii_lowercase(), SERVER_NAME),
+        None => SERVER_NAME.to_string(),
+    }
+}
+
+/// 证书公钥对应的 PeerId；证书公钥不是 ed25519 时报错
+pub fn peer_id(cert: &Certificate) -> Result<PeerId, CertError> {
+    let (_, parsed) = X509Certificate::from_der(&cert.0).map_err(|e| CertError::Parse(e.to_string()))?;
+    let spki = parsed.public_key();
+    if spki.algorithm.algorithm != OID_SIG_ED25519 {
+        return Err(CertError::Parse("证书公钥不是 ed25519".into()));
+    }
+    let public = identity::ed25519::PublicKey::try_from_bytes(&spki.subject_public_key.data)
+        .map_err(|e| CertError::Parse(e.to_string()))?;
+    Ok(PeerId::from(identity::PublicKey::from(public)))
+}
+
+/// 已建立连接的对端 PeerId，取自握手时校验过的证书
+pub fn connection_peer(connection: &quinn::Connection) -> Option<PeerId> {
+    let identity = connection.peer_identity()?;
+    let certs = identity.downcast::<Vec<Certificate>>().ok()?;
+    peer_id(certs.first()?).ok()
+}
+
+/// 校验对端证书与身份绑定（必要时还须属于指定节点）；握手签名由 rustls 按证书公钥校验
+struct IdentityVerifier {
+    expected: Option<PeerId>,
+}
+
+impl IdentityVerifier {
+    fn verify(&self, end_entity: &Certificate) -> Result<(), rustls::Error> {
+        let actual = peer_id(end_entity).map_err(|e| rustls::Error::General(e.to_string()))?;
+        match self.expected {
+            Some(expected) if expected != actual => Err(rustls::Error::General(
+                CertError::PeerMismatch { expected: expected.to_string(), actual: actual.to_string() }
+                    .to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ServerCertVerifier for IdentityVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.verify(end_entity)?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for IdentityVerifier {
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.verify(end_entity)?;
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certificate_binds_node_identity() {
+        let keypair = identity::Keypair::generate_ed25519();
+        let own = PeerId::from(keypair.public());
+        let node = NodeCert::from_identity(&keypair).unwrap();
+        assert_eq!(peer_id(&node.cert).unwrap(), own);
+
+        let verifier = IdentityVerifier { expected: Some(own) };
+        assert!(verifier.verify(&node.cert).is_ok());
+        assert!(IdentityVerifier { expected: None }.verify(&node.cert).is_ok());
+        let other = NodeCert::from_identity(&identity::Keypair::generate_ed25519()).unwrap();
+        assert!(verifier.verify(&other.cert).is_err());
+
+        // 非身份密钥签发的证书不被接受
+        let throwaway = rcgen::generate_simple_self_signed(vec![SERVER_NAME.into()]).unwrap();
+        let throwaway = Certificate(throwaway.serialize_der().unwrap());
+        assert!(IdentityVerifier { expected: None }.verify(&throwaway).is_err());
+
+        // 按 PeerId 区分的主机名是合法的 DNS 名
+        assert_eq!(server_name(None), SERVER_NAME);
+        assert!(ServerName::try_from(server_name(Some(&own.to_string())).as_str()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_resumes_session_with_0rtt() {
+        let server_key = identity::Keypair::generate_ed25519();
+        let server_peer = PeerId::from(server_key.public()).to_string();
+        let server_cert = NodeCert::from_identity(&server_key).unwrap();
+        let crypto = Arc::new(server_cert.server_config().unwrap());
+        let server = quinn::Endpoint::server(
+            quinn::ServerConfig::with_crypto(crypto),
+            "127.0.0.1:0".parse().unwrap(),
+        )
+        .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Some(connecting) = server.accept().await {
+                tokio::spawn(async move {
+                    let (connection, _) = connecting.into_0rtt().ok()?;
+                    let mut recv = connection.accept_uni().await.ok()?;
+                    let echoed = recv.read_to_end(64).await.ok()?;
+                    let mut send = connection.open_uni().await.ok()?;
+                    send.write_all(&echoed).await.ok()?;
+                    send.finish().await.ok()
+                });
+            }
+        });
+
+        let client_cert = NodeCert::from_identity(&identity::Keypair::generate_ed25519()).unwrap();
+        // 容量按票据数计，每个节点最多 8 张；过小时缓存存不下任何节点
+        let tickets = Arc::new(ClientSessionMemoryCache::new(64));
+        let crypto = client_cert
+            .client_config(Some(server_peer.parse().unwrap()), tickets)
+            .unwrap();
+        let mut client = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        client.set_default_client_config(quinn::ClientConfig::new(Arc::new(crypto)));
+        let round_trip = |connection: quinn::Connection| async move {
+            let mut send = connection.open_uni().await.unwrap();
+            send.write_all(b"ping").await.unwrap();
+            send.finish().await.unwrap();
+            let mut recv = connection.accept_uni().await.unwrap();
+            assert_eq!(recv.read_to_end(64).await.unwrap(), b"ping");
+            connection.close(0u32.into(), b"done");
+        };
+        let name = server_name(Some(&server_peer));
+
+        // 首次连接没有票据，只能完成完整握手；握手后收到票据
+        let connecting = client.connect(addr, &name).unwrap();
+        let connecting = match connecting.into_0rtt() {
+            Ok(_) => panic!("首次连接不应有 0-RTT"),
+            Err(connecting) => connecting,
+        };
+        round_trip(connecting.await.unwrap()).await;
+
+        // 重连时凭票据恢复会话，0-RTT 数据被对端接受
+        let Ok((connection, accepted)) = client.connect(addr, &name).unwrap().into_0rtt() else {
+            panic!("重连未能恢复会话");
+        };
+        round_trip(connection).await;
+        assert!(accepted.await);
+    }
+}
@@ -0,0 +1,214 @@
+//! 模型检查点文件
+//!
+//! 定期（以及退出时）把参数、误差残差与版本写入检查点目录，重启时由 `load_or_random`
+//! 从最新的完好检查点恢复，而不是重新随机初始化。每个检查点一个文件 `model-<版本>.ckpt`，
+//! 只保留最近若干个；写入先落临时文件再 rename，文件尾部带 Keccak256 校验和，
+//! 半写或损坏的文件在恢复时跳过并退回更早的检查点。
+//!
+//! 格式（小端）：`GGSCKPT1` | 版本 u64 | 维度 u64 | 参数 f32 × 维度 | 残差 f32 × 维度 | 校验和 32 字节
+
+use crate::inference::ModelCheckpoint;
+use crate::persistence::write_atomic;
+use anyhow::{anyhow, Result};
+use sha3::{Digest, Keccak256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAGIC: &[u8; 8] = b"GGSCKPT1";
+const EXTENSION: &str = "ckpt";
+/// 检查点允许的最大维度，防止损坏或伪造的文件头导致超大分配与溢出
+pub const MAX_DIM: usize = 1 << 28;
+
+#[derive(Clone, Debug)]
+pub struct CheckpointConfig {
+    /// 两次写入之间的间隔
+    pub interval: Duration,
+    /// 保留的检查点个数
+    pub keep: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(300),
+            keep: 3,
+        }
+    }
+}
+
+fn checkpoint_path(dir: &Path, version: u64) -> PathBuf {
+    dir.join(format!("model-{:020}.{}", version, EXTENSION))
+}
+
+pub fn encode(checkpoint: &ModelCheckpoint) -> Vec<u8> {
+    let dim = checkpoint.params.len();
+    let mut out = Vec::with_capacity(8 + 16 + dim * 8 + 32);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&checkpoint.version.to_le_bytes());
+    out.extend_from_slice(&(dim as u64).to_le_bytes());
+    for value in &checkpoint.params {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    // 旧检查点或刚初始化的模型没有残差，按全零写入
+    for i in 0..dim {
+        let value = checkpoint.residual.get(i).copied().unwrap_or(0.0);
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    let digest: [u8; 32] = Keccak256::digest(&out).into();
+    out.extend_from_slice(&digest);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<ModelCheckpoint> {
+    let body_len = bytes
+        .len()
+        .checked_sub(32)
+        .ok_or_else(|| anyhow!("检查点过短"))?;
+    let (body, digest) = bytes.split_at(body_len);
+    let expected: [u8; 32] = Keccak256::digest(body).into();
+    if expected[..] != *digest {
+        return Err(anyhow!("检查点校验和不符"));
+    }
+    if body.len() < 24 || &body[..8] != MAGIC {
+        return Err(anyhow!("不是检查点文件"));
+    }
+    let version = u64::from_le_bytes(body[8..16].try_into()?);
+    let dim = u64::from_le_bytes(body[16..24].try_into()?);
+    let dim = usize::try_from(dim)
+        .ok()
+        .filter(|dim| *dim <= MAX_DIM)
+        .ok_or_else(|| anyhow!("检查点维度 {} 超过上限 {}", dim, MAX_DIM))?;
+    let floats = &body[24..];
+    if dim.checked_mul(8) != Some(floats.len()) {
+        return Err(anyhow!("检查点长度与维度 {} 不符", dim));
+    }
+    let read = |chunk: &[u8]| -> Vec<f32> {
+        chunk
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    };
+    let (params, residual) = floats.split_at(dim * 4);
+    Ok(ModelCheckpoint {
+        params: read(params),
+        version,
+        residual: read(residual),
+        previous_params: None,
+    })
+}
+
+/// 写入检查点并清理超出保留个数的旧文件
+pub fn save(dir: &Path, checkpoint: &ModelCheckpoint, keep: usize) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = checkpoint_path(dir, checkpoint.version);
+    write_atomic(&path, &encode(checkpoint))?;
+    let mut existing = list(dir)?;
+    existing.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    for (_, old) in existing.into_iter().skip(keep.max(1)) {
+        if let Err(e) = std::fs::remove_file(&old) {
+            eprintln!("[检查点] 删除旧检查点 {} 失败: {:?}", old.display(), e);
+        }
+    }
+    Ok(path)
+}
+
+/// 最新的、维度匹配的完好检查点（不指定维度时不检查）；目录不存在或没有可用检查点时返回 None
+pub fn latest(dir: &Path, dim: Option<usize>) -> Result<Option<ModelCheckpoint>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let mut existing = list(dir)?;
+    existing.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    for (_, path) in existing {
+        let checkpoint = std::fs::read(&path).map_err(anyhow::Error::from).and_then(|b| decode(&b));
+        match checkpoint {
+            Ok(checkpoint) if dim.is_none_or(|dim| checkpoint.params.len() == dim) => {
+                return Ok(Some(checkpoint))
+            }
+            Ok(checkpoint) => eprintln!(
+                "[检查点] 跳过 {}：维度 {} 与模型维度 {:?} 不符",
+                path.display(),
+                checkpoint.params.len(),
+                dim
+            ),
+            Err(e) => eprintln!("[检查点] 跳过损坏的 {}: {}", path.display(), e),
+        }
+    }
+    Ok(None)
+}
+
+/// 目录中所有检查点文件的解码结果（新版本在前），供审计报告损坏的文件
+pub fn scan(dir: &Path) -> Result<Vec<(PathBuf, Result<ModelCheckpoint>)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut existing = list(dir)?;
+    existing.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    Ok(existing
+        .into_iter()
+        .map(|(_, path)| {
+            let checkpoint = std::fs::read(&path).map_err(anyhow::Error::from).and_then(|b| decode(&b));
+            (path, checkpoint)
+        })
+        .collect())
+}
+
+/// 目录中的检查点文件 (版本, 路径)
+fn list(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let version = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix("model-"))
+            .and_then(|v| v.parse().ok());
+        if let Some(version) = version {
+            out.push((version, path));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resumes_latest_intact_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("ggs-ckpt-{}", rand::random::<u64>()));
+        let checkpoint = |version, value| ModelCheckpoint {
+            params: vec![value; 4],
+            version,
+            residual: vec![value / 2.0; 4],
+            previous_params: None,
+        };
+        for version in 1..=4 {
+            save(&dir, &checkpoint(version, version as f32), 2).unwrap();
+        }
+        assert_eq!(list(&dir).unwrap().len(), 2);
+        let restored = latest(&dir, Some(4)).unwrap().unwrap();
+        assert_eq!(restored.version, 4);
+        assert_eq!(restored.residual, vec![2.0; 4]);
+
+        // 最新的文件损坏时退回更早的检查点，维度不符时不恢复
+        let newest = checkpoint_path(&dir, 4);
+        let mut bytes = std::fs::read(&newest).unwrap();
+        bytes[30] ^= 0xff;
+        std::fs::write(&newest, bytes).unwrap();
+        assert_eq!(latest(&dir, Some(4)).unwrap().unwrap().version, 3);
+        assert!(latest(&dir, Some(8)).unwrap().is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+
+        // 维度字段过大时报错，而不是溢出或按其分配内存
+        let mut body = MAGIC.to_vec();
+        body.extend_from_slice(&1u64.to_le_bytes());
+        body.extend_from_slice(&(u64::MAX / 4).to_le_bytes());
+        let digest: [u8; 32] = Keccak256::digest(&body).into();
+        body.extend_from_slice(&digest);
+        assert!(decode(&body).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! 心跳中的分块模型哈希
+//!
+//! 参数向量按固定长度切成若干块，每块一个叶子哈希，再两两合并成 Merkle 根。
+//! 心跳可选携带根与第一层（叶子）哈希：接收方发现模型哈希不一致时只需对比叶子，
+//! 就能定位到哪些区间出现分歧，分层同步与分片下载可以只拉取这些区间。
+//!
+//! 叶子 = Keccak256(0x00 | 块序号 u64 | f32 小端 × 块长)，内部节点 = Keccak256(0x01 | 左 | 右)，
+//! 奇数个节点时最后一个直接升入上一层。
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelChunkHashes {
+    /// 每块的参数个数（最后一块可能更短）
+    pub chunk_len: usize,
+    pub root: String,
+    /// 第一层叶子哈希，按块顺序
+    pub hashes: Vec<String>,
+}
+
+impl ModelChunkHashes {
+    /// 把参数切成约 `chunks` 块计算哈希
+    pub fn compute(params: &[f32], chunks: usize) -> Self {
+        let chunk_len = params.len().div_ceil(chunks.max(1)).max(1);
+        Self::with_chunk_len(params, chunk_len)
+    }
+
+    /// 按给定块长计算，接收方用对方的块长计算本地哈希以便逐块对比
+    pub fn with_chunk_len(params: &[f32], chunk_len: usize) -> Self {
+        let leaves: Vec<[u8; 32]> = params
+            .chunks(chunk_len.max(1))
+            .enumerate()
+            .map(|(index, chunk)| leaf_hash(index, chunk))
+            .collect();
+        Self {
+            chunk_len: chunk_len.max(1),
+            root: hex::encode(merkle_root(&leaves)),
+            hashes: leaves.iter().map(hex::encode).collect(),
+        }
+    }
+
+    /// 叶子哈希能否重建出宣告的根
+    pub fn verify(&self) -> bool {
+        let leaves: Option<Vec<[u8; 32]>> = self
+            .hashes
+            .iter()
+            .map(|h| hex::decode(h).ok().and_then(|b| b.try_into().ok()))
+            .collect();
+        leaves.is_some_and(|leaves| hex::encode(merkle_root(&leaves)) == self.root)
+    }
+
+    /// 与本地参数不一致的区间；块数与维度对不上时返回 None
+    pub fn diverging_ranges(&self, local: &[f32]) -> Option<Vec<Range<usize>>> {
+        let chunk_len = self.chunk_len.max(1);
+        if self.hashes.len() != local.len().div_ceil(chunk_len) {
+            return None;
+        }
+        let ours = Self::with_chunk_len(local, chunk_len);
+        if ours.root == self.root {
+            return Some(Vec::new());
+        }
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (index, (theirs, ours)) in self.hashes.iter().zip(&ours.hashes).enumerate() {
+            if theirs == ours {
+                continue;
+            }
+            let range = index * chunk_len..((index + 1) * chunk_len).min(local.len());
+            // 相邻的分歧块合并成一个区间
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        Some(ranges)
+    }
+}
+
+fn leaf_hash(index: usize, chunk: &[f32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update([0u8]);
+    hasher.update((index as u64).to_le_bytes());
+    for value in chunk {
+        hasher.update(value.to_le_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return Keccak256::digest([]).into();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = Keccak256::new();
+                    hasher.update([1u8]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize().into()
+                }
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localizes_diverging_chunks() {
+        let params: Vec<f32> = (0..10).map(|i| i as f32).collect();
+        let hashes = ModelChunkHashes::compute(&params, 4);
+        assert_eq!((hashes.chunk_len, hashes.hashes.len()), (3, 4));
+        assert!(hashes.verify());
+        assert_eq!(hashes.diverging_ranges(&params), Some(Vec::new()));
+
+        // 相邻的第 2、3 块合并成一个区间，最后一块截断到维度
+        let mut local = params.clone();
+        local[1] += 1.0;
+        local[7] += 1.0;
+        local[9] += 1.0;
+        assert_eq!(hashes.diverging_ranges(&local), Some(vec![0..3, 6..10]));
+        assert_eq!(hashes.diverging_ranges(&local[..4]), None);
+
+        let mut forged = hashes.clone();
+        forged.hashes[0] = forged.hashes[1].clone();
+        assert!(!forged.verify());
+    }
+}
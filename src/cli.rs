@@ -0,0 +1,618 @@
+//! 命令行子命令
+//!
+//! `ggs run` 运行节点（参数见 [`NodeArgs`]；不带子命令直接传参数时等同 run，
+//! 兼容旧的启动方式），其余子命令是离线工具：`keygen` 生成 ETH / Solana / libp2p 身份，
+//! `inspect` 解码 SignedGossip 或 TensorSnapshot，`checkpoint export/import` 导出导入模型状态，
+//! `vectors generate/verify` 生成与检查互操作测试向量。
+
+use crate::aggregation::AggregationRule;
+use crate::analytics::l2_norm;
+use crate::applyqueue::Shedding;
+use crate::checkpoint;
+use crate::codec::{self, WireCodec};
+use crate::comms::{load_or_create_identity, SendPolicy, TransportKind};
+use crate::consensus::{signature_valid, SignedGossip};
+use crate::crypto::{CryptoConfig, CryptoSuite, Keystore, KEYSTORE_PASSPHRASE_ENV};
+use crate::gating::TokenRequirement;
+use crate::hyperparams::{EpochSchedule, HyperParams};
+use crate::inference::ModelCheckpoint;
+use crate::layers::LayerLayout;
+use crate::persistence::StateStore;
+use crate::role::NodeRole;
+use crate::session::DEFAULT_SESSION;
+use crate::types::{GeoPoint, GgsMessage, TensorSnapshot};
+use crate::vectors;
+use crate::windows::{parse_utc_offset, TimeWindow};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use libp2p::Multiaddr;
+use ndarray::Array1;
+use ndarray_npy::{ReadNpyExt, WriteNpyExt};
+use std::fs::File;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// 解码后超过该长度的消息不整段打印
+const MAX_PRINT_BYTES: usize = 4096;
+
+#[derive(Parser)]
+#[command(name = "ggs", about = "GGS 去中心化训练节点")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 运行节点（参数见 README）
+    Run(Box<NodeArgs>),
+    /// 生成 ETH / Solana / libp2p 身份
+    Keygen {
+        /// 写入 libp2p 身份密钥文件（已存在时拒绝覆盖）
+        #[arg(long)]
+        identity: Option<PathBuf>,
+        /// 把种子加密写入密钥库目录（口令取自 GGS_KEYSTORE_PASSPHRASE），否则明文打印
+        #[arg(long)]
+        keystore: Option<PathBuf>,
+    },
+    /// 解码 SignedGossip 或 TensorSnapshot（JSON）
+    Inspect { file: PathBuf },
+    /// 模型状态导出 / 导入
+    #[command(subcommand)]
+    Checkpoint(CheckpointCommand),
+    /// 互操作测试向量（供其他语言的实现核对线上格式）
+    #[command(subcommand)]
+    Vectors(VectorsCommand),
+}
+
+#[derive(Subcommand)]
+enum VectorsCommand {
+    /// 为每种消息类型与每种直连编码生成签名消息，连同密钥与期望哈希写入目录
+    Generate { dir: PathBuf },
+    /// 检查目录中的语料：文件哈希、签名、签名字节与消息 id
+    Verify { dir: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum CheckpointCommand {
+    /// 导出模型：.npy 只含参数，.ckpt 含版本与残差
+    Export {
+        /// 从节点状态目录（node_state.json）导出
+        #[arg(long, conflicts_with = "checkpoint_dir", required_unless_present = "checkpoint_dir")]
+        state_dir: Option<PathBuf>,
+        /// 从检查点目录中最新的检查点导出
+        #[arg(long)]
+        checkpoint_dir: Option<PathBuf>,
+        /// 状态目录中的会话
+        #[arg(long, default_value = DEFAULT_SESSION)]
+        session: String,
+        out: PathBuf,
+    },
+    /// 把 .ckpt 或 .npy 导入检查点目录，节点下次启动时从中恢复
+    Import {
+        file: PathBuf,
+        #[arg(long)]
+        checkpoint_dir: PathBuf,
+        /// .npy 没有版本信息，导入时使用的版本
+        #[arg(long, default_value_t = 0)]
+        version: u64,
+        #[arg(long, default_value_t = checkpoint::CheckpointConfig::default().keep)]
+        keep: usize,
+    },
+}
+
+/// 节点参数。命令行值无法解析时报错退出；未给出的项沿用配置文件、GGS_* 环境变量或默认值
+#[derive(Parser, Debug)]
+#[command(about = "运行 GGS 节点", args_override_self = true)]
+pub struct NodeArgs {
+    /// TOML 配置文件，未指定时读取 GGS_CONFIG 环境变量
+    #[arg(long = "config")]
+    pub config_file: Option<PathBuf>,
+    /// 定期把统计数据导出到该 JSON 文件
+    #[arg(long)]
+    pub stats_output: Option<String>,
+    #[arg(long)]
+    pub node_id: Option<usize>,
+    #[arg(long)]
+    pub model_dim: Option<usize>,
+    #[arg(long)]
+    pub heartbeat_secs: Option<u64>,
+    #[arg(long)]
+    pub probe_secs: Option<u64>,
+    #[arg(long)]
+    pub train_secs: Option<u64>,
+    #[arg(long)]
+    pub snapshot_secs: Option<u64>,
+    #[arg(long)]
+    pub persist_secs: Option<u64>,
+    #[arg(long)]
+    pub announce_secs: Option<u64>,
+    /// 周期任务的随机抖动比例（0 ~ 0.5）
+    #[arg(long = "jitter")]
+    pub jitter_ratio: Option<f32>,
+    /// 管理接口监听地址
+    #[arg(long = "admin-addr")]
+    pub admin_bind: Option<SocketAddr>,
+    #[arg(long)]
+    pub state_dir: Option<PathBuf>,
+    /// 附加会话，可重复
+    #[arg(long = "session")]
+    pub session_specs: Vec<String>,
+    /// 发布任务描述文件，可重复
+    #[arg(long = "announce-job")]
+    pub job_files: Vec<PathBuf>,
+    #[arg(long)]
+    pub no_auto_join: bool,
+    #[arg(long)]
+    pub role: Option<NodeRole>,
+    /// 轻量节点，只作为观察者
+    #[arg(long)]
+    pub light: bool,
+    #[arg(long)]
+    pub quic_advertise: Option<SocketAddr>,
+    #[arg(long)]
+    pub aggregation: Option<AggregationRule>,
+    /// 合并队列容量
+    #[arg(long = "apply-queue")]
+    pub apply_queue_capacity: Option<usize>,
+    #[arg(long)]
+    pub apply_shedding: Option<Shedding>,
+    /// 本节点位置，格式 纬度,经度
+    #[arg(long, value_parser = parse_position, allow_hyphen_values = true)]
+    pub position: Option<GeoPoint>,
+    #[arg(long)]
+    pub geofence_km: Option<f32>,
+    /// 允许的 geohash 前缀，可重复
+    #[arg(long = "geofence-prefix")]
+    pub geofence_prefixes: Vec<String>,
+    #[arg(long = "token-gate")]
+    pub token_requirement: Option<TokenRequirement>,
+    #[arg(long)]
+    pub rewards_contract: Option<String>,
+    #[arg(long)]
+    pub chain_id: Option<u64>,
+    #[arg(long)]
+    pub rewards_interval_secs: Option<u64>,
+    #[arg(long)]
+    pub stake_proofs: bool,
+    #[arg(long)]
+    pub sol_rpc: Option<String>,
+    #[arg(long)]
+    pub stake_attest_quorum: Option<usize>,
+    /// 心跳中携带的模型分块哈希数
+    #[arg(long)]
+    pub heartbeat_chunks: Option<usize>,
+    /// 直连编码，逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    pub wire_codecs: Option<Vec<WireCodec>>,
+    #[arg(long)]
+    pub gossip_codec: Option<WireCodec>,
+    /// libp2p 传输栈，逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    pub transports: Option<Vec<TransportKind>>,
+    #[arg(long)]
+    pub no_tcp_fallback: bool,
+    #[arg(long)]
+    pub send_policy: Option<SendPolicy>,
+    #[arg(long = "proxy")]
+    pub proxy_url: Option<String>,
+    #[arg(long)]
+    pub proxy_no_mdns: bool,
+    #[arg(long)]
+    pub max_inbound: Option<u32>,
+    #[arg(long)]
+    pub max_outbound: Option<u32>,
+    #[arg(long)]
+    pub eval_shards: Option<u32>,
+    #[arg(long)]
+    pub eval_epoch_secs: Option<u64>,
+    #[arg(long)]
+    pub eval_seed: Option<u64>,
+    /// 看门狗期限，0 关闭看门狗
+    #[arg(long)]
+    pub watchdog_deadline_secs: Option<u64>,
+    #[arg(long)]
+    pub memory_budget_mb: Option<usize>,
+    /// 热备节点地址，向其复制状态
+    #[arg(long)]
+    pub replicate_to: Option<SocketAddr>,
+    /// 以热备模式在该地址接收复制
+    #[arg(long)]
+    pub standby_listen: Option<SocketAddr>,
+    #[arg(long)]
+    pub standby_takeover_secs: Option<u64>,
+    #[arg(long)]
+    pub ack_retries: Option<u32>,
+    #[arg(long)]
+    pub clusters: Option<usize>,
+    #[arg(long)]
+    pub cluster_interval_secs: Option<u64>,
+    #[arg(long)]
+    pub cross_cluster_fraction: Option<f32>,
+    #[arg(long)]
+    pub fanout: Option<usize>,
+    #[arg(long)]
+    pub propose_hyperparams: Option<HyperParams>,
+    #[arg(long)]
+    pub endorse_hyperparams: bool,
+    /// 超参数委员会成员，逗号分隔
+    #[arg(long)]
+    pub hyperparam_committee: Option<String>,
+    /// 每个纪元按质押选出的委员数
+    #[arg(long)]
+    pub hyperparam_elected: Option<usize>,
+    #[arg(long)]
+    pub hyperparam_quorum: Option<usize>,
+    #[arg(long)]
+    pub epoch_schedule: Option<EpochSchedule>,
+    #[arg(long)]
+    pub hyperparam_epoch_secs: Option<u64>,
+    /// 启用随机信标并设置门限
+    #[arg(long)]
+    pub beacon_threshold: Option<usize>,
+    #[arg(long)]
+    pub beacon_epoch_secs: Option<u64>,
+    /// 纠删码分片，格式 K:M，0:0 关闭
+    #[arg(long, value_parser = parse_erasure)]
+    pub erasure: Option<(usize, usize)>,
+    #[arg(long)]
+    pub erasure_min_bytes: Option<usize>,
+    #[arg(long)]
+    pub analytics_db: Option<PathBuf>,
+    #[arg(long = "record-trace")]
+    pub trace_record: Option<PathBuf>,
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long)]
+    pub layers: Option<LayerLayout>,
+    #[arg(long)]
+    pub analytics_retention_days: Option<u64>,
+    #[arg(long)]
+    pub ledger_db: Option<PathBuf>,
+    #[arg(long)]
+    pub ledger_retention_days: Option<u64>,
+    /// 每个发送者的持续消息速率（条/秒）
+    #[arg(long)]
+    pub rate_limit: Option<f64>,
+    #[arg(long = "import-reputation")]
+    pub reputation_import: Option<PathBuf>,
+    #[arg(long)]
+    pub sparse_k: Option<usize>,
+    #[arg(long)]
+    pub fixed_sparse_k: bool,
+    #[arg(long = "pow-bits")]
+    pub pow_difficulty: Option<u8>,
+    #[arg(long)]
+    pub eth_rpc: Option<String>,
+    #[arg(long)]
+    pub sns_api: Option<String>,
+    #[arg(long)]
+    pub nickname: Option<String>,
+    #[arg(long)]
+    pub organization: Option<String>,
+    #[arg(long)]
+    pub region_label: Option<String>,
+    #[arg(long)]
+    pub contact: Option<String>,
+    /// 训练时间窗口，可重复
+    #[arg(long = "train-window")]
+    pub train_windows: Vec<TimeWindow>,
+    /// 时间窗口所在时区，如 +08:00
+    #[arg(long, value_parser = parse_utc_offset, allow_hyphen_values = true)]
+    pub utc_offset: Option<i32>,
+    #[arg(long)]
+    pub power_saver: bool,
+    #[arg(long = "offline-max-age-secs")]
+    pub offline_max_age: Option<u64>,
+    #[arg(long = "listen")]
+    pub listen_addr: Option<Multiaddr>,
+    /// 对外宣告的地址，可重复
+    #[arg(long = "external-addr")]
+    pub external_addrs: Vec<Multiaddr>,
+    /// 引导节点，可重复
+    #[arg(long = "bootstrap-peer", visible_alias = "dial")]
+    pub bootstrap_peers: Vec<Multiaddr>,
+    /// 会合点，可重复
+    #[arg(long = "rendezvous")]
+    pub rendezvous_points: Vec<Multiaddr>,
+    #[arg(long)]
+    pub rendezvous_server: bool,
+    #[arg(long)]
+    pub dht_bootstrap: Vec<Multiaddr>,
+    #[arg(long)]
+    pub no_dht: bool,
+    #[arg(long)]
+    pub no_dual_stack: bool,
+    #[arg(long)]
+    pub accept_legacy_timestamps: bool,
+    /// 电路中继，可重复
+    #[arg(long = "circuit-relay")]
+    pub circuit_relays: Vec<Multiaddr>,
+    #[arg(long)]
+    pub relay_server: bool,
+    #[arg(long)]
+    pub relay_url: Option<String>,
+    #[arg(long)]
+    pub relay_token: Option<String>,
+    /// gossip 主题
+    #[arg(long)]
+    pub topic: Option<String>,
+    #[arg(long)]
+    pub checkpoint_dir: Option<PathBuf>,
+    #[arg(long = "checkpoint-interval-secs")]
+    pub checkpoint_interval: Option<u64>,
+    /// QUIC 直连监听地址
+    #[arg(long)]
+    pub quic_bind: Option<SocketAddr>,
+    /// libp2p 身份密钥文件
+    #[arg(long = "identity")]
+    pub identity_path: Option<PathBuf>,
+    /// 链上密钥库目录
+    #[arg(long)]
+    pub keystore: Option<PathBuf>,
+    #[arg(long)]
+    pub max_neighbors: Option<usize>,
+}
+
+/// `纬度,经度`
+fn parse_position(spec: &str) -> Result<GeoPoint> {
+    let (lat, lon) = spec
+        .split_once(',')
+        .ok_or_else(|| anyhow!("位置 {:?} 应为 纬度,经度", spec))?;
+    Ok(GeoPoint {
+        lat: lat.trim().parse()?,
+        lon: lon.trim().parse()?,
+    })
+}
+
+/// `K:M`：数据分片数与校验分片数
+fn parse_erasure(spec: &str) -> Result<(usize, usize)> {
+    let (k, m) = spec
+        .split_once(':')
+        .ok_or_else(|| anyhow!("纠删码参数 {:?} 应为 K:M", spec))?;
+    Ok((k.parse()?, m.parse()?))
+}
+
+/// 处理子命令；返回 Some(参数) 时由 main 以这些参数运行节点，None 表示子命令已执行完毕
+pub fn dispatch(args: Vec<String>) -> Result<Option<NodeArgs>> {
+    let legacy = args
+        .get(1)
+        .is_none_or(|arg| arg.starts_with('-') && arg != "-h" && arg != "--help");
+    if legacy {
+        return Ok(Some(NodeArgs::parse_from(args)));
+    }
+    match Cli::parse_from(args).command {
+        Command::Run(args) => Ok(Some(*args)),
+        Command::Keygen { identity, keystore } => keygen(identity, keystore).map(|_| None),
+        Command::Inspect { file } => inspect(&file).map(|_| None),
+        Command::Checkpoint(CheckpointCommand::Export {
+            state_dir,
+            checkpoint_dir,
+            session,
+            out,
+        }) => {
+            let model = match (state_dir, checkpoint_dir) {
+                (Some(dir), _) => model_from_state(&dir, &session)?,
+                (None, Some(dir)) => checkpoint::latest(&dir, None)?
+                    .ok_or_else(|| anyhow!("{} 中没有可用的检查点", dir.display()))?,
+                (None, None) => return Err(anyhow!("需要 --state-dir 或 --checkpoint-dir")),
+            };
+            export(&model, &out)?;
+            println!("[检查点] 已导出模型 v{} ({} 维) 到 {}", model.version, model.params.len(), out.display());
+            Ok(None)
+        }
+        Command::Checkpoint(CheckpointCommand::Import {
+            file,
+            checkpoint_dir,
+            version,
+            keep,
+        }) => {
+            let model = import(&file, version)?;
+            let path = checkpoint::save(&checkpoint_dir, &model, keep)?;
+            println!("[检查点] 已导入模型 v{} ({} 维) 到 {}", model.version, model.params.len(), path.display());
+            Ok(None)
+        }
+        Command::Vectors(VectorsCommand::Generate { dir }) => {
+            let corpus = vectors::generate(&dir)?;
+            println!(
+                "[测试向量] 已生成 {} 种消息 × {} 种编码到 {}",
+                corpus.vectors.len(),
+                codec::WireCodec::ALL.len(),
+                dir.display()
+            );
+            Ok(None)
+        }
+        Command::Vectors(VectorsCommand::Verify { dir }) => {
+            let checked = vectors::verify(&dir)?;
+            println!("[测试向量] {} 个文件全部通过", checked);
+            Ok(None)
+        }
+    }
+}
+
+fn keygen(identity: Option<PathBuf>, keystore: Option<PathBuf>) -> Result<()> {
+    let seeds = match keystore {
+        Some(dir) => {
+            let passphrase = std::env::var(KEYSTORE_PASSPHRASE_ENV)
+                .map_err(|_| anyhow!("创建密钥库需要设置 {}", KEYSTORE_PASSPHRASE_ENV))?;
+            Keystore::new(dir).create(&passphrase, &CryptoConfig::default())?
+        }
+        None => {
+            let seeds = CryptoSuite::generate_seeds();
+            println!("ETH 种子: {}", seeds.eth_hex_seed.as_deref().unwrap_or_default());
+            println!("SOL 种子: {}", seeds.sol_bs58_seed.as_deref().unwrap_or_default());
+            seeds
+        }
+    };
+    let suite = CryptoSuite::new(seeds)?;
+    println!("ETH 地址: {}", suite.eth_address());
+    println!("SOL 公钥: {}", suite.sol_address());
+    let peer_id = match identity {
+        Some(path) if path.exists() => {
+            return Err(anyhow!("身份密钥文件 {} 已存在，拒绝覆盖", path.display()))
+        }
+        Some(path) => load_or_create_identity(&path)?.public().to_peer_id(),
+        None => libp2p::identity::Keypair::generate_ed25519().public().to_peer_id(),
+    };
+    println!("PeerId: {}", peer_id);
+    Ok(())
+}
+
+fn inspect(path: &Path) -> Result<()> {
+    let bytes = std::fs::read(path)?;
+    if let Ok(signed) = SignedGossip::from_json(&bytes) {
+        let payload = serde_json::to_value(&signed.payload)?;
+        let variant = payload
+            .as_object()
+            .and_then(|object| object.keys().next().cloned())
+            .unwrap_or_default();
+        println!("SignedGossip");
+        println!("  会话: {}", signed.session);
+        println!("  发送时间: {} ms", signed.sent_at_ms);
+        println!("  类型: {}", variant);
+        println!("  发送者: {}", signed.payload.sender());
+        println!("  ETH 地址: {}", signed.signature.eth.address);
+        println!("  SOL 公钥: {}", signed.signature.sol.pubkey);
+        println!("  签名: {}", if signature_valid(&signed) { "有效" } else { "无效" });
+        println!("  质押分: {:.4}", signed.staking_score);
+        match &signed.payload {
+            GgsMessage::DenseSnapshot { snapshot, .. } | GgsMessage::DenseSlice { snapshot, .. } => {
+                print_snapshot(snapshot)
+            }
+            _ => {
+                let json = serde_json::to_string_pretty(&payload)?;
+                if json.len() <= MAX_PRINT_BYTES {
+                    println!("{}", json);
+                } else {
+                    println!("  (内容 {} 字节，省略)", json.len());
+                }
+            }
+        }
+        return Ok(());
+    }
+    let snapshot: TensorSnapshot = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow!("{} 既不是 SignedGossip 也不是 TensorSnapshot: {}", path.display(), e))?;
+    println!("TensorSnapshot");
+    print_snapshot(&snapshot);
+    Ok(())
+}
+
+fn print_snapshot(snapshot: &TensorSnapshot) {
+    let min = snapshot.values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = snapshot.values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mean = snapshot.values.iter().sum::<f32>() / snapshot.values.len().max(1) as f32;
+    println!("  维度: {} (切片 {} 个值，偏移 {})", snapshot.dim, snapshot.values.len(), snapshot.offset);
+    println!("  版本: {}", snapshot.version);
+    println!("  哈希: {}", snapshot.hash());
+    println!("  最小 / 最大 / 均值: {:.6} / {:.6} / {:.6}", min, max, mean);
+    println!("  L2 范数: {:.6}", l2_norm(&snapshot.values));
+}
+
+fn model_from_state(dir: &Path, session: &str) -> Result<ModelCheckpoint> {
+    let state = StateStore::new(dir)?
+        .load()?
+        .ok_or_else(|| anyhow!("{} 中没有节点状态", dir.display()))?;
+    let model = if session == DEFAULT_SESSION {
+        state.model
+    } else {
+        state.session_models.get(session).cloned()
+    };
+    model.ok_or_else(|| anyhow!("节点状态中没有会话 {} 的模型", session))
+}
+
+fn is_npy(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("npy")
+}
+
+fn export(model: &ModelCheckpoint, out: &Path) -> Result<()> {
+    if is_npy(out) {
+        Array1::from_vec(model.params.clone()).write_npy(File::create(out)?)?;
+    } else {
+        std::fs::write(out, checkpoint::encode(model))?;
+    }
+    Ok(())
+}
+
+fn import(path: &Path, version: u64) -> Result<ModelCheckpoint> {
+    if is_npy(path) {
+        let params: Array1<f32> = Array1::read_npy(File::open(path)?)?;
+        return Ok(ModelCheckpoint {
+            params: params.to_vec(),
+            version,
+            residual: Vec::new(),
+            previous_params: None,
+        });
+    }
+    checkpoint::decode(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_args_and_checkpoint_round_trip() {
+        let args = |line: &str| {
+            line.split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        // 旧的启动方式与 run 子命令得到相同的节点参数
+        let peer = "/ip4/10.0.0.1/tcp/4001";
+        let legacy = dispatch(args(&format!("ggs --model-dim 8 --dial {peer}")))
+            .unwrap()
+            .unwrap();
+        let run = dispatch(args(&format!(
+            "ggs run --model-dim 8 --bootstrap-peer {peer}"
+        )))
+        .unwrap()
+        .unwrap();
+        assert_eq!(legacy.model_dim, Some(8));
+        assert_eq!(
+            (run.model_dim, run.bootstrap_peers),
+            (legacy.model_dim, legacy.bootstrap_peers)
+        );
+        // 可重复的参数逐个累积，重复的单值参数以最后一个为准，负的时区偏移不被当作参数名
+        let node = NodeArgs::try_parse_from(args(
+            "ggs --session a --session b --max-neighbors 4 --max-neighbors 6 \
+             --utc-offset -05:30 --position -33.9,151.2 --erasure 4:2",
+        ))
+        .unwrap();
+        assert_eq!(node.session_specs, ["a", "b"]);
+        assert_eq!(node.max_neighbors, Some(6));
+        assert_eq!(node.utc_offset, Some(-330));
+        assert_eq!(node.erasure, Some((4, 2)));
+        // 无法解析的值一律报错，不再静默忽略
+        for bad in [
+            "--max-neighbors many",
+            "--quic-bind nowhere",
+            "--position north",
+            "--erasure 4",
+            "--eval-shards -1",
+            "--no-such-flag",
+        ] {
+            let parsed = NodeArgs::try_parse_from(args(&format!("ggs {bad}")));
+            assert!(parsed.is_err(), "{bad}");
+        }
+
+        let dir = std::env::temp_dir().join(format!("ggs-cli-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = ModelCheckpoint {
+            params: vec![0.5, -1.0, 2.0],
+            version: 7,
+            residual: vec![0.1; 3],
+            previous_params: None,
+        };
+        let npy = dir.join("model.npy");
+        export(&model, &npy).unwrap();
+        let imported = import(&npy, 9).unwrap();
+        assert_eq!((imported.params.clone(), imported.version), (model.params.clone(), 9));
+
+        let ckpt = dir.join("model.ckpt");
+        export(&model, &ckpt).unwrap();
+        assert_eq!(import(&ckpt, 0).unwrap().residual, model.residual);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//! 时钟偏差估计与消息时间戳校验
+//!
+//! 每条 `SignedGossip` 携带签名覆盖的发送时间戳。收到消息时用本地时间减去发送时间，
+//! 得到 “时钟偏差 + 单向延迟” 的观测值，按节点做指数滑动平均作为偏差估计；
+//! 发送时间明显在未来或过于久远的消息直接拒绝，防止重放和错误时钟污染训练状态。
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 偏差估计的平滑系数
+const SKEW_EWMA_ALPHA: f64 = 0.2;
+
+#[derive(Clone, Debug)]
+pub struct ClockConfig {
+    /// 允许的最大超前（发送时间晚于本地时间）
+    pub max_future: Duration,
+    /// 允许的最大消息年龄
+    pub max_age: Duration,
+    /// 接受不带时间戳的旧版本消息（仅限从未发过带时间戳消息的节点）
+    pub accept_legacy: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            max_future: Duration::from_secs(30),
+            max_age: Duration::from_secs(300),
+            accept_legacy: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampError {
+    /// 发送时间超前本地时间（毫秒）
+    InFuture(u64),
+    /// 消息年龄（毫秒）
+    TooOld(u64),
+    /// 未携带时间戳
+    Missing,
+}
+
+pub struct ClockSkewTracker {
+    config: ClockConfig,
+    /// peer -> 偏差估计（毫秒，正值表示对方时钟落后或链路延迟）
+    estimates: RwLock<HashMap<String, f64>>,
+}
+
+impl ClockSkewTracker {
+    pub fn new(config: ClockConfig) -> Self {
+        Self {
+            config,
+            estimates: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 校验时间戳并更新偏差估计。时间戳为 0 表示旧版本消息：默认拒绝，`accept_legacy` 时
+    /// 只放行从未发过带时间戳消息的节点，已升级的节点不能靠发 0 绕过检查
+    pub fn observe(&self, peer: &str, sent_at_ms: u64, now_ms: u64) -> Result<(), TimestampError> {
+        if sent_at_ms == 0 {
+            return if self.config.accept_legacy && !self.estimates.read().contains_key(peer) {
+                Ok(())
+            } else {
+                Err(TimestampError::Missing)
+            };
+        }
+        if sent_at_ms > now_ms {
+            let ahead = sent_at_ms - now_ms;
+            if ahead > self.config.max_future.as_millis() as u64 {
+                return Err(TimestampError::InFuture(ahead));
+            }
+        } else {
+            let age = now_ms - sent_at_ms;
+            if age > self.config.max_age.as_millis() as u64 {
+                return Err(TimestampError::TooOld(age));
+            }
+        }
+        let sample = now_ms as f64 - sent_at_ms as f64;
+        let mut estimates = self.estimates.write();
+        estimates
+            .entry(peer.to_string())
+            .and_modify(|e| *e = (1.0 - SKEW_EWMA_ALPHA) * *e + SKEW_EWMA_ALPHA * sample)
+            .or_insert(sample);
+        Ok(())
+    }
+
+    pub fn estimate_ms(&self, peer: &str) -> Option<i64> {
+        self.estimates.read().get(peer).map(|e| e.round() as i64)
+    }
+}
+
+pub fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_implausible_timestamps() {
+        let tracker = ClockSkewTracker::new(ClockConfig::default());
+        let now = 1_000_000_000;
+        assert_eq!(
+            tracker.observe("a", now + 60_000, now),
+            Err(TimestampError::InFuture(60_000))
+        );
+        assert_eq!(
+            tracker.observe("a", now - 600_000, now),
+            Err(TimestampError::TooOld(600_000))
+        );
+        assert!(tracker.estimate_ms("a").is_none());
+
+        tracker.observe("a", now - 1_000, now).unwrap();
+        tracker.observe("a", now - 2_000, now).unwrap();
+        assert_eq!(tracker.estimate_ms("a"), Some(1_200));
+        assert_eq!(tracker.observe("legacy", 0, now), Err(TimestampError::Missing));
+
+        let tracker = ClockSkewTracker::new(ClockConfig {
+            accept_legacy: true,
+            ..ClockConfig::default()
+        });
+        assert!(tracker.observe("legacy", 0, now).is_ok());
+        tracker.observe("a", now - 1_000, now).unwrap();
+        assert_eq!(tracker.observe("a", 0, now), Err(TimestampError::Missing));
+    }
+}
@@ -0,0 +1,265 @@
+//! 嵌入空间的节点聚类
+//!
+//! 定期把已知节点探测宣告的嵌入压缩成短草图（按段均值池化后归一化），连同本地嵌入做 k-means，
+//! 得到各节点的簇标签。簇标签只在本地有意义，因此对外 gossip 的是本节点所在簇的成员列表；
+//! 同簇节点宣告的成员被视为同簇提示，补全本地没有嵌入的节点。
+//! 拓扑据此按比例混合同簇与跨簇邻居，兼顾专精与多样性。
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct ClusterConfig {
+    /// 簇数，0 表示关闭聚类
+    pub clusters: usize,
+    /// 两次聚类之间的间隔
+    pub interval: Duration,
+    /// 草图维度
+    pub sketch_dim: usize,
+    pub iterations: usize,
+    /// 远端簇视图的有效期
+    pub view_ttl: Duration,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            clusters: 3,
+            interval: Duration::from_secs(120),
+            sketch_dim: 32,
+            iterations: 20,
+            view_ttl: Duration::from_secs(600),
+        }
+    }
+}
+
+impl ClusterConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.clusters > 1
+    }
+}
+
+/// 一次聚类的结果
+#[derive(Clone, Debug)]
+pub struct Clustering {
+    /// 本节点所在簇
+    pub local: usize,
+    /// 节点 -> 簇标签
+    pub labels: HashMap<String, usize>,
+}
+
+impl Clustering {
+    /// 与本节点同簇的节点（按 peer id 排序）
+    pub fn members(&self) -> Vec<String> {
+        let mut members: Vec<String> = self
+            .labels
+            .iter()
+            .filter(|(_, label)| **label == self.local)
+            .map(|(peer, _)| peer.clone())
+            .collect();
+        members.sort();
+        members
+    }
+}
+
+/// 某个会话的本地聚类结果与收到的远端簇视图
+pub struct ClusterBoard {
+    config: ClusterConfig,
+    current: Option<Clustering>,
+    /// 节点 -> (其宣告的同簇成员, 收到时间)
+    remote: HashMap<String, (Vec<String>, Instant)>,
+}
+
+impl ClusterBoard {
+    pub fn new(config: ClusterConfig) -> Self {
+        Self {
+            config,
+            current: None,
+            remote: HashMap::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&Clustering> {
+        self.current.as_ref()
+    }
+
+    /// 对本地嵌入与节点嵌入重新聚类；与本地嵌入长度不同的节点不参与（草图维度不一致），
+    /// 节点数不足每簇两个时不聚类
+    pub fn recluster(
+        &mut self,
+        self_embedding: &[f32],
+        mut peers: Vec<(String, Vec<f32>)>,
+    ) -> Option<&Clustering> {
+        peers.retain(|(_, embedding)| embedding.len() == self_embedding.len());
+        let k = self.config.clusters;
+        if !self.config.is_enabled() || self_embedding.is_empty() || peers.len() + 1 < k * 2 {
+            self.current = None;
+            return None;
+        }
+        let mut points = Vec::with_capacity(peers.len() + 1);
+        points.push(sketch(self_embedding, self.config.sketch_dim));
+        points.extend(peers.iter().map(|(_, e)| sketch(e, self.config.sketch_dim)));
+        let assignment = kmeans(&points, k, self.config.iterations);
+        self.current = Some(Clustering {
+            local: assignment[0],
+            labels: peers
+                .into_iter()
+                .map(|(peer, _)| peer)
+                .zip(assignment[1..].iter().copied())
+                .collect(),
+        });
+        self.current.as_ref()
+    }
+
+    pub fn observe_view(&mut self, sender: &str, members: Vec<String>) {
+        self.remote
+            .insert(sender.to_string(), (members, Instant::now()));
+        let ttl = self.config.view_ttl;
+        self.remote.retain(|_, (_, seen)| seen.elapsed() <= ttl);
+    }
+
+    /// 同簇节点：本地聚类结果加上同簇节点宣告的成员；尚未聚类时返回 None
+    pub fn same_cluster(&self, own: &str) -> Option<HashSet<String>> {
+        let current = self.current.as_ref()?;
+        let mut same: HashSet<String> = current.members().into_iter().collect();
+        let ttl = self.config.view_ttl;
+        for (peer, (members, seen)) in &self.remote {
+            if !same.contains(peer) || seen.elapsed() > ttl {
+                continue;
+            }
+            // 只补全本地没有嵌入的节点，本地判为跨簇的以本地为准
+            same.extend(
+                members
+                    .iter()
+                    .filter(|m| *m != own && !current.labels.contains_key(*m))
+                    .cloned(),
+            );
+        }
+        Some(same)
+    }
+}
+
+/// 按段均值池化并做 L2 归一化，使 k-means 的欧氏距离近似余弦距离
+pub fn sketch(embedding: &[f32], dim: usize) -> Vec<f32> {
+    let dim = dim.max(1).min(embedding.len().max(1));
+    let mut out = vec![0.0f32; dim];
+    if embedding.is_empty() {
+        return out;
+    }
+    let segment = embedding.len().div_ceil(dim);
+    for (slot, chunk) in out.iter_mut().zip(embedding.chunks(segment)) {
+        *slot = chunk.iter().sum::<f32>() / chunk.len() as f32;
+    }
+    let norm = out.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        out.iter_mut().for_each(|v| *v /= norm);
+    }
+    out
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// k-means++ 初始化的 k-means，返回每个点的簇标签；种子固定，结果可复现
+fn kmeans(points: &[Vec<f32>], k: usize, iterations: usize) -> Vec<usize> {
+    let k = k.min(points.len()).max(1);
+    let mut rng = StdRng::seed_from_u64(points.len() as u64);
+    let mut centroids = vec![points[rng.gen_range(0..points.len())].clone()];
+    while centroids.len() < k {
+        let weights: Vec<f32> = points
+            .iter()
+            .map(|p| {
+                centroids
+                    .iter()
+                    .map(|c| distance(p, c))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = rng.gen_range(0.0..total);
+        let mut chosen = points.len() - 1;
+        for (i, w) in weights.iter().enumerate() {
+            if target < *w {
+                chosen = i;
+                break;
+            }
+            target -= w;
+        }
+        centroids.push(points[chosen].clone());
+    }
+
+    let nearest = |p: &[f32], centroids: &[Vec<f32>]| {
+        centroids
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, distance(p, c)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(i, _)| i)
+    };
+    let mut labels: Vec<usize> = points.iter().map(|p| nearest(p, &centroids)).collect();
+    for _ in 0..iterations {
+        for (label, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f32>> = points
+                .iter()
+                .zip(&labels)
+                .filter(|(_, l)| **l == label)
+                .map(|(p, _)| p)
+                .collect();
+            // 空簇保留原质心
+            if members.is_empty() {
+                continue;
+            }
+            for (d, value) in centroid.iter_mut().enumerate() {
+                *value = members.iter().map(|p| p[d]).sum::<f32>() / members.len() as f32;
+            }
+        }
+        let next: Vec<usize> = points.iter().map(|p| nearest(p, &centroids)).collect();
+        if next == labels {
+            break;
+        }
+        labels = next;
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clusters_embeddings_and_merges_remote_views() {
+        let embedding = |sign: f32, jitter: f32| -> Vec<f32> {
+            (0..64).map(|i| sign * (1.0 + jitter) * if i < 32 { 1.0 } else { -1.0 }).collect()
+        };
+        let mut board = ClusterBoard::new(ClusterConfig {
+            clusters: 2,
+            ..ClusterConfig::default()
+        });
+        let mut peers = vec![
+            ("a".to_string(), embedding(1.0, 0.1)),
+            ("b".to_string(), embedding(1.0, 0.2)),
+            ("x".to_string(), embedding(-1.0, 0.1)),
+            ("y".to_string(), embedding(-1.0, 0.3)),
+        ];
+        // 节点不足时不聚类
+        assert!(board.recluster(&embedding(1.0, 0.0), peers[..2].to_vec()).is_none());
+        // 长度不同的嵌入被剔除，不会按质心维度越界
+        peers.push(("short".to_string(), vec![1.0; 3]));
+        let clustering = board.recluster(&embedding(1.0, 0.0), peers).unwrap();
+        assert_eq!(clustering.members(), vec!["a", "b"]);
+        assert_ne!(clustering.labels["x"], clustering.local);
+
+        // 同簇节点宣告的未知成员并入同簇，跨簇节点的宣告与本地已判定的节点不采纳
+        board.observe_view("a", vec!["c".into(), "x".into(), "me".into()]);
+        board.observe_view("x", vec!["d".into()]);
+        let mut same: Vec<_> = board.same_cluster("me").unwrap().into_iter().collect();
+        same.sort();
+        assert_eq!(same, vec!["a", "b", "c"]);
+    }
+}
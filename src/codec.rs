@@ -0,0 +1,231 @@
+//! 消息编码与 QUIC 直连的编码协商
+//!
+//! 心跳携带本节点支持的编码名称（能力标志）。直连发给某个节点时使用双方都支持的最优编码，
+//! 未宣告能力的旧节点只会 JSON，对它们退回 JSON；gossip 与直连广播面向不确定的接收方，
+//! 使用配置的编码，全网升级完成前保持 JSON。
+//! 二进制编码以一个模式版本字节开头，其后是 bincode（变长整数）序列化的消息，f32 按 4 字节原样写入，
+//! 密集快照与稀疏更新比 JSON 小数倍。bincode 按位置编码，`#[serde(default)]` 新增字段也会让
+//! 不同版本互相无法解码，因此编码名称带模式版本（`binary/1`），`GgsMessage` 及其字段每次变化都要
+//! 递增 [`BINARY_SCHEMA`]；版本不同的节点协商不出二进制编码，退回 zstd 或 JSON。
+//! 签名仍覆盖载荷的 JSON 序列化，与线上编码无关；
+//! JSON 与 zstd 帧按收到的载荷原文校验签名并原样转发，新版本节点增加的字段不会导致验签失败。
+//! 接收端按帧头自动识别：zstd 帧以固定魔数开头，二进制帧以格式字节开头，其余按 JSON 解析。
+//! 宣告中出现本地不认识的编码名称时忽略，便于以后增加新编码。
+
+use crate::consensus::SignedGossip;
+use anyhow::{anyhow, Result};
+use bincode::Options;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+/// zstd 帧魔数（小端 0xFD2FB528）
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ZSTD_LEVEL: i32 = 3;
+/// 二进制编码的模式版本，同时是二进制帧的首字节；`GgsMessage` 及其字段变化时递增，
+/// 并同步修改 [`WireCodec::name`] 中的版本号
+pub const BINARY_SCHEMA: u8 = 1;
+/// 二进制帧首字节的取值范围，不会出现在 JSON（`{` 或空白）与 zstd 帧的开头
+const BINARY_TAGS: std::ops::RangeInclusive<u8> = 0x01..=0x08;
+
+/// 直连编码，按优先级从低到高排列
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WireCodec {
+    /// 所有版本都支持的最低公共编码
+    Json,
+    /// zstd 压缩的 JSON
+    Zstd,
+    /// 带模式版本字节的 bincode，只在模式版本相同的节点间使用
+    Binary,
+}
+
+impl WireCodec {
+    pub const ALL: [WireCodec; 3] = [WireCodec::Json, WireCodec::Zstd, WireCodec::Binary];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WireCodec::Json => "json",
+            WireCodec::Zstd => "zstd",
+            WireCodec::Binary => "binary/1",
+        }
+    }
+}
+
+impl FromStr for WireCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        WireCodec::ALL
+            .into_iter()
+            .find(|codec| codec.name() == s.trim())
+            .ok_or_else(|| {
+                anyhow!(
+                    "未知的编码 {:?}（可选 json, zstd, binary/{}）",
+                    s,
+                    BINARY_SCHEMA
+                )
+            })
+    }
+}
+
+/// 双方都支持的最优编码；对方未宣告或没有交集（含二进制模式版本不同）时退回 JSON
+pub fn negotiate(ours: &[WireCodec], theirs: &[String]) -> WireCodec {
+    theirs
+        .iter()
+        .filter_map(|name| name.parse::<WireCodec>().ok())
+        .filter(|codec| ours.contains(codec))
+        .max()
+        .unwrap_or(WireCodec::Json)
+}
+
+fn binary() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+pub fn encode(codec: WireCodec, signed: &SignedGossip) -> Result<Vec<u8>> {
+    match codec {
+        WireCodec::Json => Ok(signed.to_json()?),
+        WireCodec::Zstd => Ok(zstd::encode_all(signed.to_json()?.as_slice(), ZSTD_LEVEL)?),
+        WireCodec::Binary => {
+            let mut out = vec![BINARY_SCHEMA];
+            binary().serialize_into(&mut out, signed)?;
+            Ok(out)
+        }
+    }
+}
+
+/// 按帧头识别编码并解码；解压或解码时超过 `limit` 字节视为无效
+pub fn decode(bytes: &[u8], limit: usize) -> Result<SignedGossip> {
+    match bytes.split_first() {
+        Some((&BINARY_SCHEMA, body)) => {
+            // 从切片解码时 bincode 不检查长度上限，这里先按帧长拒绝
+            if body.len() > limit {
+                return Err(anyhow!("二进制直连消息超过 {} 字节", limit));
+            }
+            return binary()
+                .deserialize(body)
+                .map_err(|e| anyhow!("二进制消息解码失败: {}", e));
+        }
+        Some((version, _)) if BINARY_TAGS.contains(version) => {
+            return Err(anyhow!(
+                "二进制消息的模式版本 {} 与本地的 {} 不符",
+                version,
+                BINARY_SCHEMA
+            ));
+        }
+        _ => {}
+    }
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return Ok(SignedGossip::from_json(bytes)?);
+    }
+    let mut json = Vec::new();
+    zstd::stream::Decoder::new(bytes)?
+        .take(limit as u64 + 1)
+        .read_to_end(&mut json)?;
+    if json.len() > limit {
+        return Err(anyhow!("解压后的直连消息超过 {} 字节", limit));
+    }
+    Ok(SignedGossip::from_json(&json)?)
+}
+
+/// 各节点协商出的直连编码，按 PeerId 与其宣告的 QUIC 地址索引
+#[derive(Default)]
+pub struct PeerCodecs {
+    by_peer: HashMap<String, WireCodec>,
+    by_addr: HashMap<SocketAddr, WireCodec>,
+}
+
+impl PeerCodecs {
+    /// 记录对方宣告的能力，返回协商结果发生变化时的新编码
+    pub fn record(
+        &mut self,
+        ours: &[WireCodec],
+        peer: &str,
+        addr: Option<SocketAddr>,
+        theirs: &[String],
+    ) -> Option<WireCodec> {
+        let codec = negotiate(ours, theirs);
+        if let Some(addr) = addr {
+            self.by_addr.insert(crate::netaddr::canonical(addr), codec);
+        }
+        let previous = self.by_peer.insert(peer.to_string(), codec);
+        (previous != Some(codec)).then_some(codec)
+    }
+
+    pub fn for_peer(&self, peer: &str) -> WireCodec {
+        self.by_peer.get(peer).copied().unwrap_or(WireCodec::Json)
+    }
+
+    pub fn for_addr(&self, addr: &SocketAddr) -> WireCodec {
+        self.by_addr
+            .get(&crate::netaddr::canonical(*addr))
+            .copied().unwrap_or(WireCodec::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::session::DEFAULT_SESSION;
+    use crate::types::{GgsMessage, TensorSnapshot};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_negotiates_per_peer_and_round_trips() {
+        let ours = WireCodec::ALL;
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let addr: SocketAddr = "127.0.0.1:9300".parse().unwrap();
+        let mut codecs = PeerCodecs::default();
+        // 旧节点不宣告能力，未知的编码名称被忽略
+        assert_eq!(codecs.record(&ours, "legacy", None, &[]), Some(WireCodec::Json));
+        assert_eq!(
+            codecs.record(&ours, "new", Some(addr), &names(&["json", "brotli", "zstd"])),
+            Some(WireCodec::Zstd)
+        );
+        assert_eq!(codecs.record(&ours, "new", Some(addr), &names(&["zstd"])), None);
+        assert_eq!(negotiate(&ours, &names(&["zstd", "binary/1"])), WireCodec::Binary);
+        // 模式版本不同（或未带版本）的二进制编码协商不上
+        assert_eq!(negotiate(&ours, &names(&["zstd", "binary/2"])), WireCodec::Zstd);
+        assert_eq!(negotiate(&ours, &names(&["binary"])), WireCodec::Json);
+        assert_eq!(WireCodec::Binary.name(), format!("binary/{BINARY_SCHEMA}"));
+        assert_eq!(codecs.for_peer("legacy"), WireCodec::Json);
+        assert_eq!(codecs.for_addr(&addr), WireCodec::Zstd);
+        assert_eq!(codecs.for_peer("unknown"), WireCodec::Json);
+        // 本地关闭 zstd 时与任何节点都用 JSON
+        assert_eq!(negotiate(&[WireCodec::Json], &names(&["zstd"])), WireCodec::Json);
+
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let engine = ConsensusEngine::new(crypto, ConsensusConfig::default());
+        let snapshot = GgsMessage::DenseSnapshot {
+            snapshot: TensorSnapshot::new(vec![0.25; 4096], 3),
+            sender: "me".into(),
+        };
+        let signed = engine.sign(DEFAULT_SESSION, snapshot).unwrap();
+        let json = encode(WireCodec::Json, &signed).unwrap();
+        let zstd = encode(WireCodec::Zstd, &signed).unwrap();
+        assert!(zstd.len() < json.len() / 4);
+        for bytes in [&json, &zstd] {
+            let decoded = decode(bytes, json.len()).unwrap();
+            assert!(engine.verify(&decoded));
+        }
+        assert!(decode(&zstd, json.len() - 1).is_err());
+
+        // 256 维快照：二进制编码按 4 字节写入 f32，远小于 JSON 的十进制文本
+        let snapshot = GgsMessage::DenseSnapshot {
+            snapshot: TensorSnapshot::new((0..256).map(|i| (i as f32).sin()).collect(), 3),
+            sender: "me".into(),
+        };
+        let signed = engine.sign(DEFAULT_SESSION, snapshot).unwrap();
+        let json = encode(WireCodec::Json, &signed).unwrap();
+        let binary = encode(WireCodec::Binary, &signed).unwrap();
+        assert!(binary.len() * 2 < json.len());
+        assert!(engine.verify(&decode(&binary, json.len()).unwrap()));
+        assert!(decode(&binary, 1024).is_err());
+        let mut newer = binary.clone();
+        newer[0] = BINARY_SCHEMA + 1;
+        assert!(decode(&newer, json.len()).is_err());
+    }
+}
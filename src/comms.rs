@@ -1,31 +1,409 @@
+use crate::certs::{self, NodeCert};
+use crate::codec::{self, PeerCodecs, WireCodec};
+use crate::connmgr::{Conn, ConnectionManager, ConnectionManagerConfig};
 use crate::consensus::SignedGossip;
 use crate::device::NetworkType;
+use crate::latency::{LatencyPath, LatencySummary, LatencyTracker};
+use crate::netaddr;
+use crate::persistence::{unix_now_secs, write_secret};
+use crate::pipeline::Verdict;
+use crate::proxy::{ProxyConfig, Socks5Transport};
+use crate::pubqueue::{Attempt, PublishQueue, PublishQueueConfig, PublishQueueStats};
+use crate::relay::{RelayClient, RelayConfig};
+use crate::session::DEFAULT_SESSION;
+use crate::supervisor::TaskFactory;
+use crate::sync::{self, InboundRequest, SyncError, SyncRequest, SyncResponse};
+use crate::transport::{GossipBackend, NodeBackend, TransportEvent};
+use crate::watchdog::Beat;
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt, StreamExt};
 use libp2p::{
+    autonat::{self, NatStatus},
+    connection_limits::{self, ConnectionLimits},
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade},
+    dcutr, dns,
     gossipsub::{
         self, Behaviour as GossipsubBehaviour, Event as GossipsubEvent, IdentTopic as Topic,
-        MessageAuthenticity, ValidationMode,
+        MessageAcceptance, MessageAuthenticity, MessageId, PeerScoreParams, PeerScoreThresholds,
+        PublishError, SubscriptionError, ValidationMode,
+    },
+    identify, identity,
+    kad::{
+        store::MemoryStore, BootstrapOk, Kademlia, KademliaConfig, KademliaEvent, QueryResult,
     },
-    identity,
     mdns::{self, tokio::Behaviour as Mdns, Event as MdnsEvent},
-    swarm::{NetworkBehaviour, SwarmBuilder},
-    Multiaddr, PeerId, Swarm,
+    multiaddr::Protocol,
+    noise, relay,
+    rendezvous::{self, Cookie, Namespace},
+    request_response::{self, ProtocolName, ProtocolSupport},
+    core::ConnectedPoint,
+    swarm::{
+        behaviour::toggle::Toggle, dial_opts::DialOpts, AddressScore, NetworkBehaviour,
+        SwarmBuilder, SwarmEvent,
+    },
+    tcp, websocket, yamux, Multiaddr, PeerId, Swarm, Transport,
 };
-use parking_lot::RwLock;
-use quinn::{Endpoint, ServerConfig};
-use rcgen::generate_simple_self_signed;
-use rustls::{Certificate, PrivateKey};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use parking_lot::{Mutex, RwLock};
+use quinn::{ClientConfig, Endpoint, ServerConfig};
+use rustls::client::ClientSessionMemoryCache;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tokio::time::interval;
 
+/// 状态目录中的 libp2p 身份密钥文件名
+pub const IDENTITY_FILE: &str = "identity.key";
+
+/// 单条 QUIC 直连消息的最大字节数
+const MAX_DIRECT_MESSAGE_BYTES: usize = 8 * 1024 * 1024;
+/// QUIC 不通时直连消息改经 libp2p 连接发送所用的协议
+const DIRECT_PROTOCOL: &str = "/ggs/direct/1.0.0";
+/// 到同一节点的 QUIC 直连连续失败该次数后判定受阻
+const QUIC_BLOCKED_AFTER: u32 = 3;
+/// 判定受阻后隔多久再试一次 QUIC
+const QUIC_RETRY_AFTER: Duration = Duration::from_secs(300);
+/// 按节点记录的 QUIC 可达性条目上限
+const MAX_REACHABILITY_ENTRIES: usize = 4096;
+/// QUIC 保活间隔与空闲超时：保活帧让 NAT 映射不过期，对端消失时连接在超时后关闭
+const QUIC_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+const QUIC_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// 异常断开的出站 QUIC 连接重连的初始间隔与上限，每次失败后间隔翻倍，连续失败该次数后放弃
+const QUIC_REDIAL_INITIAL: Duration = Duration::from_secs(1);
+const QUIC_REDIAL_MAX: Duration = Duration::from_secs(300);
+const QUIC_REDIAL_ATTEMPTS: u32 = 10;
+/// 缓存 TLS 会话票据的主机名数（每个已知对端一个）
+const SESSION_TICKETS: usize = 256;
+/// 与同一节点最多保持的 libp2p 连接数（如直连与经中继各一条）
+const MAX_CONNECTIONS_PER_PEER: u32 = 2;
+
+/// 地址簿中每个节点最多保留的地址数（最近使用的在前）
+const MAX_ADDRS_PER_PEER: usize = 8;
+/// 超过该时长未再见到的节点不再持久化、启动时也不再拨号
+const ADDRESS_BOOK_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 引导节点重拨的初始间隔与上限，每次失败后间隔翻倍
+const BOOTSTRAP_RETRY_INITIAL: Duration = Duration::from_secs(2);
+const BOOTSTRAP_RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// GGS 专用的 Kademlia 协议名，与公共 IPFS DHT 隔离
+const DHT_PROTOCOL: &[u8] = b"/ggs/kad/1.0.0";
+/// 已连接的 gossip 节点少于该数时，主动拨号经 DHT 新发现的节点以补充 mesh
+const DHT_DIAL_TARGET: usize = 8;
+
+/// identify 协议中宣告的协议族版本
+const IDENTIFY_PROTOCOL: &str = "/ggs/id/1.0.0";
+/// 每个节点最多保留的 identify 监听地址数
+const MAX_IDENTIFY_ADDRS: usize = 16;
+/// 心跳中宣告的 libp2p 地址数上限
+const MAX_HEARTBEAT_ADDRS: usize = 8;
+
+/// 通信层的错误，`code()` 为稳定的错误码
+#[derive(Debug, thiserror::Error)]
+pub enum CommsError {
+    /// 引导、中继或 rendezvous 地址必须包含 `/p2p/<PeerId>`
+    #[error("{kind} address {addr} lacks /p2p/<PeerId>")]
+    MissingPeerId { kind: &'static str, addr: Multiaddr },
+    #[error("topic too long for a rendezvous namespace")]
+    TopicTooLong,
+    #[error("unknown session {0}")]
+    UnknownSession(String),
+    #[error("QUIC 未启用")]
+    QuicDisabled,
+    /// QUIC 端点初始化、建连或发送失败
+    #[error("QUIC 直连失败: {0}")]
+    Quic(String),
+    #[error("gossip 发布失败: {0}")]
+    Publish(#[from] PublishError),
+    #[error("订阅主题失败: {0}")]
+    Subscribe(#[from] SubscriptionError),
+    /// libp2p 传输层初始化或监听失败
+    #[error("传输层错误: {0}")]
+    Transport(String),
+    #[error("身份密钥文件 {path} 无效: {reason}")]
+    InvalidIdentity { path: PathBuf, reason: String },
+    #[error("消息编码失败: {0}")]
+    Encode(#[from] serde_json::Error),
+    /// 按配置的线上编码序列化消息失败
+    #[error("按线上编码序列化消息失败: {0}")]
+    Codec(String),
+    #[error("读写失败: {0}")]
+    Io(#[from] std::io::Error),
+    /// QUIC 不通，消息已交给 libp2p 连接（TCP / WebSocket）发送，是否送达未知
+    #[error("QUIC 不通，已改经 TCP 回退发送，送达未确认")]
+    FallbackQueued,
+}
+
+impl CommsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommsError::MissingPeerId { .. } => "comms.missing_peer_id",
+            CommsError::TopicTooLong => "comms.topic_too_long",
+            CommsError::UnknownSession(_) => "comms.unknown_session",
+            CommsError::QuicDisabled => "comms.quic_disabled",
+            CommsError::Quic(_) => "comms.quic",
+            CommsError::Publish(PublishError::InsufficientPeers) => "comms.insufficient_peers",
+            CommsError::Publish(PublishError::Duplicate) => "comms.duplicate",
+            CommsError::Publish(_) => "comms.publish",
+            CommsError::Subscribe(_) => "comms.subscribe",
+            CommsError::Transport(_) => "comms.transport",
+            CommsError::InvalidIdentity { .. } => "comms.invalid_identity",
+            CommsError::Encode(_) => "comms.encode",
+            CommsError::Codec(_) => "comms.codec",
+            CommsError::Io(_) => "comms.io",
+            CommsError::FallbackQueued => "comms.fallback_queued",
+        }
+    }
+
+    fn quic(err: impl std::fmt::Display) -> Self {
+        CommsError::Quic(format!("{err:#}"))
+    }
+
+    fn transport(err: impl std::fmt::Display) -> Self {
+        CommsError::Transport(format!("{err:#}"))
+    }
+}
+
 pub struct CommsConfig {
     pub topic: String,
     pub listen_addr: Option<Multiaddr>,
     pub quic_bind: Option<SocketAddr>,
+    /// 同时监听 IPv4 与 IPv6：`/ip4/0.0.0.0` 监听地址追加对应的 `/ip6/::` 地址，
+    /// `[::]` 上的 QUIC 套接字接收 IPv4 连接，mDNS 同时在 IPv6 上发现节点
+    pub dual_stack: bool,
     pub quic_bootstrap: Vec<SocketAddr>,
+    /// 对外宣告的 QUIC 地址（聚合者需要配置，供训练者直连）
+    pub quic_advertise: Option<SocketAddr>,
+    /// 对外可达的 libp2p 地址（rendezvous 注册时使用），为空时使用监听地址
+    pub external_addrs: Vec<Multiaddr>,
+    pub rendezvous: RendezvousConfig,
+    pub dht: DhtConfig,
+    pub nat: NatConfig,
+    /// P2P 受限时镜像消息的 WebSocket 中继
+    pub relay: RelayConfig,
     pub bandwidth: BandwidthBudgetConfig,
+    /// libp2p 身份密钥文件，首次运行时创建；None 时每次启动生成新的 PeerId
+    pub identity_path: Option<PathBuf>,
+    /// 本节点支持的直连编码，随心跳宣告
+    pub codecs: Vec<WireCodec>,
+    /// gossip 与直连广播使用的编码；旧节点只能解析 JSON，全网升级后再切换为 binary
+    pub gossip_codec: WireCodec,
+    /// 启动时拨号的 libp2p 引导节点，连接成功后记入地址簿；
+    /// gossipsub mesh 中出现第一个节点之前按指数退避重拨
+    pub bootstrap_peers: Vec<Multiaddr>,
+    pub transport: TransportConfig,
+    /// 出站连接（libp2p 拨号、WebSocket 中继）经 SOCKS5 代理建立
+    pub proxy: ProxyConfig,
+    /// swarm 与 QUIC 直连各自的连接数上限与空闲清理
+    pub connections: ConnectionManagerConfig,
+    /// 消息在 gossipsub 与 QUIC 两条路径上的发送策略
+    pub send_policy: SendPolicy,
+    /// mesh 为空、发布失败的消息暂存队列
+    pub publish_queue: PublishQueueConfig,
+    pub gossip: GossipConfig,
+}
+
+/// 发布消息时 gossipsub 与 QUIC 的分工。两路都发时接收方按消息 id 去重，
+/// 但每条消息仍要传输、校验签名两次
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendPolicy {
+    /// 每条消息都经 gossipsub 发布，同时经 QUIC 推送给邻居（模型数据）或所有连接（控制消息）
+    #[default]
+    Both,
+    /// 模型数据经 QUIC 发给拓扑邻居，没有邻居送达时才经 gossipsub 发布；
+    /// 其余消息只经 gossipsub 发布
+    QuicNeighbors,
+}
+
+impl std::str::FromStr for SendPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "both" => Ok(SendPolicy::Both),
+            "quic_neighbors" => Ok(SendPolicy::QuicNeighbors),
+            _ => Err(anyhow!("未知的发送策略 {:?}（可选 both, quic_neighbors）", s)),
+        }
+    }
+}
+
+/// libp2p 传输栈中的一种传输，都经 noise 认证、yamux 多路复用
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportKind {
+    Tcp,
+    WebSocket,
+}
+
+impl std::str::FromStr for TransportKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "tcp" => Ok(TransportKind::Tcp),
+            "ws" | "websocket" => Ok(TransportKind::WebSocket),
+            _ => Err(anyhow!("未知的传输 {:?}（可选 tcp, ws）", s)),
+        }
+    }
+}
+
+/// 传输配置：libp2p 启用的传输（circuit relay 客户端始终启用），以及 QUIC 直连不通
+/// （如企业网络丢弃 UDP）时是否把直连消息改经 libp2p 连接发送
+#[derive(Clone, Debug)]
+pub struct TransportConfig {
+    pub stack: Vec<TransportKind>,
+    pub tcp_fallback: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            stack: vec![TransportKind::Tcp, TransportKind::WebSocket],
+            tcp_fallback: true,
+        }
+    }
+}
+
+/// gossipsub 消息 id 的计算方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GossipMessageId {
+    /// libp2p 默认：发布者 PeerId + 序号
+    #[default]
+    Source,
+    /// 消息内容的 Keccak-256：同一条签名消息被不同节点重新发布（中继、重发）时只传播一次
+    ContentHash,
+}
+
+impl std::str::FromStr for GossipMessageId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "source" => Ok(GossipMessageId::Source),
+            "content" | "content_hash" => Ok(GossipMessageId::ContentHash),
+            _ => Err(anyhow!("未知的消息 id 方式 {:?}（可选 source, content）", s)),
+        }
+    }
+}
+
+/// gossipsub 传播参数。大快照经 gossip 传播时需要调高 `max_transmit_size`
+#[derive(Clone, Debug)]
+pub struct GossipConfig {
+    /// mesh 目标节点数，上下限按 libp2p 默认比例推出
+    pub mesh_n: usize,
+    pub heartbeat_interval: Duration,
+    /// 单条消息的最大字节数
+    pub max_transmit_size: usize,
+    pub message_id: GossipMessageId,
+    /// 只支持 `strict`：要求每条消息带发布者签名。签名校验、TOFU 绑定与节点评分都信任
+    /// gossipsub 的消息源，未签名的消息源可被伪造
+    pub validation: ValidationMode,
+    /// 启用 gossipsub 节点评分：共识层记录的直连签名失败与无效更新作为应用层评分（gossip
+    /// 转发的无效消息经校验结果上报给 gossipsub，不向转发者扣应用层分），
+    /// 评分为负的节点在心跳时被逐出 mesh，更低时不再向其发送 gossip
+    pub peer_scoring: bool,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            mesh_n: 6,
+            heartbeat_interval: Duration::from_secs(1),
+            max_transmit_size: 65_536,
+            message_id: GossipMessageId::default(),
+            validation: ValidationMode::Strict,
+            peer_scoring: true,
+        }
+    }
+}
+
+impl GossipConfig {
+    pub fn build(&self) -> Result<gossipsub::Config, CommsError> {
+        let mesh_n = self.mesh_n.max(1);
+        let mut builder = gossipsub::ConfigBuilder::default();
+        builder
+            .mesh_n(mesh_n)
+            .mesh_n_low(mesh_n.saturating_sub(1).max(1))
+            .mesh_n_high(mesh_n * 2)
+            .mesh_outbound_min((mesh_n / 2).min(2))
+            .heartbeat_interval(self.heartbeat_interval)
+            .max_transmit_size(self.max_transmit_size)
+            .validation_mode(self.validation.clone())
+            // 节点校验签名并上报结果后才转发，无效消息不会先被转发出去
+            .validate_messages();
+        if self.message_id == GossipMessageId::ContentHash {
+            builder.message_id_fn(|message: &gossipsub::Message| {
+                gossipsub::MessageId::new(&Keccak256::digest(&message.data))
+            });
+        }
+        builder
+            .build()
+            .map_err(|e| CommsError::Transport(format!("gossipsub 配置无效: {e}")))
+    }
+
+    /// 节点评分参数：分数完全由应用层评分决定，不按同 IP 节点数扣分（本地多节点测试常见）
+    fn score_params() -> PeerScoreParams {
+        PeerScoreParams {
+            app_specific_weight: 1.0,
+            ip_colocation_factor_weight: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// 解析 gossipsub 校验模式。permissive、none 与 anonymous 下消息源未经认证，
+/// 而入站管道的来源校验、TOFU 绑定和节点评分都依赖消息源，因此一律拒绝
+pub fn parse_validation_mode(s: &str) -> Result<ValidationMode> {
+    match s.trim() {
+        "strict" => Ok(ValidationMode::Strict),
+        mode @ ("permissive" | "none" | "anonymous") => Err(anyhow!(
+            "gossipsub 校验模式 {:?} 不认证消息源，不支持（只能为 strict）",
+            mode
+        )),
+        _ => Err(anyhow!("未知的 gossipsub 校验模式 {:?}（可选 strict）", s)),
+    }
+}
+
+/// rendezvous 协议配置：在已知的 rendezvous 节点上以主题名为命名空间注册并发现其他节点，
+/// 无需运行完整 DHT 即可跨 NAT 互相发现
+#[derive(Clone, Debug, Default)]
+pub struct RendezvousConfig {
+    /// rendezvous 节点地址，必须包含 `/p2p/<PeerId>`
+    pub points: Vec<Multiaddr>,
+    /// 本节点同时作为 rendezvous 服务端
+    pub serve: bool,
+}
+
+/// Kademlia DHT 配置：经引导节点加入 GGS 专用的 DHT，跨公网发现节点并补充 gossipsub mesh
+#[derive(Clone, Debug)]
+pub struct DhtConfig {
+    pub enabled: bool,
+    /// 引导节点地址，必须包含 `/p2p/<PeerId>`
+    pub bootstrap: Vec<Multiaddr>,
+}
+
+/// NAT 穿透配置：AutoNAT 探测本节点是否公网可达，判定处于 NAT 之后时在 circuit relay v2
+/// 中继节点上预留中继地址；其他节点经中继连上后由 DCUtR 尝试打洞升级为直连
+#[derive(Clone, Debug, Default)]
+pub struct NatConfig {
+    /// circuit relay 节点地址，必须包含 `/p2p/<PeerId>`
+    pub relays: Vec<Multiaddr>,
+    /// 本节点同时作为 circuit relay 服务端（应部署在公网可达的节点上）
+    pub relay_server: bool,
+}
+
+impl Default for DhtConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bootstrap: Vec::new(),
+        }
+    }
 }
 
 impl Default for CommsConfig {
@@ -33,9 +411,26 @@ impl Default for CommsConfig {
         Self {
             topic: "ggs-training".into(),
             listen_addr: None,
-            quic_bind: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 9234)),
+            quic_bind: Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 9234)),
+            dual_stack: true,
             quic_bootstrap: Vec::new(),
+            quic_advertise: None,
+            external_addrs: Vec::new(),
+            rendezvous: RendezvousConfig::default(),
+            dht: DhtConfig::default(),
+            nat: NatConfig::default(),
+            relay: RelayConfig::default(),
             bandwidth: BandwidthBudgetConfig::default(),
+            identity_path: None,
+            codecs: WireCodec::ALL.to_vec(),
+            gossip_codec: WireCodec::Json,
+            bootstrap_peers: Vec::new(),
+            transport: TransportConfig::default(),
+            proxy: ProxyConfig::default(),
+            connections: ConnectionManagerConfig::default(),
+            send_policy: SendPolicy::default(),
+            publish_queue: PublishQueueConfig::default(),
+            gossip: GossipConfig::default(),
         }
     }
 }
@@ -47,6 +442,18 @@ pub struct BandwidthBudgetConfig {
     pub window_secs: u64,
 }
 
+impl BandwidthBudgetConfig {
+    /// 按比例切分出一个子预算（每窗口至少保留 1 次稀疏更新）
+    pub fn scaled(&self, share: f32) -> Self {
+        let share = share.clamp(0.0, 1.0);
+        Self {
+            sparse_per_window: ((self.sparse_per_window as f32 * share) as u32).max(1),
+            dense_bytes_per_window: (self.dense_bytes_per_window as f32 * share) as usize,
+            window_secs: self.window_secs,
+        }
+    }
+}
+
 impl Default for BandwidthBudgetConfig {
     fn default() -> Self {
         Self {
@@ -57,27 +464,51 @@ impl Default for BandwidthBudgetConfig {
     }
 }
 
+/// 上一个完整窗口的稀疏更新预算使用情况
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetUsage {
+    /// 窗口序号，单调递增
+    pub window: u64,
+    /// 已用次数 / 预算次数
+    pub utilization: f32,
+    /// 因预算耗尽被拒绝的次数
+    pub denied: u32,
+}
+
 struct BandwidthBudget {
     config: BandwidthBudgetConfig,
+    /// 占总预算的比例，热加载总预算时按它重新切分
+    share: f32,
     window_start: Instant,
     sparse_sent: u32,
+    sparse_denied: u32,
     dense_sent: usize,
+    last_usage: Option<BudgetUsage>,
 }
 
 impl BandwidthBudget {
-    fn new(config: BandwidthBudgetConfig) -> Self {
+    fn new(total: &BandwidthBudgetConfig, share: f32) -> Self {
         Self {
-            config,
+            config: total.scaled(share),
+            share,
             window_start: Instant::now(),
             sparse_sent: 0,
+            sparse_denied: 0,
             dense_sent: 0,
+            last_usage: None,
         }
     }
 
     fn rotate(&mut self) {
         if self.window_start.elapsed() >= Duration::from_secs(self.config.window_secs) {
+            self.last_usage = Some(BudgetUsage {
+                window: self.last_usage.map(|u| u.window + 1).unwrap_or(0),
+                utilization: self.sparse_sent as f32 / self.config.sparse_per_window.max(1) as f32,
+                denied: self.sparse_denied,
+            });
             self.window_start = Instant::now();
             self.sparse_sent = 0;
+            self.sparse_denied = 0;
             self.dense_sent = 0;
         }
     }
@@ -88,6 +519,7 @@ impl BandwidthBudget {
             self.sparse_sent += 1;
             true
         } else {
+            self.sparse_denied += 1;
             false
         }
     }
@@ -107,13 +539,33 @@ impl BandwidthBudget {
 #[behaviour(out_event = "OutEvent")]
 pub struct Behaviour {
     gossipsub: GossipsubBehaviour,
-    mdns: Mdns,
+    mdns: Toggle<Mdns>,
+    mdns6: Toggle<Mdns>,
+    rendezvous: rendezvous::client::Behaviour,
+    rendezvous_server: Toggle<rendezvous::server::Behaviour>,
+    kademlia: Toggle<Kademlia<MemoryStore>>,
+    identify: identify::Behaviour,
+    autonat: autonat::Behaviour,
+    relay_client: relay::client::Behaviour,
+    relay_server: Toggle<relay::Behaviour>,
+    dcutr: dcutr::Behaviour,
+    direct: request_response::Behaviour<DirectCodec>,
+    limits: connection_limits::Behaviour,
 }
 
 #[derive(Debug)]
 pub enum OutEvent {
     Gossipsub(GossipsubEvent),
     Mdns(MdnsEvent),
+    Rendezvous(rendezvous::client::Event),
+    RendezvousServer(rendezvous::server::Event),
+    Kademlia(KademliaEvent),
+    Identify(identify::Event),
+    Autonat(autonat::Event),
+    RelayClient(relay::client::Event),
+    RelayServer(relay::Event),
+    Dcutr(dcutr::Event),
+    Direct(request_response::Event<Vec<u8>, ()>),
 }
 
 impl From<GossipsubEvent> for OutEvent {
@@ -128,164 +580,1953 @@ impl From<MdnsEvent> for OutEvent {
     }
 }
 
+impl From<rendezvous::client::Event> for OutEvent {
+    fn from(v: rendezvous::client::Event) -> Self {
+        OutEvent::Rendezvous(v)
+    }
+}
+
+impl From<rendezvous::server::Event> for OutEvent {
+    fn from(v: rendezvous::server::Event) -> Self {
+        OutEvent::RendezvousServer(v)
+    }
+}
+
+impl From<KademliaEvent> for OutEvent {
+    fn from(v: KademliaEvent) -> Self {
+        OutEvent::Kademlia(v)
+    }
+}
+
+impl From<identify::Event> for OutEvent {
+    fn from(v: identify::Event) -> Self {
+        OutEvent::Identify(v)
+    }
+}
+
+impl From<autonat::Event> for OutEvent {
+    fn from(v: autonat::Event) -> Self {
+        OutEvent::Autonat(v)
+    }
+}
+
+impl From<relay::client::Event> for OutEvent {
+    fn from(v: relay::client::Event) -> Self {
+        OutEvent::RelayClient(v)
+    }
+}
+
+impl From<relay::Event> for OutEvent {
+    fn from(v: relay::Event) -> Self {
+        OutEvent::RelayServer(v)
+    }
+}
+
+impl From<dcutr::Event> for OutEvent {
+    fn from(v: dcutr::Event) -> Self {
+        OutEvent::Dcutr(v)
+    }
+}
+
+impl From<request_response::Event<Vec<u8>, ()>> for OutEvent {
+    fn from(v: request_response::Event<Vec<u8>, ()>) -> Self {
+        OutEvent::Direct(v)
+    }
+}
+
+impl From<void::Void> for OutEvent {
+    fn from(v: void::Void) -> Self {
+        void::unreachable(v)
+    }
+}
+
+#[derive(Clone)]
+pub struct DirectProtocol;
+
+impl ProtocolName for DirectProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        DIRECT_PROTOCOL.as_bytes()
+    }
+}
+
+/// 经 libp2p 连接发送的直连消息：请求为按协商编码编码后的 SignedGossip，响应为空确认
+#[derive(Clone)]
+pub struct DirectCodec;
+
+#[async_trait]
+impl request_response::Codec for DirectCodec {
+    type Protocol = DirectProtocol;
+    type Request = Vec<u8>;
+    type Response = ();
+
+    async fn read_request<T>(&mut self, _: &DirectProtocol, io: &mut T) -> std::io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        upgrade::read_length_prefixed(io, MAX_DIRECT_MESSAGE_BYTES).await
+    }
+
+    async fn read_response<T>(&mut self, _: &DirectProtocol, io: &mut T) -> std::io::Result<()>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        upgrade::read_length_prefixed(io, 0).await.map(|_| ())
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &DirectProtocol,
+        io: &mut T,
+        request: Vec<u8>,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        upgrade::write_length_prefixed(io, request).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(&mut self, _: &DirectProtocol, io: &mut T, _: ()) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        upgrade::write_length_prefixed(io, []).await?;
+        io.close().await
+    }
+}
+
+/// 到某个节点的 QUIC（UDP）直连可达性：连续失败达到阈值判定为受阻，冷却后再试一次 QUIC
+#[derive(Default)]
+struct QuicReachability {
+    failures: u32,
+    blocked_until: Option<Instant>,
+}
+
+impl QuicReachability {
+    fn usable(&self) -> bool {
+        self.blocked_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    /// 记录一次发送结果；刚判定受阻时返回 Some(true)，从受阻中恢复时返回 Some(false)
+    fn record(&mut self, ok: bool) -> Option<bool> {
+        if ok {
+            self.failures = 0;
+            return self.blocked_until.take().map(|_| false);
+        }
+        self.failures += 1;
+        if self.failures < QUIC_BLOCKED_AFTER {
+            return None;
+        }
+        let newly_blocked = self.blocked_until.is_none();
+        self.blocked_until = Some(Instant::now() + QUIC_RETRY_AFTER);
+        newly_blocked.then_some(true)
+    }
+}
+
+/// 可持久化的节点地址簿条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerAddresses {
+    pub peer_id: String,
+    pub addrs: Vec<String>,
+    /// 最后一次发现或连接成功的时间（Unix 秒），0 表示旧版本未记录
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+/// identify 协议交换得到的节点信息
+#[derive(Debug, Clone)]
+pub struct IdentifiedPeer {
+    pub agent_version: String,
+    /// 对方宣告的监听地址（含外部地址与中继地址）
+    pub listen_addrs: Vec<Multiaddr>,
+}
+
+/// 地址簿中的一个节点
+struct KnownPeer {
+    addrs: Vec<Multiaddr>,
+    last_seen: u64,
+}
+
+fn address_expired(last_seen: u64, now: u64) -> bool {
+    last_seen != 0 && now.saturating_sub(last_seen) > ADDRESS_BOOK_TTL.as_secs()
+}
+
 pub struct CommsHandle {
     pub peer_id: PeerId,
     pub swarm: Swarm<Behaviour>,
     pub topic: Topic,
+    /// 会话 id -> gossip 主题
+    session_topics: HashMap<String, Topic>,
+    /// 经 mDNS、rendezvous 发现或主动拨号成功的节点地址，随节点状态持久化
+    address_book: HashMap<PeerId, KnownPeer>,
     quic: Option<Arc<QuicGateway>>,
-    bandwidth: RwLock<BandwidthBudget>,
+    quic_advertise: Option<SocketAddr>,
+    /// rendezvous 节点 PeerId -> 地址
+    rendezvous_points: HashMap<PeerId, Multiaddr>,
+    /// 每个 rendezvous 节点上次发现返回的 cookie，下次只取增量
+    rendezvous_cookies: HashMap<PeerId, Cookie>,
+    rendezvous_namespace: Namespace,
+    /// DHT 引导节点 PeerId -> 地址
+    dht_bootstrap: HashMap<PeerId, Multiaddr>,
+    /// circuit relay 节点 PeerId -> 地址
+    circuit_relays: HashMap<PeerId, Multiaddr>,
+    /// 是否已在中继节点上请求预留（AutoNAT 判定处于 NAT 之后时请求一次）
+    relay_reserved: bool,
+    bootstrap_peers: Vec<Multiaddr>,
+    /// 下一次重拨引导节点的时间与当前退避间隔，mesh 中有节点后为 None
+    bootstrap_retry: Option<(Instant, Duration)>,
+    /// 未配置外部地址时，把监听地址当作外部地址注册
+    advertise_listen_addrs: bool,
+    /// QUIC 直连收到的消息
+    direct_rx: mpsc::UnboundedReceiver<(String, SignedGossip)>,
+    /// 对端经双向流发来的同步请求
+    sync_requests: mpsc::UnboundedReceiver<InboundRequest>,
+    /// 本节点发出的同步请求的结果
+    sync_replies_tx: mpsc::UnboundedSender<(String, Result<SyncResponse, SyncError>)>,
+    sync_replies_rx: mpsc::UnboundedReceiver<(String, Result<SyncResponse, SyncError>)>,
+    relay: Option<RelayClient>,
+    bandwidth_config: BandwidthBudgetConfig,
+    /// 会话 id -> 带宽子预算
+    bandwidth: RwLock<HashMap<String, BandwidthBudget>>,
     network_type: parking_lot::RwLock<crate::device::NetworkType>,
+    codecs: Vec<WireCodec>,
+    gossip_codec: WireCodec,
+    /// 与各节点协商出的直连编码
+    peer_codecs: RwLock<PeerCodecs>,
+    /// 各节点按路径的单向延迟直方图
+    latency: LatencyTracker,
+    /// 已连接节点的 identify 信息，断开后仍保留最后一次的结果
+    identified: HashMap<PeerId, IdentifiedPeer>,
+    tcp_fallback: bool,
+    send_policy: SendPolicy,
+    /// 出站连接经 SOCKS5 代理，直连消息只走 libp2p 连接
+    proxied: bool,
+    /// 开启了 mDNS 局域网发现，其他节点宣告的非公网地址也可拨号
+    lan_discovery: bool,
+    /// 节点（身份未知时为地址）-> QUIC 直连可达性
+    quic_reachability: Mutex<HashMap<String, QuicReachability>>,
+    /// 节点 -> 心跳宣告的 QUIC 地址，只用于按该节点身份校验证书的拨号
+    announced_addrs: RwLock<HashMap<String, SocketAddr>>,
+    /// 待经 libp2p 连接发送的直连消息，由事件循环交给 swarm
+    fallback_tx: mpsc::UnboundedSender<(PeerId, Vec<u8>)>,
+    fallback_rx: mpsc::UnboundedReceiver<(PeerId, Vec<u8>)>,
+    /// 其他节点观测到的本节点地址（NAT 之后为映射后的公网地址）
+    observed_addrs: Vec<Multiaddr>,
+    /// 连接上限与清理策略，与 QUIC 网关共用
+    conn_manager: Arc<RwLock<ConnectionManager>>,
+    /// 已连接节点 -> (是否入站, 最近一次消息往来)
+    peer_activity: HashMap<PeerId, (bool, Instant)>,
+    /// 因没有 gossipsub 节点而发布失败、等待重发的消息
+    publish_queue: PublishQueue,
 }
 
 impl CommsHandle {
-    pub async fn new(config: CommsConfig) -> Result<Self> {
-        let local_key = identity::Keypair::generate_ed25519();
+    pub async fn new(config: CommsConfig) -> Result<Self, CommsError> {
+        let local_key = match &config.identity_path {
+            Some(path) => load_or_create_identity(path)?,
+            None => identity::Keypair::generate_ed25519(),
+        };
         let peer_id = PeerId::from(local_key.public());
 
-        let transport = libp2p::tokio_development_transport(local_key.clone())?;
-        let gossipsub_config = gossipsub::ConfigBuilder::default()
-            .validation_mode(ValidationMode::Permissive)
-            .build()
-            .expect("valid config");
+        let (relay_transport, relay_client) = relay::client::new(peer_id);
+        let transport = build_transport(
+            &local_key,
+            relay_transport,
+            &config.transport.stack,
+            &config.proxy,
+        )
+        .map_err(CommsError::transport)?;
+        let proxied = config.proxy.enabled();
+        if let Some(server) = &config.proxy.server {
+            println!("[代理] 出站连接经 SOCKS5 代理 {}，不发起 QUIC 直连", server);
+        }
         let mut gossipsub = GossipsubBehaviour::new(
             MessageAuthenticity::Signed(local_key.clone()),
-            gossipsub_config,
+            config.gossip.build()?,
         )
-        .map_err(|e| anyhow!(e))?;
+        .map_err(CommsError::transport)?;
+        if config.gossip.peer_scoring {
+            gossipsub
+                .with_peer_score(GossipConfig::score_params(), PeerScoreThresholds::default())
+                .map_err(|e| CommsError::Transport(format!("gossipsub 节点评分参数无效: {e}")))?;
+        }
         let topic = Topic::new(config.topic.clone());
         gossipsub.subscribe(&topic)?;
-        let mdns = Mdns::new(mdns::Config::default(), peer_id)?;
-        let behaviour = Behaviour { gossipsub, mdns };
+        let use_mdns = !(proxied && config.proxy.disable_mdns);
+        if !use_mdns {
+            println!("[代理] 已关闭 mDNS");
+        }
+        let mdns = if use_mdns {
+            Some(Mdns::new(mdns::Config::default(), peer_id)?)
+        } else {
+            None
+        };
+        let mdns6 = if use_mdns && config.dual_stack {
+            let ipv6 = mdns::Config {
+                enable_ipv6: true,
+                ..Default::default()
+            };
+            Some(Mdns::new(ipv6, peer_id)?)
+        } else {
+            None
+        };
+        let rendezvous_server = config
+            .rendezvous
+            .serve
+            .then(|| rendezvous::server::Behaviour::new(rendezvous::server::Config::default()));
+        let mut dht_bootstrap = HashMap::new();
+        for addr in &config.dht.bootstrap {
+            let peer = require_peer_id("DHT bootstrap", addr)?;
+            dht_bootstrap.insert(peer, addr.clone());
+        }
+        let kademlia = config.dht.enabled.then(|| {
+            let mut kad_config = KademliaConfig::default();
+            kad_config.set_protocol_names(vec![Cow::Borrowed(DHT_PROTOCOL)]);
+            let mut kademlia =
+                Kademlia::with_config(peer_id, MemoryStore::new(peer_id), kad_config);
+            for (peer, addr) in &dht_bootstrap {
+                kademlia.add_address(peer, addr.clone());
+            }
+            kademlia
+        });
+        let mut circuit_relays = HashMap::new();
+        for addr in &config.nat.relays {
+            let peer = require_peer_id("circuit relay", addr)?;
+            circuit_relays.insert(peer, addr.clone());
+        }
+        let mut autonat = autonat::Behaviour::new(peer_id, autonat::Config::default());
+        for (peer, addr) in &circuit_relays {
+            autonat.add_server(*peer, Some(addr.clone()));
+        }
+        let behaviour = Behaviour {
+            gossipsub,
+            mdns: mdns.into(),
+            mdns6: mdns6.into(),
+            rendezvous: rendezvous::client::Behaviour::new(local_key.clone()),
+            rendezvous_server: rendezvous_server.into(),
+            kademlia: kademlia.into(),
+            identify: identify::Behaviour::new(
+                identify::Config::new(IDENTIFY_PROTOCOL.to_string(), local_key.public())
+                    .with_agent_version(format!("ggs/{}", env!("CARGO_PKG_VERSION"))),
+            ),
+            autonat,
+            relay_client,
+            relay_server: config
+                .nat
+                .relay_server
+                .then(|| relay::Behaviour::new(peer_id, relay::Config::default()))
+                .into(),
+            dcutr: dcutr::Behaviour::new(peer_id),
+            direct: request_response::Behaviour::new(
+                DirectCodec,
+                [(DirectProtocol, ProtocolSupport::Full)],
+                request_response::Config::default(),
+            ),
+            limits: connection_limits::Behaviour::new(
+                ConnectionLimits::default()
+                    .with_max_established_incoming(Some(config.connections.max_inbound))
+                    .with_max_established_outgoing(Some(config.connections.max_outbound))
+                    .with_max_established_per_peer(Some(MAX_CONNECTIONS_PER_PEER)),
+            ),
+        };
         let mut swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build();
         if let Some(addr) = config.listen_addr {
-            swarm.listen_on(addr)?;
+            // IPv6 不可用时只在 IPv4 上监听
+            let ipv6 = netaddr::ipv6_counterpart(&addr).filter(|_| config.dual_stack);
+            swarm.listen_on(addr).map_err(CommsError::transport)?;
+            if let Some(addr) = ipv6 {
+                if let Err(e) = swarm.listen_on(addr.clone()) {
+                    eprintln!("[网络] 监听 {} 失败: {:?}", addr, e);
+                }
+            }
+        }
+        for addr in &config.external_addrs {
+            swarm.add_external_address(addr.clone(), AddressScore::Infinite);
+        }
+        let rendezvous_namespace =
+            Namespace::new(config.topic.clone()).map_err(|_| CommsError::TopicTooLong)?;
+        let mut rendezvous_points = HashMap::new();
+        for addr in &config.rendezvous.points {
+            let peer = require_peer_id("rendezvous", addr)?;
+            if let Err(e) = swarm.dial(addr.clone()) {
+                eprintln!("[Rendezvous] 拨号 {} 失败: {:?}", addr, e);
+            }
+            rendezvous_points.insert(peer, addr.clone());
+        }
+        for addr in &config.bootstrap_peers {
+            if let Err(e) = swarm.dial(addr.clone()) {
+                eprintln!("[引导] 拨号 {} 失败: {:?}", addr, e);
+            }
+        }
+        // 先连上中继节点，AutoNAT 以它们为探测服务端
+        for addr in circuit_relays.values() {
+            if let Err(e) = swarm.dial(addr.clone()) {
+                eprintln!("[NAT] 拨号中继 {} 失败: {:?}", addr, e);
+            }
+        }
+        if let Some(kademlia) = swarm.behaviour_mut().kademlia.as_mut() {
+            // 路由表为空（未配置引导节点）时等 mDNS、rendezvous 或拨号带来第一批节点
+            if kademlia.bootstrap().is_ok() {
+                println!("[DHT] 经 {} 个引导节点加入 DHT", dht_bootstrap.len());
+            }
         }
 
+        let (direct_tx, direct_rx) = mpsc::unbounded_channel();
+        let (fallback_tx, fallback_rx) = mpsc::unbounded_channel();
+        let (sync_tx, sync_requests) = mpsc::unbounded_channel();
+        let (sync_replies_tx, sync_replies_rx) = mpsc::unbounded_channel();
+        let conn_manager = Arc::new(RwLock::new(ConnectionManager::new(config.connections)));
         let quic = if let Some(bind) = config.quic_bind {
-            let gateway = Arc::new(QuicGateway::new(bind)?);
-            for addr in &config.quic_bootstrap {
-                let _ = gateway.connect(*addr).await;
+            let mut gateway = QuicGateway::new(bind, config.dual_stack, direct_tx, Some(sync_tx), &local_key)
+                .map_err(CommsError::quic)?;
+            gateway.advertise = config.quic_advertise;
+            gateway.manager = Arc::clone(&conn_manager);
+            let gateway = Arc::new(gateway);
+            tokio::spawn(QuicGateway::redial_loop(Arc::downgrade(&gateway)));
+            // QUIC 无法经 SOCKS5 代理，代理模式下只接受入站连接
+            for addr in config.quic_bootstrap.iter().filter(|_| !proxied) {
+                if gateway.is_own(*addr) {
+                    println!("[QUIC] 引导地址 {} 指向本节点，跳过", addr);
+                    continue;
+                }
+                let _ = gateway.connect(*addr, None).await;
             }
             Some(gateway)
         } else {
             None
         };
 
+        let mut session_topics = HashMap::new();
+        session_topics.insert(DEFAULT_SESSION.to_string(), topic.clone());
+        let mut budgets = HashMap::new();
+        budgets.insert(
+            DEFAULT_SESSION.to_string(),
+            BandwidthBudget::new(&config.bandwidth, 1.0),
+        );
+
         Ok(Self {
-            peer_id: swarm.local_peer_id().clone(),
+            peer_id: *swarm.local_peer_id(),
             swarm,
             topic,
+            session_topics,
+            address_book: HashMap::new(),
             quic,
-            bandwidth: RwLock::new(BandwidthBudget::new(config.bandwidth)),
+            rendezvous_points,
+            rendezvous_cookies: HashMap::new(),
+            rendezvous_namespace,
+            dht_bootstrap,
+            circuit_relays,
+            relay_reserved: false,
+            bootstrap_retry: (!config.bootstrap_peers.is_empty())
+                .then(|| (Instant::now() + BOOTSTRAP_RETRY_INITIAL, BOOTSTRAP_RETRY_INITIAL)),
+            bootstrap_peers: config.bootstrap_peers,
+            advertise_listen_addrs: config.external_addrs.is_empty(),
+            quic_advertise: config
+                .quic_advertise
+                .or(config.quic_bind.filter(|addr| !addr.ip().is_unspecified())),
+            direct_rx,
+            sync_requests,
+            sync_replies_tx,
+            sync_replies_rx,
+            relay: RelayClient::spawn(config.relay, config.proxy),
+            bandwidth_config: config.bandwidth,
+            bandwidth: RwLock::new(budgets),
             network_type: parking_lot::RwLock::new(NetworkType::Unknown),
+            codecs: config.codecs,
+            gossip_codec: config.gossip_codec,
+            peer_codecs: RwLock::new(PeerCodecs::default()),
+            latency: LatencyTracker::default(),
+            identified: HashMap::new(),
+            observed_addrs: Vec::new(),
+            tcp_fallback: config.transport.tcp_fallback,
+            send_policy: config.send_policy,
+            proxied,
+            lan_discovery: use_mdns,
+            conn_manager,
+            peer_activity: HashMap::new(),
+            quic_reachability: Mutex::new(HashMap::new()),
+            announced_addrs: RwLock::new(HashMap::new()),
+            fallback_tx,
+            fallback_rx,
+            publish_queue: PublishQueue::new(config.publish_queue),
         })
     }
 
-    pub fn publish(&mut self, signed: &SignedGossip) -> Result<()> {
-        let data = serde_json::to_vec(signed)?;
-        self.swarm
-            .behaviour_mut()
-            .gossipsub
-            .publish(self.topic.clone(), data)?;
-        Ok(())
+    /// 退出前退订所有会话主题，在宽限期内继续驱动 swarm 把退订消息发给 mesh 节点，然后关闭 QUIC 连接
+    pub async fn shutdown(&mut self, grace: Duration) {
+        for topic in self.session_topics.values() {
+            if let Err(e) = self.swarm.behaviour_mut().gossipsub.unsubscribe(topic) {
+                eprintln!("[退出] 退订主题 {} 失败: {:?}", topic, e);
+            }
+        }
+        let deadline = tokio::time::sleep(grace / 2);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                _ = &mut deadline => break,
+                _ = self.swarm.select_next_some() => {}
+            }
+        }
+        if let Some(quic) = &self.quic {
+            quic.close(grace / 2).await;
+        }
     }
 
-    pub fn allow_sparse_update(&self) -> bool {
-        self.bandwidth.write().allow_sparse()
+    /// 热加载总带宽预算，各会话按原比例重新切分，当前窗口的已用计数保留
+    pub fn set_bandwidth(&mut self, config: BandwidthBudgetConfig) {
+        for budget in self.bandwidth.write().values_mut() {
+            budget.config = config.scaled(budget.share);
+        }
+        self.bandwidth_config = config;
     }
 
-    pub fn allow_dense_snapshot(&self, bytes: usize) -> bool {
-        // 检查网络类型是否允许密集快照
-        let network_type = *self.network_type.read();
-        if !network_type.allows_dense_snapshot() {
-            return false;
-        }
-        self.bandwidth.write().allow_dense(bytes)
+    /// 根据 gossip 主题查找所属会话
+    fn session_for_topic(&self, topic: &gossipsub::TopicHash) -> Option<&str> {
+        self.session_topics
+            .iter()
+            .find(|(_, t)| &t.hash() == topic)
+            .map(|(id, _)| id.as_str())
     }
 
-    /// 更新网络类型
-    pub fn update_network_type(&self, network_type: NetworkType) {
-        *self.network_type.write() = network_type;
-        println!("[网络] 网络类型更新: {:?}", network_type);
+    /// 记录节点地址（来自 mDNS、rendezvous 或拨号成功的连接），最近使用的地址排在最前
+    fn record_address(&mut self, peer: PeerId, addr: Multiaddr) {
+        if peer == self.peer_id {
+            return;
+        }
+        let known = self.address_book.entry(peer).or_insert(KnownPeer {
+            addrs: Vec::new(),
+            last_seen: 0,
+        });
+        known.addrs.retain(|a| a != &addr);
+        known.addrs.insert(0, addr.clone());
+        known.addrs.truncate(MAX_ADDRS_PER_PEER);
+        known.last_seen = unix_now_secs();
+        self.swarm.behaviour_mut().direct.add_address(&peer, addr.clone());
+        if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+            kademlia.add_address(&peer, addr);
+        }
     }
 
-    /// 获取当前网络类型
-    pub fn network_type(&self) -> NetworkType {
-        *self.network_type.read()
+    /// 心跳宣告的地址记入地址簿：未知的地址排在已用过的地址之后，供拨号与 TCP 回退使用
+    pub fn learn_peer_addrs(&mut self, peer: &str, addrs: &[String]) {
+        let Ok(peer) = peer.parse::<PeerId>() else {
+            return;
+        };
+        if peer == self.peer_id {
+            return;
+        }
+        for addr in addrs.iter().take(MAX_HEARTBEAT_ADDRS) {
+            let Ok(addr) = addr.parse::<Multiaddr>() else {
+                continue;
+            };
+            if !self.lan_discovery && !netaddr::is_global_multiaddr(&addr) {
+                continue;
+            }
+            let known = self.address_book.entry(peer).or_insert(KnownPeer {
+                addrs: Vec::new(),
+                last_seen: 0,
+            });
+            if known.addrs.contains(&addr) || known.addrs.len() >= MAX_ADDRS_PER_PEER {
+                continue;
+            }
+            known.addrs.push(addr.clone());
+            self.swarm.behaviour_mut().direct.add_address(&peer, addr.clone());
+            if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+                kademlia.add_address(&peer, addr);
+            }
+        }
     }
 
-    pub async fn broadcast_realtime(&self, signed: &SignedGossip) -> bool {
-        if let Some(quic) = &self.quic {
-            return quic.broadcast(signed).await;
+    /// 心跳宣告的 libp2p 地址：外部地址在前，其次是指定了 IP 的监听地址
+    pub fn reachable_addrs(&self) -> Vec<String> {
+        let listeners = self.swarm.listeners().filter(|addr| {
+            !addr.iter().any(|p| match p {
+                Protocol::Ip4(ip) => ip.is_unspecified(),
+                Protocol::Ip6(ip) => ip.is_unspecified(),
+                _ => false,
+            })
+        });
+        let mut addrs: Vec<String> = Vec::new();
+        for addr in self.swarm.external_addresses().map(|r| &r.addr).chain(listeners) {
+            let addr = addr.to_string();
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
         }
-        false
+        addrs.truncate(MAX_HEARTBEAT_ADDRS);
+        addrs
     }
-}
 
-struct QuicGateway {
-    endpoint: Endpoint,
-    connections: Arc<RwLock<Vec<ConnectionInfo>>>,
-}
+    /// 导出地址簿，跳过长期未见的节点
+    pub fn export_address_book(&self) -> Vec<PeerAddresses> {
+        let now = unix_now_secs();
+        self.address_book
+            .iter()
+            .filter(|(_, known)| !address_expired(known.last_seen, now))
+            .map(|(peer, known)| PeerAddresses {
+                peer_id: peer.to_string(),
+                addrs: known.addrs.iter().map(|a| a.to_string()).collect(),
+                last_seen: known.last_seen,
+            })
+            .collect()
+    }
 
-struct ConnectionInfo {
-    connection: quinn::Connection,
-    last_health_check: Instant,
-    consecutive_failures: u32,
-}
+    /// 恢复持久化的地址簿，每个节点用其全部已知地址拨号一次，
+    /// 无需等待本地发现即可在广域网上重新加入 mesh
+    pub fn restore_address_book(&mut self, entries: Vec<PeerAddresses>) {
+        let now = unix_now_secs();
+        for entry in entries {
+            let Ok(peer) = entry.peer_id.parse::<PeerId>() else {
+                continue;
+            };
+            if peer == self.peer_id || address_expired(entry.last_seen, now) {
+                continue;
+            }
+            let addrs: Vec<Multiaddr> = entry
+                .addrs
+                .iter()
+                .filter_map(|a| a.parse().ok())
+                .take(MAX_ADDRS_PER_PEER)
+                .collect();
+            if addrs.is_empty() {
+                continue;
+            }
+            let opts = DialOpts::peer_id(peer).addresses(addrs.clone()).build();
+            if let Err(e) = self.swarm.dial(opts) {
+                eprintln!("[地址簿] 重新拨号 {} 失败: {:?}", peer, e);
+            }
+            // 旧版本未记录时间的条目视为刚刚见过
+            let last_seen = if entry.last_seen == 0 { now } else { entry.last_seen };
+            self.address_book.insert(peer, KnownPeer { addrs, last_seen });
+        }
+    }
 
-impl ConnectionInfo {
-    fn new(connection: quinn::Connection) -> Self {
-        Self {
-            connection,
-            last_health_check: Instant::now(),
-            consecutive_failures: 0,
+    /// 新的监听地址：中继地址总是作为外部地址宣告；未配置外部地址时监听地址作为 rendezvous 注册地址
+    fn on_new_listen_addr(&mut self, addr: Multiaddr) {
+        if addr.iter().any(|p| p == Protocol::P2pCircuit) {
+            self.swarm.add_external_address(addr, AddressScore::Infinite);
+        } else if self.advertise_listen_addrs && !self.rendezvous_points.is_empty() {
+            self.swarm.add_external_address(addr, AddressScore::Finite(1));
         }
     }
 
-    fn is_healthy(&self) -> bool {
-        self.consecutive_failures < 3
+    /// 主动拨号成功的地址记入地址簿；与 rendezvous 节点建立连接后立即注册并发现
+    fn on_connection_established(&mut self, peer: PeerId, dialed: Option<Multiaddr>) {
+        if let Some(addr) = dialed {
+            self.record_address(peer, addr);
+        }
+        if self.rendezvous_points.contains_key(&peer) {
+            self.register_and_discover(peer);
+        }
     }
 
-    fn mark_failure(&mut self) {
-        self.consecutive_failures += 1;
+    fn touch_peer(&mut self, peer: PeerId) {
+        if let Some((_, last_active)) = self.peer_activity.get_mut(&peer) {
+            *last_active = Instant::now();
+        }
     }
 
-    fn mark_success(&mut self) {
-        self.consecutive_failures = 0;
-        self.last_health_check = Instant::now();
+    /// 按拓扑评分从高到低排好序的邻居，连接清理时优先保留
+    pub fn set_preferred_peers(&mut self, ranked: Vec<String>) {
+        self.conn_manager.write().set_preferred(ranked);
     }
-}
 
-impl QuicGateway {
-    fn new(bind: SocketAddr) -> Result<Self> {
-        let cert = generate_simple_self_signed(vec!["ggs-quic".into()])?;
-        let cert_der = cert.serialize_der()?;
-        let key_der = cert.serialize_private_key_der();
-        let mut server_config = ServerConfig::with_single_cert(
-            vec![Certificate(cert_der.clone())],
-            PrivateKey(key_der.clone()),
-        )?;
-        server_config.transport = Arc::new(quinn::TransportConfig::default());
-        let endpoint = Endpoint::server(server_config, bind)?;
-        let connections = Arc::new(RwLock::new(Vec::new()));
-        let accept_endpoint = endpoint.clone();
-        let accept_pool = connections.clone();
-        tokio::spawn(async move {
-            loop {
-                match accept_endpoint.accept().await {
-                    Some(connecting) => match connecting.await {
-                        Ok(conn) => {
-                            accept_pool.write().push(ConnectionInfo::new(conn));
-                        }
-                        Err(err) => eprintln!("[QUIC] accept error: {err:?}"),
-                    },
-                    None => tokio::time::sleep(Duration::from_secs(1)).await,
+    /// 断开空闲与低价值的连接：libp2p 连接中引导节点、中继、rendezvous 与 DHT 引导节点不断开
+    pub fn prune_connections(&mut self) {
+        let ids: Vec<(PeerId, String)> = self
+            .peer_activity
+            .keys()
+            .map(|peer| (*peer, peer.to_string()))
+            .collect();
+        let conns: Vec<Conn<'_>> = ids
+            .iter()
+            .map(|(peer, id)| {
+                let (inbound, last_active) = self.peer_activity[peer];
+                Conn {
+                    id,
+                    inbound,
+                    last_active,
+                    protected: self.is_infrastructure(peer),
+                }
+            })
+            .collect();
+        let victims = self.conn_manager.read().prune(&conns, Instant::now());
+        if !victims.is_empty() {
+            println!("[连接] 断开 {} 个空闲或低价值的 libp2p 连接", victims.len());
+        }
+        for index in victims {
+            let _ = self.swarm.disconnect_peer_id(ids[index].0);
+        }
+        if let Some(quic) = &self.quic {
+            quic.prune();
+        }
+    }
+
+    fn is_infrastructure(&self, peer: &PeerId) -> bool {
+        self.rendezvous_points.contains_key(peer)
+            || self.circuit_relays.contains_key(peer)
+            || self.dht_bootstrap.contains_key(peer)
+            || self.bootstrap_peers.iter().any(|addr| peer_id_of(addr) == Some(*peer))
+    }
+
+    /// 周期性续约注册并增量发现；断开的 rendezvous 节点重新拨号
+    pub fn refresh_rendezvous(&mut self) {
+        let points: Vec<(PeerId, Multiaddr)> = self
+            .rendezvous_points
+            .iter()
+            .map(|(peer, addr)| (*peer, addr.clone()))
+            .collect();
+        for (peer, addr) in points {
+            if self.swarm.is_connected(&peer) {
+                self.register_and_discover(peer);
+            } else if let Err(e) = self.swarm.dial(addr.clone()) {
+                eprintln!("[Rendezvous] 拨号 {} 失败: {:?}", addr, e);
+            }
+        }
+    }
+
+    fn register_and_discover(&mut self, point: PeerId) {
+        let namespace = self.rendezvous_namespace.clone();
+        let cookie = self.rendezvous_cookies.get(&point).cloned();
+        let client = &mut self.swarm.behaviour_mut().rendezvous;
+        client.register(namespace.clone(), point, None);
+        client.discover(Some(namespace), cookie, None, point);
+    }
+
+    fn handle_rendezvous_event(&mut self, event: rendezvous::client::Event) {
+        match event {
+            rendezvous::client::Event::Discovered {
+                rendezvous_node,
+                registrations,
+                cookie,
+            } => {
+                self.rendezvous_cookies.insert(rendezvous_node, cookie);
+                for registration in registrations {
+                    let peer = registration.record.peer_id();
+                    if peer == self.peer_id || self.swarm.is_connected(&peer) {
+                        continue;
+                    }
+                    let addrs = registration.record.addresses().to_vec();
+                    println!("[Rendezvous] 经 {} 发现节点 {} {:?}", rendezvous_node, peer, addrs);
+                    for addr in &addrs {
+                        self.record_address(peer, addr.clone());
+                    }
+                    let opts = DialOpts::peer_id(peer).addresses(addrs).build();
+                    if let Err(e) = self.swarm.dial(opts) {
+                        eprintln!("[Rendezvous] 拨号 {} 失败: {:?}", peer, e);
+                    }
+                }
+            }
+            rendezvous::client::Event::Registered {
+                rendezvous_node,
+                ttl,
+                namespace,
+            } => {
+                println!(
+                    "[Rendezvous] 已在 {} 注册命名空间 {} (ttl {}s)",
+                    rendezvous_node, namespace, ttl
+                );
+            }
+            rendezvous::client::Event::RegisterFailed(e) => {
+                eprintln!("[Rendezvous] 注册失败: {:?}", e);
+            }
+            rendezvous::client::Event::DiscoverFailed {
+                rendezvous_node,
+                error,
+                ..
+            } => {
+                eprintln!("[Rendezvous] 在 {} 上发现失败: {:?}", rendezvous_node, error);
+            }
+            rendezvous::client::Event::Expired { .. } => {}
+        }
+    }
+
+    /// 下一次重拨引导节点的时间，不再需要重拨时为 None
+    pub fn next_bootstrap_retry(&self) -> Option<Instant> {
+        self.bootstrap_retry.map(|(at, _)| at)
+    }
+
+    /// mesh 中仍没有节点时重拨未连接的引导节点并加倍退避间隔；出现第一个节点后停止
+    pub fn retry_bootstrap(&mut self) {
+        let Some((_, delay)) = self.bootstrap_retry else {
+            return;
+        };
+        if self.gossip_peer_count() > 0 {
+            println!("[引导] gossipsub mesh 已有节点，停止重拨引导节点");
+            self.bootstrap_retry = None;
+            return;
+        }
+        for addr in &self.bootstrap_peers {
+            if peer_id_of(addr).is_some_and(|peer| self.swarm.is_connected(&peer)) {
+                continue;
+            }
+            if let Err(e) = self.swarm.dial(addr.clone()) {
+                eprintln!("[引导] 拨号 {} 失败: {:?}", addr, e);
+            }
+        }
+        let delay = (delay * 2).min(BOOTSTRAP_RETRY_MAX);
+        println!("[引导] mesh 中暂无节点，已重拨引导节点，{:?} 后重试", delay);
+        self.bootstrap_retry = Some((Instant::now() + delay, delay));
+    }
+
+    /// mDNS 发现的节点加为 gossipsub 显式节点并拨号，使其加入 mesh；记录过期后移除
+    fn handle_mdns_event(&mut self, event: MdnsEvent) {
+        match event {
+            MdnsEvent::Discovered(found) => {
+                let mut peers: HashMap<PeerId, Vec<Multiaddr>> = HashMap::new();
+                for (peer, addr) in found {
+                    if peer != self.peer_id {
+                        peers.entry(peer).or_default().push(addr);
+                    }
+                }
+                for (peer, addrs) in peers {
+                    println!("通过 mDNS 发现节点 {peer} {:?}", addrs);
+                    for addr in &addrs {
+                        self.record_address(peer, addr.clone());
+                    }
+                    self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer);
+                    if self.swarm.is_connected(&peer) {
+                        continue;
+                    }
+                    let opts = DialOpts::peer_id(peer).addresses(addrs).build();
+                    if let Err(e) = self.swarm.dial(opts) {
+                        eprintln!("[mDNS] 拨号 {} 失败: {:?}", peer, e);
+                    }
+                }
+            }
+            MdnsEvent::Expired(expired) => {
+                for (peer, _) in expired {
+                    // 同一节点的其他地址可能仍然有效
+                    let behaviour = self.swarm.behaviour();
+                    let ipv4 = behaviour.mdns.as_ref().is_some_and(|m| m.has_node(&peer));
+                    let ipv6 = behaviour.mdns6.as_ref().is_some_and(|m| m.has_node(&peer));
+                    if !ipv4 && !ipv6 {
+                        println!("[mDNS] 节点 {peer} 已离开局域网");
+                        self.swarm.behaviour_mut().gossipsub.remove_explicit_peer(&peer);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 周期性刷新 DHT：断开的引导节点重新加入路由表，再做一次随机游走补充路由表
+    pub fn refresh_dht(&mut self) {
+        let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() else {
+            return;
+        };
+        for (peer, addr) in &self.dht_bootstrap {
+            kademlia.add_address(peer, addr.clone());
+        }
+        let _ = kademlia.bootstrap();
+    }
+
+    /// DHT 新发现的节点记入地址簿；gossip 节点不足时拨号，使其加入 gossipsub mesh
+    fn handle_dht_event(&mut self, event: KademliaEvent) {
+        match event {
+            KademliaEvent::RoutingUpdated {
+                peer,
+                is_new_peer: true,
+                addresses,
+                ..
+            } => {
+                let addrs = addresses.into_vec();
+                if let Some(known) = self.address_book.get_mut(&peer) {
+                    known.last_seen = unix_now_secs();
+                } else {
+                    println!("[DHT] 发现节点 {} {:?}", peer, addrs);
+                    self.address_book.insert(
+                        peer,
+                        KnownPeer {
+                            addrs: addrs.iter().take(MAX_ADDRS_PER_PEER).cloned().collect(),
+                            last_seen: unix_now_secs(),
+                        },
+                    );
+                }
+                if self.swarm.is_connected(&peer) || self.gossip_peer_count() >= DHT_DIAL_TARGET {
+                    return;
+                }
+                let opts = DialOpts::peer_id(peer).addresses(addrs).build();
+                if let Err(e) = self.swarm.dial(opts) {
+                    eprintln!("[DHT] 拨号 {} 失败: {:?}", peer, e);
+                }
+            }
+            KademliaEvent::OutboundQueryProgressed {
+                result: QueryResult::Bootstrap(result),
+                ..
+            } => match result {
+                Ok(BootstrapOk {
+                    num_remaining: 0, ..
+                }) => {
+                    let known: usize = self
+                        .swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .as_mut()
+                        .map_or(0, |k| k.kbuckets().map(|b| b.num_entries()).sum());
+                    println!("[DHT] 引导完成，路由表中有 {} 个节点", known);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[DHT] 引导失败: {:?}", e),
+            },
+            _ => {}
+        }
+    }
+
+    /// 处理 identify、AutoNAT、circuit relay 与 DCUtR 事件
+    fn handle_nat_event(&mut self, event: OutEvent) {
+        match event {
+            OutEvent::Identify(identify::Event::Received { peer_id, info }) => {
+                self.on_identified(peer_id, info)
+            }
+            OutEvent::Autonat(autonat::Event::StatusChanged { new, .. }) => match new {
+                NatStatus::Public(addr) => println!("[NAT] 公网可达: {}", addr),
+                NatStatus::Private => {
+                    println!("[NAT] 节点处于 NAT 之后，外部观测地址: {:?}", self.observed_addrs);
+                    self.reserve_relays();
+                }
+                NatStatus::Unknown => {}
+            },
+            OutEvent::RelayClient(relay::client::Event::ReservationReqAccepted {
+                relay_peer_id,
+                renewal: false,
+                ..
+            }) => println!("[NAT] 中继 {} 已预留地址", relay_peer_id),
+            OutEvent::RelayClient(relay::client::Event::ReservationReqFailed {
+                relay_peer_id,
+                error,
+                ..
+            }) => eprintln!("[NAT] 中继 {} 预留失败: {:?}", relay_peer_id, error),
+            OutEvent::RelayServer(relay::Event::ReservationReqAccepted {
+                src_peer_id,
+                renewed: false,
+            }) => println!("[NAT] 为节点 {} 提供中继", src_peer_id),
+            OutEvent::Dcutr(dcutr::Event::DirectConnectionUpgradeSucceeded { remote_peer_id }) => {
+                println!("[NAT] 与 {} 打洞成功，已升级为直连", remote_peer_id)
+            }
+            OutEvent::Dcutr(dcutr::Event::DirectConnectionUpgradeFailed {
+                remote_peer_id,
+                error,
+            }) => eprintln!("[NAT] 与 {} 打洞失败，继续经中继通信: {:?}", remote_peer_id, error),
+            _ => {}
+        }
+    }
+
+    /// 记录对方的版本与监听地址（监听地址同时加入 DHT 路由表），以及对方观测到的本节点地址；
+    /// 观测地址由 identify 自动报告给 swarm 作为外部地址候选，供 AutoNAT 验证
+    fn on_identified(&mut self, peer: PeerId, info: identify::Info) {
+        if !self.observed_addrs.contains(&info.observed_addr) {
+            println!("[identify] {} 观测到本节点地址 {}", peer, info.observed_addr);
+            self.observed_addrs.insert(0, info.observed_addr);
+            self.observed_addrs.truncate(MAX_ADDRS_PER_PEER);
+        }
+        let mut listen_addrs = info.listen_addrs;
+        listen_addrs.truncate(MAX_IDENTIFY_ADDRS);
+        if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+            for addr in &listen_addrs {
+                kademlia.add_address(&peer, addr.clone());
+            }
+        }
+        let changed = self
+            .identified
+            .get(&peer)
+            .is_none_or(|known| known.agent_version != info.agent_version);
+        if changed {
+            println!("[identify] 节点 {} 版本 {}", peer, info.agent_version);
+        }
+        self.identified.insert(
+            peer,
+            IdentifiedPeer {
+                agent_version: info.agent_version,
+                listen_addrs,
+            },
+        );
+    }
+
+    /// identify 得到的节点信息
+    pub fn identified_peer(&self, peer: &str) -> Option<IdentifiedPeer> {
+        let peer = peer.parse::<PeerId>().ok()?;
+        self.identified.get(&peer).cloned()
+    }
+
+    /// 在所有配置的中继节点上监听 `/p2p-circuit` 地址，由 relay 客户端发起预留
+    fn reserve_relays(&mut self) {
+        if self.relay_reserved || self.circuit_relays.is_empty() {
+            return;
+        }
+        self.relay_reserved = true;
+        let addrs: Vec<Multiaddr> = self.circuit_relays.values().cloned().collect();
+        for addr in addrs {
+            if let Err(e) = self.swarm.listen_on(addr.clone().with(Protocol::P2pCircuit)) {
+                eprintln!("[NAT] 经中继 {} 监听失败: {:?}", addr, e);
+            }
+        }
+    }
+
+    /// 记录一条已通过入站检查的消息的延迟，返回该路径的分位数（样本不足时为 None）
+    pub fn observe_latency(
+        &self,
+        peer: &str,
+        path: LatencyPath,
+        sent_at_ms: u64,
+        now_ms: u64,
+    ) -> Option<LatencySummary> {
+        self.latency.observe(peer, path, sent_at_ms, now_ms)
+    }
+
+    /// 节点较快路径的延迟分位数
+    pub fn peer_latency(&self, peer: &str) -> Option<LatencySummary> {
+        self.latency.best(peer)
+    }
+
+    /// 发往该节点的消息是否应改走 gossip 而不是 QUIC 直连
+    pub fn prefers_gossip(&self, peer: &str) -> bool {
+        self.latency.prefers_gossip(peer)
+    }
+
+    pub fn allow_sparse_update(&self, session: &str) -> bool {
+        self.bandwidth
+            .write()
+            .get_mut(session)
+            .map(|budget| budget.allow_sparse())
+            .unwrap_or(false)
+    }
+
+    /// 会话上一个完整窗口的稀疏更新预算使用情况
+    pub fn sparse_budget_usage(&self, session: &str) -> Option<BudgetUsage> {
+        let mut budgets = self.bandwidth.write();
+        let budget = budgets.get_mut(session)?;
+        budget.rotate();
+        budget.last_usage
+    }
+
+    pub fn allow_dense_snapshot(&self, session: &str, bytes: usize) -> bool {
+        // 检查网络类型是否允许密集快照
+        let network_type = *self.network_type.read();
+        if !network_type.allows_dense_snapshot() {
+            return false;
+        }
+        self.bandwidth
+            .write()
+            .get_mut(session)
+            .map(|budget| budget.allow_dense(bytes))
+            .unwrap_or(false)
+    }
+
+    /// 更新网络类型
+    pub fn update_network_type(&self, network_type: NetworkType) {
+        *self.network_type.write() = network_type;
+        println!("[网络] 网络类型更新: {:?}", network_type);
+    }
+
+    /// 获取当前网络类型
+    pub fn network_type(&self) -> NetworkType {
+        *self.network_type.read()
+    }
+
+    /// QUIC accept 循环的任务工厂，交给 Supervisor 监督，进展报告给看门狗
+    pub fn quic_accept_task(&self, beat: Beat) -> Option<TaskFactory> {
+        let quic = self.quic.clone()?;
+        Some(Box::new(move || {
+            let quic = quic.clone();
+            let beat = beat.clone();
+            Box::pin(async move { quic.accept_loop(beat).await })
+        }))
+    }
+
+    pub fn send_policy(&self) -> SendPolicy {
+        self.send_policy
+    }
+
+    pub fn publish_queue_stats(&self) -> PublishQueueStats {
+        self.publish_queue.stats()
+    }
+
+    /// 重发排队中的消息，在有节点订阅主题时与每次发布前调用；仍没有节点时保留等待下次
+    pub fn flush_publish_queue(&mut self) {
+        if self.publish_queue.is_empty() {
+            return;
+        }
+        let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+        let sent = self.publish_queue.retry(Instant::now(), |topic, data| {
+            match gossipsub.publish(topic.clone(), data.to_vec()) {
+                Ok(_) | Err(PublishError::Duplicate) => Attempt::Sent,
+                Err(PublishError::InsufficientPeers) => Attempt::NoPeers,
+                Err(e) => {
+                    eprintln!("[发布队列] 重发失败，丢弃: {:?}", e);
+                    Attempt::Failed
                 }
             }
         });
-        
+        if sent > 0 {
+            println!("[发布队列] 已重发 {} 条排队消息", sent);
+        }
+    }
+
+    /// 更新 gossipsub 应用层评分，未启用节点评分或节点未连接时忽略
+    pub fn set_application_scores(&mut self, scores: &[(String, f64)]) {
+        let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+        for (peer, score) in scores {
+            if let Ok(peer_id) = peer.parse::<PeerId>() {
+                gossipsub.set_application_score(&peer_id, *score);
+            }
+        }
+    }
+
+    /// 上报节点对 gossip 消息的校验结果；消息已过期出缓存时忽略
+    pub fn report_validation(&mut self, message_id: &[u8], propagation: &str, verdict: Verdict) {
+        let Ok(peer_id) = propagation.parse::<PeerId>() else {
+            return;
+        };
+        let acceptance = match verdict {
+            Verdict::Pass => MessageAcceptance::Accept,
+            Verdict::Drop => MessageAcceptance::Ignore,
+            Verdict::Reject => MessageAcceptance::Reject,
+        };
+        let _ = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(&MessageId::new(message_id), &peer_id, acceptance);
+    }
+
+    pub fn quic_advertise(&self) -> Option<SocketAddr> {
+        self.quic_advertise
+    }
+
+    /// 心跳宣告的全部 QUIC 地址：首选的宣告地址，以及与之不同的具体绑定地址（如局域网地址）
+    pub fn quic_addrs(&self) -> Vec<SocketAddr> {
+        let Some(quic) = &self.quic else {
+            return Vec::new();
+        };
+        let bound = quic
+            .endpoint
+            .local_addr()
+            .ok()
+            .filter(|addr| !addr.ip().is_unspecified())
+            .map(netaddr::canonical);
+        let mut addrs: Vec<SocketAddr> = self.quic_advertise.into_iter().collect();
+        addrs.extend(bound.filter(|addr| !addrs.contains(addr)));
+        addrs
+    }
+
+    /// 其他节点宣告的地址是否可拨号：公网地址，或开启局域网发现时的任意地址
+    fn dialable(&self, addr: SocketAddr) -> bool {
+        self.lan_discovery || netaddr::is_global(addr.ip())
+    }
+
+    /// 在后台与尚无 QUIC 连接的主邻居建立实时通道，依次尝试对方宣告的各个公网地址
+    pub fn connect_realtime(&self, neighbors: Vec<(String, Vec<SocketAddr>)>) {
+        let Some(quic) = &self.quic else {
+            return;
+        };
+        if self.proxied {
+            return;
+        }
+        for (peer, addrs) in neighbors {
+            let addrs: Vec<SocketAddr> = addrs.into_iter().filter(|a| self.dialable(*a)).collect();
+            let Some(first) = addrs.first() else {
+                continue;
+            };
+            if !self.quic_usable(&peer) {
+                continue;
+            }
+            if quic.find_connection(netaddr::canonical(*first), Some(&peer)).is_some()
+                || !quic.dialing.lock().insert(peer.clone())
+            {
+                continue;
+            }
+            let quic = Arc::clone(quic);
+            tokio::spawn(async move {
+                for addr in addrs {
+                    match quic.connect(netaddr::canonical(addr), Some(&peer)).await {
+                        Ok(_) => {
+                            println!("[QUIC] 已与主邻居 {} 建立直连 ({})", peer, addr);
+                            break;
+                        }
+                        Err(err) => eprintln!("[QUIC] 连接主邻居 {} ({}) 失败: {:?}", peer, addr, err),
+                    }
+                }
+                quic.dialing.lock().remove(&peer);
+            });
+        }
+    }
+
+    /// 在后台经 QUIC 双向流向 `peer` 发出同步请求，结果作为 [`TransportEvent::SyncReply`] 交给节点
+    pub fn request_sync(&self, peer: &str, addr: SocketAddr, request: SyncRequest) {
+        let replies = self.sync_replies_tx.clone();
+        let peer = peer.to_string();
+        let Some(quic) = self.quic.as_ref().filter(|_| !self.proxied) else {
+            let _ = replies.send((peer, Err(SyncError::Unavailable("未启用 QUIC 直连".into()))));
+            return;
+        };
+        let quic = Arc::clone(quic);
+        tokio::spawn(async move {
+            let result = quic.request(netaddr::canonical(addr), &peer, &request).await;
+            let _ = replies.send((peer, result));
+        });
+    }
+
+    /// 心跳宣告的本节点编码能力
+    pub fn codec_names(&self) -> Vec<String> {
+        self.codecs.iter().map(|c| c.name().to_string()).collect()
+    }
+
+    /// 根据对方心跳宣告的能力协商直连编码
+    pub fn record_peer_codecs(&self, peer: &str, addr: Option<SocketAddr>, theirs: &[String]) {
+        if let Some(addr) = addr.filter(|a| self.dialable(*a)) {
+            self.announced_addrs
+                .write()
+                .insert(peer.to_string(), netaddr::canonical(addr));
+        }
+        if let Some(codec) = self.peer_codecs.write().record(&self.codecs, peer, addr, theirs) {
+            println!("[编码] 与 {} 的直连消息使用 {}", peer, codec.name());
+        }
+    }
+
+    /// 只发给指定节点 (PeerId, 心跳宣告的 QUIC 地址)，任一送达即返回 true
+    pub async fn send_realtime(&self, signed: &SignedGossip, targets: &[(String, SocketAddr)]) -> bool {
+        // 每种编码只编码一次
+        let mut encoded: HashMap<WireCodec, Vec<u8>> = HashMap::new();
+        let delivery = Delivery::of(signed);
+        let mut success = false;
+        for (peer, addr) in targets {
+            let codec = self.peer_codecs.read().for_peer(peer);
+            if let std::collections::hash_map::Entry::Vacant(entry) = encoded.entry(codec) {
+                match codec::encode(codec, signed) {
+                    Ok(bytes) => entry.insert(bytes),
+                    Err(err) => {
+                        eprintln!("[QUIC] 编码直连消息失败: {:?}", err);
+                        continue;
+                    }
+                };
+            }
+            let bytes = &encoded[&codec];
+            match self.send_bytes(*addr, Some(peer), bytes, delivery).await {
+                Ok(()) => success = true,
+                Err(err) => eprintln!("[QUIC] 发送到 {} ({}) 失败: {:?}", peer, addr, err),
+            }
+        }
+        success
+    }
+
+    /// 单播给指定节点：经 PeerId 索引复用到该节点的 QUIC 连接，没有连接时拨号其心跳宣告的地址；
+    /// 与 `send_realtime` 一样在 QUIC 不通时经 libp2p 连接回退
+    pub async fn send_to(&self, peer: &str, signed: &SignedGossip) -> Result<(), CommsError> {
+        let codec = self.peer_codecs.read().for_peer(peer);
+        let bytes = codec::encode(codec, signed).map_err(CommsError::quic)?;
+        let delivery = Delivery::of(signed);
+        let quic = self.quic.as_ref().filter(|_| !self.proxied);
+        if let Some(connection) = quic.and_then(|quic| quic.peer_connection(peer)) {
+            if QuicGateway::send_message(&connection, &bytes, delivery.datagram).await.is_ok() {
+                self.record_quic(peer, true);
+                return Ok(());
+            }
+        }
+        let addr = self
+            .announced_addrs
+            .read()
+            .get(peer)
+            .copied()
+            .ok_or_else(|| CommsError::Quic(format!("不知道 {} 的 QUIC 地址", peer)))?;
+        self.send_bytes(addr, Some(peer), &bytes, delivery).await
+    }
+
+    /// QUIC 可用时直连发送；发送失败或已判定到该节点受阻时改经 libp2p 连接（TCP / WebSocket）发给该节点。
+    /// 只有 QUIC 送达返回 Ok，交给回退路径的消息返回 [`CommsError::FallbackQueued`]
+    async fn send_bytes(
+        &self,
+        addr: SocketAddr,
+        peer: Option<&str>,
+        bytes: &[u8],
+        delivery: Delivery,
+    ) -> Result<(), CommsError> {
+        let fallback = peer
+            .and_then(|p| p.parse::<PeerId>().ok())
+            .filter(|_| self.tcp_fallback || self.proxied);
+        let key = peer.map_or_else(|| netaddr::canonical(addr).to_string(), str::to_string);
+        let usable = !self.proxied && (!self.tcp_fallback || self.quic_usable(&key));
+        let mut error = CommsError::QuicDisabled;
+        if let Some(quic) = self.quic.as_ref().filter(|_| usable) {
+            match quic.send_to(addr, peer, bytes, delivery).await {
+                Ok(()) => {
+                    self.record_quic(&key, true);
+                    return Ok(());
+                }
+                Err(err) => {
+                    self.record_quic(&key, false);
+                    error = CommsError::quic(err);
+                }
+            }
+        } else if self.proxied {
+            error = CommsError::Quic("经代理时不发起 QUIC 直连".into());
+        } else if self.quic.is_some() {
+            error = CommsError::Quic("到该节点的 QUIC 直连受阻".into());
+        }
+        let Some(peer) = fallback else {
+            return Err(error);
+        };
+        // 接收端与发送端都由本结构持有，入队不会失败；送达结果只在事件循环中得知
+        let _ = self.fallback_tx.send((peer, bytes.to_vec()));
+        Err(CommsError::FallbackQueued)
+    }
+
+    fn quic_usable(&self, key: &str) -> bool {
+        self.quic_reachability
+            .lock()
+            .get(key)
+            .is_none_or(QuicReachability::usable)
+    }
+
+    /// 记录到 `key` 的一次 QUIC 发送结果；条目达到上限时先清掉未受阻的条目，仍满则不再记录新节点
+    fn record_quic(&self, key: &str, ok: bool) {
+        let mut reachability = self.quic_reachability.lock();
+        let outcome = if ok {
+            reachability.remove(key).and_then(|mut r| r.record(true))
+        } else {
+            if !reachability.contains_key(key) && reachability.len() >= MAX_REACHABILITY_ENTRIES {
+                reachability.retain(|_, r| !r.usable());
+                if reachability.len() >= MAX_REACHABILITY_ENTRIES {
+                    return;
+                }
+            }
+            reachability.entry(key.to_string()).or_default().record(false)
+        };
+        drop(reachability);
+        match outcome {
+            Some(true) => println!(
+                "[传输] 到 {} 的 QUIC 直连连续 {} 次失败，判定受阻，直连消息改经 TCP 发送，{:?} 后重试 QUIC",
+                key, QUIC_BLOCKED_AFTER, QUIC_RETRY_AFTER
+            ),
+            Some(false) => println!("[传输] 到 {} 的 QUIC 直连恢复", key),
+            None => {}
+        }
+    }
+
+    /// 经 libp2p 连接到达的直连消息（对方 QUIC 不通时的回退路径），确认后与 QUIC 直连消息一样交给节点
+    fn handle_direct_event(
+        &mut self,
+        event: request_response::Event<Vec<u8>, ()>,
+    ) -> Option<TransportEvent> {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Request { request, channel, .. },
+            } => {
+                self.touch_peer(peer);
+                let _ = self.swarm.behaviour_mut().direct.send_response(channel, ());
+                match codec::decode(&request, MAX_DIRECT_MESSAGE_BYTES) {
+                    Ok(signed) => {
+                        return Some(TransportEvent::Direct {
+                            signed,
+                            from: peer.to_string(),
+                        })
+                    }
+                    Err(err) => eprintln!("[传输] 无法解析 {} 经 TCP 发来的直连消息: {:?}", peer, err),
+                }
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                eprintln!("[传输] 经 TCP 向 {} 发送直连消息失败: {:?}", peer, error);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// 控制消息：发给连接池中的所有连接（接收方不确定，使用与 gossip 相同的编码）
+    pub async fn broadcast_realtime(&self, signed: &SignedGossip) -> bool {
+        if let Some(quic) = &self.quic {
+            return quic.broadcast(signed, self.gossip_codec).await;
+        }
+        false
+    }
+}
+
+impl CommsHandle {
+    /// 处理 swarm 事件：节点发现、连接与监听地址在内部处理，只把 gossip 消息交给节点
+    fn handle_swarm_event<E>(&mut self, event: SwarmEvent<OutEvent, E>) -> Option<TransportEvent> {
+        match event {
+            SwarmEvent::Behaviour(OutEvent::Gossipsub(GossipsubEvent::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => {
+                self.touch_peer(propagation_source);
+                let propagation = propagation_source.to_string();
+                let decoded = codec::decode(&message.data, MAX_DIRECT_MESSAGE_BYTES).ok();
+                // 无法解码、或信封中的会话与接收主题不一致的消息直接拒绝
+                let Some(signed) = decoded.filter(|signed| {
+                    self.session_for_topic(&message.topic) == Some(signed.session.as_str())
+                }) else {
+                    self.report_validation(&message_id.0, &propagation, Verdict::Reject);
+                    return None;
+                };
+                return Some(TransportEvent::Gossip {
+                    signed,
+                    source: message.source.map(|p| p.to_string()),
+                    propagation,
+                    message_id: message_id.0,
+                });
+            }
+            SwarmEvent::Behaviour(OutEvent::Gossipsub(GossipsubEvent::Subscribed { .. })) => {
+                self.flush_publish_queue();
+            }
+            SwarmEvent::Behaviour(OutEvent::Mdns(event)) => self.handle_mdns_event(event),
+            SwarmEvent::Behaviour(OutEvent::Rendezvous(event)) => self.handle_rendezvous_event(event),
+            SwarmEvent::Behaviour(OutEvent::RendezvousServer(
+                rendezvous::server::Event::PeerRegistered { peer, registration },
+            )) => {
+                println!("[Rendezvous] 节点 {} 注册命名空间 {}", peer, registration.namespace);
+            }
+            SwarmEvent::Behaviour(OutEvent::Kademlia(event)) => self.handle_dht_event(event),
+            SwarmEvent::Behaviour(OutEvent::Direct(event)) => return self.handle_direct_event(event),
+            SwarmEvent::Behaviour(event) => self.handle_nat_event(event),
+            SwarmEvent::ConnectionEstablished {
+                peer_id, endpoint, ..
+            } => {
+                self.peer_activity
+                    .entry(peer_id)
+                    .or_insert((endpoint.is_listener(), Instant::now()));
+                let dialed = match endpoint {
+                    ConnectedPoint::Dialer { address, .. } => Some(address),
+                    ConnectedPoint::Listener { .. } => None,
+                };
+                self.on_connection_established(peer_id, dialed);
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.peer_activity.remove(&peer_id);
+            }
+            SwarmEvent::NewListenAddr { address, .. } => {
+                println!("监听地址: {address}");
+                self.on_new_listen_addr(address);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[async_trait(?Send)]
+impl GossipBackend for CommsHandle {
+    /// 订阅会话主题并分配带宽子预算（默认会话只调整预算比例）
+    fn register_session(&mut self, session: &str, topic: &str, share: f32) -> Result<()> {
+        if !self.session_topics.contains_key(session) {
+            let topic = Topic::new(topic.to_string());
+            self.swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&topic)
+                .map_err(CommsError::from)?;
+            self.session_topics.insert(session.to_string(), topic);
+        }
+        self.bandwidth.write().insert(
+            session.to_string(),
+            BandwidthBudget::new(&self.bandwidth_config, share),
+        );
+        Ok(())
+    }
+
+    fn publish(&mut self, signed: &SignedGossip) -> Result<()> {
+        let topic = self
+            .session_topics
+            .get(&signed.session)
+            .cloned()
+            .ok_or_else(|| CommsError::UnknownSession(signed.session.clone()))?;
+        if let Some(relay) = &self.relay {
+            relay.mirror(signed);
+        }
+        let data = codec::encode(self.gossip_codec, signed)
+            .map_err(|e| CommsError::Codec(format!("{e:#}")))?;
+        // 先发排队的消息，保持发布顺序
+        self.flush_publish_queue();
+        if !self.publish_queue.is_empty() {
+            self.publish_queue.push(topic.hash(), data, Instant::now());
+            return Ok(());
+        }
+        match self.swarm.behaviour_mut().gossipsub.publish(topic.clone(), data.clone()) {
+            // 已镜像到中继的消息同样排队，P2P 节点出现后再经 gossipsub 发出
+            Err(PublishError::InsufficientPeers) => {
+                println!("[发布队列] 暂无 gossipsub 节点，消息排队等待重发");
+                self.publish_queue.push(topic.hash(), data, Instant::now());
+                Ok(())
+            }
+            result => result.map(|_| ()).map_err(|e| CommsError::from(e).into()),
+        }
+    }
+
+    /// 通过 QUIC 把消息直接发给指定节点（如训练者 -> 聚合者），使用按地址协商的编码；
+    /// QUIC 不通时经 libp2p 连接发给曾在该地址完成证书握手的节点
+    async fn send_direct(&self, addr: SocketAddr, signed: &SignedGossip) -> Result<()> {
+        let peer = self.quic.as_ref().and_then(|quic| quic.addr_peer(addr));
+        let codec = match &peer {
+            Some(peer) => self.peer_codecs.read().for_peer(peer),
+            None => self.peer_codecs.read().for_addr(&addr),
+        };
+        let bytes = codec::encode(codec, signed).map_err(CommsError::quic)?;
+        Ok(self.send_bytes(addr, peer.as_deref(), &bytes, Delivery::of(signed)).await?)
+    }
+
+    /// 订阅了本节点主题的 gossipsub 节点数量，已连接的中继算作一个节点
+    fn gossip_peer_count(&self) -> usize {
+        let topic_hash = self.topic.hash();
+        let relay = self.relay.as_ref().is_some_and(|r| r.is_connected());
+        self.swarm
+            .behaviour()
+            .gossipsub
+            .all_peers()
+            .filter(|(_, topics)| topics.contains(&&topic_hash))
+            .count()
+            + usize::from(relay)
+    }
+
+    async fn next_event(&mut self) -> Option<TransportEvent> {
+        loop {
+            tokio::select! {
+                event = self.swarm.select_next_some() => {
+                    if let Some(event) = self.handle_swarm_event(event) {
+                        return Some(event);
+                    }
+                }
+                direct = self.direct_rx.recv() => {
+                    return direct.map(|(from, signed)| TransportEvent::Direct { signed, from });
+                }
+                Some(request) = self.sync_requests.recv() => return Some(TransportEvent::SyncRequest(request)),
+                Some((peer, result)) = self.sync_replies_rx.recv() => {
+                    return Some(TransportEvent::SyncReply { peer, result });
+                }
+                Some((peer, bytes)) = self.fallback_rx.recv() => {
+                    self.swarm.behaviour_mut().direct.send_request(&peer, bytes);
+                }
+                Some(signed) = recv_relay(&mut self.relay) => {
+                    // 中继回传的本节点消息与未加入会话的消息直接丢弃；中继无法证明消息来源，源留空
+                    let sender = signed.payload.sender();
+                    if sender != self.peer_id.to_string() && self.session_topics.contains_key(&signed.session) {
+                        return Some(TransportEvent::Gossip {
+                            signed,
+                            source: None,
+                            propagation: "relay".to_string(),
+                            message_id: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl NodeBackend for CommsHandle {
+    fn local_peer_id(&self) -> String {
+        self.peer_id.to_string()
+    }
+
+    async fn send_to(&self, peer: &str, signed: &SignedGossip) -> Result<()> {
+        Ok(CommsHandle::send_to(self, peer, signed).await?)
+    }
+
+    fn network_type(&self) -> NetworkType {
+        CommsHandle::network_type(self)
+    }
+
+    fn update_network_type(&self, network_type: NetworkType) {
+        CommsHandle::update_network_type(self, network_type)
+    }
+
+    async fn shutdown(&mut self, grace: Duration) {
+        CommsHandle::shutdown(self, grace).await
+    }
+
+    fn set_bandwidth(&mut self, config: BandwidthBudgetConfig) {
+        CommsHandle::set_bandwidth(self, config)
+    }
+
+    fn allow_sparse_update(&self, session: &str) -> bool {
+        CommsHandle::allow_sparse_update(self, session)
+    }
+
+    fn allow_dense_snapshot(&self, session: &str, bytes: usize) -> bool {
+        CommsHandle::allow_dense_snapshot(self, session, bytes)
+    }
+
+    fn sparse_budget_usage(&self, session: &str) -> Option<BudgetUsage> {
+        CommsHandle::sparse_budget_usage(self, session)
+    }
+
+    fn send_policy(&self) -> SendPolicy {
+        CommsHandle::send_policy(self)
+    }
+
+    async fn send_realtime(&self, signed: &SignedGossip, targets: &[(String, SocketAddr)]) -> bool {
+        CommsHandle::send_realtime(self, signed, targets).await
+    }
+
+    async fn broadcast_realtime(&self, signed: &SignedGossip) -> bool {
+        CommsHandle::broadcast_realtime(self, signed).await
+    }
+
+    fn quic_advertise(&self) -> Option<SocketAddr> {
+        CommsHandle::quic_advertise(self)
+    }
+
+    fn quic_accept_task(&self, beat: Beat) -> Option<TaskFactory> {
+        CommsHandle::quic_accept_task(self, beat)
+    }
+
+    fn quic_addrs(&self) -> Vec<SocketAddr> {
+        CommsHandle::quic_addrs(self)
+    }
+
+    fn connect_realtime(&self, neighbors: Vec<(String, Vec<SocketAddr>)>) {
+        CommsHandle::connect_realtime(self, neighbors)
+    }
+
+    fn request_sync(&self, peer: &str, addr: SocketAddr, request: SyncRequest) {
+        CommsHandle::request_sync(self, peer, addr, request)
+    }
+
+    fn codec_names(&self) -> Vec<String> {
+        CommsHandle::codec_names(self)
+    }
+
+    fn record_peer_codecs(&self, peer: &str, addr: Option<SocketAddr>, theirs: &[String]) {
+        CommsHandle::record_peer_codecs(self, peer, addr, theirs)
+    }
+
+    fn prefers_gossip(&self, peer: &str) -> bool {
+        CommsHandle::prefers_gossip(self, peer)
+    }
+
+    fn observe_latency(
+        &self,
+        peer: &str,
+        path: LatencyPath,
+        sent_at_ms: u64,
+        now_ms: u64,
+    ) -> Option<LatencySummary> {
+        CommsHandle::observe_latency(self, peer, path, sent_at_ms, now_ms)
+    }
+
+    fn peer_latency(&self, peer: &str) -> Option<LatencySummary> {
+        CommsHandle::peer_latency(self, peer)
+    }
+
+    fn flush_publish_queue(&mut self) {
+        CommsHandle::flush_publish_queue(self)
+    }
+
+    fn publish_queue_stats(&self) -> PublishQueueStats {
+        CommsHandle::publish_queue_stats(self)
+    }
+
+    fn set_application_scores(&mut self, scores: &[(String, f64)]) {
+        CommsHandle::set_application_scores(self, scores)
+    }
+
+    fn report_validation(&mut self, message_id: &[u8], propagation: &str, verdict: Verdict) {
+        CommsHandle::report_validation(self, message_id, propagation, verdict)
+    }
+
+    fn set_preferred_peers(&mut self, ranked: Vec<String>) {
+        CommsHandle::set_preferred_peers(self, ranked)
+    }
+
+    fn prune_connections(&mut self) {
+        CommsHandle::prune_connections(self)
+    }
+
+    fn next_bootstrap_retry(&self) -> Option<Instant> {
+        CommsHandle::next_bootstrap_retry(self)
+    }
+
+    fn retry_bootstrap(&mut self) {
+        CommsHandle::retry_bootstrap(self)
+    }
+
+    fn refresh_rendezvous(&mut self) {
+        CommsHandle::refresh_rendezvous(self)
+    }
+
+    fn refresh_dht(&mut self) {
+        CommsHandle::refresh_dht(self)
+    }
+
+    fn reachable_addrs(&self) -> Vec<String> {
+        CommsHandle::reachable_addrs(self)
+    }
+
+    fn learn_peer_addrs(&mut self, peer: &str, addrs: &[String]) {
+        CommsHandle::learn_peer_addrs(self, peer, addrs)
+    }
+
+    fn identified_peer(&self, peer: &str) -> Option<IdentifiedPeer> {
+        CommsHandle::identified_peer(self, peer)
+    }
+
+    fn export_address_book(&self) -> Vec<PeerAddresses> {
+        CommsHandle::export_address_book(self)
+    }
+
+    fn restore_address_book(&mut self, entries: Vec<PeerAddresses>) {
+        CommsHandle::restore_address_book(self, entries)
+    }
+}
+
+/// circuit relay 客户端与传输栈中启用的 TCP（含 DNS）、WebSocket 传输，各自经 noise 认证、yamux 多路复用
+fn build_transport(
+    keypair: &identity::Keypair,
+    relay_transport: relay::client::Transport,
+    stack: &[TransportKind],
+    proxy: &ProxyConfig,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    if stack.is_empty() {
+        return Err(anyhow!("传输栈为空，至少启用 tcp 或 ws"));
+    }
+    let mut transport = authenticate(relay_transport, keypair)?;
+    for kind in stack {
+        let tcp = tcp::tokio::Transport::new(tcp::Config::new().nodelay(true));
+        // 经代理时域名交给代理解析，不经本机 DNS
+        let next = match kind {
+            TransportKind::Tcp if proxy.enabled() => {
+                authenticate(Socks5Transport::new(proxy.clone(), tcp), keypair)?
+            }
+            TransportKind::WebSocket if proxy.enabled() => authenticate(
+                websocket::WsConfig::new(Socks5Transport::new(proxy.clone(), tcp)),
+                keypair,
+            )?,
+            TransportKind::Tcp => authenticate(dns::TokioDnsConfig::system(tcp)?, keypair)?,
+            TransportKind::WebSocket => authenticate(
+                websocket::WsConfig::new(dns::TokioDnsConfig::system(tcp)?),
+                keypair,
+            )?,
+        };
+        transport = transport
+            .or_transport(next)
+            .map(|either, _| match either {
+                futures::future::Either::Left(output) | futures::future::Either::Right(output) => output,
+            })
+            .boxed();
+    }
+    Ok(transport)
+}
+
+fn authenticate<T>(transport: T, keypair: &identity::Keypair) -> Result<Boxed<(PeerId, StreamMuxerBox)>>
+where
+    T: Transport + Send + Unpin + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Error: Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
+    Ok(transport
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(keypair)?)
+        .multiplex(yamux::Config::default())
+        .timeout(Duration::from_secs(20))
+        .boxed())
+}
+
+/// 未配置中继时永远挂起
+async fn recv_relay(relay: &mut Option<RelayClient>) -> Option<SignedGossip> {
+    match relay {
+        Some(relay) => relay.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 读取身份密钥文件，不存在时生成 ed25519 密钥并写入（仅所有者可读）
+pub fn load_or_create_identity(path: &Path) -> Result<identity::Keypair, CommsError> {
+    let invalid = |reason: String| CommsError::InvalidIdentity {
+        path: path.to_path_buf(),
+        reason,
+    };
+    if path.exists() {
+        let bytes = std::fs::read(path)?;
+        let keypair = identity::Keypair::from_protobuf_encoding(&bytes)
+            .map_err(|e| invalid(e.to_string()))?;
+        println!("[身份] 从 {} 加载 PeerId {}", path.display(), PeerId::from(keypair.public()));
+        return Ok(keypair);
+    }
+    let keypair = identity::Keypair::generate_ed25519();
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .map_err(|e| invalid(format!("无法编码身份密钥: {}", e)))?;
+    write_secret(path, &bytes).map_err(std::io::Error::other)?;
+    println!("[身份] 生成新的 PeerId {}，密钥保存到 {}", PeerId::from(keypair.public()), path.display());
+    Ok(keypair)
+}
+
+/// 身份文件对应的 PeerId，未指定身份文件时随机生成（不连接网络的重放节点使用）
+pub fn configured_peer_id(config: &CommsConfig) -> Result<PeerId, CommsError> {
+    Ok(match &config.identity_path {
+        Some(path) => PeerId::from(load_or_create_identity(path)?.public()),
+        None => PeerId::random(),
+    })
+}
+
+/// 独立的 QUIC 直连接收端，不创建 libp2p swarm（热备节点接管前使用）
+pub struct DirectListener {
+    quic: Arc<QuicGateway>,
+    accept: tokio::task::JoinHandle<Result<()>>,
+    rx: mpsc::UnboundedReceiver<(String, SignedGossip)>,
+}
+
+impl DirectListener {
+    pub fn bind(addr: SocketAddr, identity: &identity::Keypair) -> Result<Self, CommsError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        // 热备节点不应答同步请求
+        let quic = Arc::new(QuicGateway::new(addr, true, tx, None, identity).map_err(CommsError::quic)?);
+        let gateway = quic.clone();
+        let accept = tokio::spawn(async move { gateway.accept_loop(Beat::default()).await });
+        Ok(Self { quic, accept, rx })
+    }
+
+    /// 下一条直连消息；复制消息由签名校验来源，不需要发送方
+    pub async fn recv(&mut self) -> Option<SignedGossip> {
+        self.rx.recv().await.map(|(_, signed)| signed)
+    }
+
+    /// 停止接收并释放监听端口
+    pub fn close(self) {
+        self.accept.abort();
+        self.quic.endpoint.close(0u32.into(), b"closed");
+    }
+}
+
+/// 取出地址末尾 `/p2p/<PeerId>` 中的节点 id
+fn peer_id_of(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|p| match p {
+        Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+        _ => None,
+    })
+}
+
+fn require_peer_id(kind: &'static str, addr: &Multiaddr) -> Result<PeerId, CommsError> {
+    peer_id_of(addr).ok_or_else(|| CommsError::MissingPeerId {
+        kind,
+        addr: addr.clone(),
+    })
+}
+
+struct QuicGateway {
+    endpoint: Endpoint,
+    connections: Arc<RwLock<Vec<ConnectionInfo>>>,
+    /// PeerId -> 连接池中到该节点的连接，单播时按 PeerId 直接取用；随连接移出连接池同步清理
+    by_peer: Arc<RwLock<HashMap<String, quinn::Connection>>>,
+    inbound: mpsc::UnboundedSender<(String, SignedGossip)>,
+    /// 同步请求交给节点的通道，None 时不接受双向流
+    requests: Option<mpsc::UnboundedSender<InboundRequest>>,
+    /// 本节点对外宣告的地址，连向它等于连自己
+    advertise: Option<SocketAddr>,
+    manager: Arc<RwLock<ConnectionManager>>,
+    /// 拨号地址 -> 等待重连的出站连接
+    redial: Arc<Mutex<HashMap<SocketAddr, Redial>>>,
+    /// 正在后台建立实时通道的节点
+    dialing: Mutex<HashSet<String>>,
+    /// 与节点身份绑定的证书，拨号时按对端 PeerId 生成校验配置
+    cert: NodeCert,
+    /// 各节点的 TLS 会话票据，重连时恢复会话（可发送 0-RTT 数据）
+    tickets: Arc<ClientSessionMemoryCache>,
+    /// 对端地址 -> 在该地址上完成证书握手的节点，QUIC 不通时按地址找到对应的 libp2p 节点
+    addr_peers: RwLock<HashMap<SocketAddr, String>>,
+}
+
+/// 一条直连消息的发送方式
+#[derive(Clone, Copy, Debug)]
+struct Delivery {
+    /// 装得进一个数据报时以不可靠数据报发出
+    datagram: bool,
+    /// 没有现成连接时以 0-RTT 随恢复的会话立即发出
+    early: bool,
+}
+
+impl Delivery {
+    fn of(signed: &SignedGossip) -> Self {
+        Self {
+            datagram: signed.payload.is_loss_tolerant(),
+            early: signed.payload.is_replay_safe(),
+        }
+    }
+}
+
+/// 异常断开、等待重连的出站连接
+struct Redial {
+    peer: Option<String>,
+    at: Instant,
+    delay: Duration,
+    attempts: u32,
+}
+
+
+fn quic_transport_config() -> Arc<quinn::TransportConfig> {
+    let mut transport = quinn::TransportConfig::default();
+    transport.keep_alive_interval(Some(QUIC_KEEPALIVE_INTERVAL));
+    transport.max_idle_timeout(QUIC_IDLE_TIMEOUT.try_into().ok());
+    Arc::new(transport)
+}
+
+struct ConnectionInfo {
+    connection: quinn::Connection,
+    /// 连接对端的 PeerId，取自握手时校验过的对端证书
+    peer: Option<String>,
+    /// 对端发起的连接
+    inbound: bool,
+    /// 最近一次成功收发消息的时间
+    last_health_check: Instant,
+    consecutive_failures: u32,
+}
+
+impl ConnectionInfo {
+    fn new(connection: quinn::Connection, inbound: bool) -> Self {
+        Self {
+            connection,
+            peer: None,
+            inbound,
+            last_health_check: Instant::now(),
+            consecutive_failures: 0,
+        }
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.consecutive_failures < 3
+    }
+
+    fn mark_failure(&mut self) {
+        self.consecutive_failures += 1;
+    }
+
+    fn mark_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.last_health_check = Instant::now();
+    }
+}
+
+impl QuicGateway {
+    fn new(
+        bind: SocketAddr,
+        dual_stack: bool,
+        inbound: mpsc::UnboundedSender<(String, SignedGossip)>,
+        requests: Option<mpsc::UnboundedSender<InboundRequest>>,
+        identity: &identity::Keypair,
+    ) -> Result<Self> {
+        let cert = NodeCert::from_identity(identity)?;
+        let mut server_config = ServerConfig::with_crypto(Arc::new(cert.server_config()?));
+        server_config.transport = quic_transport_config();
+        let runtime = quinn::default_runtime().ok_or_else(|| anyhow!("QUIC 缺少异步运行时"))?;
+        let socket = netaddr::bind_udp(bind, dual_stack)?;
+        let endpoint =
+            Endpoint::new(quinn::EndpointConfig::default(), Some(server_config), socket, runtime)?;
+        let connections = Arc::new(RwLock::new(Vec::<ConnectionInfo>::new()));
+        let by_peer = Arc::new(RwLock::new(HashMap::new()));
+
         // 启动连接健康检查任务
         let health_check_connections = connections.clone();
+        let health_check_peers = Arc::clone(&by_peer);
         tokio::spawn(async move {
             let mut health_check_interval = interval(Duration::from_secs(30));
             loop {
@@ -302,57 +2543,497 @@ impl QuicGateway {
                     }
                     info.is_healthy()
                 });
+                Self::forget_removed(&conns, &health_check_peers);
             }
         });
         Ok(Self {
             endpoint,
             connections,
+            by_peer,
+            inbound,
+            requests,
+            advertise: None,
+            manager: Arc::new(RwLock::new(ConnectionManager::new(Default::default()))),
+            redial: Arc::new(Mutex::new(HashMap::new())),
+            dialing: Mutex::new(HashSet::new()),
+            cert,
+            tickets: Arc::new(ClientSessionMemoryCache::new(SESSION_TICKETS)),
+            addr_peers: RwLock::new(HashMap::new()),
         })
     }
 
-    async fn connect(&self, addr: SocketAddr) -> Result<()> {
-        match self.endpoint.connect(addr, "ggs-quic") {
-            Ok(connecting) => match connecting.await {
-                Ok(connection) => {
-                    self.connections
-                        .write()
-                        .push(ConnectionInfo::new(connection));
-                    Ok(())
-                }
-                Err(err) => Err(err.into()),
-            },
-            Err(err) => Err(err.into()),
+    /// 按退避间隔重连异常断开的出站连接，网关释放后退出
+    async fn redial_loop(gateway: Weak<Self>) {
+        let mut tick = interval(Duration::from_secs(1));
+        loop {
+            tick.tick().await;
+            let Some(gateway) = gateway.upgrade() else {
+                return;
+            };
+            gateway.redial_due().await;
         }
     }
 
-    /// 尝试重连所有失效的连接
-    #[allow(dead_code)]
-    pub async fn reconnect_failed(&self, addrs: &[SocketAddr]) {
-        let mut conns = self.connections.write();
-        let failed_count = conns
+    async fn redial_due(&self) {
+        let now = Instant::now();
+        let due: Vec<(SocketAddr, Option<String>)> = self
+            .redial
+            .lock()
             .iter()
-            .filter(|info| !info.is_healthy())
-            .count();
-        
-        if failed_count > 0 {
-            println!("[QUIC] 检测到 {} 个失效连接，尝试重连", failed_count);
-            // 移除失效连接
-            conns.retain(|info| info.is_healthy());
-            
-            // 尝试重新连接
-            for addr in addrs {
-                if let Err(e) = self.connect(*addr).await {
-                    eprintln!("[QUIC] 重连失败 {}: {:?}", addr, e);
+            .filter(|(_, r)| r.at <= now)
+            .map(|(addr, r)| (*addr, r.peer.clone()))
+            .collect();
+        for (addr, peer) in due {
+            // 期间发送时已按需重连，或对端已连入
+            if self.find_connection(addr, peer.as_deref()).is_some() {
+                self.redial.lock().remove(&addr);
+                continue;
+            }
+            let result = self.connect(addr, peer.as_deref()).await;
+            let mut redial = self.redial.lock();
+            let Err(err) = result else {
+                redial.remove(&addr);
+                println!("[QUIC] 已重连 {}", addr);
+                continue;
+            };
+            let Some(entry) = redial.get_mut(&addr) else {
+                continue;
+            };
+            entry.attempts += 1;
+            if entry.attempts >= QUIC_REDIAL_ATTEMPTS {
+                redial.remove(&addr);
+                println!("[QUIC] 重连 {} 连续失败 {} 次，放弃", addr, QUIC_REDIAL_ATTEMPTS);
+                continue;
+            }
+            entry.delay = (entry.delay * 2).min(QUIC_REDIAL_MAX);
+            entry.at = Instant::now() + entry.delay;
+            eprintln!("[QUIC] 重连 {} 失败: {:?}，{:?} 后重试", addr, err, entry.delay);
+        }
+    }
+
+    /// 通知对端关闭所有连接，并在限定时间内等待关闭帧发出
+    async fn close(&self, timeout: Duration) {
+        self.connections.write().clear();
+        self.by_peer.write().clear();
+        self.endpoint.close(0u32.into(), b"shutdown");
+        if tokio::time::timeout(timeout, self.endpoint.wait_idle()).await.is_err() {
+            eprintln!("[退出] 等待 QUIC 连接关闭超时");
+        }
+    }
+
+    /// 接受入站连接，直到 endpoint 关闭（由 Supervisor 负责重启）
+    async fn accept_loop(&self, beat: Beat) -> Result<()> {
+        loop {
+            beat.beat();
+            // 空闲等待时也定期报告，握手卡住才会被看门狗视为停滞
+            let incoming = tokio::select! {
+                incoming = self.endpoint.accept() => incoming,
+                _ = tokio::time::sleep(Duration::from_secs(10)) => continue,
+            };
+            match incoming {
+                Some(connecting) => match connecting.await {
+                    Ok(conn) => {
+                        let peer = certs::connection_peer(&conn).map(|p| p.to_string());
+                        let id = peer.clone().unwrap_or_else(|| conn.remote_address().to_string());
+                        if !self.make_room(&id, true, peer.is_some()) {
+                            conn.close(0u32.into(), b"busy");
+                            continue;
+                        }
+                        self.spawn_reader(conn.clone(), None);
+                        self.spawn_responder(conn.clone());
+                        self.bind_verified(&conn);
+                        let mut info = ConnectionInfo::new(conn, true);
+                        info.peer = peer;
+                        self.track(info);
+                    }
+                    Err(err) => eprintln!("[QUIC] accept error: {err:?}"),
+                },
+                None => return Err(anyhow!("QUIC endpoint 已关闭")),
+            }
+        }
+    }
+
+    /// 读取对端打开的单向流与发来的数据报，每个流或数据报承载一条编码后的 SignedGossip；
+    /// 连接超时或被重置时移出连接池，`redial_to` 给出拨号地址的出站连接安排重连
+    fn spawn_reader(&self, connection: quinn::Connection, redial_to: Option<SocketAddr>) {
+        let inbound = self.inbound.clone();
+        let connections = Arc::clone(&self.connections);
+        let redial = Arc::clone(&self.redial);
+        let by_peer = Arc::clone(&self.by_peer);
+        // 消息来源记为对端证书身份，没有证书时为远端地址，不采信载荷自称的发送者
+        let from = certs::connection_peer(&connection)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| netaddr::canonical(connection.remote_address()).to_string());
+        // 心跳、探测等小消息走数据报，连接关闭时随之退出
+        {
+            let connection = connection.clone();
+            let inbound = inbound.clone();
+            let connections = Arc::clone(&connections);
+            let from = from.clone();
+            tokio::spawn(async move {
+                while let Ok(bytes) = connection.read_datagram().await {
+                    if !Self::deliver(&connection, &connections, &inbound, &from, &bytes) {
+                        return;
+                    }
+                }
+            });
+        }
+        tokio::spawn(async move {
+            while let Ok(mut recv) = connection.accept_uni().await {
+                let bytes = match recv.read_to_end(MAX_DIRECT_MESSAGE_BYTES).await {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        eprintln!("[QUIC] 读取直连消息失败: {err:?}");
+                        continue;
+                    }
+                };
+                if !Self::deliver(&connection, &connections, &inbound, &from, &bytes) {
+                    return;
                 }
             }
+            // 本节点主动关闭（清理、退出）或对端正常关闭的连接不重连
+            let reason = connection.close_reason();
+            if matches!(
+                reason,
+                Some(quinn::ConnectionError::LocallyClosed | quinn::ConnectionError::ApplicationClosed(_))
+            ) {
+                return;
+            }
+            let id = connection.stable_id();
+            let peer = {
+                let mut conns = connections.write();
+                let peer = conns
+                    .iter()
+                    .find(|info| info.connection.stable_id() == id)
+                    .and_then(|info| info.peer.clone());
+                conns.retain(|info| info.connection.stable_id() != id);
+                Self::forget_removed(&conns, &by_peer);
+                peer
+            };
+            let Some(addr) = redial_to else {
+                return;
+            };
+            println!("[QUIC] 与 {} 的连接断开（{:?}），{:?} 后重连", addr, reason, QUIC_REDIAL_INITIAL);
+            redial.lock().entry(addr).or_insert(Redial {
+                peer,
+                at: Instant::now() + QUIC_REDIAL_INITIAL,
+                delay: QUIC_REDIAL_INITIAL,
+                attempts: 0,
+            });
+        });
+    }
+
+    /// 解码一条直连消息交给节点并记为该连接的一次成功通信；节点已退出时返回 false
+    fn deliver(
+        connection: &quinn::Connection,
+        connections: &RwLock<Vec<ConnectionInfo>>,
+        inbound: &mpsc::UnboundedSender<(String, SignedGossip)>,
+        from: &str,
+        bytes: &[u8],
+    ) -> bool {
+        match codec::decode(bytes, MAX_DIRECT_MESSAGE_BYTES) {
+            Ok(signed) => {
+                let id = connection.stable_id();
+                if let Some(info) = connections
+                    .write()
+                    .iter_mut()
+                    .find(|info| info.connection.stable_id() == id)
+                {
+                    info.mark_success();
+                }
+                inbound.send((from.to_string(), signed)).is_ok()
+            }
+            Err(err) => {
+                eprintln!("[QUIC] 无法解析直连消息: {err:?}");
+                true
+            }
+        }
+    }
+
+    /// 应答对端在双向流上发来的同步请求，每个流一次请求；对端身份未知的连接不应答
+    fn spawn_responder(&self, connection: quinn::Connection) {
+        let Some(requests) = self.requests.clone() else {
+            return;
+        };
+        let Some(peer) = certs::connection_peer(&connection) else {
+            return;
+        };
+        tokio::spawn(async move {
+            while let Ok((send, recv)) = connection.accept_bi().await {
+                let requests = requests.clone();
+                let peer = peer.to_string();
+                tokio::spawn(async move {
+                    if let Err(err) = sync::serve(peer.clone(), send, recv, requests).await {
+                        eprintln!("[同步] 应答 {} 的请求失败: {}", peer, err);
+                    }
+                });
+            }
+        });
+    }
+
+    /// 经到 `peer` 的连接（没有则新建）打开双向流发出同步请求
+    async fn request(&self, addr: SocketAddr, peer: &str, request: &SyncRequest) -> Result<SyncResponse, SyncError> {
+        let unavailable = |err: anyhow::Error| SyncError::Unavailable(err.to_string());
+        let connection = match self.find_connection(addr, Some(peer)) {
+            Some(connection) => connection,
+            None => self.connect(addr, Some(peer)).await.map_err(unavailable)?,
+        };
+        let (send, recv) = connection.open_bi().await.map_err(|e| unavailable(e.into()))?;
+        sync::request(send, recv, request).await
+    }
+
+    /// 连接表中各连接的标识：已知 PeerId 时用 PeerId，否则用远端地址
+    fn conn_ids(infos: &[ConnectionInfo]) -> Vec<String> {
+        infos
+            .iter()
+            .map(|info| {
+                info.peer
+                    .clone()
+                    .unwrap_or_else(|| info.connection.remote_address().to_string())
+            })
+            .collect()
+    }
+
+    fn as_conns<'a>(infos: &[ConnectionInfo], ids: &'a [String]) -> Vec<Conn<'a>> {
+        infos
+            .iter()
+            .zip(ids)
+            .map(|(info, id)| Conn {
+                id,
+                inbound: info.inbound,
+                last_active: info.last_health_check,
+                protected: false,
+            })
+            .collect()
+    }
+
+    /// 该方向已满时为证书身份已确认（`admitted`）的连接断开一条价值更低的连接；腾不出位置返回 false
+    fn make_room(&self, id: &str, inbound: bool, admitted: bool) -> bool {
+        let mut infos = self.connections.write();
+        let ids = Self::conn_ids(&infos);
+        let conns = Self::as_conns(&infos, &ids);
+        match self.manager.read().make_room(&conns, id, inbound, admitted) {
+            Some(None) => true,
+            Some(Some(index)) => {
+                let evicted = infos.remove(index);
+                evicted.connection.close(0u32.into(), b"evicted");
+                Self::forget_removed(&infos, &self.by_peer);
+                true
+            }
+            None => {
+                println!("[QUIC] 连接已满，拒绝 {} 的{}连接", id, if inbound { "入站" } else { "出站" });
+                false
+            }
+        }
+    }
+
+    fn has_room(&self, inbound: bool) -> bool {
+        let infos = self.connections.read();
+        let ids = Self::conn_ids(&infos);
+        let conns = Self::as_conns(&infos, &ids);
+        self.manager.read().has_room(&conns, inbound)
+    }
+
+    /// 断开空闲与低价值的直连连接
+    fn prune(&self) {
+        let mut infos = self.connections.write();
+        let ids = Self::conn_ids(&infos);
+        let conns = Self::as_conns(&infos, &ids);
+        let mut victims = self.manager.read().prune(&conns, Instant::now());
+        if victims.is_empty() {
+            return;
+        }
+        println!("[QUIC] 断开 {} 个空闲或低价值的直连连接", victims.len());
+        victims.sort_unstable_by(|a, b| b.cmp(a));
+        for index in victims {
+            let info = infos.remove(index);
+            info.connection.close(0u32.into(), b"pruned");
+        }
+        Self::forget_removed(&infos, &self.by_peer);
+    }
+
+    /// 地址指向本节点自己（如共享配置中的引导列表含本节点），连接只会把消息回环给自己
+    fn is_own(&self, addr: SocketAddr) -> bool {
+        self.endpoint
+            .local_addr()
+            .is_ok_and(|bound| netaddr::is_own(addr, bound, self.advertise))
+    }
+
+    async fn connect(&self, addr: SocketAddr, peer: Option<&str>) -> Result<quinn::Connection> {
+        let connection = self.dial(addr, peer)?.await?;
+        self.admit_outbound(&connection, addr, peer)?;
+        Ok(connection)
+    }
+
+    /// 与 `connect` 相同，但持有该节点的会话票据时以 0-RTT 立即返回连接，不等握手完成；
+    /// 此时一并返回对端是否接受 0-RTT 数据的结果
+    async fn connect_early(
+        &self,
+        addr: SocketAddr,
+        peer: &str,
+    ) -> Result<(quinn::Connection, Option<quinn::ZeroRttAccepted>)> {
+        let connecting = self.dial(addr, Some(peer))?;
+        // 0-RTT 连接还没有对端证书，只能使用空位；已满时等握手完成后再按身份腾位置
+        let (connection, accepted) = if self.has_room(false) {
+            match connecting.into_0rtt() {
+                Ok((connection, accepted)) => (connection, Some(accepted)),
+                Err(connecting) => (connecting.await?, None),
+            }
+        } else {
+            (connecting.await?, None)
+        };
+        self.admit_outbound(&connection, addr, Some(peer))?;
+        Ok((connection, accepted))
+    }
+
+    fn dial(&self, addr: SocketAddr, peer: Option<&str>) -> Result<quinn::Connecting> {
+        if self.is_own(addr) {
+            return Err(anyhow!("{} 是本节点自己的地址", addr));
+        }
+        // 已知对端时固定其 PeerId，证书不符即握手失败
+        let expected = peer.map(str::parse::<PeerId>).transpose()?;
+        let crypto = self.cert.client_config(expected, Arc::clone(&self.tickets))?;
+        let mut client_config = ClientConfig::new(Arc::new(crypto));
+        client_config.transport_config(quic_transport_config());
+        Ok(self
+            .endpoint
+            .connect_with(client_config, addr, &certs::server_name(peer))?)
+    }
+
+    /// 出站连接建立后占用连接数：握手已完成时按对端证书身份腾位置，腾不出时关闭新连接
+    fn admit_outbound(
+        &self,
+        connection: &quinn::Connection,
+        addr: SocketAddr,
+        peer: Option<&str>,
+    ) -> Result<()> {
+        let verified = certs::connection_peer(connection).map(|p| p.to_string());
+        let id = verified
+            .clone()
+            .or_else(|| peer.map(str::to_string))
+            .unwrap_or_else(|| addr.to_string());
+        if !self.make_room(&id, false, verified.is_some()) {
+            connection.close(0u32.into(), b"busy");
+            return Err(anyhow!("QUIC 出站连接已满"));
         }
+        self.register_outbound(connection, addr, peer);
+        Ok(())
+    }
+
+    fn register_outbound(&self, connection: &quinn::Connection, addr: SocketAddr, peer: Option<&str>) {
+        self.spawn_reader(connection.clone(), Some(netaddr::canonical(addr)));
+        self.spawn_responder(connection.clone());
+        self.bind_verified(connection);
+        let mut info = ConnectionInfo::new(connection.clone(), false);
+        // 0-RTT 连接握手尚未完成，取不到证书；已知对端的票据只会在该节点上恢复
+        info.peer = certs::connection_peer(connection)
+            .map(|p| p.to_string())
+            .or_else(|| peer.map(str::to_string));
+        self.track(info);
     }
 
-    async fn broadcast(&self, signed: &SignedGossip) -> bool {
-        let bytes = match serde_json::to_vec(signed) {
+    /// 到该节点的健康连接；不知道对端身份时按 addr 查找。
+    /// 连接的 PeerId 取自对端证书，同一地址上换了身份的节点不会复用旧连接
+    fn find_connection(&self, addr: SocketAddr, peer: Option<&str>) -> Option<quinn::Connection> {
+        if let Some(peer) = peer {
+            return self.peer_connection(peer);
+        }
+        let conns = self.connections.read();
+        conns
+            .iter()
+            .find(|info| info.is_healthy() && netaddr::canonical(info.connection.remote_address()) == addr)
+            .map(|info| info.connection.clone())
+    }
+
+    /// 握手已完成时按对端证书登记地址所属的节点；0-RTT 连接此时还没有证书，不登记
+    fn bind_verified(&self, connection: &quinn::Connection) {
+        if let Some(peer) = certs::connection_peer(connection) {
+            self.addr_peers
+                .write()
+                .insert(netaddr::canonical(connection.remote_address()), peer.to_string());
+        }
+    }
+
+    /// 在该地址上完成过证书握手的节点
+    fn addr_peer(&self, addr: SocketAddr) -> Option<String> {
+        self.addr_peers.read().get(&netaddr::canonical(addr)).cloned()
+    }
+
+    /// 按 PeerId 取到该节点的连接，已关闭的顺带清理
+    fn peer_connection(&self, peer: &str) -> Option<quinn::Connection> {
+        let connection = self.by_peer.read().get(peer).cloned()?;
+        if connection.close_reason().is_none() {
+            return Some(connection);
+        }
+        self.by_peer.write().remove(peer);
+        None
+    }
+
+    /// 加入连接池；已知对端身份的连接同时登记为到该节点的连接（新连接取代旧连接）
+    fn track(&self, info: ConnectionInfo) {
+        if let Some(peer) = &info.peer {
+            self.by_peer.write().insert(peer.clone(), info.connection.clone());
+        }
+        self.connections.write().push(info);
+    }
+
+    /// 移出连接池后调用：PeerId 索引中不再在池中的连接一并清理，
+    /// 该节点还有另一条连接（入站与出站各一条）时改用那一条
+    fn forget_removed(conns: &[ConnectionInfo], by_peer: &RwLock<HashMap<String, quinn::Connection>>) {
+        let live: HashSet<usize> = conns.iter().map(|info| info.connection.stable_id()).collect();
+        let mut by_peer = by_peer.write();
+        by_peer.retain(|_, connection| live.contains(&connection.stable_id()));
+        for info in conns {
+            if let Some(peer) = &info.peer {
+                by_peer
+                    .entry(peer.clone())
+                    .or_insert_with(|| info.connection.clone());
+            }
+        }
+    }
+
+    /// 复用到该节点或 addr 的已有连接，没有则新建，然后发送一条消息。
+    /// 可经 0-RTT 发送的消息在已知对端时随恢复的会话立即发出；对端拒绝 0-RTT 时握手完成后重发
+    async fn send_to(&self, addr: SocketAddr, peer: Option<&str>, bytes: &[u8], delivery: Delivery) -> Result<()> {
+        let addr = netaddr::canonical(addr);
+        if let Some(connection) = self.find_connection(addr, peer) {
+            return Self::send_message(&connection, bytes, delivery.datagram).await;
+        }
+        let Some(peer) = peer.filter(|_| delivery.early) else {
+            let connection = self.connect(addr, peer).await?;
+            return Self::send_message(&connection, bytes, delivery.datagram).await;
+        };
+        let (connection, accepted) = self.connect_early(addr, peer).await?;
+        let Some(accepted) = accepted else {
+            return Self::send_message(&connection, bytes, delivery.datagram).await;
+        };
+        // 0-RTT 被拒时数据报无从得知是否丢失，一律走流
+        let result = Self::send_message(&connection, bytes, false).await;
+        if result.is_err() && !accepted.await {
+            return Self::send_message(&connection, bytes, delivery.datagram).await;
+        }
+        result
+    }
+
+    /// `datagram` 为 true 且消息装得进一个数据报（对端也支持）时以不可靠数据报发出，
+    /// 否则照常打开单向流
+    async fn send_message(connection: &quinn::Connection, bytes: &[u8], datagram: bool) -> Result<()> {
+        if datagram && connection.max_datagram_size().is_some_and(|max| bytes.len() <= max) {
+            connection.send_datagram(bytes.to_vec().into())?;
+            return Ok(());
+        }
+        let mut send = connection.open_uni().await?;
+        send.write_all(bytes).await?;
+        send.finish().await?;
+        Ok(())
+    }
+
+    async fn broadcast(&self, signed: &SignedGossip, codec: WireCodec) -> bool {
+        let bytes = match codec::encode(codec, signed) {
             Ok(b) => b,
             Err(_) => return false,
         };
+        let datagram = signed.payload.is_loss_tolerant();
         
         // 收集连接和对应的原始索引，使用连接对象本身而不是索引
         // 这样可以避免在向量修改后索引失效的问题
@@ -372,18 +3053,11 @@ impl QuicGateway {
         
         // 尝试发送到所有连接
         for (conn, original_idx) in entries {
-            match conn.open_uni().await {
-                Ok(mut send) => {
-                    if send.write_all(&bytes).await.is_ok() && send.finish().await.is_ok() {
-                        success = true;
-                        success_original_indices.push(original_idx);
-                    } else {
-                        failed_original_indices.push(original_idx);
-                    }
-                }
-                Err(_) => {
-                    failed_original_indices.push(original_idx);
-                }
+            if Self::send_message(&conn, &bytes, datagram).await.is_ok() {
+                success = true;
+                success_original_indices.push(original_idx);
+            } else {
+                failed_original_indices.push(original_idx);
             }
         }
         
@@ -416,6 +3090,7 @@ impl QuicGateway {
             
             // 移除不健康的连接（这可能会改变后续索引，但我们已经处理完了）
             guard.retain(|info| info.is_healthy());
+            Self::forget_removed(&guard, &self.by_peer);
         }
         
         success
@@ -0,0 +1,500 @@
+//! TOML 配置文件
+//!
+//! `--config node.toml` 读取推理、通信、拓扑、密钥与共识配置，文件中未出现的项保持默认值；
+//! 命令行参数在文件之后应用，优先级更高。示例：
+//!
+//! ```toml
+//! [inference]
+//! model_dim = 1024
+//! layers = "4"
+//!
+//! [comms]
+//! topic = "ggs-llm"
+//! quic_bind = "[::]:9300"   # 默认双栈，同时接收 IPv4；dual_stack = false 时只用 IPv6
+//!
+//! [topology]
+//! max_neighbors = 12
+//!
+//! [schedule]
+//! heartbeat_secs = 15
+//! ```
+//!
+//! 运行中修改文件（或发送 SIGHUP）会热加载带宽预算（`[comms]` 中的 `sparse_per_window`、
+//! `dense_bytes_per_window`、`bandwidth_window_secs`）、`[topology]` 与 `[schedule]`；
+//! 其余项只在启动时读取，修改后需要重启。
+//!
+//! 容器部署时每一项都可以用环境变量 `GGS_<节>_<键>` 提供（如 `GGS_COMMS_TOPIC`、
+//! `GGS_COMMS_QUIC_BOOTSTRAP=1.2.3.4:9300,5.6.7.8:9300`、`GGS_INFERENCE_MODEL_PATH`），
+//! 配置文件路径本身可用 `GGS_CONFIG` 指定。优先级：命令行 > 环境变量 > 配置文件 > 默认值。
+//! 取值按该项的类型解释：先按 TOML 语法（数字、布尔、数组），不符时按字符串，再不符时按逗号分隔的列表。
+//! 不属于任何节的 `GGS_*` 变量（如 `GGS_ETH_SEED`、`GGS_DEVICE_TYPE`）不在此处理。
+
+use crate::comms::{parse_validation_mode, BandwidthBudgetConfig, CommsConfig};
+use crate::consensus::ConsensusConfig;
+use crate::crypto::CryptoConfig;
+use crate::inference::InferenceConfig;
+use crate::ledger::LedgerStoreConfig;
+use crate::scheduler::ScheduleConfig;
+use crate::topology::TopologyConfig;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 配置文件路径的环境变量
+pub const CONFIG_PATH_ENV: &str = "GGS_CONFIG";
+const ENV_PREFIX: &str = "GGS_";
+const SECTIONS: [&str; 6] = ["inference", "comms", "topology", "crypto", "consensus", "schedule"];
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub inference: InferenceSection,
+    pub comms: CommsSection,
+    pub topology: TopologySection,
+    pub crypto: CryptoSection,
+    pub consensus: ConsensusSection,
+    pub schedule: ScheduleSection,
+}
+
+impl ConfigFile {
+    /// 读取配置文件（可选）并叠加进程的 `GGS_*` 环境变量；既没有文件也没有相关变量时返回 None
+    pub fn load(path: Option<&Path>) -> Result<Option<Self>> {
+        let env: Vec<(String, String)> = std::env::vars().collect();
+        let text = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("读取配置文件 {} 失败: {}", path.display(), e))?,
+            None if env.iter().all(|(name, _)| env_key(name).is_none()) => return Ok(None),
+            None => String::new(),
+        };
+        Self::parse_with_env(&text, &env)
+            .map(Some)
+            .map_err(|e| match path {
+                Some(path) => anyhow!("配置文件 {} 无效: {}", path.display(), e),
+                None => e,
+            })
+    }
+
+    /// 解析配置文件文本，再用环境变量覆盖同名项
+    pub fn parse_with_env(text: &str, env: &[(String, String)]) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text)?;
+        for (name, raw) in env {
+            let Some((section, key)) = env_key(name) else {
+                continue;
+            };
+            let value = env_value(section, &key, raw)
+                .ok_or_else(|| anyhow!("环境变量 {} 无效：[{}] 中没有 {} 或取值类型不符", name, section, key))?;
+            let slot = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let toml::Value::Table(section_table) = slot else {
+                return Err(anyhow!("配置项 {} 不是一个节", section));
+            };
+            section_table.insert(key, value);
+        }
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+}
+
+/// `GGS_COMMS_QUIC_BIND` -> ("comms", "quic_bind")；不属于任何节的变量返回 None
+fn env_key(name: &str) -> Option<(&'static str, String)> {
+    let rest = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase();
+    SECTIONS.iter().find_map(|section| {
+        let key = rest.strip_prefix(section)?.strip_prefix('_')?;
+        (!key.is_empty()).then(|| (*section, key.to_string()))
+    })
+}
+
+/// 按该项的类型解释环境变量的值：依次尝试 TOML 值、字符串、逗号分隔的字符串列表
+fn env_value(section: &str, key: &str, raw: &str) -> Option<toml::Value> {
+    let typed = toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"));
+    let list = toml::Value::Array(
+        raw.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| toml::Value::String(s.to_string()))
+            .collect(),
+    );
+    typed
+        .into_iter()
+        .chain([toml::Value::String(raw.to_string()), list])
+        .find(|value| {
+            let mut section_table = toml::Table::new();
+            section_table.insert(key.to_string(), value.clone());
+            let mut table = toml::Table::new();
+            table.insert(section.to_string(), toml::Value::Table(section_table));
+            toml::Value::Table(table).try_into::<ConfigFile>().is_ok()
+        })
+}
+
+fn set<T>(slot: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *slot = value;
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct InferenceSection {
+    pub model_dim: Option<usize>,
+    pub model_path: Option<PathBuf>,
+    pub seed: Option<u64>,
+    /// 同 `--layers`
+    pub layers: Option<String>,
+    pub checkpoint_dir: Option<PathBuf>,
+    pub param_bound: Option<f32>,
+}
+
+impl InferenceSection {
+    pub fn apply(self, config: &mut InferenceConfig) -> Result<()> {
+        set(&mut config.model_dim, self.model_dim);
+        config.model_path = self.model_path.or(config.model_path.take());
+        config.seed = self.seed.or(config.seed);
+        set(&mut config.layers, self.layers.map(|l| l.parse()).transpose()?);
+        config.checkpoint_dir = self.checkpoint_dir.or(config.checkpoint_dir.take());
+        if let Some(bound) = self.param_bound {
+            if !(bound.is_finite() && bound > 0.0) {
+                return Err(anyhow!("param_bound 必须是有限正数: {}", bound));
+            }
+            config.param_bound = bound;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommsSection {
+    pub topic: Option<String>,
+    pub listen_addr: Option<String>,
+    pub quic_bind: Option<SocketAddr>,
+    pub dual_stack: Option<bool>,
+    pub quic_bootstrap: Option<Vec<SocketAddr>>,
+    pub quic_advertise: Option<SocketAddr>,
+    pub external_addrs: Option<Vec<String>>,
+    pub rendezvous_points: Option<Vec<String>>,
+    pub rendezvous_serve: Option<bool>,
+    pub dht_enabled: Option<bool>,
+    pub dht_bootstrap: Option<Vec<String>>,
+    pub bootstrap_peers: Option<Vec<String>>,
+    pub circuit_relays: Option<Vec<String>>,
+    pub relay_server: Option<bool>,
+    pub relay_url: Option<String>,
+    pub relay_token: Option<String>,
+    pub sparse_per_window: Option<u32>,
+    pub dense_bytes_per_window: Option<usize>,
+    pub bandwidth_window_secs: Option<u64>,
+    pub identity_path: Option<PathBuf>,
+    /// libp2p 传输栈，如 ["tcp", "ws"]
+    pub transports: Option<Vec<String>>,
+    pub tcp_fallback: Option<bool>,
+    /// SOCKS5 代理，如 "socks5://127.0.0.1:9050"
+    pub proxy: Option<String>,
+    pub proxy_disable_mdns: Option<bool>,
+    pub max_inbound: Option<u32>,
+    pub max_outbound: Option<u32>,
+    pub idle_timeout_secs: Option<u64>,
+    /// gossipsub 与 QUIC 的发送策略："both" 或 "quic_neighbors"
+    pub send_policy: Option<String>,
+    /// mesh 为空时最多暂存的发布数与暂存时长
+    pub publish_queue_max: Option<usize>,
+    pub publish_queue_max_age_secs: Option<u64>,
+    pub gossip_mesh_n: Option<usize>,
+    pub gossip_heartbeat_ms: Option<u64>,
+    pub gossip_max_transmit_size: Option<usize>,
+    /// gossipsub 消息 id："source" 或 "content"
+    pub gossip_message_id: Option<String>,
+    /// gossipsub 校验模式：只接受 "strict"
+    pub gossip_validation: Option<String>,
+    /// 是否按共识层记录的不当行为为 gossipsub 节点评分
+    pub gossip_peer_scoring: Option<bool>,
+    /// gossip 与直连广播的编码："json"、"zstd" 或 "binary/<模式版本>"
+    pub gossip_codec: Option<String>,
+}
+
+impl CommsSection {
+    pub fn apply(self, config: &mut CommsConfig) -> Result<()> {
+        self.apply_bandwidth(&mut config.bandwidth);
+        set(&mut config.topic, self.topic);
+        if let Some(addr) = self.listen_addr {
+            config.listen_addr = Some(addr.parse()?);
+        }
+        if self.quic_bind.is_some() {
+            config.quic_bind = self.quic_bind;
+        }
+        set(&mut config.dual_stack, self.dual_stack);
+        set(&mut config.quic_bootstrap, self.quic_bootstrap);
+        config.quic_advertise = self.quic_advertise.or(config.quic_advertise);
+        if let Some(addrs) = self.external_addrs {
+            config.external_addrs = addrs.iter().map(|a| a.parse()).collect::<Result<_, _>>()?;
+        }
+        if let Some(points) = self.rendezvous_points {
+            config.rendezvous.points = points.iter().map(|a| a.parse()).collect::<Result<_, _>>()?;
+        }
+        set(&mut config.rendezvous.serve, self.rendezvous_serve);
+        set(&mut config.dht.enabled, self.dht_enabled);
+        if let Some(addrs) = self.dht_bootstrap {
+            config.dht.bootstrap = addrs.iter().map(|a| a.parse()).collect::<Result<_, _>>()?;
+        }
+        if let Some(addrs) = self.bootstrap_peers {
+            config.bootstrap_peers = addrs.iter().map(|a| a.parse()).collect::<Result<_, _>>()?;
+        }
+        if let Some(addrs) = self.circuit_relays {
+            config.nat.relays = addrs.iter().map(|a| a.parse()).collect::<Result<_, _>>()?;
+        }
+        set(&mut config.nat.relay_server, self.relay_server);
+        config.relay.url = self.relay_url.or(config.relay.url.take());
+        config.relay.token = self.relay_token.or(config.relay.token.take());
+        config.identity_path = self.identity_path.or(config.identity_path.take());
+        if let Some(stack) = self.transports {
+            config.transport.stack = stack.iter().map(|t| t.parse()).collect::<Result<_>>()?;
+        }
+        set(&mut config.transport.tcp_fallback, self.tcp_fallback);
+        if let Some(url) = self.proxy {
+            config.proxy.set_url(&url)?;
+        }
+        set(&mut config.proxy.disable_mdns, self.proxy_disable_mdns);
+        set(&mut config.connections.max_inbound, self.max_inbound);
+        set(&mut config.connections.max_outbound, self.max_outbound);
+        if let Some(secs) = self.idle_timeout_secs {
+            config.connections.idle_timeout = Duration::from_secs(secs);
+        }
+        set(&mut config.send_policy, self.send_policy.map(|p| p.parse()).transpose()?);
+        set(&mut config.publish_queue.max_entries, self.publish_queue_max);
+        if let Some(secs) = self.publish_queue_max_age_secs {
+            config.publish_queue.max_age = Duration::from_secs(secs);
+        }
+        set(&mut config.gossip.mesh_n, self.gossip_mesh_n);
+        if let Some(ms) = self.gossip_heartbeat_ms {
+            config.gossip.heartbeat_interval = Duration::from_millis(ms.max(1));
+        }
+        set(&mut config.gossip.max_transmit_size, self.gossip_max_transmit_size);
+        set(&mut config.gossip.message_id, self.gossip_message_id.map(|m| m.parse()).transpose()?);
+        if let Some(mode) = self.gossip_validation {
+            config.gossip.validation = parse_validation_mode(&mode)?;
+        }
+        set(&mut config.gossip.peer_scoring, self.gossip_peer_scoring);
+        set(&mut config.gossip_codec, self.gossip_codec.map(|c| c.parse()).transpose()?);
+        Ok(())
+    }
+
+    /// 只应用带宽预算（热加载时使用）
+    pub fn apply_bandwidth(&self, config: &mut BandwidthBudgetConfig) {
+        set(&mut config.sparse_per_window, self.sparse_per_window);
+        set(&mut config.dense_bytes_per_window, self.dense_bytes_per_window);
+        set(&mut config.window_secs, self.bandwidth_window_secs);
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopologySection {
+    pub max_neighbors: Option<usize>,
+    pub failover_pool: Option<usize>,
+    pub min_score: Option<f32>,
+    pub geo_scale_km: Option<f32>,
+    pub peer_stale_secs: Option<u64>,
+    pub cross_cluster_fraction: Option<f32>,
+    pub embedding_weight: Option<f32>,
+    pub geo_weight: Option<f32>,
+    pub latency_weight: Option<f32>,
+    pub latency_scale_ms: Option<f32>,
+    pub silence_grace_secs: Option<u64>,
+    pub decay_half_life_secs: Option<u64>,
+    pub fanout: Option<usize>,
+}
+
+impl TopologySection {
+    pub fn apply(self, config: &mut TopologyConfig) {
+        set(&mut config.max_neighbors, self.max_neighbors);
+        set(&mut config.failover_pool, self.failover_pool);
+        set(&mut config.min_score, self.min_score);
+        set(&mut config.geo_scale_km, self.geo_scale_km);
+        set(&mut config.peer_stale_secs, self.peer_stale_secs);
+        set(
+            &mut config.cross_cluster_fraction,
+            self.cross_cluster_fraction.map(|f| f.clamp(0.0, 1.0)),
+        );
+        set(&mut config.embedding_weight, self.embedding_weight);
+        set(&mut config.geo_weight, self.geo_weight);
+        set(&mut config.latency_weight, self.latency_weight);
+        set(&mut config.latency_scale_ms, self.latency_scale_ms);
+        set(&mut config.silence_grace_secs, self.silence_grace_secs);
+        set(&mut config.decay_half_life_secs, self.decay_half_life_secs);
+        set(&mut config.fanout, self.fanout);
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleSection {
+    pub heartbeat_secs: Option<u64>,
+    pub probe_secs: Option<u64>,
+    pub train_secs: Option<u64>,
+    pub snapshot_secs: Option<u64>,
+    pub persist_secs: Option<u64>,
+    pub announce_secs: Option<u64>,
+    pub jitter_ratio: Option<f32>,
+}
+
+impl ScheduleSection {
+    pub fn apply(self, config: &mut ScheduleConfig) {
+        let secs = |v: Option<u64>| v.map(|s| Duration::from_secs(s.max(1)));
+        set(&mut config.heartbeat_interval, secs(self.heartbeat_secs));
+        set(&mut config.probe_interval, secs(self.probe_secs));
+        set(&mut config.train_interval, secs(self.train_secs));
+        set(&mut config.snapshot_interval, secs(self.snapshot_secs));
+        set(&mut config.persist_interval, secs(self.persist_secs));
+        set(&mut config.announce_interval, secs(self.announce_secs));
+        set(&mut config.jitter_ratio, self.jitter_ratio.map(|r| r.clamp(0.0, 0.5)));
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CryptoSection {
+    /// 环境变量 GGS_ETH_SEED / GGS_SOL_SEED 优先
+    pub eth_hex_seed: Option<String>,
+    pub sol_bs58_seed: Option<String>,
+    /// 同 `--keystore`，口令由环境变量 GGS_KEYSTORE_PASSPHRASE 提供
+    pub keystore: Option<PathBuf>,
+}
+
+impl CryptoSection {
+    pub fn apply(self, config: &mut CryptoConfig) {
+        config.eth_hex_seed = self.eth_hex_seed.or(config.eth_hex_seed.take());
+        config.sol_bs58_seed = self.sol_bs58_seed.or(config.sol_bs58_seed.take());
+        config.keystore = self.keystore.or(config.keystore.take());
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsensusSection {
+    pub heartbeat_timeout_secs: Option<u64>,
+    pub pow_difficulty: Option<u8>,
+    /// 持久化账本路径
+    pub ledger_db: Option<PathBuf>,
+    pub ledger_retention_days: Option<u64>,
+    pub ledger_compact_secs: Option<u64>,
+}
+
+impl ConsensusSection {
+    pub fn apply(self, config: &mut ConsensusConfig) {
+        set(
+            &mut config.heartbeat_timeout,
+            self.heartbeat_timeout_secs.map(Duration::from_secs),
+        );
+        set(&mut config.pow_difficulty, self.pow_difficulty);
+    }
+
+    pub fn apply_ledger(&self, config: &mut LedgerStoreConfig) {
+        if self.ledger_db.is_some() {
+            config.path = self.ledger_db.clone();
+        }
+        set(
+            &mut config.retention,
+            self.ledger_retention_days
+                .map(|d| Duration::from_secs(d.saturating_mul(24 * 3600))),
+        );
+        set(
+            &mut config.compact_interval,
+            self.ledger_compact_secs.map(Duration::from_secs),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::WireCodec;
+    use crate::comms::{GossipMessageId, SendPolicy};
+    use crate::layers::LayerLayout;
+
+    #[test]
+    fn test_file_overrides_only_given_fields() {
+        let file = ConfigFile::parse_with_env(
+            r#"
+            [inference]
+            model_dim = 1024
+            layers = "4"
+
+            [comms]
+            topic = "ggs-llm"
+            quic_bind = "0.0.0.0:9300"
+            send_policy = "quic_neighbors"
+            gossip_mesh_n = 8
+            gossip_max_transmit_size = 4194304
+            gossip_message_id = "content"
+            gossip_peer_scoring = false
+            gossip_codec = "binary/1"
+
+            [topology]
+            max_neighbors = 12
+            "#,
+            &[],
+        )
+        .unwrap();
+        let mut inference = InferenceConfig::default();
+        let mut comms = CommsConfig::default();
+        let mut topology = TopologyConfig::default();
+        file.inference.apply(&mut inference).unwrap();
+        file.comms.apply(&mut comms).unwrap();
+        file.topology.apply(&mut topology);
+        assert_eq!(inference.model_dim, 1024);
+        assert_eq!(inference.layers, LayerLayout::Uniform(4));
+        assert_eq!(comms.topic, "ggs-llm");
+        assert_eq!(comms.quic_bind, Some("0.0.0.0:9300".parse().unwrap()));
+        assert_eq!(comms.send_policy, SendPolicy::QuicNeighbors);
+        assert_eq!(comms.gossip.message_id, GossipMessageId::ContentHash);
+        assert!(!comms.gossip.peer_scoring);
+        assert_eq!(comms.gossip_codec, WireCodec::Binary);
+        let gossip = comms.gossip.build().unwrap();
+        assert_eq!((gossip.mesh_n(), gossip.max_transmit_size()), (8, 4 * 1024 * 1024));
+        assert_eq!(topology.max_neighbors, 12);
+        assert_eq!(topology.failover_pool, TopologyConfig::default().failover_pool);
+
+        // 拼错的键直接报错，而不是被悄悄忽略
+        assert!(ConfigFile::parse_with_env("[topology]\nmax_neighbours = 3", &[]).is_err());
+        // 不认证消息源的校验模式被拒绝
+        let file =
+            ConfigFile::parse_with_env("[comms]\ngossip_validation = \"none\"", &[]).unwrap();
+        assert!(file.comms.apply(&mut CommsConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let env = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let file = ConfigFile::parse_with_env(
+            "[comms]\ntopic = \"from-file\"\n\n[inference]\nmodel_dim = 512\n",
+            &env(&[
+                ("GGS_COMMS_TOPIC", "from-env"),
+                ("GGS_COMMS_QUIC_BOOTSTRAP", "10.0.0.1:9300, 10.0.0.2:9300"),
+                ("GGS_INFERENCE_SEED", "7"),
+                ("GGS_INFERENCE_MODEL_PATH", "/models/base.npy"),
+                // 十六进制种子按字符串而不是 TOML 整数解释
+                ("GGS_CRYPTO_ETH_HEX_SEED", "0x1f"),
+                ("GGS_SCHEDULE_JITTER_RATIO", "0.2"),
+                // 不属于任何节的变量由其他模块处理
+                ("GGS_ETH_SEED", "ignored"),
+                ("PATH", "/usr/bin"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(file.comms.topic.as_deref(), Some("from-env"));
+        assert_eq!(file.comms.quic_bootstrap.as_ref().map(Vec::len), Some(2));
+        assert_eq!(file.inference.model_dim, Some(512));
+        assert_eq!(file.inference.seed, Some(7));
+        assert_eq!(file.inference.model_path, Some(PathBuf::from("/models/base.npy")));
+        assert_eq!(file.crypto.eth_hex_seed.as_deref(), Some("0x1f"));
+        assert_eq!(file.schedule.jitter_ratio, Some(0.2));
+
+        assert!(ConfigFile::parse_with_env("", &env(&[("GGS_COMMS_TOPICS", "x")])).is_err());
+        assert!(ConfigFile::parse_with_env("", &env(&[("GGS_INFERENCE_MODEL_DIM", "big")])).is_err());
+    }
+}
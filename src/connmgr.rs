@@ -0,0 +1,212 @@
+//! 连接管理
+//!
+//! libp2p swarm 与 QUIC 直连各自限制入站、出站连接数：swarm 的上限在握手时由 connection_limits
+//! 行为执行，QUIC 在握手完成、确认对端证书身份后才为新连接挤掉已有连接，身份未确认的连接
+//! 只能使用空位，腾不出位置时拒绝新连接。节点按拓扑评分把邻居
+//! （含已知聚合者）排好序交给这里，周期性清理时：
+//! - 长时间没有消息往来的非邻居连接直接断开；
+//! - 某方向连接数超过低水位（上限留出一成余量给新的邻居）时，按价值从低到高断开，
+//!   非邻居按最近活动时间先断，邻居按评分排名从低到高断。
+//!
+//! 引导节点、中继与 rendezvous 等基础设施连接由调用方标为受保护，不参与清理。
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct ConnectionManagerConfig {
+    /// 入站连接上限
+    pub max_inbound: u32,
+    /// 出站连接上限
+    pub max_outbound: u32,
+    /// 非邻居连接空闲超过此时长即断开
+    pub idle_timeout: Duration,
+}
+
+impl Default for ConnectionManagerConfig {
+    fn default() -> Self {
+        Self {
+            max_inbound: 64,
+            max_outbound: 32,
+            idle_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+/// 参与清理的一条连接；`id` 为节点 PeerId，未知时为远端地址
+pub struct Conn<'a> {
+    pub id: &'a str,
+    pub inbound: bool,
+    pub last_active: Instant,
+    /// 基础设施连接，不断开
+    pub protected: bool,
+}
+
+pub struct ConnectionManager {
+    config: ConnectionManagerConfig,
+    /// 邻居 -> 评分排名（0 最优）
+    preferred: HashMap<String, usize>,
+}
+
+impl ConnectionManager {
+    pub fn new(config: ConnectionManagerConfig) -> Self {
+        Self {
+            config,
+            preferred: HashMap::new(),
+        }
+    }
+
+    /// 设置按评分从高到低排好序的邻居；重复出现时取最好的排名
+    pub fn set_preferred(&mut self, ranked: Vec<String>) {
+        self.preferred.clear();
+        for peer in ranked {
+            let rank = self.preferred.len();
+            self.preferred.entry(peer).or_insert(rank);
+        }
+    }
+
+    pub fn is_preferred(&self, id: &str) -> bool {
+        self.preferred.contains_key(id)
+    }
+
+    fn limit(&self, inbound: bool) -> usize {
+        if inbound {
+            self.config.max_inbound as usize
+        } else {
+            self.config.max_outbound as usize
+        }
+    }
+
+    /// 连接的保留价值，越小越先断开：非邻居最低，邻居按排名，同级按最近活动时间
+    fn value(&self, conn: &Conn<'_>) -> (usize, Instant) {
+        let rank = match self.preferred.get(conn.id) {
+            Some(rank) => self.preferred.len() - rank,
+            None => 0,
+        };
+        (rank, conn.last_active)
+    }
+
+    /// 需要断开的连接（`conns` 中的下标）：空闲超时的非邻居连接，以及各方向超出低水位后价值最低的连接
+    pub fn prune(&self, conns: &[Conn<'_>], now: Instant) -> Vec<usize> {
+        let mut victims: Vec<usize> = conns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                !c.protected
+                    && !self.is_preferred(c.id)
+                    && now.saturating_duration_since(c.last_active) > self.config.idle_timeout
+            })
+            .map(|(i, _)| i)
+            .collect();
+        for inbound in [true, false] {
+            let limit = self.limit(inbound);
+            let watermark = limit - limit / 10;
+            let mut remaining: Vec<usize> = (0..conns.len())
+                .filter(|i| conns[*i].inbound == inbound && !victims.contains(i))
+                .collect();
+            let Some(excess) = remaining.len().checked_sub(watermark).filter(|n| *n > 0) else {
+                continue;
+            };
+            remaining.retain(|i| !conns[*i].protected);
+            remaining.sort_by_key(|i| self.value(&conns[*i]));
+            victims.extend(remaining.into_iter().take(excess));
+        }
+        victims
+    }
+
+    /// 该方向是否还有空位
+    pub fn has_room(&self, conns: &[Conn<'_>], inbound: bool) -> bool {
+        conns.iter().filter(|c| c.inbound == inbound).count() < self.limit(inbound)
+    }
+
+    /// 新连接 `id` 到来而该方向已满时腾出的位置：价值低于新连接的最低价值连接；
+    /// 没有时返回 None，应拒绝新连接。未满时返回 Some(None)。
+    /// `admitted` 为 false（对端身份尚未经证书确认）时不挤掉任何连接
+    pub fn make_room(
+        &self,
+        conns: &[Conn<'_>],
+        id: &str,
+        inbound: bool,
+        admitted: bool,
+    ) -> Option<Option<usize>> {
+        if self.has_room(conns, inbound) {
+            return Some(None);
+        }
+        if !admitted {
+            return None;
+        }
+        let newcomer = self.value(&Conn {
+            id,
+            inbound,
+            last_active: Instant::now(),
+            protected: false,
+        });
+        conns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.inbound == inbound && !c.protected)
+            .min_by_key(|(_, c)| self.value(c))
+            .filter(|(_, c)| self.value(c).0 < newcomer.0 || !self.is_preferred(c.id))
+            .map(|(i, _)| Some(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prunes_idle_and_lowest_value_connections_first() {
+        let mut manager = ConnectionManager::new(ConnectionManagerConfig {
+            max_inbound: 3,
+            max_outbound: 10,
+            idle_timeout: Duration::from_secs(60),
+        });
+        manager.set_preferred(vec!["best".into(), "good".into(), "best".into()]);
+        let now = Instant::now() + Duration::from_secs(600);
+        let conn = |id, inbound, idle_secs, protected| Conn {
+            id,
+            inbound,
+            last_active: now - Duration::from_secs(idle_secs),
+            protected,
+        };
+        let conns = [
+            conn("good", true, 500, false),
+            conn("best", true, 500, false),
+            conn("fresh", true, 1, false),
+            conn("stale", true, 30, false),
+            conn("relay", true, 900, true),
+            conn("idle", false, 120, false),
+        ];
+        // 入站 5 条超出水位 3：先断非邻居中较久未活动的，邻居与受保护连接保留；空闲的出站连接断开
+        let mut victims = manager.prune(&conns, now);
+        victims.sort();
+        assert_eq!(victims, [2, 3, 5]);
+
+        // 已满时新的非邻居只能挤掉非邻居，邻居可以挤掉排名更低的邻居
+        let full = [
+            conn("good", true, 0, false),
+            conn("best", true, 0, false),
+            conn("relay", true, 0, true),
+        ];
+        assert_eq!(manager.make_room(&full, "stranger", true, true), None);
+        assert_eq!(manager.make_room(&full, "best", true, true), Some(Some(0)));
+        assert_eq!(
+            manager.make_room(&full, "stranger", false, true),
+            Some(None)
+        );
+        // 身份未确认的连接只能使用空位，不挤掉任何连接
+        assert_eq!(manager.make_room(&full, "best", true, false), None);
+        assert_eq!(manager.make_room(&full, "best", false, false), Some(None));
+        let crowded = [
+            conn("stale", true, 0, false),
+            conn("good", true, 0, false),
+            conn("best", true, 0, false),
+        ];
+        assert_eq!(
+            manager.make_room(&crowded, "new", true, true),
+            Some(Some(0))
+        );
+        assert_eq!(manager.make_room(&crowded, "new", true, false), None);
+    }
+}
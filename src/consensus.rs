@@ -1,16 +1,71 @@
-use crate::crypto::{CryptoSuite, SignatureBundle};
+use crate::admission::{self, DEFAULT_POW_DIFFICULTY};
+use crate::attestation::ReputationAttestation;
+use crate::clock::unix_now_millis;
+use crate::crypto::{verify_eth, verify_sol, CryptoError, CryptoSuite, SignatureBundle};
+use crate::session::{default_session_id, DEFAULT_SESSION};
 use crate::types::GgsMessage;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::value::RawValue;
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// 心跳间隔超过有效间隔的这个倍数视为中断，连续心跳数重新计数
+const STREAK_MAX_GAP_FACTOR: u32 = 3;
+/// 对方宣告的连续心跳数最多采信本地观测值的这个倍数
+const STREAK_CLAIM_FACTOR: u32 = 4;
+/// 连续心跳数达到该值时参与度分量为 ln 2
+const STREAK_SCALE: f32 = 360.0;
+/// 参与度分量的上限
+const MAX_PARTICIPATION_COMPONENT: f32 = 1.0;
+/// 不当行为扣分的半衰期
+const MISBEHAVIOR_HALF_LIFE: Duration = Duration::from_secs(600);
+/// 衰减到该值以下的扣分清零
+const MIN_PENALTY: f64 = 0.01;
+/// 一次账本同步最多处理的条目数
+const MAX_SYNCED_LEDGER_ENTRIES: usize = 1024;
+
+/// 节点的不当行为，折算为 gossipsub 应用层评分的扣分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// 经直连通道发来签名无效的消息（gossip 转发的由 gossipsub 校验结果处理）
+    InvalidSignature,
+    /// 发来含非有限值、长度不符或校验失败的更新与快照
+    InvalidUpdate,
+}
+
+impl Misbehavior {
+    fn penalty(self) -> f64 {
+        match self {
+            Misbehavior::InvalidSignature => 20.0,
+            Misbehavior::InvalidUpdate => 10.0,
+        }
+    }
+}
+
+/// 随时间按半衰期衰减的扣分
+#[derive(Clone, Copy, Debug)]
+struct Penalty {
+    points: f64,
+    at: Instant,
+}
+
+impl Penalty {
+    fn decayed(&self, now: Instant) -> f64 {
+        let halves = now.saturating_duration_since(self.at).as_secs_f64() / MISBEHAVIOR_HALF_LIFE.as_secs_f64();
+        self.points * 0.5f64.powf(halves)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StakeRecord {
     pub stake_eth: f64,
     pub stake_sol: f64,
     pub reputation: f64,
+    /// 经本地观测核实的连续心跳数
+    pub participation: u32,
     pub last_seen: Instant,
 }
 
@@ -18,25 +73,184 @@ impl StakeRecord {
     pub fn combined_weight(&self) -> f32 {
         let stake_component = (self.stake_eth + self.stake_sol).ln_1p() as f32;
         let rep_component = (self.reputation.max(0.0) as f32).ln_1p();
-        (stake_component + rep_component).clamp(0.0, 5.0)
+        let participation_component =
+            (self.participation as f32 / STREAK_SCALE).ln_1p().min(MAX_PARTICIPATION_COMPONENT);
+        (stake_component + rep_component + participation_component).clamp(0.0, 5.0)
     }
 }
 
+/// 连续心跳计数
+#[derive(Clone, Copy, Debug)]
+struct Streak {
+    count: u32,
+    last: Instant,
+}
+
+impl Streak {
+    fn advance(previous: Option<Streak>, now: Instant, interval: Duration) -> Streak {
+        match previous {
+            // 多个会话在同一周期各发一次心跳，只计一次
+            Some(streak) if now.duration_since(streak.last) < interval / 2 => streak,
+            Some(streak) if now.duration_since(streak.last) <= interval * STREAK_MAX_GAP_FACTOR => {
+                Streak {
+                    count: streak.count.saturating_add(1),
+                    last: now,
+                }
+            }
+            _ => Streak { count: 1, last: now },
+        }
+    }
+}
+
+/// 可持久化的账本条目（不含进程内的 last_seen）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub peer: String,
+    pub stake_eth: f64,
+    pub stake_sol: f64,
+    pub reputation: f64,
+}
+
+/// 首次见到某节点时固定的 (PeerId, ETH 地址, SOL 公钥) 绑定
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPin {
+    pub peer: String,
+    pub eth_address: String,
+    pub sol_pubkey: String,
+}
+
+/// 使用与已固定绑定不同的密钥冒充某个 PeerId 的记录
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyConflict {
+    pub peer: String,
+    pub presented_eth: String,
+    pub presented_sol: String,
+    pub attempts: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SignedGossip {
+    /// 所属训练会话
+    #[serde(default = "default_session_id")]
+    pub session: String,
+    /// 发送时间（Unix 毫秒），0 表示旧版本未携带时间戳
+    #[serde(default)]
+    pub sent_at_ms: u64,
     pub payload: GgsMessage,
     pub signature: SignatureBundle,
     pub staking_score: f32,
+    /// 从 JSON 解码时收到的载荷原文。签名覆盖发送方序列化的载荷，而新版本节点的载荷可能
+    /// 带本地不认识的字段（解码时被忽略），按原文校验签名，转发时原样写出
+    #[serde(skip)]
+    raw_payload: Option<Box<RawValue>>,
+}
+
+/// JSON 线上格式，载荷保留原文
+#[derive(Deserialize)]
+struct RawSignedGossip {
+    #[serde(default = "default_session_id")]
+    session: String,
+    #[serde(default)]
+    sent_at_ms: u64,
+    payload: Box<RawValue>,
+    signature: SignatureBundle,
+    staking_score: f32,
+}
+
+#[derive(Serialize)]
+struct RawSignedGossipRef<'a> {
+    session: &'a str,
+    sent_at_ms: u64,
+    payload: &'a RawValue,
+    signature: &'a SignatureBundle,
+    staking_score: f32,
+}
+
+impl SignedGossip {
+    /// 签名覆盖的字节：有载荷原文时按原文，否则按本地序列化
+    pub fn signing_bytes(&self) -> serde_json::Result<Vec<u8>> {
+        match &self.raw_payload {
+            Some(raw) => signing_bytes(&self.session, self.sent_at_ms, raw.as_ref()),
+            None => signing_bytes(&self.session, self.sent_at_ms, &self.payload),
+        }
+    }
+
+    /// 从 JSON 解码并保留载荷原文
+    pub fn from_json(bytes: &[u8]) -> serde_json::Result<Self> {
+        let raw: RawSignedGossip = serde_json::from_slice(bytes)?;
+        Ok(Self {
+            payload: serde_json::from_str(raw.payload.get())?,
+            session: raw.session,
+            sent_at_ms: raw.sent_at_ms,
+            signature: raw.signature,
+            staking_score: raw.staking_score,
+            raw_payload: Some(raw.payload),
+        })
+    }
+
+    /// 编码为 JSON；收到的消息写出载荷原文，签名在其他节点上依然有效
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        let Some(raw) = &self.raw_payload else {
+            return serde_json::to_vec(self);
+        };
+        serde_json::to_vec(&RawSignedGossipRef {
+            session: &self.session,
+            sent_at_ms: self.sent_at_ms,
+            payload: raw,
+            signature: &self.signature,
+            staking_score: self.staking_score,
+        })
+    }
+
+    /// 消息 id：签名覆盖会话、时间戳与载荷，相同签名即同一条消息，
+    /// 经 gossipsub 与 QUIC 各到一次时据此去重
+    pub fn message_id(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.session.as_bytes());
+        hasher.update(self.payload.sender().as_bytes());
+        hasher.update(serde_json::to_vec(&self.signature).unwrap_or_default());
+        hasher.finalize().into()
+    }
 }
 
 pub struct ConsensusConfig {
     pub heartbeat_timeout: Duration,
+    /// 无质押节点准入所需的工作量证明难度（前导零比特），0 表示不要求
+    pub pow_difficulty: u8,
 }
 
 impl Default for ConsensusConfig {
     fn default() -> Self {
         Self {
             heartbeat_timeout: Duration::from_secs(300),
+            pow_difficulty: DEFAULT_POW_DIFFICULTY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdmissionError {
+    /// 宣告的密钥与签名密钥不一致
+    KeyMismatch,
+    /// 工作量证明难度不足
+    InsufficientWork,
+}
+
+/// 签名与声誉证明的错误，`code()` 为稳定的错误码
+#[derive(Debug, thiserror::Error)]
+pub enum ConsensusError {
+    /// 签名覆盖的内容无法编码
+    #[error("消息编码失败: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+}
+
+impl ConsensusError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConsensusError::Encode(_) => "consensus.encode",
+            ConsensusError::Crypto(e) => e.code(),
         }
     }
 }
@@ -44,6 +258,16 @@ impl Default for ConsensusConfig {
 pub struct ConsensusEngine {
     crypto: Arc<CryptoSuite>,
     ledger: RwLock<HashMap<String, StakeRecord>>,
+    /// PeerId -> 首次使用时固定的密钥（trust-on-first-use）
+    pins: RwLock<HashMap<String, KeyPin>>,
+    conflicts: RwLock<HashMap<String, KeyConflict>>,
+    /// 已通过工作量证明准入的节点
+    admitted: RwLock<HashSet<String>>,
+    /// 本地观测到的各节点连续心跳
+    streaks: RwLock<HashMap<String, Streak>>,
+    own_streak: RwLock<Option<Streak>>,
+    /// 各节点的不当行为扣分，作为 gossipsub 应用层评分
+    penalties: RwLock<HashMap<String, Penalty>>,
     config: ConsensusConfig,
 }
 
@@ -52,45 +276,250 @@ impl ConsensusEngine {
         Self {
             crypto,
             ledger: RwLock::new(HashMap::new()),
+            pins: RwLock::new(HashMap::new()),
+            conflicts: RwLock::new(HashMap::new()),
+            admitted: RwLock::new(HashSet::new()),
+            streaks: RwLock::new(HashMap::new()),
+            own_streak: RwLock::new(None),
+            penalties: RwLock::new(HashMap::new()),
             config,
         }
     }
 
-    pub fn sign(&self, payload: GgsMessage) -> anyhow::Result<SignedGossip> {
-        let bytes = serde_json::to_vec(&payload)?;
-        let signature = self.crypto.sign_bytes(&bytes)?;
-        let peer_id = match &payload {
-            GgsMessage::Heartbeat { peer, .. }
-            | GgsMessage::SimilarityProbe { sender: peer, .. }
-            | GgsMessage::SparseUpdate { sender: peer, .. }
-            | GgsMessage::DenseSnapshot { sender: peer, .. } => peer.clone(),
-        };
+    pub fn pow_difficulty(&self) -> u8 {
+        self.config.pow_difficulty
+    }
+
+    /// 为本节点身份计算准入证明的 nonce，难度超出上限时返回 None
+    pub fn solve_admission(&self, peer: &str) -> Option<u64> {
+        admission::solve(
+            peer,
+            &self.crypto.eth_address(),
+            &self.crypto.sol_address(),
+            self.config.pow_difficulty,
+        )
+    }
+
+    /// 节点是否可以参与：未要求工作量证明、已有账本记录（有质押或已知声誉）或已通过准入
+    pub fn is_admitted(&self, peer: &str) -> bool {
+        self.config.pow_difficulty == 0
+            || self.ledger.read().contains_key(peer)
+            || self.admitted.read().contains(peer)
+    }
+
+    /// 校验 `KeyAnnounce`：宣告的密钥须与已固定的签名密钥一致，且工作量证明满足难度
+    pub fn admit(
+        &self,
+        peer: &str,
+        eth_address: &str,
+        sol_pubkey: &str,
+        pow_nonce: u64,
+    ) -> Result<(), AdmissionError> {
+        let pinned = self
+            .pins
+            .read()
+            .get(peer)
+            .is_some_and(|pin| {
+                pin.eth_address.eq_ignore_ascii_case(eth_address) && pin.sol_pubkey == sol_pubkey
+            });
+        if !pinned {
+            return Err(AdmissionError::KeyMismatch);
+        }
+        if !admission::verify(peer, eth_address, sol_pubkey, pow_nonce, self.config.pow_difficulty) {
+            return Err(AdmissionError::InsufficientWork);
+        }
+        self.admitted.write().insert(peer.to_string());
+        Ok(())
+    }
+
+    pub fn sign(&self, session: &str, payload: GgsMessage) -> Result<SignedGossip, ConsensusError> {
         let staking_score = self
             .ledger
             .read()
-            .get(&peer_id)
+            .get(payload.sender())
             .map(|record| record.combined_weight())
             .unwrap_or(0.1);
-        Ok(SignedGossip {
-            payload,
-            signature,
-            staking_score,
-        })
+        sign_at(&self.crypto, session, unix_now_millis(), payload, staking_score)
     }
 
+    /// 验证签名，并检查签名密钥与该 PeerId 已固定的密钥一致
     pub fn verify(&self, msg: &SignedGossip) -> bool {
-        if let Ok(bytes) = serde_json::to_vec(&msg.payload) {
-            return self.crypto.verify(&bytes, &msg.signature);
+        let Ok(bytes) = msg.signing_bytes() else {
+            return false;
+        };
+        self.crypto.verify(&bytes, &msg.signature) && self.check_pin(msg)
+    }
+
+    /// 消息由本节点自己的链上密钥签名（热备节点与主节点共用密钥）
+    pub fn verify_own(&self, msg: &SignedGossip) -> bool {
+        let Ok(bytes) = msg.signing_bytes() else {
+            return false;
+        };
+        self.crypto.verify(&bytes, &msg.signature)
+            && msg
+                .signature
+                .eth
+                .address
+                .eq_ignore_ascii_case(&self.crypto.eth_address())
+            && msg.signature.sol.pubkey == self.crypto.sol_address()
+    }
+
+    fn check_pin(&self, msg: &SignedGossip) -> bool {
+        let peer = msg.payload.sender();
+        let presented = KeyPin {
+            peer: peer.to_string(),
+            eth_address: msg.signature.eth.address.to_lowercase(),
+            sol_pubkey: msg.signature.sol.pubkey.clone(),
+        };
+        let mut pins = self.pins.write();
+        match pins.get(peer) {
+            None => {
+                println!(
+                    "[密钥固定] 首次见到 {}，固定 eth {} / sol {}",
+                    peer, presented.eth_address, presented.sol_pubkey
+                );
+                pins.insert(peer.to_string(), presented);
+                true
+            }
+            Some(pinned) if *pinned == presented => true,
+            Some(_) => {
+                drop(pins);
+                let mut conflicts = self.conflicts.write();
+                let conflict = conflicts.entry(peer.to_string()).or_insert(KeyConflict {
+                    peer: peer.to_string(),
+                    presented_eth: String::new(),
+                    presented_sol: String::new(),
+                    attempts: 0,
+                });
+                conflict.presented_eth = presented.eth_address;
+                conflict.presented_sol = presented.sol_pubkey;
+                conflict.attempts += 1;
+                eprintln!(
+                    "[密钥固定] 拒绝 {} 的消息：密钥与固定的绑定不一致（第 {} 次）",
+                    peer, conflict.attempts
+                );
+                false
+            }
         }
-        false
     }
 
+    /// 第二层校验：gossipsub 源 PeerId（libp2p 密钥签名）必须固定到与载荷签名相同的链上身份，
+    /// 拒绝把他人已签名的载荷换一个 libp2p 身份重新发布的消息
+    pub fn verify_origin(&self, source: &str, msg: &SignedGossip) -> bool {
+        let pins = self.pins.read();
+        let Some(pin) = pins.get(source) else {
+            return false;
+        };
+        pin.eth_address == msg.signature.eth.address.to_lowercase()
+            && pin.sol_pubkey == msg.signature.sol.pubkey
+    }
+
+    /// 来源无法验证的消息（如经中继转发）：签名有效且密钥与已固定的绑定一致，不做首次固定
+    pub fn verify_pinned(&self, msg: &SignedGossip) -> bool {
+        let Ok(bytes) = msg.signing_bytes() else {
+            return false;
+        };
+        self.pins.read().get(msg.payload.sender()).is_some_and(|pin| {
+            pin.eth_address == msg.signature.eth.address.to_lowercase()
+                && pin.sol_pubkey == msg.signature.sol.pubkey
+        }) && self.crypto.verify(&bytes, &msg.signature)
+    }
+
+    /// 用本节点链上密钥对任意字节签名（不封装为消息，如信标份额）
+    pub fn sign_detached(&self, bytes: &[u8]) -> Result<SignatureBundle, ConsensusError> {
+        Ok(self.crypto.sign_bytes(bytes)?)
+    }
+
+    /// 校验 `peer` 的独立签名：签名有效且密钥与该 PeerId 已固定的绑定一致
+    pub fn verify_detached(&self, peer: &str, bytes: &[u8], signature: &SignatureBundle) -> bool {
+        self.pins.read().get(peer).is_some_and(|pin| {
+            pin.eth_address == signature.eth.address.to_lowercase() && pin.sol_pubkey == signature.sol.pubkey
+        }) && self.crypto.verify(bytes, signature)
+    }
+
+    /// 独立签名由本节点自己的链上密钥签出
+    pub fn verify_own_detached(&self, bytes: &[u8], signature: &SignatureBundle) -> bool {
+        signature.eth.address.eq_ignore_ascii_case(&self.crypto.eth_address())
+            && signature.sol.pubkey == self.crypto.sol_address()
+            && self.crypto.verify(bytes, signature)
+    }
+
+    pub fn key_pin(&self, peer: &str) -> Option<KeyPin> {
+        self.pins.read().get(peer).cloned()
+    }
+
+    pub fn export_pins(&self) -> Vec<KeyPin> {
+        self.pins.read().values().cloned().collect()
+    }
+
+    /// 恢复持久化的密钥绑定，避免重启后重新 trust-on-first-use
+    pub fn import_pins(&self, pins: Vec<KeyPin>) {
+        let mut current = self.pins.write();
+        for pin in pins {
+            current.insert(pin.peer.clone(), pin);
+        }
+    }
+
+    /// 把旧 PeerId 的账本记录迁移到新 PeerId。
+    /// 只有两者固定的 ETH 地址一致（同一链上身份）时才迁移，已有记录取较大值合并。
+    pub fn migrate_identity(&self, previous: &str, new: &str) -> bool {
+        {
+            let pins = self.pins.read();
+            let (Some(old_pin), Some(new_pin)) = (pins.get(previous), pins.get(new)) else {
+                return false;
+            };
+            if previous == new || old_pin.eth_address != new_pin.eth_address {
+                return false;
+            }
+        }
+        let mut ledger = self.ledger.write();
+        let Some(old) = ledger.remove(previous) else {
+            return false;
+        };
+        ledger
+            .entry(new.to_string())
+            .and_modify(|record| {
+                record.stake_eth = record.stake_eth.max(old.stake_eth);
+                record.stake_sol = record.stake_sol.max(old.stake_sol);
+                record.reputation = record.reputation.max(old.reputation);
+                record.participation = record.participation.max(old.participation);
+                record.last_seen = Instant::now();
+            })
+            .or_insert(StakeRecord {
+                last_seen: Instant::now(),
+                ..old
+            });
+        true
+    }
+
+    /// 导出本节点的声誉迁移证明
+    pub fn export_attestation(
+        &self,
+        previous_peer: &str,
+    ) -> Result<ReputationAttestation, ConsensusError> {
+        ReputationAttestation::create(
+            &self.crypto,
+            previous_peer.to_string(),
+            self.export_ledger(),
+            self.export_pins(),
+        )
+    }
+
+    pub fn key_conflicts(&self) -> Vec<KeyConflict> {
+        self.conflicts.read().values().cloned().collect()
+    }
+
+    /// 更新账本；未准入的节点不会创建新条目
     pub fn update_stake(&self, peer: &str, delta_eth: f64, delta_sol: f64, reputation_delta: f64) {
+        if !self.is_admitted(peer) {
+            return;
+        }
         let mut ledger = self.ledger.write();
         let entry = ledger.entry(peer.to_string()).or_insert(StakeRecord {
             stake_eth: 1.0,
             stake_sol: 0.1,
             reputation: 1.0,
+            participation: 0,
             last_seen: Instant::now(),
         });
         entry.stake_eth = (entry.stake_eth + delta_eth).max(0.0);
@@ -99,10 +528,181 @@ impl ConsensusEngine {
         entry.last_seen = Instant::now();
     }
 
-    pub fn prune_stale(&self) {
+    /// 按可验证的链上余额设置质押；未准入的节点不会创建新条目
+    pub fn set_stake(&self, peer: &str, stake_eth: f64, stake_sol: f64) {
+        if !self.is_admitted(peer) {
+            return;
+        }
         let mut ledger = self.ledger.write();
+        let entry = ledger.entry(peer.to_string()).or_insert(StakeRecord {
+            stake_eth,
+            stake_sol,
+            reputation: 1.0,
+            participation: 0,
+            last_seen: Instant::now(),
+        });
+        entry.stake_eth = stake_eth.max(0.0);
+        entry.stake_sol = stake_sol.max(0.0);
+        entry.last_seen = Instant::now();
+    }
+
+    /// 清出心跳超时的节点，返回被清出的条目以便写入持久化账本
+    pub fn prune_stale(&self) -> Vec<LedgerEntry> {
         let deadline = Instant::now() - self.config.heartbeat_timeout;
-        ledger.retain(|_, record| record.last_seen >= deadline);
+        self.streaks.write().retain(|_, streak| streak.last >= deadline);
+        let mut ledger = self.ledger.write();
+        let mut evicted = Vec::new();
+        ledger.retain(|peer, record| {
+            let keep = record.last_seen >= deadline;
+            if !keep {
+                evicted.push(LedgerEntry {
+                    peer: peer.clone(),
+                    stake_eth: record.stake_eth,
+                    stake_sol: record.stake_sol,
+                    reputation: record.reputation,
+                });
+            }
+            keep
+        });
+        evicted
+    }
+
+    /// 本节点发布一轮心跳，返回本节点的连续心跳数（随心跳宣告）
+    pub fn record_own_heartbeat(&self, interval: Duration) -> u32 {
+        let mut own = self.own_streak.write();
+        let streak = Streak::advance(*own, Instant::now(), interval);
+        *own = Some(streak);
+        streak.count
+    }
+
+    /// 记录节点的心跳并更新其参与度，返回核实后的连续心跳数
+    pub fn observe_heartbeat(&self, peer: &str, claimed: u32, interval: Duration) -> u32 {
+        self.observe_heartbeat_at(peer, claimed, interval, Instant::now())
+    }
+
+    /// 宣告值只在本地观测值的若干倍以内采信：新加入的节点可以较快认可长期在线的节点，
+    /// 刚出现的节点却无法靠虚报获得参与度
+    fn observe_heartbeat_at(&self, peer: &str, claimed: u32, interval: Duration, now: Instant) -> u32 {
+        let observed = {
+            let mut streaks = self.streaks.write();
+            let streak = Streak::advance(streaks.get(peer).copied(), now, interval);
+            streaks.insert(peer.to_string(), streak);
+            streak.count
+        };
+        let verified = claimed
+            .min(observed.saturating_mul(STREAK_CLAIM_FACTOR))
+            .max(observed);
+        if let Some(record) = self.ledger.write().get_mut(peer) {
+            record.participation = verified;
+        }
+        verified
+    }
+
+    /// 记录节点的一次不当行为，扣分随时间衰减
+    pub fn record_misbehavior(&self, peer: &str, kind: Misbehavior) {
+        self.record_misbehavior_at(peer, kind, Instant::now());
+    }
+
+    fn record_misbehavior_at(&self, peer: &str, kind: Misbehavior, now: Instant) {
+        let mut penalties = self.penalties.write();
+        let points = penalties.get(peer).map_or(0.0, |p| p.decayed(now)) + kind.penalty();
+        penalties.insert(peer.to_string(), Penalty { points, at: now });
+    }
+
+    /// 各节点的 gossipsub 应用层评分（扣分取负）；扣分衰减完的节点最后返回一次 0 后不再跟踪
+    pub fn application_scores(&self) -> Vec<(String, f64)> {
+        self.application_scores_at(Instant::now())
+    }
+
+    fn application_scores_at(&self, now: Instant) -> Vec<(String, f64)> {
+        let mut scores = Vec::new();
+        self.penalties.write().retain(|peer, penalty| {
+            let points = penalty.decayed(now);
+            let tracked = points >= MIN_PENALTY;
+            scores.push((peer.clone(), if tracked { -points } else { 0.0 }));
+            tracked
+        });
+        scores
+    }
+
+    pub fn has_record(&self, peer: &str) -> bool {
+        self.ledger.read().contains_key(peer)
+    }
+
+    pub fn export_ledger(&self) -> Vec<LedgerEntry> {
+        self.ledger
+            .read()
+            .iter()
+            .map(|(peer, record)| LedgerEntry {
+                peer: peer.clone(),
+                stake_eth: record.stake_eth,
+                stake_sol: record.stake_sol,
+                reputation: record.reputation,
+            })
+            .collect()
+    }
+
+    /// 导入持久化的账本，恢复的条目视为刚刚活跃
+    pub fn import_ledger(&self, entries: Vec<LedgerEntry>) {
+        let mut ledger = self.ledger.write();
+        let now = Instant::now();
+        for entry in entries {
+            ledger.insert(
+                entry.peer,
+                StakeRecord {
+                    stake_eth: entry.stake_eth,
+                    stake_sol: entry.stake_sol,
+                    reputation: entry.reputation,
+                    participation: 0,
+                    last_seen: now,
+                },
+            );
+        }
+    }
+
+    /// 并入邻居同步来的账本。同步应答没有签名，其中的质押与声誉一律不采信：只为 `backed`
+    /// （本节点持有其签名质押证明）且本地没有记录的节点补入记录，质押为 0、声誉取初始值，
+    /// 质押待证明按链上状态确认后再设置。最多处理前 1024 条，返回补入的条目数
+    pub fn merge_ledger(&self, entries: Vec<LedgerEntry>, backed: impl Fn(&str) -> bool) -> usize {
+        let mut ledger = self.ledger.write();
+        let now = Instant::now();
+        let mut added = 0;
+        for entry in entries.into_iter().take(MAX_SYNCED_LEDGER_ENTRIES) {
+            if !backed(&entry.peer) {
+                continue;
+            }
+            if let std::collections::hash_map::Entry::Vacant(slot) = ledger.entry(entry.peer) {
+                slot.insert(StakeRecord {
+                    stake_eth: 0.0,
+                    stake_sol: 0.0,
+                    reputation: 1.0,
+                    participation: 0,
+                    last_seen: now,
+                });
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// 本节点的 ETH 地址
+    pub fn local_eth_address(&self) -> String {
+        self.crypto.eth_address()
+    }
+
+    /// 账本中所有节点的共识权重之和
+    pub fn total_stake_weight(&self) -> f32 {
+        self.ledger.read().values().map(StakeRecord::combined_weight).sum()
+    }
+
+    /// 有链上质押的节点及其质押量（ETH + SOL），不含本地观测的声誉与参与度，各节点视角一致
+    pub fn staked_peers(&self) -> Vec<(String, f32)> {
+        self.ledger
+            .read()
+            .iter()
+            .map(|(peer, record)| (peer.clone(), (record.stake_eth + record.stake_sol) as f32))
+            .filter(|(_, stake)| *stake > 0.0)
+            .collect()
     }
 
     pub fn stake_weight(&self, peer: &str) -> f32 {
@@ -113,3 +713,204 @@ impl ConsensusEngine {
             .unwrap_or(0.0)
     }
 }
+
+/// 只校验消息签名本身，不检查密钥固定（离线检查消息时使用）
+pub fn signature_valid(msg: &SignedGossip) -> bool {
+    let Ok(bytes) = msg.signing_bytes() else {
+        return false;
+    };
+    verify_eth(&bytes, &msg.signature.eth) && verify_sol(&bytes, &msg.signature.sol)
+}
+
+/// 以给定的发送时间与质押分签名；签名是确定性的，同样的输入得到同样的消息（测试向量依赖这一点）
+pub fn sign_at(
+    crypto: &CryptoSuite,
+    session: &str,
+    sent_at_ms: u64,
+    payload: GgsMessage,
+    staking_score: f32,
+) -> Result<SignedGossip, ConsensusError> {
+    let bytes = signing_bytes(session, sent_at_ms, &payload)?;
+    let signature = crypto.sign_bytes(&bytes)?;
+    Ok(SignedGossip {
+        session: session.to_string(),
+        sent_at_ms,
+        payload,
+        signature,
+        staking_score,
+        raw_payload: None,
+    })
+}
+
+/// 签名覆盖的字节：携带时间戳的消息签入 (会话, 时间戳, payload)；
+/// 旧版本消息在默认会话只签 payload，其他会话把会话 id 一并签入
+fn signing_bytes<P: Serialize + ?Sized>(
+    session: &str,
+    sent_at_ms: u64,
+    payload: &P,
+) -> serde_json::Result<Vec<u8>> {
+    if sent_at_ms != 0 {
+        serde_json::to_vec(&(session, sent_at_ms, payload))
+    } else if session == DEFAULT_SESSION {
+        serde_json::to_vec(payload)
+    } else {
+        serde_json::to_vec(&(session, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+    use crate::role::NodeRole;
+
+    fn engine() -> ConsensusEngine {
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        ConsensusEngine::new(crypto, ConsensusConfig::default())
+    }
+
+    fn heartbeat(peer: &str) -> GgsMessage {
+        GgsMessage::Heartbeat {
+            peer: peer.into(),
+            model_hash: "0x0".into(),
+            model_version: 1,
+            role: NodeRole::Trainer,
+            quic_addr: None,
+            compute: None,
+            stake_proof: None,
+            chunk_hashes: None,
+            codecs: Vec::new(),
+            streak: 0,
+            quic_addrs: Vec::new(),
+            listen_addrs: Vec::new(),
+            base_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_streak_rewards_sustained_heartbeats() {
+        let engine = ConsensusEngine::new(
+            Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap()),
+            ConsensusConfig {
+                pow_difficulty: 0,
+                ..ConsensusConfig::default()
+            },
+        );
+        let interval = Duration::from_secs(10);
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        engine.update_stake("steady", 0.0, 0.0, 0.0);
+        engine.update_stake("flash", 0.0, 0.0, 0.0);
+        let base = engine.stake_weight("steady");
+
+        let mut verified = 0;
+        for beat in 0..100 {
+            verified = engine.observe_heartbeat_at("steady", 1000 + beat, interval, at(beat as u64 * 10));
+            // 同一周期内其他会话的心跳不计数
+            engine.observe_heartbeat_at("steady", 1000 + beat, interval, at(beat as u64 * 10 + 1));
+        }
+        assert_eq!(verified, 400);
+        assert!(engine.stake_weight("steady") > base + 0.5);
+
+        // 新节点虚报的连续心跳数不被采信
+        assert_eq!(engine.observe_heartbeat_at("flash", 100_000, interval, at(0)), 4);
+        assert!(engine.stake_weight("flash") < base + 0.02);
+
+        // 中断超过三个周期后重新计数
+        assert_eq!(engine.observe_heartbeat_at("steady", 0, interval, at(2000)), 1);
+    }
+
+    #[test]
+    fn test_misbehavior_scores_decay() {
+        let engine = engine();
+        let start = Instant::now();
+        engine.record_misbehavior_at("spammer", Misbehavior::InvalidSignature, start);
+        engine.record_misbehavior_at("spammer", Misbehavior::InvalidUpdate, start);
+        assert_eq!(engine.application_scores_at(start), vec![("spammer".to_string(), -30.0)]);
+
+        let score = engine.application_scores_at(start + MISBEHAVIOR_HALF_LIFE)[0].1;
+        assert!((score + 15.0).abs() < 1e-6);
+        // 衰减完后返回一次 0 让 gossipsub 恢复评分，之后不再跟踪
+        let later = start + MISBEHAVIOR_HALF_LIFE * 20;
+        assert_eq!(engine.application_scores_at(later), vec![("spammer".to_string(), 0.0)]);
+        assert!(engine.application_scores_at(later).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_sync_never_imports_stake() {
+        let engine = engine();
+        let entry = |peer: &str| LedgerEntry {
+            peer: peer.into(),
+            stake_eth: 1000.0,
+            stake_sol: 1000.0,
+            reputation: 50.0,
+        };
+        let added = engine.merge_ledger(vec![entry("proven"), entry("unknown")], |p| p == "proven");
+        assert_eq!(added, 1);
+        assert!(!engine.has_record("unknown"));
+        assert!(engine.staked_peers().is_empty());
+        let exported = engine.export_ledger();
+        assert_eq!(exported[0].reputation, 1.0);
+
+        let flood = (0..2000).map(|i| entry(&format!("peer-{i}"))).collect();
+        let added = engine.merge_ledger(flood, |_| true);
+        assert_eq!(added, MAX_SYNCED_LEDGER_ENTRIES);
+    }
+
+    #[test]
+    fn test_rejects_peer_id_with_different_keys() {
+        let (honest, impostor, observer) = (engine(), engine(), engine());
+        let genuine = honest.sign(DEFAULT_SESSION, heartbeat("peer-a")).unwrap();
+        assert!(observer.verify(&genuine));
+        assert!(observer.verify(&genuine));
+
+        let forged = impostor.sign(DEFAULT_SESSION, heartbeat("peer-a")).unwrap();
+        assert!(!observer.verify(&forged));
+        assert_eq!(observer.key_conflicts()[0].attempts, 1);
+    }
+
+    #[test]
+    fn test_rejects_relabeled_gossip_source() {
+        let (honest, relabeler, observer) = (engine(), engine(), engine());
+        let genuine = honest.sign(DEFAULT_SESSION, heartbeat("peer-a")).unwrap();
+        assert!(observer.verify(&genuine));
+        assert!(observer.verify_origin("peer-a", &genuine));
+
+        // peer-m 以自己的 libp2p 身份重新发布 peer-a 签名的载荷
+        assert!(observer.verify(&relabeler.sign(DEFAULT_SESSION, heartbeat("peer-m")).unwrap()));
+        assert!(!observer.verify_origin("peer-m", &genuine));
+        assert!(!observer.verify_origin("peer-unknown", &genuine));
+
+        // 来源不可验证时不固定新身份
+        let unseen = relabeler.sign(DEFAULT_SESSION, heartbeat("peer-b")).unwrap();
+        assert!(!observer.verify_pinned(&unseen));
+        assert!(observer.key_pin("peer-b").is_none());
+        assert!(observer.verify_pinned(&genuine));
+    }
+
+    #[test]
+    fn test_verifies_payload_fields_unknown_to_this_version() {
+        let (newer, observer) = (engine(), engine());
+        // 新版本节点的心跳带一个本地不认识的字段
+        let mut payload = serde_json::to_value(heartbeat("peer-a")).unwrap();
+        payload["Heartbeat"]["future_field"] = serde_json::json!(42);
+        let sent_at_ms = 1_700_000_000_000u64;
+        let bytes = signing_bytes(DEFAULT_SESSION, sent_at_ms, &payload).unwrap();
+        let wire = serde_json::to_vec(&serde_json::json!({
+            "session": DEFAULT_SESSION,
+            "sent_at_ms": sent_at_ms,
+            "payload": payload,
+            "signature": newer.crypto.sign_bytes(&bytes).unwrap(),
+            "staking_score": 0.1,
+        }))
+        .unwrap();
+
+        // 按本地序列化会丢掉未知字段而验签失败，按原文则通过，转发后仍然有效
+        let reserialized: SignedGossip = serde_json::from_slice(&wire).unwrap();
+        assert!(!signature_valid(&reserialized));
+        let received = SignedGossip::from_json(&wire).unwrap();
+        assert!(observer.verify(&received));
+        let relayed = SignedGossip::from_json(&received.to_json().unwrap()).unwrap();
+        assert!(engine().verify(&relayed));
+    }
+}
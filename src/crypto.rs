@@ -1,18 +1,71 @@
-use anyhow::{anyhow, Result};
+use crate::persistence::write_secret;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
 use ed25519_dalek::{
     Keypair as SolKeypair, PublicKey as SolPublicKey, SecretKey as SolSecretKey,
     Signature as SolRawSignature, Signer as SolSigner, Verifier as SolVerifier,
 };
 use k256::ecdsa::{
-    signature::{Signer, Verifier},
-    Signature as EthSignatureRaw, SigningKey, VerifyingKey,
+    signature::Signer, RecoveryId, Signature as EthSignatureRaw, SigningKey, VerifyingKey,
 };
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+type Result<T, E = CryptoError> = std::result::Result<T, E>;
+
+/// 密钥与签名相关的错误，`code()` 为稳定的错误码
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    /// 种子格式或长度无效
+    #[error("{kind} seed invalid: {reason}")]
+    InvalidSeed { kind: &'static str, reason: String },
+    #[error("签名失败: {0}")]
+    Signing(String),
+    #[error("密钥库 {0} 已存在，拒绝覆盖")]
+    KeystoreExists(PathBuf),
+    #[error("密钥库口令不能为空")]
+    EmptyPassphrase,
+    /// 口令错误，或密钥文件（包括其中的地址）被篡改
+    #[error("无法解锁 {0} 密钥：口令错误或文件已损坏")]
+    Unlock(String),
+    #[error("密钥文件 {path} 无效: {reason}")]
+    InvalidKeyFile { path: PathBuf, reason: String },
+    #[error("密钥库 {0} 中的地址与种子不符")]
+    AddressMismatch(PathBuf),
+    /// scrypt 派生或 AES-GCM 加密失败
+    #[error("密钥加密失败: {0}")]
+    Cipher(String),
+    #[error("密钥库读写失败: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl CryptoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CryptoError::InvalidSeed { .. } => "crypto.invalid_seed",
+            CryptoError::Signing(_) => "crypto.signing",
+            CryptoError::KeystoreExists(_) => "crypto.keystore_exists",
+            CryptoError::EmptyPassphrase => "crypto.empty_passphrase",
+            CryptoError::Unlock(_) => "crypto.unlock",
+            CryptoError::InvalidKeyFile { .. } => "crypto.invalid_key_file",
+            CryptoError::AddressMismatch(_) => "crypto.address_mismatch",
+            CryptoError::Cipher(_) => "crypto.cipher",
+            CryptoError::Io(_) => "crypto.io",
+        }
+    }
+
+    fn seed(kind: &'static str, reason: impl ToString) -> Self {
+        CryptoError::InvalidSeed {
+            kind,
+            reason: reason.to_string(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EthSignature {
     pub address: String,
@@ -31,18 +84,12 @@ pub struct SignatureBundle {
     pub sol: SolSignature,
 }
 
+#[derive(Clone, Default)]
 pub struct CryptoConfig {
     pub eth_hex_seed: Option<String>,
     pub sol_bs58_seed: Option<String>,
-}
-
-impl Default for CryptoConfig {
-    fn default() -> Self {
-        Self {
-            eth_hex_seed: None,
-            sol_bs58_seed: None,
-        }
-    }
+    /// 加密密钥库目录，设置后种子从其中解锁
+    pub keystore: Option<PathBuf>,
 }
 
 #[derive(Clone)]
@@ -70,8 +117,33 @@ impl CryptoSuite {
         })
     }
 
+    /// 仅用 ETH 密钥签名（链上身份证明）
+    pub fn sign_eth(&self, payload: &[u8]) -> Result<EthSignature> {
+        self.eth.sign(payload)
+    }
+
+    /// 对 32 字节摘要做可恢复 ETH 签名（链上交易），返回 (r || s, recovery id)
+    pub fn sign_eth_digest(&self, digest: &[u8; 32]) -> Result<([u8; 64], u8)> {
+        let (signature, recovery_id) = self
+            .eth
+            .signing_key
+            .sign_prehash_recoverable(digest)
+            .map_err(|e| CryptoError::Signing(e.to_string()))?;
+        Ok((signature.to_bytes().into(), recovery_id.to_byte()))
+    }
+
+    /// 随机生成一对新种子（ETH 十六进制 / Solana base58）
+    pub fn generate_seeds() -> CryptoConfig {
+        CryptoConfig {
+            eth_hex_seed: Some(hex::encode(random_bytes())),
+            sol_bs58_seed: Some(bs58::encode(random_bytes()).into_string()),
+            keystore: None,
+        }
+    }
+
+    /// 使用签名中携带的地址 / 公钥验证（可验证任意节点的签名）
     pub fn verify(&self, payload: &[u8], sig: &SignatureBundle) -> bool {
-        self.eth.verify(payload, &sig.eth) && self.sol.verify(payload, &sig.sol)
+        verify_eth(payload, &sig.eth) && verify_sol(payload, &sig.sol)
     }
 
     pub fn eth_address(&self) -> String {
@@ -85,28 +157,26 @@ impl CryptoSuite {
 
 struct EthIdentity {
     signing_key: SigningKey,
-    verifying_key: VerifyingKey,
     address: String,
 }
 
 impl EthIdentity {
     fn new(seed: Option<String>) -> Result<Self> {
         let secret = if let Some(seed_hex) = seed {
-            let bytes = hex::decode(seed_hex.trim_start_matches("0x"))?;
+            let bytes = hex::decode(seed_hex.trim_start_matches("0x"))
+                .map_err(|e| CryptoError::seed("eth", e))?;
             let arr: [u8; 32] = bytes
                 .try_into()
-                .map_err(|_| anyhow!("eth seed must be 32 bytes"))?;
+                .map_err(|_| CryptoError::seed("eth", "must be 32 bytes"))?;
             arr
         } else {
             random_bytes()
         };
         let signing_key =
-            SigningKey::from_bytes(&secret.into()).map_err(|e| anyhow!(e.to_string()))?;
-        let verifying_key = signing_key.verifying_key().clone();
-        let address = eth_address_from_key(&verifying_key);
+            SigningKey::from_bytes(&secret.into()).map_err(|e| CryptoError::seed("eth", e))?;
+        let address = eth_address_from_key(signing_key.verifying_key());
         Ok(Self {
             signing_key,
-            verifying_key,
             address,
         })
     }
@@ -120,18 +190,39 @@ impl EthIdentity {
         })
     }
 
-    fn verify(&self, payload: &[u8], sig: &EthSignature) -> bool {
-        if sig.address.to_lowercase() != self.address.to_lowercase() {
-            return false;
-        }
-        if let Ok(bytes) = hex::decode(&sig.signature) {
-            if let Ok(signature) = EthSignatureRaw::try_from(bytes.as_slice()) {
-                let digest = keccak(payload);
-                return self.verifying_key.verify(&digest, &signature).is_ok();
-            }
-        }
-        false
-    }
+}
+
+/// 签名不含 recovery id，尝试两种取值恢复公钥，并与声明的地址比对
+pub fn verify_eth(payload: &[u8], sig: &EthSignature) -> bool {
+    let Ok(bytes) = hex::decode(&sig.signature) else {
+        return false;
+    };
+    let Ok(signature) = EthSignatureRaw::try_from(bytes.as_slice()) else {
+        return false;
+    };
+    let digest = keccak(payload);
+    let claimed = sig.address.to_lowercase();
+    (0..=1).filter_map(RecoveryId::from_byte).any(|recovery_id| {
+        VerifyingKey::recover_from_msg(&digest, &signature, recovery_id)
+            .map(|key| eth_address_from_key(&key) == claimed)
+            .unwrap_or(false)
+    })
+}
+
+pub fn verify_sol(payload: &[u8], sig: &SolSignature) -> bool {
+    let Ok(pubkey_bytes) = bs58::decode(&sig.pubkey).into_vec() else {
+        return false;
+    };
+    let Ok(pubkey) = SolPublicKey::from_bytes(&pubkey_bytes) else {
+        return false;
+    };
+    let Ok(sig_bytes) = bs58::decode(&sig.signature).into_vec() else {
+        return false;
+    };
+    let Ok(signature) = SolRawSignature::from_bytes(&sig_bytes) else {
+        return false;
+    };
+    pubkey.verify(payload, &signature).is_ok()
 }
 
 struct SolIdentity {
@@ -142,7 +233,9 @@ struct SolIdentity {
 impl SolIdentity {
     fn new(seed: Option<String>) -> Result<Self> {
         let keypair = if let Some(bs58_seed) = seed {
-            let bytes = bs58::decode(bs58_seed).into_vec()?;
+            let bytes = bs58::decode(bs58_seed)
+                .into_vec()
+                .map_err(|e| CryptoError::seed("sol", e))?;
             match bytes.len() {
                 32 => {
                     let mut arr = [0u8; 32];
@@ -152,9 +245,9 @@ impl SolIdentity {
                 64 => {
                     let mut arr = [0u8; 64];
                     arr.copy_from_slice(&bytes);
-                    SolKeypair::from_bytes(&arr).map_err(|e| anyhow!("sol key error: {e}"))?
+                    SolKeypair::from_bytes(&arr).map_err(|e| CryptoError::seed("sol", e))?
                 }
-                _ => return Err(anyhow!("Solana seed must be 32 or 64 bytes")),
+                _ => return Err(CryptoError::seed("sol", "must be 32 or 64 bytes")),
             }
         } else {
             keypair_from_secret(random_bytes())?
@@ -171,16 +264,211 @@ impl SolIdentity {
         })
     }
 
-    fn verify(&self, payload: &[u8], sig: &SolSignature) -> bool {
-        if sig.pubkey != self.pubkey {
-            return false;
+}
+
+/// 密钥库口令通过环境变量传入，避免出现在进程参数中
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "GGS_KEYSTORE_PASSPHRASE";
+pub const ETH_KEY_FILE: &str = "eth.key.json";
+pub const SOL_KEY_FILE: &str = "sol.key.json";
+
+/// 默认 scrypt 参数：N = 2^15, r = 8, p = 1（约 32 MiB 内存）
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// 单个加密密钥文件：scrypt 由口令派生密钥，AES-256-GCM 加密种子，地址作为附加认证数据
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub version: u32,
+    /// "eth" 或 "sol"
+    pub kind: String,
+    pub address: String,
+    pub kdf: ScryptParams,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+impl EncryptedKey {
+    fn seal(kind: &str, address: &str, secret: &[u8], passphrase: &str, log_n: u8) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let kdf = ScryptParams {
+            log_n,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(salt),
+        };
+        let cipher = kdf.cipher(passphrase)?;
+        let ciphertext = cipher
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: secret,
+                    aad: address.as_bytes(),
+                },
+            )
+            .map_err(|_| CryptoError::Cipher(format!("加密 {} 密钥失败", kind)))?;
+        Ok(Self {
+            version: 1,
+            kind: kind.to_string(),
+            address: address.to_string(),
+            kdf,
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// 口令错误或文件被篡改（包括地址）时解密失败
+    fn open(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let cipher = self.kdf.cipher(passphrase)?;
+        let unlock = || CryptoError::Unlock(self.kind.clone());
+        let nonce: [u8; 12] = hex::decode(&self.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(unlock)?;
+        let ciphertext = hex::decode(&self.ciphertext).map_err(|_| unlock())?;
+        cipher
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.address.as_bytes(),
+                },
+            )
+            .map_err(|_| unlock())
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| CryptoError::InvalidKeyFile {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let bytes = std::fs::read(path).map_err(|e| invalid(e.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|e| invalid(e.to_string()))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(std::io::Error::from)?;
+        write_secret(path, &bytes).map_err(std::io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl ScryptParams {
+    fn cipher(&self, passphrase: &str) -> Result<Aes256Gcm> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, 32)
+            .map_err(|e| CryptoError::Cipher(format!("scrypt 参数无效: {}", e)))?;
+        let salt =
+            hex::decode(&self.salt).map_err(|e| CryptoError::Cipher(format!("salt 无效: {}", e)))?;
+        let mut key = [0u8; 32];
+        scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key)
+            .map_err(|e| CryptoError::Cipher(format!("scrypt 派生失败: {}", e)))?;
+        Aes256Gcm::new_from_slice(&key).map_err(|e| CryptoError::Cipher(e.to_string()))
+    }
+}
+
+/// 磁盘上的加密密钥库：目录中分别保存 ETH 与 Solana 密钥文件
+pub struct Keystore {
+    dir: PathBuf,
+    log_n: u8,
+}
+
+impl Keystore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            log_n: SCRYPT_LOG_N,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.dir.join(ETH_KEY_FILE).exists() || self.dir.join(SOL_KEY_FILE).exists()
+    }
+
+    /// 加密保存种子；未给出的种子随机生成。已有密钥文件时拒绝覆盖
+    pub fn create(&self, passphrase: &str, seeds: &CryptoConfig) -> Result<CryptoConfig> {
+        if self.exists() {
+            return Err(CryptoError::KeystoreExists(self.dir.clone()));
+        }
+        if passphrase.is_empty() {
+            return Err(CryptoError::EmptyPassphrase);
         }
-        if let Ok(bytes) = bs58::decode(&sig.signature).into_vec() {
-            if let Ok(signature) = SolRawSignature::from_bytes(&bytes) {
-                return self.keypair.public.verify(payload, &signature).is_ok();
+        let eth_secret = match &seeds.eth_hex_seed {
+            Some(seed) => hex::decode(seed.trim_start_matches("0x"))
+                .map_err(|e| CryptoError::seed("eth", e))?,
+            None => random_bytes().to_vec(),
+        };
+        let sol_secret = match &seeds.sol_bs58_seed {
+            Some(seed) => bs58::decode(seed)
+                .into_vec()
+                .map_err(|e| CryptoError::seed("sol", e))?,
+            None => random_bytes().to_vec(),
+        };
+        let unlocked = CryptoConfig {
+            eth_hex_seed: Some(hex::encode(&eth_secret)),
+            sol_bs58_seed: Some(bs58::encode(&sol_secret).into_string()),
+            keystore: Some(self.dir.clone()),
+        };
+        // 先构造身份，既校验种子又得到写入文件的地址
+        let eth = EthIdentity::new(unlocked.eth_hex_seed.clone())?;
+        let sol = SolIdentity::new(unlocked.sol_bs58_seed.clone())?;
+        std::fs::create_dir_all(&self.dir)?;
+        EncryptedKey::seal("eth", &eth.address, &eth_secret, passphrase, self.log_n)?
+            .save(&self.dir.join(ETH_KEY_FILE))?;
+        EncryptedKey::seal("sol", &sol.pubkey, &sol_secret, passphrase, self.log_n)?
+            .save(&self.dir.join(SOL_KEY_FILE))?;
+        println!(
+            "[密钥库] 已在 {} 创建加密密钥: ETH {} / SOL {}",
+            self.dir.display(),
+            eth.address,
+            sol.pubkey
+        );
+        Ok(unlocked)
+    }
+
+    /// 解锁两个密钥文件，并校验解出的种子与文件记录的地址一致
+    pub fn unlock(&self, passphrase: &str) -> Result<CryptoConfig> {
+        let eth_file = EncryptedKey::load(&self.dir.join(ETH_KEY_FILE))?;
+        let sol_file = EncryptedKey::load(&self.dir.join(SOL_KEY_FILE))?;
+        let unlocked = CryptoConfig {
+            eth_hex_seed: Some(hex::encode(eth_file.open(passphrase)?)),
+            sol_bs58_seed: Some(bs58::encode(sol_file.open(passphrase)?).into_string()),
+            keystore: Some(self.dir.clone()),
+        };
+        let eth = EthIdentity::new(unlocked.eth_hex_seed.clone())?;
+        let sol = SolIdentity::new(unlocked.sol_bs58_seed.clone())?;
+        if eth.address != eth_file.address || sol.pubkey != sol_file.address {
+            return Err(CryptoError::AddressMismatch(self.dir.clone()));
+        }
+        println!(
+            "[密钥库] 已解锁 {}: ETH {} / SOL {}",
+            self.dir.display(),
+            eth.address,
+            sol.pubkey
+        );
+        Ok(unlocked)
+    }
+
+    /// 密钥库存在则解锁，否则用给定种子（或随机种子）创建
+    pub fn open_or_create(&self, passphrase: &str, seeds: &CryptoConfig) -> Result<CryptoConfig> {
+        if self.exists() {
+            if seeds.eth_hex_seed.is_some() || seeds.sol_bs58_seed.is_some() {
+                println!("[密钥库] 密钥库已存在，忽略明文种子");
             }
+            self.unlock(passphrase)
+        } else {
+            self.create(passphrase, seeds)
         }
-        false
     }
 }
 
@@ -205,8 +493,47 @@ fn random_bytes() -> [u8; 32] {
 }
 
 fn keypair_from_secret(secret_bytes: [u8; 32]) -> Result<SolKeypair> {
-    let secret =
-        SolSecretKey::from_bytes(&secret_bytes).map_err(|e| anyhow!("sol key error: {e}"))?;
+    let secret = SolSecretKey::from_bytes(&secret_bytes).map_err(|e| CryptoError::seed("sol", e))?;
     let public = SolPublicKey::from(&secret);
     Ok(SolKeypair { secret, public })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_roundtrip_and_wrong_passphrase() {
+        let dir = std::env::temp_dir().join(format!("ggs-keystore-{}", hex::encode(random_bytes())));
+        // 测试中降低 scrypt 成本
+        let keystore = Keystore {
+            dir: dir.clone(),
+            log_n: 4,
+        };
+        let created = keystore
+            .create("correct horse", &CryptoConfig::default())
+            .unwrap();
+        assert!(matches!(
+            keystore.create("correct horse", &created),
+            Err(CryptoError::KeystoreExists(_))
+        ));
+
+        let unlocked = keystore.unlock("correct horse").unwrap();
+        assert_eq!(unlocked.eth_hex_seed, created.eth_hex_seed);
+        assert_eq!(unlocked.sol_bs58_seed, created.sol_bs58_seed);
+        let suite = CryptoSuite::new(unlocked).unwrap();
+        let file = std::fs::read_to_string(dir.join(ETH_KEY_FILE)).unwrap();
+        assert!(file.contains(&suite.eth_address()));
+        assert!(!file.contains(created.eth_hex_seed.as_deref().unwrap()));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = std::fs::metadata(dir.join(ETH_KEY_FILE)).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        }
+
+        let err = keystore.unlock("wrong").err().unwrap();
+        assert_eq!(err.code(), "crypto.unlock");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+//! 按节点身份分配数据集分片
+//!
+//! 任务可以在数据集描述中附带清单：共享数据集切成的各个分片及其样本数。
+//! 同一任务的训练者按 `keccak(任务 id || 纪元 || 信标值 || PeerId)` 排序后轮流认领分片，
+//! 各节点独立算出相同且互不重叠的分配，本节点的样本数即所认领分片的样本数之和，
+//! 按样本数加权的合并因此有意义。设置了纪元长度时每个纪元重新洗牌，节点轮换数据。
+//! 启用随机信标时排序混入最近确定的信标值，节点无法通过挑选 PeerId 预先占据特定分片。
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+
+/// 数据集清单中的一个分片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestShard {
+    /// 分片位置（IPFS CID、URL 等）
+    pub uri: String,
+    pub samples: u64,
+}
+
+/// 把 `[0, shards)` 分给训练者；节点数多于分片时排在后面的节点本纪元没有数据
+pub fn assign_data_shards(
+    job_id: &str,
+    epoch: u64,
+    beacon: Option<&[u8; 32]>,
+    shards: u32,
+    peers: &[String],
+) -> HashMap<String, Vec<u32>> {
+    let mut ranked: Vec<([u8; 32], &String)> = peers
+        .iter()
+        .map(|peer| {
+            let mut hasher = Keccak256::new();
+            hasher.update(job_id.as_bytes());
+            hasher.update(epoch.to_be_bytes());
+            if let Some(beacon) = beacon {
+                hasher.update(beacon);
+            }
+            hasher.update(peer.as_bytes());
+            (hasher.finalize().into(), peer)
+        })
+        .collect();
+    ranked.sort();
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    let mut out: HashMap<String, Vec<u32>> = HashMap::new();
+    if ranked.is_empty() {
+        return out;
+    }
+    for shard in 0..shards {
+        let peer = ranked[shard as usize % ranked.len()].1;
+        out.entry(peer.clone()).or_default().push(shard);
+    }
+    out
+}
+
+/// 给定分片的样本数之和，清单中不存在的分片不计
+pub fn sample_count(manifest: &[ManifestShard], shards: &[u32]) -> u64 {
+    shards
+        .iter()
+        .filter_map(|shard| manifest.get(*shard as usize))
+        .map(|shard| shard.samples)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disjoint_deterministic_assignment() {
+        let peers: Vec<String> = ["a", "b", "c"].iter().map(|p| p.to_string()).collect();
+        let first = assign_data_shards("job", 0, None, 8, &peers);
+        // 顺序与重复不影响结果
+        let shuffled = vec![peers[2].clone(), peers[0].clone(), peers[1].clone(), peers[0].clone()];
+        assert_eq!(assign_data_shards("job", 0, None, 8, &shuffled), first);
+
+        let mut all: Vec<u32> = first.values().flatten().copied().collect();
+        all.sort();
+        assert_eq!(all, (0..8).collect::<Vec<_>>());
+        assert!(first.values().all(|s| s.len() == 2 || s.len() == 3));
+
+        // 不同任务或纪元得到不同的排序
+        let rotated = (1..16).any(|epoch| assign_data_shards("job", epoch, None, 8, &peers) != first);
+        assert!(rotated);
+        // 信标值参与排序，不同信标得到不同的分配
+        let reseeded = (0..16u8).any(|b| assign_data_shards("job", 0, Some(&[b; 32]), 8, &peers) != first);
+        assert!(reseeded);
+        assert!(assign_data_shards("job", 0, None, 8, &[]).is_empty());
+
+        let manifest: Vec<ManifestShard> = (0..8)
+            .map(|i| ManifestShard {
+                uri: format!("ipfs://shard-{i}"),
+                samples: 100 + i,
+            })
+            .collect();
+        assert_eq!(sample_count(&manifest, &[0, 7, 9]), 207);
+    }
+}
@@ -37,6 +37,16 @@ pub enum DeviceType {
     Unknown,
 }
 
+/// 在心跳中宣告的算力概要
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ComputeCapability {
+    pub cpu_cores: usize,
+    pub has_gpu: bool,
+    /// 估算的 FP32 算力（TFLOPs）
+    pub tflops: f32,
+    pub memory_mb: usize,
+}
+
 /// 设备能力信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceCapabilities {
@@ -119,16 +129,14 @@ impl DeviceCapabilities {
         let available_bytes = available_mb * 1024 * 1024;
         let max_params = available_bytes / bytes_per_param;
         // 限制在合理范围内
-        max_params.min(4096).max(64)
+        max_params.clamp(64, 4096)
     }
 
     /// 根据电池状态推荐训练频率（秒）
     pub fn recommended_tick_interval(&self) -> Duration {
         if let Some(level) = self.battery_level {
-            if self.is_charging {
-                Duration::from_secs(10) // 充电时正常频率
-            } else if level > 0.5 {
-                Duration::from_secs(10) // 高电量正常频率
+            if self.is_charging || level > 0.5 {
+                Duration::from_secs(10) // 充电或高电量时正常频率
             } else if level > 0.2 {
                 Duration::from_secs(30) // 中电量降低频率
             } else {
@@ -139,6 +147,11 @@ impl DeviceCapabilities {
         }
     }
 
+    /// 相对正常频率（10 秒）的间隔放大系数，用于缩放所有周期任务
+    pub fn tick_interval_factor(&self) -> f32 {
+        self.recommended_tick_interval().as_secs_f32() / 10.0
+    }
+
     /// 判断是否应该暂停训练（低电量且未充电）
     pub fn should_pause_training(&self) -> bool {
         if let Some(level) = self.battery_level {
@@ -148,6 +161,20 @@ impl DeviceCapabilities {
         }
     }
 
+    /// 粗略估算算力：每个 CPU 核心约 0.05 TFLOPs，GPU 按 2 TFLOPs 计
+    pub fn compute_capability(&self) -> ComputeCapability {
+        let mut tflops = self.cpu_cores as f32 * 0.05;
+        if self.has_gpu {
+            tflops += 2.0;
+        }
+        ComputeCapability {
+            cpu_cores: self.cpu_cores,
+            has_gpu: self.has_gpu,
+            tflops,
+            memory_mb: self.max_memory_mb,
+        }
+    }
+
     /// 根据设备能力推荐邻居数量
     pub fn recommended_max_neighbors(&self) -> usize {
         match self.device_type {
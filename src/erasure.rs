@@ -0,0 +1,322 @@
+//! 纠删码快照分发
+//!
+//! 大的密集快照用 Reed–Solomon 编码为 K 个数据分片 + M 个校验分片。发送方 gossip 一份
+//! 签名清单（快照哈希与各分片哈希），各分片经 QUIC 分别交给不同邻居，由它们以自己的身份
+//! 在 gossip 上转发，发送方的上行负载因此分摊到多个节点；没有可用邻居的分片由发送方自己发布。
+//! 邻居只转发快照发送方本人直连交来、且与其签名清单一致的分片。接收方按 (发送方, 快照哈希)
+//! 组装，只接受与该发送方清单哈希一致的分片，任意 K 个分片即可还原快照，不需要整份重传。
+
+use crate::types::TensorSnapshot;
+use anyhow::{anyhow, Result};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, VecDeque};
+
+/// 同时在组装的快照数
+const MAX_ASSEMBLIES: usize = 8;
+/// 清单到达前每个快照最多缓存的分片数
+const MAX_UNVERIFIED_SHARES: usize = 32;
+
+#[derive(Clone, Debug)]
+pub struct ErasureConfig {
+    /// 数据分片数 K，0 表示不使用纠删码
+    pub data_shards: usize,
+    /// 校验分片数 M，最多容忍丢失 M 个分片
+    pub parity_shards: usize,
+    /// 快照不小于该字节数时才编码
+    pub min_bytes: usize,
+}
+
+impl Default for ErasureConfig {
+    fn default() -> Self {
+        Self {
+            data_shards: 4,
+            parity_shards: 2,
+            min_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl ErasureConfig {
+    pub fn applies_to(&self, bytes: usize) -> bool {
+        self.data_shards > 0 && bytes >= self.min_bytes
+    }
+}
+
+/// 由快照发送方签名的分片清单
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShareManifest {
+    /// 还原后快照的哈希
+    pub hash: String,
+    pub version: u64,
+    pub offset: usize,
+    pub value_count: usize,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    /// 各分片内容的哈希，下标即分片序号
+    pub share_hashes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotShare {
+    /// 快照发送方，即签名清单的节点
+    pub origin: String,
+    /// 所属快照的哈希
+    pub hash: String,
+    pub index: usize,
+    pub bytes: Vec<u8>,
+}
+
+fn share_hash(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(Keccak256::digest(bytes)))
+}
+
+/// 把快照编码为清单与 K + M 个分片
+pub fn encode(
+    snapshot: &TensorSnapshot,
+    origin: &str,
+    data_shards: usize,
+    parity_shards: usize,
+) -> Result<(ShareManifest, Vec<SnapshotShare>)> {
+    let rs = ReedSolomon::new(data_shards, parity_shards).map_err(|e| anyhow!("{:?}", e))?;
+    let raw: Vec<u8> = snapshot
+        .values
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let share_len = raw.len().div_ceil(data_shards).max(1);
+    let mut shards: Vec<Vec<u8>> = (0..data_shards + parity_shards)
+        .map(|i| {
+            let mut shard = raw
+                .get(i * share_len..((i + 1) * share_len).min(raw.len()))
+                .unwrap_or_default()
+                .to_vec();
+            shard.resize(share_len, 0);
+            shard
+        })
+        .collect();
+    rs.encode(&mut shards).map_err(|e| anyhow!("{:?}", e))?;
+    let hash = snapshot.hash();
+    let manifest = ShareManifest {
+        hash: hash.clone(),
+        version: snapshot.version,
+        offset: snapshot.offset,
+        value_count: snapshot.values.len(),
+        data_shards,
+        parity_shards,
+        share_hashes: shards.iter().map(|s| share_hash(s)).collect(),
+    };
+    let shares = shards
+        .into_iter()
+        .enumerate()
+        .map(|(index, bytes)| SnapshotShare {
+            origin: origin.to_string(),
+            hash: hash.clone(),
+            index,
+            bytes,
+        })
+        .collect();
+    Ok((manifest, shares))
+}
+
+/// 组装键：(快照发送方, 快照哈希)
+type AssemblyKey = (String, String);
+
+struct Assembly {
+    /// 发送方签名的清单，到达前为 None
+    manifest: Option<ShareManifest>,
+    shares: HashMap<usize, Vec<u8>>,
+    /// 发送方直连交来、等清单到达后校验再转发的分片
+    relays: Vec<SnapshotShare>,
+    done: bool,
+}
+
+impl Assembly {
+    fn matches(&self, share: &SnapshotShare) -> Option<bool> {
+        let manifest = self.manifest.as_ref()?;
+        Some(manifest.share_hashes.get(share.index) == Some(&share_hash(&share.bytes)))
+    }
+}
+
+/// 接收方：按 (发送方, 快照哈希) 收集分片，凑够 K 个后还原
+#[derive(Default)]
+pub struct ShareAssembler {
+    assemblies: HashMap<AssemblyKey, Assembly>,
+    order: VecDeque<AssemblyKey>,
+}
+
+impl ShareAssembler {
+    fn entry(&mut self, origin: &str, hash: &str) -> &mut Assembly {
+        let key = (origin.to_string(), hash.to_string());
+        if !self.assemblies.contains_key(&key) {
+            self.order.push_back(key.clone());
+            while self.order.len() > MAX_ASSEMBLIES {
+                if let Some(old) = self.order.pop_front() {
+                    self.assemblies.remove(&old);
+                }
+            }
+        }
+        self.assemblies.entry(key).or_insert_with(|| Assembly {
+            manifest: None,
+            shares: HashMap::new(),
+            relays: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// 记录 `origin` 签名的清单；先于清单到达的分片此时补做校验。凑齐时返回 (发送方, 快照)
+    pub fn manifest(
+        &mut self,
+        origin: &str,
+        manifest: ShareManifest,
+    ) -> Option<(String, TensorSnapshot)> {
+        let assembly = self.entry(origin, &manifest.hash);
+        if assembly.manifest.is_some() {
+            return None;
+        }
+        assembly
+            .shares
+            .retain(|index, bytes| manifest.share_hashes.get(*index) == Some(&share_hash(bytes)));
+        assembly.manifest = Some(manifest);
+        Self::try_reconstruct(origin, assembly)
+    }
+
+    /// 记录分片；凑齐时返回 (发送方, 快照)
+    pub fn share(&mut self, share: SnapshotShare) -> Option<(String, TensorSnapshot)> {
+        let origin = share.origin.clone();
+        let assembly = self.entry(&origin, &share.hash);
+        if assembly.done {
+            return None;
+        }
+        match assembly.matches(&share) {
+            Some(false) => return None,
+            Some(true) => {}
+            None if assembly.shares.len() >= MAX_UNVERIFIED_SHARES => return None,
+            None => {}
+        }
+        assembly.shares.insert(share.index, share.bytes);
+        Self::try_reconstruct(&origin, assembly)
+    }
+
+    /// 发送方直连交来的待转发分片：与清单一致时立即返回以转发；清单未到时暂存，
+    /// 由 `take_relays` 在清单到达后取出
+    pub fn relay(&mut self, share: SnapshotShare) -> Option<SnapshotShare> {
+        let assembly = self.entry(&share.origin, &share.hash);
+        match assembly.matches(&share) {
+            Some(true) => Some(share),
+            Some(false) => None,
+            None => {
+                if assembly.relays.len() < MAX_UNVERIFIED_SHARES {
+                    assembly.relays.push(share);
+                }
+                None
+            }
+        }
+    }
+
+    /// 清单到达后取出与之一致的暂存待转发分片
+    pub fn take_relays(&mut self, origin: &str, hash: &str) -> Vec<SnapshotShare> {
+        let key = (origin.to_string(), hash.to_string());
+        let Some(assembly) = self.assemblies.get_mut(&key) else {
+            return Vec::new();
+        };
+        let relays = std::mem::take(&mut assembly.relays);
+        relays
+            .into_iter()
+            .filter(|share| assembly.matches(share) == Some(true))
+            .collect()
+    }
+
+    /// 已缓存分片的字节数
+    pub fn buffered_bytes(&self) -> usize {
+        self.assemblies
+            .values()
+            .map(|a| {
+                a.shares.values().map(Vec::len).sum::<usize>()
+                    + a.relays.iter().map(|s| s.bytes.len()).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// 内存紧张时按开始顺序丢弃组装，返回释放的字节数
+    pub fn shed(&mut self, target: usize) -> usize {
+        let mut freed = 0;
+        while freed < target {
+            let Some(key) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(assembly) = self.assemblies.remove(&key) {
+                freed += assembly.shares.values().map(Vec::len).sum::<usize>();
+                freed += assembly.relays.iter().map(|s| s.bytes.len()).sum::<usize>();
+            }
+        }
+        freed
+    }
+
+    fn try_reconstruct(origin: &str, assembly: &mut Assembly) -> Option<(String, TensorSnapshot)> {
+        let manifest = assembly.manifest.as_ref()?;
+        if assembly.done || assembly.shares.len() < manifest.data_shards {
+            return None;
+        }
+        assembly.done = true;
+        let rs = ReedSolomon::new(manifest.data_shards, manifest.parity_shards).ok()?;
+        let mut shards: Vec<Option<Vec<u8>>> = (0..manifest.data_shards + manifest.parity_shards)
+            .map(|i| assembly.shares.remove(&i))
+            .collect();
+        rs.reconstruct_data(&mut shards).ok()?;
+        let raw: Vec<u8> = shards
+            .into_iter()
+            .take(manifest.data_shards)
+            .flat_map(|s| s.unwrap_or_default())
+            .collect();
+        let values: Vec<f32> = raw
+            .chunks_exact(4)
+            .take(manifest.value_count)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let snapshot = TensorSnapshot::slice(values, manifest.offset, manifest.version);
+        (snapshot.hash() == manifest.hash).then(|| (origin.to_string(), snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_k_shares_reconstruct() {
+        let values: Vec<f32> = (0..1001).map(|i| i as f32 * 0.5).collect();
+        let snapshot = TensorSnapshot::slice(values, 24, 9);
+        let (manifest, shares) = encode(&snapshot, "origin", 4, 2).unwrap();
+        assert_eq!(shares.len(), 6);
+
+        let mut assembler = ShareAssembler::default();
+        // 抢先发布同一哈希清单的节点只得到自己名下的组装，不影响原发送方
+        assert!(assembler
+            .manifest("front-runner", manifest.clone())
+            .is_none());
+        assert!(assembler.relay(shares[0].clone()).is_none());
+        // 分片先于清单到达，且缺失两个分片
+        assert!(assembler.share(shares[5].clone()).is_none());
+        assert!(assembler.share(shares[1].clone()).is_none());
+        let mut tampered = shares[2].clone();
+        tampered.bytes[0] ^= 1;
+        assert!(assembler.share(tampered).is_none());
+        let manifest_hash = manifest.hash.clone();
+        assert!(assembler.manifest("origin", manifest).is_none());
+        assert!(assembler.share(shares[3].clone()).is_none());
+        let (origin, rebuilt) = assembler.share(shares[4].clone()).unwrap();
+        assert_eq!(origin, "origin");
+        assert_eq!(rebuilt.offset, 24);
+        assert_eq!(rebuilt.values, snapshot.values);
+        assert!(assembler.share(shares[0].clone()).is_none());
+        assert_eq!(
+            assembler.take_relays("origin", &manifest_hash),
+            vec![shares[0].clone()]
+        );
+        let mut forged = shares[1].clone();
+        forged.bytes[0] ^= 1;
+        assert!(assembler.relay(forged).is_none());
+    }
+}
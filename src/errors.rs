@@ -0,0 +1,51 @@
+//! 库接口错误的错误码
+//!
+//! 通信、密钥、共识与模型各自定义错误枚举（`CommsError`、`CryptoError`、`ConsensusError`、
+//! `InferenceError`），每个变体有稳定的 `code()`，嵌入方按错误码处理失败，不必匹配错误文本。
+//! 经 anyhow 传到节点逻辑的错误在这里沿错误链还原出错误码，供管理接口与统计使用。
+
+use crate::comms::CommsError;
+use crate::consensus::ConsensusError;
+use crate::crypto::CryptoError;
+use crate::inference::InferenceError;
+
+/// 错误链中第一个库错误的错误码；不是库错误（如配置解析失败）时返回 None
+pub fn code_of(err: &anyhow::Error) -> Option<&'static str> {
+    err.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<CommsError>() {
+            Some(e.code())
+        } else if let Some(e) = cause.downcast_ref::<CryptoError>() {
+            Some(e.code())
+        } else if let Some(e) = cause.downcast_ref::<ConsensusError>() {
+            Some(e.code())
+        } else {
+            cause.downcast_ref::<InferenceError>().map(InferenceError::code)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_recovers_codes_through_context_and_wrapping() {
+        let err = Err::<(), _>(CommsError::UnknownSession("s".into()))
+            .context("发布心跳")
+            .unwrap_err();
+        assert_eq!(code_of(&err), Some("comms.unknown_session"));
+
+        // 共识错误包装的密钥错误沿用密钥错误的错误码
+        let err = anyhow::Error::from(ConsensusError::from(CryptoError::EmptyPassphrase));
+        assert_eq!(code_of(&err), Some("crypto.empty_passphrase"));
+
+        let err = anyhow::Error::from(InferenceError::DimMismatch {
+            field: "params",
+            actual: 3,
+            expected: 4,
+        });
+        assert_eq!(code_of(&err), Some("inference.dim_mismatch"));
+        assert_eq!(code_of(&anyhow::anyhow!("配置无效")), None);
+    }
+}
@@ -0,0 +1,236 @@
+//! 分布式留出集评估
+//!
+//! 共享基准被切成若干评估分片。每个评估纪元内，会话中的训练者按
+//! `keccak(纪元 || PeerId)` 排序后轮流认领分片，各节点独立算出相同且互不重叠的分配，
+//! 只评估分给自己的分片，再通过 gossip 交换结果，按样本数加权合并为全网评估，
+//! 不需要每个节点都跑完整个基准。
+//!
+//! 节点没有真实的留出数据，基准由种子确定：教师向量与各分片样本都由种子生成，
+//! 损失为模型参数作为线性预测器在样本上的均方误差。
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// 每个会话保留的评估纪元数
+const MAX_EPOCHS: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct EvalConfig {
+    /// 基准分片数，0 表示不参与分布式评估
+    pub shards: u32,
+    pub samples_per_shard: usize,
+    /// 评估纪元长度，按 Unix 时间对齐
+    pub epoch: Duration,
+    /// 基准种子，全网必须一致
+    pub benchmark_seed: u64,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        Self {
+            shards: 0,
+            samples_per_shard: 32,
+            epoch: Duration::from_secs(600),
+            benchmark_seed: 0x6767_7365,
+        }
+    }
+}
+
+impl EvalConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.shards > 0
+    }
+
+    pub fn epoch_at(&self, unix_secs: u64) -> u64 {
+        unix_secs / self.epoch.as_secs().max(1)
+    }
+}
+
+/// 单个分片的评估结果
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardResult {
+    pub shard: u32,
+    pub samples: u32,
+    /// 分片上的平均损失
+    pub loss: f32,
+}
+
+/// 合并后的全网评估
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkEval {
+    pub epoch: u64,
+    pub shards_covered: u32,
+    pub shards_total: u32,
+    pub evaluators: usize,
+    /// 按样本数加权的平均损失
+    pub mean_loss: f32,
+}
+
+/// 按纪元把 `[0, shards)` 分给节点：节点按 `keccak(纪元 || PeerId)` 排序后轮流认领，
+/// 节点数多于分片时排在后面的节点本纪元不参与
+pub fn assign_eval_shards(epoch: u64, shards: u32, peers: &[String]) -> HashMap<String, Vec<u32>> {
+    let mut ranked: Vec<([u8; 32], &String)> = peers
+        .iter()
+        .map(|peer| {
+            let mut hasher = Keccak256::new();
+            hasher.update(epoch.to_be_bytes());
+            hasher.update(peer.as_bytes());
+            (hasher.finalize().into(), peer)
+        })
+        .collect();
+    ranked.sort();
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    let mut out: HashMap<String, Vec<u32>> = HashMap::new();
+    if ranked.is_empty() {
+        return out;
+    }
+    for shard in 0..shards {
+        let peer = ranked[shard as usize % ranked.len()].1;
+        out.entry(peer.clone()).or_default().push(shard);
+    }
+    out
+}
+
+/// 在一个基准分片上评估参数向量
+pub fn evaluate_shard(config: &EvalConfig, shard: u32, params: &[f32]) -> ShardResult {
+    let dim = params.len();
+    let scale = 1.0 / (dim.max(1) as f32).sqrt();
+    let mut teacher_rng = StdRng::seed_from_u64(config.benchmark_seed);
+    let teacher: Vec<f32> = (0..dim).map(|_| teacher_rng.gen_range(-1.0..1.0)).collect();
+    let mut rng = StdRng::seed_from_u64(
+        config.benchmark_seed ^ (u64::from(shard) + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15),
+    );
+    let mut total = 0.0f32;
+    for _ in 0..config.samples_per_shard {
+        let (mut target, mut prediction) = (0.0f32, 0.0f32);
+        for (w, p) in teacher.iter().zip(params) {
+            let x: f32 = rng.gen_range(-1.0..1.0);
+            target += w * x;
+            prediction += p * x;
+        }
+        let err = (prediction - target) * scale;
+        total += err * err;
+    }
+    ShardResult {
+        shard,
+        samples: config.samples_per_shard as u32,
+        loss: total / config.samples_per_shard.max(1) as f32,
+    }
+}
+
+/// 单个会话收集到的各纪元分片结果
+#[derive(Default)]
+pub struct EvalBoard {
+    /// 纪元 -> 分片 -> (评估者, 结果)，同一分片只保留最先到达的结果
+    epochs: BTreeMap<u64, HashMap<u32, (String, ShardResult)>>,
+    /// 本节点最近完成评估的纪元
+    pub last_evaluated: Option<u64>,
+}
+
+impl EvalBoard {
+    /// 记录某节点的结果，只接受 `assigned`（按 `assign_eval_shards` 分给该节点的分片）中的分片，
+    /// 返回新接受的分片数
+    pub fn record(
+        &mut self,
+        epoch: u64,
+        evaluator: &str,
+        results: &[ShardResult],
+        assigned: &[u32],
+    ) -> usize {
+        if self
+            .epochs
+            .keys()
+            .next()
+            .is_some_and(|oldest| epoch < *oldest && self.epochs.len() >= MAX_EPOCHS)
+        {
+            return 0;
+        }
+        let board = self.epochs.entry(epoch).or_default();
+        let mut accepted = 0;
+        for result in results {
+            if !assigned.contains(&result.shard) || result.samples == 0 || !result.loss.is_finite() {
+                continue;
+            }
+            if let std::collections::hash_map::Entry::Vacant(slot) = board.entry(result.shard) {
+                slot.insert((evaluator.to_string(), result.clone()));
+                accepted += 1;
+            }
+        }
+        while self.epochs.len() > MAX_EPOCHS {
+            self.epochs.pop_first();
+        }
+        accepted
+    }
+
+    /// 合并某纪元已收到的分片结果
+    pub fn summary(&self, epoch: u64, shards_total: u32) -> Option<NetworkEval> {
+        let board = self.epochs.get(&epoch).filter(|b| !b.is_empty())?;
+        let samples: u32 = board.values().map(|(_, r)| r.samples).sum();
+        let weighted: f32 = board.values().map(|(_, r)| r.loss * r.samples as f32).sum();
+        let mut evaluators: Vec<&String> = board.values().map(|(peer, _)| peer).collect();
+        evaluators.sort();
+        evaluators.dedup();
+        Some(NetworkEval {
+            epoch,
+            shards_covered: board.len() as u32,
+            shards_total,
+            evaluators: evaluators.len(),
+            mean_loss: weighted / samples.max(1) as f32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shards_are_disjoint_and_combine() {
+        let config = EvalConfig {
+            shards: 8,
+            samples_per_shard: 4,
+            ..EvalConfig::default()
+        };
+        let peers: Vec<String> = ["a", "b", "c"].iter().map(|p| p.to_string()).collect();
+        let assignment = assign_eval_shards(7, config.shards, &peers);
+        let mut covered: Vec<u32> = assignment.values().flatten().copied().collect();
+        covered.sort();
+        assert_eq!(covered, (0..8).collect::<Vec<_>>());
+        // 顺序无关，各节点算出相同的分配
+        let reversed: Vec<String> = peers.iter().rev().cloned().collect();
+        assert_eq!(assign_eval_shards(7, config.shards, &reversed), assignment);
+
+        let params = vec![0.1f32; 16];
+        let mut board = EvalBoard::default();
+        for (peer, shards) in &assignment {
+            let results: Vec<_> = shards
+                .iter()
+                .map(|s| evaluate_shard(&config, *s, &params))
+                .collect();
+            assert_eq!(board.record(7, peer, &results, shards), results.len());
+        }
+        // 未分给该节点的分片不会被计入，即使尚无结果
+        let mut fresh = EvalBoard::default();
+        let (peer, shards) = assignment.iter().next().unwrap();
+        let other = (0..config.shards).find(|s| !shards.contains(s)).unwrap();
+        assert_eq!(
+            fresh.record(7, peer, &[evaluate_shard(&config, other, &params)], shards),
+            0
+        );
+        assert_eq!(fresh.record(7, "d", &[evaluate_shard(&config, 0, &params)], &[]), 0);
+        // 重复的分片不会被计入
+        let results: Vec<_> = shards
+            .iter()
+            .map(|s| evaluate_shard(&config, *s, &params))
+            .collect();
+        assert_eq!(board.record(7, peer, &results, shards), 0);
+        let eval = board.summary(7, config.shards).unwrap();
+        assert_eq!(eval.shards_covered, 8);
+        assert_eq!(eval.evaluators, assignment.len());
+        assert!(eval.mean_loss > 0.0);
+    }
+}
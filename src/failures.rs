@@ -0,0 +1,260 @@
+//! 入站失败事件的汇总上报
+//!
+//! 签名校验失败、限速丢弃等事件在遭受攻击时可能每秒上千条，逐条打印本身就会拖垮日志。
+//! [`FailureReporter`] 对同一来源的同类事件，每个窗口内只打印第一条，其余只计数，
+//! 窗口结束后打印一条汇总；所有事件都按类别计入统计。来源应是传输层确认的身份
+//! （gossipsub 转发节点、QUIC 证书 PeerId 或远端地址），而不是载荷自称的发送者。
+//! 伪造大量来源时，每个窗口的打印总数另有上限，超出的只在窗口结束时汇总为一条；
+//! 跟踪的来源数达到上限时整体清空，内存占用有界。
+
+use crate::stats::TrainingStatsManager;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 跟踪的 (类别, 来源) 上限，清理过期条目后仍满时整体清空，避免伪造大量来源耗尽内存
+const MAX_TRACKED: usize = 10_000;
+/// 每个窗口最多打印的失败日志行数（含汇总）
+const MAX_PRINTS_PER_WINDOW: u32 = 100;
+
+/// 需要汇总上报的入站失败类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Failure {
+    /// 签名校验失败
+    Signature,
+    /// gossipsub 源与载荷声明的发送者不符
+    Origin,
+    /// 时间戳超前或过旧
+    Replay,
+    /// 未准入节点发来的消息
+    Admission,
+    /// 发送过快被限速
+    RateLimit,
+    /// 载荷异常
+    Anomaly,
+}
+
+impl Failure {
+    /// 统计中使用的类别名
+    pub fn code(self) -> &'static str {
+        match self {
+            Failure::Signature => "signature",
+            Failure::Origin => "origin",
+            Failure::Replay => "replay",
+            Failure::Admission => "admission",
+            Failure::RateLimit => "rate_limit",
+            Failure::Anomaly => "anomaly",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Failure::Signature => "签名验证失败",
+            Failure::Origin => "消息源与发送者不符",
+            Failure::Replay => "时间戳不可信",
+            Failure::Admission => "未准入",
+            Failure::RateLimit => "发送过快",
+            Failure::Anomaly => "载荷异常",
+        }
+    }
+}
+
+/// 一个 (类别, 来源) 当前窗口的状态
+struct Burst {
+    /// 窗口开始时间（Unix 毫秒），即本窗口打印的那条事件的时间
+    started_ms: u64,
+    /// 窗口内未打印的事件数
+    suppressed: u64,
+}
+
+#[derive(Default)]
+struct State {
+    bursts: HashMap<(Failure, String), Burst>,
+    /// 打印配额所属窗口的开始时间（Unix 毫秒）
+    budget_started_ms: u64,
+    /// 本窗口已打印的行数
+    printed: u32,
+    /// 因配额用完或整体清空而没有逐条或逐来源汇总的事件数
+    unprinted: u64,
+}
+
+pub struct FailureReporter {
+    window_ms: u64,
+    state: Mutex<State>,
+    stats: Arc<TrainingStatsManager>,
+}
+
+impl FailureReporter {
+    pub fn new(window: Duration, stats: Arc<TrainingStatsManager>) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            state: Mutex::new(State::default()),
+            stats,
+        }
+    }
+
+    /// 记录一次失败事件；本窗口内该来源的第一条同类事件在打印配额内打印 `detail`，其余只计数。
+    /// 返回是否打印了
+    pub fn report(&self, kind: Failure, peer: &str, now_ms: u64, detail: impl FnOnce() -> String) -> bool {
+        self.stats.record_failure(kind.code());
+        let mut state = self.state.lock();
+        let key = (kind, peer.to_string());
+        if let Some(burst) = state.bursts.get_mut(&key) {
+            if now_ms.saturating_sub(burst.started_ms) < self.window_ms {
+                burst.suppressed += 1;
+                return false;
+            }
+            let burst = state.bursts.remove(&key).expect("burst exists");
+            self.summarize(&mut state, &key, &burst, now_ms);
+        }
+        if state.bursts.len() >= MAX_TRACKED {
+            self.expire(&mut state, now_ms);
+        }
+        if state.bursts.len() >= MAX_TRACKED {
+            let evicted: u64 = state.bursts.drain().map(|(_, b)| b.suppressed).sum();
+            state.unprinted += evicted;
+        }
+        state.bursts.insert(
+            key,
+            Burst {
+                started_ms: now_ms,
+                suppressed: 0,
+            },
+        );
+        if !self.take_budget(&mut state, now_ms) {
+            state.unprinted += 1;
+            return false;
+        }
+        drop(state);
+        eprintln!("{}", detail());
+        true
+    }
+
+    /// 打印已结束窗口的汇总并清理，由节点定期调用
+    pub fn flush(&self, now_ms: u64) {
+        let mut state = self.state.lock();
+        self.expire(&mut state, now_ms);
+        self.roll_budget(&mut state, now_ms);
+    }
+
+    fn expire(&self, state: &mut State, now_ms: u64) {
+        let expired: Vec<(Failure, String)> = state
+            .bursts
+            .iter()
+            .filter(|(_, burst)| now_ms.saturating_sub(burst.started_ms) >= self.window_ms)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            if let Some(burst) = state.bursts.remove(&key) {
+                self.summarize(state, &key, &burst, now_ms);
+            }
+        }
+    }
+
+    /// 进入新的配额窗口时重置配额，并打印上一窗口未打印事件的汇总
+    fn roll_budget(&self, state: &mut State, now_ms: u64) {
+        if now_ms.saturating_sub(state.budget_started_ms) < self.window_ms {
+            return;
+        }
+        state.budget_started_ms = now_ms;
+        state.printed = 0;
+        let unprinted = std::mem::take(&mut state.unprinted);
+        if unprinted > 0 {
+            state.printed += 1;
+            eprintln!(
+                "[失败汇总] 上一个 {} 秒内另有 {} 条失败事件因日志配额用完未打印",
+                self.window_ms / 1000,
+                unprinted
+            );
+        }
+    }
+
+    /// 占用一行打印配额，用完时返回 false
+    fn take_budget(&self, state: &mut State, now_ms: u64) -> bool {
+        self.roll_budget(state, now_ms);
+        if state.printed >= MAX_PRINTS_PER_WINDOW {
+            return false;
+        }
+        state.printed += 1;
+        true
+    }
+
+    fn summarize(
+        &self,
+        state: &mut State,
+        (kind, peer): &(Failure, String),
+        burst: &Burst,
+        now_ms: u64,
+    ) {
+        if burst.suppressed == 0 {
+            return;
+        }
+        if !self.take_budget(state, now_ms) {
+            state.unprinted += burst.suppressed;
+            return;
+        }
+        eprintln!(
+            "[失败汇总] {} 在 {} 秒内另有 {} 条消息{}，未逐条打印",
+            peer,
+            self.window_ms / 1000,
+            burst.suppressed,
+            kind.label()
+        );
+    }
+
+    /// 仍在窗口内被压制、尚未汇总的事件数
+    #[cfg(test)]
+    fn pending(&self, kind: Failure, peer: &str) -> u64 {
+        self.state
+            .lock()
+            .bursts
+            .get(&(kind, peer.to_string()))
+            .map_or(0, |burst| burst.suppressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storm_logs_once_per_window_and_counts_all() {
+        let stats = Arc::new(TrainingStatsManager::new(String::new(), 0));
+        let reporter = FailureReporter::new(Duration::from_secs(60), Arc::clone(&stats));
+        let detail = || "签名验证失败，来自 mallory".to_string();
+
+        assert!(reporter.report(Failure::Signature, "mallory", 0, detail));
+        for i in 1..1_000 {
+            assert!(!reporter.report(Failure::Signature, "mallory", i, detail));
+        }
+        assert_eq!(reporter.pending(Failure::Signature, "mallory"), 999);
+        // 其他节点与其他类别各自计窗口
+        assert!(reporter.report(Failure::Signature, "eve", 10, detail));
+        assert!(reporter.report(Failure::RateLimit, "mallory", 10, detail));
+
+        // 窗口结束后的下一条重新打印
+        assert!(reporter.report(Failure::Signature, "mallory", 60_000, detail));
+        assert_eq!(reporter.pending(Failure::Signature, "mallory"), 0);
+        reporter.flush(200_000);
+        assert_eq!(reporter.state.lock().bursts.len(), 0);
+
+        let failures = stats.get().failures;
+        assert_eq!(failures["signature"], 1_002);
+        assert_eq!(failures["rate_limit"], 1);
+
+        // 伪造的来源再多，每个窗口也只打印配额内的行数，跟踪的来源数有上限
+        let printed = (0..MAX_TRACKED as u64 + 10)
+            .filter(|i| {
+                let sybil = format!("sybil-{i}");
+                reporter.report(Failure::Signature, &sybil, 300_000 + i % 100, detail)
+            })
+            .count();
+        assert_eq!(printed, MAX_PRINTS_PER_WINDOW as usize);
+        assert!(reporter.state.lock().bursts.len() < MAX_TRACKED);
+        assert!(reporter.state.lock().unprinted > 0);
+        // 下一个窗口先汇总上一窗口未打印的事件
+        reporter.flush(400_000);
+        assert_eq!(reporter.state.lock().unprinted, 0);
+    }
+}
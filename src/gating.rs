@@ -0,0 +1,231 @@
+//! 代币门槛准入
+//!
+//! 面向代币社区的训练：只有固定的 ETH 地址持有足够数量的指定 ERC-20，
+//! 或持有指定 NFT 的节点，其稀疏更新和模型快照才会被应用。
+//! 查询通过以太坊 JSON-RPC `eth_call` 在后台进行并缓存，调用方只读缓存；
+//! 尚未查询完成的节点按未达标处理，它们仍可作为 gossip 中继。
+//!
+//! 门槛格式：`erc20:<合约>:<最小余额（最小单位）>`、`nft:<合约>`（持有该集合任一 NFT）
+//! 或 `nft:<合约>:<tokenId>`（持有特定 NFT）。
+
+use crate::names::eth_call;
+use anyhow::{anyhow, Result};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// balanceOf(address)，ERC-20 与 ERC-721 相同
+const SELECTOR_BALANCE_OF: &str = "70a08231";
+/// ownerOf(uint256)
+const SELECTOR_OWNER_OF: &str = "6352211e";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenRequirement {
+    Erc20 { contract: String, min_balance: u128 },
+    Nft { contract: String, token_id: Option<u128> },
+}
+
+impl FromStr for TokenRequirement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let contract = |c: &str| -> Result<String> {
+            let hex = c.strip_prefix("0x").unwrap_or(c);
+            if hex.len() != 40 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                return Err(anyhow!("invalid contract address {:?}", c));
+            }
+            Ok(format!("0x{}", hex.to_lowercase()))
+        };
+        match parts.as_slice() {
+            ["erc20", c, min] => Ok(Self::Erc20 {
+                contract: contract(c)?,
+                min_balance: min.parse()?,
+            }),
+            ["nft", c] => Ok(Self::Nft {
+                contract: contract(c)?,
+                token_id: None,
+            }),
+            ["nft", c, id] => Ok(Self::Nft {
+                contract: contract(c)?,
+                token_id: Some(id.parse()?),
+            }),
+            _ => Err(anyhow!(
+                "expected erc20:<contract>:<min> or nft:<contract>[:<token id>]: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenGateConfig {
+    /// 以太坊 JSON-RPC 端点（与 ENS 解析共用 `--eth-rpc`）
+    pub rpc: Option<String>,
+    /// None 表示不设门槛
+    pub requirement: Option<TokenRequirement>,
+    /// 查询结果缓存时长，过期后在后台重新查询
+    pub cache_ttl: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for TokenGateConfig {
+    fn default() -> Self {
+        Self {
+            rpc: None,
+            requirement: None,
+            cache_ttl: Duration::from_secs(600),
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+struct CachedCheck {
+    allowed: bool,
+    checked_at: Instant,
+}
+
+pub struct TokenGate {
+    config: TokenGateConfig,
+    /// ETH 地址（小写） -> 查询结果
+    cache: RwLock<HashMap<String, CachedCheck>>,
+    pending: RwLock<HashSet<String>>,
+}
+
+impl TokenGate {
+    pub fn new(config: TokenGateConfig) -> Self {
+        Self {
+            config,
+            cache: RwLock::new(HashMap::new()),
+            pending: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.requirement.is_some()
+    }
+
+    /// 该地址是否满足门槛；缓存缺失或过期时在后台发起查询，过期的旧结果在刷新完成前继续使用
+    pub fn allows(self: &Arc<Self>, eth_address: &str) -> bool {
+        let Some(requirement) = self.config.requirement.clone() else {
+            return true;
+        };
+        let address = eth_address.to_lowercase();
+        let cached = self
+            .cache
+            .read()
+            .get(&address)
+            .map(|c| (c.allowed, c.checked_at.elapsed() < self.config.cache_ttl));
+        if cached.is_none_or(|(_, fresh)| !fresh) && self.pending.write().insert(address.clone()) {
+            let gate = self.clone();
+            let address = address.clone();
+            tokio::spawn(async move {
+                let result = match gate.config.rpc.as_deref() {
+                    Some(rpc) => tokio::time::timeout(
+                        gate.config.request_timeout,
+                        check_requirement(rpc, &requirement, &address),
+                    )
+                    .await
+                    .map_err(|_| anyhow!("timed out"))
+                    .and_then(|r| r),
+                    None => Err(anyhow!("no eth rpc")),
+                };
+                gate.pending.write().remove(&address);
+                match result {
+                    Ok(allowed) => {
+                        if !allowed {
+                            println!("[代币门槛] {} 未达到持有门槛", address);
+                        }
+                        gate.cache.write().insert(
+                            address,
+                            CachedCheck {
+                                allowed,
+                                checked_at: Instant::now(),
+                            },
+                        );
+                    }
+                    // 查询失败不写缓存，下次收到该节点的消息时重试
+                    Err(e) => eprintln!("[代币门槛] 查询 {} 失败: {:?}", address, e),
+                }
+            });
+        }
+        cached.is_some_and(|(allowed, _)| allowed)
+    }
+}
+
+async fn check_requirement(rpc: &str, requirement: &TokenRequirement, address: &str) -> Result<bool> {
+    let holder = address_word(address)?;
+    match requirement {
+        TokenRequirement::Erc20 {
+            contract,
+            min_balance,
+        } => {
+            let data = eth_call(rpc, contract, SELECTOR_BALANCE_OF, &holder).await?;
+            Ok(decode_uint(&data)? >= *min_balance)
+        }
+        TokenRequirement::Nft {
+            contract,
+            token_id: None,
+        } => {
+            let data = eth_call(rpc, contract, SELECTOR_BALANCE_OF, &holder).await?;
+            Ok(decode_uint(&data)? > 0)
+        }
+        TokenRequirement::Nft {
+            contract,
+            token_id: Some(id),
+        } => {
+            let mut word = [0u8; 32];
+            word[16..].copy_from_slice(&id.to_be_bytes());
+            let data = eth_call(rpc, contract, SELECTOR_OWNER_OF, &word).await?;
+            let owner = data.get(12..32).ok_or_else(|| anyhow!("short ownerOf response"))?;
+            Ok(owner == &holder[12..])
+        }
+    }
+}
+
+/// 把地址左补零为一个 ABI 字
+fn address_word(address: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))?;
+    if bytes.len() != 20 {
+        return Err(anyhow!("invalid eth address {}", address));
+    }
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+/// 解码 uint256 返回值，超出 u128 的余额按 u128::MAX 处理
+fn decode_uint(data: &[u8]) -> Result<u128> {
+    let word = data.get(..32).ok_or_else(|| anyhow!("short uint response"))?;
+    if word[..16].iter().any(|b| *b != 0) {
+        return Ok(u128::MAX);
+    }
+    Ok(u128::from_be_bytes(word[16..].try_into()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirement_and_decode_balance() {
+        let contract = "0x6B175474E89094C44Da98b954EedeAC495271d0F";
+        assert_eq!(
+            format!("erc20:{}:1000", contract).parse::<TokenRequirement>().unwrap(),
+            TokenRequirement::Erc20 {
+                contract: contract.to_lowercase(),
+                min_balance: 1000,
+            }
+        );
+        assert!(format!("nft:{}:7", contract).parse::<TokenRequirement>().is_ok());
+        assert!("nft:0x1234".parse::<TokenRequirement>().is_err());
+
+        let mut data = [0u8; 32];
+        data[31] = 5;
+        assert_eq!(decode_uint(&data).unwrap(), 5);
+        data[0] = 1;
+        assert_eq!(decode_uint(&data).unwrap(), u128::MAX);
+    }
+}
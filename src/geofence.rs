@@ -0,0 +1,118 @@
+//! 训练伙伴地理围栏
+//!
+//! 出于数据驻留合规要求，可以把模型交换限制在本节点周围一定半径内，
+//! 或限制在若干 geohash 前缀（区域）内。围栏外的节点仍可作为 gossip 中继，
+//! 但它们的更新不会被应用，本节点也不会主动向它们发送更新。
+
+use crate::types::GeoPoint;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Clone, Debug, Default)]
+pub struct GeoFenceConfig {
+    /// 允许交换模型的最大距离（公里）
+    pub radius_km: Option<f32>,
+    /// 允许的 geohash 前缀，命中任一即可
+    pub allowed_geohash_prefixes: Vec<String>,
+}
+
+impl GeoFenceConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.radius_km.is_some() || !self.allowed_geohash_prefixes.is_empty()
+    }
+
+    /// 位置未知的节点在启用围栏时一律视为围栏外
+    pub fn allows(&self, own: &GeoPoint, peer: Option<&GeoPoint>) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let Some(peer) = peer else {
+            return false;
+        };
+        if let Some(radius) = self.radius_km {
+            if own.distance_km(peer) > radius {
+                return false;
+            }
+        }
+        if !self.allowed_geohash_prefixes.is_empty() {
+            let precision = self
+                .allowed_geohash_prefixes
+                .iter()
+                .map(|p| p.len())
+                .max()
+                .unwrap_or(0);
+            let hash = geohash(peer, precision);
+            if !self
+                .allowed_geohash_prefixes
+                .iter()
+                .any(|prefix| hash.starts_with(prefix.as_str()))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 标准 geohash 编码
+pub fn geohash(point: &GeoPoint, precision: usize) -> String {
+    let (mut lat_range, mut lon_range) = ((-90.0f32, 90.0f32), (-180.0f32, 180.0f32));
+    let mut out = String::with_capacity(precision);
+    let mut even_bit = true;
+    let (mut bits, mut value) = (0, 0usize);
+    while out.len() < precision {
+        let (range, coord) = if even_bit {
+            (&mut lon_range, point.lon)
+        } else {
+            (&mut lat_range, point.lat)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        value <<= 1;
+        if coord >= mid {
+            value |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even_bit = !even_bit;
+        bits += 1;
+        if bits == 5 {
+            out.push(GEOHASH_ALPHABET[value] as char);
+            bits = 0;
+            value = 0;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geohash_and_fence() {
+        let berlin = GeoPoint {
+            lat: 52.52,
+            lon: 13.405,
+        };
+        let paris = GeoPoint {
+            lat: 48.857,
+            lon: 2.352,
+        };
+        assert_eq!(geohash(&berlin, 5), "u33dc");
+
+        let fence = GeoFenceConfig {
+            radius_km: Some(500.0),
+            allowed_geohash_prefixes: Vec::new(),
+        };
+        assert!(!fence.allows(&berlin, Some(&paris)));
+        assert!(!fence.allows(&berlin, None));
+
+        let fence = GeoFenceConfig {
+            radius_km: None,
+            allowed_geohash_prefixes: vec!["u0".into(), "u09".into()],
+        };
+        assert!(fence.allows(&berlin, Some(&paris)));
+        assert!(GeoFenceConfig::default().allows(&berlin, None));
+    }
+}
@@ -0,0 +1,440 @@
+//! 超参数共识与在线更新
+//!
+//! 提议者广播签名的 `HyperParamUpdate`，其中指定新的全局超参数（学习率、top-k、合并系数）
+//! 与生效纪元；同意的节点以自己的身份重新广播同一提议作为背书。
+//! 配置了委员会时，需达到法定人数的委员会成员（按固定的 ETH 地址识别）背书；
+//! 否则按质押加权投票，背书者的共识权重之和需达到已知节点总权重的阈值比例（本节点不计入）。
+//! 所有节点在纪元边界检查到期提议：已通过的提议同时生效，未通过的直接丢弃。
+//!
+//! 纪元调度随提议一同投票：最近一次生效的提议即全网共同的基准，之后每个纪元边界，各节点只凭
+//! 提议内容（含其中的调度）与纪元号推导本纪元的学习率、top-k 与快照间隔，不需要额外的消息
+//! 就能保持同一节奏。生效的提议随节点状态持久化，重启后接着按同一调度推导。
+
+use crate::inference::{BASE_MERGE_COEFFICIENT, DEFAULT_LEARNING_RATE, DEFAULT_SPARSE_K};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HyperParams {
+    pub learning_rate: f32,
+    pub sparse_k: usize,
+    pub merge_coefficient: f32,
+}
+
+impl Default for HyperParams {
+    fn default() -> Self {
+        Self {
+            learning_rate: DEFAULT_LEARNING_RATE,
+            sparse_k: DEFAULT_SPARSE_K,
+            merge_coefficient: BASE_MERGE_COEFFICIENT,
+        }
+    }
+}
+
+impl HyperParams {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.learning_rate > 0.0 && self.learning_rate <= 1.0) {
+            return Err(anyhow!(
+                "learning rate out of range: {}",
+                self.learning_rate
+            ));
+        }
+        if self.sparse_k == 0 {
+            return Err(anyhow!("sparse k must be positive"));
+        }
+        if !(self.merge_coefficient > 0.0 && self.merge_coefficient <= 1.0) {
+            return Err(anyhow!(
+                "merge coefficient out of range: {}",
+                self.merge_coefficient
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HyperParams {
+    type Err = anyhow::Error;
+
+    /// `lr=0.0005,k=32,merge=0.4`，未给出的项取默认值
+    fn from_str(s: &str) -> Result<Self> {
+        let mut params = HyperParams::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {:?}", part))?;
+            match key {
+                "lr" => params.learning_rate = value.parse()?,
+                "k" => params.sparse_k = value.parse()?,
+                "merge" => params.merge_coefficient = value.parse()?,
+                _ => return Err(anyhow!("unknown hyperparameter {:?}", key)),
+            }
+        }
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HyperParamProposal {
+    /// 提议者随机生成，同一 id 的不同内容视为冲突
+    pub proposal_id: u64,
+    pub params: HyperParams,
+    /// 生效纪元
+    pub apply_epoch: u64,
+    /// 生效后逐纪元推导参数，None 表示参数保持不变直到下一个提议
+    #[serde(default)]
+    pub schedule: Option<EpochSchedule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoteRule {
+    /// 委员会成员的 ETH 地址与所需背书数
+    Committee { members: Vec<String>, quorum: usize },
+    /// 背书权重占已知总权重的比例
+    Stake { threshold: f32 },
+    /// 每个纪元以随机信标为种子、按链上质押选出 `size` 名委员，所需背书数为 `quorum`
+    Elected { size: usize, quorum: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct HyperParamConfig {
+    pub rule: VoteRule,
+    /// 纪元长度，按 Unix 时间对齐
+    pub epoch: Duration,
+    /// 提议距生效的纪元数，留出背书传播的时间
+    pub lead_epochs: u64,
+    /// 自动为收到的有效提议背书
+    pub auto_endorse: bool,
+    /// 启动时发起的提议
+    pub propose: Option<HyperParams>,
+    /// 本节点发起的提议附带的纪元调度
+    pub schedule: Option<EpochSchedule>,
+}
+
+impl Default for HyperParamConfig {
+    fn default() -> Self {
+        Self {
+            rule: VoteRule::Stake {
+                threshold: 2.0 / 3.0,
+            },
+            epoch: Duration::from_secs(600),
+            lead_epochs: 2,
+            auto_endorse: false,
+            propose: None,
+            schedule: None,
+        }
+    }
+}
+
+impl HyperParamConfig {
+    pub fn epoch_at(&self, unix_secs: u64) -> u64 {
+        unix_secs / self.epoch.as_secs().max(1)
+    }
+}
+
+/// 纪元调度：以最近生效的提议为起点，按经过的纪元数衰减学习率、放大 top-k 与快照间隔
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EpochSchedule {
+    /// 每个纪元学习率乘以的系数
+    pub lr_decay: f32,
+    /// 学习率下限（不高于提议给出的学习率）
+    pub min_learning_rate: f32,
+    /// 每个纪元 top-k 乘以的系数
+    pub k_growth: f32,
+    /// top-k 上限（不低于提议给出的 top-k）
+    pub max_k: usize,
+    /// 快照间隔随学习率下降同比拉长，最多放大到此倍数
+    pub max_snapshot_factor: f32,
+}
+
+impl Default for EpochSchedule {
+    fn default() -> Self {
+        Self {
+            lr_decay: 0.9,
+            min_learning_rate: 1e-5,
+            k_growth: 1.0,
+            max_k: 1024,
+            max_snapshot_factor: 4.0,
+        }
+    }
+}
+
+impl EpochSchedule {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.lr_decay > 0.0 && self.lr_decay <= 1.0) {
+            return Err(anyhow!("lr decay out of range: {}", self.lr_decay));
+        }
+        if !(self.min_learning_rate > 0.0 && self.min_learning_rate.is_finite()) {
+            return Err(anyhow!("min learning rate must be positive"));
+        }
+        if !(self.k_growth >= 1.0 && self.k_growth.is_finite()) {
+            return Err(anyhow!("k growth must be at least 1: {}", self.k_growth));
+        }
+        if self.max_k == 0 {
+            return Err(anyhow!("max k must be positive"));
+        }
+        if !(self.max_snapshot_factor >= 1.0 && self.max_snapshot_factor.is_finite()) {
+            return Err(anyhow!(
+                "max snapshot factor must be at least 1: {}",
+                self.max_snapshot_factor
+            ));
+        }
+        Ok(())
+    }
+
+    /// 由 `since_epoch` 生效的 `base` 推导 `epoch` 的参数；只依赖输入，所有节点结果相同
+    pub fn derive(&self, base: &HyperParams, since_epoch: u64, epoch: u64) -> EpochParams {
+        let elapsed = epoch.saturating_sub(since_epoch).min(i32::MAX as u64) as i32;
+        let learning_rate = (base.learning_rate * self.lr_decay.powi(elapsed))
+            .max(self.min_learning_rate.min(base.learning_rate));
+        let sparse_k = ((base.sparse_k as f32 * self.k_growth.powi(elapsed)).round() as usize)
+            .clamp(1, self.max_k.max(base.sparse_k));
+        EpochParams {
+            epoch,
+            learning_rate,
+            sparse_k,
+            snapshot_factor: (base.learning_rate / learning_rate).clamp(1.0, self.max_snapshot_factor),
+        }
+    }
+}
+
+impl FromStr for EpochSchedule {
+    type Err = anyhow::Error;
+
+    /// `decay=0.9,min_lr=0.0001,k_growth=1.1,max_k=256,max_snapshot=4`，未给出的项取默认值
+    fn from_str(s: &str) -> Result<Self> {
+        let mut schedule = EpochSchedule::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {:?}", part))?;
+            match key {
+                "decay" => schedule.lr_decay = value.parse()?,
+                "min_lr" => schedule.min_learning_rate = value.parse()?,
+                "k_growth" => schedule.k_growth = value.parse()?,
+                "max_k" => schedule.max_k = value.parse()?,
+                "max_snapshot" => schedule.max_snapshot_factor = value.parse()?,
+                _ => return Err(anyhow!("unknown schedule parameter {:?}", key)),
+            }
+        }
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+
+/// 纪元调度推导出的某个纪元的参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochParams {
+    pub epoch: u64,
+    pub learning_rate: f32,
+    pub sparse_k: usize,
+    /// 快照间隔相对配置值的倍数
+    pub snapshot_factor: f32,
+}
+
+struct PendingProposal {
+    proposal: HyperParamProposal,
+    endorsers: HashSet<String>,
+}
+
+/// 单个会话的提议与当前生效的超参数
+#[derive(Default)]
+pub struct HyperParamBoard {
+    pending: HashMap<u64, PendingProposal>,
+    pub current: HyperParams,
+    /// 当前值所属的提议；尚无提议生效时为 None
+    finalized: Option<HyperParamProposal>,
+    /// 纪元调度最近推导到的纪元
+    scheduled: Option<u64>,
+    /// 从持久化状态恢复、尚未重新应用
+    restored: bool,
+}
+
+impl HyperParamBoard {
+    /// 记录背书，返回是否为该节点对该提议的首次背书；已过期、内容冲突的提议被拒绝
+    pub fn endorse(
+        &mut self,
+        proposal: &HyperParamProposal,
+        peer: &str,
+        epoch: u64,
+    ) -> Result<bool> {
+        proposal.params.validate()?;
+        if let Some(schedule) = &proposal.schedule {
+            schedule.validate()?;
+        }
+        if proposal.apply_epoch <= epoch {
+            return Err(anyhow!(
+                "proposal {} already past its epoch",
+                proposal.proposal_id
+            ));
+        }
+        let pending = self
+            .pending
+            .entry(proposal.proposal_id)
+            .or_insert_with(|| PendingProposal {
+                proposal: proposal.clone(),
+                endorsers: HashSet::new(),
+            });
+        if pending.proposal != *proposal {
+            return Err(anyhow!("conflicting proposal {}", proposal.proposal_id));
+        }
+        Ok(pending.endorsers.insert(peer.to_string()))
+    }
+
+    /// 本节点背书过、尚未生效的提议
+    pub fn endorsed_by(&self, peer: &str) -> Vec<HyperParamProposal> {
+        self.pending
+            .values()
+            .filter(|p| p.endorsers.contains(peer))
+            .map(|p| p.proposal.clone())
+            .collect()
+    }
+
+    /// 取出到期的提议，返回其中通过投票、生效纪元最晚（同纪元取 id 最大）的一个并设为当前值；
+    /// 没有新提议生效时，返回一次从持久化状态恢复的提议以便重新应用
+    pub fn take_due(
+        &mut self,
+        epoch: u64,
+        approves: impl Fn(&HashSet<String>) -> bool,
+    ) -> Option<HyperParamProposal> {
+        let due: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.proposal.apply_epoch <= epoch)
+            .map(|(id, _)| *id)
+            .collect();
+        let winner = due
+            .into_iter()
+            .filter_map(|id| self.pending.remove(&id))
+            .filter(|p| approves(&p.endorsers))
+            .map(|p| p.proposal)
+            .max_by_key(|p| (p.apply_epoch, p.proposal_id));
+        if std::mem::take(&mut self.restored) && winner.is_none() {
+            return self.finalized.clone();
+        }
+        let winner = winner?;
+        self.current = winner.params.clone();
+        self.finalized = Some(winner.clone());
+        self.scheduled = None;
+        Some(winner)
+    }
+
+    /// 进入新纪元时按生效提议中的调度推导本纪元的参数；尚无提议生效、提议未带调度
+    /// 或本纪元已推导过时返回 None
+    pub fn next_scheduled(&mut self, epoch: u64) -> Option<EpochParams> {
+        let finalized = self.finalized.as_ref()?;
+        let schedule = finalized.schedule.as_ref()?;
+        if self.scheduled.is_some_and(|e| e >= epoch) {
+            return None;
+        }
+        self.scheduled = Some(epoch);
+        Some(schedule.derive(&self.current, finalized.apply_epoch, epoch))
+    }
+
+    /// 当前生效的提议，用于持久化
+    pub fn finalized(&self) -> Option<&HyperParamProposal> {
+        self.finalized.as_ref()
+    }
+
+    /// 恢复重启前生效的提议；本地已有更新的生效提议时忽略
+    pub fn restore(&mut self, proposal: HyperParamProposal) {
+        if self.finalized.as_ref().is_some_and(|f| {
+            (f.apply_epoch, f.proposal_id) >= (proposal.apply_epoch, proposal.proposal_id)
+        }) {
+            return;
+        }
+        self.current = proposal.params.clone();
+        self.finalized = Some(proposal);
+        self.scheduled = None;
+        self.restored = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proposal_applies_only_with_quorum_at_epoch() {
+        let params: HyperParams = "lr=0.0005,k=32".parse().unwrap();
+        assert_eq!(params.merge_coefficient, BASE_MERGE_COEFFICIENT);
+        assert!("lr=2".parse::<HyperParams>().is_err());
+
+        let proposal = HyperParamProposal {
+            proposal_id: 1,
+            params,
+            apply_epoch: 10,
+            schedule: None,
+        };
+        let mut rejected = proposal.clone();
+        rejected.proposal_id = 2;
+
+        let mut board = HyperParamBoard::default();
+        assert!(board.endorse(&proposal, "a", 8).unwrap());
+        assert!(!board.endorse(&proposal, "a", 8).unwrap());
+        assert!(board.endorse(&proposal, "b", 9).unwrap());
+        assert!(board.endorse(&rejected, "a", 8).unwrap());
+        // 同一 id 内容不同、或已到期的提议不被接受
+        let mut conflicting = proposal.clone();
+        conflicting.params.sparse_k = 64;
+        assert!(board.endorse(&conflicting, "c", 8).is_err());
+        assert!(board.endorse(&proposal, "c", 10).is_err());
+
+        let quorum = |endorsers: &HashSet<String>| endorsers.len() >= 2;
+        assert_eq!(board.take_due(9, quorum), None);
+        assert_eq!(board.take_due(10, quorum), Some(proposal));
+        assert_eq!(board.current.sparse_k, 32);
+        // 未通过的提议到期后被丢弃
+        assert!(board.endorsed_by("a").is_empty());
+    }
+
+    #[test]
+    fn test_schedule_derives_epoch_params_from_finalized_proposal() {
+        let schedule: EpochSchedule = "decay=0.5,min_lr=0.0001,k_growth=2,max_k=100,max_snapshot=3"
+            .parse()
+            .unwrap();
+        assert!("decay=0".parse::<EpochSchedule>().is_err());
+        assert!("k_growth=0.5".parse::<EpochSchedule>().is_err());
+
+        let mut board = HyperParamBoard::default();
+        // 尚无提议生效时没有共同的基准
+        assert_eq!(board.next_scheduled(5), None);
+        let proposal = HyperParamProposal {
+            proposal_id: 7,
+            params: "lr=0.001,k=16".parse().unwrap(),
+            apply_epoch: 10,
+            schedule: Some(schedule.clone()),
+        };
+        let mut invalid = proposal.clone();
+        invalid.schedule = Some(EpochSchedule {
+            lr_decay: 2.0,
+            ..schedule.clone()
+        });
+        assert!(board.endorse(&invalid, "a", 9).is_err());
+        board.endorse(&proposal, "a", 9).unwrap();
+        // 晚一个纪元才注意到提议到期的节点，推导结果与准时的节点相同
+        board.take_due(11, |_| true).unwrap();
+        let derived = board.next_scheduled(11).unwrap();
+        assert_eq!(derived, schedule.derive(&proposal.params, 10, 11));
+        assert_eq!(derived.learning_rate, 0.0005);
+        assert_eq!(derived.sparse_k, 32);
+        assert_eq!(derived.snapshot_factor, 2.0);
+        assert_eq!(board.next_scheduled(11), None);
+
+        // 学习率、top-k 与快照倍数各自封顶
+        let late = board.next_scheduled(20).unwrap();
+        assert_eq!(late.learning_rate, 0.0001);
+        assert_eq!(late.sparse_k, 100);
+        assert_eq!(late.snapshot_factor, 3.0);
+
+        // 重启后恢复的提议重新应用一次，并接着按其中的调度推导
+        let mut restarted = HyperParamBoard::default();
+        restarted.restore(board.finalized().unwrap().clone());
+        assert_eq!(restarted.take_due(20, |_| true), Some(proposal));
+        assert_eq!(restarted.take_due(20, |_| true), None);
+        assert_eq!(restarted.next_scheduled(20), Some(late));
+    }
+}
@@ -1,17 +1,74 @@
-use crate::types::{decompress_indices, SparseUpdate, TensorSnapshot};
-use anyhow::{anyhow, Result};
+use crate::checkpoint;
+use crate::chunks::ModelChunkHashes;
+use crate::layers::LayerLayout;
+use crate::types::{compress_indices, decompress_indices, SparseUpdate, TensorSnapshot};
 use ndarray::Array1;
 use ndarray_npy::ReadNpyExt;
 use parking_lot::RwLock;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
+type Result<T, E = InferenceError> = std::result::Result<T, E>;
+
+/// 模型加载、检查点与层划分的错误，`code()` 为稳定的错误码
+#[derive(Debug, thiserror::Error)]
+pub enum InferenceError {
+    #[error("model file {0:?} not found")]
+    ModelNotFound(PathBuf),
+    #[error("读取模型文件 {path:?} 失败: {reason}")]
+    ModelLoad { path: PathBuf, reason: String },
+    /// 检查点的参数或残差长度与模型维度不符
+    #[error("checkpoint {field} dim {actual} != model dim {expected}")]
+    DimMismatch {
+        field: &'static str,
+        actual: usize,
+        expected: usize,
+    },
+    #[error("检查点读写失败: {0:#}")]
+    Checkpoint(anyhow::Error),
+    #[error("层划分无效: {0:#}")]
+    Layers(anyhow::Error),
+}
+
+impl InferenceError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            InferenceError::ModelNotFound(_) => "inference.model_not_found",
+            InferenceError::ModelLoad { .. } => "inference.model_load",
+            InferenceError::DimMismatch { .. } => "inference.dim_mismatch",
+            InferenceError::Checkpoint(_) => "inference.checkpoint",
+            InferenceError::Layers(_) => "inference.layers",
+        }
+    }
+}
+
+/// 稀疏更新默认保留的坐标数
+pub const DEFAULT_SPARSE_K: usize = 16;
+/// 稀疏更新的基准合并系数（新值所占比例）
+pub const BASE_MERGE_COEFFICIENT: f32 = 0.5;
+/// 默认学习率（本地训练步的最大扰动幅度）
+pub const DEFAULT_LEARNING_RATE: f32 = 1e-3;
+/// 默认的参数绝对值上限
+pub const DEFAULT_PARAM_BOUND: f32 = 10.0;
+
 #[derive(Clone)]
 pub struct InferenceConfig {
     pub model_dim: usize,
     pub model_path: Option<PathBuf>,
+    /// 随机初始化的种子，None 时每次启动不同（录制 / 重放时用于复现）
+    pub seed: Option<u64>,
+    /// 分层错峰同步的层划分，默认整个模型为一层
+    pub layers: LayerLayout,
+    /// 检查点目录，设置后启动时从最新检查点恢复
+    pub checkpoint_dir: Option<PathBuf>,
+    /// 合并远端更新后参数的绝对值上限
+    pub param_bound: f32,
 }
 
 impl Default for InferenceConfig {
@@ -19,15 +76,48 @@ impl Default for InferenceConfig {
         Self {
             model_dim: 256,
             model_path: None,
+            seed: None,
+            layers: LayerLayout::default(),
+            checkpoint_dir: None,
+            param_bound: DEFAULT_PARAM_BOUND,
         }
     }
 }
 
+impl InferenceConfig {
+    /// 非默认会话的检查点放在各自的子目录中
+    pub fn session_checkpoint_dir(&self, session_id: &str) -> Option<PathBuf> {
+        self.checkpoint_dir.as_ref().map(|dir| dir.join(session_id))
+    }
+}
+
+/// 模型检查点（用于崩溃恢复）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCheckpoint {
+    pub params: Vec<f32>,
+    pub version: u64,
+    /// 误差反馈残差，丢失会破坏 Top-K 压缩的误差补偿
+    #[serde(default)]
+    pub residual: Vec<f32>,
+    /// 上一步参数（收敛度追踪状态）
+    #[serde(default)]
+    pub previous_params: Option<Vec<f32>>,
+}
+
 #[derive(Clone)]
 pub struct InferenceEngine {
     state: Arc<RwLock<ModelState>>,
     config: InferenceConfig,
     memory_pressure: Arc<RwLock<MemoryPressure>>,
+    /// 稀疏更新的 top-k，由稀疏度控制器调整
+    sparse_k: Arc<AtomicUsize>,
+    /// 学习率（f32 位模式），可由超参数共识在线调整
+    learning_rate: Arc<AtomicU32>,
+    /// 各层的参数区间
+    layers: Arc<Vec<Range<usize>>>,
+    /// 稀疏更新 / 密集快照各自的轮转游标
+    sparse_layer: Arc<AtomicUsize>,
+    dense_layer: Arc<AtomicUsize>,
 }
 
 struct MemoryPressure {
@@ -42,36 +132,86 @@ struct ModelState {
     // 收敛度追踪
     previous_params: Option<Array1<f32>>,
     hash_history: Vec<String>,
+    /// 模型加载或从检查点恢复时的哈希
+    base_hash: String,
 }
 
 impl InferenceEngine {
     pub fn new(config: InferenceConfig) -> Result<Self> {
-        let params = load_or_random(config.model_dim, config.model_path.as_deref())?;
-        let residual = Array1::<f32>::zeros(params.len());
+        let initial = load_or_random(&config)?;
+        let params = Array1::from_vec(initial.params);
+        let residual = if initial.residual.len() == params.len() {
+            Array1::from_vec(initial.residual)
+        } else {
+            Array1::<f32>::zeros(params.len())
+        };
+        let layers = config
+            .layers
+            .ranges(params.len())
+            .map_err(InferenceError::Layers)?;
         
         // 估算内存使用：参数 + residual，每个 f32 4 字节
         let estimated_mb = (params.len() * 2 * 4) / (1024 * 1024);
+        let base_hash = TensorSnapshot::new(params.to_vec(), initial.version).hash();
         
         Ok(Self {
             state: Arc::new(RwLock::new(ModelState {
                 params: params.clone(),
                 residual,
-                version: 1,
+                version: initial.version,
                 previous_params: Some(params),
                 hash_history: Vec::new(),
+                base_hash,
             })),
             config,
             memory_pressure: Arc::new(RwLock::new(MemoryPressure {
                 current_usage_mb: estimated_mb,
                 pressure_threshold_mb: estimated_mb * 2, // 阈值设为当前使用的 2 倍
             })),
+            sparse_k: Arc::new(AtomicUsize::new(DEFAULT_SPARSE_K)),
+            learning_rate: Arc::new(AtomicU32::new(DEFAULT_LEARNING_RATE.to_bits())),
+            layers: Arc::new(layers),
+            sparse_layer: Arc::new(AtomicUsize::new(0)),
+            dense_layer: Arc::new(AtomicUsize::new(0)),
         })
     }
 
+    /// 按轮转顺序取下一层的区间
+    fn next_layer(&self, cursor: &AtomicUsize) -> Range<usize> {
+        let i = cursor.fetch_add(1, Ordering::Relaxed) % self.layers.len().max(1);
+        self.layers.get(i).cloned().unwrap_or(0..0)
+    }
+
+    /// 本次要广播的密集快照：轮转到的那一层的切片
+    pub fn dense_slice(&self) -> TensorSnapshot {
+        let layer = self.next_layer(&self.dense_layer);
+        let state = self.state.read();
+        let end = layer.end.min(state.params.len());
+        let start = layer.start.min(end);
+        let values = state.params.slice(ndarray::s![start..end]).to_vec();
+        TensorSnapshot::slice(values, start, state.version)
+    }
+
     pub fn model_dim(&self) -> usize {
         self.config.model_dim
     }
 
+    pub fn sparse_k(&self) -> usize {
+        self.sparse_k.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sparse_k(&self, k: usize) {
+        self.sparse_k.store(k.clamp(1, self.config.model_dim.max(1)), Ordering::Relaxed);
+    }
+
+    pub fn learning_rate(&self) -> f32 {
+        f32::from_bits(self.learning_rate.load(Ordering::Relaxed))
+    }
+
+    pub fn set_learning_rate(&self, learning_rate: f32) {
+        self.learning_rate.store(learning_rate.to_bits(), Ordering::Relaxed);
+    }
+
     pub fn embedding(&self) -> Vec<f32> {
         self.state.read().params.to_vec()
     }
@@ -81,10 +221,34 @@ impl InferenceEngine {
         TensorSnapshot::new(state.params.to_vec(), state.version)
     }
 
+    /// 模型谱系根：加载（检查点或初始化）时的参数哈希，从同一基础检查点出发的节点相同
+    pub fn base_hash(&self) -> String {
+        self.state.read().base_hash.clone()
+    }
+
+    /// `hash` 是否属于本模型的谱系：谱系根、当前哈希或最近的历史哈希
+    pub fn in_lineage(&self, hash: &str) -> bool {
+        let state = self.state.read();
+        state.base_hash == hash
+            || state.hash_history.iter().any(|h| h == hash)
+            || TensorSnapshot::new(state.params.to_vec(), state.version).hash() == hash
+    }
+
     pub fn tensor_hash(&self) -> String {
         self.tensor_snapshot().hash()
     }
 
+    /// 参数切成约 `chunks` 块的分块哈希
+    pub fn chunk_hashes(&self, chunks: usize) -> ModelChunkHashes {
+        ModelChunkHashes::compute(&self.state.read().params.to_vec(), chunks)
+    }
+
+    /// 对方宣告的分块哈希与本地参数不一致的区间
+    pub fn diverging_ranges(&self, hashes: &ModelChunkHashes) -> Option<Vec<Range<usize>>> {
+        hashes.diverging_ranges(&self.state.read().params.to_vec())
+    }
+
+    /// 在轮转到的那一层内选 top-k 生成稀疏更新
     pub fn make_sparse_update(&self, k: usize) -> SparseUpdate {
         // 检查内存压力，如果压力大则减少 Top-K
         let effective_k = if self.is_memory_pressured() {
@@ -93,48 +257,121 @@ impl InferenceEngine {
             k
         };
         
+        let layer = self.next_layer(&self.sparse_layer);
         let mut state = self.state.write();
         let dim = state.params.len();
-        if dim == 0 {
+        if dim == 0 || layer.is_empty() {
             return SparseUpdate {
                 indices: Vec::new(),
                 values: Vec::new(),
                 version: state.version,
+                seq: 0,
             };
         }
-        let mut delta = vec![0f32; dim];
-        for i in 0..dim {
-            delta[i] = state.params[i] + state.residual[i];
-        }
-        let mut idx_val: Vec<(usize, f32)> =
-            delta.iter().enumerate().map(|(i, v)| (i, *v)).collect();
+        let delta: Vec<f32> = state
+            .params
+            .iter()
+            .zip(state.residual.iter())
+            .map(|(p, r)| p + r)
+            .collect();
+        let mut idx_val: Vec<(usize, f32)> = delta
+            .iter()
+            .enumerate()
+            .skip(layer.start)
+            .take(layer.len())
+            .map(|(i, v)| (i, *v))
+            .collect();
         idx_val.sort_by(|a, b| {
             let av = a.1.abs();
             let bv = b.1.abs();
             bv.partial_cmp(&av).unwrap_or(std::cmp::Ordering::Equal)
         });
-        let take = effective_k.min(dim);
-        let topk = &idx_val[..take];
+        let take = effective_k.min(idx_val.len());
+        let topk = &mut idx_val[..take];
+        // 差分编码要求下标升序
+        topk.sort_by_key(|(i, _)| *i);
         let mut sparse_vals = Vec::with_capacity(take);
-        let mut sparse_idx = Vec::with_capacity(take);
-        let mut last = 0usize;
-        for (i, v) in topk {
-            let diff = if sparse_idx.is_empty() {
-                *i as u32
-            } else {
-                (*i - last) as u32
-            };
-            sparse_idx.push(diff);
+        let mut indices = Vec::with_capacity(take);
+        for (i, v) in topk.iter() {
+            indices.push(*i);
             sparse_vals.push(*v);
             state.residual[*i] = delta[*i] - *v;
-            last = *i;
         }
+        let sparse_idx = compress_indices(&indices);
         state.version = state.version.saturating_add(1);
         SparseUpdate {
             indices: sparse_idx,
             values: sparse_vals,
             version: state.version,
+            seq: 0,
+        }
+    }
+
+    /// 导出当前模型检查点
+    pub fn checkpoint(&self) -> ModelCheckpoint {
+        let state = self.state.read();
+        ModelCheckpoint {
+            params: state.params.to_vec(),
+            version: state.version,
+            residual: state.residual.to_vec(),
+            previous_params: state.previous_params.as_ref().map(|p| p.to_vec()),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.state.read().version
+    }
+
+    pub fn checkpoint_dir(&self) -> Option<&Path> {
+        self.config.checkpoint_dir.as_deref()
+    }
+
+    /// 写入检查点文件；未配置检查点目录时返回 None
+    pub fn save_checkpoint(&self, keep: usize) -> Result<Option<PathBuf>> {
+        let Some(dir) = &self.config.checkpoint_dir else {
+            return Ok(None);
+        };
+        checkpoint::save(dir, &self.checkpoint(), keep)
+            .map(Some)
+            .map_err(InferenceError::Checkpoint)
+    }
+
+    /// 从检查点恢复参数、残差与版本
+    ///
+    /// 先校验所有维度再在同一把写锁内整体替换，避免参数与残差不一致。
+    /// 旧格式检查点没有残差时按全零恢复。
+    pub fn restore_checkpoint(&self, checkpoint: &ModelCheckpoint) -> Result<()> {
+        let mut state = self.state.write();
+        let dim = state.params.len();
+        if checkpoint.params.len() != dim {
+            return Err(InferenceError::DimMismatch {
+                field: "params",
+                actual: checkpoint.params.len(),
+                expected: dim,
+            });
+        }
+        if !checkpoint.residual.is_empty() && checkpoint.residual.len() != dim {
+            return Err(InferenceError::DimMismatch {
+                field: "residual",
+                actual: checkpoint.residual.len(),
+                expected: dim,
+            });
         }
+        let previous = match &checkpoint.previous_params {
+            Some(prev) if prev.len() == dim => Array1::from_vec(prev.clone()),
+            _ => Array1::from_vec(checkpoint.params.clone()),
+        };
+        state.params = Array1::from_vec(checkpoint.params.clone());
+        state.residual = if checkpoint.residual.is_empty() {
+            Array1::zeros(dim)
+        } else {
+            Array1::from_vec(checkpoint.residual.clone())
+        };
+        state.previous_params = Some(previous);
+        state.version = checkpoint.version;
+        state.hash_history.clear();
+        state.base_hash = TensorSnapshot::new(checkpoint.params.clone(), checkpoint.version).hash();
+        Ok(())
     }
 
     /// 检查是否处于内存压力状态
@@ -155,10 +392,14 @@ impl InferenceEngine {
         self.memory_pressure.write().pressure_threshold_mb = threshold_mb;
     }
 
-    pub fn apply_sparse_update(&self, update: &SparseUpdate) {
+    /// 按 `coefficient`（新值所占比例，0..=1）合并稀疏更新，返回被截断或还原的参数个数
+    pub fn apply_sparse_update(&self, update: &SparseUpdate, coefficient: f32) -> usize {
+        let coefficient = coefficient.clamp(0.0, 1.0);
         if update.indices.is_empty() {
-            return;
+            return 0;
         }
+        let bound = self.config.param_bound;
+        let mut clamped = 0;
         let idxs = decompress_indices(&update.indices);
         let mut state = self.state.write();
         
@@ -168,7 +409,8 @@ impl InferenceEngine {
         for (pos, &v) in idxs.iter().zip(update.values.iter()) {
             if *pos < state.params.len() {
                 let old = state.params[*pos];
-                let merged = 0.5 * old + 0.5 * v;
+                let (merged, fixed) = bounded((1.0 - coefficient) * old + coefficient * v, old, bound);
+                clamped += usize::from(fixed);
                 state.params[*pos] = merged;
                 state.residual[*pos] += old - merged;
             }
@@ -183,17 +425,28 @@ impl InferenceEngine {
         if state.hash_history.len() > 10 {
             state.hash_history.remove(0);
         }
+        clamped
     }
 
-    pub fn apply_dense_snapshot(&self, snapshot: &TensorSnapshot) {
+    /// 融合密集快照，返回被截断或还原的参数个数
+    pub fn apply_dense_snapshot(&self, snapshot: &TensorSnapshot) -> usize {
+        let bound = self.config.param_bound;
+        let mut clamped = 0;
         let mut state = self.state.write();
         
         // 保存当前参数用于收敛度计算
         state.previous_params = Some(state.params.clone());
         
-        let len = state.params.len().min(snapshot.values.len());
+        // 分层快照只覆盖 [offset, offset + len) 区间
+        let dim = state.params.len();
+        let start = snapshot.offset.min(dim);
+        let len = (dim - start).min(snapshot.values.len());
         for i in 0..len {
-            state.params[i] = 0.8 * state.params[i] + 0.2 * snapshot.values[i];
+            let p = start + i;
+            let old = state.params[p];
+            let (merged, fixed) = bounded(0.8 * old + 0.2 * snapshot.values[i], old, bound);
+            clamped += usize::from(fixed);
+            state.params[p] = merged;
         }
         state.version = state.version.max(snapshot.version);
         
@@ -205,22 +458,25 @@ impl InferenceEngine {
         if state.hash_history.len() > 10 {
             state.hash_history.remove(0);
         }
+        clamped
     }
 
     pub fn local_train_step(&self) {
         let mut rng = rand::thread_rng();
+        let lr = self.learning_rate();
         let mut state = self.state.write();
         
         // 保存当前参数用于收敛度计算
         state.previous_params = Some(state.params.clone());
         
         for v in state.params.iter_mut() {
-            *v += rng.gen_range(-1e-3..1e-3);
+            *v += rng.gen_range(-lr..lr);
         }
         state.version = state.version.saturating_add(1);
         
         // 更新 hash 历史（保留最近 10 个）
-        let hash = self.tensor_hash();
+        // 已持有写锁，不能再经 tensor_hash 取读锁
+        let hash = TensorSnapshot::new(state.params.to_vec(), state.version).hash();
         state.hash_history.push(hash);
         if state.hash_history.len() > 10 {
             state.hash_history.remove(0);
@@ -233,7 +489,7 @@ impl InferenceEngine {
         let state = self.state.read();
         
         // 如果没有历史数据，返回 0.0
-        if state.previous_params.is_none() || state.params.len() == 0 {
+        if state.previous_params.is_none() || state.params.is_empty() {
             return 0.0;
         }
         
@@ -309,7 +565,7 @@ impl InferenceEngine {
     /// 计算参数的标准差
     pub fn parameter_std_dev(&self) -> f32 {
         let state = self.state.read();
-        if state.params.len() == 0 {
+        if state.params.is_empty() {
             return 0.0;
         }
         let mean = state.params.iter().sum::<f32>() / state.params.len() as f32;
@@ -320,17 +576,81 @@ impl InferenceEngine {
     }
 }
 
-fn load_or_random(dim: usize, path: Option<&Path>) -> Result<Array1<f32>> {
-    if let Some(path) = path {
-        if path.exists() {
-            let file = File::open(path)?;
-            let arr: Array1<f32> = Array1::read_npy(file)?;
-            return Ok(arr);
-        } else {
-            return Err(anyhow!("model file {:?} not found", path));
+/// 合并结果非有限时保留旧值，否则截断到 [-bound, bound]；第二个返回值表示是否做了修正
+fn bounded(merged: f32, old: f32, bound: f32) -> (f32, bool) {
+    if !merged.is_finite() {
+        return (old, true);
+    }
+    let clamped = merged.clamp(-bound, bound);
+    (clamped, clamped != merged)
+}
+
+/// 初始模型：检查点目录中最新的检查点优先，其次是模型文件，最后随机初始化
+fn load_or_random(config: &InferenceConfig) -> Result<ModelCheckpoint> {
+    let loaded = match &config.model_path {
+        Some(path) if path.exists() => {
+            let load = |reason: String| InferenceError::ModelLoad {
+                path: path.clone(),
+                reason,
+            };
+            let file = File::open(path).map_err(|e| load(e.to_string()))?;
+            let arr: Array1<f32> = Array1::read_npy(file).map_err(|e| load(e.to_string()))?;
+            Some(arr.to_vec())
         }
+        Some(path) => return Err(InferenceError::ModelNotFound(path.clone())),
+        None => None,
+    };
+    let dim = loaded.as_ref().map_or(config.model_dim, Vec::len);
+    if let Some(dir) = &config.checkpoint_dir {
+        if let Some(checkpoint) =
+            checkpoint::latest(dir, Some(dim)).map_err(InferenceError::Checkpoint)?
+        {
+            println!("[检查点] 从 {} 恢复模型 v{}", dir.display(), checkpoint.version);
+            return Ok(checkpoint);
+        }
+    }
+    let params = loaded.unwrap_or_else(|| {
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        (0..dim).map(|_| rng.gen_range(-0.1..0.1)).collect()
+    });
+    Ok(ModelCheckpoint {
+        params,
+        version: 1,
+        residual: Vec::new(),
+        previous_params: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merges_clamp_to_bounds_and_keep_finite() {
+        let engine = InferenceEngine::new(InferenceConfig {
+            model_dim: 4,
+            seed: Some(7),
+            param_bound: 1.0,
+            ..InferenceConfig::default()
+        })
+        .unwrap();
+        let before = engine.tensor_snapshot().values;
+        let update = SparseUpdate {
+            indices: compress_indices(&[0, 1]),
+            values: vec![100.0, f32::INFINITY],
+            version: 2,
+            seq: 0,
+        };
+        assert_eq!(engine.apply_sparse_update(&update, 1.0), 2);
+        let after = engine.tensor_snapshot().values;
+        assert_eq!(after[0], 1.0);
+        assert_eq!(after[1], before[1]);
+
+        let snapshot = TensorSnapshot::new(vec![-100.0, 0.0, 0.0, 0.0], 3);
+        assert_eq!(engine.apply_dense_snapshot(&snapshot), 1);
+        assert!(engine.tensor_snapshot().values.iter().all(|v| v.abs() <= 1.0));
     }
-    let mut rng = rand::thread_rng();
-    let data: Vec<f32> = (0..dim).map(|_| rng.gen_range(-0.1..0.1)).collect();
-    Ok(Array1::from_vec(data))
 }
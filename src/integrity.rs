@@ -0,0 +1,132 @@
+//! 密集快照应用前的完整性校验
+//!
+//! 快照可能经 gossip、惰性推拉或纠删码重建到达，都在融合前经过同一道校验：
+//! 声明的维度必须与数据长度一致；版本与发送者最近一次心跳宣告的版本相同的完整快照，
+//! 哈希必须与心跳中的模型哈希一致（版本不同时对方模型已继续训练，无法比较）；
+//! 同一发送者同一偏移处的快照版本必须递增（分层切片共用版本号）。纠删码重建的快照未经过入站管道，
+//! 这里同时拒绝非有限值。维度不符与非有限值是伪造或损坏，接收方应惩罚发送者；
+//! 融合不增加版本号，心跳之后的融合也会让同版本哈希不符，版本回退可能只是多条路径的乱序到达，
+//! 这两种只拒绝不惩罚。
+
+use crate::types::TensorSnapshot;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// 声明的维度与数据长度不符
+    DimMismatch { dim: usize, len: usize },
+    /// 含 NaN / Inf
+    NonFinite,
+    /// 与心跳宣告的同版本模型哈希不符
+    HashMismatch { advertised: String, actual: String },
+    /// 版本不高于已应用的快照
+    StaleVersion { version: u64, applied: u64 },
+}
+
+impl SnapshotError {
+    /// 是否说明发送者伪造或损坏了快照
+    pub fn is_misbehavior(&self) -> bool {
+        matches!(
+            self,
+            SnapshotError::DimMismatch { .. } | SnapshotError::NonFinite
+        )
+    }
+}
+
+/// 按（会话, 发送者, 偏移）记录已应用的快照版本
+#[derive(Default)]
+pub struct SnapshotVerifier {
+    applied: HashMap<(String, String, usize), u64>,
+}
+
+impl SnapshotVerifier {
+    /// `advertised` 为发送者最近一次心跳的（模型哈希, 版本）；`full` 表示快照覆盖整个参数向量。
+    /// 校验通过时记录版本
+    pub fn verify(
+        &mut self,
+        session: &str,
+        sender: &str,
+        snapshot: &TensorSnapshot,
+        advertised: Option<(&str, u64)>,
+        full: bool,
+    ) -> Result<(), SnapshotError> {
+        if snapshot.dim != snapshot.values.len() {
+            return Err(SnapshotError::DimMismatch {
+                dim: snapshot.dim,
+                len: snapshot.values.len(),
+            });
+        }
+        if !snapshot.is_finite() {
+            return Err(SnapshotError::NonFinite);
+        }
+        if let Some((hash, version)) = advertised.filter(|_| full) {
+            if version == snapshot.version {
+                let actual = snapshot.hash();
+                if actual != hash {
+                    return Err(SnapshotError::HashMismatch {
+                        advertised: hash.to_string(),
+                        actual,
+                    });
+                }
+            }
+        }
+        let key = (session.to_string(), sender.to_string(), snapshot.offset);
+        if let Some(&applied) = self.applied.get(&key) {
+            if snapshot.version <= applied {
+                return Err(SnapshotError::StaleVersion {
+                    version: snapshot.version,
+                    applied,
+                });
+            }
+        }
+        self.applied.insert(key, snapshot.version);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_forged_and_stale_snapshots() {
+        let mut verifier = SnapshotVerifier::default();
+        let snapshot = TensorSnapshot::new(vec![0.5, -0.25, 1.0], 7);
+        let hash = snapshot.hash();
+
+        let mut truncated = snapshot.clone();
+        truncated.values.pop();
+        let err = verifier
+            .verify("s", "a", &truncated, None, true)
+            .unwrap_err();
+        assert_eq!(err, SnapshotError::DimMismatch { dim: 3, len: 2 });
+        let mut poisoned = snapshot.clone();
+        poisoned.values[1] = f32::NAN;
+        let err = verifier.verify("s", "a", &poisoned, None, true).unwrap_err();
+        assert_eq!(err, SnapshotError::NonFinite);
+
+        let mut tampered = snapshot.clone();
+        tampered.values[0] = 9.0;
+        let err = verifier
+            .verify("s", "a", &tampered, Some((&hash, 7)), true)
+            .unwrap_err();
+        assert!(matches!(err, SnapshotError::HashMismatch { .. }) && !err.is_misbehavior());
+        // 分层切片或版本已前进时无法与心跳哈希比较
+        assert!(verifier.verify("s", "a", &tampered, Some((&hash, 7)), false).is_ok());
+
+        let err = verifier
+            .verify("s", "a", &snapshot, Some((&hash, 7)), true)
+            .unwrap_err();
+        assert_eq!(err, SnapshotError::StaleVersion { version: 7, applied: 7 });
+        assert!(!err.is_misbehavior());
+        assert!(verifier.verify("s", "b", &snapshot, Some((&hash, 7)), true).is_ok());
+
+        // 同一版本的其他层切片按偏移分别记录
+        let layer = TensorSnapshot::slice(vec![0.1, 0.2], 3, 7);
+        assert!(verifier.verify("s", "a", &layer, Some((&hash, 7)), false).is_ok());
+        let err = verifier
+            .verify("s", "a", &layer, Some((&hash, 7)), false)
+            .unwrap_err();
+        assert_eq!(err, SnapshotError::StaleVersion { version: 7, applied: 7 });
+    }
+}
@@ -0,0 +1,336 @@
+//! 训练任务定义与发现
+//!
+//! 任务发布者通过已签名的 `GgsMessage::JobAnnounce` 在默认主题上周期性广播任务描述
+//! （模型结构 / 哈希、超参数、数据集、主题、奖励条款与设备要求）。
+//! 其他节点收到后记录在 [`JobRegistry`] 中，若开启自动加入且设备能力满足要求，
+//! 则为该任务创建一个新的训练会话。任务描述写明所有者时只接受所有者签名的广播；
+//! 指定租约发放者的任务必须写明所有者，否则任何人抢先广播同一任务 id 就能接管租约。
+
+use crate::dataset::{assign_data_shards, ManifestShard};
+use crate::device::{DeviceCapabilities, NetworkType};
+use crate::inference::InferenceConfig;
+use crate::session::{SessionConfig, DEFAULT_SESSION};
+use crate::workload;
+use anyhow::{anyhow, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobHyperparams {
+    pub learning_rate: f32,
+    /// 稀疏更新的 top-k
+    pub sparse_k: usize,
+    /// 本地训练间隔（秒）
+    pub train_interval_secs: u64,
+}
+
+impl Default for JobHyperparams {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.01,
+            sparse_k: 16,
+            train_interval_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetSpec {
+    pub name: String,
+    /// 数据集位置（IPFS CID、URL 等），由训练节点自行拉取
+    #[serde(default)]
+    pub uri: Option<String>,
+    #[serde(default)]
+    pub shards: u32,
+    /// 分片清单，非空时按节点身份分配分片（分片数取清单长度），否则按算力切分 `shards`
+    #[serde(default)]
+    pub manifest: Vec<ManifestShard>,
+    /// 按身份分配时每个纪元的秒数，0 表示不轮换
+    #[serde(default)]
+    pub epoch_secs: u64,
+}
+
+impl DatasetSpec {
+    pub fn shard_count(&self) -> u32 {
+        if self.manifest.is_empty() {
+            self.shards
+        } else {
+            self.manifest.len() as u32
+        }
+    }
+
+    pub fn epoch_at(&self, unix_secs: u64) -> u64 {
+        unix_secs.checked_div(self.epoch_secs).unwrap_or(0)
+    }
+
+    /// 把分片分给训练者：有清单时按身份（与最近的信标值）分配当前纪元的分片，否则按算力 `(节点, TFLOPS)` 切分区间
+    pub fn assign(
+        &self,
+        job_id: &str,
+        nodes: &[(String, f32)],
+        unix_secs: u64,
+        beacon: Option<&[u8; 32]>,
+    ) -> HashMap<String, Vec<u32>> {
+        let total = self.shard_count();
+        if self.manifest.is_empty() {
+            return workload::assign_shards(total, nodes)
+                .into_iter()
+                .map(|(peer, range)| (peer, range.collect()))
+                .collect();
+        }
+        let peers: Vec<String> = nodes.iter().map(|(peer, _)| peer.clone()).collect();
+        assign_data_shards(job_id, self.epoch_at(unix_secs), beacon, total, &peers)
+    }
+}
+
+/// 奖励条款：每个有效训练轮次的奖励与参与门槛
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RewardTerms {
+    pub eth_per_round: f64,
+    pub sol_per_round: f64,
+    /// 参与者最低信誉
+    #[serde(default)]
+    pub min_reputation: f64,
+}
+
+/// 参与任务所需的最低设备能力
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobRequirements {
+    pub min_memory_mb: usize,
+    pub min_cpu_cores: usize,
+    #[serde(default)]
+    pub requires_gpu: bool,
+    /// 是否允许在计费网络（移动数据）上参与
+    #[serde(default)]
+    pub allow_cellular: bool,
+}
+
+impl JobRequirements {
+    pub fn satisfied_by(&self, capabilities: &DeviceCapabilities) -> bool {
+        let network_ok = self.allow_cellular
+            || !matches!(
+                capabilities.network_type,
+                NetworkType::Cellular4G | NetworkType::Cellular5G
+            );
+        capabilities.max_memory_mb >= self.min_memory_mb
+            && capabilities.cpu_cores >= self.min_cpu_cores
+            && (capabilities.has_gpu || !self.requires_gpu)
+            && network_ok
+    }
+}
+
+/// 训练任务描述
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobSpec {
+    /// 任务 id，同时作为训练会话 id
+    pub job_id: String,
+    /// 模型结构描述（如 "mlp-4096"）
+    pub architecture: String,
+    pub model_dim: usize,
+    /// 初始模型哈希，None 表示随机初始化
+    #[serde(default)]
+    pub model_hash: Option<String>,
+    #[serde(default)]
+    pub hyperparams: JobHyperparams,
+    #[serde(default)]
+    pub dataset: DatasetSpec,
+    /// 任务使用的 gossip 主题
+    pub topic: String,
+    #[serde(default)]
+    pub reward: RewardTerms,
+    #[serde(default)]
+    pub requirements: JobRequirements,
+    /// 租约发放者（聚合者 PeerId）；非空时只接受持有其中之一签发的有效租约的训练者的更新
+    #[serde(default)]
+    pub lease_issuers: Vec<String>,
+    /// 任务所有者 PeerId，只接受其签名的广播；本节点发布的任务未填写时广播前填为本节点
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+impl JobSpec {
+    /// 从 JSON 文件加载任务描述
+    pub fn load(path: &Path) -> Result<Self> {
+        let spec: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.job_id.is_empty() || self.job_id == DEFAULT_SESSION {
+            return Err(anyhow!("invalid job id {:?}", self.job_id));
+        }
+        if self.model_dim == 0 {
+            return Err(anyhow!("job {} has zero model dim", self.job_id));
+        }
+        if self.topic.is_empty() {
+            return Err(anyhow!("job {} has no topic", self.job_id));
+        }
+        // 租约中的分片取自数据集，没有分片时永远发不出租约，所有更新都会被丢弃
+        if !self.lease_issuers.is_empty() && self.dataset.shard_count() == 0 {
+            return Err(anyhow!(
+                "job {} has lease issuers but no dataset shards",
+                self.job_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// 转换为训练会话配置
+    pub fn session_config(&self, base_inference: &InferenceConfig) -> SessionConfig {
+        let mut inference = base_inference.clone();
+        inference.model_dim = self.model_dim;
+        inference.model_path = None;
+        inference.checkpoint_dir = base_inference.session_checkpoint_dir(&self.job_id);
+        SessionConfig {
+            id: self.job_id.clone(),
+            topic: self.topic.clone(),
+            inference,
+            bandwidth_share: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct JobConfig {
+    /// 发现匹配的任务后是否自动加入
+    pub auto_join: bool,
+    /// 最多自动加入的任务数
+    pub max_joined: usize,
+    /// 本节点发布的任务
+    pub announce: Vec<JobSpec>,
+    /// 超过该时间未再收到广播的任务视为已结束
+    pub expiry: Duration,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            auto_join: true,
+            max_joined: 2,
+            announce: Vec::new(),
+            expiry: Duration::from_secs(600),
+        }
+    }
+}
+
+struct DiscoveredJob {
+    spec: JobSpec,
+    announcer: String,
+    last_seen: Instant,
+}
+
+/// 已发现的任务表
+pub struct JobRegistry {
+    expiry: Duration,
+    jobs: RwLock<HashMap<String, DiscoveredJob>>,
+}
+
+impl JobRegistry {
+    pub fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            jobs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 记录一次任务广播，首次发现或描述变化时返回 true。
+    /// 写明所有者的任务只接受所有者的广播，指定租约发放者的任务必须写明所有者；
+    /// 同一任务 id 只接受最初发布者的更新，防止他人篡改任务描述。
+    pub fn observe(&self, spec: JobSpec, announcer: &str) -> bool {
+        let owned_by_announcer = spec.owner.as_deref() == Some(announcer);
+        if (spec.owner.is_some() || !spec.lease_issuers.is_empty()) && !owned_by_announcer {
+            return false;
+        }
+        let mut jobs = self.jobs.write();
+        match jobs.get_mut(&spec.job_id) {
+            Some(existing) if existing.announcer != announcer => false,
+            Some(existing) => {
+                existing.last_seen = Instant::now();
+                let changed = existing.spec != spec;
+                existing.spec = spec;
+                changed
+            }
+            None => {
+                jobs.insert(
+                    spec.job_id.clone(),
+                    DiscoveredJob {
+                        spec,
+                        announcer: announcer.to_string(),
+                        last_seen: Instant::now(),
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    pub fn prune_stale(&self) {
+        let expiry = self.expiry;
+        self.jobs
+            .write()
+            .retain(|_, job| job.last_seen.elapsed() <= expiry);
+    }
+
+    pub fn list(&self) -> Vec<JobSpec> {
+        self.jobs.read().values().map(|job| job.spec.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(id: &str) -> JobSpec {
+        JobSpec {
+            job_id: id.into(),
+            architecture: "mlp".into(),
+            model_dim: 64,
+            model_hash: None,
+            hyperparams: JobHyperparams::default(),
+            dataset: DatasetSpec::default(),
+            topic: format!("ggs-job-{id}"),
+            reward: RewardTerms::default(),
+            requirements: JobRequirements {
+                min_memory_mb: 1024,
+                min_cpu_cores: 2,
+                requires_gpu: false,
+                allow_cellular: false,
+            },
+            lease_issuers: Vec::new(),
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_rejects_foreign_updates() {
+        let registry = JobRegistry::new(Duration::from_secs(60));
+        assert!(registry.observe(spec("a"), "peer-1"));
+        assert!(!registry.observe(spec("a"), "peer-1"));
+        let mut hijacked = spec("a");
+        hijacked.topic = "evil".into();
+        assert!(!registry.observe(hijacked, "peer-2"));
+        assert_eq!(registry.list()[0].topic, "ggs-job-a");
+
+        // 带租约发放者的任务必须由写明的所有者广播，且数据集要有分片
+        let mut gated = spec("b");
+        gated.lease_issuers = vec!["aggregator".into()];
+        assert!(gated.validate().is_err());
+        gated.dataset.shards = 8;
+        assert!(gated.validate().is_ok());
+        assert!(!registry.observe(gated.clone(), "peer-1"));
+        gated.owner = Some("owner".into());
+        assert!(!registry.observe(gated.clone(), "peer-1"));
+        assert!(registry.observe(gated, "owner"));
+    }
+
+    #[test]
+    fn test_requirements_match_capabilities() {
+        let requirements = spec("a").requirements;
+        assert!(requirements.satisfied_by(&DeviceCapabilities::default_desktop()));
+        assert!(!requirements.satisfied_by(&DeviceCapabilities::low_end_mobile()));
+    }
+}
@@ -0,0 +1,177 @@
+//! 按节点统计单向消息延迟
+//!
+//! 延迟取本地接收时间减去签名覆盖的发送时间，包含双方的时钟偏差；节点时钟经 NTP 同步时，
+//! 偏差远小于广域网延迟。每个节点按传输路径（gossip / QUIC 直连）分别维护对数分桶直方图，
+//! 样本数达到上限后所有桶减半，使分位数跟随近期的链路状况。
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 直方图桶的上界（毫秒），最后一个桶收纳更大的值
+const BUCKET_BOUNDS_MS: [u64; 14] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000,
+];
+/// 样本数达到该值时所有桶减半
+const DECAY_AT: u32 = 512;
+/// 计算分位数所需的最少样本数
+const MIN_SAMPLES: u32 = 8;
+/// 超过该时长没有新样本的节点在记录新样本时被清理
+const STALE_AFTER: Duration = Duration::from_secs(600);
+/// 直连 p95 超过 gossip p95 的该倍数时改走 gossip
+const DIRECT_PENALTY_FACTOR: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LatencyPath {
+    Gossip,
+    Direct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub samples: u32,
+}
+
+struct Histogram {
+    buckets: [u32; BUCKET_BOUNDS_MS.len() + 1],
+    total: u32,
+    updated: Instant,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: [0; BUCKET_BOUNDS_MS.len() + 1],
+            total: 0,
+            updated: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, latency_ms: u64) {
+        let index = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[index] += 1;
+        self.total += 1;
+        self.updated = Instant::now();
+        if self.total >= DECAY_AT {
+            for bucket in &mut self.buckets {
+                *bucket /= 2;
+            }
+            self.total = self.buckets.iter().sum();
+        }
+    }
+
+    /// 分位数所在桶的上界，落在最后一个桶时取最大的上界
+    fn quantile(&self, q: f64) -> u64 {
+        let rank = (self.total as f64 * q).ceil().max(1.0) as u32;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS[index.min(BUCKET_BOUNDS_MS.len() - 1)];
+            }
+        }
+        BUCKET_BOUNDS_MS[BUCKET_BOUNDS_MS.len() - 1]
+    }
+
+    fn summary(&self) -> Option<LatencySummary> {
+        (self.total >= MIN_SAMPLES).then(|| LatencySummary {
+            p50_ms: self.quantile(0.5),
+            p95_ms: self.quantile(0.95),
+            samples: self.total,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LatencyTracker {
+    histograms: RwLock<HashMap<(String, LatencyPath), Histogram>>,
+}
+
+impl LatencyTracker {
+    /// 记录一条消息的延迟并返回该路径的最新分位数；时间戳为 0 的旧版本消息不计入
+    pub fn observe(
+        &self,
+        peer: &str,
+        path: LatencyPath,
+        sent_at_ms: u64,
+        now_ms: u64,
+    ) -> Option<LatencySummary> {
+        if sent_at_ms == 0 {
+            return None;
+        }
+        let mut histograms = self.histograms.write();
+        let key = (peer.to_string(), path);
+        if !histograms.contains_key(&key) {
+            histograms.retain(|_, h| h.updated.elapsed() < STALE_AFTER);
+        }
+        let histogram = histograms.entry(key).or_insert_with(Histogram::new);
+        histogram.record(now_ms.saturating_sub(sent_at_ms));
+        histogram.summary()
+    }
+
+    pub fn summary(&self, peer: &str, path: LatencyPath) -> Option<LatencySummary> {
+        self.histograms
+            .read()
+            .get(&(peer.to_string(), path))
+            .and_then(Histogram::summary)
+    }
+
+    /// 两条路径中 p50 较低的一条，用作拓扑评分的延迟信号
+    pub fn best(&self, peer: &str) -> Option<LatencySummary> {
+        [LatencyPath::Gossip, LatencyPath::Direct]
+            .into_iter()
+            .filter_map(|path| self.summary(peer, path))
+            .min_by_key(|s| s.p50_ms)
+    }
+
+    /// 两条路径都有足够样本且直连的 p95 明显高于 gossip 时，发往该节点的消息改走 gossip
+    pub fn prefers_gossip(&self, peer: &str) -> bool {
+        match (
+            self.summary(peer, LatencyPath::Direct),
+            self.summary(peer, LatencyPath::Gossip),
+        ) {
+            (Some(direct), Some(gossip)) => direct.p95_ms > gossip.p95_ms * DIRECT_PENALTY_FACTOR,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_quantiles_per_path_and_picks_faster_path() {
+        let tracker = LatencyTracker::default();
+        let now = 1_000_000;
+        assert!(tracker.observe("a", LatencyPath::Gossip, 0, now).is_none());
+        for i in 0..19 {
+            tracker.observe("a", LatencyPath::Gossip, now - 30 - i, now);
+        }
+        let gossip = tracker
+            .observe("a", LatencyPath::Gossip, now - 900, now)
+            .unwrap();
+        assert_eq!((gossip.p50_ms, gossip.p95_ms, gossip.samples), (50, 50, 20));
+        assert!(tracker.summary("a", LatencyPath::Direct).is_none());
+        assert!(!tracker.prefers_gossip("a"));
+
+        for _ in 0..MIN_SAMPLES {
+            tracker.observe("a", LatencyPath::Direct, now - 300, now);
+        }
+        assert!(tracker.prefers_gossip("a"));
+        assert_eq!(tracker.best("a"), Some(gossip));
+
+        // 衰减后样本数减半，分位数仍然有效
+        for _ in 0..DECAY_AT {
+            tracker.observe("b", LatencyPath::Direct, now - 3, now);
+        }
+        let b = tracker.summary("b", LatencyPath::Direct).unwrap();
+        assert_eq!((b.p50_ms, b.samples), (5, DECAY_AT / 2));
+    }
+}
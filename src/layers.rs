@@ -0,0 +1,100 @@
+//! 分层错峰同步
+//!
+//! 把扁平参数向量划分为若干层（张量），每次同步只发送其中一层，按轮转顺序推进：
+//! 稀疏更新只在当前层内选 top-k，密集快照只携带当前层的切片。
+//! 单次消息的体积随之缩小，带宽占用更平滑，更大的模型也能放进每个窗口的预算。
+//! 下标始终是全局下标、快照带偏移，接收方无需知道发送方的分层方式。
+
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerLayout {
+    /// 均分为 N 层，最后一层包含余数
+    Uniform(usize),
+    /// 按给定大小划分，总和必须等于模型维度
+    Sizes(Vec<usize>),
+}
+
+impl Default for LayerLayout {
+    fn default() -> Self {
+        LayerLayout::Uniform(1)
+    }
+}
+
+impl FromStr for LayerLayout {
+    type Err = anyhow::Error;
+
+    /// `4` 表示均分 4 层，`1024,2048,1024` 表示按大小划分
+    fn from_str(s: &str) -> Result<Self> {
+        if s.contains(',') {
+            let sizes = s
+                .split(',')
+                .map(|p| p.trim().parse::<usize>())
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if sizes.contains(&0) {
+                return Err(anyhow!("layer sizes must be positive: {:?}", s));
+            }
+            Ok(LayerLayout::Sizes(sizes))
+        } else {
+            match s.trim().parse()? {
+                0 => Err(anyhow!("layer count must be positive")),
+                n => Ok(LayerLayout::Uniform(n)),
+            }
+        }
+    }
+}
+
+impl LayerLayout {
+    pub fn layer_count(&self) -> usize {
+        match self {
+            LayerLayout::Uniform(n) => *n,
+            LayerLayout::Sizes(sizes) => sizes.len(),
+        }
+    }
+
+    /// 各层在参数向量中的区间
+    pub fn ranges(&self, dim: usize) -> Result<Vec<Range<usize>>> {
+        let sizes = match self {
+            LayerLayout::Uniform(n) => {
+                let n = (*n).clamp(1, dim.max(1));
+                let base = dim / n;
+                (0..n)
+                    .map(|i| if i + 1 == n { dim - base * i } else { base })
+                    .collect()
+            }
+            LayerLayout::Sizes(sizes) => {
+                if sizes.iter().sum::<usize>() != dim {
+                    return Err(anyhow!("layer sizes {:?} do not sum to model dim {}", sizes, dim));
+                }
+                sizes.clone()
+            }
+        };
+        let mut start = 0;
+        Ok(sizes
+            .into_iter()
+            .map(|size| {
+                let range = start..start + size;
+                start += size;
+                range
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_ranges() {
+        let uniform: LayerLayout = "3".parse().unwrap();
+        assert_eq!(uniform.ranges(10).unwrap(), vec![0..3, 3..6, 6..10]);
+
+        let sized: LayerLayout = "2,6,2".parse().unwrap();
+        assert_eq!(sized.ranges(10).unwrap(), vec![0..2, 2..8, 8..10]);
+        assert!(sized.ranges(12).is_err());
+        assert!("0".parse::<LayerLayout>().is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! 密集快照的惰性推拉
+//!
+//! 发送方不再把整份快照 gossip 给所有 mesh 成员，而是缓存快照并只广播
+//! `IHave { hash, version, size }`；需要的节点回复带有自身 QUIC 地址的 `IWant`，
+//! 发送方再通过 QUIC 直连把签名快照只发给它。
+//! 接收方只接受自己请求过、且哈希与来源都匹配的直连快照。
+
+use crate::types::TensorSnapshot;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// 发送方保留的最近快照数
+const MAX_OFFERS: usize = 4;
+/// 请求超过该时长未到达视为失败，可以重新请求
+const WANT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 发送方：最近宣告过的快照
+#[derive(Default)]
+pub struct OfferCache {
+    offers: VecDeque<(String, TensorSnapshot)>,
+}
+
+impl OfferCache {
+    /// 缓存快照，返回其哈希
+    pub fn offer(&mut self, snapshot: TensorSnapshot) -> String {
+        let hash = snapshot.hash();
+        self.offers.retain(|(h, _)| *h != hash);
+        self.offers.push_back((hash.clone(), snapshot));
+        while self.offers.len() > MAX_OFFERS {
+            self.offers.pop_front();
+        }
+        hash
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.offers
+            .iter()
+            .map(|(_, s)| s.values.len() * std::mem::size_of::<f32>())
+            .sum()
+    }
+
+    /// 内存紧张时丢弃最旧的快照，返回释放的字节数
+    pub fn shed(&mut self, target: usize) -> usize {
+        let mut freed = 0;
+        while freed < target {
+            match self.offers.pop_front() {
+                Some((_, snapshot)) => freed += snapshot.values.len() * std::mem::size_of::<f32>(),
+                None => break,
+            }
+        }
+        freed
+    }
+
+    pub fn get(&self, hash: &str) -> Option<TensorSnapshot> {
+        self.offers
+            .iter()
+            .find(|(h, _)| h == hash)
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+}
+
+/// 接收方：已发出、尚未到达的请求
+#[derive(Default)]
+pub struct WantTracker {
+    pending: HashMap<String, (String, Instant)>,
+}
+
+impl WantTracker {
+    /// 记录对 `peer` 宣告的快照的请求；已在等待中的快照返回 false
+    pub fn want(&mut self, hash: &str, peer: &str) -> bool {
+        self.pending.retain(|_, (_, at)| at.elapsed() < WANT_TIMEOUT);
+        if self.pending.contains_key(hash) {
+            return false;
+        }
+        self.pending
+            .insert(hash.to_string(), (peer.to_string(), Instant::now()));
+        true
+    }
+
+    /// 直连到达的快照是否是向该节点请求过的，匹配则结清
+    pub fn fulfil(&mut self, hash: &str, peer: &str) -> bool {
+        match self.pending.get(hash) {
+            Some((from, at)) if from == peer && at.elapsed() < WANT_TIMEOUT => {
+                self.pending.remove(hash);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_and_want_round_trip() {
+        let mut cache = OfferCache::default();
+        let hashes: Vec<_> = (0..6)
+            .map(|v| cache.offer(TensorSnapshot::new(vec![v as f32; 4], v)))
+            .collect();
+        // 只保留最近的快照
+        assert!(cache.get(&hashes[0]).is_none());
+        assert_eq!(cache.get(&hashes[5]).unwrap().version, 5);
+
+        let mut wants = WantTracker::default();
+        assert!(wants.want(&hashes[5], "provider"));
+        assert!(!wants.want(&hashes[5], "provider"));
+        // 未请求过的快照或来源不符的快照不被接受
+        assert!(!wants.fulfil(&hashes[4], "provider"));
+        assert!(!wants.fulfil(&hashes[5], "someone-else"));
+        assert!(wants.fulfil(&hashes[5], "provider"));
+        assert!(!wants.fulfil(&hashes[5], "provider"));
+    }
+}
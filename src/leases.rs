@@ -0,0 +1,123 @@
+//! 训练租约
+//!
+//! 任务描述中列出 `lease_issuers` 时，开放 gossip 之上多一层成员控制：
+//! 租约发放者（聚合者）为每个纪元向训练者签发 `GgsMessage::LeaseGrant`，
+//! 指定训练者负责的数据分片与到期时间。训练者按租约中的分片训练，
+//! 各节点只接受持有有效租约的训练者发来的稀疏更新。
+//! 租约随签名消息发出，发放者身份由消息签名保证。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 数据集不按纪元轮换时租约的有效期，发放者在到期前续发
+pub const DEFAULT_LEASE_TERM: Duration = Duration::from_secs(600);
+
+/// 发放者授予训练者的租约
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrainingLease {
+    pub session: String,
+    pub trainer: String,
+    pub epoch: u64,
+    /// 训练者在该纪元负责的数据分片
+    pub shards: Vec<u32>,
+    /// 到期时间（Unix 毫秒）
+    pub expires_at_ms: u64,
+}
+
+impl TrainingLease {
+    pub fn is_active(&self, now_ms: u64) -> bool {
+        now_ms < self.expires_at_ms
+    }
+}
+
+/// 租约到期时间：按纪元轮换时为纪元结束，否则为固定有效期之后
+pub fn lease_expiry(epoch: u64, epoch_secs: u64, now_ms: u64) -> u64 {
+    if epoch_secs == 0 {
+        now_ms + DEFAULT_LEASE_TERM.as_millis() as u64
+    } else {
+        (epoch + 1) * epoch_secs * 1000
+    }
+}
+
+/// 各会话中训练者持有的租约
+#[derive(Default)]
+pub struct LeaseBook {
+    /// 会话 -> 训练者 -> 租约
+    leases: HashMap<String, HashMap<String, TrainingLease>>,
+}
+
+impl LeaseBook {
+    /// 记录 `issuer` 签发的租约，发放者不在任务的发放者名单中或租约已过期时返回 false。
+    /// 同一训练者只保留纪元最新的租约
+    pub fn grant(&mut self, issuer: &str, issuers: &[String], lease: TrainingLease, now_ms: u64) -> bool {
+        if !issuers.iter().any(|known| known == issuer) || !lease.is_active(now_ms) {
+            return false;
+        }
+        let held = self.leases.entry(lease.session.clone()).or_default();
+        if held
+            .get(&lease.trainer)
+            .is_some_and(|current| current.epoch > lease.epoch && current.is_active(now_ms))
+        {
+            return false;
+        }
+        held.insert(lease.trainer.clone(), lease);
+        true
+    }
+
+    /// 训练者当前持有的有效租约
+    pub fn active(&self, session: &str, trainer: &str, now_ms: u64) -> Option<&TrainingLease> {
+        self.leases
+            .get(session)?
+            .get(trainer)
+            .filter(|lease| lease.is_active(now_ms))
+    }
+
+    pub fn prune(&mut self, now_ms: u64) {
+        for held in self.leases.values_mut() {
+            held.retain(|_, lease| lease.is_active(now_ms));
+        }
+        self.leases.retain(|_, held| !held.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease(trainer: &str, epoch: u64, expires_at_ms: u64) -> TrainingLease {
+        TrainingLease {
+            session: "vision".into(),
+            trainer: trainer.into(),
+            epoch,
+            shards: vec![epoch as u32],
+            expires_at_ms,
+        }
+    }
+
+    #[test]
+    fn test_only_listed_issuers_grant_time_bounded_leases() {
+        let issuers = vec!["aggregator".to_string()];
+        let mut book = LeaseBook::default();
+
+        assert!(!book.grant("mallory", &issuers, lease("alice", 1, 2_000), 0));
+        assert!(book.active("vision", "alice", 0).is_none());
+
+        assert!(book.grant("aggregator", &issuers, lease("alice", 1, 2_000), 0));
+        assert_eq!(book.active("vision", "alice", 1_999).unwrap().shards, vec![1]);
+        // 纪元结束后租约失效
+        assert!(book.active("vision", "alice", 2_000).is_none());
+        assert!(book.active("other", "alice", 0).is_none());
+
+        // 新纪元的租约取代旧的，旧纪元的租约不能回退
+        assert!(book.grant("aggregator", &issuers, lease("alice", 2, 3_000), 1_000));
+        assert!(!book.grant("aggregator", &issuers, lease("alice", 1, 2_000), 1_000));
+        assert_eq!(book.active("vision", "alice", 1_000).unwrap().epoch, 2);
+
+        // 已过期的租约不接受
+        assert!(!book.grant("aggregator", &issuers, lease("bob", 2, 3_000), 3_000));
+        book.prune(3_000);
+        assert!(book.leases.is_empty());
+        assert_eq!(lease_expiry(4, 60, 0), 300_000);
+    }
+}
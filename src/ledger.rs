@@ -0,0 +1,168 @@
+//! 质押账本的本地持久化
+//!
+//! 共识引擎的账本只在内存中，心跳超时的节点会被清出；本地 SQLite 按节点保存
+//! `stake_eth`、`stake_sol` 与声誉，节点离线后（包括本节点重启后）重新出现时从库中恢复，
+//! 而不是按新节点重新计分。启动时不整体载入，已清出的节点不会被带回内存参与质押加权。超过保留期未更新的条目在定期压缩时删除并回收空间。
+
+use crate::consensus::LedgerEntry;
+use anyhow::Result;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const DB_FILE: &str = "ledger.sqlite";
+
+#[derive(Clone, Debug)]
+pub struct LedgerStoreConfig {
+    /// 数据库路径，未设置时使用状态目录下的 `ledger.sqlite`
+    pub path: Option<PathBuf>,
+    /// 条目最后一次更新后的保留时长
+    pub retention: Duration,
+    /// 两次压缩之间的间隔
+    pub compact_interval: Duration,
+}
+
+impl Default for LedgerStoreConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            retention: Duration::from_secs(90 * 24 * 3600),
+            compact_interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+pub struct LedgerStore {
+    config: LedgerStoreConfig,
+    conn: Mutex<Connection>,
+}
+
+impl LedgerStore {
+    pub fn open(path: &Path, config: LedgerStoreConfig) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?, config)
+    }
+
+    fn with_connection(conn: Connection, config: LedgerStoreConfig) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS stakes (
+                peer TEXT PRIMARY KEY,
+                stake_eth REAL NOT NULL,
+                stake_sol REAL NOT NULL,
+                reputation REAL NOT NULL,
+                updated_at_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS stakes_updated ON stakes (updated_at_ms);",
+        )?;
+        Ok(Self {
+            config,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn compact_interval(&self) -> Duration {
+        self.config.compact_interval
+    }
+
+    /// 在一个事务中写入（覆盖）给定条目
+    pub fn save(&self, entries: &[LedgerEntry], now_ms: u64) -> Result<()> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO stakes (peer, stake_eth, stake_sol, reputation, updated_at_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (peer) DO UPDATE SET
+                    stake_eth = excluded.stake_eth,
+                    stake_sol = excluded.stake_sol,
+                    reputation = excluded.reputation,
+                    updated_at_ms = excluded.updated_at_ms",
+            )?;
+            for entry in entries {
+                stmt.execute(params![
+                    entry.peer,
+                    entry.stake_eth,
+                    entry.stake_sol,
+                    entry.reputation,
+                    now_ms as i64,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 库中的条目数
+    pub fn count(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .query_row("SELECT COUNT(*) FROM stakes", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn get(&self, peer: &str) -> Result<Option<LedgerEntry>> {
+        Ok(self
+            .conn
+            .lock()
+            .query_row(
+                "SELECT peer, stake_eth, stake_sol, reputation FROM stakes WHERE peer = ?1",
+                params![peer],
+                row_to_entry,
+            )
+            .optional()?)
+    }
+
+    /// 删除超过保留期未更新的条目并回收空间，返回删除条数
+    pub fn compact(&self, now_ms: u64) -> Result<usize> {
+        let cutoff = now_ms.saturating_sub(self.config.retention.as_millis() as u64);
+        let conn = self.conn.lock();
+        let removed = conn.execute("DELETE FROM stakes WHERE updated_at_ms < ?1", params![cutoff as i64])?;
+        conn.execute_batch("VACUUM")?;
+        Ok(removed)
+    }
+}
+
+fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<LedgerEntry> {
+    Ok(LedgerEntry {
+        peer: row.get(0)?,
+        stake_eth: row.get(1)?,
+        stake_sol: row.get(2)?,
+        reputation: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upserts_restores_and_compacts() {
+        let store = LedgerStore::with_connection(
+            Connection::open_in_memory().unwrap(),
+            LedgerStoreConfig {
+                retention: Duration::from_secs(60),
+                ..LedgerStoreConfig::default()
+            },
+        )
+        .unwrap();
+        let entry = |peer: &str, reputation: f64| LedgerEntry {
+            peer: peer.into(),
+            stake_eth: 2.0,
+            stake_sol: 0.5,
+            reputation,
+        };
+        store.save(&[entry("old", 1.0), entry("busy", 1.0)], 1_000).unwrap();
+        store.save(&[entry("busy", 3.5)], 100_000).unwrap();
+
+        assert_eq!(store.count().unwrap(), 2);
+        let busy = store.get("busy").unwrap().unwrap();
+        assert_eq!((busy.stake_eth, busy.reputation), (2.0, 3.5));
+        assert!(store.get("unknown").unwrap().is_none());
+
+        // 只有超过保留期未更新的条目被删除
+        assert_eq!(store.compact(100_000).unwrap(), 1);
+        assert!(store.get("old").unwrap().is_none());
+        assert!(store.get("busy").unwrap().is_some());
+    }
+}
@@ -1,34 +1,243 @@
+mod admin;
+mod admission;
+mod aggregation;
+mod applyqueue;
+mod analytics;
+mod attestation;
+mod audit;
+mod beacon;
+mod causal;
+mod certs;
+mod checkpoint;
+mod chunks;
+mod cli;
+mod clustering;
+mod codec;
+mod clock;
 mod comms;
+mod config;
+mod connmgr;
 mod consensus;
 mod crypto;
+mod dataset;
 mod device;
+mod erasure;
+mod errors;
+mod evaluation;
+mod failures;
 #[cfg(feature = "ffi")]
 mod ffi;
+mod gating;
+mod geofence;
+mod hyperparams;
 mod inference;
+mod integrity;
+mod jobs;
+mod latency;
+mod leases;
+mod layers;
+mod lazy;
+mod ledger;
+mod memory;
+mod monitor;
+mod names;
+mod netaddr;
+mod outbox;
+mod persistence;
+mod pipeline;
+mod power;
+mod provenance;
+mod proxy;
+mod pubqueue;
+mod readiness;
+mod relay;
+mod reload;
+mod retransmit;
+mod rewards;
+mod role;
+mod scheduler;
+mod session;
+mod sparsity;
+mod stake;
+mod standby;
 mod stats;
+mod supervisor;
+mod sync;
 mod topology;
+mod trace;
+mod transport;
+mod trust;
 mod types;
+mod vectors;
+mod watchdog;
+mod windows;
+mod workload;
 
-use crate::comms::{CommsConfig, CommsHandle, OutEvent};
-use crate::consensus::{ConsensusConfig, ConsensusEngine, SignedGossip};
-use crate::crypto::{CryptoConfig, CryptoSuite};
+use crate::admin::{AdminServer, AdminState};
+use crate::admission::MAX_POW_DIFFICULTY;
+use crate::aggregation::{AggregationRule, UpdateAggregator};
+use crate::applyqueue::{ApplyQueue, ApplyQueueConfig};
+use crate::analytics::{l2_norm, AnalyticsConfig, AnalyticsDb, UpdateKind, UpdateRecord};
+use crate::attestation::ReputationAttestation;
+use crate::audit::Auditor;
+use crate::beacon::{
+    derive_rng, elect, share_bytes, BeaconChain, BeaconConfig, BeaconRound, BeaconShare,
+};
+use crate::causal::{BackfillLimiter, Observation, SentLog, VersionVector};
+use crate::checkpoint::CheckpointConfig;
+use crate::chunks::ModelChunkHashes;
+use crate::clustering::{ClusterBoard, ClusterConfig};
+use crate::clock::{unix_now_millis, ClockConfig, ClockSkewTracker};
+use crate::codec::WireCodec;
+use crate::comms::{configured_peer_id, CommsConfig, CommsHandle, RendezvousConfig, SendPolicy, IDENTITY_FILE};
+use crate::config::ConfigFile;
+use crate::consensus::{ConsensusConfig, ConsensusEngine, LedgerEntry, Misbehavior, SignedGossip};
+use crate::crypto::{CryptoConfig, CryptoSuite, Keystore, KEYSTORE_PASSPHRASE_ENV};
+use crate::dataset::sample_count;
 use crate::device::{DeviceCapabilities, DeviceManager};
-use crate::inference::{InferenceConfig, InferenceEngine};
+use crate::erasure::{ErasureConfig, ShareAssembler, SnapshotShare};
+use crate::evaluation::{assign_eval_shards, evaluate_shard, EvalBoard, EvalConfig};
+use crate::gating::{TokenGate, TokenGateConfig};
+use crate::geofence::GeoFenceConfig;
+use crate::hyperparams::{HyperParamBoard, HyperParamConfig, HyperParamProposal, VoteRule};
+use crate::inference::{InferenceConfig, BASE_MERGE_COEFFICIENT};
+use crate::integrity::SnapshotVerifier;
+use crate::jobs::{JobConfig, JobRegistry, JobSpec};
+use crate::leases::{lease_expiry, LeaseBook, TrainingLease};
+use crate::latency::LatencyPath;
+use crate::lazy::{OfferCache, WantTracker};
+use crate::ledger::{LedgerStore, LedgerStoreConfig};
+use crate::memory::{MemoryBudgetConfig, MemoryUsage};
+use crate::monitor::PeerMonitor;
+use crate::names::{NameConfig, NameResolver};
+use crate::outbox::{OfflineConfig, OfflineQueue};
+use crate::persistence::{unix_now_secs, PersistedState, StateStore};
+use crate::pipeline::{Channel, Inbound, Pipeline, PipelineConfig, Verdict};
+use crate::power::{PowerSaver, PowerSaverConfig};
+use crate::readiness::{ReadinessConfig, ReadinessGate, ReadinessState};
+use crate::reload::{ConfigReloader, RuntimeConfig};
+use crate::rewards::{RewardConfig, RewardSubmitter};
+use crate::role::NodeRole;
+use crate::scheduler::{jittered, IntervalScheduler, PeriodicTask, ScheduleConfig};
+use crate::session::{default_session_id, Session, SessionConfig, DEFAULT_SESSION};
+use crate::sparsity::{SparsityConfig, SparsityController};
+use crate::retransmit::{AckTracker, Retransmit, RetransmitConfig};
+use crate::stake::{StakeProof, StakeProofConfig, StakeProver, StakeVerifier};
+use crate::standby::StandbyConfig;
 use crate::stats::TrainingStatsManager;
-use crate::topology::{TopologyConfig, TopologySelector};
-use crate::types::{GeoPoint, GgsMessage};
-use anyhow::Result;
-use futures::StreamExt;
-use libp2p::swarm::SwarmEvent;
+use crate::supervisor::{panic_reason, RestartPolicy, Supervisor, SupervisorConfig};
+use crate::sync::{InboundRequest, SyncError, SyncRequest, SyncResponse};
+use crate::topology::TopologyConfig;
+use crate::trace::{read_trace, TraceChannel, TraceEntry, TraceRecorder};
+use crate::transport::memory::MemoryNetwork;
+use crate::transport::{NodeBackend, TransportEvent};
+use crate::trust::{TrustConfig, TrustScore, TrustTracker};
+use crate::types::{GeoPoint, GgsMessage, PeerMeta, SparseUpdate, TensorSnapshot};
+use crate::watchdog::{Beat, Watchdog, WatchdogConfig};
+use crate::windows::TrainingSchedule;
+use anyhow::{anyhow, Result};
+use futures::FutureExt;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use tokio::time::{interval, Duration};
+use tokio::time::{Duration, Instant};
+
+/// 身份迁移请求的广播次数
+const MIGRATION_ANNOUNCEMENTS: u32 = 5;
+/// 退出时发送 gossip 退订与 QUIC 关闭帧的宽限时间
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+/// 邻居心跳宣告的模型版本领先本地至少这么多时，直接向其索取快照
+const SNAPSHOT_SYNC_GAP: u64 = 50;
+/// 同一会话两次索取快照的最短间隔
+const SNAPSHOT_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+/// 版本领先是自己宣告的，至少这么多个节点在一个索取间隔内都宣告领先才索取快照
+const SNAPSHOT_SYNC_CORROBORATION: usize = 3;
 
 struct AppConfig {
+    role: NodeRole,
+    /// 聚合者合并训练者更新时使用的规则
+    aggregation: AggregationRule,
+    /// 已验证更新进入模型前的合并队列
+    apply_queue: ApplyQueueConfig,
+    /// 本节点位置，None 时随机生成
+    position: Option<GeoPoint>,
+    geofence: GeoFenceConfig,
+    /// 代币门槛：只应用达到持有门槛的节点的更新
+    token_gate: TokenGateConfig,
+    /// 链上奖励提交
+    rewards: RewardConfig,
+    /// 心跳中的链上质押证明
+    stake_proof: StakeProofConfig,
+    /// 心跳附带的模型分块哈希块数，0 表示不附带
+    heartbeat_chunks: usize,
+    /// 稀疏更新 top-k 的自动调节
+    sparsity: SparsityConfig,
+    /// 训练时间窗口，窗口外只做中继
+    training_schedule: TrainingSchedule,
+    power_saver: PowerSaverConfig,
+    /// 启动时即开启低功耗模式
+    power_saver_enabled: bool,
+    /// 轻量模式：不加载模型参数，只跟踪心跳、哈希、拓扑与共识状态
+    light: bool,
+    /// 默认会话的推理配置
     inference: InferenceConfig,
+    /// 默认会话之外的训练会话
+    extra_sessions: Vec<SessionConfig>,
     comms: CommsConfig,
     topology: TopologyConfig,
     crypto: CryptoConfig,
     consensus: ConsensusConfig,
+    clock: ClockConfig,
+    /// 入站消息管道的去重与限速参数
+    pipeline: PipelineConfig,
+    schedule: ScheduleConfig,
+    readiness: ReadinessConfig,
+    /// 模型检查点的写入间隔与保留个数（目录在推理配置中）
+    checkpoint: CheckpointConfig,
+    /// 嵌入空间的节点聚类
+    clustering: ClusterConfig,
+    /// `--config` 文件，运行中修改后热加载
+    config_file: Option<std::path::PathBuf>,
+    admin_bind: Option<std::net::SocketAddr>,
+    supervisor: SupervisorConfig,
+    /// 内部循环停滞检测
+    watchdog: WatchdogConfig,
+    memory: MemoryBudgetConfig,
+    /// 热备状态复制
+    standby: StandbyConfig,
+    /// 直连稀疏更新的确认重传
+    retransmit: RetransmitConfig,
+    jobs: JobConfig,
+    /// 启动时导入的声誉迁移证明
+    reputation_import: Option<std::path::PathBuf>,
+    /// ENS / SNS 名称解析端点
+    names: NameConfig,
+    /// 对外广播的节点元数据
+    meta: PeerMeta,
+    /// 断网期间的更新队列
+    offline: OfflineConfig,
+    analytics: AnalyticsConfig,
+    /// 分析库路径，None 时放在状态目录下（未配置状态目录则不记录）
+    analytics_db: Option<std::path::PathBuf>,
+    /// 质押账本的持久化
+    ledger: LedgerStoreConfig,
+    trust: TrustConfig,
+    /// 分布式留出集评估
+    evaluation: EvalConfig,
+    /// 超参数共识
+    hyperparams: HyperParamConfig,
+    /// 委员会选举与分片分配使用的随机信标
+    beacon: BeaconConfig,
+    /// 大快照的纠删码分发
+    erasure: ErasureConfig,
+    /// 录制所有入站 gossip 的文件
+    trace_record: Option<std::path::PathBuf>,
+    /// 重放模式：不连接网络，只处理录制文件中的消息
+    replaying: bool,
+    /// 状态目录，None 表示不持久化
+    state_dir: Option<std::path::PathBuf>,
     device_manager: DeviceManager,
 }
 
@@ -42,6 +251,10 @@ impl AppConfig {
         let inference = InferenceConfig {
             model_dim,
             model_path: None,
+            seed: None,
+            layers: Default::default(),
+            checkpoint_dir: None,
+            param_bound: crate::inference::DEFAULT_PARAM_BOUND,
         };
 
         // 根据网络类型调整带宽预算
@@ -50,15 +263,32 @@ impl AppConfig {
             topic: "ggs-training".into(),
             listen_addr: None,
             quic_bind: Some(std::net::SocketAddr::new(
-                std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+                std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
                 9234,
             )),
+            dual_stack: true,
             quic_bootstrap: Vec::new(),
+            quic_advertise: None,
+            external_addrs: Vec::new(),
+            rendezvous: Default::default(),
+            dht: Default::default(),
+            nat: Default::default(),
+            relay: Default::default(),
             bandwidth: crate::comms::BandwidthBudgetConfig {
                 sparse_per_window: (12.0 * bandwidth_factor) as u32,
                 dense_bytes_per_window: ((256 * 1024) as f32 * bandwidth_factor) as usize,
                 window_secs: 60,
             },
+            identity_path: None,
+            codecs: WireCodec::ALL.to_vec(),
+            gossip_codec: WireCodec::Json,
+            bootstrap_peers: Vec::new(),
+            transport: Default::default(),
+            proxy: Default::default(),
+            connections: Default::default(),
+            send_policy: Default::default(),
+            publish_queue: Default::default(),
+            gossip: Default::default(),
         };
 
         // 根据设备能力调整拓扑配置
@@ -68,14 +298,66 @@ impl AppConfig {
             min_score: 0.15,
             geo_scale_km: 500.0,
             peer_stale_secs: 120,
+            cross_cluster_fraction: 0.3,
+            embedding_weight: 0.6,
+            geo_weight: 0.4,
+            latency_weight: 0.0,
+            latency_scale_ms: 200.0,
+            silence_grace_secs: 30,
+            decay_half_life_secs: 30,
+            fanout: 0,
         };
 
         Self {
+            role: NodeRole::default(),
+            aggregation: AggregationRule::default(),
+            apply_queue: ApplyQueueConfig::default(),
+            position: None,
+            geofence: GeoFenceConfig::default(),
+            token_gate: TokenGateConfig::default(),
+            rewards: RewardConfig::default(),
+            stake_proof: StakeProofConfig::default(),
+            heartbeat_chunks: 0,
+            sparsity: SparsityConfig::default(),
+            training_schedule: TrainingSchedule::default(),
+            power_saver: PowerSaverConfig::default(),
+            power_saver_enabled: false,
+            light: false,
             inference,
+            extra_sessions: Vec::new(),
             comms,
             topology,
             crypto: CryptoConfig::default(),
             consensus: ConsensusConfig::default(),
+            clock: ClockConfig::default(),
+            pipeline: PipelineConfig::default(),
+            schedule: ScheduleConfig::default(),
+            readiness: ReadinessConfig::default(),
+            checkpoint: CheckpointConfig::default(),
+            clustering: ClusterConfig::default(),
+            config_file: None,
+            admin_bind: None,
+            supervisor: SupervisorConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            memory: MemoryBudgetConfig::default(),
+            standby: StandbyConfig::default(),
+            retransmit: RetransmitConfig::default(),
+            jobs: JobConfig::default(),
+            reputation_import: None,
+            names: NameConfig::default(),
+            meta: PeerMeta::default(),
+            offline: OfflineConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            analytics_db: None,
+            ledger: LedgerStoreConfig::default(),
+            trust: TrustConfig::default(),
+            evaluation: EvalConfig::default(),
+            hyperparams: HyperParamConfig::default(),
+            beacon: BeaconConfig::default(),
+            erasure: ErasureConfig::default(),
+            trace_record: None,
+            replaying: false,
+            state_dir: None,
             device_manager: DeviceManager::with_capabilities(capabilities),
         }
     }
@@ -89,46 +371,420 @@ impl Default for AppConfig {
     }
 }
 
+/// 本节点作为发放者最近一次为某会话签发的租约
+struct IssuedLeases {
+    epoch: u64,
+    /// 训练者 -> 分片
+    shards: HashMap<String, Vec<u32>>,
+    granted_at: Instant,
+}
+
 struct Node {
-    comms: CommsHandle,
-    inference: InferenceEngine,
-    topology: TopologySelector,
-    consensus: ConsensusEngine,
+    /// 消息传输后端，重放时为进程内模拟网络
+    comms: Box<dyn NodeBackend>,
+    /// 训练会话，下标 0 固定为默认会话
+    sessions: Vec<Arc<Session>>,
+    consensus: Arc<ConsensusEngine>,
+    /// 入站消息在应用前依次经过的处理阶段
+    pipeline: Pipeline,
+    role: NodeRole,
+    light: bool,
+    monitor: Arc<PeerMonitor>,
+    aggregation: AggregationRule,
+    /// 等待合并的 (稀疏更新, 信任权重)
+    /// 排队的稀疏更新：(更新, 信任权重, 合并后是否确认)
+    apply_queue: ApplyQueue<(SparseUpdate, f32, bool)>,
+    position: GeoPoint,
+    geofence: GeoFenceConfig,
+    token_gate: Arc<TokenGate>,
+    /// 按 ETH 地址累计被接受的贡献并定期提交到奖励合约
+    rewards: Arc<RewardSubmitter>,
+    /// 本节点的链上余额证明，None 表示心跳不附带
+    stake_prover: Option<Arc<StakeProver>>,
+    stake_verifier: Arc<StakeVerifier>,
+    heartbeat_chunks: usize,
+    /// 已接受更新的分析库
+    analytics: Option<Arc<AnalyticsDb>>,
+    /// 持久化的质押账本
+    ledger_store: Option<Arc<LedgerStore>>,
+    last_ledger_compaction: Instant,
+    /// 统一信任分：邻居排序、合并权重与更新配额
+    trust: TrustTracker,
+    evaluation: EvalConfig,
+    /// 会话 id -> 收集到的分布式评估结果
+    eval_boards: HashMap<String, EvalBoard>,
+    hyperparams: HyperParamConfig,
+    /// 会话 id -> 待生效的超参数提议与当前值
+    hyperparam_boards: HashMap<String, HyperParamBoard>,
+    beacon: BeaconConfig,
+    /// 会话 id -> 随机信标链
+    beacons: HashMap<String, BeaconChain>,
+    /// 会话 id -> 本节点最近一次签名的 (信标纪元, 检查点)
+    beacon_shares: HashMap<String, (u64, String)>,
+    /// 纪元调度对快照间隔的放大倍数
+    epoch_snapshot_factor: f32,
+    /// 会话 id -> 本节点已发出的稀疏更新序号与补发缓存
+    sent_updates: HashMap<String, SentLog>,
+    /// 按请求方限制补发频率
+    backfill_limiter: BackfillLimiter,
+    /// 会话 id -> 各发送者已见的更新序号
+    version_vectors: HashMap<String, VersionVector>,
+    /// 密集快照融合前的完整性校验
+    snapshot_verifier: SnapshotVerifier,
+    /// 会话 id -> 已宣告、可按需直连发送的密集快照
+    dense_offers: HashMap<String, OfferCache>,
+    /// 已请求、等待直连到达的快照
+    dense_wants: WantTracker,
+    /// 主循环与周期任务的进展，由看门狗监视
+    swarm_beat: Beat,
+    training_beat: Beat,
+    memory: MemoryBudgetConfig,
+    /// 热备节点的 QUIC 地址
+    standby_target: Option<std::net::SocketAddr>,
+    erasure: ErasureConfig,
+    /// 会话 id -> 纠删码分片的组装状态
+    share_assemblers: HashMap<String, ShareAssembler>,
+    /// 入站消息录制
+    recorder: Option<TraceRecorder>,
+    /// 重放模式下不发送任何消息
+    replaying: bool,
+    /// 聚合者：会话 id -> 本窗口内缓存的训练者更新
+    aggregators: HashMap<String, UpdateAggregator>,
+    /// 训练者：会话 id -> (聚合者 peer, QUIC 地址, 最后心跳时间)
+    known_aggregators: HashMap<String, (String, std::net::SocketAddr, Instant)>,
+    /// 训练者：直连聚合者、尚未确认的稀疏更新
+    retransmit: AckTracker,
+    /// 节点 -> (心跳宣告的 QUIC 地址（首选在前）, 最后心跳时间)
+    quic_peers: HashMap<String, (Vec<std::net::SocketAddr>, Instant)>,
+    /// 会话 id -> 最近一次向邻居索取快照的时间
+    snapshot_syncs: HashMap<String, Instant>,
+    /// 会话 id -> 节点 -> 最近一次宣告版本领先本地的时间
+    snapshot_leads: HashMap<String, HashMap<String, Instant>>,
+    /// 已索取质押账本的邻居，请求失败或被拒后清空以便换一个邻居重试
+    ledger_source: Option<String>,
+    ledger_synced: bool,
+    sparsity: SparsityConfig,
+    training_schedule: TrainingSchedule,
+    /// 低功耗模式，可由管理接口在运行时切换
+    power: Arc<PowerSaver>,
+    /// 上一次检查时是否处于训练窗口内
+    training_window_open: bool,
+    /// 会话 id -> 本节点负责的数据集分片区间
+    shard_assignments: HashMap<String, Vec<u32>>,
+    /// 各训练者持有的租约
+    leases: LeaseBook,
+    /// 会话 id -> 本节点作为发放者最近一次签发的租约
+    lease_grants: HashMap<String, IssuedLeases>,
+    /// 会话 id -> 稀疏度控制器
+    sparsity_controllers: HashMap<String, SparsityController>,
     device_manager: DeviceManager,
+    names: Arc<NameResolver>,
     stats: Arc<TrainingStatsManager>,
+    scheduler: IntervalScheduler,
+    readiness: Arc<ReadinessGate>,
+    store: Option<StateStore>,
+    checkpoint: CheckpointConfig,
+    last_checkpoint: Instant,
+    clustering: ClusterConfig,
+    last_clustering: Instant,
+    /// 会话 id -> 本地聚类结果与收到的簇视图
+    cluster_boards: HashMap<String, ClusterBoard>,
+    /// 收到退出信号时通知主循环保存检查点后退出
+    shutdown: Arc<tokio::sync::Notify>,
+    /// 配置文件热加载，未指定 `--config` 时为 None
+    reloader: Option<ConfigReloader>,
+    /// 收到 SIGHUP 时通知主循环立即热加载
+    reload: Arc<tokio::sync::Notify>,
+    /// 断网期间积压的本地更新
+    offline: OfflineQueue,
+    /// 上一次检查时是否有 gossip 节点
+    was_connected: bool,
+    /// 从证明导入的旧 PeerId，剩余广播次数
+    pending_migration: Option<(String, u32)>,
+    /// 本节点的密钥宣告（含准入证明），不要求工作量证明时为 None
+    key_announce: Option<GgsMessage>,
+    meta: PeerMeta,
+    jobs: Arc<JobRegistry>,
+    job_config: JobConfig,
+    /// 自动加入任务时创建会话所需的模板
+    session_template: (InferenceConfig, GeoPoint, TopologyConfig),
     tick_counter: u64,
+    heartbeat_counter: u64,
 }
 
 impl Node {
-    async fn new(config: AppConfig) -> Result<Self> {
+    async fn new(mut config: AppConfig) -> Result<Self> {
         let mut rng = rand::thread_rng();
-        let geo = GeoPoint::random(&mut rng);
+        let geo = config
+            .position
+            .clone()
+            .unwrap_or_else(|| GeoPoint::random(&mut rng));
         let capabilities = config.device_manager.get();
+
+        // 热加载以按角色调整之前的配置为基准，加载后再按角色调整
+        let reloader = config.config_file.clone().map(|path| {
+            ConfigReloader::new(
+                path,
+                RuntimeConfig {
+                    bandwidth: config.comms.bandwidth.clone(),
+                    topology: config.topology.clone(),
+                    schedule: config.schedule.clone(),
+                },
+            )
+        });
+
+        // 角色决定带宽预算、拓扑偏好与快照频率
+        let role = config.role;
+        role.adjust_topology(&mut config.topology);
+        config.comms.bandwidth = role.adjust_bandwidth(&config.comms.bandwidth);
+        config.schedule.snapshot_interval = config
+            .schedule
+            .snapshot_interval
+            .mul_f32(role.snapshot_interval_factor());
         
-        let inference = InferenceEngine::new(config.inference)?;
-        let comms = CommsHandle::new(config.comms).await?;
-        
+        let default_topic = config.comms.topic.clone();
+        let session_template = (config.inference.clone(), geo.clone(), config.topology.clone());
+
+        // 本节点发布的任务同时作为本地会话参与训练
+        for job in &config.jobs.announce {
+            if !config.extra_sessions.iter().any(|s| s.id == job.job_id) {
+                config
+                    .extra_sessions
+                    .push(job.session_config(&config.inference));
+            }
+        }
+        if !config.light {
+            let model_bytes = memory::model_bytes(config.inference.model_dim)
+                + config
+                    .extra_sessions
+                    .iter()
+                    .map(|s| memory::model_bytes(s.inference.model_dim))
+                    .sum::<usize>();
+            config.memory.check_models_fit(model_bytes)?;
+        }
+        let mut comms: Box<dyn NodeBackend> = if config.replaying {
+            let peer_id = configured_peer_id(&config.comms)?;
+            Box::new(MemoryNetwork::default().join(&peer_id.to_string()))
+        } else {
+            Box::new(CommsHandle::new(config.comms).await?)
+        };
+
+        // 默认会话 + 额外会话，带宽预算按比例切分
+        let default_share = 1.0 / (1 + config.extra_sessions.len()) as f32;
+        comms.register_session(DEFAULT_SESSION, &default_topic, default_share)?;
+        let light = config.light;
+        let topology = config.topology.clone();
+        let make_session = |id: String, inference: InferenceConfig| -> Result<Arc<Session>> {
+            let session = if light {
+                Session::light(id, geo.clone(), topology.clone())
+            } else {
+                Session::new(id, inference, geo.clone(), topology.clone())?
+            };
+            Ok(Arc::new(session))
+        };
+        let mut sessions = vec![make_session(default_session_id(), config.inference)?];
+        for extra in config.extra_sessions {
+            comms.register_session(
+                &extra.id,
+                &extra.topic,
+                extra.bandwidth_share.unwrap_or(default_share),
+            )?;
+            println!("[会话] 加入训练会话 {} (主题 {})", extra.id, extra.topic);
+            sessions.push(make_session(extra.id, extra.inference)?);
+        }
+        let inference = sessions[0].inference.as_ref();
+
         // 设置初始网络类型
         comms.update_network_type(capabilities.network_type);
         
-        let topology = TopologySelector::new(geo.clone(), config.topology);
         let crypto_suite = Arc::new(CryptoSuite::new(config.crypto)?);
-        let consensus = ConsensusEngine::new(crypto_suite.clone(), config.consensus);
-        
+        let consensus = Arc::new(ConsensusEngine::new(crypto_suite.clone(), config.consensus));
+        let key_announce = if consensus.pow_difficulty() > 0 {
+            let sender = comms.local_peer_id();
+            let pow_nonce = consensus.solve_admission(&sender).ok_or_else(|| {
+                anyhow!("无法在搜索上限内找到难度 {} 的工作量证明", consensus.pow_difficulty())
+            })?;
+            println!(
+                "[准入] 已计算工作量证明 (难度 {} 比特, nonce {})",
+                consensus.pow_difficulty(),
+                pow_nonce
+            );
+            Some(GgsMessage::KeyAnnounce {
+                eth_address: crypto_suite.eth_address(),
+                sol_pubkey: crypto_suite.sol_address(),
+                pow_nonce,
+                sender,
+            })
+        } else {
+            None
+        };
+
+        let mut meta = config.meta;
+        if meta.hardware.is_empty() {
+            meta.hardware = format!(
+                "{} 核 / {}MB / GPU: {}",
+                capabilities.cpu_cores,
+                capabilities.max_memory_mb,
+                if capabilities.has_gpu { "有" } else { "无" }
+            );
+        }
+        meta.validate()?;
+
+        let rewards = Arc::new(RewardSubmitter::new(
+            config.rewards,
+            crypto_suite.clone(),
+            config.state_dir.clone(),
+        ));
+        let stake_prover = match (&config.names.eth_rpc, config.stake_proof.publish) {
+            (Some(rpc), true) => Some(Arc::new(StakeProver::new(
+                config.stake_proof.clone(),
+                rpc.clone(),
+                crypto_suite.clone(),
+            ))),
+            _ => None,
+        };
+        let stake_verifier = Arc::new(StakeVerifier::new(
+            config.stake_proof,
+            config.names.eth_rpc.clone(),
+        ));
+
+        let analytics_path = config
+            .analytics_db
+            .or_else(|| config.state_dir.as_ref().map(|d| d.join(analytics::DB_FILE)));
+        let analytics = analytics_path
+            .map(|path| AnalyticsDb::open(&path, config.analytics).map(Arc::new))
+            .transpose()?;
+
+        let ledger_path = config
+            .ledger
+            .path
+            .clone()
+            .or_else(|| config.state_dir.as_ref().map(|d| d.join(ledger::DB_FILE)));
+        let ledger_store = ledger_path
+            .map(|path| LedgerStore::open(&path, config.ledger).map(Arc::new))
+            .transpose()?;
+
+        let recorder = config
+            .trace_record
+            .as_deref()
+            .map(TraceRecorder::open)
+            .transpose()?;
+
+        // 本节点发起的超参数提议，自身即第一个背书者
+        let mut hyperparam_boards: HashMap<String, HyperParamBoard> = HashMap::new();
+        if let Some(params) = config.hyperparams.propose.clone() {
+            let proposal = HyperParamProposal {
+                proposal_id: rand::random(),
+                params,
+                apply_epoch: config.hyperparams.epoch_at(unix_now_secs()) + config.hyperparams.lead_epochs,
+                schedule: config.hyperparams.schedule.clone(),
+            };
+            println!(
+                "[超参数] 发起提议 {} => {:?}，调度 {:?}，纪元 {} 生效",
+                proposal.proposal_id, proposal.params, proposal.schedule, proposal.apply_epoch
+            );
+            hyperparam_boards
+                .entry(default_session_id())
+                .or_default()
+                .endorse(&proposal, &comms.local_peer_id(), proposal.apply_epoch - 1)?;
+        }
+
+        // 从持久化状态恢复（崩溃重启）
+        let store = config.state_dir.map(StateStore::new).transpose()?;
+        let offline = OfflineQueue::open(config.offline, store.as_ref().map(StateStore::dir))?;
+        if offline.len() > 0 {
+            println!("[离线] 加载 {} 条待重放的离线更新", offline.len());
+        }
+        let mut tick_counter = 0;
+        if let Some(state) = store.as_ref().map(StateStore::load).transpose()?.flatten() {
+            for session in &sessions {
+                let checkpoint = if session.is_default() {
+                    state.model.as_ref()
+                } else {
+                    state.session_models.get(&session.id)
+                };
+                if let (Some(model), Some(checkpoint)) = (&session.inference, checkpoint) {
+                    // 检查点文件比状态文件更新时保留前者
+                    if model.version() > checkpoint.version {
+                        continue;
+                    }
+                    if let Err(e) = model.restore_checkpoint(checkpoint) {
+                        eprintln!("[恢复] 会话 {} 的模型检查点不可用: {:?}", session.id, e);
+                    }
+                }
+            }
+            println!(
+                "[恢复] 模型 v{}, 账本 {} 条, 地址簿 {} 条, 轮次 {}",
+                inference.map_or(0, |m| m.tensor_snapshot().version),
+                state.ledger.len(),
+                state.address_book.len(),
+                state.last_round
+            );
+            consensus.import_ledger(state.ledger);
+            consensus.import_pins(state.key_pins);
+            comms.restore_address_book(state.address_book);
+            for (session, proposal) in state.hyperparams {
+                hyperparam_boards
+                    .entry(session)
+                    .or_default()
+                    .restore(proposal);
+            }
+            tick_counter = state.last_round;
+        }
+        // 持久化账本中的节点在重新出现时按需恢复，不在启动时整体载入
+        if let Some(ledger_store) = &ledger_store {
+            let stored = ledger_store.count()?;
+            if stored > 0 {
+                println!(
+                    "[账本] 持久化账本中有 {} 条质押记录，节点重新出现时恢复",
+                    stored
+                );
+            }
+        }
+
+        // 导入旧机器导出的声誉证明（需使用同一 ETH 密钥）
+        let mut pending_migration = None;
+        if let Some(path) = &config.reputation_import {
+            let attestation = ReputationAttestation::load(path)?;
+            attestation.verify_for(&crypto_suite.eth_address())?;
+            println!(
+                "[声誉迁移] 导入 {} 的证明：旧 PeerId {}, 账本 {} 条",
+                attestation.eth_address,
+                attestation.previous_peer,
+                attestation.ledger.len()
+            );
+            consensus.import_ledger(attestation.ledger);
+            consensus.import_pins(attestation.key_pins);
+            pending_migration = Some((attestation.previous_peer, MIGRATION_ANNOUNCEMENTS));
+        }
+
         // 初始化统计管理器
-        let model_hash = inference.tensor_hash();
-        let model_version = 1;
-        let stats = Arc::new(TrainingStatsManager::new(model_hash.clone(), model_version));
+        let model_hash = inference.map(|m| m.tensor_hash()).unwrap_or_default();
+        let model_version = inference.map_or(0, |m| m.tensor_snapshot().version);
+        let stats = Arc::new(TrainingStatsManager::new(model_hash, model_version));
+        let pipeline = Pipeline::standard(
+            &config.pipeline,
+            Arc::clone(&consensus),
+            Arc::new(ClockSkewTracker::new(config.clock)),
+            Arc::clone(&stats),
+            ledger_store.clone(),
+            &comms.local_peer_id(),
+        );
+        println!("[管道] 入站处理阶段: {}", pipeline.stage_names().join(" → "));
         
         println!(
             "启动 GGS 节点 => peer: {}, eth {}, sol {} @ ({:.2},{:.2})",
-            comms.peer_id,
+            comms.local_peer_id(),
             crypto_suite.eth_address(),
             crypto_suite.sol_address(),
             geo.lat,
             geo.lon
         );
-        println!("模型维度: {}", inference.model_dim());
+        println!("节点角色: {:?}", role);
+        match inference {
+            Some(model) => println!("模型维度: {}", model.model_dim()),
+            None => println!("轻量模式: 不加载模型参数"),
+        }
         println!(
             "设备能力: {}MB 内存, {} 核心, 网络: {:?}, 电池: {:?}",
             capabilities.max_memory_mb,
@@ -142,24 +798,109 @@ impl Node {
         
         Ok(Self {
             comms,
-            inference,
-            topology,
+            sessions,
             consensus,
+            pipeline,
+            role,
+            light,
+            monitor: Arc::new(PeerMonitor::new(Duration::from_secs(300))),
+            aggregation: config.aggregation,
+            apply_queue: ApplyQueue::new(config.apply_queue),
+            position: geo.clone(),
+            geofence: config.geofence,
+            token_gate: Arc::new(TokenGate::new(config.token_gate)),
+            rewards,
+            stake_prover,
+            stake_verifier,
+            heartbeat_chunks: config.heartbeat_chunks,
+            analytics,
+            ledger_store,
+            last_ledger_compaction: Instant::now(),
+            trust: TrustTracker::new(config.trust),
+            evaluation: config.evaluation,
+            eval_boards: HashMap::new(),
+            hyperparams: config.hyperparams,
+            hyperparam_boards,
+            beacon: config.beacon,
+            beacons: HashMap::new(),
+            beacon_shares: HashMap::new(),
+            epoch_snapshot_factor: 1.0,
+            sent_updates: HashMap::new(),
+            backfill_limiter: BackfillLimiter::default(),
+            version_vectors: HashMap::new(),
+            snapshot_verifier: SnapshotVerifier::default(),
+            dense_offers: HashMap::new(),
+            dense_wants: WantTracker::default(),
+            swarm_beat: Beat::default(),
+            training_beat: Beat::default(),
+            memory: config.memory,
+            standby_target: config.standby.replicate_to,
+            erasure: config.erasure,
+            share_assemblers: HashMap::new(),
+            recorder,
+            replaying: config.replaying,
+            aggregators: HashMap::new(),
+            known_aggregators: HashMap::new(),
+            retransmit: AckTracker::new(config.retransmit),
+            quic_peers: HashMap::new(),
+            snapshot_syncs: HashMap::new(),
+            snapshot_leads: HashMap::new(),
+            ledger_source: None,
+            ledger_synced: false,
+            sparsity: config.sparsity,
+            training_window_open: config.training_schedule.is_active_at(unix_now_secs()),
+            training_schedule: config.training_schedule,
+            power: Arc::new(PowerSaver::new(config.power_saver, config.power_saver_enabled)),
+            shard_assignments: HashMap::new(),
+            leases: LeaseBook::default(),
+            lease_grants: HashMap::new(),
+            sparsity_controllers: HashMap::new(),
             device_manager: config.device_manager,
+            names: Arc::new(NameResolver::new(config.names)),
             stats,
-            tick_counter: 0,
+            scheduler: IntervalScheduler::new(config.schedule),
+            readiness: Arc::new(ReadinessGate::new(config.readiness)),
+            store,
+            checkpoint: config.checkpoint,
+            last_checkpoint: Instant::now(),
+            clustering: config.clustering,
+            last_clustering: Instant::now(),
+            cluster_boards: HashMap::new(),
+            shutdown: Arc::new(tokio::sync::Notify::new()),
+            reloader,
+            reload: Arc::new(tokio::sync::Notify::new()),
+            offline,
+            was_connected: false,
+            pending_migration,
+            key_announce,
+            meta,
+            jobs: Arc::new(JobRegistry::new(config.jobs.expiry)),
+            job_config: config.jobs,
+            session_template,
+            tick_counter,
+            heartbeat_counter: 0,
         })
     }
 
-    async fn run(mut self) -> Result<()> {
+    async fn run(&mut self) -> Result<()> {
         let capabilities = self.device_manager.get();
-        let mut tick_interval = capabilities.recommended_tick_interval();
-        let mut ticker = interval(tick_interval);
-        let mut device_refresh = interval(Duration::from_secs(60)); // 每分钟刷新设备状态
-        
-        println!("训练频率: {:?}", tick_interval);
-        
+        self.scheduler
+            .set_scale(capabilities.tick_interval_factor() * self.power.interval_factor());
+        // 约每分钟刷新设备状态（带抖动）
+        let device_refresh_base = Duration::from_secs(60);
+        let mut next_device_refresh = Instant::now()
+            + jittered(device_refresh_base, self.scheduler.jitter_ratio());
+
+        println!(
+            "任务间隔: 心跳 {:?}, 探测 {:?}, 训练 {:?}, 快照 {:?}",
+            self.scheduler.effective_interval(PeriodicTask::Heartbeat),
+            self.scheduler.effective_interval(PeriodicTask::Probe),
+            self.scheduler.effective_interval(PeriodicTask::Train),
+            self.scheduler.effective_interval(PeriodicTask::Snapshot)
+        );
+
         loop {
+            self.swarm_beat.beat();
             // 检查是否应该暂停训练（低电量）
             let should_pause = {
                 let caps = self.device_manager.get();
@@ -168,28 +909,55 @@ impl Node {
             
             if should_pause {
                 println!("[电池保护] 电量过低，暂停训练");
+                // 暂停是有意为之，照常报告进展，避免看门狗误判训练停滞而重启节点
+                self.training_beat.beat();
                 tokio::time::sleep(Duration::from_secs(60)).await;
                 continue;
             }
             
+            let retransmit_at = self.retransmit.next_due();
+            let bootstrap_at = self.comms.next_bootstrap_retry().map(Instant::from_std);
             tokio::select! {
-                event = self.comms.swarm.select_next_some() => {
-                    if let SwarmEvent::Behaviour(out) = event {
-                        self.handle_network_event(out).await?;
-                    }
+                Some(event) = self.comms.next_event() => {
+                    self.handle_transport_event(event).await?;
+                }
+                _ = std::future::ready(()), if !self.apply_queue.is_empty() => {
+                    self.drain_apply_queue().await;
+                }
+                _ = self.shutdown.notified() => {
+                    self.graceful_shutdown().await;
+                    return Ok(());
+                }
+                _ = self.reload.notified() => {
+                    self.reload_config();
                 }
-                _ = ticker.tick() => {
-                    // 动态调整 tick 间隔（如果电池状态变化）
+                _ = tokio::time::sleep_until(retransmit_at.unwrap_or_else(Instant::now)), if retransmit_at.is_some() => {
+                    self.retransmit_updates().await?;
+                }
+                _ = tokio::time::sleep_until(bootstrap_at.unwrap_or_else(Instant::now)), if bootstrap_at.is_some() => {
+                    self.comms.retry_bootstrap();
+                }
+                _ = tokio::time::sleep(Duration::from_secs(1)), if !self.readiness.is_ready() => {
+                    self.update_readiness();
+                    self.training_beat.beat();
+                }
+                _ = tokio::time::sleep_until(self.scheduler.next_deadline()), if self.readiness.is_ready() => {
+                    // 动态调整任务间隔（电池状态变化或切换低功耗模式）
                     let caps = self.device_manager.get();
-                    let new_interval = caps.recommended_tick_interval();
-                    if new_interval != tick_interval {
-                        tick_interval = new_interval;
-                        ticker = interval(tick_interval);
-                        println!("[自适应] 调整训练频率: {:?}", tick_interval);
+                    let scale = caps.tick_interval_factor() * self.power.interval_factor();
+                    if self.scheduler.set_scale(scale) {
+                        println!(
+                            "[自适应] 调整训练频率: {:?}",
+                            self.scheduler.effective_interval(PeriodicTask::Train)
+                        );
                     }
-                    self.on_tick().await?;
+                    let due = self.scheduler.take_due();
+                    self.run_periodic(&due).await?;
+                    self.training_beat.beat();
                 }
-                _ = device_refresh.tick() => {
+                _ = tokio::time::sleep_until(next_device_refresh) => {
+                    next_device_refresh = Instant::now()
+                        + jittered(device_refresh_base, self.scheduler.jitter_ratio());
                     // 定期刷新设备状态（网络类型、电池等）
                     self.device_manager.refresh();
                     let caps = self.device_manager.get();
@@ -218,272 +986,3450 @@ impl Node {
         }
     }
 
-    async fn on_tick(&mut self) -> Result<()> {
-        self.tick_counter = self.tick_counter.wrapping_add(1);
-        self.stats.increment_tick();
-        
-        let hash = self.inference.tensor_hash();
-        let version = self.inference.tensor_snapshot().version;
-        self.stats.update_model(hash.clone(), version);
-        
-        let heartbeat = GgsMessage::Heartbeat {
-            peer: self.comms.peer_id.to_string(),
-            model_hash: hash,
-        };
-        self.publish_signed(heartbeat).await?;
-        self.stats.record_heartbeat_sent();
-
-        let embedding = self.inference.embedding();
-        let probe = GgsMessage::SimilarityProbe {
-            embedding,
-            position: self.topology.position(),
-            sender: self.comms.peer_id.to_string(),
+    /// 执行本轮到期的周期任务
+    async fn run_periodic(&mut self, due: &[PeriodicTask]) -> Result<()> {
+        let streak = if due.contains(&PeriodicTask::Heartbeat) {
+            self.consensus
+                .record_own_heartbeat(self.scheduler.effective_interval(PeriodicTask::Heartbeat))
+        } else {
+            0
         };
-        self.publish_signed(probe).await?;
-        self.stats.record_probe_sent();
+        for session in self.sessions.clone() {
+            // 轻量会话不持有模型，不发布心跳与探测
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            let mut outgoing = Vec::new();
+            if due.contains(&PeriodicTask::Heartbeat) {
+                let hash = model.tensor_hash();
+                let version = model.tensor_snapshot().version;
+                if session.is_default() {
+                    self.stats.update_model(hash.clone(), version);
+                }
+                outgoing.push(GgsMessage::Heartbeat {
+                    peer: self.comms.local_peer_id(),
+                    model_hash: hash,
+                    model_version: version,
+                    role: self.role,
+                    quic_addr: self.comms.quic_advertise(),
+                    compute: Some(self.device_manager.get().compute_capability()),
+                    stake_proof: self.stake_prover.as_ref().and_then(|p| p.current()),
+                    chunk_hashes: (self.heartbeat_chunks > 0)
+                        .then(|| model.chunk_hashes(self.heartbeat_chunks)),
+                    codecs: self.comms.codec_names(),
+                    streak,
+                    quic_addrs: self.comms.quic_addrs(),
+                    listen_addrs: self.comms.reachable_addrs(),
+                    base_hash: Some(model.base_hash()),
+                });
+            }
+            if due.contains(&PeriodicTask::Probe) && self.role.sends_probes() {
+                outgoing.push(GgsMessage::SimilarityProbe {
+                    embedding: model.embedding(),
+                    position: session.topology.position(),
+                    sender: self.comms.local_peer_id(),
+                    role: self.role,
+                    reprobe: false,
+                });
+            }
+            self.publish_batch(&session.id, outgoing).await?;
+            if due.contains(&PeriodicTask::Probe) && self.role.sends_probes() {
+                for peer in session.topology.silent_neighbors() {
+                    println!("[拓扑] 邻居 {} 静默，经直连重探测", self.peer_label(&peer));
+                    self.send_probe(&session, &peer, true).await;
+                }
+            }
+        }
 
-        self.inference.local_train_step();
-        self.consensus.prune_stale();
-        if self.tick_counter % 12 == 0 {
-            self.maybe_broadcast_dense().await?;
+        let window_open = self.check_training_window();
+        if due.contains(&PeriodicTask::Train) && window_open {
+            self.train_step();
         }
-        
-        // 更新连接的节点数量
-        let (primary, _backups) = self.topology.neighbor_sets();
-        self.stats.update_connected_peers(primary.len());
-        
-        // 每 10 个 tick 输出统计摘要
-        if self.tick_counter % 10 == 0 {
-            let summary = self.stats.get_summary();
-            let convergence = self.inference.convergence_score();
-            println!("{}", summary.format());
-            println!(
-                "  收敛度: {:.3} | 参数变化: {:.6} | 标准差: {:.6}",
-                convergence,
-                self.inference.parameter_change_magnitude(),
-                self.inference.parameter_std_dev()
-            );
+        if due.contains(&PeriodicTask::Snapshot) && self.role.broadcasts_snapshots() {
+            for session in self.sessions.clone() {
+                if self.role == NodeRole::Aggregator {
+                    self.flush_aggregated(&session).await?;
+                }
+                if window_open {
+                    self.maybe_broadcast_dense(&session).await?;
+                }
+            }
+        }
+        if due.contains(&PeriodicTask::Heartbeat) {
+            if self.reloader.as_ref().is_some_and(|r| r.changed()) {
+                self.reload_config();
+            }
+            self.on_heartbeat_tick();
+            self.pipeline.flush_failures(unix_now_millis());
+            self.grant_leases().await?;
+            self.contribute_beacon().await?;
+            self.tune_sparsity();
+            self.replay_offline_queue().await?;
+            self.rewards.maybe_submit();
+            if let Some(prover) = &self.stake_prover {
+                prover.maybe_refresh();
+            }
+            self.publish_stake_endorsements().await?;
+            self.run_evaluation().await?;
+            self.apply_hyperparams();
+            self.enforce_memory_budget()?;
+            self.replicate_to_standby().await;
+            self.manage_connections();
+        }
+        if due.contains(&PeriodicTask::Persist) {
+            self.persist_state();
+        }
+        if self.last_checkpoint.elapsed() >= self.checkpoint.interval {
+            self.save_checkpoints();
+        }
+        if self.clustering.is_enabled() && self.last_clustering.elapsed() >= self.clustering.interval {
+            self.recluster_peers().await?;
+        }
+        if due.contains(&PeriodicTask::Announce) {
+            self.announce_keys().await?;
+            self.announce_meta().await?;
+            self.announce_jobs().await?;
+            self.announce_migration().await?;
+            self.announce_hyperparams().await?;
+            self.comms.refresh_rendezvous();
+            self.comms.refresh_dht();
         }
-        
-        self.check_topology_health();
         Ok(())
     }
 
-    async fn handle_network_event(&mut self, event: OutEvent) -> Result<()> {
-        match event {
-            OutEvent::Gossipsub(g) => {
-                if let libp2p::gossipsub::Event::Message {
-                    propagation_source,
-                    message,
-                    ..
-                } = g
-                {
-                    if let Ok(signed) = serde_json::from_slice::<SignedGossip>(&message.data) {
-                        if self.consensus.verify(&signed) {
-                            self.handle_signed_message(signed, propagation_source.to_string())
-                                .await?;
-                        } else {
-                            eprintln!("签名验证失败，来自 {:?}", propagation_source);
-                        }
-                    }
+    /// 把已知聚合者与各会话的拓扑邻居（按评分排序）交给连接管理，清理空闲与低价值连接，
+    /// 并与宣告了 QUIC 地址的主邻居建立实时通道
+    fn manage_connections(&mut self) {
+        let mut ranked: Vec<String> = self
+            .known_aggregators
+            .values()
+            .map(|(peer, _, _)| peer.clone())
+            .collect();
+        for session in &self.sessions {
+            let (primary, backups) = session.topology.neighbor_sets();
+            for peer in primary.into_iter().chain(backups) {
+                if !ranked.contains(&peer) {
+                    ranked.push(peer);
                 }
             }
-            OutEvent::Mdns(event) => {
-                if let libp2p::mdns::Event::Discovered(peers) = event {
-                    for (peer, _addr) in peers {
-                        println!("通过 mDNS 发现节点 {peer}");
-                    }
+        }
+        self.comms.set_preferred_peers(ranked);
+        self.comms.prune_connections();
+        // 主邻居宣告了 QUIC 地址的，主动建立实时通道
+        let fresh_for = self.scheduler.effective_interval(PeriodicTask::Heartbeat) * 3;
+        let mut realtime: Vec<(String, Vec<std::net::SocketAddr>)> = Vec::new();
+        for session in &self.sessions {
+            for peer in session.topology.neighbor_sets().0 {
+                let Some((addrs, seen)) = self.quic_peers.get(&peer) else {
+                    continue;
+                };
+                if seen.elapsed() <= fresh_for && !realtime.iter().any(|(p, _)| *p == peer) {
+                    realtime.push((peer, addrs.clone()));
                 }
             }
         }
-        Ok(())
+        self.comms.connect_realtime(realtime);
     }
 
-    async fn publish_signed(&mut self, payload: GgsMessage) -> Result<()> {
-        let signed = self.consensus.sign(payload)?;
-        self.comms.publish(&signed)?;
-        if !self.comms.broadcast_realtime(&signed).await {
-            println!("[FAILOVER] QUIC 广播失败，已回落到纯 Gossip");
+    /// 是否处于训练窗口内，进出窗口时记录日志
+    fn check_training_window(&mut self) -> bool {
+        let open = self.training_schedule.is_active_at(unix_now_secs());
+        if open != self.training_window_open && self.training_schedule.is_restricted() {
+            if open {
+                println!("[训练窗口] 进入训练窗口，恢复本地训练与快照交换");
+            } else {
+                println!("[训练窗口] 离开训练窗口，仅作为中继运行");
+            }
         }
-        Ok(())
+        self.training_window_open = open;
+        open
     }
 
-    async fn handle_signed_message(&mut self, signed: SignedGossip, source: String) -> Result<()> {
-        match &signed.payload {
-            GgsMessage::Heartbeat { peer, .. } => {
-                self.consensus.update_stake(peer, 0.0, 0.0, 0.05);
-                self.stats.record_heartbeat_received(peer);
-                println!("收到 {} 的心跳 (via {source})", peer);
-            }
-            GgsMessage::SimilarityProbe {
-                embedding,
-                position,
-                sender,
-            } => {
-                self.stats.record_probe_received(sender);
-                let self_embedding = self.inference.embedding();
-                self.topology.update_peer(
-                    sender,
-                    embedding.clone(),
-                    position.clone(),
-                    &self_embedding,
+    /// 根据收敛度与上一带宽窗口的预算使用情况调整各会话的 top-k
+    fn tune_sparsity(&mut self) {
+        for session in &self.sessions {
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            let controller = self
+                .sparsity_controllers
+                .entry(session.id.clone())
+                .or_insert_with(|| {
+                    let controller = SparsityController::new(self.sparsity.clone());
+                    model.set_sparse_k(controller.k());
+                    controller
+                });
+            let loss = 1.0 - model.convergence_score();
+            let usage = self.comms.sparse_budget_usage(&session.id);
+            if let Some(k) = controller.observe(loss, usage) {
+                let previous = model.sparse_k();
+                model.set_sparse_k(k);
+                println!(
+                    "[稀疏度] [{}] top-k {} -> {} (损失 {:.3}, 预算利用率 {:.0}%)",
+                    session.id,
+                    previous,
+                    model.sparse_k(),
+                    loss,
+                    usage.map(|u| u.utilization * 100.0).unwrap_or(0.0)
                 );
-                if let Some(snapshot) = self.topology.peer_snapshot(sender) {
-                    let stake = self.consensus.stake_weight(sender);
+            }
+        }
+    }
+
+    /// 聚合者：合并本窗口内训练者的更新，应用到本地并广播一条合并后的更新
+    async fn flush_aggregated(&mut self, session: &Arc<Session>) -> Result<()> {
+        let Some(model) = &session.inference else {
+            return Ok(());
+        };
+        let Some(aggregator) = self.aggregators.get_mut(&session.id) else {
+            return Ok(());
+        };
+        let trainers = aggregator.pending_senders();
+        let lagging = lagging_trainers(
+            &self.monitor.trainer_compute(&session.id),
+            aggregator.contributions(),
+        );
+        if !lagging.is_empty() {
+            println!(
+                "[聚合] [{}] 以下训练者的贡献低于按算力估计的预期: {:?}",
+                session.id, lagging
+            );
+        }
+        let Some(merged) = aggregator.merge() else {
+            return Ok(());
+        };
+        let merged = self
+            .sent_updates
+            .entry(session.id.clone())
+            .or_default()
+            .stamp(merged);
+        let clamped = model.apply_sparse_update(&merged, self.merge_coefficient(&session.id));
+        self.stats.record_params_clamped(clamped);
+        println!(
+            "[聚合] [{}] 合并 {} 个训练者的更新 ({} 个坐标)",
+            session.id,
+            trainers,
+            merged.values.len()
+        );
+        let msg = GgsMessage::SparseUpdate {
+            update: merged,
+            sender: self.comms.local_peer_id(),
+        };
+        self.publish_signed(&session.id, msg).await
+    }
+
+    /// 训练者：若已知本会话的聚合者，则通过 QUIC 直接发送更新，返回是否成功
+    async fn send_to_aggregator(&mut self, session: &str, payload: &GgsMessage) -> bool {
+        if self.replaying {
+            return false;
+        }
+        // 超过 3 个心跳周期没有消息的聚合者视为离线
+        let fresh_for = self.scheduler.effective_interval(PeriodicTask::Heartbeat) * 3;
+        let Some((peer, addr, seen)) = self.known_aggregators.get(session).cloned() else {
+            return false;
+        };
+        if seen.elapsed() > fresh_for {
+            self.known_aggregators.remove(session);
+            return false;
+        }
+        // 直连明显慢于 gossip 时（如经中继转发）直接走 gossip
+        if self.comms.prefers_gossip(&peer) {
+            return false;
+        }
+        let signed = match self.consensus.sign(session, payload.clone()) {
+            Ok(signed) => signed,
+            Err(_) => return false,
+        };
+        match self.comms.send_direct(addr, &signed).await {
+            Ok(()) => {
+                self.retransmit.track(signed, &peer, addr, Instant::now());
+                true
+            }
+            Err(e) => {
+                self.record_error(&e);
+                eprintln!("[聚合] 直连聚合者 {} ({}) 失败，回落到 gossip: {:?}", peer, addr, e);
+                self.known_aggregators.remove(session);
+                false
+            }
+        }
+    }
+
+    /// 重发到期未确认的直连更新，重试用尽的改经 gossip 发布
+    async fn retransmit_updates(&mut self) -> Result<()> {
+        for item in self.retransmit.take_due(Instant::now()) {
+            match item {
+                Retransmit::Retry {
+                    signed,
+                    peer,
+                    addr,
+                    attempt,
+                } => {
                     println!(
-                        "拓扑更新：{} => sim {:.3}, geo {:.3}, stake {:.3}, dim {}, pos ({:.1},{:.1})",
-                        sender,
-                        snapshot.similarity,
-                        snapshot.geo_affinity,
-                        stake,
-                        snapshot.embedding_dim,
-                        snapshot.position.lat,
-                        snapshot.position.lon
+                        "[重传] {} 未确认更新，第 {} 次重发到 {}",
+                        signed.session,
+                        attempt,
+                        self.peer_label(&peer)
                     );
-                }
-                if self.should_send_sparse_update(sender) {
-                    if self.comms.allow_sparse_update() {
-                        let update = self.inference.make_sparse_update(16);
-                        let msg = GgsMessage::SparseUpdate {
-                            update,
-                            sender: self.comms.peer_id.to_string(),
-                        };
-                        self.publish_signed(msg).await?;
-                        self.stats.record_sparse_update_sent(sender);
-                    } else {
-                        println!("[带宽限制] 本轮跳过稀疏更新");
+                    self.stats.record_sparse_retransmit();
+                    if let Err(e) = self.comms.send_direct(addr, &signed).await {
+                        self.record_error(&e);
+                        eprintln!("[重传] 直连 {} ({}) 失败: {:?}", peer, addr, e);
                     }
                 }
-            }
-            GgsMessage::SparseUpdate { sender, update } => {
-                self.inference.apply_sparse_update(update);
-                self.consensus.update_stake(sender, 0.1, 0.0, 0.1);
-                self.stats.record_sparse_update_received(sender);
-                println!("应用来自 {} 的稀疏更新", sender);
-            }
-            GgsMessage::DenseSnapshot { snapshot, sender } => {
-                self.inference.apply_dense_snapshot(snapshot);
-                self.consensus.update_stake(sender, 0.0, 0.2, 0.05);
-                self.stats.record_dense_snapshot_received(sender);
-                println!("融合 {} 的模型快照", sender);
+                Retransmit::Fallback { signed, peer } => {
+                    println!(
+                        "[重传] {} 始终未确认更新，回落到 gossip",
+                        self.peer_label(&peer)
+                    );
+                    self.stats.record_sparse_ack_fallback();
+                    self.publish_signed(&signed.session, signed.payload).await?;
+                }
             }
         }
         Ok(())
     }
 
-    fn should_send_sparse_update(&self, target: &str) -> bool {
-        let primary = self.topology.select_neighbors();
-        if primary.iter().any(|peer| peer == target) {
-            return true;
+    /// 聚合者：确认收到训练者直连发来的已编号更新，对方据此停止重传。
+    /// 确认单播给该训练者，优先复用它连入时的连接
+    async fn ack_update(&mut self, session: &str, sender: &str, seq: u64) {
+        if self.replaying || seq == 0 {
+            return;
+        }
+        let msg = GgsMessage::UpdateAck {
+            target: sender.to_string(),
+            seq,
+            sender: self.comms.local_peer_id(),
+        };
+        let result: Result<()> = match self.consensus.sign(session, msg) {
+            Ok(signed) => self.comms.send_to(sender, &signed).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            self.record_error(&e);
+            eprintln!("[重传] 向 {} 确认更新 #{} 失败: {:?}", sender, seq, e);
         }
-        self.topology.mark_unreachable(target);
-        false
     }
 
-    fn check_topology_health(&self) {
-        let (primary, backups) = self.topology.neighbor_sets();
-        if primary.len() < self.topology.max_neighbors() && !backups.is_empty() {
-            println!(
-                "[拓扑 Failover] 主邻居 {}/{}，启用备份 {:?}",
-                primary.len(),
-                self.topology.max_neighbors(),
-                backups
-            );
-        } else if backups.len() < self.topology.failover_pool() {
-            println!(
-                "[拓扑提示] 备份邻居不足 {}/{}",
-                backups.len(),
-                self.topology.failover_pool()
-            );
+    /// 经 QUIC 直连给单个节点发一条探测：`reprobe` 为 true 时是对静默邻居的重探测，
+    /// 否则是对重探测的回应
+    async fn send_probe(&mut self, session: &Session, peer: &str, reprobe: bool) {
+        let Some(model) = &session.inference else {
+            return;
+        };
+        let Some(addr) = self.quic_addr_of(peer) else {
+            return;
+        };
+        let msg = GgsMessage::SimilarityProbe {
+            embedding: model.embedding(),
+            position: session.topology.position(),
+            sender: self.comms.local_peer_id(),
+            role: self.role,
+            reprobe,
+        };
+        let result = match self.consensus.sign(&session.id, msg) {
+            Ok(signed) => self.comms.send_direct(addr, &signed).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(()) => self.stats.record_probe_sent(),
+            Err(e) => {
+                self.record_error(&e);
+                eprintln!("[拓扑] 向 {} 直连发送探测失败: {:?}", peer, e);
+            }
         }
     }
 
-    async fn maybe_broadcast_dense(&mut self) -> Result<()> {
-        let network_type = self.comms.network_type();
-        if !network_type.allows_dense_snapshot() {
-            // 移动网络下跳过密集快照
+    /// QUIC 直连消息：聚合者接收训练者的稀疏更新，各节点接收请求过的快照、更新确认与直连探测；
+    /// 其余是发送方同时经 gossip 发出的推送，与 gossip 消息一样处理，先到的一路生效
+    async fn handle_direct_message(&mut self, signed: SignedGossip, from: &str) -> Result<()> {
+        self.record_trace(TraceChannel::Direct, &signed);
+        self.process_direct(signed, from, unix_now_millis()).await
+    }
+
+    /// `from` 为传输层确认的对端身份，用于失败上报
+    async fn process_direct(
+        &mut self,
+        signed: SignedGossip,
+        from: &str,
+        now_ms: u64,
+    ) -> Result<()> {
+        let requested = matches!(
+            signed.payload,
+            GgsMessage::DenseSnapshot { .. }
+                | GgsMessage::DenseSlice { .. }
+                | GgsMessage::SnapshotShare { .. }
+                | GgsMessage::UpdateAck { .. }
+                | GgsMessage::SimilarityProbe { .. }
+        );
+        let collected = self.role == NodeRole::Aggregator
+            && matches!(signed.payload, GgsMessage::SparseUpdate { .. });
+        let Some(session) = self.session(&signed.session) else {
+            return Ok(());
+        };
+        let channel = if requested || collected {
+            Channel::Direct { from }
+        } else {
+            Channel::Realtime { from }
+        };
+        let inbound = Inbound {
+            signed: &signed,
+            channel,
+            now_ms,
+        };
+        if !self.pipeline.run(&inbound) {
             return Ok(());
         }
-        
-        let snapshot = self.inference.tensor_snapshot();
-        let bytes = snapshot.values.len() * std::mem::size_of::<f32>();
-        if self.comms.allow_dense_snapshot(bytes) {
-            let msg = GgsMessage::DenseSnapshot {
-                snapshot,
-                sender: self.comms.peer_id.to_string(),
-            };
-            self.publish_signed(msg).await?;
-            self.stats.record_dense_snapshot_sent();
+        self.observe_latency(&session, &signed, LatencyPath::Direct, now_ms);
+        if matches!(channel, Channel::Realtime { .. }) {
+            // 与 gossip 副本共用去重窗口，后到的一路被丢弃
+            return self.handle_signed_message(&session, signed, "quic".into()).await;
+        }
+        // 只接受本节点通过 IWant 请求过的快照
+        if let GgsMessage::DenseSnapshot { snapshot, sender }
+        | GgsMessage::DenseSlice { snapshot, sender } = &signed.payload
+        {
+            if !self.dense_wants.fulfil(&snapshot.hash(), sender) {
+                return Ok(());
+            }
+        }
+        self.handle_message(&session, signed.payload, "quic").await
+    }
+
+    /// gossip 入站处理：通过入站管道的各阶段后分发，并把判定上报给 gossip 层决定是否转发
+    async fn process_gossip(
+        &mut self,
+        signed: SignedGossip,
+        source: Option<&str>,
+        propagation: String,
+        message_id: &[u8],
+        now_ms: u64,
+    ) -> Result<()> {
+        let Some(session) = self.session(&signed.session) else {
+            self.comms
+                .report_validation(message_id, &propagation, Verdict::Drop);
+            return Ok(());
+        };
+        let inbound = Inbound {
+            signed: &signed,
+            channel: Channel::Gossip {
+                source,
+                propagation: &propagation,
+            },
+            now_ms,
+        };
+        let verdict = self.pipeline.verdict(&inbound);
+        self.comms
+            .report_validation(message_id, &propagation, verdict);
+        if verdict == Verdict::Pass {
+            self.observe_latency(&session, &signed, LatencyPath::Gossip, now_ms);
+            self.handle_signed_message(&session, signed, propagation)
+                .await?;
         }
         Ok(())
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // 解析命令行参数
-    let args: Vec<String> = std::env::args().collect();
-    let mut stats_output: Option<String> = None;
-    let mut node_id: Option<usize> = None;
-    let mut model_dim: Option<usize> = None;
-    
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--stats-output" => {
-                if i + 1 < args.len() {
-                    stats_output = Some(args[i + 1].clone());
-                    i += 2;
-                } else {
-                    i += 1;
+    /// 库错误按错误码计入统计
+    fn record_error(&self, err: &anyhow::Error) {
+        if let Some(code) = errors::code_of(err) {
+            self.stats.record_error(code);
+        }
+    }
+
+    /// 通过入站检查的消息计入发送者的延迟直方图，并同步到统计与拓扑评分
+    fn observe_latency(&self, session: &Session, signed: &SignedGossip, path: LatencyPath, now_ms: u64) {
+        let sender = signed.payload.sender();
+        let Some(summary) = self.comms.observe_latency(sender, path, signed.sent_at_ms, now_ms) else {
+            return;
+        };
+        self.stats.record_latency(sender, path, summary);
+        if let Some(best) = self.comms.peer_latency(sender) {
+            session.topology.set_latency(sender, best);
+        }
+    }
+
+    fn record_trace(&mut self, channel: TraceChannel, signed: &SignedGossip) {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        let entry = TraceEntry {
+            received_at_ms: unix_now_millis(),
+            channel,
+            signed: signed.clone(),
+        };
+        if let Err(e) = recorder.record(&entry) {
+            eprintln!("[录制] 写入失败，停止录制: {:?}", e);
+            self.recorder = None;
+        }
+    }
+
+    /// 按录制顺序把消息送入入站处理流程，时间戳校验使用录制时的接收时间
+    async fn replay(&mut self, entries: Vec<TraceEntry>) -> Result<()> {
+        let total = entries.len();
+        for entry in entries {
+            match entry.channel {
+                TraceChannel::Gossip {
+                    source,
+                    propagation,
+                } => {
+                    self.process_gossip(
+                        entry.signed,
+                        source.as_deref(),
+                        propagation,
+                        &[],
+                        entry.received_at_ms,
+                    )
+                    .await?
                 }
-            }
-            "--node-id" => {
-                if i + 1 < args.len() {
-                    node_id = args[i + 1].parse().ok();
-                    i += 2;
-                } else {
-                    i += 1;
+                TraceChannel::Direct => {
+                    // 录制时没有保存连接身份，以签名校验过的发送者代替
+                    let from = entry.signed.payload.sender().to_string();
+                    self.process_direct(entry.signed, &from, entry.received_at_ms)
+                        .await?
                 }
             }
-            "--model-dim" => {
-                if i + 1 < args.len() {
-                    model_dim = args[i + 1].parse().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+        }
+        println!("[重放] 已重放 {} 条消息", total);
+        let mut dropped: Vec<_> = self.pipeline.dropped().iter().collect();
+        dropped.sort();
+        for (stage, count) in dropped {
+            println!("[重放] 管道阶段 {} 丢弃 {} 条", stage, count);
+        }
+        for session in &self.sessions {
+            if let Some(model) = &session.inference {
+                let snapshot = model.tensor_snapshot();
+                println!(
+                    "[重放] 会话 {}: 模型版本 {}, 哈希 {}",
+                    session.id,
+                    snapshot.version,
+                    snapshot.hash()
+                );
             }
-            _ => i += 1,
         }
+        Ok(())
     }
-    
-    if let Some(id) = node_id {
-        println!("节点 ID: {}", id);
+
+    /// 按心跳中的链上证明设置质押；有 RPC 时在后台核对
+    /// 模型哈希与本地不一致时，用对方的分块哈希定位分歧区间
+    fn localize_divergence(
+        &self,
+        session: &Session,
+        peer: &str,
+        model_hash: &str,
+        hashes: &ModelChunkHashes,
+    ) {
+        let Some(model) = &session.inference else {
+            return;
+        };
+        if !hashes.verify() {
+            eprintln!("[分块哈希] {} 的分块哈希与 Merkle 根不符，忽略", self.peer_label(peer));
+            return;
+        }
+        if model.tensor_hash() == model_hash {
+            self.monitor.record_divergence(&session.id, peer, &[]);
+            return;
+        }
+        match model.diverging_ranges(hashes) {
+            Some(ranges) => {
+                let diverged: usize = ranges.iter().map(|r| r.len()).sum();
+                println!(
+                    "[分块哈希] 与 {} 的模型在 {} 个区间、共 {} 个参数上不一致: {:?}",
+                    self.peer_label(peer),
+                    ranges.len(),
+                    diverged,
+                    ranges
+                );
+                self.monitor.record_divergence(&session.id, peer, &ranges);
+            }
+            None => println!(
+                "[分块哈希] {} 的分块布局与本地模型维度不符，无法定位分歧",
+                self.peer_label(peer)
+            ),
+        }
     }
-    
-    // 构建配置，支持自定义模型维度
-    let mut config = AppConfig::default();
-    if let Some(dim) = model_dim {
-        config.inference.model_dim = dim;
-        println!("使用自定义模型维度: {}", dim);
+
+    /// 证明先记为待确认，由链上核对或已确认节点的背书确认后才设置质押
+    fn apply_stake_proof(&self, peer: &str, proof: &StakeProof) {
+        let Some(pin) = self.consensus.key_pin(peer) else {
+            return;
+        };
+        match self.stake_verifier.accept(peer, &pin, proof, unix_now_secs()) {
+            Ok(()) => {
+                self.stake_verifier
+                    .audit(peer, proof.clone(), self.consensus.clone());
+            }
+            Err(e) => eprintln!("[质押证明] 忽略 {} 的证明: {}", self.peer_label(peer), e),
+        }
+    }
+
+    /// 广播本节点按链上状态核对通过的余额证明背书
+    async fn publish_stake_endorsements(&mut self) -> Result<()> {
+        for endorsement in self.stake_verifier.take_endorsements() {
+            let msg = GgsMessage::StakeAttestation {
+                endorsement,
+                sender: self.comms.local_peer_id(),
+            };
+            self.publish_signed(DEFAULT_SESSION, msg).await?;
+        }
+        Ok(())
+    }
+
+    /// 日志中展示的节点名称（已解析 ENS / SNS 时附带名称）
+    fn peer_label(&self, peer: &str) -> String {
+        self.names.label(self.consensus.key_pin(peer).as_ref(), peer)
+    }
+
+    /// 节点是否在地理围栏内（位置取自其相似度探测）
+    fn in_geofence(&self, session: &Session, peer: &str) -> bool {
+        let position = session.topology.peer_snapshot(peer).map(|s| s.position);
+        self.geofence.allows(&self.position, position.as_ref())
+    }
+
+    /// 节点固定的 ETH 地址是否达到代币门槛，尚未固定密钥的节点视为未达标
+    fn passes_token_gate(&self, peer: &str) -> bool {
+        if !self.token_gate.is_enabled() {
+            return true;
+        }
+        self.consensus
+            .key_pin(peer)
+            .is_some_and(|pin| self.token_gate.allows(&pin.eth_address))
+    }
+
+    /// 融合共识权重与当前拓扑相似度得到节点的信任分
+    fn trust_score(&self, session: &Session, peer: &str) -> TrustScore {
+        let similarity = session.topology.peer_snapshot(peer).map(|s| s.similarity);
+        self.trust
+            .score(peer, self.consensus.stake_weight(peer), similarity)
+    }
+
+    /// 把被接受的更新写入分析库
+    fn record_update(
+        &self,
+        session: &Session,
+        sender: &str,
+        kind: UpdateKind,
+        version: u64,
+        values: &[f32],
+        loss_delta: Option<f32>,
+    ) {
+        let Some(db) = &self.analytics else {
+            return;
+        };
+        let record = UpdateRecord {
+            session: session.id.clone(),
+            sender: sender.to_string(),
+            kind,
+            version,
+            k: values.len(),
+            norm: l2_norm(values),
+            received_at_ms: unix_now_millis(),
+            loss_delta,
+        };
+        if let Err(e) = db.record(&record) {
+            eprintln!("[分析] 写入更新记录失败: {:?}", e);
+        }
+    }
+
+    /// 为节点固定的 ETH 地址记一次被接受的贡献
+    fn credit_contribution(&self, peer: &str) {
+        if let Some(pin) = self.consensus.key_pin(peer) {
+            self.rewards.credit(&pin.eth_address, 1);
+        }
+    }
+
+    /// 广播签名密钥与准入证明，供新加入的节点接纳本节点
+    async fn announce_keys(&mut self) -> Result<()> {
+        let Some(msg) = self.key_announce.clone() else {
+            return Ok(());
+        };
+        self.publish_signed(DEFAULT_SESSION, msg).await
+    }
+
+    async fn announce_meta(&mut self) -> Result<()> {
+        let msg = GgsMessage::PeerMeta {
+            meta: self.meta.clone(),
+            sender: self.comms.local_peer_id(),
+        };
+        self.publish_signed(DEFAULT_SESSION, msg).await
+    }
+
+    /// 在默认主题上广播本节点发布的任务，未写明所有者的任务以本节点为所有者
+    async fn announce_jobs(&mut self) -> Result<()> {
+        let peer_id = self.comms.local_peer_id();
+        for mut job in self.job_config.announce.clone() {
+            job.owner.get_or_insert_with(|| peer_id.clone());
+            let msg = GgsMessage::JobAnnounce {
+                job,
+                sender: peer_id.clone(),
+            };
+            self.publish_signed(DEFAULT_SESSION, msg).await?;
+        }
+        Ok(())
+    }
+
+    /// 广播身份迁移请求，重复数次以覆盖稍后上线的节点
+    async fn announce_migration(&mut self) -> Result<()> {
+        let Some((previous_peer, remaining)) = self.pending_migration.take() else {
+            return Ok(());
+        };
+        let msg = GgsMessage::IdentityMigration {
+            previous_peer: previous_peer.clone(),
+            sender: self.comms.local_peer_id(),
+        };
+        self.publish_signed(DEFAULT_SESSION, msg).await?;
+        if remaining > 1 {
+            self.pending_migration = Some((previous_peer, remaining - 1));
+        }
+        Ok(())
+    }
+
+    /// 对各会话已知节点的探测嵌入重新聚类，按同簇 / 跨簇混合邻居，并广播本节点的簇视图
+    async fn recluster_peers(&mut self) -> Result<()> {
+        self.last_clustering = Instant::now();
+        let own = self.comms.local_peer_id();
+        for session in self.sessions.clone() {
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            let board = self
+                .cluster_boards
+                .entry(session.id.clone())
+                .or_insert_with(|| ClusterBoard::new(self.clustering.clone()));
+            let members = match board.recluster(&model.embedding(), session.topology.embeddings()) {
+                Some(clustering) => {
+                    let members = clustering.members();
+                    println!(
+                        "[聚类] [{}] {} 个节点分为 {} 簇，本节点所在簇 {} 有 {} 个成员",
+                        session.id,
+                        clustering.labels.len(),
+                        self.clustering.clusters,
+                        clustering.local,
+                        members.len()
+                    );
+                    members
+                }
+                None => {
+                    session.topology.set_same_cluster(None);
+                    continue;
+                }
+            };
+            session.topology.set_same_cluster(board.same_cluster(&own));
+            let msg = GgsMessage::ClusterView {
+                members,
+                sender: own.clone(),
+            };
+            self.publish_signed(&session.id, msg).await?;
+        }
+        Ok(())
+    }
+
+    /// 重新广播本节点背书过、尚未生效的超参数提议，覆盖稍后上线的节点
+    async fn announce_hyperparams(&mut self) -> Result<()> {
+        let peer_id = self.comms.local_peer_id();
+        let endorsed: Vec<(String, HyperParamProposal)> = self
+            .hyperparam_boards
+            .iter()
+            .flat_map(|(session, board)| {
+                board
+                    .endorsed_by(&peer_id)
+                    .into_iter()
+                    .map(move |p| (session.clone(), p))
+            })
+            .collect();
+        for (session, proposal) in endorsed {
+            let msg = GgsMessage::HyperParamUpdate {
+                proposal,
+                sender: peer_id.clone(),
+            };
+            self.publish_signed(&session, msg).await?;
+        }
+        Ok(())
+    }
+
+    /// 纪元边界：应用已通过投票的到期提议，再按纪元调度推导本纪元的学习率、top-k 与快照间隔
+    fn apply_hyperparams(&mut self) {
+        let epoch = self.hyperparams.epoch_at(unix_now_secs());
+        let peer_id = self.comms.local_peer_id();
+        let mut snapshot_factor = None;
+        for session in &self.sessions {
+            let beacon = self.beacon_seed(&session.id);
+            let Some(board) = self.hyperparam_boards.get_mut(&session.id) else {
+                continue;
+            };
+            let approves = |endorsers: &std::collections::HashSet<String>| {
+                hyperparams_approved(
+                    &self.hyperparams.rule,
+                    &self.consensus,
+                    &peer_id,
+                    beacon.as_ref(),
+                    endorsers,
+                )
+            };
+            if let Some(proposal) = board.take_due(epoch, approves) {
+                let params = &proposal.params;
+                if let Some(model) = &session.inference {
+                    model.set_learning_rate(params.learning_rate);
+                    model.set_sparse_k(params.sparse_k);
+                }
+                if let Some(controller) = self.sparsity_controllers.get_mut(&session.id) {
+                    controller.set_k(params.sparse_k);
+                }
+                println!(
+                    "[超参数] [{}] 纪元 {} 应用提议 {}: 学习率 {}, top-k {}, 合并系数 {}",
+                    session.id,
+                    epoch,
+                    proposal.proposal_id,
+                    params.learning_rate,
+                    params.sparse_k,
+                    params.merge_coefficient
+                );
+            }
+            let Some(derived) = board.next_scheduled(epoch) else {
+                continue;
+            };
+            if let Some(model) = &session.inference {
+                model.set_learning_rate(derived.learning_rate);
+                model.set_sparse_k(derived.sparse_k);
+            }
+            if let Some(controller) = self.sparsity_controllers.get_mut(&session.id) {
+                controller.set_k(derived.sparse_k);
+            }
+            // 快照间隔是节点级的，跟随默认会话
+            if session.id == default_session_id() {
+                snapshot_factor = Some(derived.snapshot_factor);
+            }
+            println!(
+                "[超参数] [{}] 纪元 {} 调度: 学习率 {}, top-k {}, 快照间隔 x{:.2}",
+                session.id, epoch, derived.learning_rate, derived.sparse_k, derived.snapshot_factor
+            );
+        }
+        if let Some(factor) =
+            snapshot_factor.filter(|f| (f - self.epoch_snapshot_factor).abs() > f32::EPSILON)
+        {
+            let mut config = self.scheduler.config().clone();
+            config.snapshot_interval = config
+                .snapshot_interval
+                .div_f32(self.epoch_snapshot_factor)
+                .mul_f32(factor);
+            self.epoch_snapshot_factor = factor;
+            self.scheduler.set_config(config);
+        }
+    }
+
+    /// 会话当前生效的稀疏更新基准合并系数
+    fn merge_coefficient(&self, session: &str) -> f32 {
+        self.hyperparam_boards
+            .get(session)
+            .map_or(BASE_MERGE_COEFFICIENT, |board| board.current.merge_coefficient)
+    }
+
+    /// 设备能力满足要求时自动加入发现的任务
+    fn maybe_join_job(&mut self, job: &JobSpec) -> Result<()> {
+        if !self.job_config.auto_join || self.session(&job.job_id).is_some() {
+            return Ok(());
+        }
+        let joined = (self.sessions.len() - 1).saturating_sub(self.job_config.announce.len());
+        if joined >= self.job_config.max_joined {
+            return Ok(());
+        }
+        if !job.requirements.satisfied_by(&self.device_manager.get()) {
+            println!("[任务] 设备能力不满足任务 {} 的要求，跳过", job.job_id);
+            return Ok(());
+        }
+        let (inference, position, topology) = self.session_template.clone();
+        let session_config = job.session_config(&inference);
+        if !self.light {
+            let needed = self.memory_usage().models
+                + memory::model_bytes(session_config.inference.model_dim);
+            if let Err(e) = self.memory.check_models_fit(needed) {
+                println!("[内存] 跳过任务 {}: {}", job.job_id, e);
+                return Ok(());
+            }
+        }
+        let share = 1.0 / self.sessions.len() as f32;
+        let session = if self.light {
+            Session::light(session_config.id.clone(), position, topology)
+        } else {
+            Session::new(
+                session_config.id.clone(),
+                session_config.inference,
+                position,
+                topology,
+            )?
+        };
+        self.comms
+            .register_session(&session_config.id, &session_config.topic, share)?;
+        println!(
+            "[任务] 自动加入任务 {} ({}, dim {}, 主题 {})",
+            job.job_id, job.architecture, job.model_dim, job.topic
+        );
+        self.sessions.push(Arc::new(session));
+        Ok(())
+    }
+
+    fn default_session(&self) -> &Arc<Session> {
+        &self.sessions[0]
+    }
+
+    fn session(&self, id: &str) -> Option<Arc<Session>> {
+        self.sessions.iter().find(|s| s.id == id).cloned()
+    }
+
+    /// 将模型、账本、地址簿、生效的超参数提议与轮次写入状态目录
+    fn persist_state(&self) {
+        if let Some(db) = &self.analytics {
+            match db.prune(unix_now_millis()) {
+                Ok(0) => {}
+                Ok(n) => println!("[分析] 清理 {} 条过期更新记录", n),
+                Err(e) => eprintln!("[分析] 清理过期记录失败: {:?}", e),
+            }
+        }
+        self.save_ledger(&self.consensus.export_ledger());
+        let Some(store) = &self.store else {
+            return;
+        };
+        let mut state = self.snapshot_state();
+        if let Err(e) = store.save(&mut state) {
+            eprintln!("[持久化] 保存节点状态到 {:?} 失败: {:?}", store.dir(), e);
+        }
+    }
+
+    /// 把账本条目写入持久化账本
+    fn save_ledger(&self, entries: &[LedgerEntry]) {
+        let Some(store) = &self.ledger_store else {
+            return;
+        };
+        if entries.is_empty() {
+            return;
+        }
+        if let Err(e) = store.save(entries, unix_now_millis()) {
+            eprintln!("[账本] 写入持久化账本失败: {:?}", e);
+        }
+    }
+
+    /// 热加载配置文件中的带宽预算、拓扑与周期任务间隔，按角色调整后应用；模型与账本不受影响
+    fn reload_config(&mut self) {
+        let Some(reloader) = &mut self.reloader else {
+            return;
+        };
+        let mut config = match reloader.reload() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("[配置] 热加载失败，保留当前配置: {}", e);
+                return;
+            }
+        };
+        self.role.adjust_topology(&mut config.topology);
+        self.comms.set_bandwidth(self.role.adjust_bandwidth(&config.bandwidth));
+        config.schedule.snapshot_interval = config
+            .schedule
+            .snapshot_interval
+            .mul_f32(self.role.snapshot_interval_factor() * self.epoch_snapshot_factor);
+        self.scheduler.set_config(config.schedule);
+        for session in &self.sessions {
+            session.topology.set_config(config.topology.clone());
+        }
+        self.session_template.2 = config.topology;
+        println!("[配置] 已热加载 {}", reloader.path().display());
+    }
+
+    /// 收到退出信号：先落盘模型检查点与节点状态（含质押账本），再退订 gossip 主题并关闭 QUIC 连接
+    async fn graceful_shutdown(&mut self) {
+        println!("[退出] 保存检查点与节点状态");
+        self.save_checkpoints();
+        self.persist_state();
+        self.comms.shutdown(SHUTDOWN_GRACE).await;
+        println!("[退出] 已退订 gossip 主题并关闭 QUIC 连接");
+    }
+
+    /// 各会话的模型写入检查点目录（未配置目录的会话跳过）
+    fn save_checkpoints(&mut self) {
+        self.last_checkpoint = Instant::now();
+        if self.replaying {
+            return;
+        }
+        for session in &self.sessions {
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            if let Err(e) = model.save_checkpoint(self.checkpoint.keep) {
+                self.stats.record_error(e.code());
+                eprintln!("[检查点] 保存会话 {} 的检查点失败: {:?}", session.id, e);
+            }
+        }
+    }
+
+    /// 把当前持久化状态签名后发给热备节点
+    async fn replicate_to_standby(&mut self) {
+        let Some(addr) = self.standby_target else {
+            return;
+        };
+        if self.replaying {
+            return;
+        }
+        let msg = GgsMessage::StateReplica {
+            state: self.snapshot_state(),
+            sender: self.comms.local_peer_id(),
+        };
+        let result = match self.consensus.sign(DEFAULT_SESSION, msg) {
+            Ok(signed) => self.comms.send_direct(addr, &signed).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            self.record_error(&e);
+            eprintln!("[热备] 复制状态到 {} 失败: {:?}", addr, e);
+        }
+    }
+
+    fn snapshot_state(&self) -> PersistedState {
+        PersistedState {
+            model: self
+                .default_session()
+                .inference
+                .as_ref()
+                .map(|m| m.checkpoint()),
+            session_models: self
+                .sessions
+                .iter()
+                .filter(|s| !s.is_default())
+                .filter_map(|s| Some((s.id.clone(), s.inference.as_ref()?.checkpoint())))
+                .collect(),
+            ledger: self.consensus.export_ledger(),
+            address_book: self.comms.export_address_book(),
+            key_pins: self.consensus.export_pins(),
+            hyperparams: self
+                .hyperparam_boards
+                .iter()
+                .filter_map(|(session, board)| Some((session.clone(), board.finalized()?.clone())))
+                .collect(),
+            last_round: self.tick_counter,
+            ..Default::default()
+        }
+    }
+
+    /// 同一轮产生的多条消息合并为一个 bundle，只签名、广播一次
+    async fn publish_batch(&mut self, session: &str, mut messages: Vec<GgsMessage>) -> Result<()> {
+        let heartbeats = messages
+            .iter()
+            .filter(|m| matches!(m, GgsMessage::Heartbeat { .. }))
+            .count();
+        let probes = messages
+            .iter()
+            .filter(|m| matches!(m, GgsMessage::SimilarityProbe { .. }))
+            .count();
+        let payload = match messages.len() {
+            0 => return Ok(()),
+            1 => messages.remove(0),
+            _ => GgsMessage::TickBundle {
+                messages,
+                sender: self.comms.local_peer_id(),
+            },
+        };
+        self.publish_signed(session, payload).await?;
+        for _ in 0..heartbeats {
+            self.stats.record_heartbeat_sent();
+        }
+        for _ in 0..probes {
+            self.stats.record_probe_sent();
+        }
+        Ok(())
+    }
+
+    fn train_step(&mut self) {
+        self.tick_counter = self.tick_counter.wrapping_add(1);
+        self.stats.increment_tick();
+        if !self.role.trains_locally() {
+            return;
+        }
+        let own = self.device_manager.get().compute_capability().tflops;
+        for session in &self.sessions {
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            // 算力强于会话中位数的节点每周期多跑几步
+            let relative = workload::relative_compute(own, &self.monitor.trainer_compute(&session.id));
+            for _ in 0..workload::local_steps(relative) {
+                model.local_train_step();
+            }
+        }
+    }
+
+    /// 任务不要求租约，或发送者持有有效租约
+    fn holds_lease(&self, session: &str, trainer: &str) -> bool {
+        match self.job_spec(session) {
+            Some(job) if !job.lease_issuers.is_empty() => self
+                .leases
+                .active(session, trainer, unix_now_millis())
+                .is_some(),
+            _ => true,
+        }
+    }
+
+    /// 从合并队列取出一批更新合并进模型
+    async fn drain_apply_queue(&mut self) {
+        for _ in 0..self.apply_queue.batch() {
+            let Some((session_id, sender, (update, weight, ack))) = self.apply_queue.pop() else {
+                break;
+            };
+            let Some(session) = self.session(&session_id) else {
+                continue;
+            };
+            let Some(model) = session.inference.as_ref() else {
+                continue;
+            };
+            let before = 1.0 - model.convergence_score();
+            let clamped = model.apply_sparse_update(&update, self.merge_coefficient(&session.id) * weight);
+            self.stats.record_params_clamped(clamped);
+            let delta = 1.0 - model.convergence_score() - before;
+            println!(
+                "应用来自 {} 的稀疏更新 (信任权重 {:.2})",
+                self.peer_label(&sender),
+                weight
+            );
+            self.record_update(
+                &session,
+                &sender,
+                UpdateKind::Sparse,
+                update.version,
+                &update.values,
+                Some(delta),
+            );
+            // 合并之后才确认，排队期间被丢弃的更新由对方重发
+            if ack {
+                self.ack_update(&session.id, &sender, update.seq).await;
+            }
+        }
+    }
+
+    /// 会话对应的训练任务（本节点发布的优先，其次是已发现的）
+    fn job_spec(&self, session: &str) -> Option<JobSpec> {
+        // 本节点自己发布的任务以本地描述为准，不被他人抢先广播的同 id 任务覆盖
+        self.job_config
+            .announce
+            .iter()
+            .cloned()
+            .chain(self.jobs.list())
+            .find(|job| job.job_id == session)
+    }
+
+    /// 重新计算本节点负责的数据集分片，变化时记录日志：
+    /// 任务要求租约时取租约中的分片，附带分片清单时按节点身份与纪元分配，否则按会话内训练者的算力切分
+    fn update_shard_assignments(&mut self) {
+        if !self.role.trains_locally() {
+            return;
+        }
+        let peer_id = self.comms.local_peer_id();
+        let own = self.device_manager.get().compute_capability().tflops;
+        let now_ms = unix_now_millis();
+        for session in &self.sessions {
+            let Some(job) = self
+                .job_spec(&session.id)
+                .filter(|job| job.dataset.shard_count() > 0)
+            else {
+                continue;
+            };
+            let dataset = &job.dataset;
+            let total = dataset.shard_count();
+            let shards: Vec<u32> = if !job.lease_issuers.is_empty() {
+                // 没有有效租约时保留原分配，更新会被其他节点忽略
+                match self.leases.active(&session.id, &peer_id, now_ms) {
+                    Some(lease) => lease.shards.clone(),
+                    None => continue,
+                }
+            } else {
+                let mut nodes = self.monitor.trainer_compute(&session.id);
+                nodes.push((peer_id.clone(), own));
+                let beacon = self.beacon_seed(&session.id);
+                match dataset
+                    .assign(&session.id, &nodes, unix_now_secs(), beacon.as_ref())
+                    .remove(&peer_id)
+                {
+                    Some(shards) => shards,
+                    None if dataset.manifest.is_empty() => continue,
+                    None => Vec::new(),
+                }
+            };
+            if self.shard_assignments.get(&session.id) != Some(&shards) {
+                let trainers = self.monitor.trainer_compute(&session.id).len() + 1;
+                if dataset.manifest.is_empty() {
+                    println!(
+                        "[分片] [{}] 本节点负责分片 {}..{} / {} ({} 个训练者)",
+                        session.id,
+                        shards.first().copied().unwrap_or(0),
+                        shards.last().map_or(0, |s| s + 1),
+                        total,
+                        trainers
+                    );
+                } else {
+                    println!(
+                        "[分片] [{}] 本节点负责分片 {:?} / {}，共 {} 个样本 ({} 个训练者)",
+                        session.id,
+                        shards,
+                        total,
+                        sample_count(&dataset.manifest, &shards),
+                        trainers
+                    );
+                }
+                self.shard_assignments.insert(session.id.clone(), shards);
+            }
+        }
+    }
+
+    /// 本节点是任务的租约发放者时，为会话内的训练者签发当前纪元的租约：
+    /// 纪元或分配变化时立即签发，否则在租约过半时续发
+    async fn grant_leases(&mut self) -> Result<()> {
+        if self.role != NodeRole::Aggregator {
+            return Ok(());
+        }
+        let peer_id = self.comms.local_peer_id();
+        let now_ms = unix_now_millis();
+        for session in self.sessions.clone() {
+            let Some(job) = self
+                .job_spec(&session.id)
+                .filter(|job| job.lease_issuers.contains(&peer_id))
+            else {
+                continue;
+            };
+            let dataset = &job.dataset;
+            let epoch = dataset.epoch_at(unix_now_secs());
+            let nodes = self.monitor.trainer_compute(&session.id);
+            let beacon = self.beacon_seed(&session.id);
+            let plan = dataset.assign(&session.id, &nodes, unix_now_secs(), beacon.as_ref());
+            let renew_after = lease_expiry(epoch, dataset.epoch_secs, now_ms).saturating_sub(now_ms) / 2;
+            if self.lease_grants.get(&session.id).is_some_and(|issued| {
+                issued.epoch == epoch
+                    && issued.shards == plan
+                    && (issued.granted_at.elapsed().as_millis() as u64) < renew_after
+            }) {
+                continue;
+            }
+            let expires_at_ms = lease_expiry(epoch, dataset.epoch_secs, now_ms);
+            let leases: Vec<TrainingLease> = plan
+                .iter()
+                .map(|(trainer, shards)| TrainingLease {
+                    session: session.id.clone(),
+                    trainer: trainer.clone(),
+                    epoch,
+                    shards: shards.clone(),
+                    expires_at_ms,
+                })
+                .collect();
+            for lease in &leases {
+                self.leases.grant(&peer_id, &job.lease_issuers, lease.clone(), now_ms);
+            }
+            if !leases.is_empty() {
+                println!(
+                    "[租约] [{}] 为 {} 个训练者签发纪元 {} 的租约",
+                    session.id,
+                    leases.len(),
+                    epoch
+                );
+                let msg = GgsMessage::LeaseGrant {
+                    leases,
+                    sender: peer_id.clone(),
+                };
+                self.publish_signed(&session.id, msg).await?;
+            }
+            self.lease_grants.insert(
+                session.id.clone(),
+                IssuedLeases {
+                    epoch,
+                    shards: plan,
+                    granted_at: Instant::now(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// 会话最近确定的信标值，未启用信标或尚未确定任何一轮时为 None
+    fn beacon_seed(&self, session: &str) -> Option<[u8; 32]> {
+        self.beacons.get(session).and_then(BeaconChain::seed)
+    }
+
+    /// 进入新的信标纪元时揭示上次提交的秘密、提交新秘密并广播份额，每个 (纪元, 检查点) 只签一次。
+    /// 其他节点只接受已确认质押的份额，本节点未发布质押证明时不参与
+    async fn contribute_beacon(&mut self) -> Result<()> {
+        if !self.beacon.enabled
+            || self
+                .stake_prover
+                .as_ref()
+                .and_then(|p| p.current())
+                .is_none()
+        {
+            return Ok(());
+        }
+        let epoch = self.beacon.epoch_at(unix_now_secs());
+        let peer_id = self.comms.local_peer_id();
+        let threshold = self.beacon.threshold;
+        for session in self.sessions.clone() {
+            let chain = self
+                .beacons
+                .entry(session.id.clone())
+                .or_insert_with(|| BeaconChain::new(&session.id, threshold));
+            let checkpoint = chain.checkpoint();
+            if chain.epoch().is_some_and(|latest| latest >= epoch)
+                || self.beacon_shares.get(&session.id) == Some(&(epoch, checkpoint.clone()))
+            {
+                continue;
+            }
+            let (commit, reveal) = chain.next_secret(&peer_id);
+            let signature = self.consensus.sign_detached(&share_bytes(
+                &session.id,
+                epoch,
+                &checkpoint,
+                &commit,
+                &reveal,
+            ))?;
+            let share = BeaconShare {
+                signer: peer_id.clone(),
+                commit: commit.clone(),
+                reveal: reveal.clone(),
+                signature: signature.clone(),
+            };
+            let round = chain.add_share(epoch, &checkpoint, share);
+            self.beacon_shares
+                .insert(session.id.clone(), (epoch, checkpoint.clone()));
+            let msg = GgsMessage::BeaconShare {
+                epoch,
+                checkpoint,
+                commit,
+                reveal,
+                signature,
+                sender: peer_id.clone(),
+            };
+            self.publish_signed(&session.id, msg).await?;
+            if let Some(round) = round {
+                self.publish_beacon_round(&session.id, round).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 采纳本节点凑齐门限的一轮并广播
+    async fn publish_beacon_round(&mut self, session: &str, round: BeaconRound) -> Result<()> {
+        let Some(chain) = self.beacons.get_mut(session) else {
+            return Ok(());
+        };
+        if !chain.accept(round.clone()) {
+            return Ok(());
+        }
+        println!(
+            "[信标] [{}] 纪元 {} 凑齐 {} 个份额，信标 {}",
+            session,
+            round.epoch,
+            round.shares.len(),
+            hex::encode(&round.value()[..8])
+        );
+        self.update_shard_assignments();
+        let msg = GgsMessage::BeaconRound {
+            round,
+            sender: self.comms.local_peer_id(),
+        };
+        self.publish_signed(session, msg).await
+    }
+
+    /// 根据 gossip 节点数推进就绪状态机
+    fn update_readiness(&self) {
+        let (previous, current) = self.readiness.observe(self.comms.gossip_peer_count());
+        if previous != current {
+            match current {
+                ReadinessState::Ready => println!("[就绪] gossip mesh 已形成，开始发布"),
+                ReadinessState::ReadyAlone => {
+                    println!("[就绪] 等待 gossip 节点超时，以单节点模式开始训练")
+                }
+                ReadinessState::WaitingForPeers => {}
+            }
+        }
+    }
+
+    /// 随心跳周期执行的维护工作：清理账本、更新统计、检查拓扑
+    fn on_heartbeat_tick(&mut self) {
+        self.heartbeat_counter = self.heartbeat_counter.wrapping_add(1);
+        self.update_readiness();
+        let evicted = self.consensus.prune_stale();
+        self.save_ledger(&evicted);
+        if let Some(store) = &self.ledger_store {
+            if self.last_ledger_compaction.elapsed() >= store.compact_interval() {
+                match store.compact(unix_now_millis()) {
+                    Ok(0) => {}
+                    Ok(n) => println!("[账本] 压缩持久化账本，删除 {} 条过期记录", n),
+                    Err(e) => eprintln!("[账本] 压缩持久化账本失败: {:?}", e),
+                }
+                self.last_ledger_compaction = Instant::now();
+            }
+        }
+        self.jobs.prune_stale();
+        self.monitor.prune_stale();
+        self.leases.prune(unix_now_millis());
+        self.update_shard_assignments();
+
+        // 更新连接的节点数量
+        let (primary, _backups) = self.default_session().topology.neighbor_sets();
+        self.stats.update_connected_peers(primary.len());
+        self.comms.flush_publish_queue();
+        self.comms.set_application_scores(&self.consensus.application_scores());
+        self.stats.update_publish_queue(self.comms.publish_queue_stats());
+        self.stats.update_apply_queue(self.apply_queue.stats());
+        self.stats.update_rewards(self.rewards.stats());
+
+        // 每 10 个心跳周期输出统计摘要
+        if self.heartbeat_counter.is_multiple_of(10) {
+            let summary = self.stats.get_summary();
+            println!("{}", summary.format());
+            for session in &self.sessions {
+                let Some(model) = &session.inference else {
+                    continue;
+                };
+                println!(
+                    "  [{}] 收敛度: {:.3} | 参数变化: {:.6} | 标准差: {:.6}",
+                    session.id,
+                    model.convergence_score(),
+                    model.parameter_change_magnitude(),
+                    model.parameter_std_dev()
+                );
+            }
+        }
+
+        for session in &self.sessions {
+            let (primary, backups) = session.topology.neighbor_sets();
+            self.monitor.update_topology(
+                &session.id,
+                primary,
+                backups,
+                session.topology.peer_metas(),
+            );
+            check_topology_health(session);
+        }
+    }
+
+    async fn handle_transport_event(&mut self, event: TransportEvent) -> Result<()> {
+        match event {
+            TransportEvent::Gossip {
+                signed,
+                source,
+                propagation,
+                message_id,
+            } => {
+                self.record_trace(
+                    TraceChannel::Gossip {
+                        source: source.clone(),
+                        propagation: propagation.clone(),
+                    },
+                    &signed,
+                );
+                self.process_gossip(
+                    signed,
+                    source.as_deref(),
+                    propagation,
+                    &message_id,
+                    unix_now_millis(),
+                )
+                .await
+            }
+            TransportEvent::Direct { signed, from } => {
+                self.handle_direct_message(signed, &from).await
+            }
+            TransportEvent::SyncRequest(inbound) => {
+                self.answer_sync(inbound);
+                Ok(())
+            }
+            TransportEvent::SyncReply { peer, result } => {
+                self.apply_sync_reply(&peer, result);
+                Ok(())
+            }
+        }
+    }
+
+    async fn publish_signed(&mut self, session: &str, payload: GgsMessage) -> Result<()> {
+        if self.replaying {
+            return Ok(());
+        }
+        let signed = self.consensus.sign(session, payload)?;
+        if self.comms.gossip_peer_count() == 0 {
+            // 断网时只保留本地稀疏更新，心跳、探测等时效性消息直接丢弃
+            if matches!(signed.payload, GgsMessage::SparseUpdate { .. }) {
+                self.offline.push(signed, unix_now_secs())?;
+                println!("[离线] 暂无可用节点，稀疏更新已加入离线队列 ({} 条)", self.offline.len());
+            }
+            return Ok(());
+        }
+        let both = self.comms.send_policy() == SendPolicy::Both;
+        if !signed.payload.is_realtime() {
+            self.publish_gossip(&signed)?;
+            if both && !self.comms.broadcast_realtime(&signed).await {
+                println!("[FAILOVER] QUIC 广播失败，已回落到纯 Gossip");
+            }
+            return Ok(());
+        }
+        // 模型数据只经 QUIC 发给拓扑邻居，没有已知地址的邻居时只走 gossip；
+        // 稀疏更新只发给按信任分抽取的 fanout 个邻居，有信标时以信标与更新序号派生抽样种子
+        let targets = self
+            .session(session)
+            .map(|s| {
+                let neighbors = self.quic_neighbors(&s);
+                let GgsMessage::SparseUpdate { update, sender } = &signed.payload else {
+                    return neighbors;
+                };
+                let mut rng = match self.beacon_seed(&s.id) {
+                    Some(seed) => derive_rng(&seed, &format!("fanout:{}:{}", sender, update.seq)),
+                    None => StdRng::from_entropy(),
+                };
+                s.topology.fanout_targets(neighbors, &mut rng)
+            })
+            .unwrap_or_default();
+        let delivered = !targets.is_empty() && self.comms.send_realtime(&signed, &targets).await;
+        if !targets.is_empty() && !delivered {
+            println!("[FAILOVER] QUIC 邻居发送失败，已回落到纯 Gossip");
+        }
+        // 只走 QUIC 的策略下，邻居已收到就不再经 gossip 重复发布
+        if both || !delivered {
+            self.publish_gossip(&signed)?;
+        }
+        Ok(())
+    }
+
+    fn publish_gossip(&mut self, signed: &SignedGossip) -> Result<()> {
+        if let Err(e) = self.comms.publish(signed) {
+            self.record_error(&e);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// 从断网恢复后重放离线队列，重新签名以刷新时间戳
+    async fn replay_offline_queue(&mut self) -> Result<()> {
+        let connected = self.comms.gossip_peer_count() > 0;
+        let reconnected = connected && !self.was_connected;
+        self.was_connected = connected;
+        if !reconnected || self.offline.len() == 0 {
+            return Ok(());
+        }
+        let (fresh, expired) = self.offline.drain_fresh(unix_now_secs())?;
+        println!(
+            "[离线] 网络已恢复，重放 {} 条离线更新，丢弃 {} 条过期更新",
+            fresh.len(),
+            expired
+        );
+        for signed in fresh {
+            if self.session(&signed.session).is_none() {
+                continue;
+            }
+            self.publish_signed(&signed.session, signed.payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_signed_message(
+        &mut self,
+        session: &Arc<Session>,
+        signed: SignedGossip,
+        source: String,
+    ) -> Result<()> {
+        match signed.payload {
+            GgsMessage::TickBundle { messages, sender } => {
+                for message in messages {
+                    // bundle 只能由发送者本人打包，且不允许嵌套
+                    if matches!(message, GgsMessage::TickBundle { .. })
+                        || message.sender() != sender
+                    {
+                        eprintln!("忽略 {} 的非法 bundle 条目", sender);
+                        continue;
+                    }
+                    self.handle_message(session, message, &source).await?;
+                }
+                Ok(())
+            }
+            payload => self.handle_message(session, payload, &source).await,
+        }
+    }
+
+    async fn handle_message(
+        &mut self,
+        session: &Arc<Session>,
+        payload: GgsMessage,
+        source: &str,
+    ) -> Result<()> {
+        match &payload {
+            GgsMessage::Heartbeat {
+                peer,
+                model_hash,
+                model_version,
+                role,
+                quic_addr,
+                compute,
+                stake_proof,
+                chunk_hashes,
+                codecs,
+                streak,
+                quic_addrs,
+                listen_addrs,
+                base_hash,
+            } => {
+                let mut addrs: Vec<std::net::SocketAddr> = quic_addr.iter().copied().collect();
+                for addr in quic_addrs {
+                    if !addrs.contains(addr) {
+                        addrs.push(*addr);
+                    }
+                }
+                let quic_addr = addrs.first().copied();
+                self.comms.record_peer_codecs(peer, quic_addr, codecs);
+                self.comms.learn_peer_addrs(peer, listen_addrs);
+                if !addrs.is_empty() {
+                    self.quic_peers.insert(peer.clone(), (addrs, Instant::now()));
+                }
+                match (role, quic_addr) {
+                    (NodeRole::Aggregator, Some(addr)) => {
+                        self.known_aggregators
+                            .insert(session.id.clone(), (peer.clone(), addr, Instant::now()));
+                    }
+                    _ => {
+                        if self
+                            .known_aggregators
+                            .get(&session.id)
+                            .is_some_and(|(known, _, _)| known == peer)
+                        {
+                            self.known_aggregators.remove(&session.id);
+                        }
+                    }
+                }
+                self.monitor
+                    .observe_heartbeat(&session.id, peer, model_hash, *model_version, *role, *compute);
+                if let Some(hashes) = chunk_hashes {
+                    self.localize_divergence(session, peer, model_hash, hashes);
+                }
+                // 探测到达前按模型谱系给出粗略相似度，拓扑冷启动时即可排序
+                if let Some(model) = session.inference.as_ref() {
+                    let same_lineage = model.in_lineage(model_hash)
+                        || base_hash.as_deref().is_some_and(|h| model.in_lineage(h));
+                    session.topology.seed_peer(peer, same_lineage, self.role.neighbor_bonus(*role));
+                }
+                self.consensus.update_stake(peer, 0.0, 0.0, 0.05);
+                self.consensus.observe_heartbeat(
+                    peer,
+                    *streak,
+                    self.scheduler.effective_interval(PeriodicTask::Heartbeat),
+                );
+                if let Some(proof) = stake_proof {
+                    self.apply_stake_proof(peer, proof);
+                }
+                self.trust.observe_heartbeat(
+                    peer,
+                    self.scheduler.effective_interval(PeriodicTask::Heartbeat),
+                );
+                self.stats.record_heartbeat_received(peer);
+                if let Some(addr) = quic_addr {
+                    self.request_syncs(session, peer, *model_version, addr);
+                }
+                println!("收到 {} ({:?}) 的心跳 (via {source})", self.peer_label(peer), role);
+            }
+            GgsMessage::SimilarityProbe {
+                embedding,
+                position,
+                sender,
+                role,
+                reprobe,
+            } => {
+                self.stats.record_probe_received(sender);
+                if *reprobe && source == "quic" && !self.replaying && self.role.sends_probes() {
+                    self.send_probe(session, sender, false).await;
+                }
+                // 轻量会话没有本地嵌入，只按地理位置评估邻居
+                let self_embedding = session
+                    .inference
+                    .as_ref()
+                    .map(|m| m.embedding())
+                    .unwrap_or_default();
+                session.topology.update_peer(
+                    sender,
+                    embedding.clone(),
+                    position.clone(),
+                    &self_embedding,
+                    self.role.neighbor_bonus(*role),
+                );
+                let trust = self.trust_score(session, sender);
+                session.topology.set_trust(sender, trust.score);
+                if let Some(identified) = self.comms.identified_peer(sender) {
+                    let addrs = identified.listen_addrs.iter().map(|a| a.to_string()).collect();
+                    session.topology.set_identity(sender, identified.agent_version, addrs);
+                }
+                if let Some(snapshot) = session.topology.peer_snapshot(sender) {
+                    println!(
+                        "拓扑更新：{} ({}) => sim {:.3}, geo {:.3}, trust {:.3}, dim {}, pos ({:.1},{:.1}), 延迟 p50/p95 {:?}/{:?} ms, 地址 {:?}",
+                        sender,
+                        snapshot.agent_version.as_deref().unwrap_or("?"),
+                        snapshot.similarity,
+                        snapshot.geo_affinity,
+                        trust.score,
+                        snapshot.embedding_dim,
+                        snapshot.position.lat,
+                        snapshot.position.lon,
+                        snapshot.latency_p50_ms,
+                        snapshot.latency_p95_ms,
+                        snapshot.addrs
+                    );
+                }
+                // 重放时不生成本地更新，避免改动模型残差；直连探测只用于刷新拓扑
+                let wants_update = !self.replaying
+                    && source != "quic"
+                    && self.role.sends_sparse_updates()
+                    && self.in_geofence(session, sender)
+                    && should_send_sparse_update(session, sender)
+                    && self.power.should_send();
+                if let Some(model) = session.inference.as_ref().filter(|_| wants_update) {
+                    if self.comms.allow_sparse_update(&session.id) {
+                        let update = model.make_sparse_update(self.power.scale_k(model.sparse_k()));
+                        let update = self
+                            .sent_updates
+                            .entry(session.id.clone())
+                            .or_default()
+                            .stamp(update);
+                        let msg = GgsMessage::SparseUpdate {
+                            update,
+                            sender: self.comms.local_peer_id(),
+                        };
+                        if !self.send_to_aggregator(&session.id, &msg).await {
+                            self.publish_signed(&session.id, msg).await?;
+                        }
+                        self.stats.record_sparse_update_sent(sender);
+                    } else {
+                        println!("[带宽限制] 本轮跳过稀疏更新");
+                    }
+                }
+            }
+            GgsMessage::SparseUpdate { sender, update } => {
+                self.stats.record_sparse_update_received(sender);
+                match self
+                    .version_vectors
+                    .entry(session.id.clone())
+                    .or_default()
+                    .observe(sender, update.seq)
+                {
+                    Observation::Duplicate => {
+                        // 直连重发说明上一次确认丢失；仍在合并队列里的等合并后再确认
+                        let queued = self
+                            .apply_queue
+                            .pending(&session.id, sender, |(u, _, _)| u.seq == update.seq);
+                        if source == "quic" && !queued {
+                            self.ack_update(&session.id, sender, update.seq).await;
+                        }
+                        return Ok(());
+                    }
+                    Observation::Gap(missing) => {
+                        println!(
+                            "[版本向量] {} 的更新 #{}..#{} 缺失，请求补发",
+                            self.peer_label(sender),
+                            missing.start,
+                            missing.end
+                        );
+                        let msg = GgsMessage::BackfillRequest {
+                            target: sender.clone(),
+                            from_seq: missing.start,
+                            to_seq: missing.end,
+                            sender: self.comms.local_peer_id(),
+                        };
+                        self.publish_signed(&session.id, msg).await?;
+                    }
+                    Observation::Fresh => {}
+                }
+                if !self.in_geofence(session, sender) {
+                    println!("[地理围栏] 忽略围栏外节点 {} 的稀疏更新", sender);
+                    return Ok(());
+                }
+                if !self.passes_token_gate(sender) {
+                    println!("[代币门槛] 忽略未达门槛节点 {} 的稀疏更新", self.peer_label(sender));
+                    return Ok(());
+                }
+                if !self.holds_lease(&session.id, sender) {
+                    println!("[租约] 忽略无有效租约节点 {} 的稀疏更新", self.peer_label(sender));
+                    return Ok(());
+                }
+                self.trust.observe_update_norm(sender, l2_norm(&update.values));
+                let trust = self.trust_score(session, sender);
+                if !self.trust.admit_update(sender, &trust) {
+                    println!(
+                        "[信任] {} 超出更新配额 ({}/窗口, 信任分 {:.2})，忽略稀疏更新",
+                        self.peer_label(sender),
+                        self.trust.quota(&trust),
+                        trust.score
+                    );
+                    return Ok(());
+                }
+                self.consensus.update_stake(sender, 0.1, 0.0, 0.1);
+                self.credit_contribution(sender);
+                let model = session
+                    .inference
+                    .as_ref()
+                    .filter(|_| self.role.merges_updates());
+                if self.role == NodeRole::Aggregator {
+                    // 聚合者先缓存，窗口结束时统一合并
+                    self.aggregators
+                        .entry(session.id.clone())
+                        .or_insert_with(|| UpdateAggregator::new(self.aggregation))
+                        .push(sender, update.clone());
+                    self.record_update(
+                        session,
+                        sender,
+                        UpdateKind::Sparse,
+                        update.version,
+                        &update.values,
+                        None,
+                    );
+                    if source == "quic" {
+                        self.ack_update(&session.id, sender, update.seq).await;
+                    }
+                } else if model.is_some() {
+                    // 进入合并队列，由事件循环分批合并；增量不可互相取代，逐条排队
+                    let weight = self.trust.merge_weight(&trust);
+                    let ack = source == "quic";
+                    let shed = self.apply_queue.push(
+                        &session.id,
+                        sender,
+                        trust.score,
+                        (update.clone(), weight, ack),
+                    );
+                    if let Some((victim, (dropped, _, true))) = shed {
+                        // 未确认的更新会被重发，重发时按新更新接收
+                        self.version_vectors
+                            .entry(session.id.clone())
+                            .or_default()
+                            .forget(&victim, dropped.seq);
+                    }
+                }
+            }
+            GgsMessage::DenseSnapshot { snapshot, sender }
+            | GgsMessage::DenseSlice { snapshot, sender } => {
+                self.handle_dense_snapshot(session, sender, snapshot);
+            }
+            GgsMessage::ShareManifest { manifest, sender } => {
+                let assembler = self.share_assemblers.entry(session.id.clone()).or_default();
+                let rebuilt = assembler.manifest(sender, manifest.clone());
+                let relays = assembler.take_relays(sender, &manifest.hash);
+                for share in relays {
+                    self.relay_share(&session.id, share).await?;
+                }
+                if let Some((origin, snapshot)) = rebuilt {
+                    self.handle_dense_snapshot(session, &origin, &snapshot);
+                }
+            }
+            GgsMessage::SnapshotShare { share, sender } => {
+                let assembler = self.share_assemblers.entry(session.id.clone()).or_default();
+                // 只转发快照发送方本人直连交来、且与其签名清单一致的分片
+                if source == "quic" && share.origin == *sender {
+                    if let Some(share) = assembler.relay(share.clone()) {
+                        self.relay_share(&session.id, share).await?;
+                    }
+                }
+                let rebuilt = self
+                    .share_assemblers
+                    .entry(session.id.clone())
+                    .or_default()
+                    .share(share.clone());
+                if let Some((origin, snapshot)) = rebuilt {
+                    println!(
+                        "[纠删码] 由分片还原 {} 的模型快照 v{}",
+                        self.peer_label(&origin),
+                        snapshot.version
+                    );
+                    self.handle_dense_snapshot(session, &origin, &snapshot);
+                }
+            }
+            GgsMessage::JobAnnounce { job, sender } => {
+                // 任务只在默认主题上广播
+                if !session.is_default() || job.validate().is_err() {
+                    return Ok(());
+                }
+                if self.jobs.observe(job.clone(), sender) {
+                    println!(
+                        "[任务] 发现任务 {} (发布者 {}, 数据集 {}, 奖励 {} ETH / {} SOL 每轮)",
+                        job.job_id,
+                        sender,
+                        job.dataset.name,
+                        job.reward.eth_per_round,
+                        job.reward.sol_per_round
+                    );
+                    self.maybe_join_job(job)?;
+                }
+            }
+            GgsMessage::KeyAnnounce {
+                eth_address,
+                sol_pubkey,
+                pow_nonce,
+                sender,
+            } => {
+                if self.consensus.is_admitted(sender) {
+                    return Ok(());
+                }
+                match self.consensus.admit(sender, eth_address, sol_pubkey, *pow_nonce) {
+                    Ok(()) => println!("[准入] {} 的工作量证明通过", sender),
+                    Err(e) => eprintln!("[准入] 拒绝 {} 的密钥宣告: {:?}", sender, e),
+                }
+            }
+            GgsMessage::PeerMeta { meta, sender } => {
+                if let Err(e) = meta.validate() {
+                    eprintln!("[元数据] 拒绝 {} 的元数据: {}", sender, e);
+                    return Ok(());
+                }
+                if session.topology.peer_meta(sender).as_ref() != Some(meta) {
+                    println!(
+                        "[元数据] {} => {} / {} / {}",
+                        self.peer_label(sender),
+                        meta.nickname,
+                        meta.organization,
+                        meta.region
+                    );
+                }
+                session.topology.update_meta(sender, meta.clone());
+            }
+            GgsMessage::IdentityMigration {
+                previous_peer,
+                sender,
+            } => {
+                if self.consensus.migrate_identity(previous_peer, sender) {
+                    println!("[声誉迁移] {} 的账本记录已迁移到 {}", previous_peer, sender);
+                }
+            }
+            GgsMessage::EvalResult {
+                epoch,
+                results,
+                sender,
+                ..
+            } => {
+                if !self.evaluation.is_enabled() {
+                    return Ok(());
+                }
+                // 只接受按本节点看到的训练者集合分给该发送者的分片
+                let assigned = self
+                    .eval_assignment(&session.id, *epoch)
+                    .remove(sender)
+                    .unwrap_or_default();
+                self.eval_boards
+                    .entry(session.id.clone())
+                    .or_default()
+                    .record(*epoch, sender, results, &assigned);
+            }
+            GgsMessage::IHave {
+                hash,
+                version,
+                size,
+                sender,
+            } => {
+                // 没有 QUIC 的节点无法接收直连快照
+                let Some(quic_addr) = self.comms.quic_advertise() else {
+                    return Ok(());
+                };
+                let interested = session.inference.is_some()
+                    && self.role.merges_updates()
+                    && self.training_window_open
+                    && self.in_geofence(session, sender)
+                    && self.passes_token_gate(sender);
+                if !interested || !self.dense_wants.want(hash, sender) {
+                    return Ok(());
+                }
+                println!(
+                    "[惰性推拉] 向 {} 请求模型快照 v{} ({} 字节)",
+                    self.peer_label(sender),
+                    version,
+                    size
+                );
+                let msg = GgsMessage::IWant {
+                    target: sender.clone(),
+                    hash: hash.clone(),
+                    quic_addr,
+                    sender: self.comms.local_peer_id(),
+                };
+                self.publish_signed(&session.id, msg).await?;
+            }
+            GgsMessage::IWant {
+                target,
+                hash,
+                quic_addr,
+                sender,
+            } => {
+                let own = self.comms.local_peer_id();
+                if *target != own || self.replaying {
+                    return Ok(());
+                }
+                // 只向已知 QUIC 地址的节点推送，避免被诱导向任意地址发送数据
+                let known = self
+                    .quic_peers
+                    .get(sender)
+                    .is_some_and(|(addrs, _)| addrs.contains(quic_addr));
+                if !known {
+                    println!(
+                        "[惰性推拉] {} 请求发往未知地址 {}，忽略",
+                        self.peer_label(sender),
+                        quic_addr
+                    );
+                    return Ok(());
+                }
+                let Some(snapshot) = self.dense_offers.get(&session.id).and_then(|c| c.get(hash)) else {
+                    return Ok(());
+                };
+                let bytes = snapshot.values.len() * std::mem::size_of::<f32>();
+                if !self.comms.allow_dense_snapshot(&session.id, bytes) {
+                    println!("[带宽限制] 暂不响应 {} 的快照请求", self.peer_label(sender));
+                    return Ok(());
+                }
+                let model_dim = session.inference.as_ref().map_or(0, |m| m.model_dim());
+                let signed = self
+                    .consensus
+                    .sign(&session.id, GgsMessage::dense(snapshot, model_dim, own))?;
+                match self.comms.send_direct(*quic_addr, &signed).await {
+                    Ok(()) => self.stats.record_dense_snapshot_sent(),
+                    Err(e) => {
+                        self.record_error(&e);
+                        eprintln!(
+                            "[惰性推拉] 向 {} ({}) 直连发送快照失败: {:?}",
+                            self.peer_label(sender),
+                            quic_addr,
+                            e
+                        );
+                    }
+                }
+            }
+            GgsMessage::UpdateAck {
+                target,
+                seq,
+                sender,
+            } => {
+                if *target == self.comms.local_peer_id() {
+                    self.retransmit.ack(&session.id, sender, *seq);
+                }
+            }
+            GgsMessage::BackfillRequest {
+                target,
+                from_seq,
+                to_seq,
+                sender,
+            } => {
+                let own = self.comms.local_peer_id();
+                if *target != own {
+                    return Ok(());
+                }
+                if !self
+                    .backfill_limiter
+                    .admit(sender, Instant::now().into_std())
+                {
+                    println!("[版本向量] {} 的补发请求过于频繁，忽略", self.peer_label(sender));
+                    return Ok(());
+                }
+                let updates = self
+                    .sent_updates
+                    .get(&session.id)
+                    .map(|log| log.range(&(*from_seq..*to_seq)))
+                    .unwrap_or_default();
+                println!(
+                    "[版本向量] 应 {} 请求补发 #{}..#{} 中的 {} 条更新",
+                    self.peer_label(sender),
+                    from_seq,
+                    to_seq,
+                    updates.len()
+                );
+                // 经直连只发给请求方，不再走 gossip 广播
+                for update in updates {
+                    let seq = update.seq;
+                    let msg = GgsMessage::SparseUpdate {
+                        update,
+                        sender: own.clone(),
+                    };
+                    let signed = self.consensus.sign(&session.id, msg)?;
+                    if let Err(e) = self.comms.send_to(sender, &signed).await {
+                        self.record_error(&e);
+                        eprintln!("[版本向量] 向 {} 补发更新 #{} 失败: {:?}", sender, seq, e);
+                        break;
+                    }
+                }
+            }
+            GgsMessage::HyperParamUpdate { proposal, sender } => {
+                let epoch = self.hyperparams.epoch_at(unix_now_secs());
+                let label = self.peer_label(sender);
+                let board = self.hyperparam_boards.entry(session.id.clone()).or_default();
+                match board.endorse(proposal, sender, epoch) {
+                    Ok(true) => println!(
+                        "[超参数] [{}] {} 背书提议 {} ({:?}，纪元 {} 生效)",
+                        session.id, label, proposal.proposal_id, proposal.params, proposal.apply_epoch
+                    ),
+                    Ok(false) => return Ok(()),
+                    Err(e) => {
+                        eprintln!("[超参数] 拒绝 {} 的提议: {}", label, e);
+                        return Ok(());
+                    }
+                }
+                let own = self.comms.local_peer_id();
+                if self.hyperparams.auto_endorse && board.endorse(proposal, &own, epoch)? {
+                    let msg = GgsMessage::HyperParamUpdate {
+                        proposal: proposal.clone(),
+                        sender: own,
+                    };
+                    self.publish_signed(&session.id, msg).await?;
+                }
+            }
+            GgsMessage::ClusterView { members, sender } => {
+                if !self.clustering.is_enabled() {
+                    return Ok(());
+                }
+                let board = self
+                    .cluster_boards
+                    .entry(session.id.clone())
+                    .or_insert_with(|| ClusterBoard::new(self.clustering.clone()));
+                board.observe_view(sender, members.clone());
+                // 尚未聚类时无法判断发送者是否同簇，下次聚类时再合并
+                if board.current().is_some() {
+                    session
+                        .topology
+                        .set_same_cluster(board.same_cluster(&self.comms.local_peer_id()));
+                }
+            }
+            GgsMessage::LeaseGrant { leases, sender } => {
+                let Some(job) = self.job_spec(&session.id) else {
+                    return Ok(());
+                };
+                let now_ms = unix_now_millis();
+                let granted = leases
+                    .iter()
+                    .filter(|lease| lease.session == session.id)
+                    .filter(|lease| {
+                        self.leases
+                            .grant(sender, &job.lease_issuers, (*lease).clone(), now_ms)
+                    })
+                    .count();
+                if granted < leases.len() {
+                    println!(
+                        "[租约] 忽略 {} 签发的 {} 份无效租约",
+                        self.peer_label(sender),
+                        leases.len() - granted
+                    );
+                }
+                if leases.iter().any(|lease| lease.trainer == self.comms.local_peer_id()) {
+                    self.update_shard_assignments();
+                }
+            }
+            GgsMessage::BeaconShare {
+                epoch,
+                checkpoint,
+                commit,
+                reveal,
+                signature,
+                sender,
+            } => {
+                if !self.beacon.enabled || !self.beacon.admits(*epoch, unix_now_secs()) {
+                    return Ok(());
+                }
+                if !self.stake_verifier.is_confirmed(sender) {
+                    println!(
+                        "[信标] 忽略质押未确认节点 {} 的份额",
+                        self.peer_label(sender)
+                    );
+                    return Ok(());
+                }
+                let bytes = share_bytes(&session.id, *epoch, checkpoint, commit, reveal);
+                if !self.consensus.verify_detached(sender, &bytes, signature) {
+                    println!("[信标] 忽略 {} 的无效份额", self.peer_label(sender));
+                    return Ok(());
+                }
+                let threshold = self.beacon.threshold;
+                let share = BeaconShare {
+                    signer: sender.clone(),
+                    commit: commit.clone(),
+                    reveal: reveal.clone(),
+                    signature: signature.clone(),
+                };
+                let round = self
+                    .beacons
+                    .entry(session.id.clone())
+                    .or_insert_with(|| BeaconChain::new(&session.id, threshold))
+                    .add_share(*epoch, checkpoint, share);
+                if let Some(round) = round {
+                    self.publish_beacon_round(&session.id, round).await?;
+                }
+            }
+            GgsMessage::BeaconRound { round, sender } => {
+                if !self.beacon.enabled || !self.beacon.admits(round.epoch, unix_now_secs()) {
+                    return Ok(());
+                }
+                let own = self.comms.local_peer_id();
+                let consensus = &self.consensus;
+                let stakes = &self.stake_verifier;
+                // 每个份额都必须来自已确认质押的节点，未质押的女巫节点凑不齐门限
+                let valid = round.verify(
+                    &session.id,
+                    self.beacon.threshold,
+                    |peer, bytes, signature| {
+                        if peer == own {
+                            consensus.verify_own_detached(bytes, signature)
+                        } else {
+                            stakes.is_confirmed(peer)
+                                && consensus.verify_detached(peer, bytes, signature)
+                        }
+                    },
+                );
+                if !valid {
+                    println!("[信标] 忽略 {} 广播的无效信标轮次", self.peer_label(sender));
+                    return Ok(());
+                }
+                let threshold = self.beacon.threshold;
+                let adopted = self
+                    .beacons
+                    .entry(session.id.clone())
+                    .or_insert_with(|| BeaconChain::new(&session.id, threshold))
+                    .accept(round.clone());
+                if adopted {
+                    println!(
+                        "[信标] [{}] 纪元 {} 采纳 {} 广播的信标 {}",
+                        session.id,
+                        round.epoch,
+                        self.peer_label(sender),
+                        hex::encode(&round.value()[..8])
+                    );
+                    self.update_shard_assignments();
+                }
+            }
+            GgsMessage::StakeAttestation {
+                endorsement,
+                sender,
+            } => {
+                if let Some((stake_eth, stake_sol)) =
+                    self.stake_verifier.endorse(sender, endorsement)
+                {
+                    println!(
+                        "[质押证明] {} 在区块 #{} 的余额已获足够背书: {:.4} ETH / {:.4} SOL",
+                        self.peer_label(&endorsement.peer),
+                        endorsement.block_number,
+                        stake_eth,
+                        stake_sol
+                    );
+                    self.consensus
+                        .set_stake(&endorsement.peer, stake_eth, stake_sol);
+                }
+            }
+            // 状态复制只经 QUIC 直连发给热备节点，在线节点不处理
+            GgsMessage::TickBundle { .. } | GgsMessage::StateReplica { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// 某评估纪元的分片分配：本节点看到的训练者（本节点是训练者时包括自己）
+    fn eval_assignment(&self, session: &str, epoch: u64) -> HashMap<String, Vec<u32>> {
+        let mut peers: Vec<String> = self
+            .monitor
+            .trainer_compute(session)
+            .into_iter()
+            .map(|(peer, _)| peer)
+            .collect();
+        if self.role.trains_locally() {
+            peers.push(self.comms.local_peer_id());
+        }
+        assign_eval_shards(epoch, self.evaluation.shards, &peers)
+    }
+
+    /// 进入新的评估纪元时：输出上一纪元的全网评估，评估分给本节点的基准分片并广播结果
+    async fn run_evaluation(&mut self) -> Result<()> {
+        if !self.evaluation.is_enabled() || !self.role.trains_locally() {
+            return Ok(());
+        }
+        let epoch = self.evaluation.epoch_at(unix_now_secs());
+        let peer_id = self.comms.local_peer_id();
+        for session in self.sessions.clone() {
+            let Some(model) = &session.inference else {
+                continue;
+            };
+            let mut assignment = self.eval_assignment(&session.id, epoch);
+            let evaluators = assignment.len();
+            let board = self.eval_boards.entry(session.id.clone()).or_default();
+            if board.last_evaluated == Some(epoch) {
+                continue;
+            }
+            if let Some(previous) = board.last_evaluated {
+                if let Some(eval) = board.summary(previous, self.evaluation.shards) {
+                    println!(
+                        "[评估] [{}] 纪元 {} 全网损失 {:.4} ({}/{} 分片, {} 个评估者)",
+                        session.id,
+                        eval.epoch,
+                        eval.mean_loss,
+                        eval.shards_covered,
+                        eval.shards_total,
+                        eval.evaluators
+                    );
+                }
+            }
+            board.last_evaluated = Some(epoch);
+
+            let own = assignment.remove(&peer_id).unwrap_or_default();
+            if own.is_empty() {
+                continue;
+            }
+            let snapshot = model.tensor_snapshot();
+            let results: Vec<_> = own
+                .iter()
+                .map(|shard| evaluate_shard(&self.evaluation, *shard, &snapshot.values))
+                .collect();
+            board.record(epoch, &peer_id, &results, &own);
+            println!(
+                "[评估] [{}] 纪元 {} 本节点评估分片 {:?} / {} ({} 个评估者)",
+                session.id,
+                epoch,
+                own,
+                self.evaluation.shards,
+                evaluators
+            );
+            let msg = GgsMessage::EvalResult {
+                epoch,
+                model_version: snapshot.version,
+                results,
+                sender: peer_id.clone(),
+            };
+            self.publish_signed(&session.id, msg).await?;
+        }
+        Ok(())
+    }
+
+    fn handle_dense_snapshot(&mut self, session: &Session, sender: &str, snapshot: &TensorSnapshot) {
+        self.stats.record_dense_snapshot_received(sender);
+        if !self.in_geofence(session, sender) {
+            println!("[地理围栏] 忽略围栏外节点 {} 的模型快照", sender);
+            return;
+        }
+        if !self.passes_token_gate(sender) {
+            println!("[代币门槛] 忽略未达门槛节点 {} 的模型快照", self.peer_label(sender));
+            return;
+        }
+        let advertised = self.monitor.advertised_model(&session.id, sender);
+        let full = snapshot.offset == 0
+            && session
+                .inference
+                .as_ref()
+                .is_some_and(|m| m.model_dim() == snapshot.values.len());
+        let verified = self.snapshot_verifier.verify(
+            &session.id,
+            sender,
+            snapshot,
+            advertised.as_ref().map(|(hash, version)| (hash.as_str(), *version)),
+            full,
+        );
+        if let Err(e) = verified {
+            if e.is_misbehavior() {
+                self.stats.record_update_rejected();
+                eprintln!("[快照校验] 拒绝 {} 的模型快照并降低信誉: {:?}", self.peer_label(sender), e);
+                self.consensus.update_stake(sender, 0.0, 0.0, -0.2);
+                self.consensus.record_misbehavior(sender, Misbehavior::InvalidUpdate);
+            } else {
+                println!("[快照校验] 忽略 {} 的模型快照: {:?}", self.peer_label(sender), e);
+            }
+            return;
+        }
+        self.consensus.update_stake(sender, 0.0, 0.2, 0.05);
+        self.credit_contribution(sender);
+        // 训练窗口外不做密集快照融合
+        let model = session
+            .inference
+            .as_ref()
+            .filter(|_| self.role.merges_updates() && self.training_window_open);
+        let mut delta = None;
+        if let Some(model) = model {
+            let before = 1.0 - model.convergence_score();
+            let clamped = model.apply_dense_snapshot(snapshot);
+            self.stats.record_params_clamped(clamped);
+            delta = Some(1.0 - model.convergence_score() - before);
+            println!("融合 {} 的模型快照", self.peer_label(sender));
+        }
+        self.record_update(
+            session,
+            sender,
+            UpdateKind::Dense,
+            snapshot.version,
+            &snapshot.values,
+            delta,
+        );
+    }
+
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            models: self
+                .sessions
+                .iter()
+                .filter_map(|s| s.inference.as_ref())
+                .map(|m| memory::model_bytes(m.model_dim()))
+                .sum(),
+            peer_profiles: self.sessions.iter().map(|s| s.topology.memory_bytes()).sum(),
+            queues: self.offline.memory_bytes(),
+            reassembly: self.share_assemblers.values().map(|a| a.buffered_bytes()).sum(),
+            history: self.sent_updates.values().map(|l| l.memory_bytes()).sum::<usize>()
+                + self.dense_offers.values().map(|c| c.memory_bytes()).sum::<usize>(),
+        }
+    }
+
+    /// 超过内存预算高水位时按价值从低到高释放状态
+    fn enforce_memory_budget(&mut self) -> Result<()> {
+        let usage = self.memory_usage();
+        let excess = self.memory.excess(&usage);
+        if excess == 0 {
+            return Ok(());
+        }
+        let mut freed = 0;
+        for session in &self.sessions {
+            freed += session.topology.shed_profiles(excess.saturating_sub(freed));
+        }
+        for assembler in self.share_assemblers.values_mut() {
+            freed += assembler.shed(excess.saturating_sub(freed));
+        }
+        for log in self.sent_updates.values_mut() {
+            freed += log.shed(excess.saturating_sub(freed));
+        }
+        for cache in self.dense_offers.values_mut() {
+            freed += cache.shed(excess.saturating_sub(freed));
+        }
+        freed += self.offline.shed(excess.saturating_sub(freed))?;
+        println!(
+            "[内存] 估算占用 {} KiB 超过预算 {} KiB 的高水位（{:?}），已释放 {} KiB",
+            usage.total() / 1024,
+            self.memory.limit_bytes / 1024,
+            usage,
+            freed / 1024
+        );
+        Ok(())
+    }
+
+    /// 拓扑邻居中近期通过签名心跳宣告过 QUIC 地址的节点
+    fn quic_neighbors(&self, session: &Session) -> Vec<(String, std::net::SocketAddr)> {
+        let fresh_for = self.scheduler.effective_interval(PeriodicTask::Heartbeat) * 3;
+        session
+            .topology
+            .select_neighbors()
+            .into_iter()
+            .filter_map(|peer| {
+                let (addrs, seen) = self.quic_peers.get(&peer)?;
+                let addr = *addrs.first()?;
+                (seen.elapsed() <= fresh_for).then_some((peer, addr))
+            })
+            .collect()
+    }
+
+    /// 经请求 / 应答通道主动同步：尚未取得质押账本时向该邻居索取一次；
+    /// 其宣告的模型版本领先本地较多、且有足够多的其他节点同样宣告领先时，直接索取其当前快照，
+    /// 不等下一次快照广播
+    fn request_syncs(&mut self, session: &Session, peer: &str, version: u64, addr: std::net::SocketAddr) {
+        if self.replaying {
+            return;
+        }
+        if !self.ledger_synced && self.ledger_source.is_none() {
+            self.ledger_source = Some(peer.to_string());
+            self.comms.request_sync(peer, addr, SyncRequest::StakeLedger);
+        }
+        let Some(model) = session.inference.as_ref() else {
+            return;
+        };
+        let local = model.version();
+        if version < local.saturating_add(SNAPSHOT_SYNC_GAP) {
+            return;
+        }
+        let leads = self.snapshot_leads.entry(session.id.clone()).or_default();
+        leads.retain(|_, at| at.elapsed() < SNAPSHOT_SYNC_INTERVAL);
+        leads.insert(peer.to_string(), Instant::now());
+        let corroborated = leads.len() >= SNAPSHOT_SYNC_CORROBORATION;
+        let due = self
+            .snapshot_syncs
+            .get(&session.id)
+            .is_none_or(|at| at.elapsed() >= SNAPSHOT_SYNC_INTERVAL);
+        if !corroborated || !due || !self.role.merges_updates() || !self.training_window_open {
+            return;
+        }
+        self.snapshot_syncs.insert(session.id.clone(), Instant::now());
+        self.snapshot_leads.remove(&session.id);
+        println!(
+            "[同步] {} 的模型 v{} 领先本地 v{}（{} 个节点宣告领先），直接索取快照",
+            self.peer_label(peer),
+            version,
+            local,
+            SNAPSHOT_SYNC_CORROBORATION
+        );
+        let request = SyncRequest::LatestSnapshot {
+            session: session.id.clone(),
+        };
+        self.comms.request_sync(peer, addr, request);
+    }
+
+    /// 应答邻居的同步请求：模型快照计入该会话的快照带宽预算，重放时一律拒绝
+    fn answer_sync(&mut self, inbound: InboundRequest) {
+        let response = match &inbound.request {
+            _ if self.replaying => SyncResponse::Refused {
+                reason: "节点正在重放".into(),
+            },
+            SyncRequest::LatestSnapshot { session } => {
+                let snapshot = self
+                    .session(session)
+                    .and_then(|s| s.inference.as_ref().map(|m| m.tensor_snapshot()));
+                match snapshot {
+                    Some(snapshot)
+                        if !self.comms.allow_dense_snapshot(
+                            session,
+                            snapshot.values.len() * std::mem::size_of::<f32>(),
+                        ) =>
+                    {
+                        SyncResponse::Refused {
+                            reason: "快照带宽预算不足".into(),
+                        }
+                    }
+                    snapshot => {
+                        if snapshot.is_some() {
+                            self.stats.record_dense_snapshot_sent();
+                        }
+                        SyncResponse::Snapshot {
+                            session: session.clone(),
+                            snapshot,
+                        }
+                    }
+                }
+            }
+            SyncRequest::StakeLedger => SyncResponse::Ledger {
+                entries: self.consensus.export_ledger(),
+            },
+        };
+        println!("[同步] 应答 {} 的请求 {:?}", self.peer_label(&inbound.peer), inbound.request);
+        let _ = inbound.reply.send(response);
+    }
+
+    fn apply_sync_reply(&mut self, peer: &str, result: Result<SyncResponse, SyncError>) {
+        let failed = match result {
+            Ok(SyncResponse::Snapshot {
+                session,
+                snapshot: Some(snapshot),
+            }) => {
+                if let Some(session) = self.session(&session) {
+                    println!("[同步] 收到 {} 的模型快照 v{}", self.peer_label(peer), snapshot.version);
+                    self.handle_dense_snapshot(&session, peer, &snapshot);
+                }
+                false
+            }
+            Ok(SyncResponse::Snapshot { snapshot: None, .. }) => false,
+            Ok(SyncResponse::Ledger { entries }) => {
+                let backed = |p: &str| self.stake_verifier.has_proof(p);
+                let added = self.consensus.merge_ledger(entries, backed);
+                self.ledger_synced = true;
+                println!("[同步] 从 {} 的质押账本补入 {} 个节点", self.peer_label(peer), added);
+                false
+            }
+            Ok(SyncResponse::Refused { reason }) => {
+                println!("[同步] {} 拒绝同步请求: {}", self.peer_label(peer), reason);
+                true
+            }
+            Err(e) => {
+                eprintln!("[同步] 向 {} 的同步请求失败: {}", self.peer_label(peer), e);
+                true
+            }
+        };
+        if failed && !self.ledger_synced && self.ledger_source.as_deref() == Some(peer) {
+            self.ledger_source = None;
+        }
+    }
+
+    /// 节点心跳宣告的首选 QUIC 地址
+    fn quic_addr_of(&self, peer: &str) -> Option<std::net::SocketAddr> {
+        self.quic_peers.get(peer)?.0.first().copied()
+    }
+
+    /// 以本节点身份在 gossip 上转发快照发送方交来的分片
+    async fn relay_share(&mut self, session: &str, share: SnapshotShare) -> Result<()> {
+        let msg = GgsMessage::SnapshotShare {
+            share,
+            sender: self.comms.local_peer_id(),
+        };
+        self.publish_signed(session, msg).await
+    }
+
+    /// 纠删码分发：gossip 清单，各分片经 QUIC 交给不同邻居转发，没有可用邻居的分片自己发布
+    async fn broadcast_erasure_coded(
+        &mut self,
+        session: &Session,
+        snapshot: TensorSnapshot,
+    ) -> Result<()> {
+        let own = self.comms.local_peer_id();
+        let (manifest, shares) = erasure::encode(
+            &snapshot,
+            &own,
+            self.erasure.data_shards,
+            self.erasure.parity_shards,
+        )?;
+        let msg = GgsMessage::ShareManifest {
+            manifest,
+            sender: own.clone(),
+        };
+        self.publish_signed(&session.id, msg).await?;
+
+        let relays = self.quic_neighbors(session);
+        let mut relayed = 0;
+        for (i, share) in shares.into_iter().enumerate() {
+            let msg = GgsMessage::SnapshotShare {
+                share,
+                sender: own.clone(),
+            };
+            if let Some((_, addr)) = relays.get(i % relays.len().max(1)) {
+                if !self.replaying {
+                    let signed = self.consensus.sign(&session.id, msg.clone())?;
+                    if self.comms.send_direct(*addr, &signed).await.is_ok() {
+                        relayed += 1;
+                        continue;
+                    }
+                }
+            }
+            self.publish_signed(&session.id, msg).await?;
+        }
+        println!(
+            "[纠删码] 快照 v{} 编码为 {}+{} 个分片，{} 个经邻居转发",
+            snapshot.version, self.erasure.data_shards, self.erasure.parity_shards, relayed
+        );
+        Ok(())
+    }
+
+    async fn maybe_broadcast_dense(&mut self, session: &Arc<Session>) -> Result<()> {
+        if !self.power.allows_dense_broadcast() {
+            return Ok(());
+        }
+        let network_type = self.comms.network_type();
+        if !network_type.allows_dense_snapshot() {
+            // 移动网络下跳过密集快照
+            return Ok(());
+        }
+
+        let Some(model) = &session.inference else {
+            return Ok(());
+        };
+        let snapshot = model.dense_slice();
+        let bytes = snapshot.values.len() * std::mem::size_of::<f32>();
+        if self.erasure.applies_to(bytes) {
+            // 发送方上行为 (K + M) / K 倍快照大小
+            let encoded = bytes * (self.erasure.data_shards + self.erasure.parity_shards)
+                / self.erasure.data_shards;
+            if self.comms.allow_dense_snapshot(&session.id, encoded) {
+                self.broadcast_erasure_coded(session, snapshot).await?;
+                self.stats.record_dense_snapshot_sent();
+            }
+            return Ok(());
+        }
+        if self.comms.quic_advertise().is_some() {
+            // 惰性推拉：只 gossip 宣告，按 IWant 直连发送，带宽预算在发送时扣除
+            let version = snapshot.version;
+            let hash = self
+                .dense_offers
+                .entry(session.id.clone())
+                .or_default()
+                .offer(snapshot);
+            let msg = GgsMessage::IHave {
+                hash,
+                version,
+                size: bytes,
+                sender: self.comms.local_peer_id(),
+            };
+            return self.publish_signed(&session.id, msg).await;
+        }
+        if self.comms.allow_dense_snapshot(&session.id, bytes) {
+            let msg = GgsMessage::dense(snapshot, model.model_dim(), self.comms.local_peer_id());
+            self.publish_signed(&session.id, msg).await?;
+            self.stats.record_dense_snapshot_sent();
+        }
+        Ok(())
+    }
+}
+
+fn should_send_sparse_update(session: &Session, target: &str) -> bool {
+    let primary = session.topology.select_neighbors();
+    if primary.iter().any(|peer| peer == target) {
+        return true;
+    }
+    session.topology.mark_unreachable(target);
+    false
+}
+
+/// 贡献次数低于按算力估计预期一半的训练者（以本窗口训练者的平均贡献为基准）
+fn lagging_trainers(trainers: &[(String, f32)], contributions: &HashMap<String, u32>) -> Vec<String> {
+    if trainers.is_empty() || contributions.is_empty() {
+        return Vec::new();
+    }
+    let base = contributions.values().sum::<u32>() as f32 / trainers.len() as f32;
+    let mut lagging: Vec<String> = trainers
+        .iter()
+        .filter(|(peer, tflops)| {
+            let others: Vec<_> = trainers.iter().filter(|(p, _)| p != peer).cloned().collect();
+            let relative = workload::relative_compute(*tflops, &others);
+            let expected = workload::expected_updates(relative, base);
+            (contributions.get(peer).copied().unwrap_or(0) as f32) < expected * 0.5
+        })
+        .map(|(peer, _)| peer.clone())
+        .collect();
+    lagging.sort();
+    lagging
+}
+
+fn check_topology_health(session: &Session) {
+    let topology = &session.topology;
+    let (primary, backups) = topology.neighbor_sets();
+    if primary.len() < topology.max_neighbors() && !backups.is_empty() {
+        println!(
+            "[拓扑 Failover] [{}] 主邻居 {}/{}，启用备份 {:?}",
+            session.id,
+            primary.len(),
+            topology.max_neighbors(),
+            backups
+        );
+    } else if backups.len() < topology.failover_pool() {
+        println!(
+            "[拓扑提示] [{}] 备份邻居不足 {}/{}",
+            session.id,
+            backups.len(),
+            topology.failover_pool()
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 离线子命令在这里执行完毕；run（或不带子命令）继续解析节点参数
+    let Some(args) = cli::dispatch(std::env::args().collect())? else {
+        return Ok(());
+    };
+    let cli::NodeArgs {
+        config_file,
+        stats_output,
+        node_id,
+        model_dim,
+        heartbeat_secs,
+        probe_secs,
+        train_secs,
+        snapshot_secs,
+        persist_secs,
+        announce_secs,
+        jitter_ratio,
+        admin_bind,
+        state_dir,
+        session_specs,
+        job_files,
+        no_auto_join,
+        role,
+        light,
+        quic_advertise,
+        aggregation,
+        apply_queue_capacity,
+        apply_shedding,
+        position,
+        geofence_km,
+        geofence_prefixes,
+        token_requirement,
+        rewards_contract,
+        chain_id,
+        rewards_interval_secs,
+        stake_proofs,
+        sol_rpc,
+        stake_attest_quorum,
+        heartbeat_chunks,
+        wire_codecs,
+        gossip_codec,
+        transports,
+        no_tcp_fallback,
+        send_policy,
+        proxy_url,
+        proxy_no_mdns,
+        max_inbound,
+        max_outbound,
+        eval_shards,
+        eval_epoch_secs,
+        eval_seed,
+        watchdog_deadline_secs,
+        memory_budget_mb,
+        replicate_to,
+        standby_listen,
+        standby_takeover_secs,
+        ack_retries,
+        clusters,
+        cluster_interval_secs,
+        cross_cluster_fraction,
+        fanout,
+        propose_hyperparams,
+        endorse_hyperparams,
+        hyperparam_committee,
+        hyperparam_elected,
+        hyperparam_quorum,
+        epoch_schedule,
+        hyperparam_epoch_secs,
+        beacon_threshold,
+        beacon_epoch_secs,
+        erasure: erasure_shards,
+        erasure_min_bytes,
+        analytics_db,
+        trace_record,
+        replay,
+        seed,
+        layers,
+        analytics_retention_days,
+        ledger_db,
+        ledger_retention_days,
+        rate_limit,
+        reputation_import,
+        sparse_k,
+        fixed_sparse_k,
+        pow_difficulty,
+        eth_rpc,
+        sns_api,
+        nickname,
+        organization,
+        region_label,
+        contact,
+        train_windows,
+        utc_offset,
+        power_saver,
+        offline_max_age,
+        listen_addr,
+        external_addrs,
+        bootstrap_peers,
+        rendezvous_points,
+        rendezvous_server,
+        dht_bootstrap,
+        no_dht,
+        no_dual_stack,
+        accept_legacy_timestamps,
+        circuit_relays,
+        relay_server,
+        relay_url,
+        relay_token,
+        topic,
+        checkpoint_dir,
+        checkpoint_interval,
+        quic_bind,
+        identity_path,
+        keystore,
+        max_neighbors,
+    } = args;
+    let auto_join = !no_auto_join;
+    let heartbeat_chunks = heartbeat_chunks.unwrap_or(0);
+    let intervals: Vec<(PeriodicTask, u64)> = [
+        (PeriodicTask::Heartbeat, heartbeat_secs),
+        (PeriodicTask::Probe, probe_secs),
+        (PeriodicTask::Train, train_secs),
+        (PeriodicTask::Snapshot, snapshot_secs),
+        (PeriodicTask::Persist, persist_secs),
+        (PeriodicTask::Announce, announce_secs),
+    ]
+    .into_iter()
+    .filter_map(|(task, secs)| Some((task, secs?)))
+    .collect();
+    let mut apply_queue = ApplyQueueConfig::default();
+    if let Some(capacity) = apply_queue_capacity {
+        apply_queue.capacity = capacity;
+    }
+    if let Some(policy) = apply_shedding {
+        apply_queue.shedding = policy;
+    }
+    let geofence = GeoFenceConfig {
+        radius_km: geofence_km,
+        allowed_geohash_prefixes: geofence_prefixes.iter().map(|p| p.to_lowercase()).collect(),
+    };
+    let mut rewards = RewardConfig {
+        contract: rewards_contract,
+        ..RewardConfig::default()
+    };
+    if let Some(id) = chain_id {
+        rewards.chain_id = id;
+    }
+    if let Some(secs) = rewards_interval_secs {
+        rewards.interval = Duration::from_secs(secs);
+    }
+    let mut stake_proof = StakeProofConfig {
+        publish: stake_proofs,
+        sol_rpc,
+        ..StakeProofConfig::default()
+    };
+    if let Some(n) = stake_attest_quorum {
+        stake_proof.attest_quorum = n;
+    }
+    let mut evaluation = EvalConfig::default();
+    if let Some(shards) = eval_shards {
+        evaluation.shards = shards;
+    }
+    if let Some(secs) = eval_epoch_secs {
+        evaluation.epoch = Duration::from_secs(secs);
+    }
+    if let Some(seed) = eval_seed {
+        evaluation.benchmark_seed = seed;
+    }
+    let mut watchdog = WatchdogConfig::default();
+    if let Some(secs) = watchdog_deadline_secs {
+        // 0 关闭看门狗
+        watchdog.deadline = Duration::from_secs(secs);
+    }
+    let mut memory_budget = MemoryBudgetConfig::default();
+    if let Some(mb) = memory_budget_mb {
+        memory_budget.limit_bytes = mb * 1024 * 1024;
+    }
+    let mut standby = StandbyConfig {
+        replicate_to,
+        listen: standby_listen,
+        ..StandbyConfig::default()
+    };
+    if let Some(secs) = standby_takeover_secs {
+        standby.takeover_after = Duration::from_secs(secs);
+    }
+    let mut retransmit = RetransmitConfig::default();
+    if let Some(n) = ack_retries {
+        retransmit.max_retries = n;
+    }
+    let mut clustering = ClusterConfig::default();
+    if let Some(k) = clusters {
+        clustering.clusters = k;
+    }
+    if let Some(secs) = cluster_interval_secs {
+        clustering.interval = Duration::from_secs(secs);
+    }
+    let mut hyperparams = HyperParamConfig {
+        propose: propose_hyperparams,
+        auto_endorse: endorse_hyperparams,
+        schedule: epoch_schedule,
+        ..HyperParamConfig::default()
+    };
+    if let Some(list) = hyperparam_committee {
+        let members: Vec<String> = list
+            .split(',')
+            .map(|m| m.trim().to_lowercase())
+            .filter(|m| !m.is_empty())
+            .collect();
+        hyperparams.rule = VoteRule::Committee {
+            quorum: members.len() / 2 + 1,
+            members,
+        };
+    }
+    if let Some(size) = hyperparam_elected {
+        hyperparams.rule = VoteRule::Elected {
+            size: size.max(1),
+            quorum: size.max(1) / 2 + 1,
+        };
+    }
+    if let Some(secs) = hyperparam_epoch_secs {
+        hyperparams.epoch = Duration::from_secs(secs);
+    }
+    let mut beacon = BeaconConfig::default();
+    if let Some(threshold) = beacon_threshold {
+        beacon.enabled = true;
+        beacon.threshold = threshold.max(1);
+    }
+    if let Some(secs) = beacon_epoch_secs {
+        beacon.epoch = Duration::from_secs(secs);
+    }
+    let mut erasure = ErasureConfig::default();
+    if let Some((k, m)) = erasure_shards {
+        // 0:0 关闭纠删码
+        erasure.data_shards = k;
+        erasure.parity_shards = m;
+    }
+    if let Some(bytes) = erasure_min_bytes {
+        erasure.min_bytes = bytes;
+    }
+    let mut sparsity = SparsityConfig::default();
+    if let Some(k) = sparse_k {
+        sparsity.initial_k = k;
+    }
+    if fixed_sparse_k {
+        sparsity.auto_tune = false;
+    }
+    let names = NameConfig {
+        eth_rpc,
+        sns_api,
+        ..NameConfig::default()
+    };
+    let meta = PeerMeta {
+        nickname: nickname.unwrap_or_default(),
+        organization: organization.unwrap_or_default(),
+        region: region_label.unwrap_or_default(),
+        contact: contact.unwrap_or_default(),
+        ..PeerMeta::default()
+    };
+    let mut training_schedule = TrainingSchedule {
+        windows: train_windows,
+        ..TrainingSchedule::default()
+    };
+    if let Some(minutes) = utc_offset {
+        training_schedule.utc_offset_minutes = minutes;
+    }
+    let rendezvous = RendezvousConfig {
+        points: rendezvous_points,
+        serve: rendezvous_server,
+    };
+
+    if let Some(id) = node_id {
+        println!("节点 ID: {}", id);
     }
-    let node = Node::new(config).await?;
     
+    // 构建配置，支持自定义模型维度
+    let mut config = AppConfig::default();
+    // 配置文件与 GGS_* 环境变量先于命令行应用，命令行参数覆盖其中的同名项
+    let config_file =
+        config_file.or_else(|| std::env::var_os(config::CONFIG_PATH_ENV).map(std::path::PathBuf::from));
+    if let Some(file) = ConfigFile::load(config_file.as_deref())? {
+        file.inference.apply(&mut config.inference)?;
+        file.comms.apply(&mut config.comms)?;
+        file.topology.apply(&mut config.topology);
+        file.crypto.apply(&mut config.crypto);
+        file.consensus.apply_ledger(&mut config.ledger);
+        file.consensus.apply(&mut config.consensus);
+        file.schedule.apply(&mut config.schedule);
+        match &config_file {
+            Some(path) => println!("[配置] 已加载 {}（叠加 GGS_* 环境变量）", path.display()),
+            None => println!("[配置] 已从 GGS_* 环境变量加载配置"),
+        }
+    }
+    if let Some(topic) = topic {
+        config.comms.topic = topic;
+    }
+    if let Some(mut codecs) = wire_codecs {
+        // JSON 是与旧节点通信的最低公共编码，始终保留
+        if !codecs.contains(&WireCodec::Json) {
+            codecs.push(WireCodec::Json);
+        }
+        config.comms.codecs = codecs;
+    }
+    if let Some(codec) = gossip_codec {
+        config.comms.gossip_codec = codec;
+    }
+    if let Some(stack) = transports {
+        config.comms.transport.stack = stack;
+    }
+    if no_tcp_fallback {
+        config.comms.transport.tcp_fallback = false;
+    }
+    if let Some(policy) = send_policy {
+        config.comms.send_policy = policy;
+    }
+    if let Some(url) = proxy_url {
+        config.comms.proxy.set_url(&url)?;
+    }
+    if proxy_no_mdns {
+        config.comms.proxy.disable_mdns = true;
+    }
+    if let Some(n) = max_inbound {
+        config.comms.connections.max_inbound = n;
+    }
+    if let Some(n) = max_outbound {
+        config.comms.connections.max_outbound = n;
+    }
+    if checkpoint_dir.is_some() {
+        config.inference.checkpoint_dir = checkpoint_dir;
+    }
+    if let Some(secs) = checkpoint_interval {
+        config.checkpoint.interval = Duration::from_secs(secs.max(1));
+    }
+    if quic_bind.is_some() {
+        config.comms.quic_bind = quic_bind;
+    }
+    // 未指定身份文件时，有状态目录就把身份保存在其中，重启后 PeerId 不变
+    if identity_path.is_some() {
+        config.comms.identity_path = identity_path;
+    } else if config.comms.identity_path.is_none() {
+        config.comms.identity_path = state_dir.as_ref().map(|d| d.join(IDENTITY_FILE));
+    }
+    if let Some(n) = max_neighbors {
+        config.topology.max_neighbors = n.max(1);
+    }
+    if let Some(fraction) = cross_cluster_fraction {
+        config.topology.cross_cluster_fraction = fraction.clamp(0.0, 1.0);
+    }
+    if let Some(n) = fanout {
+        config.topology.fanout = n;
+    }
+    if let Some(dim) = model_dim {
+        config.inference.model_dim = dim;
+        println!("使用自定义模型维度: {}", dim);
+    }
+    if seed.is_some() {
+        config.inference.seed = seed;
+    }
+    if let Some(layers) = layers {
+        println!("分层错峰同步: {} 层", layers.layer_count());
+        config.inference.layers = layers;
+    }
+    config.admin_bind = admin_bind;
+    config.jobs.auto_join = auto_join;
+    if let Some(role) = role {
+        config.role = role;
+    }
+    if quic_advertise.is_some() {
+        config.comms.quic_advertise = quic_advertise;
+    }
+    if listen_addr.is_some() {
+        config.comms.listen_addr = listen_addr;
+    }
+    if !bootstrap_peers.is_empty() {
+        config.comms.bootstrap_peers = bootstrap_peers;
+    }
+    if !external_addrs.is_empty() {
+        config.comms.external_addrs = external_addrs;
+    }
+    config.comms.rendezvous.points.extend(rendezvous.points);
+    config.comms.rendezvous.serve |= rendezvous.serve;
+    config.comms.dht.bootstrap.extend(dht_bootstrap);
+    if no_dht {
+        config.comms.dht.enabled = false;
+    }
+    if no_dual_stack {
+        config.comms.dual_stack = false;
+    }
+    config.clock.accept_legacy |= accept_legacy_timestamps;
+    config.comms.nat.relays.extend(circuit_relays);
+    config.comms.nat.relay_server |= relay_server;
+    config.comms.relay.url = relay_url.or(config.comms.relay.url.take());
+    config.comms.relay.token = relay_token.or(config.comms.relay.token.take());
+    if let Some(rule) = aggregation {
+        config.aggregation = rule;
+    }
+    config.apply_queue = apply_queue;
+    config.sparsity = sparsity;
+    if let Some(bits) = pow_difficulty {
+        config.consensus.pow_difficulty = bits;
+    }
+    if config.consensus.pow_difficulty > MAX_POW_DIFFICULTY {
+        return Err(anyhow!(
+            "工作量证明难度 {} 超过上限 {}",
+            config.consensus.pow_difficulty,
+            MAX_POW_DIFFICULTY
+        ));
+    }
+    if token_requirement.is_some() && names.eth_rpc.is_none() {
+        return Err(anyhow!("代币门槛需要通过 --eth-rpc 指定以太坊 RPC 端点"));
+    }
+    config.token_gate.rpc = names.eth_rpc.clone();
+    if rewards.contract.is_some() {
+        if names.eth_rpc.is_none() {
+            return Err(anyhow!("链上奖励提交需要通过 --eth-rpc 指定以太坊 RPC 端点"));
+        }
+        if std::env::var("GGS_ETH_SEED").is_err() && keystore.is_none() && config.crypto.keystore.is_none() {
+            println!("[链上奖励] 未设置 GGS_ETH_SEED，将使用临时 ETH 密钥签名交易");
+        }
+    }
+    rewards.rpc = names.eth_rpc.clone();
+    if stake_proof.publish && names.eth_rpc.is_none() {
+        return Err(anyhow!("质押证明需要通过 --eth-rpc 指定以太坊 RPC 端点"));
+    }
+    config.stake_proof = stake_proof;
+    config.heartbeat_chunks = heartbeat_chunks;
+    config.rewards = rewards;
+    config.token_gate.requirement = token_requirement;
+    config.names = names;
+    config.meta = meta;
+    config.training_schedule = training_schedule;
+    config.power_saver_enabled = power_saver;
+    config.analytics_db = analytics_db;
+    config.evaluation = evaluation;
+    config.watchdog = watchdog;
+    config.memory = memory_budget;
+    config.standby = standby;
+    config.retransmit = retransmit;
+    config.clustering = clustering;
+    config.config_file = config_file;
+    match (&mut hyperparams.rule, hyperparam_quorum) {
+        (VoteRule::Committee { quorum, .. } | VoteRule::Elected { quorum, .. }, Some(q)) => *quorum = q.max(1),
+        (VoteRule::Stake { .. }, Some(_)) => {
+            return Err(anyhow!(
+                "--hyperparam-quorum 需要同时指定 --hyperparam-committee 或 --hyperparam-elected"
+            ))
+        }
+        _ => {}
+    }
+    if matches!(hyperparams.rule, VoteRule::Elected { .. }) && !beacon.enabled {
+        return Err(anyhow!("--hyperparam-elected 需要同时指定 --beacon-threshold"));
+    }
+    config.hyperparams = hyperparams;
+    config.beacon = beacon;
+    config.erasure = erasure;
+    config.trace_record = trace_record;
+    if replay.is_some() {
+        // 重放节点使用进程内网络，不提供管理接口
+        config.replaying = true;
+        config.admin_bind = None;
+        config.trace_record = None;
+    }
+    if let Some(days) = analytics_retention_days {
+        config.analytics.retention = Duration::from_secs(days * 24 * 3600);
+    }
+    if ledger_db.is_some() {
+        config.ledger.path = ledger_db;
+    }
+    if let Some(days) = ledger_retention_days {
+        config.ledger.retention = Duration::from_secs(days.saturating_mul(24 * 3600));
+    }
+    if let Some(rate) = rate_limit {
+        config.pipeline.rate_per_sec = rate;
+    }
+    if let Some(secs) = offline_max_age {
+        config.offline.max_age = Duration::from_secs(secs);
+    }
+    if geofence.is_enabled() && position.is_none() {
+        return Err(anyhow!("地理围栏需要通过 --position 指定本节点位置"));
+    }
+    config.position = position;
+    config.reputation_import = reputation_import;
+    // 链上身份密钥通过环境变量传入，避免出现在进程参数中
+    if let Ok(seed) = std::env::var("GGS_ETH_SEED") {
+        config.crypto.eth_hex_seed = Some(seed);
+    }
+    if let Ok(seed) = std::env::var("GGS_SOL_SEED") {
+        config.crypto.sol_bs58_seed = Some(seed);
+    }
+    // 密钥库存在时从中解锁种子，否则把上面的种子（或新生成的种子）加密写入
+    if keystore.is_some() {
+        config.crypto.keystore = keystore;
+    }
+    if let Some(dir) = config.crypto.keystore.clone() {
+        let passphrase = std::env::var(KEYSTORE_PASSPHRASE_ENV)
+            .map_err(|_| anyhow!("密钥库需要通过环境变量 {} 提供口令", KEYSTORE_PASSPHRASE_ENV))?;
+        config.crypto = Keystore::new(dir).open_or_create(&passphrase, &config.crypto)?;
+    }
+    config.geofence = geofence;
+    if light {
+        // 轻量节点只能作为观察者
+        config.light = true;
+        config.role = NodeRole::Observer;
+    }
+    for path in &job_files {
+        let job = JobSpec::load(path)?;
+        println!("[任务] 发布任务 {} (主题 {})", job.job_id, job.topic);
+        config.jobs.announce.push(job);
+    }
+    for spec in &session_specs {
+        let session = SessionConfig::parse(spec, &config.comms.topic, &config.inference)?;
+        if config.extra_sessions.iter().any(|s| s.id == session.id) {
+            anyhow::bail!("duplicate session id {:?}", session.id);
+        }
+        config.extra_sessions.push(session);
+    }
+    config.state_dir = state_dir;
+    if let Some(ratio) = jitter_ratio {
+        config.schedule.jitter_ratio = ratio.clamp(0.0, 0.5);
+    }
+    for (task, secs) in intervals {
+        let secs = Duration::from_secs(secs.max(1));
+        match task {
+            PeriodicTask::Heartbeat => config.schedule.heartbeat_interval = secs,
+            PeriodicTask::Probe => config.schedule.probe_interval = secs,
+            PeriodicTask::Train => config.schedule.train_interval = secs,
+            PeriodicTask::Snapshot => config.schedule.snapshot_interval = secs,
+            PeriodicTask::Persist => config.schedule.persist_interval = secs,
+            PeriodicTask::Announce => config.schedule.announce_interval = secs,
+        }
+    }
+    let jitter_ratio = config.schedule.jitter_ratio;
+    let admin_bind = config.admin_bind;
+    let supervisor = Supervisor::new(config.supervisor.clone());
+    let watchdog = config.watchdog.is_enabled().then(|| Watchdog::new(config.watchdog.clone()));
+    // 链上 RPC 与名称解析请求同样经代理发出
+    names::route_through(&config.comms.proxy);
+    if let Some(listen) = config.standby.listen {
+        run_standby(&config, listen).await?;
+    }
+    let mut node = Node::new(config).await?;
+    if let Some(path) = replay {
+        let entries = read_trace(&path)?;
+        println!("[重放] 从 {} 读取 {} 条消息", path.display(), entries.len());
+        node.replay(entries).await?;
+        if let Some(output_path) = stats_output {
+            node.stats
+                .export_json_to_file(std::path::Path::new(&output_path))
+                .map_err(|e| anyhow!("导出统计数据失败: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    if let Some(bind) = admin_bind {
+        let state = AdminState {
+            readiness: Arc::clone(&node.readiness),
+            stats: Arc::clone(&node.stats),
+            jobs: Arc::clone(&node.jobs),
+            monitor: Arc::clone(&node.monitor),
+            consensus: Arc::clone(&node.consensus),
+            role: node.role,
+            light: node.light,
+            peer_id: node.comms.local_peer_id(),
+            names: Arc::clone(&node.names),
+            power: Arc::clone(&node.power),
+            analytics: node.analytics.clone(),
+            auditor: Auditor {
+                sessions: node.sessions.clone(),
+                consensus: Arc::clone(&node.consensus),
+                state_dir: node.store.as_ref().map(|s| s.dir().to_path_buf()),
+            },
+        };
+        supervisor.spawn(
+            "admin",
+            Box::new(move || Box::pin(AdminServer::serve(bind, state.clone()))),
+        );
+    }
+    let accept_beat = Beat::default();
+    if let Some(factory) = node.comms.quic_accept_task(accept_beat.clone()) {
+        supervisor.spawn("quic-accept", factory);
+        if let Some(watchdog) = &watchdog {
+            let supervisor = Arc::clone(&supervisor);
+            watchdog.watch(
+                "quic-accept",
+                accept_beat,
+                Box::new(move || {
+                    supervisor.restart("quic-accept");
+                }),
+            );
+        }
+    }
+    // SIGINT / SIGTERM 时让主循环落盘状态、退订主题、关闭连接后退出；再收到一次直接终止
+    let shutdown = Arc::clone(&node.shutdown);
+    tokio::spawn(async move {
+        let signal = exit_signal().await;
+        println!("[退出] 收到 {}，正在退出（再次发送将立即终止）", signal);
+        shutdown.notify_one();
+        exit_signal().await;
+        std::process::exit(130);
+    });
+    // 指定了配置文件时，SIGHUP 立即触发热加载（文件修改也会在下次心跳时自动加载）
+    #[cfg(unix)]
+    if node.reloader.is_some() {
+        let reload = Arc::clone(&node.reload);
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+                return;
+            };
+            while hangup.recv().await.is_some() {
+                reload.notify_one();
+            }
+        });
+    }
+    // 训练主循环无法从外部中止，停滞时通知 run_supervised 放弃当前运行并重新进入
+    let main_loop_stalled = Arc::new(tokio::sync::Notify::new());
+    if let Some(watchdog) = &watchdog {
+        for (name, beat) in [
+            ("swarm-poll", node.swarm_beat.clone()),
+            ("training", node.training_beat.clone()),
+        ] {
+            let stalled = Arc::clone(&main_loop_stalled);
+            watchdog.watch(name, beat, Box::new(move || stalled.notify_one()));
+        }
+        tokio::spawn(Arc::clone(watchdog).run(Arc::clone(&node.stats)));
+    }
+
     // 如果指定了统计输出文件，设置定期导出
     if let Some(output_path) = stats_output {
         let stats_path = std::path::PathBuf::from(&output_path);
         let stats_manager = Arc::clone(&node.stats);
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(30));
             loop {
-                interval.tick().await;
+                tokio::time::sleep(jittered(Duration::from_secs(30), jitter_ratio)).await;
                 if let Err(e) = stats_manager.export_json_to_file(&stats_path) {
                     eprintln!("导出统计数据失败: {:?}", e);
                 }
             }
         });
     }
-    
-    node.run().await
+
+    let result = tokio::select! {
+        result = run_supervised(node, supervisor.restart_policy(), main_loop_stalled) => result,
+        escalation = supervisor.escalated() => match escalation {
+            Some(e) => Err(anyhow!("子系统 {} 无法恢复: {}", e.subsystem, e.reason)),
+            None => Ok(()),
+        },
+    };
+    supervisor.shutdown();
+    result
+}
+
+/// 热备模式：用与主节点相同的身份与密钥接收复制状态，主节点失联后返回，由调用方以该身份启动节点
+async fn run_standby(config: &AppConfig, listen: std::net::SocketAddr) -> Result<()> {
+    let (Some(identity), Some(dir)) = (&config.comms.identity_path, &config.state_dir) else {
+        return Err(anyhow!("热备模式需要 --state-dir 与主节点的身份密钥文件"));
+    };
+    if !identity.exists() {
+        return Err(anyhow!("热备模式需要从主节点拷贝身份密钥文件 {}", identity.display()));
+    }
+    if config.crypto.eth_hex_seed.is_none() || config.crypto.sol_bs58_seed.is_none() {
+        return Err(anyhow!("热备模式需要与主节点相同的 GGS_ETH_SEED / GGS_SOL_SEED 或 --keystore"));
+    }
+    let identity = comms::load_or_create_identity(identity)?;
+    let crypto = Arc::new(CryptoSuite::new(config.crypto.clone())?);
+    let consensus = ConsensusEngine::new(crypto, ConsensusConfig::default());
+    let store = StateStore::new(dir)?;
+    standby::wait_for_takeover(&config.standby, listen, &identity, &consensus, &store).await
+}
+
+/// 在当前任务上运行训练主循环，出错或 panic 时按重启策略重新进入
+/// 等待 SIGINT（Ctrl-C）或 SIGTERM，返回信号名
+async fn exit_signal() -> &'static str {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            return tokio::select! {
+                _ = ctrl_c => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            };
+        }
+    }
+    ctrl_c.await;
+    "SIGINT"
+}
+
+async fn run_supervised(
+    mut node: Node,
+    mut policy: RestartPolicy,
+    stalled: Arc<tokio::sync::Notify>,
+) -> Result<()> {
+    loop {
+        let outcome = tokio::select! {
+            outcome = AssertUnwindSafe(node.run()).catch_unwind() => outcome,
+            _ = stalled.notified() => Ok(Err(anyhow!("看门狗检测到主循环停滞"))),
+        };
+        let reason = match outcome {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(err)) => format!("{err:?}"),
+            Err(payload) => panic_reason(payload),
+        };
+        match policy.on_failure("training", &reason) {
+            Some(backoff) => tokio::time::sleep(backoff).await,
+            None => return Err(anyhow!("子系统 training 无法恢复: {}", reason)),
+        }
+    }
+}
+
+/// 超参数提议是否已获足够背书：委员会模式按成员 ETH 地址计数，质押模式按共识权重占比，
+/// 选举模式按信标选出的委员计数（尚无信标时不通过）
+fn hyperparams_approved(
+    rule: &VoteRule,
+    consensus: &ConsensusEngine,
+    own_peer: &str,
+    beacon: Option<&[u8; 32]>,
+    endorsers: &std::collections::HashSet<String>,
+) -> bool {
+    match rule {
+        VoteRule::Committee { members, quorum } => {
+            let endorsed = endorsers
+                .iter()
+                .filter_map(|peer| {
+                    if peer == own_peer {
+                        Some(consensus.local_eth_address())
+                    } else {
+                        consensus.key_pin(peer).map(|pin| pin.eth_address)
+                    }
+                })
+                .filter(|eth| members.contains(&eth.to_lowercase()))
+                .collect::<std::collections::HashSet<_>>()
+                .len();
+            endorsed >= *quorum
+        }
+        VoteRule::Stake { threshold } => {
+            let total = consensus.total_stake_weight();
+            let endorsed: f32 = endorsers.iter().map(|peer| consensus.stake_weight(peer)).sum();
+            total > 0.0 && endorsed >= threshold * total
+        }
+        VoteRule::Elected { size, quorum } => {
+            let Some(seed) = beacon else {
+                return false;
+            };
+            let committee = elect(seed, &consensus.staked_peers(), *size);
+            endorsers.iter().filter(|peer| committee.contains(peer)).count() >= *quorum
+        }
+    }
 }
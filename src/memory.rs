@@ -0,0 +1,111 @@
+//! 内存预算
+//!
+//! 按组件估算节点常驻内存：模型（参数、误差残差、上一步参数与快照副本）、拓扑中的节点画像、
+//! 离线消息队列、纠删码分片组装缓冲，以及补发日志与惰性推拉缓存等历史记录。
+//! 总量超过高水位时按价值从低到高释放状态（过期节点画像 → 未完成的分片组装 → 历史记录 →
+//! 离线队列中最旧的消息），直到降到低水位；配置的模型本身放不进预算时拒绝启动或加入会话。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// 每个参数常驻的 f32 份数：参数、误差残差、上一步参数、快照 / 更新的临时副本
+const MODEL_COPIES: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct MemoryBudgetConfig {
+    /// 预算字节数，0 表示不限制
+    pub limit_bytes: usize,
+    /// 超过 limit * high_water 时开始释放
+    pub high_water: f32,
+    /// 释放到 limit * low_water 为止
+    pub low_water: f32,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            limit_bytes: 0,
+            high_water: 0.9,
+            low_water: 0.75,
+        }
+    }
+}
+
+impl MemoryBudgetConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.limit_bytes > 0
+    }
+
+    /// 模型必须能放进高水位以下，剩余空间留给其余状态
+    pub fn check_models_fit(&self, model_bytes: usize) -> Result<()> {
+        let ceiling = (self.limit_bytes as f64 * self.high_water as f64) as usize;
+        if self.is_enabled() && model_bytes > ceiling {
+            return Err(anyhow!(
+                "模型需要约 {} MiB，超出内存预算 {} MiB 的 {:.0}%",
+                model_bytes / (1024 * 1024),
+                self.limit_bytes / (1024 * 1024),
+                self.high_water * 100.0
+            ));
+        }
+        Ok(())
+    }
+
+    /// 需要释放的字节数；未超过高水位时为 0
+    pub fn excess(&self, usage: &MemoryUsage) -> usize {
+        let total = usage.total() as f64;
+        let limit = self.limit_bytes as f64;
+        if !self.is_enabled() || total <= limit * self.high_water as f64 {
+            return 0;
+        }
+        (total - limit * self.low_water as f64) as usize
+    }
+}
+
+/// 按参数个数估算模型常驻内存
+pub fn model_bytes(dim: usize) -> usize {
+    dim * std::mem::size_of::<f32>() * MODEL_COPIES
+}
+
+/// 各组件的估算占用（字节）
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MemoryUsage {
+    pub models: usize,
+    pub peer_profiles: usize,
+    pub queues: usize,
+    pub reassembly: usize,
+    pub history: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.models + self.peer_profiles + self.queues + self.reassembly + self.history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_refuses_oversized_models_and_sheds_to_low_water() {
+        let config = MemoryBudgetConfig {
+            limit_bytes: 1000,
+            ..MemoryBudgetConfig::default()
+        };
+        assert!(config.check_models_fit(850).is_ok());
+        assert!(config.check_models_fit(950).is_err());
+        assert!(MemoryBudgetConfig::default()
+            .check_models_fit(usize::MAX)
+            .is_ok());
+
+        let mut usage = MemoryUsage {
+            models: 600,
+            peer_profiles: 200,
+            ..MemoryUsage::default()
+        };
+        assert_eq!(config.excess(&usage), 0);
+        usage.queues = 150;
+        // 950 字节，释放到 750
+        assert_eq!(config.excess(&usage), 200);
+    }
+}
@@ -0,0 +1,231 @@
+//! 网络状态监控
+//!
+//! 记录每个节点最近一次心跳宣告的角色、模型哈希与版本，不依赖本地模型参数，
+//! 因此轻量（light）节点也能据此回答状态查询、统计全网模型一致性。
+//! 各会话的邻居集合与节点元数据由主循环定期写入，供管理接口读取。
+
+use crate::device::ComputeCapability;
+use crate::role::NodeRole;
+use crate::types::PeerMeta;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// 单个节点的最新状态
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerStatus {
+    pub peer: String,
+    pub session: String,
+    pub role: NodeRole,
+    pub model_hash: String,
+    pub model_version: u64,
+    pub compute: Option<ComputeCapability>,
+    /// 距离最后一次心跳的秒数
+    pub last_seen_secs: u64,
+    /// 按分块哈希定位到的、与本地模型不一致的参数区间 [起, 止)
+    pub diverged_ranges: Option<Vec<(usize, usize)>>,
+}
+
+/// 某个会话内的模型哈希分布
+#[derive(Debug, Clone, Serialize)]
+pub struct HashAgreement {
+    pub session: String,
+    /// 出现次数最多的模型哈希
+    pub majority_hash: String,
+    pub majority_peers: usize,
+    pub total_peers: usize,
+}
+
+struct Entry {
+    role: NodeRole,
+    model_hash: String,
+    model_version: u64,
+    compute: Option<ComputeCapability>,
+    last_seen: Instant,
+    diverged_ranges: Option<Vec<(usize, usize)>>,
+}
+
+/// 某个会话的邻居集合
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TopologyView {
+    pub session: String,
+    pub primary: Vec<String>,
+    pub backups: Vec<String>,
+    /// 节点自述的元数据
+    pub metadata: BTreeMap<String, PeerMeta>,
+}
+
+pub struct PeerMonitor {
+    stale_after: Duration,
+    /// (会话, 节点) -> 最新状态
+    peers: RwLock<HashMap<(String, String), Entry>>,
+    topology: RwLock<HashMap<String, TopologyView>>,
+}
+
+impl PeerMonitor {
+    pub fn new(stale_after: Duration) -> Self {
+        Self {
+            stale_after,
+            peers: RwLock::new(HashMap::new()),
+            topology: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn update_topology(
+        &self,
+        session: &str,
+        primary: Vec<String>,
+        backups: Vec<String>,
+        metadata: Vec<(String, PeerMeta)>,
+    ) {
+        self.topology.write().insert(
+            session.to_string(),
+            TopologyView {
+                session: session.to_string(),
+                primary,
+                backups,
+                metadata: metadata.into_iter().collect(),
+            },
+        );
+    }
+
+    pub fn topology(&self) -> Vec<TopologyView> {
+        let mut out: Vec<_> = self.topology.read().values().cloned().collect();
+        out.sort_by(|a, b| a.session.cmp(&b.session));
+        out
+    }
+
+    pub fn observe_heartbeat(
+        &self,
+        session: &str,
+        peer: &str,
+        model_hash: &str,
+        model_version: u64,
+        role: NodeRole,
+        compute: Option<ComputeCapability>,
+    ) {
+        self.peers.write().insert(
+            (session.to_string(), peer.to_string()),
+            Entry {
+                role,
+                model_hash: model_hash.to_string(),
+                model_version,
+                compute,
+                last_seen: Instant::now(),
+                diverged_ranges: None,
+            },
+        );
+    }
+
+    /// 记录最近一次心跳定位到的分歧区间
+    pub fn record_divergence(&self, session: &str, peer: &str, ranges: &[Range<usize>]) {
+        if let Some(entry) = self
+            .peers
+            .write()
+            .get_mut(&(session.to_string(), peer.to_string()))
+        {
+            entry.diverged_ranges = Some(ranges.iter().map(|r| (r.start, r.end)).collect());
+        }
+    }
+
+    /// 会话内宣告了算力的训练者：(节点, TFLOPs)
+    pub fn trainer_compute(&self, session: &str) -> Vec<(String, f32)> {
+        self.peers
+            .read()
+            .iter()
+            .filter(|((s, _), entry)| s == session && entry.role == NodeRole::Trainer)
+            .filter_map(|((_, peer), entry)| entry.compute.map(|c| (peer.clone(), c.tflops)))
+            .collect()
+    }
+
+    pub fn prune_stale(&self) {
+        let stale_after = self.stale_after;
+        self.peers
+            .write()
+            .retain(|_, entry| entry.last_seen.elapsed() <= stale_after);
+    }
+
+    pub fn peers(&self) -> Vec<PeerStatus> {
+        let mut out: Vec<_> = self
+            .peers
+            .read()
+            .iter()
+            .map(|((session, peer), entry)| PeerStatus {
+                peer: peer.clone(),
+                session: session.clone(),
+                role: entry.role,
+                model_hash: entry.model_hash.clone(),
+                model_version: entry.model_version,
+                compute: entry.compute,
+                last_seen_secs: entry.last_seen.elapsed().as_secs(),
+                diverged_ranges: entry.diverged_ranges.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.session, &a.peer).cmp(&(&b.session, &b.peer)));
+        out
+    }
+
+    /// 节点最近一次心跳宣告的（模型哈希, 版本）
+    pub fn advertised_model(&self, session: &str, peer: &str) -> Option<(String, u64)> {
+        self.peers
+            .read()
+            .get(&(session.to_string(), peer.to_string()))
+            .map(|entry| (entry.model_hash.clone(), entry.model_version))
+    }
+
+    /// 按会话统计模型哈希一致性（观察者不持有模型，不计入）
+    pub fn hash_agreement(&self) -> Vec<HashAgreement> {
+        let mut counts: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+        let peers = self.peers.read();
+        for ((session, _), entry) in peers.iter() {
+            if entry.role == NodeRole::Observer || entry.model_hash.is_empty() {
+                continue;
+            }
+            *counts
+                .entry(session.as_str())
+                .or_default()
+                .entry(entry.model_hash.as_str())
+                .or_default() += 1;
+        }
+        let mut out: Vec<_> = counts
+            .into_iter()
+            .map(|(session, hashes)| {
+                let total_peers = hashes.values().sum();
+                let (majority_hash, majority_peers) = hashes
+                    .into_iter()
+                    .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                    .unwrap_or_default();
+                HashAgreement {
+                    session: session.to_string(),
+                    majority_hash: majority_hash.to_string(),
+                    majority_peers,
+                    total_peers,
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.session.cmp(&b.session));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_agreement_ignores_observers() {
+        let monitor = PeerMonitor::new(Duration::from_secs(60));
+        monitor.observe_heartbeat("default", "a", "0x1", 3, NodeRole::Trainer, None);
+        monitor.observe_heartbeat("default", "b", "0x1", 3, NodeRole::Aggregator, None);
+        monitor.observe_heartbeat("default", "c", "0x2", 2, NodeRole::Trainer, None);
+        monitor.observe_heartbeat("default", "d", "", 0, NodeRole::Observer, None);
+        let agreement = monitor.hash_agreement();
+        assert_eq!(agreement.len(), 1);
+        assert_eq!(agreement[0].majority_hash, "0x1");
+        assert_eq!(agreement[0].majority_peers, 2);
+        assert_eq!(agreement[0].total_peers, 3);
+        assert_eq!(monitor.peers().len(), 4);
+    }
+}
@@ -0,0 +1,354 @@
+//! ENS / SNS 名称解析
+//!
+//! 把节点固定的 ETH 地址反查为 ENS 名称、SOL 公钥反查为 SNS 域名，
+//! 供日志与 `/status` 展示可读身份。解析在后台任务中进行并缓存（含未命中结果），
+//! 调用方只读缓存，不会阻塞主循环。
+//!
+//! 与管理接口一样只实现最小的 HTTP/1.0 客户端，仅支持 `http://` 端点
+//! （本地以太坊节点或本地代理）；配置了 SOCKS5 代理时所有 RPC 请求（含质押、奖励与代币门槛查询）
+//! 都经代理发出，域名由代理解析：
+//! - ENS：JSON-RPC `eth_call` 查询反向记录，并要求正向解析回同一地址，防止伪造反向记录
+//! - SNS：SNS SDK 代理的 `GET /favorite-domain/<pubkey>`
+
+use crate::consensus::KeyPin;
+use crate::proxy::ProxyConfig;
+use anyhow::{anyhow, Result};
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 以太坊主网 ENS Registry
+const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+/// resolver(bytes32)
+const SELECTOR_RESOLVER: &str = "0178b8bf";
+/// name(bytes32)
+const SELECTOR_NAME: &str = "691f3431";
+/// addr(bytes32)
+const SELECTOR_ADDR: &str = "3b3b57de";
+/// 响应体上限
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// 进程内所有 HTTP 请求使用的 SOCKS5 代理，启动时设置一次
+static HTTP_PROXY: OnceLock<ProxyConfig> = OnceLock::new();
+
+/// 之后的 RPC / HTTP 请求都经该代理发出；只在启动时调用，未启用的代理配置被忽略
+pub fn route_through(proxy: &ProxyConfig) {
+    if proxy.enabled() {
+        let _ = HTTP_PROXY.set(proxy.clone());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NameConfig {
+    /// 以太坊 JSON-RPC 端点，None 时不解析 ENS
+    pub eth_rpc: Option<String>,
+    /// SNS 代理端点，None 时不解析 SNS
+    pub sns_api: Option<String>,
+    /// 缓存有效期（未命中同样缓存）
+    pub cache_ttl: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for NameConfig {
+    fn default() -> Self {
+        Self {
+            eth_rpc: None,
+            sns_api: None,
+            cache_ttl: Duration::from_secs(3600),
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// 节点的可读名称
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeerNames {
+    pub peer: String,
+    pub ens: Option<String>,
+    pub sns: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum NameKind {
+    Ens,
+    Sns,
+}
+
+struct CachedName {
+    name: Option<String>,
+    resolved_at: Instant,
+}
+
+pub struct NameResolver {
+    config: NameConfig,
+    /// (类型, 地址) -> 解析结果
+    cache: RwLock<HashMap<(NameKind, String), CachedName>>,
+    /// 正在后台解析的地址，避免重复请求
+    pending: RwLock<HashSet<(NameKind, String)>>,
+}
+
+impl NameResolver {
+    pub fn new(config: NameConfig) -> Self {
+        Self {
+            config,
+            cache: RwLock::new(HashMap::new()),
+            pending: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.eth_rpc.is_some() || self.config.sns_api.is_some()
+    }
+
+    /// 读取缓存中的名称，缓存缺失或过期时在后台发起解析
+    pub fn names(self: &Arc<Self>, pin: &KeyPin) -> PeerNames {
+        PeerNames {
+            peer: pin.peer.clone(),
+            ens: self
+                .config
+                .eth_rpc
+                .as_ref()
+                .and_then(|_| self.lookup(NameKind::Ens, &pin.eth_address)),
+            sns: self
+                .config
+                .sns_api
+                .as_ref()
+                .and_then(|_| self.lookup(NameKind::Sns, &pin.sol_pubkey)),
+        }
+    }
+
+    /// 日志中使用的节点标签：有名称时为 `名称 (PeerId)`
+    pub fn label(self: &Arc<Self>, pin: Option<&KeyPin>, peer: &str) -> String {
+        let Some(pin) = pin.filter(|_| self.is_enabled()) else {
+            return peer.to_string();
+        };
+        let names = self.names(pin);
+        match names.ens.or(names.sns) {
+            Some(name) => format!("{} ({})", name, peer),
+            None => peer.to_string(),
+        }
+    }
+
+    fn lookup(self: &Arc<Self>, kind: NameKind, address: &str) -> Option<String> {
+        let key = (kind, address.to_string());
+        if let Some(cached) = self.cache.read().get(&key) {
+            if cached.resolved_at.elapsed() < self.config.cache_ttl {
+                return cached.name.clone();
+            }
+        }
+        if self.pending.write().insert(key.clone()) {
+            let resolver = self.clone();
+            let key = key.clone();
+            tokio::spawn(async move {
+                let result = tokio::time::timeout(
+                    resolver.config.request_timeout,
+                    resolver.resolve(kind, &key.1),
+                )
+                .await
+                .map_err(|_| anyhow!("timed out"))
+                .and_then(|r| r);
+                let name = match result {
+                    Ok(name) => name,
+                    Err(e) => {
+                        eprintln!("[名称解析] 解析 {} 失败: {:?}", key.1, e);
+                        None
+                    }
+                };
+                resolver.pending.write().remove(&key);
+                resolver.cache.write().insert(
+                    key,
+                    CachedName {
+                        name,
+                        resolved_at: Instant::now(),
+                    },
+                );
+            });
+        }
+        // 过期的旧结果在刷新完成前继续使用
+        self.cache.read().get(&key).and_then(|c| c.name.clone())
+    }
+
+    async fn resolve(&self, kind: NameKind, address: &str) -> Result<Option<String>> {
+        match kind {
+            NameKind::Ens => {
+                let rpc = self.config.eth_rpc.as_deref().ok_or_else(|| anyhow!("no eth rpc"))?;
+                resolve_ens(rpc, address).await
+            }
+            NameKind::Sns => {
+                let api = self.config.sns_api.as_deref().ok_or_else(|| anyhow!("no sns api"))?;
+                resolve_sns(api, address).await
+            }
+        }
+    }
+}
+
+/// ENS 反向解析：addr.reverse 记录，再正向解析校验
+async fn resolve_ens(rpc: &str, address: &str) -> Result<Option<String>> {
+    let address = address.trim_start_matches("0x").to_lowercase();
+    let reverse_node = namehash(&format!("{}.addr.reverse", address));
+    let Some(resolver) = ens_resolver(rpc, &reverse_node).await? else {
+        return Ok(None);
+    };
+    let data = eth_call(rpc, &resolver, SELECTOR_NAME, &reverse_node).await?;
+    let name = decode_abi_string(&data)?;
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let forward_node = namehash(&name);
+    let Some(forward_resolver) = ens_resolver(rpc, &forward_node).await? else {
+        return Ok(None);
+    };
+    let data = eth_call(rpc, &forward_resolver, SELECTOR_ADDR, &forward_node).await?;
+    let forward = data.get(12..32).map(hex::encode).unwrap_or_default();
+    Ok((forward == address).then_some(name))
+}
+
+async fn ens_resolver(rpc: &str, node: &[u8; 32]) -> Result<Option<String>> {
+    let data = eth_call(rpc, ENS_REGISTRY, SELECTOR_RESOLVER, node).await?;
+    let resolver = data.get(12..32).ok_or_else(|| anyhow!("short resolver response"))?;
+    if resolver.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+    Ok(Some(format!("0x{}", hex::encode(resolver))))
+}
+
+pub async fn eth_call(rpc: &str, to: &str, selector: &str, node: &[u8; 32]) -> Result<Vec<u8>> {
+    let data = format!("0x{}{}", selector, hex::encode(node));
+    let result = rpc_call(rpc, "eth_call", json!([{"to": to, "data": data}, "latest"])).await?;
+    let result = result.as_str().ok_or_else(|| anyhow!("missing rpc result"))?;
+    Ok(hex::decode(result.trim_start_matches("0x"))?)
+}
+
+/// 以太坊 JSON-RPC 调用，返回 `result` 字段
+pub async fn rpc_call(rpc: &str, method: &str, params: Value) -> Result<Value> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let mut response: Value = serde_json::from_str(&http_request(rpc, Some(&request.to_string())).await?)?;
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("rpc error: {}", error));
+    }
+    Ok(response["result"].take())
+}
+
+/// SNS 反向解析：钱包设置的首选域名
+async fn resolve_sns(api: &str, pubkey: &str) -> Result<Option<String>> {
+    let url = format!("{}/favorite-domain/{}", api.trim_end_matches('/'), pubkey);
+    let response: Value = serde_json::from_str(&http_request(&url, None).await?)?;
+    if response["s"].as_str() != Some("ok") {
+        return Ok(None);
+    }
+    Ok(response["result"]["reverse"]
+        .as_str()
+        .map(|name| format!("{}.sol", name)))
+}
+
+/// ENS namehash
+fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    for label in name.rsplit('.').filter(|l| !l.is_empty()) {
+        let label_hash: [u8; 32] = Keccak256::digest(label.as_bytes()).into();
+        let mut hasher = Keccak256::new();
+        hasher.update(node);
+        hasher.update(label_hash);
+        node = hasher.finalize().into();
+    }
+    node
+}
+
+/// 解码 ABI 编码的单个 string 返回值；偏移或长度越界（RPC 返回被截断或伪造）时返回错误
+fn decode_abi_string(data: &[u8]) -> Result<String> {
+    let truncated = || anyhow!("truncated abi string ({} bytes)", data.len());
+    let word = |at: usize| -> Result<usize> {
+        let bytes = at
+            .checked_add(32)
+            .and_then(|end| data.get(at..end))
+            .ok_or_else(truncated)?;
+        let (high, low) = bytes.split_at(24);
+        if high.iter().any(|b| *b != 0) {
+            return Err(anyhow!("abi word out of range"));
+        }
+        Ok(u64::from_be_bytes(low.try_into()?) as usize)
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let bytes = offset
+        .checked_add(32)
+        .and_then(|start| data.get(start..start.checked_add(len)?))
+        .ok_or_else(truncated)?;
+    Ok(String::from_utf8(bytes.to_vec())?)
+}
+
+/// 最小 HTTP/1.0 客户端：有 body 时 POST JSON，否则 GET，返回响应体
+async fn http_request(url: &str, body: Option<&str>) -> Result<String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| anyhow!("only http:// endpoints are supported: {}", url))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) if !port.ends_with(']') => (name, port.parse::<u16>()?),
+        _ => (host, 80),
+    };
+    let name = name.trim_matches(['[', ']']);
+    let mut stream = match HTTP_PROXY.get() {
+        Some(proxy) => proxy.connect(name, port).await?,
+        None => TcpStream::connect((name, port)).await?,
+    };
+    let request = match body {
+        Some(body) => format!(
+            "POST {path} HTTP/1.0\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        ),
+        None => format!("GET {path} HTTP/1.0\r\nHost: {host}\r\nAccept: application/json\r\n\r\n"),
+    };
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE_BYTES).read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| anyhow!("malformed http response"))?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(anyhow!("http status {}", status));
+    }
+    Ok(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namehash_and_abi_string() {
+        assert_eq!(namehash(""), [0u8; 32]);
+        assert_eq!(
+            hex::encode(namehash("eth")),
+            "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+
+        let mut data = vec![0u8; 96];
+        data[31] = 0x20;
+        data[63] = 9;
+        data[64..73].copy_from_slice(b"alice.eth");
+        assert_eq!(decode_abi_string(&data).unwrap(), "alice.eth");
+        assert!(decode_abi_string(&data[..40]).is_err());
+        assert!(decode_abi_string(&data[..70]).is_err());
+        // 偏移指向 u64::MAX 附近时不能溢出
+        let mut huge = vec![0u8; 32];
+        huge[24..].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(decode_abi_string(&huge).is_err());
+    }
+}
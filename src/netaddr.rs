@@ -0,0 +1,164 @@
+//! 地址族无关的地址处理
+//!
+//! 双栈监听时 IPv4 对端经 IPv6 套接字到达，远端地址表现为 `::ffff:a.b.c.d` 形式的映射地址；
+//! 记录或比较地址前统一还原成规范形式，同一节点的 IPv4 / 映射地址不会被当作两个节点。
+//! 其他节点宣告的地址只有公网可路由时才拨号，局域网、回环等地址仅在开启局域网发现时使用。
+
+use libp2p::multiaddr::{Multiaddr, Protocol};
+use socket2::{Domain, Protocol as SocketProtocol, Socket, Type};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+/// IPv4 映射的 IPv6 地址还原为 IPv4，其他地址原样返回
+pub fn canonical(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+/// 公网可路由的地址：排除未指定、回环、私有、链路本地、CGNAT、组播、文档与保留地址
+pub fn is_global(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let [first, second, ..] = ip.segments();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || (first, second) == (0x2001, 0x0db8))
+        }
+    }
+}
+
+/// multiaddr 开头的 IP 是否公网可路由；以域名等开头的地址不在此判断，视为可路由
+pub fn is_global_multiaddr(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => is_global(ip.into()),
+        Some(Protocol::Ip6(ip)) => is_global(ip.into()),
+        _ => true,
+    }
+}
+
+/// `addr` 是否就是本节点：与对外宣告的 QUIC 地址相同，或指向本机绑定的套接字
+/// （绑定在未指定地址上时，同端口的回环地址也算）
+pub fn is_own(addr: SocketAddr, bound: SocketAddr, advertise: Option<SocketAddr>) -> bool {
+    let addr = canonical(addr);
+    if advertise.is_some_and(|a| canonical(a) == addr) {
+        return true;
+    }
+    let bound = canonical(bound);
+    addr.port() == bound.port()
+        && (addr.ip() == bound.ip() || (bound.ip().is_unspecified() && addr.ip().is_loopback()))
+}
+
+/// `/ip4/0.0.0.0/...` 监听地址对应的 `/ip6/::/...` 地址，其他地址返回 None
+pub fn ipv6_counterpart(addr: &Multiaddr) -> Option<Multiaddr> {
+    let mut protocols = addr.iter();
+    match protocols.next()? {
+        Protocol::Ip4(ip) if ip.is_unspecified() => {
+            let mut v6 = Multiaddr::empty().with(Protocol::Ip6(Ipv6Addr::UNSPECIFIED));
+            for protocol in protocols {
+                v6.push(protocol);
+            }
+            Some(v6)
+        }
+        _ => None,
+    }
+}
+
+/// 绑定 UDP 套接字。`[::]` 绑定显式关闭 IPV6_V6ONLY，同一端口同时接收 IPv4 与 IPv6；
+/// 系统不支持 IPv6 时退回 `0.0.0.0` 的同一端口
+pub fn bind_udp(addr: SocketAddr, dual_stack: bool) -> io::Result<UdpSocket> {
+    if !addr.is_ipv6() {
+        return UdpSocket::bind(addr);
+    }
+    let bind_v6 = || -> io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(SocketProtocol::UDP))?;
+        socket.set_only_v6(!dual_stack)?;
+        socket.bind(&addr.into())?;
+        Ok(socket.into())
+    };
+    match bind_v6() {
+        Ok(socket) => Ok(socket),
+        Err(err) if dual_stack && addr.ip().is_unspecified() => {
+            eprintln!("[网络] 绑定 {} 失败（{}），改用 IPv4", addr, err);
+            UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port()))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalizes_mapped_addresses_and_derives_ipv6_listen() {
+        let mapped: SocketAddr = "[::ffff:10.0.0.7]:9300".parse().unwrap();
+        assert_eq!(canonical(mapped), "10.0.0.7:9300".parse().unwrap());
+        let v6: SocketAddr = "[fd00::2]:9300".parse().unwrap();
+        assert_eq!(canonical(v6), v6);
+
+        let any: Multiaddr = "/ip4/0.0.0.0/tcp/4001/ws".parse().unwrap();
+        assert_eq!(
+            ipv6_counterpart(&any),
+            Some("/ip6/::/tcp/4001/ws".parse().unwrap())
+        );
+        let fixed: Multiaddr = "/ip4/192.168.1.2/tcp/4001".parse().unwrap();
+        assert_eq!(ipv6_counterpart(&fixed), None);
+
+        let bound: SocketAddr = "[::]:9300".parse().unwrap();
+        let public: SocketAddr = "203.0.113.5:9300".parse().unwrap();
+        assert!(is_own("127.0.0.1:9300".parse().unwrap(), bound, None));
+        assert!(is_own(public, bound, Some(public)));
+        assert!(!is_own(public, bound, None));
+        assert!(!is_own("127.0.0.1:9301".parse().unwrap(), bound, Some(public)));
+
+        // 只有公网地址算可路由，映射地址按 IPv4 判断
+        for ip in ["8.8.8.8", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_global(ip.parse().unwrap()), "{}", ip);
+        }
+        let local = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "100.64.0.1",
+            "169.254.1.1",
+            "::ffff:10.0.0.7",
+            "::1",
+            "fd00::2",
+            "fe80::1",
+        ];
+        for ip in local {
+            assert!(!is_global(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(!is_global_multiaddr(&fixed));
+        let named: Multiaddr = "/dns4/example.org/tcp/4001".parse().unwrap();
+        assert!(is_global_multiaddr(&named));
+
+        // 双栈套接字同时接收 IPv4 报文；主机未启用 IPv6 时跳过
+        if UdpSocket::bind("[::1]:0").is_err() {
+            eprintln!("本机不支持 IPv6，跳过双栈检查");
+            return;
+        }
+        let socket = bind_udp("[::]:0".parse().unwrap(), true).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(socket.local_addr().unwrap().is_ipv6());
+        socket.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        sender.send_to(b"ping", ("127.0.0.1", port)).unwrap();
+        let mut buf = [0u8; 4];
+        let (_, from) = socket.recv_from(&mut buf).unwrap();
+        assert_eq!(canonical(from), sender.local_addr().unwrap());
+    }
+}
@@ -0,0 +1,164 @@
+//! 断网期间的离线队列
+//!
+//! 边缘节点链路不稳定时，没有 gossip 节点可发送的本地稀疏更新不再直接丢弃，
+//! 而是写入状态目录下的队列文件；检测到重新连上网络后按顺序重放，
+//! 超过时效的更新在重放前丢弃。重放时重新签名以刷新时间戳，
+//! 否则会被接收方的时间戳校验当作过旧消息拒绝。
+
+use crate::consensus::SignedGossip;
+use crate::persistence::write_atomic;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const QUEUE_FILE: &str = "offline_queue.json";
+
+#[derive(Clone, Debug)]
+pub struct OfflineConfig {
+    /// 超过该时长的更新不再重放
+    pub max_age: Duration,
+    /// 队列上限，超出时丢弃最旧的更新
+    pub max_entries: usize,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(1800),
+            max_entries: 256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedMessage {
+    queued_at_secs: u64,
+    signed: SignedGossip,
+}
+
+pub struct OfflineQueue {
+    config: OfflineConfig,
+    /// 队列文件，None 表示只保存在内存中
+    path: Option<PathBuf>,
+    entries: VecDeque<QueuedMessage>,
+}
+
+impl OfflineQueue {
+    /// 打开队列，状态目录中有上次未重放的更新时一并加载
+    pub fn open(config: OfflineConfig, dir: Option<&Path>) -> Result<Self> {
+        let path = dir.map(|d| d.join(QUEUE_FILE));
+        let entries = match &path {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => VecDeque::new(),
+        };
+        Ok(Self {
+            config,
+            path,
+            entries,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 按序列化大小估算的内存占用
+    pub fn memory_bytes(&self) -> usize {
+        self.entries.iter().map(entry_bytes).sum()
+    }
+
+    /// 内存紧张时丢弃最旧的消息，返回释放的字节数
+    pub fn shed(&mut self, target: usize) -> Result<usize> {
+        let mut freed = 0;
+        while freed < target {
+            match self.entries.pop_front() {
+                Some(entry) => freed += entry_bytes(&entry),
+                None => break,
+            }
+        }
+        if freed > 0 {
+            self.persist()?;
+        }
+        Ok(freed)
+    }
+
+    pub fn push(&mut self, signed: SignedGossip, now_secs: u64) -> Result<()> {
+        self.entries.push_back(QueuedMessage {
+            queued_at_secs: now_secs,
+            signed,
+        });
+        while self.entries.len() > self.config.max_entries {
+            self.entries.pop_front();
+        }
+        self.persist()
+    }
+
+    /// 取出全部未过期的更新（按入队顺序），返回 (更新, 丢弃的过期数量)
+    pub fn drain_fresh(&mut self, now_secs: u64) -> Result<(Vec<SignedGossip>, usize)> {
+        let max_age = self.config.max_age.as_secs();
+        let total = self.entries.len();
+        let fresh: Vec<SignedGossip> = self
+            .entries
+            .drain(..)
+            .filter(|e| now_secs.saturating_sub(e.queued_at_secs) <= max_age)
+            .map(|e| e.signed)
+            .collect();
+        self.persist()?;
+        let expired = total - fresh.len();
+        Ok((fresh, expired))
+    }
+
+    fn persist(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => write_atomic(path, &serde_json::to_vec(&self.entries)?),
+            None => Ok(()),
+        }
+    }
+}
+
+fn entry_bytes(entry: &QueuedMessage) -> usize {
+    serde_json::to_vec(&entry.signed).map_or(0, |bytes| bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::session::DEFAULT_SESSION;
+    use crate::types::{GgsMessage, SparseUpdate};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_queue_survives_restart_and_drops_stale() {
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let engine = ConsensusEngine::new(crypto, ConsensusConfig::default());
+        let update = |version| {
+            let payload = GgsMessage::SparseUpdate {
+                update: SparseUpdate {
+                    indices: Vec::new(),
+                    values: Vec::new(),
+                    version,
+                    seq: 0,
+                },
+                sender: "me".into(),
+            };
+            engine.sign(DEFAULT_SESSION, payload).unwrap()
+        };
+        let dir = std::env::temp_dir().join(format!("ggs-outbox-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut queue = OfflineQueue::open(OfflineConfig::default(), Some(&dir)).unwrap();
+        queue.push(update(1), 1_000).unwrap();
+        queue.push(update(2), 3_000).unwrap();
+
+        let mut reopened = OfflineQueue::open(OfflineConfig::default(), Some(&dir)).unwrap();
+        assert_eq!(reopened.len(), 2);
+        let (fresh, expired) = reopened.drain_fresh(3_500).unwrap();
+        assert_eq!((fresh.len(), expired), (1, 1));
+        assert_eq!(OfflineQueue::open(OfflineConfig::default(), Some(&dir)).unwrap().len(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+//! 节点状态持久化
+//!
+//! 将模型检查点、质押账本、节点地址簿、已生效的超参数提议和训练轮次写入状态目录下的单个 JSON 文件，
+//! 启动时若存在则从中恢复，避免崩溃重启后节点被网络视为全新节点。
+
+use crate::comms::PeerAddresses;
+use crate::consensus::{KeyPin, LedgerEntry};
+use crate::hyperparams::HyperParamProposal;
+use crate::inference::ModelCheckpoint;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STATE_FILE: &str = "node_state.json";
+
+/// 持久化的节点状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistedState {
+    /// 保存时间（Unix 时间戳，秒）
+    pub saved_at_secs: u64,
+    /// 默认会话的模型检查点
+    pub model: Option<ModelCheckpoint>,
+    /// 其他会话的模型检查点
+    #[serde(default)]
+    pub session_models: HashMap<String, ModelCheckpoint>,
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+    #[serde(default)]
+    pub address_book: Vec<PeerAddresses>,
+    /// trust-on-first-use 固定的节点密钥
+    #[serde(default)]
+    pub key_pins: Vec<KeyPin>,
+    /// 各会话当前生效的超参数提议（含纪元调度）
+    #[serde(default)]
+    pub hyperparams: HashMap<String, HyperParamProposal>,
+    /// 最后完成的训练轮次
+    #[serde(default)]
+    pub last_round: u64,
+}
+
+pub struct StateStore {
+    dir: PathBuf,
+}
+
+impl StateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.dir.join(STATE_FILE)
+    }
+
+    /// 读取上次保存的状态，不存在时返回 None
+    pub fn load(&self) -> Result<Option<PersistedState>> {
+        let path = self.state_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(&path)?;
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    /// 原子写入：先写临时文件再 rename，崩溃时不会留下半个文件
+    pub fn save(&self, state: &mut PersistedState) -> Result<()> {
+        state.saved_at_secs = unix_now_secs();
+        let json = serde_json::to_vec(state)?;
+        write_atomic(&self.state_path(), &json)
+    }
+}
+
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 与 `write_atomic` 相同，但临时文件创建时即为仅属主可读写（0o600），
+/// 密钥在任何时刻都不会以默认权限落盘
+pub fn write_secret(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    // 残留的临时文件可能是以默认权限创建的，权限只在创建时生效
+    match std::fs::remove_file(&tmp) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub fn unix_now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
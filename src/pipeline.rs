@@ -0,0 +1,650 @@
+//! 入站消息处理管道
+//!
+//! 收到的 `SignedGossip` 在分发给处理逻辑之前依次经过一组阶段，每个阶段实现
+//! [`MessageStage`]，返回放行或丢弃。内置阶段的顺序为：
+//! 签名 → 自身 → 去重 → 重放（时间戳）→ 准入 → 限速 → 异常检查，全部放行后才应用消息。
+//! 部署方与测试可以在任意内置阶段前插入自定义策略，而无需改动主循环。
+//! 各阶段经共享的 [`FailureReporter`] 上报丢弃原因（同一节点的同类原因按窗口汇总打印），
+//! 管道只负责按阶段统计丢弃数量。
+//! 签名与载荷异常还记为对应节点的不当行为，折算为 gossipsub 评分，使其被逐出 mesh。
+
+use crate::clock::ClockSkewTracker;
+use crate::consensus::{signature_valid, ConsensusEngine, Misbehavior, SignedGossip};
+use crate::failures::{Failure, FailureReporter};
+use crate::ledger::LedgerStore;
+use crate::stats::TrainingStatsManager;
+use crate::types::GgsMessage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 去重缓存的条目上限，超出时提前清理
+const MAX_DEDUP_ENTRIES: usize = 100_000;
+/// 未准入节点的失败事件汇总时共用的键
+const UNADMITTED: &str = "未准入节点";
+
+/// 消息到达的通道
+#[derive(Debug, Clone, Copy)]
+pub enum Channel<'a> {
+    /// gossip：gossipsub 源 PeerId 与传播路径
+    Gossip {
+        source: Option<&'a str>,
+        propagation: &'a str,
+    },
+    /// QUIC 直连：本节点请求过的消息或发给聚合者的更新，重复到达也要处理。
+    /// `from` 为传输层确认的对端身份（证书 PeerId 或远端地址）
+    Direct { from: &'a str },
+    /// QUIC 推送：发送方同时经 gossip 发出的实时与控制消息，哪一路先到就处理哪一路
+    Realtime { from: &'a str },
+}
+
+/// 交给各阶段检查的入站消息
+pub struct Inbound<'a> {
+    pub signed: &'a SignedGossip,
+    pub channel: Channel<'a>,
+    /// 接收时间（Unix 毫秒），重放录制时为录制的接收时间
+    pub now_ms: u64,
+}
+
+impl Inbound<'_> {
+    pub fn sender(&self) -> &str {
+        self.signed.payload.sender()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Drop,
+    /// 消息本身无效（签名或来源不符）：gossip 层拒绝转发，并按 gossipsub 的无效消息规则评分
+    Reject,
+}
+
+/// 管道中的一个处理阶段
+pub trait MessageStage: Send {
+    /// 阶段名，用于插入定位与丢弃统计
+    fn name(&self) -> &'static str;
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict;
+}
+
+#[derive(Clone, Debug)]
+pub struct PipelineConfig {
+    /// 去重窗口：窗口内完全相同的消息只处理一次
+    pub dedup_window: Duration,
+    /// 每个发送者的持续消息速率（条/秒）
+    pub rate_per_sec: f64,
+    /// 每个发送者允许的突发消息数
+    pub rate_burst: f64,
+    /// 同一节点的同类丢弃原因在该窗口内只打印一次，其余汇总后打印
+    pub failure_log_window: Duration,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            dedup_window: Duration::from_secs(300),
+            rate_per_sec: 20.0,
+            rate_burst: 200.0,
+            failure_log_window: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn MessageStage>>,
+    /// 阶段名 -> 丢弃的消息数
+    dropped: HashMap<&'static str, u64>,
+    /// 内置阶段共享的失败上报，自定义管道没有
+    failures: Option<Arc<FailureReporter>>,
+}
+
+impl Pipeline {
+    /// 内置阶段组成的标准管道
+    pub fn standard(
+        config: &PipelineConfig,
+        consensus: Arc<ConsensusEngine>,
+        clock: Arc<ClockSkewTracker>,
+        stats: Arc<TrainingStatsManager>,
+        ledger: Option<Arc<LedgerStore>>,
+        local_peer: &str,
+    ) -> Self {
+        let failures = Arc::new(FailureReporter::new(config.failure_log_window, Arc::clone(&stats)));
+        let mut pipeline = Self {
+            failures: Some(Arc::clone(&failures)),
+            ..Self::default()
+        };
+        pipeline.push(SignatureStage {
+            consensus: Arc::clone(&consensus),
+            failures: Arc::clone(&failures),
+        });
+        pipeline.push(SelfOriginStage::new(local_peer));
+        pipeline.push(DedupStage::new(config.dedup_window));
+        pipeline.push(ReplayStage {
+            clock,
+            stats: Arc::clone(&stats),
+            failures: Arc::clone(&failures),
+        });
+        pipeline.push(AdmissionStage {
+            consensus: Arc::clone(&consensus),
+            ledger,
+            failures: Arc::clone(&failures),
+        });
+        pipeline.push(RateLimitStage::new(config.rate_per_sec, config.rate_burst, Arc::clone(&failures)));
+        pipeline.push(AnomalyStage {
+            consensus,
+            stats,
+            failures,
+        });
+        pipeline
+    }
+
+    pub fn push(&mut self, stage: impl MessageStage + 'static) {
+        self.stages.push(Box::new(stage));
+    }
+
+    /// 在名为 `before` 的阶段之前插入自定义阶段，找不到时追加到末尾
+    #[allow(dead_code)]
+    pub fn insert_before(&mut self, before: &str, stage: impl MessageStage + 'static) {
+        let index = self
+            .stages
+            .iter()
+            .position(|s| s.name() == before)
+            .unwrap_or(self.stages.len());
+        self.stages.insert(index, Box::new(stage));
+    }
+
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|s| s.name()).collect()
+    }
+
+    /// 依次执行各阶段，任一阶段丢弃即停止；全部放行返回 true
+    pub fn run(&mut self, msg: &Inbound<'_>) -> bool {
+        self.verdict(msg) == Verdict::Pass
+    }
+
+    /// 同 [`Pipeline::run`]，返回第一个未放行阶段的判定，供 gossip 层上报校验结果
+    pub fn verdict(&mut self, msg: &Inbound<'_>) -> Verdict {
+        for stage in &mut self.stages {
+            let verdict = stage.check(msg);
+            if verdict != Verdict::Pass {
+                *self.dropped.entry(stage.name()).or_default() += 1;
+                return verdict;
+            }
+        }
+        Verdict::Pass
+    }
+
+    pub fn dropped(&self) -> &HashMap<&'static str, u64> {
+        &self.dropped
+    }
+
+    /// 打印已结束窗口的失败汇总，由节点定期调用
+    pub fn flush_failures(&self, now_ms: u64) {
+        if let Some(failures) = &self.failures {
+            failures.flush(now_ms);
+        }
+    }
+}
+
+/// 双层签名校验；gossip 消息还要求 gossipsub 源与载荷声明的发送者一致
+pub struct SignatureStage {
+    consensus: Arc<ConsensusEngine>,
+    failures: Arc<FailureReporter>,
+}
+
+impl MessageStage for SignatureStage {
+    fn name(&self) -> &'static str {
+        "signature"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        let signed = msg.signed;
+        match msg.channel {
+            Channel::Direct { from } | Channel::Realtime { from } => {
+                if !self.consensus.verify(signed) {
+                    // 按连接身份汇总并扣分：载荷里的发送者在验签前可以随意伪造
+                    self.consensus
+                        .record_misbehavior(from, Misbehavior::InvalidSignature);
+                    self.failures.report(Failure::Signature, from, msg.now_ms, || {
+                        format!("直连消息签名验证失败，来自 {}", from)
+                    });
+                    return Verdict::Drop;
+                }
+            }
+            Channel::Gossip {
+                source: None,
+                propagation,
+            } => {
+                // 后端无法证明来源（如中继转发）：只接受密钥已经固定的发送者，不据此首次固定
+                if !self.consensus.verify_pinned(signed) {
+                    self.failures.report(Failure::Origin, propagation, msg.now_ms, || {
+                        format!(
+                            "拒绝 {} 转发的消息：发送者 {} 的密钥尚未固定或与固定的绑定不符",
+                            propagation,
+                            msg.sender()
+                        )
+                    });
+                    // 签名本身无效时停止传播；只是密钥尚未固定则不能断定消息无效
+                    return if signature_valid(signed) {
+                        Verdict::Drop
+                    } else {
+                        Verdict::Reject
+                    };
+                }
+            }
+            Channel::Gossip {
+                source: Some(source),
+                propagation,
+            } => {
+                // 转发者不一定是作者：不向其扣分，交给 gossipsub 拒绝并停止传播
+                if !self.consensus.verify(signed) {
+                    self.failures.report(Failure::Signature, propagation, msg.now_ms, || {
+                        format!("签名验证失败，来自 {}", propagation)
+                    });
+                    return Verdict::Reject;
+                }
+                // 严格模式下 gossipsub 已验证源签名，这里交叉校验源 PeerId 与链上密钥
+                if source != msg.sender() || !self.consensus.verify_origin(source, signed) {
+                    self.failures.report(Failure::Origin, propagation, msg.now_ms, || {
+                        format!(
+                            "[双重验证] 拒绝 {} 转发的消息：gossipsub 源 {} 与载荷链上身份 {} 不符",
+                            propagation,
+                            source,
+                            msg.sender()
+                        )
+                    });
+                    return Verdict::Reject;
+                }
+            }
+        }
+        Verdict::Pass
+    }
+}
+
+/// 本节点自己的消息经 gossip mesh、中继或 QUIC 回环回到本节点时丢弃，避免据此更新自己的
+/// 质押、拓扑与信任记录。按签名校验后的发送者判断，放在签名阶段之后：冒用本节点身份的
+/// 消息由签名阶段拒绝并打印。回环是正常现象，这里只计数不打印
+pub struct SelfOriginStage {
+    local_peer: String,
+}
+
+impl SelfOriginStage {
+    pub fn new(local_peer: &str) -> Self {
+        Self {
+            local_peer: local_peer.to_string(),
+        }
+    }
+}
+
+impl MessageStage for SelfOriginStage {
+    fn name(&self) -> &'static str {
+        "self"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        if msg.sender() == self.local_peer {
+            Verdict::Drop
+        } else {
+            Verdict::Pass
+        }
+    }
+}
+
+/// 去重：同一条签名消息经直连与 gossip 各到一次，或被重新注入时按消息 id 只处理一次。
+/// 直连的重复消息仍然放行——训练者重发说明上一次确认丢失，需要再次确认，
+/// 重复的更新由版本向量丢弃
+pub struct DedupStage {
+    window_ms: u64,
+    /// 消息 id -> 首次收到时间
+    seen: HashMap<[u8; 32], u64>,
+}
+
+impl DedupStage {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            seen: HashMap::new(),
+        }
+    }
+
+    fn prune(&mut self, now_ms: u64) {
+        let window = self.window_ms;
+        self.seen.retain(|_, at| now_ms.saturating_sub(*at) <= window);
+    }
+}
+
+impl MessageStage for DedupStage {
+    fn name(&self) -> &'static str {
+        "dedup"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        if self.seen.len() >= MAX_DEDUP_ENTRIES {
+            self.prune(msg.now_ms);
+        }
+        let id = msg.signed.message_id();
+        let window = self.window_ms;
+        let duplicate = self
+            .seen
+            .get(&id)
+            .is_some_and(|at| msg.now_ms.saturating_sub(*at) <= window);
+        if !duplicate {
+            self.seen.insert(id, msg.now_ms);
+            return Verdict::Pass;
+        }
+        match msg.channel {
+            Channel::Direct { .. } => Verdict::Pass,
+            Channel::Gossip { .. } | Channel::Realtime { .. } => Verdict::Drop,
+        }
+    }
+}
+
+/// 重放检查：拒绝时间戳超前或过旧的消息，并更新发送者的时钟偏差估计
+pub struct ReplayStage {
+    clock: Arc<ClockSkewTracker>,
+    stats: Arc<TrainingStatsManager>,
+    failures: Arc<FailureReporter>,
+}
+
+impl MessageStage for ReplayStage {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        let sender = msg.sender();
+        match self.clock.observe(sender, msg.signed.sent_at_ms, msg.now_ms) {
+            Ok(()) => {
+                if let Some(skew) = self.clock.estimate_ms(sender) {
+                    self.stats.record_clock_skew(sender, skew);
+                }
+                Verdict::Pass
+            }
+            Err(e) => {
+                self.failures.report(Failure::Replay, sender, msg.now_ms, || {
+                    format!("[时钟] 拒绝 {} 的消息，时间戳不可信: {:?}", sender, e)
+                });
+                Verdict::Drop
+            }
+        }
+    }
+}
+
+/// 未准入的节点只允许发送 `KeyAnnounce`；被清出内存的节点先从持久化账本恢复
+pub struct AdmissionStage {
+    consensus: Arc<ConsensusEngine>,
+    ledger: Option<Arc<LedgerStore>>,
+    failures: Arc<FailureReporter>,
+}
+
+impl AdmissionStage {
+    fn restore_stake(&self, peer: &str) {
+        let Some(store) = &self.ledger else {
+            return;
+        };
+        if self.consensus.has_record(peer) {
+            return;
+        }
+        match store.get(peer) {
+            Ok(Some(entry)) => {
+                println!("[账本] 从持久化账本恢复 {} 的质押与声誉", peer);
+                self.consensus.import_ledger(vec![entry]);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[账本] 读取 {} 的持久化记录失败: {:?}", peer, e),
+        }
+    }
+}
+
+impl MessageStage for AdmissionStage {
+    fn name(&self) -> &'static str {
+        "admission"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        let sender = msg.sender();
+        self.restore_stake(sender);
+        if matches!(msg.signed.payload, GgsMessage::KeyAnnounce { .. })
+            || self.consensus.is_admitted(sender)
+        {
+            return Verdict::Pass;
+        }
+        // 未准入的发送者可以随意伪造，按发送者分窗口挡不住刷屏，所有未准入节点共用一个窗口
+        self.failures.report(Failure::Admission, UNADMITTED, msg.now_ms, || {
+            format!("[准入] 忽略未准入节点 {} 的消息，等待其工作量证明", sender)
+        });
+        Verdict::Drop
+    }
+}
+
+/// 按发送者的令牌桶限速，防止单个节点刷屏；稀疏更新另有按信任分的配额
+pub struct RateLimitStage {
+    rate_per_ms: f64,
+    burst: f64,
+    /// 发送者 -> (剩余令牌, 上次补充时间)
+    buckets: HashMap<String, (f64, u64)>,
+    failures: Arc<FailureReporter>,
+}
+
+impl RateLimitStage {
+    pub fn new(rate_per_sec: f64, burst: f64, failures: Arc<FailureReporter>) -> Self {
+        Self {
+            rate_per_ms: rate_per_sec.max(0.0) / 1000.0,
+            burst: burst.max(1.0),
+            buckets: HashMap::new(),
+            failures,
+        }
+    }
+
+    fn refill(&self, tokens: f64, last_ms: u64, now_ms: u64) -> f64 {
+        (tokens + now_ms.saturating_sub(last_ms) as f64 * self.rate_per_ms).min(self.burst)
+    }
+}
+
+impl MessageStage for RateLimitStage {
+    fn name(&self) -> &'static str {
+        "rate_limit"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        let now = msg.now_ms;
+        if self.buckets.len() >= MAX_DEDUP_ENTRIES {
+            // 已补满的桶与新建的桶等价，可以丢弃
+            let (rate, burst) = (self.rate_per_ms, self.burst);
+            self.buckets.retain(|_, (tokens, last)| {
+                *tokens + now.saturating_sub(*last) as f64 * rate < burst
+            });
+        }
+        let burst = self.burst;
+        let (tokens, last) = self
+            .buckets
+            .get(msg.sender())
+            .copied()
+            .unwrap_or((burst, now));
+        let tokens = self.refill(tokens, last, now);
+        if tokens < 1.0 {
+            self.buckets.insert(msg.sender().to_string(), (tokens, now));
+            self.failures.report(Failure::RateLimit, msg.sender(), now, || {
+                format!("[限速] {} 发送过快，丢弃消息", msg.sender())
+            });
+            return Verdict::Drop;
+        }
+        self.buckets
+            .insert(msg.sender().to_string(), (tokens - 1.0, now));
+        Verdict::Pass
+    }
+}
+
+/// 载荷异常检查：下标与数值长度不一致或含非有限值的更新与快照直接丢弃，
+/// bundle 中的条目逐个检查；丢弃的更新计入统计，并记为发送者的不当行为
+pub struct AnomalyStage {
+    consensus: Arc<ConsensusEngine>,
+    stats: Arc<TrainingStatsManager>,
+    failures: Arc<FailureReporter>,
+}
+
+impl AnomalyStage {
+    fn anomaly(payload: &GgsMessage) -> Option<&'static str> {
+        match payload {
+            GgsMessage::SparseUpdate { update, .. } => {
+                if update.indices.len() != update.values.len() {
+                    Some("稀疏更新的下标与数值长度不一致")
+                } else if !update.is_finite() {
+                    Some("稀疏更新含非有限值")
+                } else {
+                    None
+                }
+            }
+            GgsMessage::DenseSnapshot { snapshot, .. } if snapshot.offset != 0 => {
+                Some("完整快照的偏移不为 0")
+            }
+            GgsMessage::DenseSnapshot { snapshot, .. }
+            | GgsMessage::DenseSlice { snapshot, .. } => {
+                (!snapshot.is_finite()).then_some("密集快照含非有限值")
+            }
+            GgsMessage::TickBundle { messages, .. } => messages.iter().find_map(Self::anomaly),
+            _ => None,
+        }
+    }
+}
+
+impl MessageStage for AnomalyStage {
+    fn name(&self) -> &'static str {
+        "anomaly"
+    }
+
+    fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+        match Self::anomaly(&msg.signed.payload) {
+            Some(reason) => {
+                self.failures.report(Failure::Anomaly, msg.sender(), msg.now_ms, || {
+                    format!("[异常] 丢弃 {} 的消息: {}", msg.sender(), reason)
+                });
+                self.consensus
+                    .record_misbehavior(msg.sender(), Misbehavior::InvalidUpdate);
+                self.stats.record_update_rejected();
+                Verdict::Drop
+            }
+            None => Verdict::Pass,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::ConsensusConfig;
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::role::NodeRole;
+
+    /// 拒绝指定发送者的自定义策略
+    struct Blocklist(&'static str);
+
+    impl MessageStage for Blocklist {
+        fn name(&self) -> &'static str {
+            "blocklist"
+        }
+
+        fn check(&mut self, msg: &Inbound<'_>) -> Verdict {
+            if msg.sender() == self.0 {
+                Verdict::Drop
+            } else {
+                Verdict::Pass
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_stage_dedup_and_rate_limit() {
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let consensus = Arc::new(ConsensusEngine::new(crypto, ConsensusConfig::default()));
+        let heartbeat = |peer: &str, model_version: u64| GgsMessage::Heartbeat {
+            peer: peer.into(),
+            model_hash: "0x0".into(),
+            model_version,
+            role: NodeRole::Trainer,
+            quic_addr: None,
+            compute: None,
+            stake_proof: None,
+            chunk_hashes: None,
+            codecs: Vec::new(),
+            streak: 0,
+            quic_addrs: Vec::new(),
+            listen_addrs: Vec::new(),
+            base_hash: None,
+        };
+        let mut pipeline = Pipeline::default();
+        pipeline.push(SelfOriginStage::new("me"));
+        pipeline.push(DedupStage::new(Duration::from_secs(60)));
+        let stats = Arc::new(TrainingStatsManager::new(String::new(), 0));
+        let failures = Arc::new(FailureReporter::new(Duration::from_secs(60), Arc::clone(&stats)));
+        pipeline.push(RateLimitStage::new(1.0, 2.0, Arc::clone(&failures)));
+        pipeline.push(AnomalyStage {
+            consensus: Arc::clone(&consensus),
+            stats: Arc::clone(&stats),
+            failures,
+        });
+        pipeline.insert_before("rate_limit", Blocklist("mallory"));
+        assert_eq!(
+            pipeline.stage_names(),
+            ["self", "dedup", "blocklist", "rate_limit", "anomaly"]
+        );
+
+        let gossip = Channel::Gossip {
+            source: None,
+            propagation: "test",
+        };
+        let run = |pipeline: &mut Pipeline, signed: &SignedGossip, channel, now_ms| {
+            pipeline.run(&Inbound {
+                signed,
+                channel,
+                now_ms,
+            })
+        };
+        let blocked = consensus.sign("default", heartbeat("mallory", 1)).unwrap();
+        assert!(!run(&mut pipeline, &blocked, gossip, 0));
+        // 本节点自己的消息回环到达，直连与 gossip 都丢弃
+        let own = consensus.sign("default", heartbeat("me", 1)).unwrap();
+        assert!(!run(&mut pipeline, &own, gossip, 0));
+        let direct = Channel::Direct { from: "quic" };
+        assert!(!run(&mut pipeline, &own, direct, 0));
+
+        // 同一条消息经 gossip 或 QUIC 推送重复到达被丢弃，直连重复仍然放行
+        let first = consensus.sign("default", heartbeat("alice", 1)).unwrap();
+        assert!(run(&mut pipeline, &first, gossip, 0));
+        assert!(!run(&mut pipeline, &first, gossip, 10));
+        let realtime = Channel::Realtime { from: "quic" };
+        assert!(run(&mut pipeline, &first, direct, 20));
+        assert!(!run(&mut pipeline, &first, realtime, 25));
+        // 令牌桶已耗尽，一秒后补充一个令牌
+        let second = consensus.sign("default", heartbeat("alice", 2)).unwrap();
+        assert!(!run(&mut pipeline, &second, gossip, 30));
+        let third = consensus.sign("default", heartbeat("alice", 3)).unwrap();
+        assert!(run(&mut pipeline, &third, gossip, 1_100));
+
+        assert_eq!(pipeline.dropped()["self"], 2);
+        assert_eq!(pipeline.dropped()["blocklist"], 1);
+        assert_eq!(pipeline.dropped()["dedup"], 2);
+        assert_eq!(pipeline.dropped()["rate_limit"], 1);
+        assert_eq!(stats.get().failures["rate_limit"], 1);
+
+        // 签名无效的 gossip 消息判为拒绝，由 gossipsub 停止传播；直连消息只丢弃
+        let mut checked = Pipeline::default();
+        checked.push(SignatureStage {
+            consensus: Arc::clone(&consensus),
+            failures: Arc::new(FailureReporter::new(Duration::from_secs(60), stats)),
+        });
+        let mut forged = consensus.sign("default", heartbeat("alice", 4)).unwrap();
+        forged.sent_at_ms += 1;
+        let verdict = |pipeline: &mut Pipeline, channel| {
+            pipeline.verdict(&Inbound {
+                signed: &forged,
+                channel,
+                now_ms: 0,
+            })
+        };
+        assert_eq!(verdict(&mut checked, gossip), Verdict::Reject);
+        assert_eq!(verdict(&mut checked, direct), Verdict::Drop);
+    }
+}
@@ -0,0 +1,75 @@
+//! 低功耗 / 计费网络节省模式
+//!
+//! 面向笔记本和手机热点上的节点：拉长所有周期任务的间隔、缩小稀疏更新的 k、
+//! 停止广播密集快照，并且只发送一部分稀疏更新（优先接收而不是发送）。
+//! 可通过启动参数开启，也可以在运行时经管理接口切换。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Clone, Debug)]
+pub struct PowerSaverConfig {
+    /// 周期任务间隔放大倍数
+    pub interval_factor: f32,
+    /// 稀疏更新 k 的缩小比例
+    pub k_factor: f32,
+    /// 应发送的稀疏更新中实际发送的比例
+    pub send_ratio: f32,
+}
+
+impl Default for PowerSaverConfig {
+    fn default() -> Self {
+        Self {
+            interval_factor: 3.0,
+            k_factor: 0.25,
+            send_ratio: 0.5,
+        }
+    }
+}
+
+pub struct PowerSaver {
+    config: PowerSaverConfig,
+    enabled: AtomicBool,
+}
+
+impl PowerSaver {
+    pub fn new(config: PowerSaverConfig, enabled: bool) -> Self {
+        Self {
+            config,
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 切换模式，返回之前的状态
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::Relaxed)
+    }
+
+    pub fn interval_factor(&self) -> f32 {
+        if self.is_enabled() {
+            self.config.interval_factor
+        } else {
+            1.0
+        }
+    }
+
+    pub fn scale_k(&self, k: usize) -> usize {
+        if self.is_enabled() {
+            ((k as f32 * self.config.k_factor) as usize).max(1)
+        } else {
+            k
+        }
+    }
+
+    pub fn allows_dense_broadcast(&self) -> bool {
+        !self.is_enabled()
+    }
+
+    /// 是否发送本次稀疏更新
+    pub fn should_send(&self) -> bool {
+        !self.is_enabled() || rand::random::<f32>() < self.config.send_ratio
+    }
+}
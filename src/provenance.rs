@@ -0,0 +1,168 @@
+//! 模型来源查询
+//!
+//! 第三方给出一个模型哈希，管理接口 `GET /provenance/<哈希>` 返回本节点记录到的来源信息：
+//! - 哈希对应的会话与版本：当前内存模型，或重新计算哈希后相符的完好检查点
+//! - 最近一次签名心跳宣告持有该哈希的节点
+//! - 分析库中该会话版本不超过此模型的已接受更新，按发送者汇总
+//! - 模型生成前提交、Merkle 根与叶子相符的奖励批次（按 ETH 地址累计的积分），可对照链上提交核对
+//!
+//! 网络对模型没有单独的最终确认与委员会签名，这里只汇总各节点已签名或已上链的记录；接口只读。
+
+use crate::analytics::{AnalyticsDb, PeerContribution};
+use crate::checkpoint;
+use crate::monitor::PeerMonitor;
+use crate::persistence::unix_now_secs;
+use crate::rewards::{self, RewardBatch};
+use crate::session::Session;
+use crate::types::TensorSnapshot;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// 内存模型的 `source`
+const SOURCE_MEMORY: &str = "memory";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub hash: String,
+    pub session: String,
+    pub version: u64,
+    /// `memory` 表示当前内存模型，否则为检查点文件名
+    pub source: String,
+    /// 检查点写入时间（Unix 秒），内存模型为查询时间
+    pub produced_at: u64,
+    /// 最近一次心跳宣告持有该哈希的节点
+    pub announced_by: Vec<String>,
+    /// 未启用分析库时为空
+    pub contributors: Vec<PeerContribution>,
+    pub reward_batches: Vec<RewardBatch>,
+}
+
+struct Located {
+    session: String,
+    version: u64,
+    source: String,
+    produced_at: u64,
+}
+
+/// 查找哈希为 `hash` 的模型并汇总其来源；本节点没有该模型时返回 None
+pub fn lookup(
+    hash: &str,
+    sessions: &[Arc<Session>],
+    state_dir: Option<&Path>,
+    monitor: &PeerMonitor,
+    analytics: Option<&AnalyticsDb>,
+) -> Result<Option<Provenance>> {
+    let Some(model) = sessions.iter().find_map(|session| locate(hash, session)) else {
+        return Ok(None);
+    };
+    let announced_by = monitor
+        .peers()
+        .into_iter()
+        .filter(|p| p.session == model.session && p.model_hash == hash)
+        .map(|p| p.peer)
+        .collect();
+    let contributors = match analytics {
+        Some(db) => db.model_contributions(&model.session, model.version)?,
+        None => Vec::new(),
+    };
+    let mut reward_batches = Vec::new();
+    if let Some(dir) = state_dir {
+        for (_, batch) in rewards::load_batches(dir)? {
+            // 损坏或根与叶子不符的批次不能作为证据
+            match batch {
+                Ok(batch) if batch.epoch <= model.produced_at && batch.root_matches()? => {
+                    reward_batches.push(batch)
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(Some(Provenance {
+        hash: hash.to_string(),
+        session: model.session,
+        version: model.version,
+        source: model.source,
+        produced_at: model.produced_at,
+        announced_by,
+        contributors,
+        reward_batches,
+    }))
+}
+
+fn locate(hash: &str, session: &Session) -> Option<Located> {
+    let model = session.inference.as_ref()?;
+    let snapshot = model.tensor_snapshot();
+    if snapshot.hash() == hash {
+        return Some(Located {
+            session: session.id.clone(),
+            version: snapshot.version,
+            source: SOURCE_MEMORY.to_string(),
+            produced_at: unix_now_secs(),
+        });
+    }
+    let scanned = checkpoint::scan(model.checkpoint_dir()?).ok()?;
+    scanned.into_iter().find_map(|(path, result)| {
+        let checkpoint = result.ok()?;
+        let version = checkpoint.version;
+        if TensorSnapshot::new(checkpoint.params, version).hash() != hash {
+            return None;
+        }
+        let produced_at = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        Some(Located {
+            session: session.id.clone(),
+            version,
+            source: path.file_name()?.to_string_lossy().into_owned(),
+            produced_at,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::InferenceConfig;
+    use crate::role::NodeRole;
+    use crate::topology::TopologyConfig;
+    use crate::types::GeoPoint;
+    use std::time::Duration;
+
+    #[test]
+    fn test_locates_memory_and_checkpoint_models() {
+        let dir = std::env::temp_dir().join(format!("ggs-provenance-{}", rand::random::<u64>()));
+        let config = InferenceConfig {
+            model_dim: 8,
+            seed: Some(7),
+            checkpoint_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let position = GeoPoint { lat: 0.0, lon: 0.0 };
+        let session = Session::new("default".into(), config, position, TopologyConfig::default());
+        let sessions = [Arc::new(session.unwrap())];
+        let model = sessions[0].inference.as_ref().unwrap();
+        model.save_checkpoint(4).unwrap();
+        let saved = model.tensor_snapshot().hash();
+        let monitor = PeerMonitor::new(Duration::from_secs(60));
+        monitor.observe_heartbeat("default", "alice", &saved, 0, NodeRole::Trainer, None);
+        monitor.observe_heartbeat("default", "bob", "0xother", 0, NodeRole::Trainer, None);
+
+        let found = lookup(&saved, &sessions, None, &monitor, None).unwrap().unwrap();
+        assert_eq!(found.source, SOURCE_MEMORY);
+        assert_eq!(found.announced_by, ["alice"]);
+
+        // 内存模型前进后，旧哈希只能在检查点中找到
+        let params = vec![0.5; 8];
+        model.apply_dense_snapshot(&TensorSnapshot::new(params, 9));
+        let found = lookup(&saved, &sessions, None, &monitor, None).unwrap().unwrap();
+        assert!(found.source.ends_with(".ckpt"));
+        assert!(found.produced_at > 0);
+        assert!(lookup("0xmissing", &sessions, None, &monitor, None).unwrap().is_none());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,165 @@
+//! gossipsub 发布失败时的出站队列
+//!
+//! 刚启动或 mesh 暂时为空时 gossipsub 发布会因没有可发送的节点（`InsufficientPeers`）失败。
+//! 失败的消息按顺序放进有界队列，有节点订阅主题后依次重发；队列满时丢弃最旧的消息，
+//! 排队过久的消息重发前丢弃（心跳等时效性消息过期后已无意义），丢弃数量计入统计。
+
+use libp2p::gossipsub::TopicHash;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct PublishQueueConfig {
+    /// 队列上限，超出时丢弃最旧的消息
+    pub max_entries: usize,
+    /// 排队超过该时长的消息不再重发
+    pub max_age: Duration,
+}
+
+impl Default for PublishQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 256,
+            max_age: Duration::from_secs(60),
+        }
+    }
+}
+
+/// 队列计数，随统计导出
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PublishQueueStats {
+    /// 当前排队的消息数
+    pub pending: usize,
+    /// 累计入队的消息数
+    pub queued: u64,
+    /// 重发成功的消息数
+    pub retried: u64,
+    /// 因队列已满、排队过久或重发出错丢弃的消息数
+    pub dropped: u64,
+}
+
+/// 一次重发的结果
+pub enum Attempt {
+    Sent,
+    /// 仍然没有可发送的节点，保留该消息与其后的消息等待下次重发
+    NoPeers,
+    /// 其他错误，丢弃该消息
+    Failed,
+}
+
+struct Queued {
+    topic: TopicHash,
+    data: Vec<u8>,
+    queued_at: Instant,
+}
+
+pub struct PublishQueue {
+    config: PublishQueueConfig,
+    entries: VecDeque<Queued>,
+    stats: PublishQueueStats,
+}
+
+impl PublishQueue {
+    pub fn new(config: PublishQueueConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            stats: PublishQueueStats::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(&mut self, topic: TopicHash, data: Vec<u8>, now: Instant) {
+        while self.entries.len() >= self.config.max_entries.max(1) {
+            self.entries.pop_front();
+            self.stats.dropped += 1;
+        }
+        self.entries.push_back(Queued {
+            topic,
+            data,
+            queued_at: now,
+        });
+        self.stats.queued += 1;
+    }
+
+    /// 按入队顺序重发：过期的丢弃，遇到仍无节点可发时停止，返回重发成功的条数
+    pub fn retry(&mut self, now: Instant, mut publish: impl FnMut(&TopicHash, &[u8]) -> Attempt) -> usize {
+        let mut sent = 0;
+        while let Some(entry) = self.entries.front() {
+            if now.saturating_duration_since(entry.queued_at) > self.config.max_age {
+                self.entries.pop_front();
+                self.stats.dropped += 1;
+                continue;
+            }
+            match publish(&entry.topic, &entry.data) {
+                Attempt::Sent => {
+                    sent += 1;
+                    self.stats.retried += 1;
+                }
+                Attempt::NoPeers => break,
+                Attempt::Failed => self.stats.dropped += 1,
+            }
+            self.entries.pop_front();
+        }
+        sent
+    }
+
+    pub fn stats(&self) -> PublishQueueStats {
+        PublishQueueStats {
+            pending: self.entries.len(),
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_queue_retries_in_order_and_expires() {
+        let mut queue = PublishQueue::new(PublishQueueConfig {
+            max_entries: 3,
+            max_age: Duration::from_secs(60),
+        });
+        let topic = TopicHash::from_raw("ggs-training");
+        let start = Instant::now();
+        for i in 0..4u8 {
+            queue.push(topic.clone(), vec![i], start);
+        }
+        // 超出上限时丢弃最旧的一条
+        assert_eq!(queue.stats().dropped, 1);
+
+        // 仍无节点时一条都不出队
+        assert_eq!(queue.retry(start, |_, _| Attempt::NoPeers), 0);
+        assert_eq!(queue.stats().pending, 3);
+
+        let mut sent = Vec::new();
+        let retried = queue.retry(start, |_, data| {
+            sent.push(data[0]);
+            if data[0] == 2 {
+                Attempt::Failed
+            } else {
+                Attempt::Sent
+            }
+        });
+        assert_eq!((retried, sent), (2, vec![1, 2, 3]));
+        assert!(queue.is_empty());
+
+        queue.push(topic, vec![9], start);
+        assert_eq!(queue.retry(start + Duration::from_secs(61), |_, _| Attempt::Sent), 0);
+        assert_eq!(
+            queue.stats(),
+            PublishQueueStats {
+                pending: 0,
+                queued: 5,
+                retried: 2,
+                dropped: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! 启动就绪状态机
+//!
+//! 刚启动时 gossipsub mesh 尚未形成，publish 会返回 InsufficientPeers。
+//! 节点在至少发现 `min_peers` 个订阅同一主题的 gossip 节点（或等待超时）后才进入 Ready，
+//! 主循环据此决定是否开始周期任务。
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ReadinessState {
+    /// 等待 gossip 节点加入
+    WaitingForPeers,
+    /// 已有足够的 gossip 节点
+    Ready,
+    /// 等待超时，仍以单节点模式运行
+    ReadyAlone,
+}
+
+impl ReadinessState {
+    pub fn is_ready(&self) -> bool {
+        !matches!(self, ReadinessState::WaitingForPeers)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReadinessConfig {
+    pub min_peers: usize,
+    pub timeout: Duration,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            min_peers: 1,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 就绪状态快照（供健康检查接口序列化）
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub state: ReadinessState,
+    pub ready: bool,
+    pub gossip_peers: usize,
+    pub uptime_secs: u64,
+}
+
+pub struct ReadinessGate {
+    config: ReadinessConfig,
+    started: Instant,
+    inner: RwLock<(ReadinessState, usize)>,
+}
+
+impl ReadinessGate {
+    pub fn new(config: ReadinessConfig) -> Self {
+        let state = if config.min_peers == 0 {
+            ReadinessState::Ready
+        } else {
+            ReadinessState::WaitingForPeers
+        };
+        Self {
+            config,
+            started: Instant::now(),
+            inner: RwLock::new((state, 0)),
+        }
+    }
+
+    /// 根据当前 gossip 节点数推进状态，返回 (旧状态, 新状态)
+    pub fn observe(&self, gossip_peers: usize) -> (ReadinessState, ReadinessState) {
+        let mut inner = self.inner.write();
+        let previous = inner.0;
+        inner.1 = gossip_peers;
+        let next = if gossip_peers >= self.config.min_peers {
+            ReadinessState::Ready
+        } else if previous.is_ready() || self.started.elapsed() >= self.config.timeout {
+            // 一旦就绪就不再回退到等待状态，掉线由 publish 失败路径处理
+            ReadinessState::ReadyAlone
+        } else {
+            ReadinessState::WaitingForPeers
+        };
+        inner.0 = next;
+        (previous, next)
+    }
+
+    pub fn state(&self) -> ReadinessState {
+        self.inner.read().0
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state().is_ready()
+    }
+
+    pub fn report(&self) -> ReadinessReport {
+        let (state, gossip_peers) = *self.inner.read();
+        ReadinessReport {
+            state,
+            ready: state.is_ready(),
+            gossip_peers,
+            uptime_secs: self.started.elapsed().as_secs(),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! 经 WebSocket 中继镜像 gossip 消息
+//!
+//! P2P 连接被防火墙阻断的网络中，节点可以配置一个中继服务器：本节点发布的每条消息
+//! 同时发给中继，中继转发来的其他节点消息按 gossip 入站流程处理（签名、去重、时间戳等校验不变）。
+//! 连接时以 `Authorization: Bearer <token>` 认证；断线后按指数退避重连，断线期间的消息不补发，
+//! 对方以 gossip 或下一轮消息为准。完全依赖中继的节点牺牲了去中心化，但仍能参与训练。
+
+use crate::consensus::SignedGossip;
+use crate::proxy::ProxyConfig;
+use futures::{SinkExt, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 重连的初始间隔与上限
+const RECONNECT_INITIAL: Duration = Duration::from_secs(2);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default)]
+pub struct RelayConfig {
+    /// 中继地址（`ws://` 或 `wss://`），None 表示不使用中继
+    pub url: Option<String>,
+    /// 连接时出示的访问令牌
+    pub token: Option<String>,
+}
+
+/// 中继连接的句柄，连接在后台任务中维护
+pub struct RelayClient {
+    outbound: mpsc::UnboundedSender<String>,
+    inbound: mpsc::UnboundedReceiver<SignedGossip>,
+    connected: Arc<AtomicBool>,
+}
+
+impl RelayClient {
+    /// 未配置中继地址时返回 None；配置了代理时经代理连接中继
+    pub fn spawn(config: RelayConfig, proxy: ProxyConfig) -> Option<Self> {
+        let url = config.url?;
+        let (outbound, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, inbound) = mpsc::unbounded_channel();
+        let connected = Arc::new(AtomicBool::new(false));
+        tokio::spawn(run(url, config.token, proxy, out_rx, in_tx, Arc::clone(&connected)));
+        Some(Self {
+            outbound,
+            inbound,
+            connected,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// 把本节点发布的消息镜像到中继
+    pub fn mirror(&self, signed: &SignedGossip) {
+        match serde_json::to_string(signed) {
+            Ok(text) => {
+                let _ = self.outbound.send(text);
+            }
+            Err(e) => eprintln!("[中继] 序列化消息失败: {:?}", e),
+        }
+    }
+
+    /// 下一条中继转发来的消息
+    pub async fn recv(&mut self) -> Option<SignedGossip> {
+        self.inbound.recv().await
+    }
+}
+
+async fn run(
+    url: String,
+    token: Option<String>,
+    proxy: ProxyConfig,
+    mut out_rx: mpsc::UnboundedReceiver<String>,
+    in_tx: mpsc::UnboundedSender<SignedGossip>,
+    connected: Arc<AtomicBool>,
+) {
+    let mut delay = RECONNECT_INITIAL;
+    loop {
+        let result = session(&url, token.as_deref(), &proxy, &mut out_rx, &in_tx, &connected).await;
+        connected.store(false, Ordering::Relaxed);
+        match result {
+            Ok(()) => return,
+            Err(e) => eprintln!("[中继] 与 {} 的连接中断: {:?}，{:?} 后重连", url, e, delay),
+        }
+        tokio::time::sleep(delay).await;
+        // 断线期间积压的消息已经过时，直接丢弃
+        while out_rx.try_recv().is_ok() {}
+        delay = (delay * 2).min(RECONNECT_MAX);
+        if in_tx.is_closed() {
+            return;
+        }
+    }
+}
+
+/// 维持一次连接；节点关闭（两端通道都已关闭）时返回 Ok
+async fn session(
+    url: &str,
+    token: Option<&str>,
+    proxy: &ProxyConfig,
+    out_rx: &mut mpsc::UnboundedReceiver<String>,
+    in_tx: &mpsc::UnboundedSender<SignedGossip>,
+    connected: &AtomicBool,
+) -> anyhow::Result<()> {
+    let mut request = url.into_client_request()?;
+    if let Some(token) = token {
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, format!("Bearer {}", token).parse()?);
+    }
+    let (ws, _) = if proxy.enabled() {
+        let uri = request.uri();
+        let host = uri.host().ok_or_else(|| anyhow::anyhow!("中继地址缺少主机名"))?;
+        let port = uri
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+        let stream = proxy.connect(host.trim_matches(['[', ']']), port).await?;
+        tokio_tungstenite::client_async_tls(request, stream).await?
+    } else {
+        tokio_tungstenite::connect_async(request).await?
+    };
+    println!("[中继] 已连接 {}", url);
+    connected.store(true, Ordering::Relaxed);
+    let (mut sink, mut stream) = ws.split();
+    loop {
+        tokio::select! {
+            text = out_rx.recv() => {
+                let Some(text) = text else {
+                    let _ = sink.close().await;
+                    return Ok(());
+                };
+                sink.send(Message::Text(text)).await?;
+            }
+            frame = stream.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => anyhow::bail!("中继关闭了连接"),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                match SignedGossip::from_json(text.as_bytes()) {
+                    Ok(signed) => {
+                        if in_tx.send(signed).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => eprintln!("[中继] 忽略无法解析的消息: {:?}", e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::types::GgsMessage;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    #[tokio::test]
+    async fn test_mirrors_and_ingests_through_authenticated_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // 回显中继：拒绝令牌不符的连接，把收到的消息原样发回
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                // 回调签名由 tungstenite 规定
+                #[allow(clippy::result_large_err)]
+                let check = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+                    let authorized = req
+                        .headers()
+                        .get(AUTHORIZATION)
+                        .is_some_and(|v| v == "Bearer secret");
+                    if authorized {
+                        Ok(resp)
+                    } else {
+                        Err(ErrorResponse::new(Some("unauthorized".into())))
+                    }
+                };
+                let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, check).await else {
+                    continue;
+                };
+                let (mut sink, mut stream) = ws.split();
+                while let Some(Ok(msg)) = stream.next().await {
+                    if msg.is_text() && sink.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let engine = ConsensusEngine::new(crypto, ConsensusConfig::default());
+        let signed = engine
+            .sign(
+                "default",
+                GgsMessage::UpdateAck {
+                    target: "peer".into(),
+                    seq: 7,
+                    sender: "me".into(),
+                },
+            )
+            .unwrap();
+
+        let mut client = RelayClient::spawn(
+            RelayConfig {
+                url: Some(url),
+                token: Some("secret".into()),
+            },
+            ProxyConfig::default(),
+        )
+        .unwrap();
+        // 连接建立前发出的消息可能在重连前被丢弃，反复镜像直到收到回显
+        let echoed = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                client.mirror(&signed);
+                if let Ok(Some(echoed)) =
+                    tokio::time::timeout(Duration::from_millis(200), client.recv()).await
+                {
+                    return echoed;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(engine.verify(&echoed));
+        assert_eq!(echoed.sent_at_ms, signed.sent_at_ms);
+
+        assert!(RelayClient::spawn(RelayConfig::default(), ProxyConfig::default()).is_none());
+    }
+}
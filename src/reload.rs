@@ -0,0 +1,106 @@
+//! 配置文件热加载
+//!
+//! 启动时记录 `--config` 文件的修改时间与启动时生效的带宽、拓扑与调度配置；
+//! 文件被修改（主循环在心跳时检查）或收到 SIGHUP 时重新解析文件，
+//! 把其中的可热加载部分叠加到启动配置上，交给主循环应用。
+//! `GGS_*` 环境变量在重新加载时同样叠加在文件之上，保持与启动时相同的优先级。
+//! 模型、共识账本等运行状态不受影响，其余配置项仍需重启才能生效。
+
+use crate::comms::BandwidthBudgetConfig;
+use crate::config::ConfigFile;
+use crate::scheduler::ScheduleConfig;
+use crate::topology::TopologyConfig;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 可在运行时替换的配置（尚未按角色调整）
+#[derive(Clone)]
+pub struct RuntimeConfig {
+    pub bandwidth: BandwidthBudgetConfig,
+    pub topology: TopologyConfig,
+    pub schedule: ScheduleConfig,
+}
+
+pub struct ConfigReloader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    /// 启动时的配置（已包含文件与命令行参数），热加载的文件内容叠加在它之上
+    base: RuntimeConfig,
+}
+
+impl ConfigReloader {
+    pub fn new(path: PathBuf, base: RuntimeConfig) -> Self {
+        let modified = modified_at(&path);
+        Self {
+            path,
+            modified,
+            base,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 文件修改时间与上次加载时不同
+    pub fn changed(&self) -> bool {
+        modified_at(&self.path) != self.modified
+    }
+
+    /// 重新解析文件；解析失败时保留当前配置，下次修改后再试
+    pub fn reload(&mut self) -> Result<RuntimeConfig> {
+        self.modified = modified_at(&self.path);
+        let file = ConfigFile::load(Some(&self.path))?.unwrap_or_default();
+        let mut config = self.base.clone();
+        file.comms.apply_bandwidth(&mut config.bandwidth);
+        file.topology.apply(&mut config.topology);
+        file.schedule.apply(&mut config.schedule);
+        Ok(config)
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reload_layers_file_over_startup_config() {
+        let path = std::env::temp_dir().join(format!("ggs-reload-{}.toml", rand::random::<u64>()));
+        std::fs::write(&path, "[topology]\nmax_neighbors = 4\n").unwrap();
+        let base = RuntimeConfig {
+            bandwidth: BandwidthBudgetConfig::default(),
+            topology: TopologyConfig {
+                failover_pool: 7,
+                ..TopologyConfig::default()
+            },
+            schedule: ScheduleConfig::default(),
+        };
+        let mut reloader = ConfigReloader::new(path.clone(), base);
+        assert!(!reloader.changed());
+
+        std::fs::write(
+            &path,
+            "[comms]\nsparse_per_window = 5\n\n[topology]\ngeo_weight = 0.8\n\n[schedule]\nheartbeat_secs = 3\n",
+        )
+        .unwrap();
+        let config = reloader.reload().unwrap();
+        assert!(!reloader.changed());
+        assert_eq!(config.bandwidth.sparse_per_window, 5);
+        assert_eq!(config.topology.geo_weight, 0.8);
+        // 文件中没有的项取启动配置，而不是上一次加载的文件内容
+        assert_eq!(config.topology.failover_pool, 7);
+        assert_eq!(config.topology.max_neighbors, TopologyConfig::default().max_neighbors);
+        assert_eq!(config.schedule.heartbeat_interval, Duration::from_secs(3));
+
+        // 无效文件报错，不影响当前配置
+        std::fs::write(&path, "[topology]\nmax_neighbours = 3\n").unwrap();
+        assert!(reloader.reload().is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+//! QUIC 直连稀疏更新的确认与重传
+//!
+//! 训练者经 QUIC 直接发给聚合者的稀疏更新不经过 gossip，单向流写入成功并不代表对方已处理。
+//! 聚合者验签通过后回复 `UpdateAck`；发送方在退避期满仍未收到确认时重发同一条签名消息，
+//! 退避逐次翻倍，重试次数用尽后改经 gossipsub 发布，避免更新悄悄丢失。
+
+use crate::consensus::SignedGossip;
+use crate::types::GgsMessage;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use tokio::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct RetransmitConfig {
+    /// 首次发送之后的最大重发次数
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetransmitConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(16),
+        }
+    }
+}
+
+struct Pending {
+    signed: SignedGossip,
+    peer: String,
+    addr: SocketAddr,
+    attempts: u32,
+    backoff: Duration,
+    next_at: Instant,
+}
+
+/// 到期的待确认更新
+#[derive(Debug)]
+pub enum Retransmit {
+    /// 再次直连发送（attempt 从 1 开始计数）
+    Retry {
+        signed: SignedGossip,
+        peer: String,
+        addr: SocketAddr,
+        attempt: u32,
+    },
+    /// 重试用尽，改经 gossip 发布
+    Fallback { signed: SignedGossip, peer: String },
+}
+
+/// (会话, 序号) -> 待确认的直连更新
+pub struct AckTracker {
+    config: RetransmitConfig,
+    pending: HashMap<(String, u64), Pending>,
+}
+
+impl AckTracker {
+    pub fn new(config: RetransmitConfig) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 记录一条已直连发出的稀疏更新；未编号的更新无法确认，不追踪
+    pub fn track(&mut self, signed: SignedGossip, peer: &str, addr: SocketAddr, now: Instant) {
+        let GgsMessage::SparseUpdate { update, .. } = &signed.payload else {
+            return;
+        };
+        if update.seq == 0 {
+            return;
+        }
+        let key = (signed.session.clone(), update.seq);
+        let backoff = self.config.initial_backoff;
+        self.pending.insert(
+            key,
+            Pending {
+                signed,
+                peer: peer.to_string(),
+                addr,
+                attempts: 0,
+                backoff,
+                next_at: now + backoff,
+            },
+        );
+    }
+
+    /// 处理确认；只接受原目标节点的确认
+    pub fn ack(&mut self, session: &str, peer: &str, seq: u64) -> bool {
+        let key = (session.to_string(), seq);
+        match self.pending.get(&key) {
+            Some(pending) if pending.peer == peer => {
+                self.pending.remove(&key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|p| p.next_at).min()
+    }
+
+    /// 取出到期项：未用尽重试的重新排期并返回重发，用尽的移出并返回回落
+    pub fn take_due(&mut self, now: Instant) -> Vec<Retransmit> {
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.next_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut out = Vec::with_capacity(due.len());
+        for key in due {
+            let Some(pending) = self.pending.get_mut(&key) else {
+                continue;
+            };
+            if pending.attempts >= self.config.max_retries {
+                if let Some(pending) = self.pending.remove(&key) {
+                    out.push(Retransmit::Fallback {
+                        signed: pending.signed,
+                        peer: pending.peer,
+                    });
+                }
+                continue;
+            }
+            pending.attempts += 1;
+            pending.backoff = (pending.backoff * 2).min(self.config.max_backoff);
+            pending.next_at = now + pending.backoff;
+            out.push(Retransmit::Retry {
+                signed: pending.signed.clone(),
+                peer: pending.peer.clone(),
+                addr: pending.addr,
+                attempt: pending.attempts,
+            });
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::session::DEFAULT_SESSION;
+    use crate::types::SparseUpdate;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_retries_with_backoff_then_falls_back() {
+        let crypto = CryptoSuite::new(CryptoConfig::default()).unwrap();
+        let engine = ConsensusEngine::new(Arc::new(crypto), ConsensusConfig::default());
+        let update = |seq| GgsMessage::SparseUpdate {
+            update: SparseUpdate {
+                indices: vec![1],
+                values: vec![0.5],
+                version: 1,
+                seq,
+            },
+            sender: "me".to_string(),
+        };
+        let addr: SocketAddr = "127.0.0.1:9300".parse().unwrap();
+        let mut tracker = AckTracker::new(RetransmitConfig {
+            max_retries: 2,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+        });
+        let start = Instant::now();
+        tracker.track(engine.sign(DEFAULT_SESSION, update(1)).unwrap(), "agg", addr, start);
+        tracker.track(engine.sign(DEFAULT_SESSION, update(2)).unwrap(), "agg", addr, start);
+        // 其他节点的确认无效
+        assert!(!tracker.ack(DEFAULT_SESSION, "other", 2));
+        assert!(tracker.ack(DEFAULT_SESSION, "agg", 2));
+
+        assert!(tracker.take_due(start).is_empty());
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!(matches!(
+            tracker.take_due(at(1)).as_slice(),
+            [Retransmit::Retry { attempt: 1, .. }]
+        ));
+        // 退避翻倍到 2 秒
+        assert!(tracker.take_due(at(2)).is_empty());
+        assert!(matches!(
+            tracker.take_due(at(3)).as_slice(),
+            [Retransmit::Retry { attempt: 2, .. }]
+        ));
+        // 封顶 3 秒后重试用尽，回落到 gossip
+        assert_eq!(tracker.next_due(), Some(at(6)));
+        assert!(matches!(
+            tracker.take_due(at(6)).as_slice(),
+            [Retransmit::Fallback { .. }]
+        ));
+        assert_eq!(tracker.next_due(), None);
+    }
+}
@@ -0,0 +1,611 @@
+//! 链上奖励提交
+//!
+//! 本节点按 ETH 地址累计其他节点被接受的更新次数（积分），每个周期把
+//! 地址 -> 积分 打包为 Merkle 树，用本节点的 ETH 密钥签名一笔调用奖励合约
+//! `submitRewards(bytes32 root, uint256 epoch)` 的交易并经 JSON-RPC 发送。
+//! 叶子为 `keccak256(address || uint256 credits)`，内部节点按字节序排序后拼接哈希
+//! （与 OpenZeppelin MerkleProof 一致）；每批的叶子写入状态目录，供领取时构造证明。
+//!
+//! 交易使用 EIP-155 legacy 格式：nonce 取本地记录与链上 pending 计数的较大值，
+//! gas 用 `eth_estimateGas` 估算，失败时退回配置值。nonce 一经选定就记在批次上，
+//! 发送失败或超时后按指数退避、以同一 nonce 抬高 gas 价格重发，替换而不是重复提交；
+//! 只有该 nonce 被其他交易占用（本批的交易都没有上链）时才换新的 nonce。
+//! 尚未选定 nonce 就超过重试次数的批次，积分并入下一批；已选定 nonce 的批次不再重试，
+//! 标记为搁置写入状态目录并计入统计，由运维确认其交易是否上链后处理。
+
+use crate::crypto::CryptoSuite;
+use crate::names::rpc_call;
+use crate::persistence::{unix_now_secs, write_atomic};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const SUBMIT_SIGNATURE: &str = "submitRewards(bytes32,uint256)";
+
+#[derive(Clone, Debug)]
+pub struct RewardConfig {
+    /// 以太坊 JSON-RPC 端点（与 ENS 解析共用 `--eth-rpc`）
+    pub rpc: Option<String>,
+    /// 奖励合约地址，None 表示不提交
+    pub contract: Option<String>,
+    pub chain_id: u64,
+    /// 两次提交之间的最短间隔
+    pub interval: Duration,
+    /// gas 估算失败时使用的 gas 上限
+    pub gas_limit: u64,
+    /// 每批最多提交的次数，也是 gas 价格抬高的次数上限
+    pub max_attempts: u32,
+    /// 首次重试的等待时间，之后逐次翻倍
+    pub retry_backoff: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            rpc: None,
+            contract: None,
+            chain_id: 1,
+            interval: Duration::from_secs(3600),
+            gas_limit: 200_000,
+            max_attempts: 5,
+            retry_backoff: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardLeaf {
+    pub address: String,
+    pub credits: u64,
+}
+
+/// 一批待提交的积分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardBatch {
+    pub epoch: u64,
+    pub root: String,
+    pub leaves: Vec<RewardLeaf>,
+    pub attempts: u32,
+    pub tx_hash: Option<String>,
+    /// 本批交易使用的 nonce，选定后重试沿用
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    /// 以该 nonce 签过的各次交易的哈希（重试抬价后哈希不同），用于确认是否已有一笔上链
+    #[serde(default)]
+    pub sent: Vec<String>,
+    /// 已选定 nonce 但重试次数用尽、不再自动提交的批次
+    #[serde(default)]
+    pub parked: bool,
+}
+
+impl RewardBatch {
+    /// 由叶子重新计算的 Merkle 根与记录的根一致
+    pub fn root_matches(&self) -> Result<bool> {
+        Ok(format!("0x{}", hex::encode(merkle_root(&self.leaves)?)) == self.root)
+    }
+}
+
+/// 奖励提交计数，随统计导出
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RewardStats {
+    /// 当前待提交批次已失败的次数
+    pub pending_attempts: u32,
+    /// 重试用尽后搁置的批次数
+    pub parked: u64,
+}
+
+/// 状态目录中保存的各批奖励记录（按纪元排序），解析失败的文件附带错误
+pub fn load_batches(dir: &Path) -> Result<Vec<(PathBuf, Result<RewardBatch>)>> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_batch = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("reward_batch_") && n.ends_with(".json"));
+        if !is_batch {
+            continue;
+        }
+        let batch = std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(serde_json::from_slice::<RewardBatch>(&bytes)?));
+        out.push((path, batch));
+    }
+    out.sort_by_key(|(_, batch)| batch.as_ref().map_or(0, |b| b.epoch));
+    Ok(out)
+}
+
+struct SubmitterState {
+    /// ETH 地址（小写） -> 尚未打包的积分
+    credits: BTreeMap<String, u64>,
+    pending: Option<RewardBatch>,
+    /// 下一笔交易的 nonce 下限，None 时完全以链上计数为准
+    next_nonce: Option<u64>,
+    last_batch: Instant,
+    next_attempt: Instant,
+    in_flight: bool,
+    parked: u64,
+}
+
+pub struct RewardSubmitter {
+    config: RewardConfig,
+    crypto: Arc<CryptoSuite>,
+    /// 批次记录目录，None 表示不保存
+    dir: Option<PathBuf>,
+    state: Mutex<SubmitterState>,
+}
+
+impl RewardSubmitter {
+    pub fn new(config: RewardConfig, crypto: Arc<CryptoSuite>, dir: Option<PathBuf>) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            crypto,
+            dir,
+            state: Mutex::new(SubmitterState {
+                credits: BTreeMap::new(),
+                pending: None,
+                next_nonce: None,
+                last_batch: now,
+                next_attempt: now,
+                in_flight: false,
+                parked: 0,
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.contract.is_some() && self.config.rpc.is_some()
+    }
+
+    pub fn stats(&self) -> RewardStats {
+        let state = self.state.lock();
+        RewardStats {
+            pending_attempts: state.pending.as_ref().map_or(0, |b| b.attempts),
+            parked: state.parked,
+        }
+    }
+
+    /// 记录某地址被接受的贡献
+    pub fn credit(&self, eth_address: &str, units: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+        *self
+            .state
+            .lock()
+            .credits
+            .entry(eth_address.to_lowercase())
+            .or_default() += units;
+    }
+
+    /// 到达提交周期时打包积分，并在后台发送（或重试）待提交的批次
+    pub fn maybe_submit(self: &Arc<Self>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let (mut batch, nonce) = {
+            let mut state = self.state.lock();
+            if state.in_flight {
+                return;
+            }
+            if state.pending.is_none() {
+                if state.credits.is_empty() || state.last_batch.elapsed() < self.config.interval {
+                    return;
+                }
+                let leaves: Vec<RewardLeaf> = std::mem::take(&mut state.credits)
+                    .into_iter()
+                    .map(|(address, credits)| RewardLeaf { address, credits })
+                    .collect();
+                let root = match merkle_root(&leaves) {
+                    Ok(root) => root,
+                    Err(e) => {
+                        eprintln!("[链上奖励] 构造 Merkle 树失败: {:?}", e);
+                        return;
+                    }
+                };
+                state.pending = Some(RewardBatch {
+                    epoch: unix_now_secs(),
+                    root: format!("0x{}", hex::encode(root)),
+                    leaves,
+                    attempts: 0,
+                    tx_hash: None,
+                    nonce: None,
+                    sent: Vec::new(),
+                    parked: false,
+                });
+                state.last_batch = Instant::now();
+            }
+            if Instant::now() < state.next_attempt {
+                return;
+            }
+            state.in_flight = true;
+            (state.pending.clone().expect("pending batch"), state.next_nonce)
+        };
+        let submitter = self.clone();
+        tokio::spawn(async move {
+            // 超时时 submit 已写入批次的 nonce 与交易哈希保留下来，重试时沿用
+            let result = tokio::time::timeout(
+                submitter.config.request_timeout,
+                submitter.submit(&mut batch, nonce),
+            )
+            .await
+            .map_err(|_| anyhow!("timed out"))
+            .and_then(|r| r);
+            submitter.finish(batch, result);
+        });
+    }
+
+    fn finish(&self, mut batch: RewardBatch, result: Result<(String, u64)>) {
+        let mut state = self.state.lock();
+        state.in_flight = false;
+        match result {
+            Ok((tx_hash, nonce)) => {
+                println!(
+                    "[链上奖励] 已提交批次 {} (root {}, {} 个地址)，交易 {}",
+                    batch.epoch,
+                    batch.root,
+                    batch.leaves.len(),
+                    tx_hash
+                );
+                batch.tx_hash = Some(tx_hash);
+                state.pending = None;
+                state.next_nonce = Some(nonce + 1);
+                if let Err(e) = self.save_batch(&batch) {
+                    eprintln!("[链上奖励] 保存批次记录失败: {:?}", e);
+                }
+            }
+            Err(e) => {
+                batch.attempts = (batch.attempts + 1).min(self.config.max_attempts.max(1));
+                state.next_nonce = None;
+                if batch.attempts < self.config.max_attempts {
+                    let exponent = batch.attempts - 1;
+                    let backoff = self.config.retry_backoff * 2u32.pow(exponent);
+                    eprintln!(
+                        "[链上奖励] 批次 {} 第 {} 次提交失败，{:?} 后重试: {:?}",
+                        batch.epoch, batch.attempts, backoff, e
+                    );
+                    state.next_attempt = Instant::now() + backoff;
+                    state.pending = Some(batch);
+                } else if batch.nonce.is_none() {
+                    eprintln!(
+                        "[链上奖励] 批次 {} 提交失败 {} 次，积分并入下一批: {:?}",
+                        batch.epoch, batch.attempts, e
+                    );
+                    for leaf in batch.leaves {
+                        *state.credits.entry(leaf.address).or_default() += leaf.credits;
+                    }
+                    state.pending = None;
+                } else {
+                    // 已选定 nonce 的批次可能已有交易在内存池中，不能把积分并入下一批重复提交，
+                    // 也不再以更高的 gas 价格无限重发
+                    eprintln!(
+                        "[链上奖励] 错误: 批次 {} (nonce {:?}) 提交失败 {} 次，已搁置，需人工确认交易 {:?} 是否上链: {:?}",
+                        batch.epoch, batch.nonce, batch.attempts, batch.sent, e
+                    );
+                    batch.parked = true;
+                    state.parked += 1;
+                    state.pending = None;
+                    if let Err(e) = self.save_batch(&batch) {
+                        eprintln!("[链上奖励] 保存搁置批次记录失败: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 构造、签名并发送交易，返回 (交易哈希, 使用的 nonce)。选定的 nonce 与签出的交易哈希
+    /// 在发送前写入批次；该 nonce 已上链时，若是本批的交易则视为已提交，否则换用新 nonce
+    async fn submit(&self, batch: &mut RewardBatch, nonce_hint: Option<u64>) -> Result<(String, u64)> {
+        let rpc = self.config.rpc.as_deref().ok_or_else(|| anyhow!("no eth rpc"))?;
+        let contract = self.config.contract.as_deref().ok_or_else(|| anyhow!("no contract"))?;
+        let from = self.crypto.eth_address();
+
+        let nonce = match batch.nonce {
+            Some(nonce) => {
+                let mined =
+                    quantity(&rpc_call(rpc, "eth_getTransactionCount", json!([from, "latest"])).await?)?
+                        as u64;
+                if mined > nonce {
+                    for hash in &batch.sent {
+                        if !rpc_call(rpc, "eth_getTransactionReceipt", json!([hash])).await?.is_null() {
+                            return Ok((hash.clone(), nonce));
+                        }
+                    }
+                    batch.nonce = None;
+                    batch.sent.clear();
+                    return Err(anyhow!("nonce {} was used by another transaction", nonce));
+                }
+                nonce
+            }
+            None => {
+                let chain_nonce =
+                    quantity(&rpc_call(rpc, "eth_getTransactionCount", json!([from, "pending"])).await?)?
+                        as u64;
+                let nonce = nonce_hint.map_or(chain_nonce, |n| n.max(chain_nonce));
+                batch.nonce = Some(nonce);
+                nonce
+            }
+        };
+        let gas_price = replacement_gas_price(
+            quantity(&rpc_call(rpc, "eth_gasPrice", json!([])).await?)?,
+            batch.attempts,
+            self.config.max_attempts,
+        );
+
+        let mut data = Keccak256::digest(SUBMIT_SIGNATURE.as_bytes())[..4].to_vec();
+        data.extend(hex::decode(batch.root.trim_start_matches("0x"))?);
+        data.extend(uint_word(batch.epoch as u128));
+        let data_hex = format!("0x{}", hex::encode(&data));
+        let gas_limit = match rpc_call(
+            rpc,
+            "eth_estimateGas",
+            json!([{"from": from, "to": contract, "data": data_hex}]),
+        )
+        .await
+        .and_then(|v| quantity(&v))
+        {
+            Ok(estimate) => (estimate as u64) * 6 / 5,
+            Err(e) => {
+                eprintln!("[链上奖励] gas 估算失败，使用默认上限: {:?}", e);
+                self.config.gas_limit
+            }
+        };
+
+        let tx = LegacyTx {
+            nonce,
+            gas_price,
+            gas_limit,
+            to: address_bytes(contract)?,
+            value: 0,
+            data,
+        };
+        let raw = tx.sign(&self.crypto, self.config.chain_id)?;
+        batch
+            .sent
+            .push(format!("0x{}", hex::encode(Keccak256::digest(&raw))));
+        let hash = rpc_call(
+            rpc,
+            "eth_sendRawTransaction",
+            json!([format!("0x{}", hex::encode(raw))]),
+        )
+        .await?;
+        let hash = hash.as_str().ok_or_else(|| anyhow!("missing tx hash"))?;
+        Ok((hash.to_string(), nonce))
+    }
+
+    fn save_batch(&self, batch: &RewardBatch) -> Result<()> {
+        match &self.dir {
+            Some(dir) => write_atomic(
+                &dir.join(format!("reward_batch_{}.json", batch.epoch)),
+                &serde_json::to_vec_pretty(batch)?,
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 每次重试抬高 12.5% 以替换仍在内存池中的旧交易，最多抬高 `max_attempts` 次
+fn replacement_gas_price(base: u128, attempts: u32, max_attempts: u32) -> u128 {
+    base * (8 + attempts.min(max_attempts) as u128) / 8
+}
+
+/// EIP-155 legacy 交易
+struct LegacyTx {
+    nonce: u64,
+    gas_price: u128,
+    gas_limit: u64,
+    to: [u8; 20],
+    value: u128,
+    data: Vec<u8>,
+}
+
+impl LegacyTx {
+    fn fields(&self) -> Vec<Vec<u8>> {
+        vec![
+            rlp_uint(self.nonce as u128),
+            rlp_uint(self.gas_price),
+            rlp_uint(self.gas_limit as u128),
+            rlp_bytes(&self.to),
+            rlp_uint(self.value),
+            rlp_bytes(&self.data),
+        ]
+    }
+
+    fn signing_hash(&self, chain_id: u64) -> [u8; 32] {
+        let mut fields = self.fields();
+        fields.extend([rlp_uint(chain_id as u128), rlp_uint(0), rlp_uint(0)]);
+        Keccak256::digest(rlp_list(&fields)).into()
+    }
+
+    fn sign(&self, crypto: &CryptoSuite, chain_id: u64) -> Result<Vec<u8>> {
+        let (signature, recovery_id) = crypto.sign_eth_digest(&self.signing_hash(chain_id))?;
+        let v = recovery_id as u128 + chain_id as u128 * 2 + 35;
+        let mut fields = self.fields();
+        fields.extend([
+            rlp_uint(v),
+            rlp_bytes(strip_zeros(&signature[..32])),
+            rlp_bytes(strip_zeros(&signature[32..])),
+        ]);
+        Ok(rlp_list(&fields))
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn rlp_length_prefix(len: usize, offset: u8) -> Vec<u8> {
+    if len <= 55 {
+        return vec![offset + len as u8];
+    }
+    let len_bytes = (len as u64).to_be_bytes();
+    let len_bytes = strip_zeros(&len_bytes);
+    let mut out = vec![offset + 55 + len_bytes.len() as u8];
+    out.extend_from_slice(len_bytes);
+    out
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_length_prefix(bytes.len(), 0x80);
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn rlp_uint(value: u128) -> Vec<u8> {
+    rlp_bytes(strip_zeros(&value.to_be_bytes()))
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload: Vec<u8> = items.concat();
+    let mut out = rlp_length_prefix(payload.len(), 0xc0);
+    out.extend(payload);
+    out
+}
+
+fn uint_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn address_bytes(address: &str) -> Result<[u8; 20]> {
+    hex::decode(address.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow!("invalid eth address {}", address))
+}
+
+/// 解析 JSON-RPC 返回的十六进制数量
+pub fn quantity(value: &Value) -> Result<u128> {
+    let s = value.as_str().ok_or_else(|| anyhow!("expected hex quantity"))?;
+    Ok(u128::from_str_radix(s.trim_start_matches("0x"), 16)?)
+}
+
+fn merkle_leaf(leaf: &RewardLeaf) -> Result<[u8; 32]> {
+    let mut hasher = Keccak256::new();
+    hasher.update(address_bytes(&leaf.address)?);
+    hasher.update(uint_word(leaf.credits as u128));
+    Ok(hasher.finalize().into())
+}
+
+/// 叶子按哈希排序，逐层两两排序拼接哈希，奇数个时最后一个直接上移
+fn merkle_root(leaves: &[RewardLeaf]) -> Result<[u8; 32]> {
+    let mut level = leaves.iter().map(merkle_leaf).collect::<Result<Vec<_>>>()?;
+    level.sort();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => {
+                    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+                    let mut hasher = Keccak256::new();
+                    hasher.update(lo);
+                    hasher.update(hi);
+                    hasher.finalize().into()
+                }
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    Ok(level.first().copied().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eip155_signing_hash_and_merkle_root() {
+        // EIP-155 规范中的示例交易
+        let tx = LegacyTx {
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: address_bytes("0x3535353535353535353535353535353535353535").unwrap(),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+        };
+        assert_eq!(
+            hex::encode(tx.signing_hash(1)),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        let leaves = vec![
+            RewardLeaf {
+                address: "0x3535353535353535353535353535353535353535".into(),
+                credits: 3,
+            },
+            RewardLeaf {
+                address: "0x00000000000000000000000000000000000000aa".into(),
+                credits: 1,
+            },
+        ];
+        assert_eq!(merkle_root(&leaves[..1]).unwrap(), merkle_leaf(&leaves[0]).unwrap());
+        let mut reversed = leaves.clone();
+        reversed.reverse();
+        assert_eq!(merkle_root(&leaves).unwrap(), merkle_root(&reversed).unwrap());
+    }
+
+    #[test]
+    fn test_repeated_timeouts_park_batch_with_nonce() {
+        use crate::crypto::CryptoConfig;
+
+        let dir = std::env::temp_dir().join(format!("ggs-rewards-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = RewardConfig {
+            max_attempts: 3,
+            retry_backoff: Duration::ZERO,
+            ..RewardConfig::default()
+        };
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let submitter = RewardSubmitter::new(config, crypto, Some(dir.clone()));
+        let mut batch = RewardBatch {
+            epoch: 42,
+            root: String::new(),
+            leaves: vec![RewardLeaf {
+                address: "0x00000000000000000000000000000000000000aa".into(),
+                credits: 2,
+            }],
+            attempts: 0,
+            tx_hash: None,
+            nonce: Some(7),
+            sent: vec!["0x01".into()],
+            parked: false,
+        };
+        for attempt in 1..3 {
+            submitter.finish(batch, Err(anyhow!("timed out")));
+            let stats = submitter.stats();
+            assert_eq!((stats.pending_attempts, stats.parked), (attempt, 0));
+            batch = submitter.state.lock().pending.clone().unwrap();
+            assert_eq!(batch.nonce, Some(7));
+        }
+        // 第三次超时后不再重试，也不把积分并入下一批
+        submitter.finish(batch, Err(anyhow!("timed out")));
+        let stats = submitter.stats();
+        assert_eq!((stats.pending_attempts, stats.parked), (0, 1));
+        assert!(submitter.state.lock().pending.is_none());
+        assert!(submitter.state.lock().credits.is_empty());
+        let saved = load_batches(&dir).unwrap();
+        let parked = saved[0].1.as_ref().unwrap();
+        assert!(parked.parked);
+        assert_eq!((parked.attempts, parked.nonce), (3, Some(7)));
+
+        // gas 价格最多抬高 max_attempts 次
+        assert_eq!(replacement_gas_price(800, 3, 3), 1_100);
+        assert_eq!(replacement_gas_price(800, u32::MAX, 3), 1_100);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+//! 节点角色
+//!
+//! - `Trainer`：执行本地训练并发送稀疏更新
+//! - `Aggregator`：不持有本地数据，收集并融合更新后重新分发密集快照
+//! - `Observer`：只做验证、转发与监控，不修改模型
+
+use crate::comms::BandwidthBudgetConfig;
+use crate::topology::TopologyConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// 训练者对聚合者邻居的额外拓扑评分
+const AGGREGATOR_NEIGHBOR_BONUS: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeRole {
+    #[default]
+    Trainer,
+    Aggregator,
+    Observer,
+}
+
+impl NodeRole {
+    pub fn trains_locally(&self) -> bool {
+        matches!(self, NodeRole::Trainer)
+    }
+
+    pub fn sends_sparse_updates(&self) -> bool {
+        matches!(self, NodeRole::Trainer)
+    }
+
+    /// 是否将收到的更新 / 快照合并到本地模型
+    pub fn merges_updates(&self) -> bool {
+        !matches!(self, NodeRole::Observer)
+    }
+
+    pub fn broadcasts_snapshots(&self) -> bool {
+        !matches!(self, NodeRole::Observer)
+    }
+
+    /// 是否发送相似度探测（观察者不参与拓扑选择）
+    pub fn sends_probes(&self) -> bool {
+        !matches!(self, NodeRole::Observer)
+    }
+
+    /// 快照间隔倍数：聚合者更频繁地分发融合后的模型
+    pub fn snapshot_interval_factor(&self) -> f32 {
+        match self {
+            NodeRole::Aggregator => 0.25,
+            _ => 1.0,
+        }
+    }
+
+    /// 按角色调整带宽预算：聚合者承担快照分发，观察者不发送模型数据
+    pub fn adjust_bandwidth(&self, budget: &BandwidthBudgetConfig) -> BandwidthBudgetConfig {
+        match self {
+            NodeRole::Trainer => budget.clone(),
+            NodeRole::Aggregator => BandwidthBudgetConfig {
+                sparse_per_window: 0,
+                dense_bytes_per_window: budget.dense_bytes_per_window * 4,
+                window_secs: budget.window_secs,
+            },
+            NodeRole::Observer => budget.scaled(0.0),
+        }
+    }
+
+    /// 按角色调整拓扑：聚合者连接更多训练者，观察者只保留少量邻居
+    pub fn adjust_topology(&self, topology: &mut TopologyConfig) {
+        match self {
+            NodeRole::Trainer => {}
+            NodeRole::Aggregator => {
+                topology.max_neighbors *= 2;
+                topology.failover_pool *= 2;
+            }
+            NodeRole::Observer => {
+                topology.max_neighbors = topology.max_neighbors.min(2);
+                topology.failover_pool = 0;
+            }
+        }
+    }
+
+    /// 本节点对某一角色邻居的额外评分
+    pub fn neighbor_bonus(&self, peer_role: NodeRole) -> f32 {
+        match (self, peer_role) {
+            (NodeRole::Trainer, NodeRole::Aggregator) => AGGREGATOR_NEIGHBOR_BONUS,
+            _ => 0.0,
+        }
+    }
+}
+
+impl FromStr for NodeRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "trainer" => Ok(NodeRole::Trainer),
+            "aggregator" => Ok(NodeRole::Aggregator),
+            "observer" => Ok(NodeRole::Observer),
+            other => Err(anyhow!("unknown node role {:?}", other)),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! 周期任务调度器
+//!
+//! 心跳、相似度探测、本地训练、密集快照、任务广播各自拥有独立的间隔，
+//! 由 Node 主循环通过 `next_deadline` / `take_due` 驱动。
+
+use rand::Rng;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 周期任务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodicTask {
+    Heartbeat,
+    Probe,
+    Train,
+    Snapshot,
+    /// 持久化节点状态
+    Persist,
+    /// 广播本节点发布的训练任务
+    Announce,
+}
+
+impl PeriodicTask {
+    pub const ALL: [PeriodicTask; 6] = [
+        PeriodicTask::Heartbeat,
+        PeriodicTask::Probe,
+        PeriodicTask::Train,
+        PeriodicTask::Snapshot,
+        PeriodicTask::Persist,
+        PeriodicTask::Announce,
+    ];
+}
+
+#[derive(Clone, Debug)]
+pub struct ScheduleConfig {
+    pub heartbeat_interval: Duration,
+    pub probe_interval: Duration,
+    pub train_interval: Duration,
+    pub snapshot_interval: Duration,
+    pub persist_interval: Duration,
+    pub announce_interval: Duration,
+    /// 随机抖动比例（0.0-0.5），每次调度在 [1-r, 1+r] 倍间隔内随机，避免全网同步
+    pub jitter_ratio: f32,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(10),
+            probe_interval: Duration::from_secs(10),
+            train_interval: Duration::from_secs(10),
+            snapshot_interval: Duration::from_secs(120),
+            persist_interval: Duration::from_secs(60),
+            announce_interval: Duration::from_secs(60),
+            jitter_ratio: 0.1,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    pub fn interval(&self, task: PeriodicTask) -> Duration {
+        match task {
+            PeriodicTask::Heartbeat => self.heartbeat_interval,
+            PeriodicTask::Probe => self.probe_interval,
+            PeriodicTask::Train => self.train_interval,
+            PeriodicTask::Snapshot => self.snapshot_interval,
+            PeriodicTask::Persist => self.persist_interval,
+            PeriodicTask::Announce => self.announce_interval,
+        }
+    }
+}
+
+pub struct IntervalScheduler {
+    config: ScheduleConfig,
+    /// 间隔缩放系数（电池保护时 > 1.0）
+    scale: f32,
+    next_due: Vec<(PeriodicTask, Instant)>,
+}
+
+impl IntervalScheduler {
+    pub fn new(config: ScheduleConfig) -> Self {
+        let now = Instant::now();
+        // 心跳 / 探测 / 训练在启动后很快执行（错开一个抖动窗口），快照与持久化等待一个完整间隔
+        let next_due = PeriodicTask::ALL
+            .iter()
+            .map(|&task| {
+                let interval = config.interval(task);
+                let due = match task {
+                    PeriodicTask::Snapshot | PeriodicTask::Persist => {
+                        now + jittered(interval, config.jitter_ratio)
+                    }
+                    _ => now + startup_offset(interval, config.jitter_ratio),
+                };
+                (task, due)
+            })
+            .collect();
+        Self {
+            config,
+            scale: 1.0,
+            next_due,
+        }
+    }
+
+    pub fn config(&self) -> &ScheduleConfig {
+        &self.config
+    }
+
+    pub fn jitter_ratio(&self) -> f32 {
+        self.config.jitter_ratio
+    }
+
+    /// 某个任务当前生效的间隔（已应用缩放）
+    pub fn effective_interval(&self, task: PeriodicTask) -> Duration {
+        self.config.interval(task).mul_f32(self.scale)
+    }
+
+    /// 热加载间隔配置：间隔缩短的任务提前到新间隔内执行，其余保持原到期时间
+    pub fn set_config(&mut self, config: ScheduleConfig) {
+        let now = Instant::now();
+        for (task, due) in &mut self.next_due {
+            let next = now + config.interval(*task).mul_f32(self.scale);
+            if next < *due {
+                *due = next;
+            }
+        }
+        self.config = config;
+    }
+
+    /// 调整所有间隔的缩放系数，返回是否发生变化
+    pub fn set_scale(&mut self, scale: f32) -> bool {
+        let scale = scale.max(0.1);
+        if (scale - self.scale).abs() < f32::EPSILON {
+            return false;
+        }
+        self.scale = scale;
+        true
+    }
+
+    /// 最早到期的任务时间点
+    pub fn next_deadline(&self) -> Instant {
+        self.next_due
+            .iter()
+            .map(|(_, due)| *due)
+            .min()
+            .unwrap_or_else(Instant::now)
+    }
+
+    /// 取出所有已到期的任务并安排下一次执行
+    pub fn take_due(&mut self) -> Vec<PeriodicTask> {
+        let now = Instant::now();
+        let mut due_tasks = Vec::new();
+        for i in 0..self.next_due.len() {
+            let (task, due) = self.next_due[i];
+            if due <= now {
+                due_tasks.push(task);
+                let interval = self.effective_interval(task);
+                self.next_due[i].1 = now + jittered(interval, self.config.jitter_ratio);
+            }
+        }
+        due_tasks
+    }
+}
+
+/// 在 [1-ratio, 1+ratio] 倍范围内随机化间隔
+pub fn jittered(base: Duration, ratio: f32) -> Duration {
+    let ratio = ratio.clamp(0.0, 0.5);
+    if ratio == 0.0 {
+        return base;
+    }
+    let factor = rand::thread_rng().gen_range(1.0 - ratio..=1.0 + ratio);
+    base.mul_f32(factor)
+}
+
+/// 启动时的随机偏移，范围 [0, ratio * interval]
+fn startup_offset(interval: Duration, ratio: f32) -> Duration {
+    let ratio = ratio.clamp(0.0, 0.5);
+    if ratio == 0.0 {
+        return Duration::ZERO;
+    }
+    interval.mul_f32(rand::thread_rng().gen_range(0.0..=ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_independent_intervals() {
+        let mut scheduler = IntervalScheduler::new(ScheduleConfig {
+            heartbeat_interval: Duration::from_secs(5),
+            probe_interval: Duration::from_secs(10),
+            train_interval: Duration::from_secs(10),
+            snapshot_interval: Duration::from_secs(30),
+            persist_interval: Duration::from_secs(60),
+            announce_interval: Duration::from_secs(60),
+            jitter_ratio: 0.0,
+        });
+        let first = scheduler.take_due();
+        assert_eq!(
+            first,
+            vec![
+                PeriodicTask::Heartbeat,
+                PeriodicTask::Probe,
+                PeriodicTask::Train,
+                PeriodicTask::Announce
+            ]
+        );
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(scheduler.take_due(), vec![PeriodicTask::Heartbeat]);
+
+        tokio::time::advance(Duration::from_secs(25)).await;
+        assert!(scheduler.take_due().contains(&PeriodicTask::Snapshot));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_ratio() {
+        let base = Duration::from_secs(10);
+        for _ in 0..100 {
+            let d = jittered(base, 0.2);
+            assert!(d >= Duration::from_secs(8) && d <= Duration::from_secs(12));
+        }
+        assert_eq!(jittered(base, 0.0), base);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scale_stretches_intervals() {
+        let mut scheduler = IntervalScheduler::new(ScheduleConfig::default());
+        assert!(scheduler.set_scale(3.0));
+        assert!(!scheduler.set_scale(3.0));
+        assert_eq!(
+            scheduler.effective_interval(PeriodicTask::Heartbeat),
+            Duration::from_secs(30)
+        );
+    }
+}
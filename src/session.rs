@@ -0,0 +1,88 @@
+//! 多模型训练会话
+//!
+//! 一个节点可以同时参与多个训练任务，每个会话拥有独立的推理引擎、gossip 主题、
+//! 拓扑视图和带宽子预算。消息信封 `SignedGossip::session` 标识其所属会话。
+
+use crate::inference::{InferenceConfig, InferenceEngine};
+use crate::layers::LayerLayout;
+use crate::topology::{TopologyConfig, TopologySelector};
+use crate::types::GeoPoint;
+use anyhow::{anyhow, Result};
+
+/// 默认会话 id（使用 CommsConfig 中的主题）
+pub const DEFAULT_SESSION: &str = "default";
+
+pub fn default_session_id() -> String {
+    DEFAULT_SESSION.to_string()
+}
+
+#[derive(Clone)]
+pub struct SessionConfig {
+    pub id: String,
+    pub topic: String,
+    pub inference: InferenceConfig,
+    /// 占用总带宽预算的比例，None 表示与其他会话平分
+    pub bandwidth_share: Option<f32>,
+}
+
+impl SessionConfig {
+    /// 解析命令行会话描述 `id[:model_dim]`，主题为 `<base_topic>-<id>`
+    pub fn parse(spec: &str, base_topic: &str, base_inference: &InferenceConfig) -> Result<Self> {
+        let mut parts = spec.splitn(2, ':');
+        let id = parts.next().unwrap_or_default().trim();
+        if id.is_empty() || id == DEFAULT_SESSION {
+            return Err(anyhow!("invalid session id {:?}", id));
+        }
+        let mut inference = base_inference.clone();
+        if let Some(dim) = parts.next() {
+            inference.model_dim = dim
+                .parse()
+                .map_err(|_| anyhow!("invalid model dim in session spec {:?}", spec))?;
+            inference.model_path = None;
+            // 维度不同的会话沿用层数，按新维度均分
+            inference.layers = LayerLayout::Uniform(inference.layers.layer_count());
+        }
+        inference.checkpoint_dir = base_inference.session_checkpoint_dir(id);
+        Ok(Self {
+            id: id.to_string(),
+            topic: format!("{base_topic}-{id}"),
+            inference,
+            bandwidth_share: None,
+        })
+    }
+}
+
+pub struct Session {
+    pub id: String,
+    /// 轻量模式下为 None，不加载任何模型参数
+    pub inference: Option<InferenceEngine>,
+    pub topology: TopologySelector,
+}
+
+impl Session {
+    pub fn new(
+        id: String,
+        inference: InferenceConfig,
+        position: GeoPoint,
+        topology: TopologyConfig,
+    ) -> Result<Self> {
+        Ok(Self {
+            id,
+            inference: Some(InferenceEngine::new(inference)?),
+            topology: TopologySelector::new(position, topology),
+        })
+    }
+
+    /// 不持有模型的轻量会话，只跟踪拓扑与心跳
+    pub fn light(id: String, position: GeoPoint, topology: TopologyConfig) -> Self {
+        Self {
+            id,
+            inference: None,
+            topology: TopologySelector::new(position, topology),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_SESSION
+    }
+}
@@ -0,0 +1,127 @@
+//! 稀疏度（top-k）自动调节
+//!
+//! 把收敛反馈和带宽预算连成闭环：每个带宽窗口结束后读取一次指标，
+//! 损失停滞且稀疏更新预算用不满时增大 k，让每条更新携带更多信息；
+//! 预算被打满或出现因预算拒绝发送时减小 k，降低单条更新的体积。
+//! 节点没有验证集，损失用 `1 - convergence_score` 近似。
+
+use crate::comms::BudgetUsage;
+use crate::inference::DEFAULT_SPARSE_K;
+
+#[derive(Clone, Debug)]
+pub struct SparsityConfig {
+    /// 关闭后 k 固定为 `initial_k`
+    pub auto_tune: bool,
+    pub initial_k: usize,
+    pub min_k: usize,
+    pub max_k: usize,
+    /// 损失相对下降低于该比例视为停滞
+    pub stagnation_ratio: f32,
+    /// 预算利用率低于该值视为带宽有富余
+    pub low_utilization: f32,
+    /// 预算利用率高于该值视为带宽饱和
+    pub high_utilization: f32,
+}
+
+impl Default for SparsityConfig {
+    fn default() -> Self {
+        Self {
+            auto_tune: true,
+            initial_k: DEFAULT_SPARSE_K,
+            min_k: 4,
+            max_k: 512,
+            stagnation_ratio: 0.01,
+            low_utilization: 0.5,
+            high_utilization: 0.9,
+        }
+    }
+}
+
+/// 单个会话的 k 控制器
+pub struct SparsityController {
+    config: SparsityConfig,
+    k: usize,
+    last_loss: Option<f32>,
+    last_window: Option<u64>,
+}
+
+impl SparsityController {
+    pub fn new(config: SparsityConfig) -> Self {
+        let k = config.initial_k.clamp(config.min_k, config.max_k);
+        Self {
+            config,
+            k,
+            last_loss: None,
+            last_window: None,
+        }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// 超参数共识下发新的 k 时重置，之后继续在此基础上自动调节
+    pub fn set_k(&mut self, k: usize) {
+        self.k = k.clamp(self.config.min_k, self.config.max_k);
+    }
+
+    /// 输入当前损失和上一个完整带宽窗口的使用情况，k 变化时返回新值；
+    /// 同一窗口只评估一次
+    pub fn observe(&mut self, loss: f32, usage: Option<BudgetUsage>) -> Option<usize> {
+        if !self.config.auto_tune {
+            return None;
+        }
+        let usage = usage?;
+        if self.last_window == Some(usage.window) {
+            return None;
+        }
+        self.last_window = Some(usage.window);
+        let stagnating = self
+            .last_loss
+            .map(|prev| prev - loss <= prev.abs() * self.config.stagnation_ratio)
+            .unwrap_or(false);
+        self.last_loss = Some(loss);
+
+        let next = if usage.denied > 0 || usage.utilization >= self.config.high_utilization {
+            self.k * 3 / 4
+        } else if stagnating && usage.utilization < self.config.low_utilization {
+            self.k * 2
+        } else {
+            self.k
+        }
+        .clamp(self.config.min_k, self.config.max_k);
+        if next == self.k {
+            return None;
+        }
+        self.k = next;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(window: u64, utilization: f32, denied: u32) -> Option<BudgetUsage> {
+        Some(BudgetUsage {
+            window,
+            utilization,
+            denied,
+        })
+    }
+
+    #[test]
+    fn test_k_follows_loss_and_budget() {
+        let mut controller = SparsityController::new(SparsityConfig::default());
+        assert_eq!(controller.observe(0.5, usage(0, 0.2, 0)), None);
+        // 同一窗口不重复评估
+        assert_eq!(controller.observe(0.5, usage(0, 0.2, 0)), None);
+        // 损失停滞、预算富余 -> 增大
+        assert_eq!(controller.observe(0.5, usage(1, 0.2, 0)), Some(32));
+        // 损失仍在下降 -> 保持
+        assert_eq!(controller.observe(0.3, usage(2, 0.2, 0)), None);
+        // 预算被拒绝 -> 减小
+        assert_eq!(controller.observe(0.3, usage(3, 1.0, 2)), Some(24));
+        assert_eq!(controller.k(), 24);
+    }
+}
@@ -0,0 +1,491 @@
+//! 心跳中的链上质押证明
+//!
+//! 配置了 `--eth-rpc` 的节点定期查询自己 ETH 地址在最新区块的余额（配置 `--sol-rpc` 时
+//! 同时查询 Solana 余额），连同区块号与区块哈希用自己的 ETH 密钥签名，作为证明附在心跳中。
+//! 证明是自签名的，只说明 “我声称有这么多”：接收方先检查地址与发送者固定的密钥一致、签名有效、
+//! 未过期且区块号不回退，再按链上状态确认后才据此设置账本中的 `stake_eth` / `stake_sol`。
+//! 有 RPC 的接收方自己核对区块哈希与余额，核对通过后广播 `StakeAttestation` 背书；
+//! 没有 RPC 的接收方等到 `attest_quorum` 个质押已确认的节点为同一区块的证明背书后才采信。
+//! 核对不符的节点被扣除声誉，之后的证明不再采信。
+
+use crate::consensus::{ConsensusEngine, KeyPin};
+use crate::crypto::{verify_eth, CryptoSuite, EthSignature};
+use crate::names::rpc_call;
+use crate::persistence::unix_now_secs;
+use crate::rewards::quantity;
+use anyhow::{anyhow, Result};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const WEI_PER_ETH: f64 = 1e18;
+const LAMPORTS_PER_SOL: f64 = 1e9;
+/// 核对不符时扣除的声誉
+const FALSE_PROOF_PENALTY: f64 = 5.0;
+
+#[derive(Clone, Debug)]
+pub struct StakeProofConfig {
+    /// 在心跳中附带本节点的质押证明（需要 `--eth-rpc`）
+    pub publish: bool,
+    /// Solana JSON-RPC 端点，None 时证明不含 SOL 余额
+    pub sol_rpc: Option<String>,
+    /// 重新查询余额的间隔
+    pub refresh: Duration,
+    /// 超过该时长的证明不再采信
+    pub max_age: Duration,
+    pub request_timeout: Duration,
+    /// 没有 RPC 时，采信一份证明需要的已确认质押节点背书数
+    pub attest_quorum: usize,
+}
+
+impl Default for StakeProofConfig {
+    fn default() -> Self {
+        Self {
+            publish: false,
+            sol_rpc: None,
+            refresh: Duration::from_secs(600),
+            max_age: Duration::from_secs(3600),
+            request_timeout: Duration::from_secs(10),
+            attest_quorum: 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StakeProof {
+    pub eth_address: String,
+    pub eth_balance_wei: u128,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub sol_pubkey: String,
+    /// 未配置 Solana RPC 时为 None
+    pub sol_lamports: Option<u64>,
+    pub observed_at_secs: u64,
+    /// 证明者（即被证明的节点）对以上字段的 ETH 签名
+    pub signature: EthSignature,
+}
+
+impl StakeProof {
+    fn signing_bytes(&self) -> Vec<u8> {
+        format!(
+            "ggs-stake-proof:{}:{}:{}:{}:{}:{}:{}",
+            self.eth_address.to_lowercase(),
+            self.eth_balance_wei,
+            self.block_number,
+            self.block_hash.to_lowercase(),
+            self.sol_pubkey,
+            self.sol_lamports.map(|l| l.to_string()).unwrap_or_default(),
+            self.observed_at_secs
+        )
+        .into_bytes()
+    }
+
+    fn sign(mut self, crypto: &CryptoSuite) -> Result<Self> {
+        self.signature = crypto.sign_eth(&self.signing_bytes())?;
+        Ok(self)
+    }
+
+    pub fn stake_eth(&self) -> f64 {
+        self.eth_balance_wei as f64 / WEI_PER_ETH
+    }
+
+    pub fn stake_sol(&self) -> f64 {
+        self.sol_lamports.unwrap_or(0) as f64 / LAMPORTS_PER_SOL
+    }
+
+    /// 证明与发送者固定的密钥一致、签名有效且未过期
+    pub fn verify(&self, pin: &KeyPin, max_age: Duration, now_secs: u64) -> Result<()> {
+        if !self.eth_address.eq_ignore_ascii_case(&pin.eth_address)
+            || self.sol_pubkey != pin.sol_pubkey
+        {
+            return Err(anyhow!("证明的地址与节点固定的密钥不符"));
+        }
+        if !self.signature.address.eq_ignore_ascii_case(&pin.eth_address)
+            || !verify_eth(&self.signing_bytes(), &self.signature)
+        {
+            return Err(anyhow!("证明签名无效"));
+        }
+        if now_secs.saturating_sub(self.observed_at_secs) > max_age.as_secs() {
+            return Err(anyhow!("证明已过期"));
+        }
+        Ok(())
+    }
+}
+
+/// 查询本节点余额并签名
+async fn query_own_proof(
+    eth_rpc: &str,
+    sol_rpc: Option<&str>,
+    crypto: &CryptoSuite,
+) -> Result<StakeProof> {
+    let block = rpc_call(eth_rpc, "eth_getBlockByNumber", json!(["latest", false])).await?;
+    let block_number = quantity(&block["number"])? as u64;
+    let block_hash = block["hash"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing block hash"))?
+        .to_string();
+    let eth_address = crypto.eth_address();
+    let eth_balance_wei = eth_balance_at(eth_rpc, &eth_address, block_number).await?;
+    let sol_pubkey = crypto.sol_address();
+    let sol_lamports = match sol_rpc {
+        Some(rpc) => {
+            let result = rpc_call(rpc, "getBalance", json!([sol_pubkey])).await?;
+            Some(
+                result["value"]
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("missing lamports"))?,
+            )
+        }
+        None => None,
+    };
+    StakeProof {
+        eth_address,
+        eth_balance_wei,
+        block_number,
+        block_hash,
+        sol_pubkey,
+        sol_lamports,
+        observed_at_secs: unix_now_secs(),
+        signature: EthSignature {
+            address: String::new(),
+            signature: String::new(),
+        },
+    }
+    .sign(crypto)
+}
+
+async fn eth_balance_at(rpc: &str, address: &str, block_number: u64) -> Result<u128> {
+    let block = format!("0x{:x}", block_number);
+    quantity(&rpc_call(rpc, "eth_getBalance", json!([address, block])).await?)
+}
+
+/// 核对证明中的区块哈希与 ETH 余额
+async fn audit_proof(rpc: &str, proof: &StakeProof) -> Result<bool> {
+    let block_number = format!("0x{:x}", proof.block_number);
+    let block = rpc_call(rpc, "eth_getBlockByNumber", json!([block_number, false])).await?;
+    let hash_matches = block["hash"]
+        .as_str()
+        .is_some_and(|h| h.eq_ignore_ascii_case(&proof.block_hash));
+    if !hash_matches {
+        return Ok(false);
+    }
+    let balance = eth_balance_at(rpc, &proof.eth_address, proof.block_number).await?;
+    Ok(balance == proof.eth_balance_wei)
+}
+
+struct ProverState {
+    latest: Option<StakeProof>,
+    refreshed_at: Option<Instant>,
+    in_flight: bool,
+}
+
+/// 本节点的证明：后台定期刷新，心跳读取最近一次结果
+pub struct StakeProver {
+    config: StakeProofConfig,
+    eth_rpc: String,
+    crypto: Arc<CryptoSuite>,
+    state: Mutex<ProverState>,
+}
+
+impl StakeProver {
+    pub fn new(config: StakeProofConfig, eth_rpc: String, crypto: Arc<CryptoSuite>) -> Self {
+        Self {
+            config,
+            eth_rpc,
+            crypto,
+            state: Mutex::new(ProverState {
+                latest: None,
+                refreshed_at: None,
+                in_flight: false,
+            }),
+        }
+    }
+
+    /// 最近一次证明；已过期的不再附带
+    pub fn current(&self) -> Option<StakeProof> {
+        let max_age = self.config.max_age.as_secs();
+        self.state
+            .lock()
+            .latest
+            .clone()
+            .filter(|p| unix_now_secs().saturating_sub(p.observed_at_secs) <= max_age)
+    }
+
+    pub fn maybe_refresh(self: &Arc<Self>) {
+        {
+            let mut state = self.state.lock();
+            if state.in_flight
+                || state
+                    .refreshed_at
+                    .is_some_and(|at| at.elapsed() < self.config.refresh)
+            {
+                return;
+            }
+            state.in_flight = true;
+        }
+        let prover = self.clone();
+        tokio::spawn(async move {
+            let result = tokio::time::timeout(
+                prover.config.request_timeout,
+                query_own_proof(
+                    &prover.eth_rpc,
+                    prover.config.sol_rpc.as_deref(),
+                    &prover.crypto,
+                ),
+            )
+            .await
+            .map_err(|_| anyhow!("timed out"))
+            .and_then(|r| r);
+            let mut state = prover.state.lock();
+            state.in_flight = false;
+            state.refreshed_at = Some(Instant::now());
+            match result {
+                Ok(proof) => {
+                    println!(
+                        "[质押证明] 区块 #{}: {:.4} ETH / {:.4} SOL",
+                        proof.block_number,
+                        proof.stake_eth(),
+                        proof.stake_sol()
+                    );
+                    state.latest = Some(proof);
+                }
+                Err(e) => eprintln!("[质押证明] 查询余额失败: {:?}", e),
+            }
+        });
+    }
+}
+
+/// 为另一节点在某区块的证明背书：背书者已按链上状态核对过该证明
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeEndorsement {
+    pub peer: String,
+    pub block_number: u64,
+    pub block_hash: String,
+}
+
+/// 接收方：校验其他节点心跳中的证明，按链上核对或已确认节点的背书确认后才采信
+pub struct StakeVerifier {
+    config: StakeProofConfig,
+    eth_rpc: Option<String>,
+    /// 节点 -> 签名有效、等待确认的最新证明，拒绝区块回退的旧证明
+    pending: RwLock<HashMap<String, StakeProof>>,
+    /// 节点 -> 已确认的最高区块号；只有这些节点的背书计入门槛
+    confirmed: RwLock<HashMap<String, u64>>,
+    /// (节点, 区块号, 区块哈希) -> 背书者
+    endorsements: RwLock<HashMap<(String, u64, String), HashSet<String>>>,
+    /// 本节点核对通过、待广播的背书
+    outbox: Mutex<Vec<StakeEndorsement>>,
+    /// 节点 -> 已核对的最高区块号，同一区块的证明只核对一次
+    audited: RwLock<HashMap<String, u64>>,
+    /// 核对不符的节点
+    refuted: RwLock<HashSet<String>>,
+}
+
+impl StakeVerifier {
+    pub fn new(config: StakeProofConfig, eth_rpc: Option<String>) -> Self {
+        Self {
+            config,
+            eth_rpc,
+            pending: RwLock::new(HashMap::new()),
+            confirmed: RwLock::new(HashMap::new()),
+            endorsements: RwLock::new(HashMap::new()),
+            outbox: Mutex::new(Vec::new()),
+            audited: RwLock::new(HashMap::new()),
+            refuted: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// 检查证明签名与区块号并记为待确认；证明中的余额此时还不能使用
+    pub fn accept(&self, peer: &str, pin: &KeyPin, proof: &StakeProof, now_secs: u64) -> Result<()> {
+        if self.refuted.read().contains(peer) {
+            return Err(anyhow!("该节点此前的证明与链上不符"));
+        }
+        proof.verify(pin, self.config.max_age, now_secs)?;
+        let mut pending = self.pending.write();
+        let last = pending.get(peer).map_or(0, |p| p.block_number);
+        if proof.block_number < last {
+            return Err(anyhow!("区块号 #{} 早于已采信的 #{}", proof.block_number, last));
+        }
+        pending.insert(peer.to_string(), proof.clone());
+        Ok(())
+    }
+
+    /// 持有该节点签名有效的质押证明（已确认或待确认）
+    pub fn has_proof(&self, peer: &str) -> bool {
+        self.pending.read().contains_key(peer)
+    }
+
+    /// 节点的质押已按链上状态确认
+    pub fn is_confirmed(&self, peer: &str) -> bool {
+        self.confirmed.read().contains_key(peer)
+    }
+
+    /// 记录 `endorser` 的背书；背书者自己的质押未确认时不计入。
+    /// 凑够门槛且与待确认的证明一致时确认，返回 (stake_eth, stake_sol)
+    pub fn endorse(&self, endorser: &str, endorsement: &StakeEndorsement) -> Option<(f64, f64)> {
+        if endorser == endorsement.peer
+            || !self.is_confirmed(endorser)
+            || self.refuted.read().contains(&endorsement.peer)
+        {
+            return None;
+        }
+        let count = {
+            let key = (
+                endorsement.peer.clone(),
+                endorsement.block_number,
+                endorsement.block_hash.to_lowercase(),
+            );
+            let mut endorsements = self.endorsements.write();
+            let endorsers = endorsements.entry(key).or_default();
+            endorsers.insert(endorser.to_string());
+            endorsers.len()
+        };
+        if count < self.config.attest_quorum.max(1) {
+            return None;
+        }
+        let proof = self.pending.read().get(&endorsement.peer).cloned()?;
+        if proof.block_number != endorsement.block_number
+            || !proof
+                .block_hash
+                .eq_ignore_ascii_case(&endorsement.block_hash)
+        {
+            return None;
+        }
+        self.confirm(&endorsement.peer, &proof);
+        Some((proof.stake_eth(), proof.stake_sol()))
+    }
+
+    fn confirm(&self, peer: &str, proof: &StakeProof) {
+        let mut confirmed = self.confirmed.write();
+        if confirmed
+            .get(peer)
+            .is_some_and(|block| *block >= proof.block_number)
+        {
+            return;
+        }
+        confirmed.insert(peer.to_string(), proof.block_number);
+        let block_number = proof.block_number;
+        self.endorsements
+            .write()
+            .retain(|(p, block, _), _| p != peer || *block > block_number);
+    }
+
+    /// 取出本节点核对通过、待广播的背书
+    pub fn take_endorsements(&self) -> Vec<StakeEndorsement> {
+        std::mem::take(&mut *self.outbox.lock())
+    }
+
+    /// 有 RPC 时在后台核对新区块的证明：一致则确认并设置质押、排队广播背书；
+    /// 不符则扣除声誉并不再采信该节点
+    pub fn audit(self: &Arc<Self>, peer: &str, proof: StakeProof, consensus: Arc<ConsensusEngine>) {
+        let Some(rpc) = self.eth_rpc.clone() else {
+            return;
+        };
+        {
+            let mut audited = self.audited.write();
+            if audited.get(peer).is_some_and(|block| *block >= proof.block_number) {
+                return;
+            }
+            audited.insert(peer.to_string(), proof.block_number);
+        }
+        let verifier = self.clone();
+        let peer = peer.to_string();
+        tokio::spawn(async move {
+            let result = tokio::time::timeout(verifier.config.request_timeout, audit_proof(&rpc, &proof))
+                .await
+                .map_err(|_| anyhow!("timed out"))
+                .and_then(|r| r);
+            match result {
+                Ok(true) => {
+                    verifier.confirm(&peer, &proof);
+                    consensus.set_stake(&peer, proof.stake_eth(), proof.stake_sol());
+                    verifier.outbox.lock().push(StakeEndorsement {
+                        peer: peer.clone(),
+                        block_number: proof.block_number,
+                        block_hash: proof.block_hash.clone(),
+                    });
+                }
+                Ok(false) => {
+                    eprintln!(
+                        "[质押证明] {} 在区块 #{} 的余额证明与链上不符",
+                        peer, proof.block_number
+                    );
+                    verifier.refuted.write().insert(peer.clone());
+                    consensus.set_stake(&peer, 0.0, 0.0);
+                    consensus.update_stake(&peer, 0.0, 0.0, -FALSE_PROOF_PENALTY);
+                }
+                Err(e) => eprintln!("[质押证明] 核对 {} 的证明失败: {:?}", peer, e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoConfig;
+
+    #[test]
+    fn test_proof_must_match_pinned_keys_and_not_regress() {
+        let crypto = CryptoSuite::new(CryptoConfig::default()).unwrap();
+        let pin = KeyPin {
+            peer: "peer-a".into(),
+            eth_address: crypto.eth_address(),
+            sol_pubkey: crypto.sol_address(),
+        };
+        let proof = |block_number, observed_at_secs| {
+            StakeProof {
+                eth_address: crypto.eth_address(),
+                eth_balance_wei: 2_500_000_000_000_000_000,
+                block_number,
+                block_hash: "0xabc".into(),
+                sol_pubkey: crypto.sol_address(),
+                sol_lamports: Some(3_000_000_000),
+                observed_at_secs,
+                signature: EthSignature {
+                    address: String::new(),
+                    signature: String::new(),
+                },
+            }
+            .sign(&crypto)
+            .unwrap()
+        };
+        let verifier = StakeVerifier::new(StakeProofConfig::default(), None);
+        verifier
+            .accept("peer-a", &pin, &proof(100, 1_000), 1_000)
+            .unwrap();
+        // 区块回退、过期或篡改余额的证明都被拒绝
+        assert!(verifier.accept("peer-a", &pin, &proof(99, 1_000), 1_000).is_err());
+        assert!(verifier.accept("peer-a", &pin, &proof(101, 1_000), 10_000).is_err());
+        let mut forged = proof(102, 1_000);
+        forged.eth_balance_wei *= 10;
+        assert!(verifier.accept("peer-a", &pin, &forged, 1_000).is_err());
+
+        // 其他节点的密钥签不出与本节点固定密钥一致的证明
+        let other = CryptoSuite::new(CryptoConfig::default()).unwrap();
+        let mut stolen = proof(103, 1_000);
+        stolen.signature = other.sign_eth(&stolen.signing_bytes()).unwrap();
+        assert!(verifier.accept("peer-a", &pin, &stolen, 1_000).is_err());
+
+        // 自签名的余额要等已确认质押的节点背书够门槛才采信
+        verifier
+            .accept("peer-a", &pin, &proof(104, 1_000), 1_000)
+            .unwrap();
+        let endorsement = StakeEndorsement {
+            peer: "peer-a".into(),
+            block_number: 104,
+            block_hash: "0xABC".into(),
+        };
+        assert!(verifier.endorse("sybil", &endorsement).is_none());
+        assert!(!verifier.is_confirmed("peer-a"));
+        verifier.confirmed.write().insert("staked-1".into(), 1);
+        verifier.confirmed.write().insert("staked-2".into(), 1);
+        assert!(verifier.endorse("staked-1", &endorsement).is_none());
+        assert!(verifier.endorse("staked-1", &endorsement).is_none());
+        assert_eq!(verifier.endorse("staked-2", &endorsement), Some((2.5, 3.0)));
+        assert!(verifier.is_confirmed("peer-a"));
+    }
+}
@@ -0,0 +1,176 @@
+//! 热备状态复制
+//!
+//! 主节点每个心跳周期把模型检查点、质押账本、地址簿等持久化状态签名后经 QUIC 直连发给热备节点。
+//! 热备节点与主节点共用身份密钥文件与 ETH / Solana 种子，只接受由这组密钥签名、
+//! 发送者为同一 PeerId 的复制消息，写入自己的状态目录；期间不加入 gossip，避免同一 PeerId 同时在线。
+//! 复制按签名时间戳与训练轮次单调前进，重放旧的复制不会让热备状态回退。
+//! 收到过复制后主节点超过期限没有消息，热备节点即以同一身份从复制的状态启动，接管主节点。
+
+use crate::comms::DirectListener;
+use crate::consensus::{ConsensusEngine, SignedGossip};
+use crate::persistence::{PersistedState, StateStore};
+use crate::types::GgsMessage;
+use anyhow::Result;
+use libp2p::identity::{self, PeerId};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Clone, Debug)]
+pub struct StandbyConfig {
+    /// 主节点：热备节点的 QUIC 地址
+    pub replicate_to: Option<SocketAddr>,
+    /// 热备节点：接收复制的 QUIC 监听地址，设置后以热备模式启动
+    pub listen: Option<SocketAddr>,
+    /// 主节点停止复制超过该时长后接管
+    pub takeover_after: Duration,
+}
+
+impl Default for StandbyConfig {
+    fn default() -> Self {
+        Self {
+            replicate_to: None,
+            listen: None,
+            takeover_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 已接受的最新复制
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplicaMark {
+    /// 签名时间戳（主节点时钟，毫秒）
+    sent_at_ms: u64,
+    last_round: u64,
+}
+
+impl ReplicaMark {
+    /// 从状态目录中已有的状态开始，不接受比它更旧的复制
+    pub fn from_state(state: &PersistedState) -> Self {
+        Self {
+            sent_at_ms: 0,
+            last_round: state.last_round,
+        }
+    }
+}
+
+/// 取出由本节点密钥签名、发送者为本节点 PeerId、且比 `latest` 更新的复制状态，并前移 `latest`
+pub fn replica_state(
+    consensus: &ConsensusEngine,
+    own_peer: &str,
+    signed: SignedGossip,
+    latest: &mut ReplicaMark,
+) -> Option<PersistedState> {
+    if !consensus.verify_own(&signed) {
+        return None;
+    }
+    let sent_at_ms = signed.sent_at_ms;
+    match signed.payload {
+        GgsMessage::StateReplica { state, sender }
+            if sender == own_peer
+                && sent_at_ms > latest.sent_at_ms
+                && state.last_round >= latest.last_round =>
+        {
+            *latest = ReplicaMark {
+                sent_at_ms,
+                last_round: state.last_round,
+            };
+            Some(state)
+        }
+        _ => None,
+    }
+}
+
+/// 以热备模式运行，直到主节点失联；复制的状态已写入状态目录
+pub async fn wait_for_takeover(
+    config: &StandbyConfig,
+    listen: SocketAddr,
+    identity: &identity::Keypair,
+    consensus: &ConsensusEngine,
+    store: &StateStore,
+) -> Result<()> {
+    let own_peer = &PeerId::from(identity.public()).to_string();
+    // 与主节点出示同一身份的 QUIC 证书
+    let mut listener = DirectListener::bind(listen, identity)?;
+    println!("[热备] 在 {} 等待主节点 {} 的状态复制", listen, own_peer);
+    let mut last_replica: Option<Instant> = None;
+    let mut latest = store
+        .load()?
+        .map(|state| ReplicaMark::from_state(&state))
+        .unwrap_or_default();
+    loop {
+        let deadline = last_replica.map(|at| at + config.takeover_after);
+        tokio::select! {
+            signed = listener.recv() => {
+                let Some(signed) = signed else {
+                    anyhow::bail!("热备复制通道已关闭");
+                };
+                match replica_state(consensus, own_peer, signed, &mut latest) {
+                    Some(mut state) => {
+                        if last_replica.is_none() {
+                            println!("[热备] 已收到主节点的首次状态复制");
+                        }
+                        store.save(&mut state)?;
+                        last_replica = Some(Instant::now());
+                    }
+                    None => eprintln!("[热备] 拒绝未由本节点密钥签名或不比当前状态新的复制消息"),
+                }
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                println!(
+                    "[热备] 主节点 {:?} 未复制状态，以 {} 的身份接管",
+                    config.takeover_after, own_peer
+                );
+                listener.close();
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{sign_at, ConsensusConfig};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::session::DEFAULT_SESSION;
+    use std::sync::Arc;
+
+    fn engine() -> (Arc<CryptoSuite>, ConsensusEngine) {
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let engine = ConsensusEngine::new(crypto.clone(), ConsensusConfig::default());
+        (crypto, engine)
+    }
+
+    #[test]
+    fn test_only_own_keys_and_peer_are_accepted() {
+        let (crypto, primary) = engine();
+        let replica = |sender: &str, last_round: u64| GgsMessage::StateReplica {
+            state: PersistedState {
+                last_round,
+                ..PersistedState::default()
+            },
+            sender: sender.to_string(),
+        };
+        let sign = |msg: GgsMessage, sent_at_ms: u64| {
+            sign_at(&crypto, DEFAULT_SESSION, sent_at_ms, msg, 0.0).unwrap()
+        };
+        let mut latest = ReplicaMark::default();
+        let signed = sign(replica("me", 7), 1_000);
+        assert_eq!(
+            replica_state(&primary, "me", signed.clone(), &mut latest).map(|s| s.last_round),
+            Some(7)
+        );
+        // 重放同一条或更旧的复制不接受
+        assert!(replica_state(&primary, "me", signed.clone(), &mut latest).is_none());
+        let older_round = sign(replica("me", 6), 2_000);
+        assert!(replica_state(&primary, "me", older_round, &mut latest).is_none());
+        let newer = sign(replica("me", 8), 2_000);
+        assert!(replica_state(&primary, "me", newer, &mut latest).is_some());
+        // 其他密钥签名、或发送者不是本节点 PeerId 的复制都不接受
+        let mut fresh = ReplicaMark::default();
+        assert!(replica_state(&engine().1, "me", signed, &mut fresh).is_none());
+        let other_peer = sign(replica("other", 9), 3_000);
+        assert!(replica_state(&primary, "me", other_peer, &mut latest).is_none());
+    }
+}
@@ -1,3 +1,7 @@
+use crate::applyqueue::ApplyQueueStats;
+use crate::latency::{LatencyPath, LatencySummary};
+use crate::pubqueue::PublishQueueStats;
+use crate::rewards::RewardStats;
 use parking_lot::RwLock;
 use serde::Serialize;
 use std::collections::HashMap;
@@ -38,6 +42,26 @@ pub struct TrainingStats {
     pub model_hash: String,
     /// 每个节点的交互统计
     pub peer_stats: HashMap<String, PeerStats>,
+    /// 看门狗因停滞重启各子系统的次数
+    pub watchdog_restarts: HashMap<String, u64>,
+    /// 未确认而重发的直连稀疏更新数量
+    pub sparse_retransmits: u64,
+    /// 重试用尽后回落到 gossip 的直连稀疏更新数量
+    pub sparse_ack_fallbacks: u64,
+    /// 因长度不符、非有限值或完整性校验失败被拒绝的更新与快照数量
+    pub updates_rejected: u64,
+    /// 合并后被截断到参数边界（或因非有限值被还原）的参数个数
+    pub params_clamped: u64,
+    /// 按错误码统计的通信、密钥、共识与模型错误次数
+    pub errors: HashMap<String, u64>,
+    /// 按类别统计的入站失败事件（签名校验失败、限速丢弃等），含未逐条打印的
+    pub failures: HashMap<String, u64>,
+    /// mesh 为空时暂存待重发的 gossip 发布
+    pub publish_queue: PublishQueueStats,
+    /// 已验证、等待合并进模型的稀疏更新
+    pub apply_queue: ApplyQueueStats,
+    /// 链上奖励批次的重试与搁置
+    pub rewards: RewardStats,
 }
 
 /// 单个节点的统计信息
@@ -52,6 +76,12 @@ pub struct PeerStats {
     pub last_interaction: Instant,
     /// 最后交互时间戳（用于序列化）
     pub last_interaction_secs: u64,
+    /// 估计的时钟偏差（毫秒，含单向延迟）
+    pub clock_skew_ms: Option<i64>,
+    /// 经 gossip 收到消息的单向延迟分位数
+    pub gossip_latency: Option<LatencySummary>,
+    /// 经 QUIC 直连收到消息的单向延迟分位数
+    pub direct_latency: Option<LatencySummary>,
 }
 
 impl PeerStats {
@@ -62,6 +92,9 @@ impl PeerStats {
             updates_sent: 0,
             last_interaction: now,
             last_interaction_secs: now.elapsed().as_secs(), // 相对时间戳
+            clock_skew_ms: None,
+            gossip_latency: None,
+            direct_latency: None,
         }
     }
 }
@@ -97,6 +130,16 @@ impl TrainingStatsManager {
                 model_version,
                 model_hash,
                 peer_stats: HashMap::new(),
+                watchdog_restarts: HashMap::new(),
+                sparse_retransmits: 0,
+                sparse_ack_fallbacks: 0,
+                updates_rejected: 0,
+                params_clamped: 0,
+                errors: HashMap::new(),
+                failures: HashMap::new(),
+                publish_queue: PublishQueueStats::default(),
+                apply_queue: ApplyQueueStats::default(),
+                rewards: RewardStats::default(),
             })),
         }
     }
@@ -110,7 +153,7 @@ impl TrainingStatsManager {
         stats.sparse_updates_received += 1;
         let now = Instant::now();
         let start_time = stats.start_time;
-        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_insert_with(PeerStats::new);
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
         peer_stat.updates_received += 1;
         peer_stat.last_interaction = now;
         peer_stat.last_interaction_secs = now.duration_since(start_time).as_secs();
@@ -121,7 +164,7 @@ impl TrainingStatsManager {
         stats.dense_snapshots_received += 1;
         let now = Instant::now();
         let start_time = stats.start_time;
-        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_insert_with(PeerStats::new);
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
         peer_stat.updates_received += 1;
         peer_stat.last_interaction = now;
         peer_stat.last_interaction_secs = now.duration_since(start_time).as_secs();
@@ -132,7 +175,7 @@ impl TrainingStatsManager {
         stats.sparse_updates_sent += 1;
         let now = Instant::now();
         let start_time = stats.start_time;
-        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_insert_with(PeerStats::new);
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
         peer_stat.updates_sent += 1;
         peer_stat.last_interaction = now;
         peer_stat.last_interaction_secs = now.duration_since(start_time).as_secs();
@@ -151,7 +194,7 @@ impl TrainingStatsManager {
         stats.heartbeats_received += 1;
         let now = Instant::now();
         let start_time = stats.start_time;
-        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_insert_with(PeerStats::new);
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
         peer_stat.last_interaction = now;
         peer_stat.last_interaction_secs = now.duration_since(start_time).as_secs();
     }
@@ -165,15 +208,88 @@ impl TrainingStatsManager {
         stats.probes_received += 1;
         let now = Instant::now();
         let start_time = stats.start_time;
-        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_insert_with(PeerStats::new);
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
         peer_stat.last_interaction = now;
         peer_stat.last_interaction_secs = now.duration_since(start_time).as_secs();
     }
 
+    pub fn record_clock_skew(&self, peer_id: &str, skew_ms: i64) {
+        let mut stats = self.stats.write();
+        stats
+            .peer_stats
+            .entry(peer_id.to_string())
+            .or_default()
+            .clock_skew_ms = Some(skew_ms);
+    }
+
+    pub fn record_latency(&self, peer_id: &str, path: LatencyPath, summary: LatencySummary) {
+        let mut stats = self.stats.write();
+        let peer_stat = stats.peer_stats.entry(peer_id.to_string()).or_default();
+        match path {
+            LatencyPath::Gossip => peer_stat.gossip_latency = Some(summary),
+            LatencyPath::Direct => peer_stat.direct_latency = Some(summary),
+        }
+    }
+
+    pub fn record_watchdog_restart(&self, subsystem: &str) {
+        *self
+            .stats
+            .write()
+            .watchdog_restarts
+            .entry(subsystem.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn record_sparse_retransmit(&self) {
+        self.stats.write().sparse_retransmits += 1;
+    }
+
+    pub fn record_sparse_ack_fallback(&self) {
+        self.stats.write().sparse_ack_fallbacks += 1;
+    }
+
+    pub fn record_update_rejected(&self) {
+        self.stats.write().updates_rejected += 1;
+    }
+
+    pub fn record_params_clamped(&self, count: usize) {
+        self.stats.write().params_clamped += count as u64;
+    }
+
+    pub fn record_error(&self, code: &str) {
+        *self
+            .stats
+            .write()
+            .errors
+            .entry(code.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn record_failure(&self, kind: &str) {
+        *self
+            .stats
+            .write()
+            .failures
+            .entry(kind.to_string())
+            .or_default() += 1;
+    }
+
     pub fn update_connected_peers(&self, count: usize) {
         self.stats.write().connected_peers = count;
     }
 
+    pub fn update_publish_queue(&self, queue: PublishQueueStats) {
+        self.stats.write().publish_queue = queue;
+    }
+
+    pub fn update_apply_queue(&self, queue: ApplyQueueStats) {
+        self.stats.write().apply_queue = queue;
+    }
+
+    pub fn update_rewards(&self, rewards: RewardStats) {
+        self.stats.write().rewards = rewards;
+    }
+
     pub fn update_model(&self, hash: String, version: u64) {
         let mut stats = self.stats.write();
         stats.model_hash = hash;
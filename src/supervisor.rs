@@ -0,0 +1,220 @@
+//! 子系统监督器
+//!
+//! 每个子系统（QUIC accept、管理接口等）由一个可重复调用的工厂创建。
+//! 子系统返回错误或 panic 时按指数退避重启；在统计窗口内失败次数过多时上报升级，
+//! 由 main 负责干净地关闭节点。
+//!
+//! 训练主循环持有非 `Sync` 的 Swarm，无法放入 `tokio::spawn`，
+//! 因此由 main 直接驱动并复用同一套 [`RestartPolicy`]。
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinHandle};
+
+/// 子系统工厂：每次重启都会调用一次，生成新的运行 future
+pub type TaskFactory = Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct SupervisorConfig {
+    /// 统计窗口内允许的最大重启次数
+    pub max_restarts: u32,
+    /// 重启次数统计窗口
+    pub restart_window: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            restart_window: Duration::from_secs(300),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 重启策略：记录失败时间并计算退避，超过阈值时返回 None 表示需要升级
+pub struct RestartPolicy {
+    config: SupervisorConfig,
+    failures: Vec<Instant>,
+    backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub fn new(config: SupervisorConfig) -> Self {
+        let backoff = config.initial_backoff;
+        Self {
+            config,
+            failures: Vec::new(),
+            backoff,
+        }
+    }
+
+    /// 记录一次失败，返回下一次重启前的等待时间；None 表示应升级处理
+    pub fn on_failure(&mut self, subsystem: &str, reason: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let window = self.config.restart_window;
+        self.failures.retain(|t| now.duration_since(*t) <= window);
+        self.failures.push(now);
+        if self.failures.len() as u32 > self.config.max_restarts {
+            eprintln!(
+                "[Supervisor] 子系统 {} 在 {:?} 内失败 {} 次，升级处理: {}",
+                subsystem,
+                window,
+                self.failures.len(),
+                reason
+            );
+            return None;
+        }
+        let backoff = self.backoff;
+        eprintln!(
+            "[Supervisor] 子系统 {} 失败（{}），{:?} 后重启",
+            subsystem, reason, backoff
+        );
+        self.backoff = (self.backoff * 2).min(self.config.max_backoff);
+        Some(backoff)
+    }
+}
+
+/// 将 panic 负载转换为可读的原因
+pub fn panic_reason(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        format!("panic: {s}")
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        format!("panic: {s}")
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// 需要升级处理（关闭节点）的子系统故障
+#[derive(Debug, Clone)]
+pub struct Escalation {
+    pub subsystem: String,
+    pub reason: String,
+}
+
+pub struct Supervisor {
+    config: SupervisorConfig,
+    handles: Mutex<Vec<(String, JoinHandle<()>)>>,
+    /// 各子系统当前运行实例，供看门狗强制重启
+    running: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    escalate_tx: mpsc::UnboundedSender<Escalation>,
+    escalate_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<Escalation>>,
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig) -> Arc<Self> {
+        let (escalate_tx, escalate_rx) = mpsc::unbounded_channel();
+        Arc::new(Self {
+            config,
+            handles: Mutex::new(Vec::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            escalate_tx,
+            escalate_rx: tokio::sync::Mutex::new(escalate_rx),
+        })
+    }
+
+    /// 启动并监督一个子系统
+    pub fn spawn(&self, name: &str, factory: TaskFactory) {
+        let name_owned = name.to_string();
+        let mut policy = self.restart_policy();
+        let escalate_tx = self.escalate_tx.clone();
+        let running = Arc::clone(&self.running);
+        let handle = tokio::spawn(async move {
+            loop {
+                // 在独立任务中运行，以便捕获 panic
+                let task = tokio::spawn(factory());
+                running.lock().insert(name_owned.clone(), task.abort_handle());
+                let outcome = task.await;
+                let reason = match outcome {
+                    Ok(Ok(())) => {
+                        println!("[Supervisor] 子系统 {} 正常退出", name_owned);
+                        return;
+                    }
+                    Ok(Err(err)) => format!("{err:?}"),
+                    Err(join_err) if join_err.is_panic() => format!("panic: {join_err}"),
+                    Err(join_err) => format!("{join_err}"),
+                };
+
+                match policy.on_failure(&name_owned, &reason) {
+                    Some(backoff) => tokio::time::sleep(backoff).await,
+                    None => {
+                        let _ = escalate_tx.send(Escalation {
+                            subsystem: name_owned.clone(),
+                            reason,
+                        });
+                        return;
+                    }
+                }
+            }
+        });
+        self.handles.lock().push((name.to_string(), handle));
+    }
+
+    /// 终止子系统的当前实例，按失败处理并重启；子系统不存在时返回 false
+    pub fn restart(&self, name: &str) -> bool {
+        match self.running.lock().get(name) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy::new(self.config.clone())
+    }
+
+    /// 等待任一子系统升级
+    pub async fn escalated(&self) -> Option<Escalation> {
+        self.escalate_rx.lock().await.recv().await
+    }
+
+    /// 终止所有受监督的子系统
+    pub fn shutdown(&self) {
+        for (name, handle) in self.handles.lock().drain(..) {
+            handle.abort();
+            println!("[Supervisor] 已停止子系统 {}", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_escalates_after_repeated_failures() {
+        let supervisor = Supervisor::new(SupervisorConfig {
+            max_restarts: 2,
+            restart_window: Duration::from_secs(60),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(20),
+        });
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        supervisor.spawn(
+            "flaky",
+            Box::new(move || {
+                let counter = counter.clone();
+                Box::pin(async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    anyhow::bail!("boom")
+                })
+            }),
+        );
+        let escalation = supervisor.escalated().await.expect("escalation");
+        assert_eq!(escalation.subsystem, "flaky");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+}
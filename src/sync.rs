@@ -0,0 +1,189 @@
+//! 点对点请求 / 应答同步
+//!
+//! 节点可经 QUIC 双向流直接向指定节点索取其最新模型快照或质押账本，不必等待周期性 gossip：
+//! - 每个双向流承载一次请求与一次应答，各为一帧：4 字节大端长度 + JSON 正文；
+//!   声明长度超过上限的帧在读取正文前即被拒绝
+//! - 只有证书绑定了身份的连接才接受请求（见 `certs`），应答无需另行签名；
+//!   收到的快照仍按对端心跳宣告的哈希校验
+//! - 请求方整体有超时；应答方读取请求与等待节点生成应答也各有超时，卡住的流不会一直占用
+
+use crate::consensus::LedgerEntry;
+use crate::types::TensorSnapshot;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
+
+/// 请求帧上限
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024;
+/// 应答帧上限，与直连消息上限相同
+pub const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+/// 请求方从打开流到读完应答的时限
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// 应答方等待节点主循环生成应答的时限
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncRequest {
+    /// 对端在该会话中的当前模型快照
+    LatestSnapshot { session: String },
+    /// 对端的质押账本
+    StakeLedger,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncResponse {
+    /// 对端没有该会话的模型时为 None
+    Snapshot {
+        session: String,
+        snapshot: Option<TensorSnapshot>,
+    },
+    Ledger { entries: Vec<LedgerEntry> },
+    /// 对端拒绝应答（重放中、带宽预算不足等）
+    Refused { reason: String },
+}
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("帧长度 {len} 超过上限 {limit}")]
+    TooLarge { len: usize, limit: usize },
+    #[error("同步流读写失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("同步帧编码错误: {0}")]
+    Codec(#[from] serde_json::Error),
+    #[error("同步请求超时")]
+    Timeout,
+    #[error("节点已停止处理同步请求")]
+    Closed,
+    #[error("同步不可用: {0}")]
+    Unavailable(String),
+}
+
+/// 对端发来的请求，节点经 `reply` 回复；丢弃 `reply` 即不应答
+#[derive(Debug)]
+pub struct InboundRequest {
+    /// 对端证书中的 PeerId
+    pub peer: String,
+    pub request: SyncRequest,
+    pub reply: oneshot::Sender<SyncResponse>,
+}
+
+pub async fn write_frame<W, T>(writer: &mut W, value: &T, limit: usize) -> Result<(), SyncError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let body = serde_json::to_vec(value)?;
+    if body.len() > limit {
+        return Err(SyncError::TooLarge {
+            len: body.len(),
+            limit,
+        });
+    }
+    writer.write_all(&(body.len() as u32).to_be_bytes()).await?;
+    writer.write_all(&body).await?;
+    Ok(())
+}
+
+pub async fn read_frame<R, T>(reader: &mut R, limit: usize) -> Result<T, SyncError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let len = reader.read_u32().await? as usize;
+    if len > limit {
+        return Err(SyncError::TooLarge { len, limit });
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// 请求方：在一个双向流上发出请求并读取应答
+pub async fn request<W, R>(mut send: W, mut recv: R, request: &SyncRequest) -> Result<SyncResponse, SyncError>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let exchange = async {
+        write_frame(&mut send, request, MAX_REQUEST_BYTES).await?;
+        send.shutdown().await?;
+        read_frame(&mut recv, MAX_RESPONSE_BYTES).await
+    };
+    timeout(REQUEST_TIMEOUT, exchange).await.map_err(|_| SyncError::Timeout)?
+}
+
+/// 应答方：读取一个请求交给节点，把节点的应答写回同一个流
+pub async fn serve<W, R>(
+    peer: String,
+    mut send: W,
+    mut recv: R,
+    requests: mpsc::UnboundedSender<InboundRequest>,
+) -> Result<(), SyncError>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let request = timeout(REQUEST_TIMEOUT, read_frame(&mut recv, MAX_REQUEST_BYTES))
+        .await
+        .map_err(|_| SyncError::Timeout)??;
+    let (reply, response) = oneshot::channel();
+    requests
+        .send(InboundRequest {
+            peer,
+            request,
+            reply,
+        })
+        .map_err(|_| SyncError::Closed)?;
+    let response = timeout(REPLY_TIMEOUT, response)
+        .await
+        .map_err(|_| SyncError::Timeout)?
+        .map_err(|_| SyncError::Closed)?;
+    write_frame(&mut send, &response, MAX_RESPONSE_BYTES).await?;
+    send.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_response_round_trip_and_frame_limit() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (client_recv, client_send) = tokio::io::split(client);
+        let (server_recv, server_send) = tokio::io::split(server);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let served = tokio::spawn(serve("alice".into(), server_send, server_recv, tx));
+        let answer = tokio::spawn(async move {
+            let inbound = rx.recv().await.unwrap();
+            assert_eq!(inbound.peer, "alice");
+            assert_eq!(inbound.request, SyncRequest::StakeLedger);
+            let entry = LedgerEntry {
+                peer: "bob".into(),
+                stake_eth: 1.0,
+                stake_sol: 0.0,
+                reputation: 0.5,
+            };
+            inbound.reply.send(SyncResponse::Ledger { entries: vec![entry] }).unwrap();
+        });
+        let response = request(client_send, client_recv, &SyncRequest::StakeLedger).await.unwrap();
+        let SyncResponse::Ledger { entries } = response else {
+            panic!("unexpected response: {response:?}");
+        };
+        assert_eq!(entries[0].peer, "bob");
+        served.await.unwrap().unwrap();
+        answer.await.unwrap();
+
+        // 声明长度超限的帧不读正文即被拒绝
+        let mut oversized = ((MAX_REQUEST_BYTES + 1) as u32).to_be_bytes().to_vec();
+        oversized.extend_from_slice(b"{}");
+        let read = read_frame::<_, SyncRequest>(&mut oversized.as_slice(), MAX_REQUEST_BYTES).await;
+        assert!(matches!(read, Err(SyncError::TooLarge { .. })));
+    }
+}
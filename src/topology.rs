@@ -1,20 +1,52 @@
-use crate::types::GeoPoint;
+use crate::latency::LatencySummary;
+use crate::types::{GeoPoint, PeerMeta};
 use parking_lot::RwLock;
+use rand::Rng;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
-const EMBEDDING_WEIGHT: f32 = 0.6;
-const GEO_WEIGHT: f32 = 0.4;
-
 #[derive(Clone, Debug)]
 pub struct PeerProfile {
     pub embedding: Vec<f32>,
     pub position: GeoPoint,
     pub similarity: f32,
     pub geo_affinity: f32,
+    /// 统一信任分，设置后在排序中取代原始相似度
+    pub trust: Option<f32>,
+    /// 角色带来的排序加成
+    pub bonus: f32,
+    /// 收到该节点消息的单向延迟（较快路径）
+    pub latency: Option<LatencySummary>,
+    /// identify 宣告的软件版本
+    pub agent_version: Option<String>,
+    /// identify 宣告的真实地址
+    pub addrs: Vec<String>,
     pub score: f32,
     pub last_seen: Instant,
+    /// 本轮静默已发出过重探测，收到新的探测后清除
+    pub reprobed: bool,
+}
+
+impl PeerProfile {
+    fn rank_score(&self, config: &TopologyConfig) -> f32 {
+        config.embedding_weight * self.trust.unwrap_or(self.similarity)
+            + config.geo_weight * self.geo_affinity
+            + self.bonus
+            - config.latency_weight * self.latency.map_or(0.0, |l| latency_penalty(config, l))
+    }
+
+    /// 排序时使用的分数：静默超过宽限期后按半衰期衰减，节点逐步让出邻居位置，
+    /// 而不是在过期清理时突然消失
+    fn decayed_score(&self, config: &TopologyConfig, now: Instant) -> f32 {
+        let silent = now.saturating_duration_since(self.last_seen).as_secs_f32();
+        let overdue = silent - config.silence_grace_secs as f32;
+        if overdue <= 0.0 || self.score <= 0.0 {
+            return self.score;
+        }
+        let half_life = config.decay_half_life_secs.max(1) as f32;
+        self.score * 0.5f32.powf(overdue / half_life)
+    }
 }
 
 #[derive(Clone)]
@@ -24,6 +56,22 @@ pub struct TopologyConfig {
     pub min_score: f32,
     pub geo_scale_km: f32,
     pub peer_stale_secs: u64,
+    /// 已聚类时主邻居中跨簇节点的比例
+    pub cross_cluster_fraction: f32,
+    /// 排序分中嵌入相似度（或信任分）的权重
+    pub embedding_weight: f32,
+    /// 排序分中地理亲和度的权重
+    pub geo_weight: f32,
+    /// 排序分中延迟惩罚的权重，0 表示延迟不参与排序
+    pub latency_weight: f32,
+    /// p50 延迟达到该值时惩罚为权重的一半
+    pub latency_scale_ms: f32,
+    /// 超过该时长没有收到探测的节点开始衰减排序分，主邻居同时被主动重探测
+    pub silence_grace_secs: u64,
+    /// 静默节点排序分的衰减半衰期
+    pub decay_half_life_secs: u64,
+    /// 每条稀疏更新经 QUIC 定向发送的邻居数，按信任分加权随机抽取；0 表示发给全部主邻居
+    pub fanout: usize,
 }
 
 impl Default for TopologyConfig {
@@ -34,6 +82,14 @@ impl Default for TopologyConfig {
             min_score: 0.15,
             geo_scale_km: 500.0,
             peer_stale_secs: 120,
+            cross_cluster_fraction: 0.3,
+            embedding_weight: 0.6,
+            geo_weight: 0.4,
+            latency_weight: 0.0,
+            latency_scale_ms: 200.0,
+            silence_grace_secs: 30,
+            decay_half_life_secs: 30,
+            fanout: 0,
         }
     }
 }
@@ -41,7 +97,12 @@ impl Default for TopologyConfig {
 pub struct TopologySelector {
     position: GeoPoint,
     peers: RwLock<HashMap<String, PeerProfile>>,
-    config: TopologyConfig,
+    /// 节点元数据与接收时间，独立于探测更新保存
+    metas: RwLock<HashMap<String, (PeerMeta, Instant)>>,
+    /// 嵌入聚类得到的同簇节点，未聚类时为 None
+    same_cluster: RwLock<Option<HashSet<String>>>,
+    /// 可在运行时热加载
+    config: RwLock<TopologyConfig>,
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +111,10 @@ pub struct PeerSnapshot {
     pub geo_affinity: f32,
     pub position: GeoPoint,
     pub embedding_dim: usize,
+    pub latency_p50_ms: Option<u64>,
+    pub latency_p95_ms: Option<u64>,
+    pub agent_version: Option<String>,
+    pub addrs: Vec<String>,
 }
 
 impl TopologySelector {
@@ -57,7 +122,9 @@ impl TopologySelector {
         Self {
             position,
             peers: RwLock::new(HashMap::new()),
-            config,
+            metas: RwLock::new(HashMap::new()),
+            same_cluster: RwLock::new(None),
+            config: RwLock::new(config),
         }
     }
 
@@ -71,59 +138,190 @@ impl TopologySelector {
         embedding: Vec<f32>,
         position: GeoPoint,
         self_embedding: &[f32],
+        score_bonus: f32,
     ) {
         let similarity = cosine_sim(self_embedding, &embedding);
         let geo_affinity = self.geo_affinity(&position);
-        let score = EMBEDDING_WEIGHT * similarity + GEO_WEIGHT * geo_affinity;
-        let profile = PeerProfile {
+        let mut peers = self.peers.write();
+        let previous = peers.get(peer_id);
+        let trust = previous.and_then(|p| p.trust);
+        let latency = previous.and_then(|p| p.latency);
+        let agent_version = previous.and_then(|p| p.agent_version.clone());
+        let addrs = previous.map(|p| p.addrs.clone()).unwrap_or_default();
+        let mut profile = PeerProfile {
             embedding,
             position,
             similarity,
             geo_affinity,
-            score,
+            trust,
+            bonus: score_bonus,
+            latency,
+            agent_version,
+            addrs,
+            score: 0.0,
             last_seen: Instant::now(),
+            reprobed: false,
         };
+        profile.score = profile.rank_score(&self.config.read());
+        peers.insert(peer_id.to_string(), profile);
+        self.cleanup_locked(&mut peers);
+    }
+
+    /// 冷启动先验：尚未收到探测的节点按模型谱系估计相似度，先参与排序；
+    /// 收到探测后由 [`update_peer`](Self::update_peer) 以真实嵌入取代。已有真实嵌入的节点不受影响
+    pub fn seed_peer(&self, peer_id: &str, same_lineage: bool, score_bonus: f32) {
         let mut peers = self.peers.write();
+        if peers.get(peer_id).is_some_and(|p| !p.embedding.is_empty()) {
+            return;
+        }
+        let previous = peers.get(peer_id);
+        let mut profile = PeerProfile {
+            embedding: Vec::new(),
+            position: self.position.clone(),
+            similarity: if same_lineage { LINEAGE_PRIOR } else { FOREIGN_PRIOR },
+            // 位置未知，不计地理亲和度
+            geo_affinity: 0.0,
+            trust: previous.and_then(|p| p.trust),
+            bonus: score_bonus,
+            latency: previous.and_then(|p| p.latency),
+            agent_version: previous.and_then(|p| p.agent_version.clone()),
+            addrs: previous.map(|p| p.addrs.clone()).unwrap_or_default(),
+            score: 0.0,
+            last_seen: Instant::now(),
+            reprobed: false,
+        };
+        profile.score = profile.rank_score(&self.config.read());
         peers.insert(peer_id.to_string(), profile);
         self.cleanup_locked(&mut peers);
     }
 
+    pub fn update_meta(&self, peer_id: &str, meta: PeerMeta) {
+        let mut metas = self.metas.write();
+        metas.insert(peer_id.to_string(), (meta, Instant::now()));
+        let deadline = Instant::now() - Duration::from_secs(self.config.read().peer_stale_secs);
+        metas.retain(|_, (_, seen)| *seen >= deadline);
+    }
+
+    pub fn peer_meta(&self, peer_id: &str) -> Option<PeerMeta> {
+        self.metas.read().get(peer_id).map(|(meta, _)| meta.clone())
+    }
+
+    pub fn peer_metas(&self) -> Vec<(String, PeerMeta)> {
+        self.metas
+            .read()
+            .iter()
+            .map(|(peer, (meta, _))| (peer.clone(), meta.clone()))
+            .collect()
+    }
+
     pub fn neighbor_sets(&self) -> (Vec<String>, Vec<String>) {
+        let config = self.config.read().clone();
         let peers = self.peers.read();
-        let mut ranked: Vec<_> = peers.iter().collect();
-        ranked.sort_by(|(_, a), (_, b)| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
-        let mut primary = Vec::new();
-        let mut backups = Vec::new();
-        for (_idx, (peer, profile)) in ranked.into_iter().enumerate() {
-            if profile.score < self.config.min_score {
-                continue;
-            }
-            if primary.len() < self.config.max_neighbors {
-                primary.push(peer.clone());
-            } else if backups.len() < self.config.failover_pool {
-                backups.push(peer.clone());
-            } else {
-                break;
-            }
-        }
+        let now = Instant::now();
+        let mut ranked: Vec<_> = peers
+            .iter()
+            .map(|(peer, profile)| (peer, profile.decayed_score(&config, now)))
+            .filter(|(_, score)| *score >= config.min_score)
+            .collect();
+        ranked.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        let ranked: Vec<String> = ranked.into_iter().map(|(peer, _)| peer.clone()).collect();
+        let primary = match &*self.same_cluster.read() {
+            Some(same) => mix_clusters(&config, &ranked, same),
+            None => ranked.iter().take(config.max_neighbors).cloned().collect(),
+        };
+        let backups = ranked
+            .into_iter()
+            .filter(|peer| !primary.contains(peer))
+            .take(config.failover_pool)
+            .collect();
         (primary, backups)
     }
 
+    /// 热加载拓扑配置，按新的权重重新计算所有节点的排序分
+    pub fn set_config(&self, config: TopologyConfig) {
+        let mut peers = self.peers.write();
+        for profile in peers.values_mut() {
+            profile.score = profile.rank_score(&config);
+        }
+        *self.config.write() = config;
+    }
+
+    /// 设置嵌入聚类得到的同簇节点，None 表示取消按簇混合
+    pub fn set_same_cluster(&self, same: Option<HashSet<String>>) {
+        *self.same_cluster.write() = same;
+    }
+
+    /// 各节点最近一次探测宣告的嵌入
+    pub fn embeddings(&self) -> Vec<(String, Vec<f32>)> {
+        self.peers
+            .read()
+            .iter()
+            .filter(|(_, profile)| !profile.embedding.is_empty())
+            .map(|(peer, profile)| (peer.clone(), profile.embedding.clone()))
+            .collect()
+    }
+
     pub fn select_neighbors(&self) -> Vec<String> {
         self.neighbor_sets().0
     }
 
+    /// 静默超过宽限期、尚未重探测过的主邻居，返回前标记为已重探测
+    pub fn silent_neighbors(&self) -> Vec<String> {
+        let (primary, _) = self.neighbor_sets();
+        let grace = Duration::from_secs(self.config.read().silence_grace_secs);
+        let mut peers = self.peers.write();
+        primary
+            .into_iter()
+            .filter(|peer| {
+                peers.get_mut(peer).is_some_and(|profile| {
+                    let silent = !profile.reprobed && profile.last_seen.elapsed() > grace;
+                    profile.reprobed |= silent;
+                    silent
+                })
+            })
+            .collect()
+    }
+
     pub fn mark_unreachable(&self, peer_id: &str) {
         let mut peers = self.peers.write();
         peers.remove(peer_id);
     }
 
     pub fn max_neighbors(&self) -> usize {
-        self.config.max_neighbors
+        self.config.read().max_neighbors
     }
 
     pub fn failover_pool(&self) -> usize {
-        self.config.failover_pool
+        self.config.read().failover_pool
+    }
+
+    /// 从候选邻居中选出定向发送一条稀疏更新的目标：未限制 fanout 或候选不多于 fanout 时全部保留，
+    /// 否则按信任分加权、不放回地随机抽取，尚无信任分的节点按相似度计。候选按节点排序后抽取，
+    /// 结果只取决于 `rng`（节点用信标派生），与候选的先后顺序无关
+    pub fn fanout_targets<T>(
+        &self,
+        mut candidates: Vec<(String, T)>,
+        rng: &mut impl Rng,
+    ) -> Vec<(String, T)> {
+        let fanout = self.config.read().fanout;
+        if fanout == 0 || candidates.len() <= fanout {
+            return candidates;
+        }
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+        let weights: Vec<f32> = {
+            let peers = self.peers.read();
+            candidates
+                .iter()
+                .map(|(peer, _)| peers.get(peer).map_or(0.0, |p| p.trust.unwrap_or(p.similarity)))
+                .collect()
+        };
+        let chosen = weighted_sample(&weights, fanout, rng);
+        candidates
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| chosen.contains(i))
+            .map(|(_, candidate)| candidate)
+            .collect()
     }
 
     pub fn peer_snapshot(&self, peer_id: &str) -> Option<PeerSnapshot> {
@@ -132,20 +330,131 @@ impl TopologySelector {
             geo_affinity: profile.geo_affinity,
             position: profile.position.clone(),
             embedding_dim: profile.embedding.len(),
+            latency_p50_ms: profile.latency.map(|l| l.p50_ms),
+            latency_p95_ms: profile.latency.map(|l| l.p95_ms),
+            agent_version: profile.agent_version.clone(),
+            addrs: profile.addrs.clone(),
         })
     }
 
+    /// 记录 identify 得到的版本与地址
+    pub fn set_identity(&self, peer_id: &str, agent_version: String, addrs: Vec<String>) {
+        if let Some(profile) = self.peers.write().get_mut(peer_id) {
+            profile.agent_version = Some(agent_version);
+            profile.addrs = addrs;
+        }
+    }
+
+    /// 更新节点的延迟分位数并重新计算排序分数
+    pub fn set_latency(&self, peer_id: &str, latency: LatencySummary) {
+        if let Some(profile) = self.peers.write().get_mut(peer_id) {
+            profile.latency = Some(latency);
+            profile.score = profile.rank_score(&self.config.read());
+        }
+    }
+
+    /// 更新节点的信任分并重新计算排序分数
+    pub fn set_trust(&self, peer_id: &str, trust: f32) {
+        if let Some(profile) = self.peers.write().get_mut(peer_id) {
+            profile.trust = Some(trust);
+            profile.score = profile.rank_score(&self.config.read());
+        }
+    }
+
     pub fn geo_affinity(&self, other: &GeoPoint) -> f32 {
         let dist = self.position.distance_km(other);
-        (self.config.geo_scale_km / (self.config.geo_scale_km + dist)).clamp(0.0, 1.0)
+        let scale = self.config.read().geo_scale_km;
+        (scale / (scale + dist)).clamp(0.0, 1.0)
+    }
+
+    /// 节点画像（含嵌入向量）与元数据的估算内存占用
+    pub fn memory_bytes(&self) -> usize {
+        let profiles: usize = self.peers.read().values().map(profile_bytes).sum();
+        profiles + self.metas.read().len() * META_BYTES
+    }
+
+    /// 内存紧张时丢弃不在邻居与备用集合中的画像，最久未见的先丢，返回释放的估算字节数
+    pub fn shed_profiles(&self, target: usize) -> usize {
+        let (primary, backups) = self.neighbor_sets();
+        let mut peers = self.peers.write();
+        let mut candidates: Vec<(Instant, String)> = peers
+            .iter()
+            .filter(|(peer, _)| !primary.contains(peer) && !backups.contains(peer))
+            .map(|(peer, profile)| (profile.last_seen, peer.clone()))
+            .collect();
+        candidates.sort();
+        let mut metas = self.metas.write();
+        let mut freed = 0;
+        for (_, peer) in candidates {
+            if freed >= target {
+                break;
+            }
+            if let Some(profile) = peers.remove(&peer) {
+                freed += profile_bytes(&profile);
+            }
+            if metas.remove(&peer).is_some() {
+                freed += META_BYTES;
+            }
+        }
+        freed
     }
 
     fn cleanup_locked(&self, peers: &mut HashMap<String, PeerProfile>) {
-        let deadline = Instant::now() - Duration::from_secs(self.config.peer_stale_secs);
+        let deadline = Instant::now() - Duration::from_secs(self.config.read().peer_stale_secs);
         peers.retain(|_, profile| profile.last_seen >= deadline);
     }
 }
 
+/// 按比例从跨簇与同簇节点中各取排名靠前的，一方不足时由另一方补齐
+fn mix_clusters(config: &TopologyConfig, ranked: &[String], same: &HashSet<String>) -> Vec<String> {
+    let max = config.max_neighbors;
+    let (same_ranked, cross_ranked): (Vec<&String>, Vec<&String>) =
+        ranked.iter().partition(|peer| same.contains(*peer));
+    let cross_target = ((max as f32 * config.cross_cluster_fraction).round() as usize)
+        .min(cross_ranked.len());
+    let same_target = (max - cross_target).min(same_ranked.len());
+    let cross_target = (max - same_target).min(cross_ranked.len());
+    let chosen: HashSet<&String> = same_ranked[..same_target]
+        .iter()
+        .chain(&cross_ranked[..cross_target])
+        .copied()
+        .collect();
+    // 保持总体排名顺序
+    ranked.iter().filter(|peer| chosen.contains(peer)).cloned().collect()
+}
+
+/// 冷启动先验相似度：同一模型谱系（相同谱系根或近期哈希）的节点
+const LINEAGE_PRIOR: f32 = 0.9;
+/// 冷启动先验相似度：谱系不同的节点，排在同谱系节点之后
+const FOREIGN_PRIOR: f32 = 0.3;
+
+/// 定向发送抽样时的最低权重，信任分为 0 的邻居仍有少量机会被选中
+const MIN_FANOUT_WEIGHT: f32 = 0.01;
+
+/// 不放回加权抽样（Efraimidis-Spirakis）：每项取键 u^(1/w)，保留键最大的 k 项
+fn weighted_sample(weights: &[f32], k: usize, rng: &mut impl Rng) -> HashSet<usize> {
+    let mut keyed: Vec<(f32, usize)> = weights
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (rng.gen::<f32>().powf(1.0 / w.max(MIN_FANOUT_WEIGHT)), i))
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed.into_iter().take(k).map(|(_, i)| i).collect()
+}
+
+/// 单条节点元数据的估算占用（昵称、组织、联系方式等短字符串）
+const META_BYTES: usize = 512;
+
+/// 延迟惩罚落在 [0, 1)，p50 越高越接近 1
+fn latency_penalty(config: &TopologyConfig, latency: LatencySummary) -> f32 {
+    let p50 = latency.p50_ms as f32;
+    p50 / (p50 + config.latency_scale_ms.max(1.0))
+}
+
+fn profile_bytes(profile: &PeerProfile) -> usize {
+    std::mem::size_of::<PeerProfile>() + profile.embedding.len() * std::mem::size_of::<f32>()
+}
+
 fn cosine_sim(a: &[f32], b: &[f32]) -> f32 {
     let mut dot = 0.0f32;
     let mut na = 0.0f32;
@@ -160,3 +469,81 @@ fn cosine_sim(a: &[f32], b: &[f32]) -> f32 {
     }
     dot / (na.sqrt() * nb.sqrt())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silent_neighbor_is_reprobed_then_decays_out() {
+        let here = GeoPoint { lat: 0.0, lon: 0.0 };
+        let topology = TopologySelector::new(here.clone(), TopologyConfig::default());
+        topology.update_peer("quiet", vec![1.0, 0.0], here.clone(), &[1.0, 0.0], 0.0);
+        topology.update_peer("chatty", vec![0.0, 1.0], here.clone(), &[1.0, 0.0], 0.0);
+        assert_eq!(topology.select_neighbors(), ["quiet", "chatty"]);
+        assert!(topology.silent_neighbors().is_empty());
+
+        // 静默 70 秒：只重探测一次，排序分衰减到落后于持续探测的节点
+        topology.peers.write().get_mut("quiet").unwrap().last_seen -= Duration::from_secs(70);
+        assert_eq!(topology.silent_neighbors(), ["quiet"]);
+        assert!(topology.silent_neighbors().is_empty());
+        assert_eq!(topology.select_neighbors(), ["chatty", "quiet"]);
+
+        // 继续静默则跌破最低分，退出邻居集合；重新收到探测后恢复
+        topology.peers.write().get_mut("quiet").unwrap().last_seen -= Duration::from_secs(60);
+        assert_eq!(topology.select_neighbors(), ["chatty"]);
+        topology.update_peer("quiet", vec![1.0, 0.0], here.clone(), &[1.0, 0.0], 0.0);
+        assert_eq!(topology.select_neighbors(), ["quiet", "chatty"]);
+    }
+
+    #[test]
+    fn test_lineage_prior_ranks_until_probes_arrive() {
+        let here = GeoPoint { lat: 0.0, lon: 0.0 };
+        let topology = TopologySelector::new(here.clone(), TopologyConfig::default());
+        topology.seed_peer("foreign", false, 0.0);
+        topology.seed_peer("sibling", true, 0.0);
+        assert_eq!(topology.select_neighbors(), ["sibling", "foreign"]);
+        assert!(topology.embeddings().is_empty());
+
+        // 真实嵌入取代先验，之后的心跳不再覆盖
+        topology.update_peer("foreign", vec![1.0, 0.0], here.clone(), &[1.0, 0.0], 0.0);
+        topology.update_peer("sibling", vec![0.0, 1.0], here, &[1.0, 0.0], 0.0);
+        topology.seed_peer("sibling", true, 0.0);
+        assert_eq!(topology.select_neighbors(), ["foreign", "sibling"]);
+    }
+
+    #[test]
+    fn test_fanout_samples_neighbors_by_trust() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let weights = [0.9, 0.0, 0.05, 0.05];
+        let mut trusted = 0;
+        for _ in 0..200 {
+            let chosen = weighted_sample(&weights, 2, &mut rng);
+            assert_eq!(chosen.len(), 2);
+            trusted += usize::from(chosen.contains(&0));
+        }
+        assert!(trusted > 190, "trusted neighbor chosen {trusted} times");
+
+        let here = GeoPoint { lat: 0.0, lon: 0.0 };
+        let config = TopologyConfig { fanout: 2, ..TopologyConfig::default() };
+        let topology = TopologySelector::new(here, config);
+        let candidates: Vec<(String, u16)> = (0..4).map(|i| (format!("peer{i}"), i)).collect();
+        let picked = topology.fanout_targets(
+            candidates.clone(),
+            &mut rand::rngs::StdRng::seed_from_u64(1),
+        );
+        assert_eq!(picked.len(), 2);
+        // 同一种子下与候选顺序无关
+        let reversed: Vec<(String, u16)> = candidates.iter().rev().cloned().collect();
+        assert_eq!(
+            topology.fanout_targets(reversed, &mut rand::rngs::StdRng::seed_from_u64(1)),
+            picked
+        );
+        assert_eq!(
+            topology.fanout_targets(candidates[..2].to_vec(), &mut rng),
+            candidates[..2]
+        );
+    }
+}
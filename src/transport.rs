@@ -0,0 +1,432 @@
+//! 可替换的消息传输层
+//!
+//! 节点逻辑只通过 [`GossipBackend`] 收发消息：按会话广播、直连发送、统计 gossip 节点数，
+//! 以及取下一条入站消息；[`NodeBackend`] 在其上补充节点主循环用到的点对点发送、带宽预算
+//! 与节点发现等操作。默认实现是 `comms::CommsHandle`（libp2p gossipsub + QUIC 直连），
+//! 重放与测试使用进程内的模拟网络；纯 QUIC mesh、受限网络下的中心化中继等后端实现同一 trait 即可接入，
+//! libp2p 专有的事务（DHT、rendezvous、地址簿等）有空的默认实现。
+
+use crate::comms::{BandwidthBudgetConfig, BudgetUsage, IdentifiedPeer, PeerAddresses, SendPolicy};
+use crate::consensus::SignedGossip;
+use crate::device::NetworkType;
+use crate::latency::{LatencyPath, LatencySummary};
+use crate::pipeline::Verdict;
+use crate::pubqueue::PublishQueueStats;
+use crate::supervisor::TaskFactory;
+use crate::sync::{InboundRequest, SyncError, SyncRequest, SyncResponse};
+use crate::watchdog::Beat;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// 后端交给节点的入站事件
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// gossip 消息：`source` 为消息源节点（后端能验证时），`propagation` 为转发来的节点。
+    /// 后端在节点上报校验结果（[`NodeBackend::report_validation`]）之前不转发该消息
+    Gossip {
+        signed: SignedGossip,
+        source: Option<String>,
+        propagation: String,
+        message_id: Vec<u8>,
+    },
+    /// 直连消息：`from` 为传输层确认的对端身份（证书 PeerId，未知时为远端地址）
+    Direct { signed: SignedGossip, from: String },
+    /// 对端经请求 / 应答通道发来的同步请求
+    SyncRequest(InboundRequest),
+    /// 本节点向 `peer` 发出的同步请求的结果
+    SyncReply {
+        peer: String,
+        result: Result<SyncResponse, SyncError>,
+    },
+}
+
+#[async_trait(?Send)]
+pub trait GossipBackend: Send {
+    /// 注册一个训练会话：订阅其主题，`share` 为该会话占用的带宽比例
+    fn register_session(&mut self, session: &str, topic: &str, share: f32) -> Result<()>;
+
+    /// 广播到消息所属会话的主题
+    fn publish(&mut self, signed: &SignedGossip) -> Result<()>;
+
+    /// 直接发给指定地址的节点
+    async fn send_direct(&self, addr: SocketAddr, signed: &SignedGossip) -> Result<()>;
+
+    /// 订阅了默认会话主题的节点数量
+    fn gossip_peer_count(&self) -> usize;
+
+    /// 等待下一条入站消息；后端关闭时返回 None。取消后再次调用不会丢失消息
+    async fn next_event(&mut self) -> Option<TransportEvent>;
+}
+
+/// 节点主循环使用的完整后端接口。除本节点身份、按节点发送与网络类型外都有默认实现：
+/// 不限带宽、没有实时直连通道，节点发现与连接管理为空操作
+#[async_trait(?Send)]
+pub trait NodeBackend: GossipBackend {
+    /// 本节点在该后端上的身份
+    fn local_peer_id(&self) -> String;
+
+    /// 直接发给指定节点
+    async fn send_to(&self, peer: &str, signed: &SignedGossip) -> Result<()>;
+
+    fn network_type(&self) -> NetworkType;
+
+    fn update_network_type(&self, network_type: NetworkType);
+
+    /// 退订主题、关闭连接，最多等待 `grace`
+    async fn shutdown(&mut self, _grace: Duration) {}
+
+    fn set_bandwidth(&mut self, _config: BandwidthBudgetConfig) {}
+
+    fn allow_sparse_update(&self, _session: &str) -> bool {
+        true
+    }
+
+    fn allow_dense_snapshot(&self, _session: &str, _bytes: usize) -> bool {
+        self.network_type().allows_dense_snapshot()
+    }
+
+    fn sparse_budget_usage(&self, _session: &str) -> Option<BudgetUsage> {
+        None
+    }
+
+    fn send_policy(&self) -> SendPolicy {
+        SendPolicy::default()
+    }
+
+    /// 经实时通道发给拓扑邻居，返回是否至少送达一个
+    async fn send_realtime(
+        &self,
+        _signed: &SignedGossip,
+        _targets: &[(String, SocketAddr)],
+    ) -> bool {
+        false
+    }
+
+    /// 经实时通道发给所有已连接节点，返回是否至少送达一个
+    async fn broadcast_realtime(&self, _signed: &SignedGossip) -> bool {
+        false
+    }
+
+    /// 心跳中宣告的直连地址
+    fn quic_advertise(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    fn quic_addrs(&self) -> Vec<SocketAddr> {
+        Vec::new()
+    }
+
+    /// 直连 accept 循环的任务工厂；没有 QUIC 通道的后端返回 None
+    fn quic_accept_task(&self, _beat: Beat) -> Option<TaskFactory> {
+        None
+    }
+
+    fn connect_realtime(&self, _neighbors: Vec<(String, Vec<SocketAddr>)>) {}
+
+    /// 向节点发出同步请求，结果以 [`TransportEvent::SyncReply`] 送回；不支持时忽略
+    fn request_sync(&self, _peer: &str, _addr: SocketAddr, _request: SyncRequest) {}
+
+    fn codec_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn record_peer_codecs(&self, _peer: &str, _addr: Option<SocketAddr>, _theirs: &[String]) {}
+
+    fn prefers_gossip(&self, _peer: &str) -> bool {
+        false
+    }
+
+    fn observe_latency(
+        &self,
+        _peer: &str,
+        _path: LatencyPath,
+        _sent_at_ms: u64,
+        _now_ms: u64,
+    ) -> Option<LatencySummary> {
+        None
+    }
+
+    fn peer_latency(&self, _peer: &str) -> Option<LatencySummary> {
+        None
+    }
+
+    fn flush_publish_queue(&mut self) {}
+
+    fn publish_queue_stats(&self) -> PublishQueueStats {
+        PublishQueueStats::default()
+    }
+
+    fn set_application_scores(&mut self, _scores: &[(String, f64)]) {}
+
+    /// 上报 gossip 消息的校验结果：放行的继续转发，拒绝的计入转发者的无效消息评分
+    fn report_validation(&mut self, _message_id: &[u8], _propagation: &str, _verdict: Verdict) {}
+
+    fn set_preferred_peers(&mut self, _ranked: Vec<String>) {}
+
+    fn prune_connections(&mut self) {}
+
+    fn next_bootstrap_retry(&self) -> Option<Instant> {
+        None
+    }
+
+    fn retry_bootstrap(&mut self) {}
+
+    fn refresh_rendezvous(&mut self) {}
+
+    fn refresh_dht(&mut self) {}
+
+    fn reachable_addrs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn learn_peer_addrs(&mut self, _peer: &str, _addrs: &[String]) {}
+
+    fn identified_peer(&self, _peer: &str) -> Option<IdentifiedPeer> {
+        None
+    }
+
+    fn export_address_book(&self) -> Vec<PeerAddresses> {
+        Vec::new()
+    }
+
+    fn restore_address_book(&mut self, _entries: Vec<PeerAddresses>) {}
+}
+
+/// 进程内模拟网络：所有节点共享一个集线器，广播按会话投递给其他订阅者，直连按地址或身份投递
+pub mod memory {
+    use super::*;
+    use crate::session::DEFAULT_SESSION;
+    use anyhow::anyhow;
+    use parking_lot::Mutex;
+    use std::collections::{HashMap, HashSet};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    struct MemoryPeer {
+        sessions: HashSet<String>,
+        addr: SocketAddr,
+        tx: mpsc::UnboundedSender<TransportEvent>,
+    }
+
+    #[derive(Default)]
+    struct Hub {
+        peers: HashMap<String, MemoryPeer>,
+        next_port: u16,
+    }
+
+    #[derive(Clone, Default)]
+    pub struct MemoryNetwork {
+        hub: Arc<Mutex<Hub>>,
+    }
+
+    impl MemoryNetwork {
+        /// 以给定身份加入网络，默认订阅默认会话
+        pub fn join(&self, peer_id: &str) -> MemoryBackend {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let mut hub = self.hub.lock();
+            hub.next_port += 1;
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), hub.next_port);
+            hub.peers.insert(
+                peer_id.to_string(),
+                MemoryPeer {
+                    sessions: HashSet::from([DEFAULT_SESSION.to_string()]),
+                    addr,
+                    tx,
+                },
+            );
+            MemoryBackend {
+                peer_id: peer_id.to_string(),
+                addr,
+                hub: Arc::clone(&self.hub),
+                rx,
+                network_type: Mutex::new(NetworkType::WiFi),
+            }
+        }
+    }
+
+    pub struct MemoryBackend {
+        peer_id: String,
+        addr: SocketAddr,
+        hub: Arc<Mutex<Hub>>,
+        rx: mpsc::UnboundedReceiver<TransportEvent>,
+        network_type: Mutex<NetworkType>,
+    }
+
+    impl MemoryBackend {
+        /// 其他节点直连本节点使用的地址
+        pub fn addr(&self) -> SocketAddr {
+            self.addr
+        }
+    }
+
+    impl Drop for MemoryBackend {
+        fn drop(&mut self) {
+            self.hub.lock().peers.remove(&self.peer_id);
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl GossipBackend for MemoryBackend {
+        fn register_session(&mut self, session: &str, _topic: &str, _share: f32) -> Result<()> {
+            if let Some(peer) = self.hub.lock().peers.get_mut(&self.peer_id) {
+                peer.sessions.insert(session.to_string());
+            }
+            Ok(())
+        }
+
+        fn publish(&mut self, signed: &SignedGossip) -> Result<()> {
+            let hub = self.hub.lock();
+            for (id, peer) in &hub.peers {
+                if *id == self.peer_id || !peer.sessions.contains(&signed.session) {
+                    continue;
+                }
+                let _ = peer.tx.send(TransportEvent::Gossip {
+                    signed: signed.clone(),
+                    source: Some(self.peer_id.clone()),
+                    propagation: self.peer_id.clone(),
+                    message_id: Vec::new(),
+                });
+            }
+            Ok(())
+        }
+
+        async fn send_direct(&self, addr: SocketAddr, signed: &SignedGossip) -> Result<()> {
+            let hub = self.hub.lock();
+            let peer = hub
+                .peers
+                .values()
+                .find(|p| p.addr == addr)
+                .ok_or_else(|| anyhow!("no simulated peer at {}", addr))?;
+            let event = TransportEvent::Direct {
+                signed: signed.clone(),
+                from: self.peer_id.clone(),
+            };
+            peer.tx
+                .send(event)
+                .map_err(|_| anyhow!("simulated peer at {} is gone", addr))
+        }
+
+        fn gossip_peer_count(&self) -> usize {
+            let hub = self.hub.lock();
+            hub.peers
+                .iter()
+                .filter(|(id, p)| **id != self.peer_id && p.sessions.contains(DEFAULT_SESSION))
+                .count()
+        }
+
+        async fn next_event(&mut self) -> Option<TransportEvent> {
+            self.rx.recv().await
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl NodeBackend for MemoryBackend {
+        fn local_peer_id(&self) -> String {
+            self.peer_id.clone()
+        }
+
+        async fn send_to(&self, peer: &str, signed: &SignedGossip) -> Result<()> {
+            let hub = self.hub.lock();
+            let target = hub
+                .peers
+                .get(peer)
+                .ok_or_else(|| anyhow!("no simulated peer {}", peer))?;
+            let event = TransportEvent::Direct {
+                signed: signed.clone(),
+                from: self.peer_id.clone(),
+            };
+            target
+                .tx
+                .send(event)
+                .map_err(|_| anyhow!("simulated peer {} is gone", peer))
+        }
+
+        fn network_type(&self) -> NetworkType {
+            *self.network_type.lock()
+        }
+
+        fn update_network_type(&self, network_type: NetworkType) {
+            *self.network_type.lock() = network_type;
+        }
+
+        fn quic_advertise(&self) -> Option<SocketAddr> {
+            Some(self.addr())
+        }
+
+        fn quic_addrs(&self) -> Vec<SocketAddr> {
+            vec![self.addr()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::memory::MemoryNetwork;
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::crypto::{CryptoConfig, CryptoSuite};
+    use crate::types::GgsMessage;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn probe(engine: &ConsensusEngine, session: &str, sender: &str) -> SignedGossip {
+        let payload = GgsMessage::UpdateAck {
+            target: String::new(),
+            seq: 1,
+            sender: sender.into(),
+        };
+        engine.sign(session, payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_routes_by_session_and_address() {
+        let crypto = Arc::new(CryptoSuite::new(CryptoConfig::default()).unwrap());
+        let engine = ConsensusEngine::new(crypto, ConsensusConfig::default());
+        let network = MemoryNetwork::default();
+        let mut a = network.join("a");
+        let mut b = network.join("b");
+        let mut c = network.join("c");
+        // 后端以 trait 对象使用，节点逻辑不依赖具体实现
+        let backends: [&mut dyn GossipBackend; 3] = [&mut a, &mut b, &mut c];
+        assert!(backends.iter().all(|b| b.gossip_peer_count() == 2));
+        c.register_session("vision", "ggs-vision", 0.5).unwrap();
+
+        a.publish(&probe(&engine, "default", "a")).unwrap();
+        a.publish(&probe(&engine, "vision", "a")).unwrap();
+        for backend in [&mut b, &mut c] {
+            let Some(TransportEvent::Gossip { signed, source, .. }) = backend.next_event().await else {
+                panic!("expected gossip");
+            };
+            assert_eq!((signed.session.as_str(), source.as_deref()), ("default", Some("a")));
+        }
+        // 只有订阅了 vision 的 c 收到第二条
+        let Some(TransportEvent::Gossip { signed, .. }) = c.next_event().await else {
+            panic!("expected gossip");
+        };
+        assert_eq!(signed.session, "vision");
+        let idle = tokio::time::timeout(Duration::from_millis(20), b.next_event()).await;
+        assert!(idle.is_err());
+
+        b.send_direct(c.addr(), &probe(&engine, "default", "b")).await.unwrap();
+        let Some(TransportEvent::Direct { from, .. }) = c.next_event().await else {
+            panic!("expected direct message");
+        };
+        assert_eq!(from, "b");
+        let gone = c.addr();
+        drop(c);
+        assert!(b.send_direct(gone, &probe(&engine, "default", "b")).await.is_err());
+        assert_eq!(a.gossip_peer_count(), 1);
+
+        // 按身份直连
+        let node: &dyn NodeBackend = &b;
+        node.send_to("a", &probe(&engine, "default", "b")).await.unwrap();
+        let Some(TransportEvent::Direct { from, .. }) = a.next_event().await else {
+            panic!("expected direct message");
+        };
+        assert_eq!(from, "b");
+        assert!(node.send_to("c", &probe(&engine, "default", "b")).await.is_err());
+    }
+}
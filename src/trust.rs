@@ -0,0 +1,235 @@
+//! 统一信任评分
+//!
+//! 把共识权重（质押 + 声誉）、拓扑相似度、更新异常度与投递可靠性融合为每个节点一个
+//! 0..=1 的信任分，由邻居选择、更新合并权重与入站更新配额统一使用，
+//! 取代此前分散在各处的临时启发式规则：
+//! - 异常度：更新 L2 范数相对全网滑动均值 / 方差的 z 分数，一个标准差以内不计，超过上限记为完全异常，按 EWMA 平滑
+//! - 可靠性：心跳按期到达记为成功、每个漏掉的心跳周期记为一次失败，按 EWMA 平滑
+//! - 配额：每个窗口内接受的稀疏更新数随信任分线性增长，至少为 1
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 共识权重的上限（`StakeRecord::combined_weight` 的取值范围）
+const MAX_CONSENSUS_WEIGHT: f32 = 5.0;
+/// 尚未探测过的节点按中性相似度处理
+const UNKNOWN_SIMILARITY: f32 = 0.5;
+/// 范数标准差下限（相对均值），避免各节点范数几乎一致时把正常抖动判为异常
+const MIN_RELATIVE_SPREAD: f32 = 0.1;
+/// 单次心跳间隔最多计入的漏报次数
+const MAX_MISSED_HEARTBEATS: u32 = 5;
+
+#[derive(Clone, Debug)]
+pub struct TrustConfig {
+    pub consensus_weight: f32,
+    pub similarity_weight: f32,
+    pub anomaly_weight: f32,
+    pub reliability_weight: f32,
+    /// EWMA 平滑系数
+    pub ewma_alpha: f32,
+    /// z 分数在 1 以内视为正常，达到该值即视为完全异常
+    pub anomaly_z_limit: f32,
+    /// 合并权重下限，避免完全忽略信任分低的节点
+    pub min_merge_weight: f32,
+    /// 信任分为 0.5 的节点每个窗口可被接受的稀疏更新数
+    pub base_quota: u32,
+    pub quota_window: Duration,
+}
+
+impl Default for TrustConfig {
+    fn default() -> Self {
+        Self {
+            consensus_weight: 0.25,
+            similarity_weight: 0.35,
+            anomaly_weight: 0.2,
+            reliability_weight: 0.2,
+            ewma_alpha: 0.2,
+            anomaly_z_limit: 3.0,
+            min_merge_weight: 0.1,
+            base_quota: 12,
+            quota_window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// 信任分及其各项分量（均为 0..=1，异常度越高越差）
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustScore {
+    pub score: f32,
+    pub consensus: f32,
+    pub similarity: f32,
+    pub anomaly: f32,
+    pub reliability: f32,
+}
+
+struct PeerTrust {
+    anomaly: f32,
+    reliability: f32,
+    last_heartbeat: Option<Instant>,
+    window_start: Instant,
+    accepted: u32,
+}
+
+impl PeerTrust {
+    fn new() -> Self {
+        Self {
+            anomaly: 0.0,
+            reliability: 1.0,
+            last_heartbeat: None,
+            window_start: Instant::now(),
+            accepted: 0,
+        }
+    }
+}
+
+/// 全网更新范数的滑动均值与方差
+struct NormStats {
+    mean: f32,
+    var: f32,
+    samples: u64,
+}
+
+pub struct TrustTracker {
+    config: TrustConfig,
+    peers: RwLock<HashMap<String, PeerTrust>>,
+    norms: RwLock<NormStats>,
+}
+
+impl TrustTracker {
+    pub fn new(config: TrustConfig) -> Self {
+        Self {
+            config,
+            peers: RwLock::new(HashMap::new()),
+            norms: RwLock::new(NormStats {
+                mean: 0.0,
+                var: 0.0,
+                samples: 0,
+            }),
+        }
+    }
+
+    /// 记录一次更新的范数，更新该节点的异常度
+    pub fn observe_update_norm(&self, peer: &str, norm: f32) {
+        let alpha = self.config.ewma_alpha;
+        let deviation = {
+            let mut stats = self.norms.write();
+            // 先用更新前的分布评估，避免异常值稀释自身的 z 分数
+            let deviation = if stats.samples < 2 {
+                0.0
+            } else {
+                let spread = stats.var.sqrt().max(MIN_RELATIVE_SPREAD * stats.mean.abs()).max(1e-6);
+                let z = (norm - stats.mean).abs() / spread;
+                ((z - 1.0) / (self.config.anomaly_z_limit - 1.0).max(1e-3)).clamp(0.0, 1.0)
+            };
+            if stats.samples == 0 {
+                stats.mean = norm;
+            } else {
+                let diff = norm - stats.mean;
+                stats.mean += alpha * diff;
+                stats.var = (1.0 - alpha) * (stats.var + alpha * diff * diff);
+            }
+            stats.samples += 1;
+            deviation
+        };
+        let mut peers = self.peers.write();
+        let entry = peers.entry(peer.to_string()).or_insert_with(PeerTrust::new);
+        entry.anomaly += alpha * (deviation - entry.anomaly);
+    }
+
+    /// 记录心跳到达：间隔超过预期的每个周期计一次投递失败
+    pub fn observe_heartbeat(&self, peer: &str, expected_interval: Duration) {
+        let alpha = self.config.ewma_alpha;
+        let now = Instant::now();
+        let mut peers = self.peers.write();
+        let entry = peers.entry(peer.to_string()).or_insert_with(PeerTrust::new);
+        if let Some(last) = entry.last_heartbeat {
+            let periods = now.duration_since(last).as_secs_f32()
+                / expected_interval.as_secs_f32().max(1e-3);
+            let missed = (periods.round() as u32).saturating_sub(1).min(MAX_MISSED_HEARTBEATS);
+            for _ in 0..missed {
+                entry.reliability -= alpha * entry.reliability;
+            }
+            entry.reliability += alpha * (1.0 - entry.reliability);
+        }
+        entry.last_heartbeat = Some(now);
+    }
+
+    /// 融合各项分量。`consensus_weight` 为共识引擎的质押 + 声誉权重，
+    /// `similarity` 为当前拓扑相似度（未探测过时为 None）
+    pub fn score(&self, peer: &str, consensus_weight: f32, similarity: Option<f32>) -> TrustScore {
+        let (anomaly, reliability) = self
+            .peers
+            .read()
+            .get(peer)
+            .map_or((0.0, 1.0), |p| (p.anomaly, p.reliability));
+        let consensus = (consensus_weight / MAX_CONSENSUS_WEIGHT).clamp(0.0, 1.0);
+        let similarity = similarity.unwrap_or(UNKNOWN_SIMILARITY).clamp(0.0, 1.0);
+        let c = &self.config;
+        let total = c.consensus_weight + c.similarity_weight + c.anomaly_weight + c.reliability_weight;
+        let weighted = c.consensus_weight * consensus
+            + c.similarity_weight * similarity
+            + c.anomaly_weight * (1.0 - anomaly)
+            + c.reliability_weight * reliability;
+        TrustScore {
+            score: if total > 0.0 { weighted / total } else { 0.0 },
+            consensus,
+            similarity,
+            anomaly,
+            reliability,
+        }
+    }
+
+    /// 合并该节点更新时的权重
+    pub fn merge_weight(&self, trust: &TrustScore) -> f32 {
+        trust.score.clamp(self.config.min_merge_weight, 1.0)
+    }
+
+    /// 该节点每个窗口可被接受的稀疏更新数
+    pub fn quota(&self, trust: &TrustScore) -> u32 {
+        ((self.config.base_quota as f32 * (0.5 + trust.score)).round() as u32).max(1)
+    }
+
+    /// 在配额内则计入并返回 true
+    pub fn admit_update(&self, peer: &str, trust: &TrustScore) -> bool {
+        let quota = self.quota(trust);
+        let mut peers = self.peers.write();
+        let entry = peers.entry(peer.to_string()).or_insert_with(PeerTrust::new);
+        if entry.window_start.elapsed() >= self.config.quota_window {
+            entry.window_start = Instant::now();
+            entry.accepted = 0;
+        }
+        if entry.accepted < quota {
+            entry.accepted += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anomalous_updates_lower_trust_and_quota() {
+        let tracker = TrustTracker::new(TrustConfig::default());
+        for i in 0..20 {
+            tracker.observe_update_norm("steady", 1.0 + (i % 3) as f32 * 0.05);
+        }
+        for _ in 0..5 {
+            tracker.observe_update_norm("spiky", 50.0);
+        }
+        let steady = tracker.score("steady", 1.0, Some(0.8));
+        let spiky = tracker.score("spiky", 1.0, Some(0.8));
+        assert!(steady.anomaly < 0.05 && spiky.anomaly > 0.1, "{:?} vs {:?}", spiky, steady);
+        assert!(steady.score > spiky.score);
+        assert!(tracker.quota(&steady) >= tracker.quota(&spiky));
+
+        let quota = tracker.quota(&spiky);
+        let admitted = (0..quota + 3).filter(|_| tracker.admit_update("spiky", &spiky)).count();
+        assert_eq!(admitted as u32, quota);
+    }
+}
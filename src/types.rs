@@ -1,5 +1,19 @@
+use crate::beacon::BeaconRound;
+use crate::chunks::ModelChunkHashes;
+use crate::crypto::SignatureBundle;
+use crate::device::ComputeCapability;
+use crate::erasure::{ShareManifest, SnapshotShare};
+use crate::persistence::PersistedState;
+use crate::evaluation::ShardResult;
+use crate::hyperparams::HyperParamProposal;
+use crate::jobs::JobSpec;
+use crate::leases::TrainingLease;
+use crate::role::NodeRole;
+use crate::stake::{StakeEndorsement, StakeProof};
+use anyhow::{anyhow, Result};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 
 /// 地理位置点
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,22 +42,87 @@ impl GeoPoint {
     }
 }
 
+/// 节点自述的元数据，空字符串表示未填写
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerMeta {
+    #[serde(default)]
+    pub nickname: String,
+    #[serde(default)]
+    pub organization: String,
+    /// 区域标签（如 "eu-west"），与精确位置无关
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub contact: String,
+    /// 硬件概要，未填写时由设备能力生成
+    #[serde(default)]
+    pub hardware: String,
+}
+
+impl PeerMeta {
+    /// 各字段允许的最大字符数
+    const LIMITS: [(&'static str, usize); 5] = [
+        ("nickname", 32),
+        ("organization", 64),
+        ("region", 32),
+        ("contact", 128),
+        ("hardware", 128),
+    ];
+
+    fn fields(&self) -> [&str; 5] {
+        [
+            &self.nickname,
+            &self.organization,
+            &self.region,
+            &self.contact,
+            &self.hardware,
+        ]
+    }
+
+    /// 校验长度并拒绝控制字符，防止超大消息与日志注入
+    pub fn validate(&self) -> Result<()> {
+        for ((name, limit), value) in Self::LIMITS.iter().zip(self.fields()) {
+            if value.chars().count() > *limit {
+                return Err(anyhow!("{} exceeds {} characters", name, limit));
+            }
+            if value.chars().any(char::is_control) {
+                return Err(anyhow!("{} contains control characters", name));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TensorSnapshot {
     pub dim: usize,
     pub values: Vec<f32>,
     pub version: u64,
+    /// 分层同步时该切片在参数向量中的起始位置，完整快照为 0；
+    /// 只有 `GgsMessage::DenseSlice` 携带非 0 偏移
+    #[serde(default)]
+    pub offset: usize,
 }
 
 impl TensorSnapshot {
     pub fn new(values: Vec<f32>, version: u64) -> Self {
+        Self::slice(values, 0, version)
+    }
+
+    pub fn slice(values: Vec<f32>, offset: usize, version: u64) -> Self {
         Self {
             dim: values.len(),
             values,
             version,
+            offset,
         }
     }
 
+    /// 所有数值都是有限值（不含 NaN / Inf）
+    pub fn is_finite(&self) -> bool {
+        self.values.iter().all(|v| v.is_finite())
+    }
+
     pub fn hash(&self) -> String {
         use sha3::{Digest, Keccak256};
         let mut hasher = Keccak256::new();
@@ -61,6 +140,27 @@ pub struct SparseUpdate {
     pub indices: Vec<u32>,
     pub values: Vec<f32>,
     pub version: u64,
+    /// 发送者为自己的更新分配的递增序号，0 表示未编号
+    #[serde(default)]
+    pub seq: u64,
+}
+
+impl SparseUpdate {
+    /// 所有数值都是有限值（不含 NaN / Inf）
+    pub fn is_finite(&self) -> bool {
+        self.values.iter().all(|v| v.is_finite())
+    }
+}
+
+/// 将升序下标编码为差分形式
+pub fn compress_indices(sorted: &[usize]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(sorted.len());
+    let mut last = 0usize;
+    for &idx in sorted {
+        out.push((idx - last) as u32);
+        last = idx;
+    }
+    out
 }
 
 pub fn decompress_indices(compressed: &[u32]) -> Vec<usize> {
@@ -80,6 +180,38 @@ pub enum GgsMessage {
     Heartbeat {
         peer: String,
         model_hash: String,
+        /// 当前模型版本，重启后继续宣告以免被视为新节点
+        #[serde(default)]
+        model_version: u64,
+        #[serde(default)]
+        role: NodeRole,
+        /// 节点的 QUIC 直连地址（训练者据此直连聚合者，快照发送方据此分发纠删码分片）
+        #[serde(default)]
+        quic_addr: Option<SocketAddr>,
+        /// 算力概要，用于按能力分配分片与本地批量
+        #[serde(default)]
+        compute: Option<ComputeCapability>,
+        /// 本节点签名的链上余额证明，接收方核对链上状态或收到足够背书后据此更新质押
+        #[serde(default)]
+        stake_proof: Option<StakeProof>,
+        /// 可选的模型分块哈希（Merkle 根 + 第一层），用于定位分歧区间
+        #[serde(default)]
+        chunk_hashes: Option<ModelChunkHashes>,
+        /// 支持的直连编码名称，旧节点不携带（只支持 JSON）
+        #[serde(default)]
+        codecs: Vec<String>,
+        /// 发送者自己统计的连续心跳数，接收方结合本地观测核实
+        #[serde(default)]
+        streak: u32,
+        /// 全部可达的 QUIC 地址（如公网映射地址与局域网地址），旧节点只携带 `quic_addr`
+        #[serde(default)]
+        quic_addrs: Vec<SocketAddr>,
+        /// 可拨号的 libp2p 地址（外部地址与监听地址），接收方记入地址簿
+        #[serde(default)]
+        listen_addrs: Vec<String>,
+        /// 模型谱系根：模型加载（检查点或初始化）时的哈希，接收方据此在探测到达前估计相似度
+        #[serde(default)]
+        base_hash: Option<String>,
     },
     SparseUpdate {
         update: SparseUpdate,
@@ -93,5 +225,197 @@ pub enum GgsMessage {
         embedding: Vec<f32>,
         position: GeoPoint,
         sender: String,
+        #[serde(default)]
+        role: NodeRole,
+        /// 对静默邻居的主动重探测（经 QUIC 直连），接收方直连回一条探测
+        #[serde(default)]
+        reprobe: bool,
+    },
+    /// 单个 tick 内的多条消息合并后统一签名发送，减少签名与 gossip 开销
+    TickBundle {
+        messages: Vec<GgsMessage>,
+        sender: String,
+    },
+    /// 训练任务广播
+    JobAnnounce {
+        job: JobSpec,
+        sender: String,
+    },
+    /// 宣告本节点的签名密钥，无质押节点需附带工作量证明才能被接纳
+    KeyAnnounce {
+        eth_address: String,
+        sol_pubkey: String,
+        pow_nonce: u64,
+        sender: String,
+    },
+    /// 节点元数据（昵称、组织、区域、联系方式、硬件概要）
+    PeerMeta {
+        meta: PeerMeta,
+        sender: String,
     },
+    /// 同一 ETH 身份换用新 PeerId 后，请求把旧 PeerId 的账本记录迁移过来
+    IdentityMigration {
+        previous_peer: String,
+        sender: String,
+    },
+    /// 本节点在某评估纪元内分到的基准分片结果
+    EvalResult {
+        epoch: u64,
+        model_version: u64,
+        results: Vec<ShardResult>,
+        sender: String,
+    },
+    /// 主节点经 QUIC 直连复制给热备节点的持久化状态
+    StateReplica {
+        state: PersistedState,
+        sender: String,
+    },
+    /// 纠删码快照的分片清单，由快照发送方签名
+    ShareManifest {
+        manifest: ShareManifest,
+        sender: String,
+    },
+    /// 纠删码快照分片；QUIC 直连时 sender 为快照发送方，gossip 时为转发者，发送方见 `share.origin`
+    SnapshotShare {
+        share: SnapshotShare,
+        sender: String,
+    },
+    /// 惰性推拉：宣告本节点持有的密集快照，需要的节点回复 `IWant`
+    IHave {
+        hash: String,
+        version: u64,
+        /// 快照字节数
+        size: usize,
+        sender: String,
+    },
+    /// 请求 `target` 通过 QUIC 把快照直接发到 `quic_addr`
+    IWant {
+        target: String,
+        hash: String,
+        quic_addr: SocketAddr,
+        sender: String,
+    },
+    /// 请求 `target` 补发序号在 `[from_seq, to_seq)` 内的稀疏更新
+    BackfillRequest {
+        target: String,
+        from_seq: u64,
+        to_seq: u64,
+        sender: String,
+    },
+    /// 聚合者经 QUIC 确认收到 `target` 直连发来的序号为 `seq` 的稀疏更新
+    UpdateAck {
+        target: String,
+        seq: u64,
+        sender: String,
+    },
+    /// 超参数提议；同意的节点以自己的身份重新广播作为背书
+    HyperParamUpdate {
+        proposal: HyperParamProposal,
+        sender: String,
+    },
+    /// 发送者按嵌入聚类得到的同簇成员
+    ClusterView {
+        members: Vec<String>,
+        sender: String,
+    },
+    /// 租约发放者为本纪元的训练者签发的租约
+    LeaseGrant {
+        leases: Vec<TrainingLease>,
+        sender: String,
+    },
+    /// 发送者的信标份额：揭示上一份额提交的秘密、提交下一份额的秘密，并对其签名
+    BeaconShare {
+        epoch: u64,
+        checkpoint: String,
+        commit: String,
+        reveal: String,
+        signature: SignatureBundle,
+        sender: String,
+    },
+    /// 凑齐门限的一轮信标，由收集到份额的节点广播
+    BeaconRound {
+        round: BeaconRound,
+        sender: String,
+    },
+    /// 分层同步的模型切片。旧节点不认识该变体会直接丢弃，而不是像 `DenseSnapshot` 那样
+    /// 忽略 `offset`、把切片当作从 0 开始的快照覆盖错误的区间
+    DenseSlice {
+        snapshot: TensorSnapshot,
+        sender: String,
+    },
+    /// 发送者按链上状态核对过另一节点的余额证明，为其背书
+    StakeAttestation {
+        endorsement: StakeEndorsement,
+        sender: String,
+    },
+}
+
+impl GgsMessage {
+    /// 密集快照消息：覆盖整个模型（`model_dim` 维）时为 `DenseSnapshot`，分层切片为 `DenseSlice`
+    pub fn dense(snapshot: TensorSnapshot, model_dim: usize, sender: String) -> Self {
+        if snapshot.offset == 0 && snapshot.values.len() == model_dim {
+            GgsMessage::DenseSnapshot { snapshot, sender }
+        } else {
+            GgsMessage::DenseSlice { snapshot, sender }
+        }
+    }
+
+    /// 消息声明的发送者 peer id
+    pub fn sender(&self) -> &str {
+        match self {
+            GgsMessage::Heartbeat { peer, .. } => peer,
+            GgsMessage::SimilarityProbe { sender, .. }
+            | GgsMessage::SparseUpdate { sender, .. }
+            | GgsMessage::DenseSnapshot { sender, .. }
+            | GgsMessage::DenseSlice { sender, .. }
+            | GgsMessage::TickBundle { sender, .. }
+            | GgsMessage::JobAnnounce { sender, .. }
+            | GgsMessage::KeyAnnounce { sender, .. }
+            | GgsMessage::PeerMeta { sender, .. }
+            | GgsMessage::IdentityMigration { sender, .. }
+            | GgsMessage::EvalResult { sender, .. }
+            | GgsMessage::BackfillRequest { sender, .. }
+            | GgsMessage::UpdateAck { sender, .. }
+            | GgsMessage::StateReplica { sender, .. }
+            | GgsMessage::ShareManifest { sender, .. }
+            | GgsMessage::SnapshotShare { sender, .. }
+            | GgsMessage::IHave { sender, .. }
+            | GgsMessage::IWant { sender, .. }
+            | GgsMessage::HyperParamUpdate { sender, .. }
+            | GgsMessage::ClusterView { sender, .. }
+            | GgsMessage::LeaseGrant { sender, .. }
+            | GgsMessage::BeaconShare { sender, .. }
+            | GgsMessage::BeaconRound { sender, .. }
+            | GgsMessage::StakeAttestation { sender, .. } => sender,
+        }
+    }
+
+    /// 模型数据类消息：QUIC 实时通道只发给拓扑邻居，其余控制消息才广播到所有连接
+    pub fn is_realtime(&self) -> bool {
+        matches!(
+            self,
+            GgsMessage::SparseUpdate { .. }
+                | GgsMessage::DenseSnapshot { .. }
+                | GgsMessage::DenseSlice { .. }
+        )
+    }
+
+    /// 体积小、重放无害的控制消息（接收方按消息 id 去重并拒绝过旧的时间戳）：
+    /// 重连后可经 QUIC 0-RTT 在握手完成前发出
+    pub fn is_replay_safe(&self) -> bool {
+        matches!(
+            self,
+            GgsMessage::Heartbeat { .. }
+                | GgsMessage::SimilarityProbe { .. }
+                | GgsMessage::UpdateAck { .. }
+        )
+    }
+
+    /// 体积小、丢了等下一条即可的消息：QUIC 直连时用不可靠数据报发送，省去每条消息开一个流
+    pub fn is_loss_tolerant(&self) -> bool {
+        matches!(
+            self,
+            GgsMessage::Heartbeat { .. } | GgsMessage::SimilarityProbe { .. }
+        )
+    }
 }
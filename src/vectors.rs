@@ -0,0 +1,492 @@
+//! 互操作测试向量
+//!
+//! `ggs vectors generate <dir>` 用固定密钥与固定时间戳为每种消息类型各签一条 `SignedGossip`，
+//! 按每种直连编码写成文件，并在 `vectors.json` 中记录密钥、签名覆盖的字节、消息 id 与各文件的哈希。
+//! ETH（RFC 6979）与 Ed25519 签名都是确定性的，同一版本重复生成得到相同的语料。
+//! 其他语言的实现据此核对自己的 JSON 序列化、签名与编解码；`ggs vectors verify <dir>`
+//! 用本 crate 检查一份语料（包括其他实现生成的语料）。
+
+use crate::beacon::{commitment, genesis, share_bytes, BeaconRound, BeaconShare};
+use crate::chunks::ModelChunkHashes;
+use crate::codec::{self, WireCodec};
+use crate::consensus::{sign_at, signature_valid, SignedGossip};
+use crate::crypto::{CryptoConfig, CryptoSuite};
+use crate::device::ComputeCapability;
+use crate::erasure;
+use crate::evaluation::ShardResult;
+use crate::hyperparams::{HyperParamProposal, HyperParams};
+use crate::jobs::JobSpec;
+use crate::leases::TrainingLease;
+use crate::persistence::PersistedState;
+use crate::role::NodeRole;
+use crate::stake::StakeEndorsement;
+use crate::types::{compress_indices, GeoPoint, GgsMessage, PeerMeta, SparseUpdate, TensorSnapshot};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::net::SocketAddr;
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "vectors.json";
+/// 语料格式版本，字段含义变化时递增
+const FORMAT: u32 = 1;
+const SESSION: &str = "vectors";
+const SENT_AT_MS: u64 = 1_700_000_000_000;
+const STAKING_SCORE: f32 = 0.5;
+const ETH_SEED: [u8; 32] = [0x01; 32];
+const SOL_SEED: [u8; 32] = [0x02; 32];
+/// Ed25519 私钥 `[0x03; 32]` 对应的 PeerId，作为所有向量的发送者
+const SENDER: &str = "12D3KooWRndVhVZPCiQwHBBBdg769GyrPUW13zxwqQyf9r3ANaba";
+/// Ed25519 私钥 `[0x04; 32]` 对应的 PeerId，作为定向消息的接收方
+const TARGET: &str = "12D3KooWPT98FXMfDQYavZm66EeVjTqP9Nnehn1gyaydqV8L8BQw";
+/// 解码语料文件的大小上限
+const MAX_VECTOR_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Corpus {
+    pub format: u32,
+    pub keys: VectorKeys,
+    pub vectors: Vec<Vector>,
+}
+
+/// 签名所用的密钥与由其导出的身份
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorKeys {
+    pub eth_hex_seed: String,
+    pub sol_bs58_seed: String,
+    pub eth_address: String,
+    pub sol_pubkey: String,
+    pub peer_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vector {
+    /// 载荷的消息类型（JSON 中外层的变体名）
+    pub variant: String,
+    pub session: String,
+    pub sent_at_ms: u64,
+    /// 签名覆盖的字节（十六进制）
+    pub signing_bytes: String,
+    /// 签名覆盖字节的 Keccak-256，ETH 签名即对它签名
+    pub signing_hash: String,
+    /// 接收方去重使用的消息 id（十六进制）
+    pub message_id: String,
+    pub encodings: Vec<Encoding>,
+}
+
+/// 同一条消息按一种直连编码写成的文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Encoding {
+    pub codec: String,
+    /// 相对语料目录的文件名
+    pub file: String,
+    /// 文件内容的 Keccak-256
+    pub hash: String,
+}
+
+fn keccak_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(Keccak256::digest(bytes)))
+}
+
+fn variant(payload: &GgsMessage) -> Result<String> {
+    serde_json::to_value(payload)?
+        .as_object()
+        .and_then(|object| object.keys().next().cloned())
+        .ok_or_else(|| anyhow!("载荷不是带变体名的 JSON 对象"))
+}
+
+fn suite() -> Result<CryptoSuite> {
+    Ok(CryptoSuite::new(CryptoConfig {
+        eth_hex_seed: Some(hex::encode(ETH_SEED)),
+        sol_bs58_seed: Some(bs58::encode(SOL_SEED).into_string()),
+        keystore: None,
+    })?)
+}
+
+/// 每种消息类型一条示例，可选字段尽量填上以覆盖更多字段
+fn samples(crypto: &CryptoSuite) -> Result<Vec<GgsMessage>> {
+    let sender = || SENDER.to_string();
+    let params = vec![0.5, -0.25, 1.0, 0.0, -1.5, 2.0, 0.125, -0.75];
+    let snapshot = TensorSnapshot::new(params.clone(), 3);
+    let hash = snapshot.hash();
+    let quic_addr: SocketAddr = "127.0.0.1:9234".parse()?;
+    let (manifest, shares) = erasure::encode(&snapshot, SENDER, 2, 1)?;
+    let checkpoint = genesis(SESSION);
+    let commit = commitment("beacon-secret");
+    let beacon_signature = crypto.sign_bytes(&share_bytes(SESSION, 5, &checkpoint, &commit, ""))?;
+    let ack = GgsMessage::UpdateAck {
+        target: TARGET.into(),
+        seq: 1,
+        sender: sender(),
+    };
+    let have = GgsMessage::IHave {
+        hash: hash.clone(),
+        version: snapshot.version,
+        size: params.len() * std::mem::size_of::<f32>(),
+        sender: sender(),
+    };
+    Ok(vec![
+        GgsMessage::Heartbeat {
+            peer: sender(),
+            model_hash: hash.clone(),
+            model_version: snapshot.version,
+            role: NodeRole::Trainer,
+            quic_addr: Some(quic_addr),
+            compute: Some(ComputeCapability {
+                cpu_cores: 8,
+                has_gpu: false,
+                tflops: 0.5,
+                memory_mb: 16_384,
+            }),
+            stake_proof: None,
+            chunk_hashes: Some(ModelChunkHashes::compute(&params, 2)),
+            codecs: WireCodec::ALL.iter().map(|c| c.name().to_string()).collect(),
+            streak: 12,
+            quic_addrs: vec![quic_addr, "[::1]:9234".parse()?],
+            listen_addrs: vec!["/ip4/127.0.0.1/tcp/9000".into()],
+            base_hash: Some(hash.clone()),
+        },
+        GgsMessage::SparseUpdate {
+            update: SparseUpdate {
+                indices: compress_indices(&[1, 4, 6]),
+                values: vec![0.25, -0.5, 0.125],
+                version: snapshot.version,
+                seq: 1,
+            },
+            sender: sender(),
+        },
+        GgsMessage::DenseSnapshot {
+            snapshot: snapshot.clone(),
+            sender: sender(),
+        },
+        GgsMessage::DenseSlice {
+            snapshot: TensorSnapshot::slice(params[2..6].to_vec(), 2, snapshot.version),
+            sender: sender(),
+        },
+        GgsMessage::SimilarityProbe {
+            embedding: vec![0.5, -0.25, 1.0],
+            position: GeoPoint {
+                lat: 31.25,
+                lon: 121.5,
+            },
+            sender: sender(),
+            role: NodeRole::Trainer,
+            reprobe: true,
+        },
+        GgsMessage::TickBundle {
+            messages: vec![ack.clone(), have.clone()],
+            sender: sender(),
+        },
+        GgsMessage::JobAnnounce {
+            job: JobSpec {
+                job_id: "vision".into(),
+                architecture: "mlp-8".into(),
+                model_dim: params.len(),
+                model_hash: Some(hash.clone()),
+                hyperparams: Default::default(),
+                dataset: Default::default(),
+                topic: "ggs-vision".into(),
+                reward: Default::default(),
+                requirements: Default::default(),
+                lease_issuers: vec![sender()],
+                owner: Some(sender()),
+            },
+            sender: sender(),
+        },
+        GgsMessage::KeyAnnounce {
+            eth_address: crypto.eth_address(),
+            sol_pubkey: crypto.sol_address(),
+            pow_nonce: 42,
+            sender: sender(),
+        },
+        GgsMessage::PeerMeta {
+            meta: PeerMeta {
+                nickname: "vectors".into(),
+                region: "eu-west".into(),
+                ..PeerMeta::default()
+            },
+            sender: sender(),
+        },
+        GgsMessage::IdentityMigration {
+            previous_peer: TARGET.into(),
+            sender: sender(),
+        },
+        GgsMessage::EvalResult {
+            epoch: 5,
+            model_version: snapshot.version,
+            results: vec![ShardResult {
+                shard: 0,
+                samples: 128,
+                loss: 0.75,
+            }],
+            sender: sender(),
+        },
+        GgsMessage::StateReplica {
+            state: PersistedState {
+                saved_at_secs: SENT_AT_MS / 1000,
+                ..PersistedState::default()
+            },
+            sender: sender(),
+        },
+        GgsMessage::ShareManifest {
+            manifest,
+            sender: sender(),
+        },
+        GgsMessage::SnapshotShare {
+            share: shares.into_iter().next().ok_or_else(|| anyhow!("纠删码没有产生分片"))?,
+            sender: sender(),
+        },
+        have,
+        GgsMessage::IWant {
+            target: TARGET.into(),
+            hash,
+            quic_addr,
+            sender: sender(),
+        },
+        GgsMessage::BackfillRequest {
+            target: TARGET.into(),
+            from_seq: 1,
+            to_seq: 4,
+            sender: sender(),
+        },
+        ack,
+        GgsMessage::HyperParamUpdate {
+            proposal: HyperParamProposal {
+                proposal_id: 7,
+                params: HyperParams::default(),
+                apply_epoch: 6,
+                schedule: None,
+            },
+            sender: sender(),
+        },
+        GgsMessage::ClusterView {
+            members: vec![sender(), TARGET.into()],
+            sender: sender(),
+        },
+        GgsMessage::LeaseGrant {
+            leases: vec![TrainingLease {
+                session: SESSION.into(),
+                trainer: TARGET.into(),
+                epoch: 5,
+                shards: vec![0, 3],
+                expires_at_ms: SENT_AT_MS + 60_000,
+            }],
+            sender: sender(),
+        },
+        GgsMessage::BeaconShare {
+            epoch: 5,
+            checkpoint: checkpoint.clone(),
+            commit: commit.clone(),
+            reveal: String::new(),
+            signature: beacon_signature.clone(),
+            sender: sender(),
+        },
+        GgsMessage::BeaconRound {
+            round: BeaconRound {
+                epoch: 5,
+                checkpoint,
+                shares: vec![BeaconShare {
+                    signer: sender(),
+                    commit,
+                    reveal: String::new(),
+                    signature: beacon_signature,
+                }],
+            },
+            sender: sender(),
+        },
+        GgsMessage::StakeAttestation {
+            endorsement: StakeEndorsement {
+                peer: TARGET.into(),
+                block_number: 19_000_000,
+                block_hash: format!("0x{}", "ab".repeat(32)),
+            },
+            sender: sender(),
+        },
+    ])
+}
+
+/// 生成语料写入 `dir`（已有同名文件时覆盖）
+pub fn generate(dir: &Path) -> Result<Corpus> {
+    std::fs::create_dir_all(dir)?;
+    let crypto = suite()?;
+    let mut vectors = Vec::new();
+    for payload in samples(&crypto)? {
+        let variant = variant(&payload)?;
+        let signed = sign_at(&crypto, SESSION, SENT_AT_MS, payload, STAKING_SCORE)?;
+        let signing_bytes = signed.signing_bytes()?;
+        let mut encodings = Vec::new();
+        for codec in WireCodec::ALL {
+            let bytes = codec::encode(codec, &signed)?;
+            // 编码名带模式版本（binary/1），文件名里的 / 换成 -
+            let file = format!("{}.{}", variant, codec.name().replace('/', "-"));
+            std::fs::write(dir.join(&file), &bytes)?;
+            encodings.push(Encoding {
+                codec: codec.name().to_string(),
+                file,
+                hash: keccak_hex(&bytes),
+            });
+        }
+        vectors.push(Vector {
+            variant,
+            session: signed.session.clone(),
+            sent_at_ms: signed.sent_at_ms,
+            signing_hash: keccak_hex(&signing_bytes),
+            signing_bytes: hex::encode(signing_bytes),
+            message_id: hex::encode(signed.message_id()),
+            encodings,
+        });
+    }
+    let corpus = Corpus {
+        format: FORMAT,
+        keys: VectorKeys {
+            eth_hex_seed: hex::encode(ETH_SEED),
+            sol_bs58_seed: bs58::encode(SOL_SEED).into_string(),
+            eth_address: crypto.eth_address(),
+            sol_pubkey: crypto.sol_address(),
+            peer_id: SENDER.to_string(),
+        },
+        vectors,
+    };
+    std::fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&corpus)?)?;
+    Ok(corpus)
+}
+
+/// 检查 `dir` 中的语料，返回检查过的文件数；任一文件不符时返回列出全部问题的错误
+pub fn verify(dir: &Path) -> Result<usize> {
+    let corpus: Corpus = serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE))?)?;
+    if corpus.format != FORMAT {
+        return Err(anyhow!("不支持的语料格式版本 {}（本实现为 {}）", corpus.format, FORMAT));
+    }
+    let mut problems = Vec::new();
+    let crypto = CryptoSuite::new(CryptoConfig {
+        eth_hex_seed: Some(corpus.keys.eth_hex_seed.clone()),
+        sol_bs58_seed: Some(corpus.keys.sol_bs58_seed.clone()),
+        keystore: None,
+    })?;
+    if !crypto.eth_address().eq_ignore_ascii_case(&corpus.keys.eth_address) {
+        problems.push(format!("ETH 种子导出的地址是 {}", crypto.eth_address()));
+    }
+    if crypto.sol_address() != corpus.keys.sol_pubkey {
+        problems.push(format!("SOL 种子导出的公钥是 {}", crypto.sol_address()));
+    }
+    let mut checked = 0;
+    for vector in &corpus.vectors {
+        for encoding in &vector.encodings {
+            checked += 1;
+            let label = format!("{} ({})", vector.variant, encoding.file);
+            if let Err(e) = verify_file(dir, &corpus.keys, vector, encoding) {
+                problems.push(format!("{}: {}", label, e));
+            }
+        }
+    }
+    if problems.is_empty() {
+        Ok(checked)
+    } else {
+        Err(anyhow!("{} 处不符:\n  {}", problems.len(), problems.join("\n  ")))
+    }
+}
+
+fn verify_file(dir: &Path, keys: &VectorKeys, vector: &Vector, encoding: &Encoding) -> Result<()> {
+    let bytes = std::fs::read(dir.join(&encoding.file))?;
+    if keccak_hex(&bytes) != encoding.hash {
+        return Err(anyhow!("文件哈希不符"));
+    }
+    let codec: WireCodec = encoding.codec.parse()?;
+    let signed: SignedGossip = codec::decode(&bytes, MAX_VECTOR_BYTES)?;
+    if variant(&signed.payload)? != vector.variant {
+        return Err(anyhow!("消息类型不符"));
+    }
+    if signed.session != vector.session || signed.sent_at_ms != vector.sent_at_ms {
+        return Err(anyhow!("会话或发送时间不符"));
+    }
+    let signing_bytes = signed.signing_bytes()?;
+    if hex::encode(&signing_bytes) != vector.signing_bytes {
+        return Err(anyhow!("签名覆盖的字节不符（JSON 序列化不一致）"));
+    }
+    if keccak_hex(&signing_bytes) != vector.signing_hash {
+        return Err(anyhow!("签名字节的哈希不符"));
+    }
+    if !signature_valid(&signed) {
+        return Err(anyhow!("签名无效"));
+    }
+    if !signed.signature.eth.address.eq_ignore_ascii_case(&keys.eth_address)
+        || signed.signature.sol.pubkey != keys.sol_pubkey
+    {
+        return Err(anyhow!("签名密钥与语料声明的密钥不符"));
+    }
+    if hex::encode(signed.message_id()) != vector.message_id {
+        return Err(anyhow!("消息 id 不符"));
+    }
+    // JSON 编码的文件重新编码后必须逐字节一致；压缩与二进制编码只要求解码结果一致
+    if codec == WireCodec::Json && codec::encode(codec, &signed)? != bytes {
+        return Err(anyhow!("重新编码后的 JSON 与文件不一致"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 消息类型在 `GgsMessage` 中的序号。穷举匹配：新增类型时这里无法编译，
+    /// 提醒同时更新 `VARIANTS` 并在 `samples` 中补上示例
+    fn variant_index(payload: &GgsMessage) -> usize {
+        match payload {
+            GgsMessage::Heartbeat { .. } => 0,
+            GgsMessage::SparseUpdate { .. } => 1,
+            GgsMessage::DenseSnapshot { .. } => 2,
+            GgsMessage::SimilarityProbe { .. } => 3,
+            GgsMessage::TickBundle { .. } => 4,
+            GgsMessage::JobAnnounce { .. } => 5,
+            GgsMessage::KeyAnnounce { .. } => 6,
+            GgsMessage::PeerMeta { .. } => 7,
+            GgsMessage::IdentityMigration { .. } => 8,
+            GgsMessage::EvalResult { .. } => 9,
+            GgsMessage::StateReplica { .. } => 10,
+            GgsMessage::ShareManifest { .. } => 11,
+            GgsMessage::SnapshotShare { .. } => 12,
+            GgsMessage::IHave { .. } => 13,
+            GgsMessage::IWant { .. } => 14,
+            GgsMessage::BackfillRequest { .. } => 15,
+            GgsMessage::UpdateAck { .. } => 16,
+            GgsMessage::HyperParamUpdate { .. } => 17,
+            GgsMessage::ClusterView { .. } => 18,
+            GgsMessage::LeaseGrant { .. } => 19,
+            GgsMessage::BeaconShare { .. } => 20,
+            GgsMessage::BeaconRound { .. } => 21,
+            GgsMessage::DenseSlice { .. } => 22,
+            GgsMessage::StakeAttestation { .. } => 23,
+        }
+    }
+
+    const VARIANTS: usize = 24;
+
+    #[test]
+    fn test_generated_corpus_verifies_and_is_reproducible() {
+        // 每种消息类型恰好一条示例
+        let samples = samples(&suite().unwrap()).unwrap();
+        let mut covered: Vec<usize> = samples.iter().map(variant_index).collect();
+        covered.sort_unstable();
+        assert_eq!(covered, (0..VARIANTS).collect::<Vec<_>>());
+
+        let dir = std::env::temp_dir().join(format!("ggs-vectors-{}", rand::random::<u64>()));
+        let corpus = generate(&dir).unwrap();
+        // 每条按每种编码各一个文件
+        let variants: std::collections::HashSet<_> = corpus.vectors.iter().map(|v| &v.variant).collect();
+        assert_eq!(variants.len(), samples.len());
+        assert_eq!(corpus.vectors.len(), samples.len());
+        assert_eq!(verify(&dir).unwrap(), samples.len() * WireCodec::ALL.len());
+        assert_eq!(generate(&dir).unwrap(), corpus);
+
+        // 改动载荷后签名与哈希都对不上
+        let heartbeat = corpus.vectors.iter().position(|v| v.variant == "Heartbeat");
+        let file = dir.join(&corpus.vectors[heartbeat.unwrap()].encodings[0].file);
+        let original = std::fs::read_to_string(&file).unwrap();
+        let tampered = original.replace("\"streak\":12", "\"streak\":13");
+        assert_ne!(tampered, original);
+        std::fs::write(&file, tampered).unwrap();
+        let err = verify(&dir).unwrap_err().to_string();
+        assert!(err.contains("Heartbeat"), "{}", err);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! 子系统看门狗
+//!
+//! 内部循环（swarm 轮询、QUIC accept、训练任务）每轮调用 [`Beat::beat`] 报告进展。
+//! 看门狗定期检查，任一循环超过期限没有进展时打印诊断信息、调用该子系统的重启回调，
+//! 并计入统计。Supervisor 只能发现返回错误或 panic 的子系统，看门狗补上卡住不动
+//! （死锁、永不完成的 await）的情况；重启后仍无进展的，每过一个期限再重试一次。
+
+use crate::stats::TrainingStatsManager;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct WatchdogConfig {
+    /// 循环超过该时长没有进展视为停滞，0 表示不启用看门狗
+    pub deadline: Duration,
+    pub check_interval: Duration,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            // 需大于低电量时放大后的最长任务间隔
+            deadline: Duration::from_secs(180),
+            check_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.deadline.is_zero()
+    }
+}
+
+/// 循环的进展记录，可在线程间克隆共享
+#[derive(Clone)]
+pub struct Beat {
+    created: Instant,
+    /// 最后一次进展，距 created 的毫秒数
+    last_ms: Arc<AtomicU64>,
+    count: Arc<AtomicU64>,
+}
+
+impl Default for Beat {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            last_ms: Arc::new(AtomicU64::new(0)),
+            count: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl Beat {
+    pub fn beat(&self) {
+        self.last_ms
+            .store(self.created.elapsed().as_millis() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn idle(&self) -> Duration {
+        let last = Duration::from_millis(self.last_ms.load(Ordering::Relaxed));
+        self.created.elapsed().saturating_sub(last)
+    }
+}
+
+/// 停滞时调用的重启回调
+pub type RestartHook = Box<dyn Fn() + Send + Sync>;
+
+struct Watched {
+    name: String,
+    beat: Beat,
+    restart: RestartHook,
+    /// 最近一次因停滞触发重启的时间
+    stalled_at: Option<Instant>,
+}
+
+pub struct Watchdog {
+    config: WatchdogConfig,
+    loops: Mutex<Vec<Watched>>,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            loops: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 监视一个循环；从此刻起计时
+    pub fn watch(&self, name: &str, beat: Beat, restart: RestartHook) {
+        beat.beat();
+        self.loops.lock().push(Watched {
+            name: name.to_string(),
+            beat,
+            restart,
+            stalled_at: None,
+        });
+    }
+
+    /// 检查一次，返回本次触发重启的子系统
+    pub fn check(&self) -> Vec<String> {
+        let deadline = self.config.deadline;
+        let mut restarted = Vec::new();
+        for watched in self.loops.lock().iter_mut() {
+            let idle = watched.beat.idle();
+            if idle <= deadline {
+                if watched.stalled_at.take().is_some() {
+                    println!("[看门狗] 子系统 {} 已恢复", watched.name);
+                }
+                continue;
+            }
+            if watched.stalled_at.is_some_and(|at| at.elapsed() <= deadline) {
+                continue;
+            }
+            eprintln!(
+                "[看门狗] 子系统 {} 已 {:?} 无进展（期限 {:?}，累计 {} 轮，{}），尝试重启",
+                watched.name,
+                idle,
+                deadline,
+                watched.beat.count.load(Ordering::Relaxed),
+                if watched.stalled_at.is_some() {
+                    "上次重启后仍未恢复"
+                } else {
+                    "首次停滞"
+                }
+            );
+            (watched.restart)();
+            watched.stalled_at = Some(Instant::now());
+            restarted.push(watched.name.clone());
+        }
+        restarted
+    }
+
+    /// 按检查间隔持续运行
+    pub async fn run(self: Arc<Self>, stats: Arc<TrainingStatsManager>) {
+        let mut ticker = tokio::time::interval(self.config.check_interval);
+        loop {
+            ticker.tick().await;
+            for name in self.check() {
+                stats.record_watchdog_restart(&name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    #[test]
+    fn test_stalled_loop_is_restarted_until_it_recovers() {
+        let watchdog = Watchdog::new(WatchdogConfig {
+            deadline: Duration::from_millis(30),
+            check_interval: Duration::from_millis(10),
+        });
+        let restarts = Arc::new(AtomicU32::new(0));
+        let counter = restarts.clone();
+        let (stuck, live) = (Beat::default(), Beat::default());
+        watchdog.watch(
+            "stuck",
+            stuck.clone(),
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        );
+        watchdog.watch("live", live.clone(), Box::new(|| panic!("live loop restarted")));
+
+        std::thread::sleep(Duration::from_millis(40));
+        live.beat();
+        assert_eq!(watchdog.check(), vec!["stuck".to_string()]);
+        // 一个期限内不重复重启
+        assert!(watchdog.check().is_empty());
+        std::thread::sleep(Duration::from_millis(40));
+        live.beat();
+        assert_eq!(watchdog.check(), vec!["stuck".to_string()]);
+        stuck.beat();
+        assert!(watchdog.check().is_empty());
+        assert_eq!(restarts.load(Ordering::SeqCst), 2);
+    }
+}
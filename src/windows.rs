@@ -0,0 +1,157 @@
+//! 训练时间窗口（静默时段）
+//!
+//! 类 cron 的时间窗口配置：节点只在窗口内执行本地训练和密集快照交换，
+//! 窗口外仍然参与 gossip 中继、心跳与共识，但不消耗算力和大流量。
+//! 适用于只在夜间空闲时贡献算力的家庭宽带节点。
+//!
+//! 窗口格式：`<星期> <HH:MM>-<HH:MM>`，星期为 `*`、`mon`、`mon-fri` 或 `sat,sun`，
+//! 结束时间早于开始时间表示跨越午夜（归属开始那天），例如 `mon-fri 22:00-06:00`。
+
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWindow {
+    /// 按星期（0 = 周日）标记窗口开始的日子
+    days: [bool; 7],
+    start_min: u32,
+    end_min: u32,
+}
+
+impl TimeWindow {
+    /// `day`: 0 = 周日；`minute`: 当天第几分钟
+    fn contains(&self, day: usize, minute: u32) -> bool {
+        if self.start_min <= self.end_min {
+            self.days[day] && (self.start_min..self.end_min).contains(&minute)
+        } else {
+            // 跨午夜：当天晚段，或前一天开始的窗口的凌晨段
+            (self.days[day] && minute >= self.start_min)
+                || (self.days[(day + 6) % 7] && minute < self.end_min)
+        }
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (days, times) = s
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("expected '<days> <HH:MM>-<HH:MM>': {:?}", s))?;
+        let (start, end) = times
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| anyhow!("invalid time range {:?}", times))?;
+        Ok(Self {
+            days: parse_days(days)?,
+            start_min: parse_time(start)?,
+            end_min: parse_time(end)?,
+        })
+    }
+}
+
+fn parse_day(name: &str) -> Result<usize> {
+    DAY_NAMES
+        .iter()
+        .position(|d| d.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("unknown day {:?}", name))
+}
+
+fn parse_days(spec: &str) -> Result<[bool; 7]> {
+    let mut days = [false; 7];
+    if spec == "*" {
+        return Ok([true; 7]);
+    }
+    for part in spec.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse_day(from)?, parse_day(to)?);
+                let mut day = from;
+                loop {
+                    days[day] = true;
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_day(part)?] = true,
+        }
+    }
+    Ok(days)
+}
+
+fn parse_time(spec: &str) -> Result<u32> {
+    let (h, m) = spec
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid time {:?}", spec))?;
+    let (h, m): (u32, u32) = (h.parse()?, m.parse()?);
+    if h > 24 || m > 59 || h * 60 + m > MINUTES_PER_DAY {
+        return Err(anyhow!("invalid time {:?}", spec));
+    }
+    Ok(h * 60 + m)
+}
+
+/// 训练窗口集合，为空表示全天可训练
+#[derive(Debug, Clone, Default)]
+pub struct TrainingSchedule {
+    pub windows: Vec<TimeWindow>,
+    /// 本地时区相对 UTC 的偏移（分钟）
+    pub utc_offset_minutes: i32,
+}
+
+impl TrainingSchedule {
+    pub fn is_restricted(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    /// 给定 Unix 秒时是否处于训练窗口内
+    pub fn is_active_at(&self, unix_secs: u64) -> bool {
+        if self.windows.is_empty() {
+            return true;
+        }
+        let local_min = (unix_secs / 60) as i64 + self.utc_offset_minutes as i64;
+        let days = local_min.div_euclid(MINUTES_PER_DAY as i64);
+        let minute = local_min.rem_euclid(MINUTES_PER_DAY as i64) as u32;
+        // 1970-01-01 是周四
+        let day = (days + 4).rem_euclid(7) as usize;
+        self.windows.iter().any(|w| w.contains(day, minute))
+    }
+}
+
+/// 解析 `+08:00` / `-05:30` 形式的时区偏移
+pub fn parse_utc_offset(spec: &str) -> Result<i32> {
+    let (sign, rest) = match spec.as_bytes().first() {
+        Some(b'+') => (1, &spec[1..]),
+        Some(b'-') => (-1, &spec[1..]),
+        _ => (1, spec),
+    };
+    let minutes = parse_time(rest)? as i32;
+    Ok(sign * minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overnight_weekday_window() {
+        let schedule = TrainingSchedule {
+            windows: vec!["mon-fri 22:00-06:00".parse().unwrap()],
+            utc_offset_minutes: 60,
+        };
+        // 2024-01-05 (周五) 21:30 UTC = 22:30 本地
+        let fri_night = 1_704_490_200;
+        assert!(schedule.is_active_at(fri_night));
+        // 周六 05:30 本地仍属于周五开始的窗口
+        assert!(schedule.is_active_at(fri_night + 7 * 3600));
+        // 周六 22:30 本地不在窗口内
+        assert!(!schedule.is_active_at(fri_night + 24 * 3600));
+        assert!(TrainingSchedule::default().is_active_at(fri_night));
+        assert!("mon 25:00-26:00".parse::<TimeWindow>().is_err());
+    }
+}
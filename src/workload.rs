@@ -0,0 +1,77 @@
+//! 按算力分配工作量
+//!
+//! 节点在心跳中宣告算力概要，同一会话内的节点据此独立、确定地算出相同的分配结果：
+//! - 数据集分片按算力比例切分，算力强的节点分到更大的分片区间
+//! - 每个训练周期的本地步数（批量）随相对算力增长
+//! - 聚合者按相对算力估计每个训练者在一个窗口内应贡献的更新次数
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// 每个训练周期最多执行的本地步数
+pub const MAX_LOCAL_STEPS: usize = 4;
+
+/// 本节点算力相对会话中位数的倍数
+pub fn relative_compute(own: f32, others: &[(String, f32)]) -> f32 {
+    let mut all: Vec<f32> = others.iter().map(|(_, t)| *t).chain([own]).collect();
+    all.sort_by(|a, b| a.total_cmp(b));
+    let median = all[all.len() / 2];
+    if median <= 0.0 {
+        return 1.0;
+    }
+    own / median
+}
+
+/// 每个训练周期的本地步数
+pub fn local_steps(relative: f32) -> usize {
+    (relative.round() as usize).clamp(1, MAX_LOCAL_STEPS)
+}
+
+/// 一个窗口内期望的更新次数：基准次数按相对算力缩放
+pub fn expected_updates(relative: f32, base: f32) -> f32 {
+    base * relative.clamp(0.25, MAX_LOCAL_STEPS as f32)
+}
+
+/// 按算力比例把 `[0, total)` 切成连续分片区间，节点按 id 排序保证各节点结果一致
+pub fn assign_shards(total: u32, nodes: &[(String, f32)]) -> HashMap<String, Range<u32>> {
+    let mut sorted: Vec<&(String, f32)> = nodes.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    let sum: f32 = sorted.iter().map(|(_, t)| t.max(0.0)).sum();
+    let mut out = HashMap::new();
+    let (mut cumulative, mut start) = (0.0f32, 0u32);
+    for (i, (peer, tflops)) in sorted.iter().enumerate() {
+        cumulative += tflops.max(0.0);
+        let end = if i + 1 == sorted.len() {
+            total
+        } else if sum > 0.0 {
+            ((total as f32 * cumulative / sum).round() as u32).clamp(start, total)
+        } else {
+            total * (i as u32 + 1) / sorted.len() as u32
+        };
+        out.insert(peer.clone(), start..end);
+        start = end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stronger_nodes_get_more_work() {
+        let nodes = vec![
+            ("b".to_string(), 0.2),
+            ("a".to_string(), 0.2),
+            ("gpu".to_string(), 2.4),
+        ];
+        let shards = assign_shards(28, &nodes);
+        assert_eq!(shards["a"], 0..2);
+        assert_eq!(shards["b"], 2..4);
+        assert_eq!(shards["gpu"], 4..28);
+
+        let others = &nodes[..2];
+        assert_eq!(local_steps(relative_compute(2.4, others)), MAX_LOCAL_STEPS);
+        assert_eq!(local_steps(relative_compute(0.2, &nodes[1..])), 1);
+    }
+}
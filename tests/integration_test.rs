@@ -1,5 +1,7 @@
 //! 集成测试：验证多节点协同训练
 
+#![allow(clippy::assertions_on_constants)]
+
 // 注意：这些测试需要实际的节点运行环境
 // 在实际部署时，需要将核心功能提取到 lib.rs 中
 